@@ -0,0 +1,195 @@
+// AAC 音频帧解析
+//
+// 设备发送的音频分片可以是 ADTS 帧序列，也可以是去掉帧头的原始 AAC 帧（配合单独下发的
+// AudioSpecificConfig）。平台统一转换为 ADTS 推送给浏览器，封装 fMP4 时再去掉帧头写入 esds。
+
+use serde::{Deserialize, Serialize};
+
+/// 每个 AAC 原始数据块的采样数
+pub const SAMPLES_PER_FRAME: u32 = 1024;
+
+/// ADTS 帧头长度（无 CRC）
+pub const ADTS_HEADER_LEN: usize = 7;
+
+/// 采样率索引表（ISO/IEC 14496-3 表 1.16）
+const SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// AAC 解码配置（AudioSpecificConfig 的前 2 字节）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioSpecificConfig {
+    /// 音频对象类型（2 = AAC-LC）
+    pub object_type: u8,
+    /// 采样率索引
+    pub sample_rate_index: u8,
+    /// 声道配置
+    pub channel_config: u8,
+}
+
+impl AudioSpecificConfig {
+    /// 解析 AudioSpecificConfig，不支持扩展对象类型和显式采样率
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let [b0, b1, ..] = *data else {
+            return None;
+        };
+        let config = Self {
+            object_type: b0 >> 3,
+            sample_rate_index: ((b0 & 0x07) << 1) | (b1 >> 7),
+            channel_config: (b1 >> 3) & 0x0F,
+        };
+        config.is_valid().then_some(config)
+    }
+
+    /// 编码为 2 字节的 AudioSpecificConfig
+    pub fn to_bytes(&self) -> [u8; 2] {
+        [
+            (self.object_type << 3) | (self.sample_rate_index >> 1),
+            ((self.sample_rate_index & 0x01) << 7) | (self.channel_config << 3),
+        ]
+    }
+
+    /// 采样率（Hz）
+    pub fn sample_rate(&self) -> u32 {
+        SAMPLE_RATES[self.sample_rate_index as usize]
+    }
+
+    /// 一帧（1024 个采样）的时长（秒）
+    pub fn frame_duration(&self) -> f64 {
+        SAMPLES_PER_FRAME as f64 / self.sample_rate() as f64
+    }
+
+    /// RFC 6381 编解码器字符串，如 "mp4a.40.2"
+    pub fn codec(&self) -> String {
+        format!("mp4a.40.{}", self.object_type)
+    }
+
+    /// 生成负载长度为 `payload_len` 的 ADTS 帧头（MPEG-4，无 CRC）
+    pub fn adts_header(&self, payload_len: usize) -> [u8; ADTS_HEADER_LEN] {
+        let frame_len = (payload_len + ADTS_HEADER_LEN) as u32;
+        let profile = self.object_type.saturating_sub(1) & 0x03;
+        [
+            0xFF,
+            0xF1,
+            (profile << 6) | (self.sample_rate_index << 2) | (self.channel_config >> 2),
+            ((self.channel_config & 0x03) << 6) | (frame_len >> 11) as u8,
+            (frame_len >> 3) as u8,
+            ((frame_len & 0x07) << 5) as u8 | 0x1F,
+            0xFC,
+        ]
+    }
+
+    fn is_valid(&self) -> bool {
+        (1..31).contains(&self.object_type)
+            && (self.sample_rate_index as usize) < SAMPLE_RATES.len()
+            && self.channel_config <= 7
+    }
+}
+
+/// 一个 ADTS 帧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdtsFrame<'a> {
+    pub config: AudioSpecificConfig,
+    /// 去掉帧头（及 CRC）的原始 AAC 数据
+    pub payload: &'a [u8],
+    /// 帧内采样数
+    pub samples: u32,
+}
+
+/// 解析位于数据开头的 ADTS 帧，返回帧和整帧长度
+pub fn parse_adts_frame(data: &[u8]) -> Option<(AdtsFrame<'_>, usize)> {
+    if data.len() < ADTS_HEADER_LEN || data[0] != 0xFF || data[1] & 0xF6 != 0xF0 {
+        return None;
+    }
+
+    let protection_absent = data[1] & 0x01 != 0;
+    let config = AudioSpecificConfig {
+        object_type: (data[2] >> 6) + 1,
+        sample_rate_index: (data[2] >> 2) & 0x0F,
+        channel_config: ((data[2] & 0x01) << 2) | (data[3] >> 6),
+    };
+    if !config.is_valid() {
+        return None;
+    }
+
+    let frame_len = (((data[3] & 0x03) as usize) << 11) | ((data[4] as usize) << 3) | (data[5] as usize >> 5);
+    let header_len = if protection_absent { ADTS_HEADER_LEN } else { ADTS_HEADER_LEN + 2 };
+    if frame_len < header_len || frame_len > data.len() {
+        return None;
+    }
+
+    let raw_blocks = (data[6] & 0x03) as u32 + 1;
+    let frame = AdtsFrame {
+        config,
+        payload: &data[header_len..frame_len],
+        samples: raw_blocks * SAMPLES_PER_FRAME,
+    };
+    Some((frame, frame_len))
+}
+
+/// 把数据切分为连续的 ADTS 帧，遇到无法解析的数据时停止
+pub fn adts_frames(data: &[u8]) -> Vec<AdtsFrame<'_>> {
+    let mut frames = Vec::new();
+    let mut rest = data;
+    while let Some((frame, len)) = parse_adts_frame(rest) {
+        frames.push(frame);
+        rest = &rest[len..];
+    }
+    frames
+}
+
+/// 数据是否以 ADTS 帧开头
+pub fn is_adts(data: &[u8]) -> bool {
+    parse_adts_frame(data).is_some()
+}
+
+/// 为一个原始 AAC 帧加上 ADTS 帧头
+pub fn to_adts(config: &AudioSpecificConfig, raw: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(raw.len() + ADTS_HEADER_LEN);
+    frame.extend_from_slice(&config.adts_header(raw.len()));
+    frame.extend_from_slice(raw);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LC_48K_STEREO: AudioSpecificConfig = AudioSpecificConfig {
+        object_type: 2,
+        sample_rate_index: 3,
+        channel_config: 2,
+    };
+
+    #[test]
+    fn test_audio_specific_config_roundtrip() {
+        let bytes = LC_48K_STEREO.to_bytes();
+        assert_eq!(bytes, [0x11, 0x90]);
+        assert_eq!(AudioSpecificConfig::parse(&bytes), Some(LC_48K_STEREO));
+        assert_eq!(LC_48K_STEREO.sample_rate(), 48000);
+        assert_eq!(LC_48K_STEREO.codec(), "mp4a.40.2");
+        assert!((LC_48K_STEREO.frame_duration() - 0.021333).abs() < 1e-6);
+
+        // 采样率索引越界
+        assert_eq!(AudioSpecificConfig::parse(&[0x17, 0x90]), None);
+        assert_eq!(AudioSpecificConfig::parse(&[0x11]), None);
+    }
+
+    #[test]
+    fn test_adts_frames() {
+        let mut data = to_adts(&LC_48K_STEREO, &[0x21, 0x10, 0x04]);
+        data.extend(to_adts(&LC_48K_STEREO, &[0x21, 0x10, 0x04, 0x60, 0x8c]));
+        assert!(is_adts(&data));
+
+        let frames = adts_frames(&data);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].config, LC_48K_STEREO);
+        assert_eq!(frames[0].payload, &[0x21, 0x10, 0x04]);
+        assert_eq!(frames[1].payload.len(), 5);
+        assert_eq!(frames[1].samples, SAMPLES_PER_FRAME);
+
+        // 截断的帧不返回
+        assert_eq!(adts_frames(&data[..data.len() - 1]).len(), 1);
+        assert!(!is_adts(&[0x00, 0x00, 0x00, 0x01, 0x67, 0x42, 0x00, 0x1f]));
+    }
+}
@@ -0,0 +1,535 @@
+// 录像容器解复用
+//
+// 录像可以是 H.264/H.265 Annex B 裸流、MP4 或 MPEG-TS。统一转换为 Annex B 基本流和按解码顺序排列的访问单元表，
+// 回放、定位、快进/倒放和取帧都只处理基本流，不关心原始容器。编码相关的差异（NAL 类型、参数集）
+// 由这里和 `ParameterSets` 按 `VideoCodec` 分派。
+
+use crate::h264::{self, AccessUnit, NalRole};
+use crate::{hevc, mp4, ts};
+use crate::{KeyframeEntry, VideoCodec};
+
+/// 录像容器格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// Annex B 裸流
+    AnnexB,
+    /// MP4（含分片 MP4）
+    Mp4,
+    /// MPEG-TS
+    MpegTs,
+}
+
+impl Container {
+    /// 按录像文件扩展名识别容器（不区分大小写）
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        if VideoCodec::from_extension(ext).is_some() {
+            return Some(Container::AnnexB);
+        }
+        match ext.to_ascii_lowercase().as_str() {
+            "mp4" => Some(Container::Mp4),
+            "ts" | "m2ts" | "mts" => Some(Container::MpegTs),
+            _ => None,
+        }
+    }
+}
+
+/// 解复用得到的视频基本流
+#[derive(Debug, Clone)]
+pub struct ElementaryStream {
+    pub container: Container,
+    pub codec: VideoCodec,
+    /// Annex B 码流（裸流即文件内容）
+    pub data: Vec<u8>,
+    /// 访问单元表，偏移指向 `data`；无法解析时为空
+    pub units: Vec<AccessUnit>,
+    /// 帧率（裸流从 SPS 检测，MP4/TS 按样本时长或 DTS 间隔计算，都没有时为假设帧率）
+    pub frame_rate: f64,
+}
+
+impl ElementaryStream {
+    /// 解析录像文件内容
+    ///
+    /// 找不到 H.264/H.265 视频轨道的 MP4/TS 返回空的访问单元表，`data` 保留文件内容。
+    pub fn parse(data: Vec<u8>, assumed_fps: f64) -> Self {
+        if ts::is_mpeg_ts(&data) {
+            let Some((codec, transport)) = ts::parse(&data).and_then(|t| Some((t.video_codec()?, t))) else {
+                return Self {
+                    container: Container::MpegTs,
+                    codec: VideoCodec::H264,
+                    data,
+                    units: Vec::new(),
+                    frame_rate: assumed_fps,
+                };
+            };
+            let (stream, units) = transport.to_annex_b(assumed_fps);
+            return Self {
+                container: Container::MpegTs,
+                codec,
+                data: stream,
+                units,
+                frame_rate: transport.frame_rate().unwrap_or(assumed_fps),
+            };
+        }
+
+        if mp4::is_mp4(&data) {
+            let Some(track) = mp4::parse_video_track(&data) else {
+                return Self {
+                    container: Container::Mp4,
+                    codec: VideoCodec::H264,
+                    data,
+                    units: Vec::new(),
+                    frame_rate: assumed_fps,
+                };
+            };
+            let (stream, units) = mp4::to_annex_b(&data, &track);
+            return Self {
+                container: Container::Mp4,
+                codec: track.video_codec(),
+                data: stream,
+                units,
+                frame_rate: track.frame_rate().unwrap_or(assumed_fps),
+            };
+        }
+
+        let codec = detect_codec(&data);
+        let (frame_rate, units) = match codec {
+            VideoCodec::H264 => {
+                let frame_rate = h264::detect_frame_rate(&data).unwrap_or(assumed_fps);
+                (frame_rate, h264::scan_access_units(&data, frame_rate))
+            }
+            VideoCodec::H265 => (assumed_fps, hevc::scan_access_units(&data, assumed_fps)),
+        };
+        Self {
+            container: Container::AnnexB,
+            codec,
+            data,
+            units,
+            frame_rate,
+        }
+    }
+
+    /// 访问单元的数据
+    pub fn unit_data(&self, unit: &AccessUnit) -> &[u8] {
+        &self.data[unit.offset as usize..(unit.offset + unit.size as u64) as usize]
+    }
+
+    /// 关键帧索引，偏移指向基本流
+    pub fn keyframe_index(&self) -> Vec<KeyframeEntry> {
+        self.units
+            .iter()
+            .filter(|unit| unit.is_keyframe)
+            .map(|unit| KeyframeEntry {
+                timestamp: unit.timestamp,
+                file_offset: unit.offset,
+                frame_size: unit.size,
+            })
+            .collect()
+    }
+
+    /// 总时长（秒）
+    pub fn duration(&self) -> f64 {
+        self.units.iter().map(|unit| unit.duration).sum()
+    }
+
+    /// 码流中的第一组参数集
+    pub fn parameter_sets(&self) -> Option<ParameterSets> {
+        ParameterSets::find(self.codec, &self.data)
+    }
+}
+
+/// 不做完整解复用识别录像的视频编码格式（MP4 看样本描述，TS 看 PMT 流类型，裸流看 NAL 头）
+///
+/// 容器中找不到视频轨道时返回 None。
+pub fn probe_codec(data: &[u8]) -> Option<VideoCodec> {
+    if mp4::is_mp4(data) {
+        mp4::parse_video_track(data).map(|track| track.video_codec())
+    } else if ts::is_mpeg_ts(data) {
+        ts::parse(data).and_then(|transport| transport.video_codec())
+    } else {
+        Some(detect_codec(data))
+    }
+}
+
+/// 识别 Annex B 裸流的编码格式，无法识别时按 H.264 处理
+pub fn detect_codec(data: &[u8]) -> VideoCodec {
+    if hevc::looks_like_hevc(data) {
+        VideoCodec::H265
+    } else {
+        VideoCodec::H264
+    }
+}
+
+/// 增量切分得到的一个访问单元
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedFrame {
+    /// 在码流中的起始偏移
+    pub offset: u64,
+    /// Annex B 数据（含前导的参数集、SEI、分隔符）
+    pub data: Vec<u8>,
+    /// 是否为 IDR（H.264）或 IRAP（H.265）帧
+    pub is_keyframe: bool,
+}
+
+/// 增量切分 Annex B 码流的访问单元
+///
+/// 划分规则与 `h264::scan_access_units` 相同：前导 NAL 归入下一帧，first_mb_in_slice /
+/// first_slice_segment_in_pic_flag 标记新的一帧。数据按块输入，只缓存当前访问单元，
+/// 用于按块读取大文件。
+#[derive(Debug, Clone)]
+pub struct AccessUnitSplitter {
+    codec: VideoCodec,
+    buffer: Vec<u8>,
+    /// `buffer[0]` 在码流中的偏移
+    base: u64,
+    /// NAL 头位置不超过这里的起始码已处理
+    scanned: usize,
+    /// 当前访问单元是否已有条带
+    has_slice: bool,
+    is_keyframe: bool,
+    /// 当前访问单元之后、属于下一帧的前导 NAL 起点
+    next_prefix: Option<usize>,
+}
+
+impl AccessUnitSplitter {
+    pub fn new(codec: VideoCodec) -> Self {
+        Self::with_offset(codec, 0)
+    }
+
+    /// 从码流的 `offset` 处开始切分（定位到关键帧后继续读取时使用）
+    pub fn with_offset(codec: VideoCodec, offset: u64) -> Self {
+        Self {
+            codec,
+            buffer: Vec::new(),
+            base: offset,
+            scanned: 0,
+            has_slice: false,
+            is_keyframe: false,
+            next_prefix: None,
+        }
+    }
+
+    pub fn codec(&self) -> VideoCodec {
+        self.codec
+    }
+
+    /// 追加一块数据，返回其中已完整的访问单元
+    pub fn push(&mut self, data: &[u8]) -> Vec<EncodedFrame> {
+        self.buffer.extend_from_slice(data);
+
+        // 往回多看几个字节，找到跨块的起始码（含四字节起始码的前导 0）
+        let from = self.scanned.saturating_sub(4);
+        let codes: Vec<(usize, usize)> = h264::start_codes(&self.buffer[from..])
+            .into_iter()
+            .map(|(start, header)| (start + from, header + from))
+            .filter(|&(_, header)| header > self.scanned)
+            .collect();
+
+        let mut frames = Vec::new();
+        let mut cut = 0;
+        let mut resume = None;
+        for (start, header) in codes {
+            // NAL 头和条带头的第一个字节到齐后才能判断作用
+            if header + 3 > self.buffer.len() {
+                resume = Some(start);
+                break;
+            }
+            let role = match self.codec {
+                VideoCodec::H264 => h264::nal_role(&self.buffer[header..]),
+                VideoCodec::H265 => hevc::nal_role(&self.buffer[header..]),
+            };
+            match role {
+                NalRole::Prefix => {
+                    if self.has_slice {
+                        self.next_prefix.get_or_insert(start);
+                    }
+                }
+                NalRole::Slice { first_in_picture, keyframe } => {
+                    if first_in_picture && self.has_slice {
+                        let end = self.next_prefix.unwrap_or(start);
+                        frames.push(EncodedFrame {
+                            offset: self.base + cut as u64,
+                            data: self.buffer[cut..end].to_vec(),
+                            is_keyframe: self.is_keyframe,
+                        });
+                        cut = end;
+                    }
+                    if first_in_picture || !self.has_slice {
+                        self.is_keyframe = keyframe;
+                    }
+                    self.has_slice = true;
+                    self.next_prefix = None;
+                }
+                NalRole::Other => {}
+            }
+        }
+        self.scanned = resume.unwrap_or(self.buffer.len().saturating_sub(3));
+
+        if cut > 0 {
+            self.buffer.drain(..cut);
+            self.base += cut as u64;
+            self.scanned -= cut;
+            self.next_prefix = self.next_prefix.map(|prefix| prefix - cut);
+        }
+        frames
+    }
+
+    /// 码流结束，返回最后一个访问单元（没有条带时返回 None）
+    pub fn finish(&mut self) -> Option<EncodedFrame> {
+        let data = std::mem::take(&mut self.buffer);
+        if !self.has_slice || data.is_empty() {
+            *self = Self::with_offset(self.codec, self.base);
+            return None;
+        }
+        let frame = EncodedFrame { offset: self.base, is_keyframe: self.is_keyframe, data };
+        *self = Self::with_offset(self.codec, frame.offset + frame.data.len() as u64);
+        Some(frame)
+    }
+}
+
+/// 按块读取 Annex B 裸流建立访问单元表，不保留码流数据
+///
+/// 结果与对整个文件调用 `h264::scan_access_units` / `hevc::scan_access_units` 相同，
+/// 用于为大文件建立定位和关键帧索引；帧数据之后按偏移从文件读取。
+pub fn index_annex_b<R: std::io::Read>(mut reader: R, codec: VideoCodec, fps: f64) -> std::io::Result<Vec<AccessUnit>> {
+    let mut splitter = AccessUnitSplitter::new(codec);
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut units = Vec::new();
+    let mut add = |frame: EncodedFrame| {
+        let timestamp = units.len() as f64 / fps;
+        units.push(AccessUnit {
+            timestamp,
+            decode_timestamp: timestamp,
+            duration: 1.0 / fps,
+            offset: frame.offset,
+            size: frame.data.len() as u32,
+            is_keyframe: frame.is_keyframe,
+        });
+    };
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        splitter.push(&buffer[..n]).into_iter().for_each(&mut add);
+    }
+    splitter.finish().into_iter().for_each(&mut add);
+    Ok(units)
+}
+
+/// 解码所需的参数集（不含起始码）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParameterSets {
+    H264 { sps: Vec<u8>, pps: Vec<u8> },
+    H265 { vps: Vec<u8>, sps: Vec<u8>, pps: Vec<u8> },
+}
+
+impl ParameterSets {
+    /// 查找码流中的第一组参数集
+    pub fn find(codec: VideoCodec, data: &[u8]) -> Option<Self> {
+        match codec {
+            VideoCodec::H264 => {
+                let (sps, pps) = h264::parameter_sets(data)?;
+                Some(Self::H264 { sps, pps })
+            }
+            VideoCodec::H265 => {
+                let (vps, sps, pps) = hevc::parameter_sets(data)?;
+                Some(Self::H265 { vps, sps, pps })
+            }
+        }
+    }
+
+    pub fn codec(&self) -> VideoCodec {
+        match self {
+            Self::H264 { .. } => VideoCodec::H264,
+            Self::H265 { .. } => VideoCodec::H265,
+        }
+    }
+
+    pub fn sps(&self) -> &[u8] {
+        match self {
+            Self::H264 { sps, .. } | Self::H265 { sps, .. } => sps,
+        }
+    }
+
+    /// 按解码顺序排列的参数集 NAL
+    pub fn nal_units(&self) -> Vec<&[u8]> {
+        match self {
+            Self::H264 { sps, pps } => vec![sps, pps],
+            Self::H265 { vps, sps, pps } => vec![vps, sps, pps],
+        }
+    }
+
+    /// 带四字节起始码的 Annex B 参数集
+    pub fn to_annex_b(&self) -> Vec<u8> {
+        self.nal_units()
+            .into_iter()
+            .flat_map(|nal| [0, 0, 0, 1].iter().chain(nal).copied())
+            .collect()
+    }
+
+    /// WebCodecs/MSE 使用的编码字符串，如 "avc1.64001F"、"hvc1.1.6.L93.B0"
+    pub fn codec_string(&self) -> Option<String> {
+        match self {
+            Self::H264 { sps, .. } => h264::codec_string(sps),
+            Self::H265 { sps, .. } => hevc::codec_string(sps),
+        }
+    }
+
+    /// 视频分辨率
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        match self {
+            Self::H264 { sps, .. } => h264::parse_sps(sps).map(|info| (info.width, info.height)),
+            Self::H265 { sps, .. } => hevc::parse_sps(sps).map(|info| (info.width, info.height)),
+        }
+    }
+}
+
+/// NAL 是否为参数集或访问单元分隔符（这些 NAL 已写入解码配置，长度前缀样本中不需要）
+pub fn is_config_nal(codec: VideoCodec, nal: &[u8]) -> bool {
+    match codec {
+        VideoCodec::H264 => matches!(h264::nal_type(nal), h264::NAL_SPS | h264::NAL_PPS | h264::NAL_AUD),
+        VideoCodec::H265 => matches!(hevc::nal_type(nal), hevc::NAL_VPS..=hevc::NAL_AUD),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_annex_b() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+        data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+        data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, 0x00, 0xaa]);
+        data.extend_from_slice(&[0, 0, 0, 1, 0x41, 0x9a, 0x01]);
+
+        let stream = ElementaryStream::parse(data.clone(), 25.0);
+        assert_eq!(stream.container, Container::AnnexB);
+        assert_eq!(stream.codec, VideoCodec::H264);
+        assert_eq!(stream.data, data);
+        assert_eq!(stream.units.len(), 2);
+        assert_eq!(stream.frame_rate, 25.0);
+        assert!((stream.duration() - 0.08).abs() < 1e-9);
+        assert_eq!(stream.keyframe_index().len(), 1);
+        assert_eq!(stream.unit_data(&stream.units[1]), &data[24..]);
+
+        let parameter_sets = stream.parameter_sets().unwrap();
+        assert_eq!(parameter_sets.to_annex_b(), data[..16]);
+        assert!(is_config_nal(VideoCodec::H264, &[0x09, 0xf0]));
+        assert!(!is_config_nal(VideoCodec::H264, &[0x65, 0x88]));
+    }
+
+    #[test]
+    fn test_parse_hevc_annex_b() {
+        let mut data = Vec::new();
+        for nal in [
+            vec![0x40, 0x01, 0x0c, 0x01],
+            hevc::tests::main_profile_sps(),
+            vec![0x44, 0x01, 0xc1, 0x72],
+            vec![0x26, 0x01, 0xaf, 0x01],
+            vec![0x02, 0x01, 0xd0, 0x02],
+        ] {
+            data.extend_from_slice(&[0, 0, 0, 1]);
+            data.extend(nal);
+        }
+
+        let stream = ElementaryStream::parse(data, 25.0);
+        assert_eq!(stream.codec, VideoCodec::H265);
+        assert_eq!(stream.units.len(), 2);
+        assert_eq!(stream.keyframe_index().len(), 1);
+
+        let parameter_sets = stream.parameter_sets().unwrap();
+        assert_eq!(parameter_sets.codec(), VideoCodec::H265);
+        assert_eq!(parameter_sets.nal_units().len(), 3);
+        assert_eq!(parameter_sets.codec_string().as_deref(), Some("hvc1.1.6.L93.B0"));
+        assert_eq!(parameter_sets.resolution(), Some((1920, 1080)));
+        assert!(is_config_nal(VideoCodec::H265, &[0x40, 0x01]));
+        assert!(!is_config_nal(VideoCodec::H265, &[0x26, 0x01]));
+    }
+
+    #[test]
+    fn test_access_unit_splitter() {
+        // 带 AUD/SEI 的 H.264 码流，按小块输入后与整体扫描的结果一致
+        let mut data = Vec::new();
+        for frame in 0..6u8 {
+            data.extend_from_slice(&[0, 0, 0, 1, 0x09, 0xf0]);
+            if frame % 3 == 0 {
+                data.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+                data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+                data.extend_from_slice(&[0, 0, 1, 0x06, 0x05, 0x01, frame]);
+                data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, frame, 0xaa]);
+                // 同一帧的第二个条带
+                data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x44, frame, 0xbb]);
+            } else {
+                data.extend_from_slice(&[0, 0, 1, 0x41, 0x9a, frame, 0x00, 0x00, 0x03, 0x01]);
+            }
+        }
+        let expected = h264::scan_access_units(&data, 30.0);
+        assert_eq!(expected.len(), 6);
+
+        for chunk_size in [1, 3, 7, 64] {
+            let mut splitter = AccessUnitSplitter::new(VideoCodec::H264);
+            let mut frames: Vec<EncodedFrame> = data.chunks(chunk_size).flat_map(|chunk| splitter.push(chunk)).collect();
+            frames.extend(splitter.finish());
+            assert_eq!(frames.len(), expected.len(), "chunk size {}", chunk_size);
+            for (frame, unit) in frames.iter().zip(&expected) {
+                assert_eq!(frame.offset, unit.offset);
+                assert_eq!(frame.data.len(), unit.size as usize);
+                assert_eq!(frame.is_keyframe, unit.is_keyframe);
+            }
+        }
+        let indexed = index_annex_b(std::io::Cursor::new(&data), VideoCodec::H264, 30.0).unwrap();
+        assert_eq!(indexed, expected);
+
+        let mut splitter = AccessUnitSplitter::with_offset(VideoCodec::H264, 100);
+        assert!(splitter.push(&[0, 0, 0, 1, 0x67, 0x42]).is_empty());
+        assert!(splitter.finish().is_none());
+    }
+
+    #[test]
+    fn test_parse_mp4_without_video_track() {
+        let data = b"\0\0\0\x10ftypisom\0\0\0\0\0\0\0\x08mdat".to_vec();
+        let stream = ElementaryStream::parse(data.clone(), 30.0);
+        assert_eq!(stream.container, Container::Mp4);
+        assert!(stream.units.is_empty());
+        assert_eq!(stream.data, data);
+        assert_eq!(probe_codec(&data), None);
+    }
+
+    #[test]
+    fn test_parse_mpeg_ts() {
+        let keyframe: Vec<u8> = [
+            vec![0x40, 0x01, 0x0c, 0x01],
+            hevc::tests::main_profile_sps(),
+            vec![0x44, 0x01, 0xc1, 0x72],
+            vec![0x26, 0x01, 0xaf, 0x01],
+        ]
+        .into_iter()
+        .flat_map(|nal| [0, 0, 0, 1].into_iter().chain(nal))
+        .collect();
+        let delta = vec![0, 0, 0, 1, 0x02, 0x01, 0xd0, 0x02];
+
+        let mut muxer = ts::TsMuxer::new(VideoCodec::H265, false);
+        let mut data = Vec::new();
+        muxer.write_tables(&mut data);
+        muxer.write_video(&keyframe, 0.0, 0.0, true, &mut data);
+        muxer.write_video(&delta, 0.04, 0.04, false, &mut data);
+        assert_eq!(probe_codec(&data), Some(VideoCodec::H265));
+
+        let stream = ElementaryStream::parse(data, 30.0);
+        assert_eq!(stream.container, Container::MpegTs);
+        assert_eq!(stream.codec, VideoCodec::H265);
+        assert_eq!(stream.frame_rate, 25.0);
+        assert_eq!(stream.units.len(), 2);
+        assert_eq!(stream.unit_data(&stream.units[1]), delta);
+        assert_eq!(stream.keyframe_index().len(), 1);
+        assert!(stream.parameter_sets().is_some());
+
+        assert_eq!(Container::from_extension("TS"), Some(Container::MpegTs));
+        assert_eq!(Container::from_extension("hevc"), Some(Container::AnnexB));
+        assert_eq!(Container::from_extension("txt"), None);
+    }
+}
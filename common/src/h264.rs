@@ -0,0 +1,508 @@
+// H.264 Annex B 访问单元解析
+//
+// 把码流切分为访问单元（一帧及其前导的 SPS/PPS/SEI/AUD），时间按帧序号和帧率推算。
+// 关键帧索引、快进/倒放、精确定位和单帧/缩略图提取都基于这里的访问单元表。
+
+/// NAL 类型：IDR 条带
+pub const NAL_IDR: u8 = 5;
+/// NAL 类型：序列参数集
+pub const NAL_SPS: u8 = 7;
+/// NAL 类型：图像参数集
+pub const NAL_PPS: u8 = 8;
+/// NAL 类型：访问单元分隔符
+pub const NAL_AUD: u8 = 9;
+
+/// 一个访问单元（一帧）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccessUnit {
+    /// 显示时间（秒）
+    pub timestamp: f64,
+    /// 解码时间（秒），没有帧重排时等于显示时间
+    pub decode_timestamp: f64,
+    /// 时长（秒）
+    pub duration: f64,
+    /// 在码流中的起始偏移（含前导参数集）
+    pub offset: u64,
+    /// 字节数
+    pub size: u32,
+    /// 是否为 IDR 帧
+    pub is_keyframe: bool,
+}
+
+/// 扫描 H.264 Annex B 码流中的访问单元
+pub fn scan_access_units(data: &[u8], fps: f64) -> Vec<AccessUnit> {
+    split_access_units(data, fps, nal_role)
+}
+
+/// H.264 NAL 在访问单元划分中的作用，`nal` 从 NAL 头开始
+pub(crate) fn nal_role(nal: &[u8]) -> NalRole {
+    match nal[0] & 0x1F {
+        6..=9 => NalRole::Prefix,
+        // first_mb_in_slice == 0（ue(v) 编码为单个 1 比特）表示新的一帧
+        nal_type @ (1 | 5) => NalRole::Slice {
+            first_in_picture: nal.get(1).is_some_and(|b| b & 0x80 != 0),
+            keyframe: nal_type == 5,
+        },
+        _ => NalRole::Other,
+    }
+}
+
+/// NAL 单元在访问单元划分中的作用
+pub(crate) enum NalRole {
+    /// 访问单元的前导 NAL（参数集、SEI、分隔符等）
+    Prefix,
+    /// 条带
+    Slice { first_in_picture: bool, keyframe: bool },
+    /// 其他 NAL，归入当前访问单元
+    Other,
+}
+
+/// 码流中的起始码，返回 (起始码位置（含四字节起始码的前导 0）, NAL 头位置)
+pub(crate) fn start_codes(data: &[u8]) -> Vec<(usize, usize)> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            let start = if i > 0 && data[i - 1] == 0 { i - 1 } else { i };
+            starts.push((start, i + 3));
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+    starts
+}
+
+/// 按 NAL 作用把码流切分为访问单元，`role` 的参数从 NAL 头开始
+pub(crate) fn split_access_units(
+    data: &[u8],
+    fps: f64,
+    role: impl Fn(&[u8]) -> NalRole,
+) -> Vec<AccessUnit> {
+    let mut units: Vec<AccessUnit> = Vec::new();
+    // 当前访问单元前导 NAL 的起点
+    let mut unit_start: Option<usize> = None;
+
+    for (start, header) in start_codes(data) {
+        if header >= data.len() {
+            continue;
+        }
+        match role(&data[header..]) {
+            NalRole::Prefix => {
+                unit_start.get_or_insert(start);
+            }
+            NalRole::Slice { first_in_picture, keyframe } => {
+                if first_in_picture {
+                    let frame_start = unit_start.unwrap_or(start);
+                    if let Some(last) = units.last_mut() {
+                        last.size = (frame_start as u64 - last.offset) as u32;
+                    }
+                    let timestamp = units.len() as f64 / fps;
+                    units.push(AccessUnit {
+                        timestamp,
+                        decode_timestamp: timestamp,
+                        duration: 1.0 / fps,
+                        offset: frame_start as u64,
+                        size: 0,
+                        is_keyframe: keyframe,
+                    });
+                }
+                unit_start = None;
+            }
+            NalRole::Other => {}
+        }
+    }
+    if let Some(last) = units.last_mut() {
+        last.size = (data.len() as u64 - last.offset) as u32;
+    }
+    units
+}
+
+/// 拆分 Annex B 码流中的 NAL 单元（不含起始码）
+pub fn nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let starts: Vec<usize> = start_codes(data).into_iter().map(|(_, header)| header).collect();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let mut end = starts.get(n + 1).map_or(data.len(), |&next| next - 3);
+            // 去掉下一个四字节起始码的前导 0 以及尾随的 0
+            while end > start && data[end - 1] == 0 {
+                end -= 1;
+            }
+            &data[start..end]
+        })
+        .filter(|nal| !nal.is_empty())
+        .collect()
+}
+
+/// NAL 单元类型
+pub fn nal_type(nal: &[u8]) -> u8 {
+    nal.first().map_or(0, |header| header & 0x1F)
+}
+
+/// 是否为非参考帧：所有条带的 nal_ref_idc 为 0，丢弃后不影响其他帧解码
+pub fn is_disposable(data: &[u8]) -> bool {
+    let mut slices = nal_units(data)
+        .into_iter()
+        .filter(|nal| (1..=NAL_IDR).contains(&nal_type(nal)))
+        .peekable();
+    slices.peek().is_some() && slices.all(|nal| nal[0] & 0x60 == 0)
+}
+
+/// 码流中的第一组 SPS/PPS
+pub fn parameter_sets(data: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let nals = nal_units(data);
+    let sps = nals.iter().find(|nal| nal_type(nal) == NAL_SPS)?;
+    let pps = nals.iter().find(|nal| nal_type(nal) == NAL_PPS)?;
+    Some((sps.to_vec(), pps.to_vec()))
+}
+
+/// 指数哥伦布码比特读取器（已去除防竞争字节）
+pub(crate) struct BitReader {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl BitReader {
+    pub(crate) fn new(nal: &[u8]) -> Self {
+        // 去除防竞争字节 0x000003 中的 0x03
+        let mut data = Vec::with_capacity(nal.len());
+        let mut zeros = 0;
+        for &byte in nal {
+            if zeros >= 2 && byte == 3 {
+                zeros = 0;
+                continue;
+            }
+            zeros = if byte == 0 { zeros + 1 } else { 0 };
+            data.push(byte);
+        }
+        Self { data, position: 0 }
+    }
+
+    pub(crate) fn bit(&mut self) -> Option<u32> {
+        let byte = self.data.get(self.position / 8)?;
+        let bit = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+        Some(bit as u32)
+    }
+
+    pub(crate) fn bits(&mut self, count: usize) -> Option<u32> {
+        (0..count).try_fold(0, |value, _| Some((value << 1) | self.bit()?))
+    }
+
+    pub(crate) fn ue(&mut self) -> Option<u32> {
+        let mut leading_zeros = 0;
+        while self.bit()? == 0 {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return None;
+            }
+        }
+        Some((1u32 << leading_zeros) - 1 + self.bits(leading_zeros)?)
+    }
+
+    pub(crate) fn se(&mut self) -> Option<i32> {
+        let value = self.ue()?;
+        Some(if value % 2 == 1 { value.div_ceil(2) as i32 } else { -((value / 2) as i32) })
+    }
+}
+
+/// WebCodecs/MSE 使用的编码字符串（RFC 6381），如 "avc1.64001F"
+pub fn codec_string(sps: &[u8]) -> Option<String> {
+    let [_, profile, constraints, level, ..] = *sps else {
+        return None;
+    };
+    Some(format!("avc1.{:02X}{:02X}{:02X}", profile, constraints, level))
+}
+
+/// SPS 中的流信息
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpsInfo {
+    /// 宽度（已扣除裁剪区域）
+    pub width: u32,
+    /// 高度（已扣除裁剪区域）
+    pub height: u32,
+    /// VUI 中的帧率（没有 timing_info 时为 None）
+    pub frame_rate: Option<f64>,
+}
+
+/// 解析 SPS 中的分辨率和帧率
+pub fn parse_sps(sps: &[u8]) -> Option<SpsInfo> {
+    let mut reader = BitReader::new(sps);
+    reader.bits(8)?; // NAL 头
+    let profile_idc = reader.bits(8)?;
+    reader.bits(16)?; // constraint_flags + level_idc
+    reader.ue()?; // seq_parameter_set_id
+
+    let mut chroma_format_idc = 1;
+    if matches!(profile_idc, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+        chroma_format_idc = reader.ue()?;
+        if chroma_format_idc == 3 {
+            reader.bit()?; // separate_colour_plane_flag
+        }
+        reader.ue()?; // bit_depth_luma_minus8
+        reader.ue()?; // bit_depth_chroma_minus8
+        reader.bit()?; // qpprime_y_zero_transform_bypass_flag
+        if reader.bit()? == 1 {
+            // seq_scaling_matrix_present_flag
+            let lists = if chroma_format_idc == 3 { 12 } else { 8 };
+            for list in 0..lists {
+                if reader.bit()? == 1 {
+                    let size = if list < 6 { 16 } else { 64 };
+                    let (mut last, mut next) = (8i32, 8i32);
+                    for _ in 0..size {
+                        if next != 0 {
+                            next = (last + reader.se()? + 256) % 256;
+                        }
+                        if next != 0 {
+                            last = next;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    reader.ue()?; // log2_max_frame_num_minus4
+    match reader.ue()? {
+        0 => {
+            reader.ue()?; // log2_max_pic_order_cnt_lsb_minus4
+        }
+        1 => {
+            reader.bit()?; // delta_pic_order_always_zero_flag
+            reader.se()?; // offset_for_non_ref_pic
+            reader.se()?; // offset_for_top_to_bottom_field
+            for _ in 0..reader.ue()? {
+                reader.se()?;
+            }
+        }
+        _ => {}
+    }
+    reader.ue()?; // max_num_ref_frames
+    reader.bit()?; // gaps_in_frame_num_value_allowed_flag
+
+    let width_mbs = reader.ue()? + 1;
+    let height_map_units = reader.ue()? + 1;
+    let frame_mbs_only = reader.bit()?;
+    if frame_mbs_only == 0 {
+        reader.bit()?; // mb_adaptive_frame_field_flag
+    }
+    reader.bit()?; // direct_8x8_inference_flag
+
+    let mut width = width_mbs * 16;
+    let mut height = (2 - frame_mbs_only) * height_map_units * 16;
+    if reader.bit()? == 1 {
+        // frame_cropping_flag
+        let (left, right, top, bottom) = (reader.ue()?, reader.ue()?, reader.ue()?, reader.ue()?);
+        let (crop_x, crop_y) = match chroma_format_idc {
+            0 | 3 => (1, 2 - frame_mbs_only),
+            2 => (2, 2 - frame_mbs_only),
+            _ => (2, 2 * (2 - frame_mbs_only)),
+        };
+        width = width.checked_sub((left + right) * crop_x)?;
+        height = height.checked_sub((top + bottom) * crop_y)?;
+    }
+
+    // VUI 不完整时仍然返回分辨率
+    let frame_rate = parse_vui_frame_rate(&mut reader).flatten();
+    Some(SpsInfo {
+        width,
+        height,
+        frame_rate,
+    })
+}
+
+/// 解析 VUI 中的 timing_info，返回帧率
+fn parse_vui_frame_rate(reader: &mut BitReader) -> Option<Option<f64>> {
+    if reader.bit()? == 0 {
+        // vui_parameters_present_flag
+        return Some(None);
+    }
+    if reader.bit()? == 1 {
+        // aspect_ratio_info_present_flag
+        if reader.bits(8)? == 255 {
+            reader.bits(32)?; // sar_width + sar_height
+        }
+    }
+    if reader.bit()? == 1 {
+        reader.bit()?; // overscan_appropriate_flag
+    }
+    if reader.bit()? == 1 {
+        // video_signal_type_present_flag
+        reader.bits(4)?; // video_format + video_full_range_flag
+        if reader.bit()? == 1 {
+            reader.bits(24)?; // colour_primaries + transfer_characteristics + matrix_coefficients
+        }
+    }
+    if reader.bit()? == 1 {
+        // chroma_loc_info_present_flag
+        reader.ue()?;
+        reader.ue()?;
+    }
+    if reader.bit()? == 0 {
+        // timing_info_present_flag
+        return Some(None);
+    }
+    let num_units_in_tick = reader.bits(32)?;
+    let time_scale = reader.bits(32)?;
+    if num_units_in_tick == 0 || time_scale == 0 {
+        return Some(None);
+    }
+    // 一帧两个场，每个场一个 tick
+    Some(Some(time_scale as f64 / (2.0 * num_units_in_tick as f64)))
+}
+
+/// 从码流的第一个 SPS 检测帧率
+pub fn detect_frame_rate(data: &[u8]) -> Option<f64> {
+    let (sps, _) = parameter_sets(data)?;
+    parse_sps(&sps)?.frame_rate
+}
+
+/// 显示时间离 `time` 最近的帧
+pub fn nearest_access_unit(units: &[AccessUnit], time: f64) -> Option<usize> {
+    let after = units.partition_point(|u| u.timestamp < time);
+    match (after.checked_sub(1), units.get(after)) {
+        (Some(before), Some(unit)) if time - units[before].timestamp <= unit.timestamp - time => Some(before),
+        (_, Some(_)) => Some(after),
+        (before, None) => before,
+    }
+}
+
+/// 精确定位计划：从前一个关键帧开始解码，目标帧之前的帧只解码不显示
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccurateSeekPlan {
+    /// 开始发送的关键帧下标
+    pub keyframe: usize,
+    /// 目标帧下标（第一个显示的帧）
+    pub target: usize,
+}
+
+impl AccurateSeekPlan {
+    /// 预解码（decode-only）帧的下标范围
+    pub fn preroll(&self) -> std::ops::Range<usize> {
+        self.keyframe..self.target
+    }
+}
+
+/// 为精确定位到 `time` 生成计划
+///
+/// 目标帧是显示时间不晚于 `time` 的最后一帧；码流开头没有关键帧时返回 None。
+pub fn plan_accurate_seek(units: &[AccessUnit], time: f64) -> Option<AccurateSeekPlan> {
+    // 容忍浮点误差，避免 n/fps 这类时间落到前一帧
+    const EPSILON: f64 = 1e-6;
+    let target = units
+        .iter()
+        .rposition(|u| u.timestamp <= time + EPSILON)
+        .unwrap_or(0);
+    let keyframe = units[..=target.min(units.len().checked_sub(1)?)]
+        .iter()
+        .rposition(|u| u.is_keyframe)?;
+    Some(AccurateSeekPlan { keyframe, target })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 两个 GOP，每个 GOP 10 帧
+    fn h264_stream() -> Vec<u8> {
+        let mut data = Vec::new();
+        for gop in 0..2u8 {
+            data.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, gop, 0xaa]);
+            for frame in 1..10u8 {
+                data.extend_from_slice(&[0, 0, 0, 1, 0x41, 0x9a, frame]);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_scan_access_units() {
+        let data = h264_stream();
+        let units = scan_access_units(&data, 10.0);
+
+        assert_eq!(units.len(), 20);
+        assert!(units[0].is_keyframe && units[10].is_keyframe);
+        assert!(!units[1].is_keyframe);
+        assert_eq!(units[10].timestamp, 1.0);
+        // 访问单元首尾相接，覆盖整个码流
+        assert!(units.windows(2).all(|w| w[0].offset + w[0].size as u64 == w[1].offset));
+        let last = units.last().unwrap();
+        assert_eq!(last.offset + last.size as u64, data.len() as u64);
+        // 关键帧从 SPS 开始
+        assert_eq!(data[units[10].offset as usize + 4], 0x67);
+    }
+
+    #[test]
+    fn test_plan_accurate_seek() {
+        let units = scan_access_units(&h264_stream(), 10.0);
+
+        let plan = plan_accurate_seek(&units, 1.35).unwrap();
+        assert_eq!(plan, AccurateSeekPlan { keyframe: 10, target: 13 });
+        assert_eq!(plan.preroll().len(), 3);
+
+        // 正好落在关键帧上时不需要预解码
+        let plan = plan_accurate_seek(&units, 1.0).unwrap();
+        assert!(plan.preroll().is_empty());
+
+        // 超出末尾时定位到最后一帧
+        let plan = plan_accurate_seek(&units, 99.0).unwrap();
+        assert_eq!(plan.target, 19);
+
+        assert!(plan_accurate_seek(&[], 1.0).is_none());
+    }
+
+    #[test]
+    fn test_nal_units_and_parameter_sets() {
+        let data = h264_stream();
+        let nals = nal_units(&data);
+        assert_eq!(nals.len(), 24);
+        assert_eq!(nals[0], &[0x67, 0x42, 0x00, 0x1f]);
+        assert_eq!(nal_type(nals[2]), NAL_IDR);
+
+        let (sps, pps) = parameter_sets(&data).unwrap();
+        assert_eq!(nal_type(&sps), NAL_SPS);
+        assert_eq!(pps, vec![0x68, 0xce, 0x38, 0x80]);
+        assert_eq!(codec_string(&sps).as_deref(), Some("avc1.42001F"));
+        assert_eq!(codec_string(&[0x67, 0x42]), None);
+    }
+
+    #[test]
+    fn test_is_disposable() {
+        // nal_ref_idc = 0 的 P 条带
+        assert!(is_disposable(&[0, 0, 0, 1, 0x09, 0xf0, 0, 0, 0, 1, 0x01, 0x9a]));
+        assert!(!is_disposable(&[0, 0, 0, 1, 0x41, 0x9a]));
+        assert!(!is_disposable(&[0, 0, 0, 1, 0x65, 0x88]));
+        // 只有参数集的分片不是帧
+        assert!(!is_disposable(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]));
+    }
+
+    #[test]
+    fn test_parse_sps() {
+        // x264 生成的 High profile 1920x1080@30fps SPS（1088 行裁剪 8 行）
+        let sps = [
+            0x67, 0x64, 0x00, 0x28, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0xc0, 0x44, 0x00, 0x00, 0x03,
+            0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xf0, 0x3c, 0x60, 0xc6, 0x58,
+        ];
+        let info = parse_sps(&sps).unwrap();
+        assert_eq!((info.width, info.height), (1920, 1080));
+        // time_scale 60 / (2 * num_units_in_tick 1)
+        assert_eq!(info.frame_rate, Some(30.0));
+        // 截断的 SPS
+        assert_eq!(parse_sps(&sps[..6]), None);
+    }
+
+    #[test]
+    fn test_nearest_access_unit() {
+        let units = scan_access_units(&h264_stream(), 10.0);
+        assert_eq!(nearest_access_unit(&units, 0.0), Some(0));
+        assert_eq!(nearest_access_unit(&units, 0.34), Some(3));
+        assert_eq!(nearest_access_unit(&units, 0.36), Some(4));
+        assert_eq!(nearest_access_unit(&units, 50.0), Some(19));
+        assert_eq!(nearest_access_unit(&[], 1.0), None);
+    }
+}
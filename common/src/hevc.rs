@@ -0,0 +1,431 @@
+// H.265/HEVC Annex B 解析
+//
+// NAL 头为两个字节，类型在第一个字节的中间 6 位。访问单元的划分规则与 H.264 相同：
+// 参数集/SEI/分隔符归入下一帧，条带头的 first_slice_segment_in_pic_flag 标记新的一帧；
+// IRAP 帧（BLA/IDR/CRA）作为关键帧。访问单元表复用 `h264::AccessUnit`。
+
+use crate::h264::{self, AccessUnit, BitReader, NalRole};
+
+/// NAL 类型：IRAP 范围的起点（BLA_W_LP）
+pub const NAL_BLA_W_LP: u8 = 16;
+/// NAL 类型：IDR 条带（带 RADL 前导帧）
+pub const NAL_IDR_W_RADL: u8 = 19;
+/// NAL 类型：IDR 条带（无前导帧）
+pub const NAL_IDR_N_LP: u8 = 20;
+/// NAL 类型：CRA 条带
+pub const NAL_CRA: u8 = 21;
+/// NAL 类型：视频参数集
+pub const NAL_VPS: u8 = 32;
+/// NAL 类型：序列参数集
+pub const NAL_SPS: u8 = 33;
+/// NAL 类型：图像参数集
+pub const NAL_PPS: u8 = 34;
+/// NAL 类型：访问单元分隔符
+pub const NAL_AUD: u8 = 35;
+/// NAL 类型：前缀 SEI
+pub const NAL_PREFIX_SEI: u8 = 39;
+
+/// NAL 单元类型
+pub fn nal_type(nal: &[u8]) -> u8 {
+    nal.first().map_or(0, |header| (header >> 1) & 0x3F)
+}
+
+/// 是否为 IRAP（随机访问点）条带
+pub fn is_irap(nal_type: u8) -> bool {
+    (NAL_BLA_W_LP..=23).contains(&nal_type)
+}
+
+/// 扫描 H.265 Annex B 码流中的访问单元
+pub fn scan_access_units(data: &[u8], fps: f64) -> Vec<AccessUnit> {
+    h264::split_access_units(data, fps, nal_role)
+}
+
+/// H.265 NAL 在访问单元划分中的作用，`nal` 从 NAL 头开始
+pub(crate) fn nal_role(nal: &[u8]) -> NalRole {
+    match nal_type(nal) {
+        NAL_VPS..=NAL_AUD | NAL_PREFIX_SEI | 41..=44 | 48..=55 => NalRole::Prefix,
+        // 条带头紧跟两字节 NAL 头，第一位是 first_slice_segment_in_pic_flag
+        nal_type @ 0..=31 => NalRole::Slice {
+            first_in_picture: nal.get(2).is_some_and(|b| b & 0x80 != 0),
+            keyframe: is_irap(nal_type),
+        },
+        _ => NalRole::Other,
+    }
+}
+
+/// 是否为子层非参考帧（TRAIL_N/TSA_N/STSA_N/RADL_N/RASL_N 等偶数 VCL 类型）
+pub fn is_disposable(data: &[u8]) -> bool {
+    let mut slices = h264::nal_units(data)
+        .into_iter()
+        .filter(|nal| nal_type(nal) <= 31)
+        .peekable();
+    slices.peek().is_some() && slices.all(|nal| nal_type(nal) <= 14 && nal_type(nal) & 1 == 0)
+}
+
+/// 码流中的第一组 VPS/SPS/PPS
+pub fn parameter_sets(data: &[u8]) -> Option<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+    let nals = h264::nal_units(data);
+    let find = |kind: u8| nals.iter().find(|nal| nal_type(nal) == kind).map(|nal| nal.to_vec());
+    Some((find(NAL_VPS)?, find(NAL_SPS)?, find(NAL_PPS)?))
+}
+
+/// 码流是否像 H.265：前几个 NAL 中出现 H.265 的参数集/分隔符头
+///
+/// H.265 参数集的 NAL 头是 `40 01`/`42 01`/`44 01`/`46 01`，对应的 H.264 NAL 类型
+/// （0/2/4/6 且 nal_ref_idc 为 2）在实际码流中不会出现。
+pub fn looks_like_hevc(data: &[u8]) -> bool {
+    h264::start_codes(data)
+        .into_iter()
+        .take(8)
+        .any(|(_, header)| {
+            matches!(data.get(header..header + 2), Some([0x40 | 0x42 | 0x44 | 0x46, 0x01]))
+        })
+}
+
+/// profile_tier_level 中的 general 部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileTierLevel {
+    pub profile_space: u8,
+    /// 是否为 High tier
+    pub tier_flag: bool,
+    pub profile_idc: u8,
+    pub profile_compatibility_flags: u32,
+    /// progressive/interlaced/non_packed/frame_only 以及 44 位保留约束位
+    pub constraint_indicator_flags: [u8; 6],
+    pub level_idc: u8,
+}
+
+impl ProfileTierLevel {
+    fn parse(reader: &mut BitReader) -> Option<Self> {
+        let profile_space = reader.bits(2)? as u8;
+        let tier_flag = reader.bit()? == 1;
+        let profile_idc = reader.bits(5)? as u8;
+        let profile_compatibility_flags = reader.bits(32)?;
+        let mut constraint_indicator_flags = [0u8; 6];
+        for byte in &mut constraint_indicator_flags {
+            *byte = reader.bits(8)? as u8;
+        }
+        let level_idc = reader.bits(8)? as u8;
+        Some(Self {
+            profile_space,
+            tier_flag,
+            profile_idc,
+            profile_compatibility_flags,
+            constraint_indicator_flags,
+            level_idc,
+        })
+    }
+
+    /// WebCodecs/MSE 使用的编码字符串（ISO/IEC 14496-15 附录 E），如 "hvc1.1.6.L93.B0"
+    ///
+    /// 兼容标志按位反转后以十六进制表示，约束字节省略末尾的 0。
+    pub fn codec_string(&self) -> String {
+        let space = match self.profile_space {
+            1 => "A",
+            2 => "B",
+            3 => "C",
+            _ => "",
+        };
+        let mut codec = format!(
+            "hvc1.{}{}.{:X}.{}{}",
+            space,
+            self.profile_idc,
+            self.profile_compatibility_flags.reverse_bits(),
+            if self.tier_flag { 'H' } else { 'L' },
+            self.level_idc
+        );
+        let used = self
+            .constraint_indicator_flags
+            .iter()
+            .rposition(|&b| b != 0)
+            .map_or(0, |last| last + 1);
+        for byte in &self.constraint_indicator_flags[..used] {
+            codec.push_str(&format!(".{:X}", byte));
+        }
+        codec
+    }
+}
+
+/// SPS 中的流信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpsInfo {
+    /// 宽度（已扣除一致性窗口）
+    pub width: u32,
+    /// 高度（已扣除一致性窗口）
+    pub height: u32,
+    pub chroma_format_idc: u32,
+    pub bit_depth_luma: u32,
+    pub bit_depth_chroma: u32,
+    /// 时域子层数
+    pub max_sub_layers: u32,
+    pub temporal_id_nesting: bool,
+    pub profile: ProfileTierLevel,
+}
+
+/// 解析 SPS 中的档次级别、分辨率和位深
+pub fn parse_sps(sps: &[u8]) -> Option<SpsInfo> {
+    let mut reader = BitReader::new(sps);
+    reader.bits(16)?; // NAL 头
+    reader.bits(4)?; // sps_video_parameter_set_id
+    let max_sub_layers_minus1 = reader.bits(3)?;
+    let temporal_id_nesting = reader.bit()? == 1;
+    let profile = ProfileTierLevel::parse(&mut reader)?;
+
+    // 子层的 profile/level，只需要跳过
+    let sub_layers = (0..max_sub_layers_minus1)
+        .map(|_| Some((reader.bit()?, reader.bit()?)))
+        .collect::<Option<Vec<_>>>()?;
+    if max_sub_layers_minus1 > 0 {
+        for _ in max_sub_layers_minus1..8 {
+            reader.bits(2)?; // reserved_zero_2bits
+        }
+    }
+    for (profile_present, level_present) in sub_layers {
+        if profile_present == 1 {
+            reader.bits(32)?;
+            reader.bits(32)?;
+            reader.bits(24)?;
+        }
+        if level_present == 1 {
+            reader.bits(8)?;
+        }
+    }
+
+    reader.ue()?; // sps_seq_parameter_set_id
+    let chroma_format_idc = reader.ue()?;
+    let mut separate_colour_plane = false;
+    if chroma_format_idc == 3 {
+        separate_colour_plane = reader.bit()? == 1;
+    }
+    let mut width = reader.ue()?;
+    let mut height = reader.ue()?;
+    if reader.bit()? == 1 {
+        // conformance_window_flag，偏移以色度采样为单位
+        let (left, right, top, bottom) = (reader.ue()?, reader.ue()?, reader.ue()?, reader.ue()?);
+        let (sub_width, sub_height) = match (chroma_format_idc, separate_colour_plane) {
+            (1, _) => (2, 2),
+            (2, _) => (2, 1),
+            _ => (1, 1),
+        };
+        width = width.checked_sub((left + right) * sub_width)?;
+        height = height.checked_sub((top + bottom) * sub_height)?;
+    }
+    let bit_depth_luma = reader.ue()? + 8;
+    let bit_depth_chroma = reader.ue()? + 8;
+
+    Some(SpsInfo {
+        width,
+        height,
+        chroma_format_idc,
+        bit_depth_luma,
+        bit_depth_chroma,
+        max_sub_layers: max_sub_layers_minus1 + 1,
+        temporal_id_nesting,
+        profile,
+    })
+}
+
+/// SPS 对应的编码字符串
+pub fn codec_string(sps: &[u8]) -> Option<String> {
+    Some(parse_sps(sps)?.profile.codec_string())
+}
+
+/// 生成 hvcC（HEVCDecoderConfigurationRecord）内容，NAL 长度前缀为 4 字节
+pub fn decoder_configuration(vps: &[u8], sps: &[u8], pps: &[u8]) -> Option<Vec<u8>> {
+    let info = parse_sps(sps)?;
+    let profile = info.profile;
+
+    let mut config = vec![
+        1, // configurationVersion
+        (profile.profile_space << 6) | ((profile.tier_flag as u8) << 5) | profile.profile_idc,
+    ];
+    config.extend_from_slice(&profile.profile_compatibility_flags.to_be_bytes());
+    config.extend_from_slice(&profile.constraint_indicator_flags);
+    config.push(profile.level_idc);
+    config.extend_from_slice(&0xF000u16.to_be_bytes()); // min_spatial_segmentation_idc
+    config.push(0xFC); // parallelismType（未知）
+    config.push(0xFC | info.chroma_format_idc as u8);
+    config.push(0xF8 | (info.bit_depth_luma - 8) as u8);
+    config.push(0xF8 | (info.bit_depth_chroma - 8) as u8);
+    config.extend_from_slice(&0u16.to_be_bytes()); // avgFrameRate
+    // constantFrameRate(2) | numTemporalLayers(3) | temporalIdNested(1) | lengthSizeMinusOne(2)
+    config.push(((info.max_sub_layers as u8 & 0x07) << 3) | ((info.temporal_id_nesting as u8) << 2) | 0x03);
+
+    config.push(3); // numOfArrays
+    for (kind, nal) in [(NAL_VPS, vps), (NAL_SPS, sps), (NAL_PPS, pps)] {
+        config.push(0x80 | kind); // array_completeness
+        config.extend_from_slice(&1u16.to_be_bytes());
+        config.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+        config.extend_from_slice(nal);
+    }
+    Some(config)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// 逐位写入 RBSP，最后插入防竞争字节
+    struct BitWriter {
+        bytes: Vec<u8>,
+        bits: usize,
+    }
+
+    impl BitWriter {
+        fn new() -> Self {
+            Self { bytes: Vec::new(), bits: 0 }
+        }
+
+        fn put(&mut self, value: u64, count: usize) {
+            for i in (0..count).rev() {
+                if self.bits.is_multiple_of(8) {
+                    self.bytes.push(0);
+                }
+                let bit = ((value >> i) & 1) as u8;
+                *self.bytes.last_mut().unwrap() |= bit << (7 - self.bits % 8);
+                self.bits += 1;
+            }
+        }
+
+        fn ue(&mut self, value: u32) {
+            let code = value as u64 + 1;
+            let len = 64 - code.leading_zeros() as usize;
+            self.put(0, len - 1);
+            self.put(code, len);
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            self.put(1, 1); // rbsp_stop_one_bit
+            let mut nal = Vec::new();
+            let mut zeros = 0;
+            for byte in self.bytes {
+                if zeros >= 2 && byte <= 3 {
+                    nal.push(3);
+                    zeros = 0;
+                }
+                zeros = if byte == 0 { zeros + 1 } else { 0 };
+                nal.push(byte);
+            }
+            nal
+        }
+    }
+
+    /// Main profile、Level 3.1、1920x1080（1088 行裁剪 8 行）的 SPS
+    pub(crate) fn main_profile_sps() -> Vec<u8> {
+        let mut w = BitWriter::new();
+        w.put(0x4201, 16); // NAL 头
+        w.put(0, 4); // sps_video_parameter_set_id
+        w.put(0, 3); // sps_max_sub_layers_minus1
+        w.put(1, 1); // sps_temporal_id_nesting_flag
+        w.put(0, 2); // general_profile_space
+        w.put(0, 1); // general_tier_flag
+        w.put(1, 5); // general_profile_idc（Main）
+        w.put(0x6000_0000, 32); // 兼容 Main 与 Main 10
+        w.put(0xB000_0000_0000, 48); // progressive、non_packed、frame_only
+        w.put(93, 8); // general_level_idc（3.1）
+        w.ue(0); // sps_seq_parameter_set_id
+        w.ue(1); // chroma_format_idc（4:2:0）
+        w.ue(1920);
+        w.ue(1088);
+        w.put(1, 1); // conformance_window_flag
+        w.ue(0);
+        w.ue(0);
+        w.ue(0);
+        w.ue(4);
+        w.ue(0); // bit_depth_luma_minus8
+        w.ue(0); // bit_depth_chroma_minus8
+        w.finish()
+    }
+
+    /// VPS/SPS/PPS + IDR、两个非关键帧、CRA，每帧两个条带
+    fn hevc_stream() -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut nal = |bytes: &[u8]| {
+            data.extend_from_slice(&[0, 0, 0, 1]);
+            data.extend_from_slice(bytes);
+        };
+        nal(&[0x40, 0x01, 0x0c, 0x01]);
+        nal(&main_profile_sps());
+        nal(&[0x44, 0x01, 0xc1, 0x72]);
+        nal(&[0x26, 0x01, 0xaf, 0x01]); // IDR_W_RADL，首条带
+        nal(&[0x26, 0x01, 0x20, 0x02]); // 同一帧的第二个条带
+        nal(&[0x02, 0x01, 0xd0, 0x03]); // TRAIL_R
+        nal(&[0x02, 0x01, 0x40, 0x04]);
+        nal(&[0x4e, 0x01, 0x05, 0x01]); // 前缀 SEI
+        nal(&[0x02, 0x01, 0xd0, 0x05]);
+        nal(&[0x2a, 0x01, 0xa0, 0x06]); // CRA
+        data
+    }
+
+    #[test]
+    fn test_scan_access_units() {
+        let data = hevc_stream();
+        let units = scan_access_units(&data, 25.0);
+
+        assert_eq!(units.len(), 4);
+        assert!(units[0].is_keyframe && units[3].is_keyframe);
+        assert!(!units[1].is_keyframe && !units[2].is_keyframe);
+        // 关键帧从 VPS 开始，SEI 归入后一帧
+        assert_eq!(units[0].offset, 0);
+        assert_eq!(&data[units[2].offset as usize + 4..][..2], &[0x4e, 0x01]);
+        let last = units.last().unwrap();
+        assert_eq!(last.offset + last.size as u64, data.len() as u64);
+        assert_eq!(units[3].timestamp, 0.12);
+    }
+
+    #[test]
+    fn test_parameter_sets_and_detection() {
+        let data = hevc_stream();
+        let (vps, sps, pps) = parameter_sets(&data).unwrap();
+        assert_eq!(nal_type(&vps), NAL_VPS);
+        assert_eq!(sps, main_profile_sps());
+        assert_eq!(nal_type(&pps), NAL_PPS);
+
+        assert!(looks_like_hevc(&data));
+        assert!(!looks_like_hevc(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f, 0, 0, 0, 1, 0x68, 0xce]));
+    }
+
+    #[test]
+    fn test_is_disposable() {
+        // TRAIL_N
+        assert!(is_disposable(&[0, 0, 0, 1, 0x00, 0x01, 0xaf]));
+        // TRAIL_R、IDR_W_RADL
+        assert!(!is_disposable(&[0, 0, 0, 1, 0x02, 0x01, 0xaf]));
+        assert!(!is_disposable(&[0, 0, 0, 1, 0x26, 0x01, 0xaf]));
+        assert!(!is_disposable(&[0, 0, 0, 1, 0x40, 0x01, 0x0c]));
+    }
+
+    #[test]
+    fn test_parse_sps_and_codec_string() {
+        let info = parse_sps(&main_profile_sps()).unwrap();
+        assert_eq!((info.width, info.height), (1920, 1080));
+        assert_eq!((info.bit_depth_luma, info.chroma_format_idc), (8, 1));
+        assert_eq!(info.profile.profile_idc, 1);
+        assert_eq!(codec_string(&main_profile_sps()).as_deref(), Some("hvc1.1.6.L93.B0"));
+
+        let high_tier = ProfileTierLevel {
+            profile_space: 0,
+            tier_flag: true,
+            profile_idc: 2,
+            profile_compatibility_flags: 0x2000_0000,
+            constraint_indicator_flags: [0; 6],
+            level_idc: 120,
+        };
+        assert_eq!(high_tier.codec_string(), "hvc1.2.4.H120");
+
+        assert_eq!(parse_sps(&main_profile_sps()[..8]), None);
+    }
+
+    #[test]
+    fn test_decoder_configuration() {
+        let sps = main_profile_sps();
+        let config = decoder_configuration(&[0x40, 0x01, 0x0c], &sps, &[0x44, 0x01, 0xc1]).unwrap();
+        assert_eq!(config[1], 0x01);
+        assert_eq!(&config[2..6], &[0x60, 0, 0, 0]);
+        assert_eq!(config[12], 93);
+        assert_eq!(config[21] & 0x03, 3);
+        assert_eq!(config[22], 3);
+        assert_eq!(config[23], 0x80 | NAL_VPS);
+        assert!(config.ends_with(&[0x44, 0x01, 0xc1]));
+    }
+}
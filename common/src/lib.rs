@@ -2,6 +2,13 @@ pub mod types;
 pub mod protocol;
 pub mod error;
 pub mod utils;
+pub mod trick_play;
+pub mod h264;
+pub mod hevc;
+pub mod aac;
+pub mod mp4;
+pub mod ts;
+pub mod demux;
 
 pub use types::*;
 pub use protocol::*;
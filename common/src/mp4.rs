@@ -0,0 +1,722 @@
+// MP4（ISO-BMFF）视频轨道解复用
+//
+// 解析 moov/trak/stbl 样本表（stsz、stsc、stco/co64、stss、stts、ctts）以及分片 MP4 的
+// moof/traf/trun，得到视频轨道每个样本的位置、解码/显示时间和关键帧标志。
+// 样本是长度前缀格式（avcC/hvcC），转换为 Annex B 后与裸流共用访问单元的处理流程。
+
+use crate::h264::{self, AccessUnit};
+use crate::{hevc, VideoCodec};
+
+/// 样本标志：非同步样本（sample_is_non_sync_sample）
+const SAMPLE_FLAG_NON_SYNC: u32 = 0x0001_0000;
+
+/// 视频轨道中的一个样本（一帧）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    /// 在文件中的偏移
+    pub offset: u64,
+    /// 字节数
+    pub size: u32,
+    /// 解码时间（轨道时间刻度）
+    pub decode_time: u64,
+    /// 显示时间相对解码时间的偏移（ctts / trun）
+    pub composition_offset: i32,
+    /// 时长（轨道时间刻度）
+    pub duration: u32,
+    /// 是否为同步样本（关键帧）
+    pub is_keyframe: bool,
+}
+
+/// MP4 中的视频轨道
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoTrack {
+    pub track_id: u32,
+    /// 时间刻度（每秒的单位数）
+    pub timescale: u32,
+    /// 样本描述类型（avc1/avc3/hvc1/hev1）
+    pub codec: [u8; 4],
+    /// NAL 长度前缀的字节数
+    pub nal_length_size: usize,
+    /// 解码配置中的参数集，avcC 按 SPS、PPS 顺序，hvcC 按记录中的数组顺序（通常为 VPS、SPS、PPS）
+    pub parameter_sets: Vec<Vec<u8>>,
+    /// 按解码顺序排列的样本
+    pub samples: Vec<Sample>,
+}
+
+impl VideoTrack {
+    /// 视频编码格式
+    pub fn video_codec(&self) -> VideoCodec {
+        match &self.codec {
+            b"hvc1" | b"hev1" => VideoCodec::H265,
+            _ => VideoCodec::H264,
+        }
+    }
+
+    /// 样本的解码时间（秒）
+    pub fn decode_timestamp(&self, sample: &Sample) -> f64 {
+        sample.decode_time as f64 / self.timescale as f64
+    }
+
+    /// 样本的显示时间（秒）
+    pub fn presentation_timestamp(&self, sample: &Sample) -> f64 {
+        (sample.decode_time as i64 + sample.composition_offset as i64) as f64 / self.timescale as f64
+    }
+
+    /// 按样本平均时长计算的帧率
+    pub fn frame_rate(&self) -> Option<f64> {
+        let total: u64 = self.samples.iter().map(|s| s.duration as u64).sum();
+        (total > 0).then(|| self.samples.len() as f64 * self.timescale as f64 / total as f64)
+    }
+
+    /// 把长度前缀格式的样本转换为 Annex B 追加到 `out`
+    ///
+    /// 关键帧自身不带 SPS 时在前面补上解码配置中的参数集，保证每个 GOP 可独立解码。
+    pub fn write_annex_b(&self, sample_data: &[u8], is_keyframe: bool, out: &mut Vec<u8>) {
+        let nals = self.sample_nal_units(sample_data);
+        let is_sps = |nal: &[u8]| match self.video_codec() {
+            VideoCodec::H264 => h264::nal_type(nal) == h264::NAL_SPS,
+            VideoCodec::H265 => hevc::nal_type(nal) == hevc::NAL_SPS,
+        };
+        if is_keyframe && !nals.iter().any(|nal| is_sps(nal)) {
+            for parameter_set in &self.parameter_sets {
+                out.extend_from_slice(&[0, 0, 0, 1]);
+                out.extend_from_slice(parameter_set);
+            }
+        }
+        for nal in nals {
+            out.extend_from_slice(&[0, 0, 0, 1]);
+            out.extend_from_slice(nal);
+        }
+    }
+
+    /// 拆分样本中的 NAL 单元，遇到越界的长度时停止
+    fn sample_nal_units<'a>(&self, sample_data: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut nals = Vec::new();
+        let mut rest = sample_data;
+        while rest.len() >= self.nal_length_size {
+            let (prefix, body) = rest.split_at(self.nal_length_size);
+            let len = prefix.iter().fold(0usize, |len, &b| (len << 8) | b as usize);
+            if len > body.len() {
+                break;
+            }
+            if len > 0 {
+                nals.push(&body[..len]);
+            }
+            rest = &body[len..];
+        }
+        nals
+    }
+}
+
+/// 数据是否以 MP4 box 开头
+pub fn is_mp4(data: &[u8]) -> bool {
+    data.len() >= 8 && matches!(&data[4..8], b"ftyp" | b"styp" | b"moov" | b"moof" | b"mdat")
+}
+
+/// 解析第一个 H.264/H.265 视频轨道（含分片 MP4 中 moof 的样本）
+pub fn parse_video_track(data: &[u8]) -> Option<VideoTrack> {
+    let top = parse_boxes(data, 0);
+    let moov = top.iter().find(|b| &b.kind == b"moov")?;
+    let moov_children = parse_boxes(moov.body, moov.body_offset);
+    let mut track = moov_children
+        .iter()
+        .filter(|b| &b.kind == b"trak")
+        .find_map(|trak| parse_trak(trak.body))?;
+
+    // 分片 MP4：样本的默认值在 mvex/trex 中
+    let trex = moov_children
+        .iter()
+        .find(|b| &b.kind == b"mvex")
+        .and_then(|mvex| {
+            parse_boxes(mvex.body, 0)
+                .iter()
+                .filter(|b| &b.kind == b"trex")
+                .filter_map(|b| parse_trex(b.body))
+                .find(|d| d.track_id == track.track_id)
+        })
+        .unwrap_or_default();
+
+    let mut next_decode_time = track
+        .samples
+        .last()
+        .map_or(0, |s| s.decode_time + s.duration as u64);
+    for moof in top.iter().filter(|b| &b.kind == b"moof") {
+        for traf in parse_boxes(moof.body, moof.body_offset).iter().filter(|b| &b.kind == b"traf") {
+            parse_traf(traf.body, moof.offset as u64, trex, &mut track, &mut next_decode_time);
+        }
+    }
+
+    (!track.samples.is_empty()).then_some(track)
+}
+
+/// 把视频轨道转换为 Annex B 码流，返回码流和按解码顺序排列的访问单元表
+///
+/// 显示时间整体平移到从 0 开始（相当于编辑列表去掉首帧的合成时间偏移）；
+/// 超出文件末尾的样本（文件被截断）不输出。
+pub fn to_annex_b(data: &[u8], track: &VideoTrack) -> (Vec<u8>, Vec<AccessUnit>) {
+    // 按时间刻度整数计算平移，避免浮点误差
+    let start = track
+        .samples
+        .iter()
+        .map(|s| s.decode_time as i64 + s.composition_offset as i64)
+        .min()
+        .unwrap_or(0);
+    let seconds = |ticks: i64| (ticks - start) as f64 / track.timescale as f64;
+
+    let mut stream = Vec::new();
+    let mut units = Vec::with_capacity(track.samples.len());
+    for sample in &track.samples {
+        let begin = sample.offset as usize;
+        let Some(sample_data) = data.get(begin..begin + sample.size as usize) else {
+            break;
+        };
+        let offset = stream.len();
+        track.write_annex_b(sample_data, sample.is_keyframe, &mut stream);
+        units.push(AccessUnit {
+            timestamp: seconds(sample.decode_time as i64 + sample.composition_offset as i64),
+            decode_timestamp: seconds(sample.decode_time as i64),
+            duration: sample.duration as f64 / track.timescale as f64,
+            offset: offset as u64,
+            size: (stream.len() - offset) as u32,
+            is_keyframe: sample.is_keyframe,
+        });
+    }
+    (stream, units)
+}
+
+/// 一个 box
+struct Mp4Box<'a> {
+    kind: [u8; 4],
+    /// 内容（不含头）
+    body: &'a [u8],
+    /// box 在文件中的偏移
+    offset: usize,
+    /// 内容在文件中的偏移
+    body_offset: usize,
+}
+
+/// 拆分连续的 box，`base` 为 `data` 在文件中的偏移；遇到截断的 box 时停止
+fn parse_boxes(data: &[u8], base: usize) -> Vec<Mp4Box<'_>> {
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let mut reader = Reader::new(&data[pos..]);
+        let (Some(size), Some(kind)) = (reader.u32(), reader.fourcc()) else {
+            break;
+        };
+        let (header, size) = match size {
+            // 延伸到文件末尾
+            0 => (8, data.len() - pos),
+            1 => match reader.u64() {
+                Some(size) => (16, size as usize),
+                None => break,
+            },
+            size => (8, size as usize),
+        };
+        if size < header || size > data.len() - pos {
+            break;
+        }
+        boxes.push(Mp4Box {
+            kind,
+            body: &data[pos + header..pos + size],
+            offset: base + pos,
+            body_offset: base + pos + header,
+        });
+        pos += size;
+    }
+    boxes
+}
+
+/// 查找指定类型的第一个子 box
+fn find_box<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    parse_boxes(data, 0)
+        .into_iter()
+        .find(|b| &b.kind == kind)
+        .map(|b| b.body)
+}
+
+/// 解析视频 trak，非视频轨道或不支持的编码返回 None
+fn parse_trak(trak: &[u8]) -> Option<VideoTrack> {
+    let mut tkhd = Reader::new(find_box(trak, b"tkhd")?);
+    let version = tkhd.u8()?;
+    tkhd.skip(3 + if version == 1 { 16 } else { 8 })?;
+    let track_id = tkhd.u32()?;
+
+    let mdia = find_box(trak, b"mdia")?;
+    let hdlr = find_box(mdia, b"hdlr")?;
+    if hdlr.get(8..12)? != b"vide" {
+        return None;
+    }
+
+    let mut mdhd = Reader::new(find_box(mdia, b"mdhd")?);
+    let version = mdhd.u8()?;
+    mdhd.skip(3 + if version == 1 { 16 } else { 8 })?;
+    let timescale = mdhd.u32().filter(|&t| t > 0)?;
+
+    let stbl = find_box(find_box(mdia, b"minf")?, b"stbl")?;
+    let (codec, nal_length_size, parameter_sets) = parse_stsd(find_box(stbl, b"stsd")?)?;
+
+    Some(VideoTrack {
+        track_id,
+        timescale,
+        codec,
+        nal_length_size,
+        parameter_sets,
+        samples: parse_sample_table(stbl)?,
+    })
+}
+
+/// 解析第一个样本描述，返回编码类型、NAL 长度前缀字节数和参数集
+fn parse_stsd(stsd: &[u8]) -> Option<([u8; 4], usize, Vec<Vec<u8>>)> {
+    let entry = parse_boxes(stsd.get(8..)?, 0).into_iter().next()?;
+    // VisualSampleEntry 固定字段共 78 字节，之后是子 box
+    let children = entry.body.get(78..)?;
+    let (nal_length_size, parameter_sets) = match &entry.kind {
+        b"avc1" | b"avc3" => parse_avcc(find_box(children, b"avcC")?)?,
+        b"hvc1" | b"hev1" => parse_hvcc(find_box(children, b"hvcC")?)?,
+        _ => return None,
+    };
+    Some((entry.kind, nal_length_size, parameter_sets))
+}
+
+/// 解析 AVCDecoderConfigurationRecord，返回 NAL 长度前缀字节数和参数集
+fn parse_avcc(avcc: &[u8]) -> Option<(usize, Vec<Vec<u8>>)> {
+    let mut reader = Reader::new(avcc);
+    reader.skip(4)?;
+    let nal_length_size = (reader.u8()? & 0x03) as usize + 1;
+    let mut parameter_sets = Vec::new();
+    for mask in [0x1F, 0xFF] {
+        let count = reader.u8()? & mask;
+        for _ in 0..count {
+            let len = reader.u16()? as usize;
+            parameter_sets.push(reader.bytes(len)?.to_vec());
+        }
+    }
+    Some((nal_length_size, parameter_sets))
+}
+
+/// 解析 HEVCDecoderConfigurationRecord，返回 NAL 长度前缀字节数和参数集
+fn parse_hvcc(hvcc: &[u8]) -> Option<(usize, Vec<Vec<u8>>)> {
+    let mut reader = Reader::new(hvcc);
+    // configurationVersion 到 avgFrameRate 共 21 字节
+    reader.skip(21)?;
+    let nal_length_size = (reader.u8()? & 0x03) as usize + 1;
+    let mut parameter_sets = Vec::new();
+    for _ in 0..reader.u8()? {
+        reader.u8()?; // array_completeness + NAL_unit_type
+        for _ in 0..reader.u16()? {
+            let len = reader.u16()? as usize;
+            parameter_sets.push(reader.bytes(len)?.to_vec());
+        }
+    }
+    Some((nal_length_size, parameter_sets))
+}
+
+/// 根据样本表计算每个样本的位置、时间和关键帧标志
+fn parse_sample_table(stbl: &[u8]) -> Option<Vec<Sample>> {
+    // stsz：样本大小
+    let mut stsz = Reader::new(find_box(stbl, b"stsz")?);
+    stsz.skip(4)?;
+    let fixed_size = stsz.u32()?;
+    let count = stsz.u32()? as usize;
+    let sizes = if fixed_size == 0 {
+        (0..count).map(|_| stsz.u32()).collect::<Option<Vec<_>>>()?
+    } else {
+        vec![fixed_size; count]
+    };
+    if count == 0 {
+        return Some(Vec::new());
+    }
+
+    // stco/co64：块偏移
+    let chunk_offsets: Vec<u64> = if let Some(stco) = find_box(stbl, b"stco") {
+        read_table(stco, |r| r.u32().map(u64::from))?
+    } else {
+        read_table(find_box(stbl, b"co64")?, |r| r.u64())?
+    };
+
+    // stsc：(first_chunk, samples_per_chunk, sample_description_index)
+    let stsc = read_table(find_box(stbl, b"stsc")?, |r| Some((r.u32()?, r.u32()?, r.u32()?)))?;
+    let mut offsets = Vec::with_capacity(count);
+    let mut entry = 0;
+    for (index, &chunk_offset) in chunk_offsets.iter().enumerate() {
+        let chunk = index as u32 + 1;
+        while stsc.get(entry + 1).is_some_and(|next| next.0 <= chunk) {
+            entry += 1;
+        }
+        let mut offset = chunk_offset;
+        for _ in 0..stsc.get(entry).map_or(0, |e| e.1) {
+            let Some(&size) = sizes.get(offsets.len()) else {
+                break;
+            };
+            offsets.push(offset);
+            offset += size as u64;
+        }
+    }
+
+    // stts：(sample_count, sample_delta)
+    let stts = read_table(find_box(stbl, b"stts")?, |r| Some((r.u32()?, r.u32()?)))?;
+    let durations: Vec<u32> = stts
+        .iter()
+        .flat_map(|&(n, delta)| std::iter::repeat_n(delta, n as usize))
+        .collect();
+
+    // ctts：(sample_count, sample_offset)，版本 0 的偏移按有符号数解释（兼容常见编码器）
+    let composition_offsets: Vec<i32> = find_box(stbl, b"ctts")
+        .and_then(|ctts| read_table(ctts, |r| Some((r.u32()?, r.u32()? as i32))))
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|&(n, offset)| std::iter::repeat_n(offset, n as usize))
+        .collect();
+
+    // stss：同步样本序号（从 1 开始），没有 stss 时所有样本都是同步样本
+    let sync_samples = find_box(stbl, b"stss").and_then(|stss| read_table(stss, |r| r.u32()));
+
+    let mut decode_time = 0u64;
+    let samples = offsets
+        .iter()
+        .zip(&sizes)
+        .enumerate()
+        .map(|(i, (&offset, &size))| {
+            let duration = durations.get(i).or(durations.last()).copied().unwrap_or(0);
+            let sample = Sample {
+                offset,
+                size,
+                decode_time,
+                composition_offset: composition_offsets.get(i).copied().unwrap_or(0),
+                duration,
+                is_keyframe: sync_samples
+                    .as_ref()
+                    .is_none_or(|sync| sync.binary_search(&(i as u32 + 1)).is_ok()),
+            };
+            decode_time += duration as u64;
+            sample
+        })
+        .collect();
+    Some(samples)
+}
+
+/// 读取全 box（FullBox）中的条目表：版本/标志、条目数、条目
+fn read_table<T>(body: &[u8], mut entry: impl FnMut(&mut Reader) -> Option<T>) -> Option<Vec<T>> {
+    let mut reader = Reader::new(body);
+    reader.skip(4)?;
+    let count = reader.u32()?;
+    (0..count).map(|_| entry(&mut reader)).collect()
+}
+
+/// 分片样本的默认值（trex / tfhd）
+#[derive(Debug, Clone, Copy, Default)]
+struct TrackDefaults {
+    track_id: u32,
+    duration: u32,
+    size: u32,
+    flags: u32,
+}
+
+fn parse_trex(trex: &[u8]) -> Option<TrackDefaults> {
+    let mut reader = Reader::new(trex);
+    reader.skip(4)?;
+    let track_id = reader.u32()?;
+    reader.skip(4)?; // default_sample_description_index
+    Some(TrackDefaults {
+        track_id,
+        duration: reader.u32()?,
+        size: reader.u32()?,
+        flags: reader.u32()?,
+    })
+}
+
+/// 解析 traf 中属于视频轨道的样本，追加到轨道样本表
+fn parse_traf(
+    traf: &[u8],
+    moof_offset: u64,
+    trex: TrackDefaults,
+    track: &mut VideoTrack,
+    next_decode_time: &mut u64,
+) -> Option<()> {
+    let children = parse_boxes(traf, 0);
+
+    let mut tfhd = Reader::new(children.iter().find(|b| &b.kind == b"tfhd")?.body);
+    let flags = tfhd.u32()? & 0x00FF_FFFF;
+    if tfhd.u32()? != track.track_id {
+        return None;
+    }
+    // 没有 base_data_offset 时以 moof 起点为基准（default-base-is-moof）
+    let base = if flags & 0x01 != 0 { tfhd.u64()? } else { moof_offset };
+    if flags & 0x02 != 0 {
+        tfhd.skip(4)?;
+    }
+    let mut defaults = trex;
+    if flags & 0x08 != 0 {
+        defaults.duration = tfhd.u32()?;
+    }
+    if flags & 0x10 != 0 {
+        defaults.size = tfhd.u32()?;
+    }
+    if flags & 0x20 != 0 {
+        defaults.flags = tfhd.u32()?;
+    }
+
+    if let Some(tfdt) = children.iter().find(|b| &b.kind == b"tfdt") {
+        let mut reader = Reader::new(tfdt.body);
+        let version = reader.u8()?;
+        reader.skip(3)?;
+        *next_decode_time = if version == 1 { reader.u64()? } else { reader.u32()? as u64 };
+    }
+
+    let mut data_end = base;
+    for trun in children.iter().filter(|b| &b.kind == b"trun") {
+        let mut reader = Reader::new(trun.body);
+        let flags = reader.u32()? & 0x00FF_FFFF;
+        let count = reader.u32()?;
+        let mut offset = if flags & 0x01 != 0 {
+            base.checked_add_signed(reader.u32()? as i32 as i64)?
+        } else {
+            data_end
+        };
+        let first_flags = if flags & 0x04 != 0 { Some(reader.u32()?) } else { None };
+
+        for i in 0..count {
+            let duration = if flags & 0x100 != 0 { reader.u32()? } else { defaults.duration };
+            let size = if flags & 0x200 != 0 { reader.u32()? } else { defaults.size };
+            let sample_flags = if flags & 0x400 != 0 {
+                reader.u32()?
+            } else {
+                first_flags.filter(|_| i == 0).unwrap_or(defaults.flags)
+            };
+            let composition_offset = if flags & 0x800 != 0 { reader.u32()? as i32 } else { 0 };
+
+            track.samples.push(Sample {
+                offset,
+                size,
+                decode_time: *next_decode_time,
+                composition_offset,
+                duration,
+                is_keyframe: sample_flags & SAMPLE_FLAG_NON_SYNC == 0,
+            });
+            offset += size as u64;
+            *next_decode_time += duration as u64;
+        }
+        data_end = offset;
+    }
+    Some(())
+}
+
+/// 大端字节读取器
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.position..self.position.checked_add(len)?)?;
+        self.position += len;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.bytes(8).map(|b| u64::from_be_bytes(b.try_into().unwrap()))
+    }
+
+    fn fourcc(&mut self) -> Option<[u8; 4]> {
+        self.bytes(4).map(|b| [b[0], b[1], b[2], b[3]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPS: [u8; 4] = [0x67, 0x42, 0x00, 0x1f];
+    const PPS: [u8; 4] = [0x68, 0xce, 0x38, 0x80];
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(body);
+        data
+    }
+
+    /// 全 box：版本/标志 + 32 位字段
+    fn full_box(kind: &[u8; 4], version_flags: u32, fields: &[u32]) -> Vec<u8> {
+        let body: Vec<u8> = std::iter::once(version_flags)
+            .chain(fields.iter().copied())
+            .flat_map(u32::to_be_bytes)
+            .collect();
+        mp4_box(kind, &body)
+    }
+
+    /// 长度前缀的单 NAL 样本
+    fn sample(nal: &[u8]) -> Vec<u8> {
+        let mut data = (nal.len() as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(nal);
+        data
+    }
+
+    /// 时间刻度 1000 的 H.264 视频 trak
+    fn video_trak(sample_table: &[Vec<u8>]) -> Vec<u8> {
+        let mut avcc = vec![1, 0x42, 0x00, 0x1f, 0xFF, 0xE1];
+        avcc.extend_from_slice(&(SPS.len() as u16).to_be_bytes());
+        avcc.extend_from_slice(&SPS);
+        avcc.push(1);
+        avcc.extend_from_slice(&(PPS.len() as u16).to_be_bytes());
+        avcc.extend_from_slice(&PPS);
+        let mut avc1 = vec![0u8; 78];
+        avc1.extend(mp4_box(b"avcC", &avcc));
+        let mut stsd = full_box(b"stsd", 0, &[1]);
+        stsd.extend(mp4_box(b"avc1", &avc1));
+        let stsd = mp4_box(b"stsd", &stsd[8..]);
+
+        let stbl = mp4_box(b"stbl", &[stsd, sample_table.concat()].concat());
+        let mdia = [
+            full_box(b"mdhd", 0, &[0, 0, 1000, 0, 0]),
+            full_box(b"hdlr", 0, &[0, u32::from_be_bytes(*b"vide"), 0, 0, 0]),
+            mp4_box(b"minf", &stbl),
+        ]
+        .concat();
+        let trak = [full_box(b"tkhd", 0, &[0, 0, 1, 0, 0]), mp4_box(b"mdia", &mdia)].concat();
+        mp4_box(b"trak", &trak)
+    }
+
+    #[test]
+    fn test_parse_sample_table() {
+        // I P B：解码顺序时间 0/100/200，ctts 把 P 帧推到 B 帧之后显示
+        let samples = [sample(&[0x65, 0x88, 0x01]), sample(&[0x41, 0x9a, 0x02]), sample(&[0x01, 0x9e, 0x03])];
+        let sizes: Vec<u32> = samples.iter().map(|s| s.len() as u32).collect();
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\0\0");
+
+        let build = |mdat_offset: u32| {
+            let table = vec![
+                full_box(b"stts", 0, &[1, 3, 100]),
+                full_box(b"ctts", 0, &[3, 1, 100, 1, 200, 1, 0]),
+                full_box(b"stss", 0, &[1, 1]),
+                full_box(b"stsc", 0, &[1, 1, 3, 1]),
+                full_box(b"stsz", 0, &[0, 3, sizes[0], sizes[1], sizes[2]]),
+                full_box(b"stco", 0, &[1, mdat_offset]),
+            ];
+            mp4_box(b"moov", &video_trak(&table))
+        };
+        let mdat_offset = (ftyp.len() + build(0).len() + 8) as u32;
+        let data = [ftyp, build(mdat_offset), mp4_box(b"mdat", &samples.concat())].concat();
+        assert!(is_mp4(&data));
+
+        let track = parse_video_track(&data).unwrap();
+        assert_eq!(&track.codec, b"avc1");
+        assert_eq!(track.nal_length_size, 4);
+        assert_eq!(track.parameter_sets, vec![SPS.to_vec(), PPS.to_vec()]);
+        assert_eq!(track.samples.len(), 3);
+        assert_eq!(track.samples[1].offset, mdat_offset as u64 + sizes[0] as u64);
+        assert_eq!(track.frame_rate(), Some(10.0));
+        assert!(track.samples[0].is_keyframe && !track.samples[1].is_keyframe);
+
+        let (stream, units) = to_annex_b(&data, &track);
+        // 显示时间从 0 开始，P 帧在 B 帧之后显示
+        let pts: Vec<f64> = units.iter().map(|u| u.timestamp).collect();
+        assert_eq!(pts, vec![0.0, 0.2, 0.1]);
+        assert!(units.windows(2).all(|w| w[0].decode_timestamp < w[1].decode_timestamp));
+        // 关键帧前补上 SPS/PPS，转换后的码流可按 Annex B 重新解析
+        let types: Vec<u8> = h264::nal_units(&stream).iter().map(|nal| h264::nal_type(nal)).collect();
+        assert_eq!(types, vec![h264::NAL_SPS, h264::NAL_PPS, h264::NAL_IDR, 1, 1]);
+        assert_eq!(units[0].size as usize, 4 * 3 + SPS.len() + PPS.len() + 3);
+        assert_eq!(&stream[units[1].offset as usize + 4..][..3], &[0x41, 0x9a, 0x02]);
+    }
+
+    #[test]
+    fn test_parse_fragmented() {
+        let empty_table = vec![
+            full_box(b"stts", 0, &[0]),
+            full_box(b"stsc", 0, &[0]),
+            full_box(b"stsz", 0, &[0, 0]),
+            full_box(b"stco", 0, &[0]),
+        ];
+        // trex：默认时长 40，默认非同步样本
+        let mvex = mp4_box(b"mvex", &full_box(b"trex", 0, &[1, 1, 40, 0, SAMPLE_FLAG_NON_SYNC]));
+        let moov = mp4_box(b"moov", &[video_trak(&empty_table), mvex].concat());
+
+        let samples = [sample(&[0x65, 0x88, 0x01]), sample(&[0x41, 0x9a, 0x02, 0x03])];
+        let sizes: Vec<u32> = samples.iter().map(|s| s.len() as u32).collect();
+        let build_moof = |data_offset: u32| {
+            let traf = [
+                // default-base-is-moof
+                full_box(b"tfhd", 0x02_0000, &[1]),
+                full_box(b"tfdt", 0, &[4000]),
+                // data_offset + first_sample_flags + sample_size
+                full_box(b"trun", 0x0205, &[2, data_offset, 0, sizes[0], sizes[1]]),
+            ]
+            .concat();
+            mp4_box(b"moof", &[full_box(b"mfhd", 0, &[1]), mp4_box(b"traf", &traf)].concat())
+        };
+        let data_offset = (build_moof(0).len() + 8) as u32;
+        let data = [moov.clone(), build_moof(data_offset), mp4_box(b"mdat", &samples.concat())].concat();
+
+        let track = parse_video_track(&data).unwrap();
+        assert_eq!(track.samples.len(), 2);
+        assert_eq!(track.samples[0].offset, (moov.len() as u32 + data_offset) as u64);
+        assert_eq!(track.samples[1].decode_time, 4040);
+        // 首个样本的标志覆盖默认值
+        assert!(track.samples[0].is_keyframe && !track.samples[1].is_keyframe);
+        assert_eq!(track.decode_timestamp(&track.samples[0]), 4.0);
+
+        let (stream, units) = to_annex_b(&data, &track);
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[1].timestamp, 0.04);
+        assert_eq!(&stream[units[1].offset as usize..], &[0, 0, 0, 1, 0x41, 0x9a, 0x02, 0x03]);
+
+        // 截断的文件只返回完整的样本
+        let (_, units) = to_annex_b(&data[..data.len() - 1], &track);
+        assert_eq!(units.len(), 1);
+        assert!(parse_video_track(b"\0\0\0\x08free").is_none());
+    }
+
+    #[test]
+    fn test_parse_hevc_sample_entry() {
+        let (vps, sps, pps) = (vec![0x40, 0x01, 0x0c], hevc::tests::main_profile_sps(), vec![0x44, 0x01, 0xc1]);
+        let hvcc = hevc::decoder_configuration(&vps, &sps, &pps).unwrap();
+        let mut hvc1 = vec![0u8; 78];
+        hvc1.extend(mp4_box(b"hvcC", &hvcc));
+        let stsd = [0, 0, 0, 0, 0, 0, 0, 1].into_iter().chain(mp4_box(b"hvc1", &hvc1)).collect::<Vec<u8>>();
+
+        let (codec, nal_length_size, parameter_sets) = parse_stsd(&stsd).unwrap();
+        assert_eq!(&codec, b"hvc1");
+        assert_eq!(nal_length_size, 4);
+        assert_eq!(parameter_sets, vec![vps.clone(), sps.clone(), pps.clone()]);
+
+        // IRAP 样本不带参数集时补上 VPS/SPS/PPS
+        let track = VideoTrack {
+            track_id: 1,
+            timescale: 1000,
+            codec,
+            nal_length_size,
+            parameter_sets,
+            samples: Vec::new(),
+        };
+        assert_eq!(track.video_codec(), VideoCodec::H265);
+        let mut out = Vec::new();
+        track.write_annex_b(&sample(&[0x26, 0x01, 0xaf]), true, &mut out);
+        let nals = h264::nal_units(&out);
+        assert_eq!(nals.len(), 4);
+        assert_eq!(nals[0], vps.as_slice());
+        assert_eq!(nals[3], &[0x26, 0x01, 0xaf]);
+    }
+}
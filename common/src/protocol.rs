@@ -1,13 +1,624 @@
+use crate::error::{Result, VideoStreamError};
 use crate::types::*;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::SystemTime;
 
-/// 会话开始请求
+/// 协议版本
+///
+/// 主版本号不同即不兼容；次版本号用于在不破坏旧设备的前提下演进消息结构。
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    /// 未协商的旧版协议：SessionStart 直接携带 DeviceInfo，平台回复 "OK"
+    pub const V1_0: ProtocolVersion = ProtocolVersion::new(1, 0);
+    /// 引入版本协商的协议
+    pub const V1_1: ProtocolVersion = ProtocolVersion::new(1, 1);
+    /// 分片携带设备采集/发送时间戳和序号
+    pub const V1_2: ProtocolVersion = ProtocolVersion::new(1, 2);
+    /// 设备声明多路码流，分片携带码流ID
+    pub const V1_3: ProtocolVersion = ProtocolVersion::new(1, 3);
+    /// 分片和录像信息携带视频编码格式（H.264/H.265）
+    pub const V1_4: ProtocolVersion = ProtocolVersion::new(1, 4);
+    /// 分片时间戳改为声明时间基的整数 PTS/DTS 和时长
+    pub const V1_5: ProtocolVersion = ProtocolVersion::new(1, 5);
+    /// 当前实现的协议版本
+    pub const CURRENT: ProtocolVersion = ProtocolVersion::V1_5;
+    /// 仍保留解码器的最低协议版本（至少兼容上一个版本）
+    pub const MIN_SUPPORTED: ProtocolVersion = ProtocolVersion::V1_0;
+
+    pub const fn new(major: u16, minor: u16) -> Self {
+        Self { major, minor }
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// 支持的协议版本范围（闭区间）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProtocolVersionRange {
+    pub min: ProtocolVersion,
+    pub max: ProtocolVersion,
+}
+
+impl ProtocolVersionRange {
+    pub const fn new(min: ProtocolVersion, max: ProtocolVersion) -> Self {
+        Self { min, max }
+    }
+
+    /// 本端实现支持的版本范围
+    pub const fn supported() -> Self {
+        Self::new(ProtocolVersion::MIN_SUPPORTED, ProtocolVersion::CURRENT)
+    }
+
+    pub fn contains(&self, version: ProtocolVersion) -> bool {
+        self.min <= version && version <= self.max
+    }
+
+    /// 选出双方都支持的最高版本，没有交集时返回 None
+    pub fn negotiate(&self, peer: &ProtocolVersionRange) -> Option<ProtocolVersion> {
+        let low = self.min.max(peer.min);
+        let high = self.max.min(peer.max);
+        if low <= high {
+            Some(high)
+        } else {
+            None
+        }
+    }
+}
+
+/// 特性标志位（随 SessionStart 协商，与协议版本独立）
+#[allow(non_snake_case)]
+pub mod FeatureFlags {
+    pub const PLAYBACK_CONTROL: u32 = 0x0000_0001;
+    pub const LIVE_STREAM: u32 = 0x0000_0002;
+    pub const KEYFRAME_INDEX: u32 = 0x0000_0004;
+    pub const RECORDING: u32 = 0x0000_0008;
+    /// 设备响应 ClockSync 时钟同步请求
+    pub const CLOCK_SYNC: u32 = 0x0000_0010;
+    /// 设备支持按 SwitchRendition 切换直通播放码流
+    pub const RENDITION_SWITCH: u32 = 0x0000_0020;
+    /// 设备响应 KeyframeRequest，立即输出 IDR 帧
+    pub const KEYFRAME_REQUEST: u32 = 0x0000_0040;
+    /// 设备在直通播放时发送 AAC 音频分片（`StreamType::AUDIO`）
+    pub const AUDIO: u32 = 0x0000_0080;
+    /// 设备可将整个录像文件上传到平台存储（可续传、SHA-256 校验）
+    pub const FILE_UPLOAD: u32 = 0x0000_0100;
+    /// 设备在每个会话的长期单向流上发送长度前缀分片（`MEDIA_STREAM_MAGIC`）
+    pub const MEDIA_STREAM: u32 = 0x0000_0200;
+    /// 设备可把非参考帧作为 QUIC DATAGRAM 发送，丢包时直接丢弃；平台按序号与流上分片重排
+    pub const MEDIA_DATAGRAM: u32 = 0x0000_0400;
+    /// 设备把关键帧放在优先级更高的独立媒体流上（`MEDIA_KEYFRAME_STREAM_MAGIC`），平台按序号与普通帧合并
+    pub const KEYFRAME_STREAM: u32 = 0x0000_0800;
+
+    /// 本端实现支持的全部特性
+    pub const ALL: u32 = PLAYBACK_CONTROL
+        | LIVE_STREAM
+        | KEYFRAME_INDEX
+        | RECORDING
+        | CLOCK_SYNC
+        | RENDITION_SWITCH
+        | KEYFRAME_REQUEST
+        | AUDIO
+        | FILE_UPLOAD
+        | MEDIA_STREAM
+        | MEDIA_DATAGRAM
+        | KEYFRAME_STREAM;
+}
+
+/// 会话开始请求（协议 1.1 起使用）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionStartRequest {
     pub device_id: String,
     pub device_name: String,
     pub device_type: DeviceType,
     pub capabilities: DeviceCapabilities,
+    pub protocol_versions: ProtocolVersionRange,
+    pub features: u32,
+}
+
+impl SessionStartRequest {
+    /// 转换为平台侧使用的设备信息
+    pub fn into_device_info(self) -> DeviceInfo {
+        DeviceInfo {
+            device_id: self.device_id,
+            device_name: self.device_name,
+            device_type: self.device_type,
+            connection_status: ConnectionStatus::Online,
+            connection_time: SystemTime::now(),
+            last_heartbeat: SystemTime::now(),
+            capabilities: self.capabilities,
+        }
+    }
+}
+
+/// 会话开始响应（平台选定的协议版本与特性）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionStartResponse {
+    pub accepted: bool,
+    pub selected_version: ProtocolVersion,
+    pub supported_versions: ProtocolVersionRange,
+    pub features: u32,
+    pub error_message: Option<String>,
+}
+
+/// 连接上协商生效的协议参数
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NegotiatedProtocol {
+    pub version: ProtocolVersion,
+    pub features: u32,
+}
+
+impl NegotiatedProtocol {
+    /// 旧版设备（未协商）的默认协议参数
+    pub const LEGACY: NegotiatedProtocol = NegotiatedProtocol {
+        version: ProtocolVersion::V1_0,
+        features: 0,
+    };
+
+    pub fn supports(&self, feature: u32) -> bool {
+        self.features & feature == feature
+    }
+}
+
+/// SessionStart 载荷前缀，用于区分协商请求与旧版 DeviceInfo 载荷。
+///
+/// 旧版载荷以 bincode 编码的字符串长度（u64）开头，不会与该前缀冲突。
+pub const SESSION_START_MAGIC: [u8; 4] = *b"VSPN";
+
+/// 协议 1.3 起的 SessionStart 载荷前缀（`DeviceCapabilities` 增加了码流列表）
+pub const SESSION_START_MAGIC_V1_3: [u8; 4] = *b"VSP3";
+
+/// 协议 1.3 之前的设备能力布局（不含码流列表）
+#[derive(Serialize, Deserialize)]
+struct DeviceCapabilitiesV1_0 {
+    max_resolution: String,
+    supported_formats: Vec<String>,
+    max_bitrate: u64,
+    supports_playback_control: bool,
+    supports_recording: bool,
+}
+
+impl From<DeviceCapabilitiesV1_0> for DeviceCapabilities {
+    fn from(legacy: DeviceCapabilitiesV1_0) -> Self {
+        Self {
+            max_resolution: legacy.max_resolution,
+            supported_formats: legacy.supported_formats,
+            max_bitrate: legacy.max_bitrate,
+            supports_playback_control: legacy.supports_playback_control,
+            supports_recording: legacy.supports_recording,
+            renditions: Vec::new(),
+        }
+    }
+}
+
+/// 协议 1.1/1.2 的会话开始请求布局
+#[derive(Deserialize)]
+struct SessionStartRequestV1_1 {
+    device_id: String,
+    device_name: String,
+    device_type: DeviceType,
+    capabilities: DeviceCapabilitiesV1_0,
+    protocol_versions: ProtocolVersionRange,
+    features: u32,
+}
+
+/// 协议 1.0 的设备信息布局
+#[derive(Deserialize)]
+struct DeviceInfoV1_0 {
+    device_id: String,
+    device_name: String,
+    device_type: DeviceType,
+    connection_status: ConnectionStatus,
+    connection_time: SystemTime,
+    last_heartbeat: SystemTime,
+    capabilities: DeviceCapabilitiesV1_0,
+}
+
+/// 解码后的 SessionStart 载荷
+#[derive(Debug, Clone)]
+pub enum SessionStartPayload {
+    /// 协议 1.1+：携带版本范围和特性标志
+    Negotiated(SessionStartRequest),
+    /// 协议 1.0：仅携带设备信息
+    Legacy(DeviceInfo),
+}
+
+impl SessionStartRequest {
+    /// 编码为 SessionStart 载荷（带协商前缀）
+    pub fn encode_payload(&self) -> Result<Vec<u8>> {
+        let body = bincode::serialize(self)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        let mut payload = Vec::with_capacity(SESSION_START_MAGIC_V1_3.len() + body.len());
+        payload.extend_from_slice(&SESSION_START_MAGIC_V1_3);
+        payload.extend_from_slice(&body);
+        Ok(payload)
+    }
+}
+
+/// 解码 SessionStart 载荷，兼容 1.3 之前的设备和未协商的旧版设备
+pub fn decode_session_start(payload: &[u8]) -> Result<SessionStartPayload> {
+    if let Some(body) = payload.strip_prefix(&SESSION_START_MAGIC_V1_3[..]) {
+        let request = bincode::deserialize::<SessionStartRequest>(body)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        return Ok(SessionStartPayload::Negotiated(request));
+    }
+
+    if let Some(body) = payload.strip_prefix(&SESSION_START_MAGIC[..]) {
+        let request = bincode::deserialize::<SessionStartRequestV1_1>(body)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        return Ok(SessionStartPayload::Negotiated(SessionStartRequest {
+            device_id: request.device_id,
+            device_name: request.device_name,
+            device_type: request.device_type,
+            capabilities: request.capabilities.into(),
+            protocol_versions: request.protocol_versions,
+            features: request.features,
+        }));
+    }
+
+    let device = bincode::deserialize::<DeviceInfoV1_0>(payload)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+    Ok(SessionStartPayload::Legacy(DeviceInfo {
+        device_id: device.device_id,
+        device_name: device.device_name,
+        device_type: device.device_type,
+        connection_status: device.connection_status,
+        connection_time: device.connection_time,
+        last_heartbeat: device.last_heartbeat,
+        capabilities: device.capabilities.into(),
+    }))
+}
+
+/// 协议 1.0/1.1 的视频分片布局（不含时间戳字段）
+#[derive(Serialize, Deserialize)]
+struct VideoSegmentV1_0<'a> {
+    stream_type: u8,
+    segment_id: uuid::Uuid,
+    session_id: uuid::Uuid,
+    timestamp: f64,
+    duration: f64,
+    frame_count: u32,
+    flags: u8,
+    data_length: u32,
+    #[serde(borrow)]
+    data: std::borrow::Cow<'a, [u8]>,
+}
+
+/// 协议 1.2 的视频分片布局（不含码流ID）
+#[derive(Serialize, Deserialize)]
+struct VideoSegmentV1_2<'a> {
+    stream_type: u8,
+    segment_id: uuid::Uuid,
+    session_id: uuid::Uuid,
+    timestamp: f64,
+    duration: f64,
+    frame_count: u32,
+    flags: u8,
+    data_length: u32,
+    #[serde(borrow)]
+    data: std::borrow::Cow<'a, [u8]>,
+    capture_time_us: u64,
+    send_time_us: u64,
+    sequence: u64,
+}
+
+/// 协议 1.3 的视频分片布局（不含编码格式）
+#[derive(Serialize, Deserialize)]
+struct VideoSegmentV1_3<'a> {
+    stream_type: u8,
+    segment_id: uuid::Uuid,
+    session_id: uuid::Uuid,
+    timestamp: f64,
+    duration: f64,
+    frame_count: u32,
+    flags: u8,
+    data_length: u32,
+    #[serde(borrow)]
+    data: std::borrow::Cow<'a, [u8]>,
+    capture_time_us: u64,
+    send_time_us: u64,
+    sequence: u64,
+    rendition_id: u8,
+}
+
+/// 协议 1.4 的视频分片布局（时间戳和时长为秒，不含解码时间戳）
+#[derive(Serialize, Deserialize)]
+struct VideoSegmentV1_4<'a> {
+    stream_type: u8,
+    segment_id: uuid::Uuid,
+    session_id: uuid::Uuid,
+    timestamp: f64,
+    duration: f64,
+    frame_count: u32,
+    flags: u8,
+    data_length: u32,
+    #[serde(borrow)]
+    data: std::borrow::Cow<'a, [u8]>,
+    capture_time_us: u64,
+    send_time_us: u64,
+    sequence: u64,
+    rendition_id: u8,
+    codec: VideoCodec,
+}
+
+/// 按协商版本编码视频分片
+pub fn encode_segment(version: ProtocolVersion, segment: &VideoSegment) -> Result<Vec<u8>> {
+    check_segment_version(version)?;
+
+    if version >= ProtocolVersion::V1_5 {
+        return bincode::serialize(segment)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()));
+    }
+
+    // 1.5 之前的分片时间戳为秒，只有显示时间戳
+    let timestamp = segment.pts_secs();
+    let duration = segment.duration_secs();
+
+    if version == ProtocolVersion::V1_4 {
+        let v1_4 = VideoSegmentV1_4 {
+            stream_type: segment.stream_type,
+            segment_id: segment.segment_id,
+            session_id: segment.session_id,
+            timestamp,
+            duration,
+            frame_count: segment.frame_count,
+            flags: segment.flags,
+            data_length: segment.data_length,
+            data: std::borrow::Cow::Borrowed(&segment.data),
+            capture_time_us: segment.capture_time_us,
+            send_time_us: segment.send_time_us,
+            sequence: segment.sequence,
+            rendition_id: segment.rendition_id,
+            codec: segment.codec,
+        };
+        return bincode::serialize(&v1_4)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()));
+    }
+
+    if version == ProtocolVersion::V1_3 {
+        let v1_3 = VideoSegmentV1_3 {
+            stream_type: segment.stream_type,
+            segment_id: segment.segment_id,
+            session_id: segment.session_id,
+            timestamp,
+            duration,
+            frame_count: segment.frame_count,
+            flags: segment.flags,
+            data_length: segment.data_length,
+            data: std::borrow::Cow::Borrowed(&segment.data),
+            capture_time_us: segment.capture_time_us,
+            send_time_us: segment.send_time_us,
+            sequence: segment.sequence,
+            rendition_id: segment.rendition_id,
+        };
+        return bincode::serialize(&v1_3)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()));
+    }
+
+    if version == ProtocolVersion::V1_2 {
+        let v1_2 = VideoSegmentV1_2 {
+            stream_type: segment.stream_type,
+            segment_id: segment.segment_id,
+            session_id: segment.session_id,
+            timestamp,
+            duration,
+            frame_count: segment.frame_count,
+            flags: segment.flags,
+            data_length: segment.data_length,
+            data: std::borrow::Cow::Borrowed(&segment.data),
+            capture_time_us: segment.capture_time_us,
+            send_time_us: segment.send_time_us,
+            sequence: segment.sequence,
+        };
+        return bincode::serialize(&v1_2)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()));
+    }
+
+    let legacy = VideoSegmentV1_0 {
+        stream_type: segment.stream_type,
+        segment_id: segment.segment_id,
+        session_id: segment.session_id,
+        timestamp,
+        duration,
+        frame_count: segment.frame_count,
+        flags: segment.flags,
+        data_length: segment.data_length,
+        data: std::borrow::Cow::Borrowed(&segment.data),
+    };
+    bincode::serialize(&legacy).map_err(|e| VideoStreamError::BincodeError(e.to_string()))
+}
+
+/// 按协商版本解码视频分片
+///
+/// 每次修改 `VideoSegment` 的线上布局都需要提升次版本号，
+/// 并在这里为上一个版本保留解码分支。
+pub fn decode_segment(version: ProtocolVersion, data: &[u8]) -> Result<VideoSegment> {
+    check_segment_version(version)?;
+
+    if version >= ProtocolVersion::V1_5 {
+        return bincode::deserialize::<VideoSegment>(data)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()));
+    }
+
+    // 1.5 之前的秒级时间戳换算为 90kHz 刻度，解码时间戳等于显示时间戳
+    if version == ProtocolVersion::V1_4 {
+        let v1_4 = bincode::deserialize::<VideoSegmentV1_4>(data)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        let pts = VideoSegment::ticks(v1_4.timestamp);
+        return Ok(VideoSegment {
+            stream_type: v1_4.stream_type,
+            segment_id: v1_4.segment_id,
+            session_id: v1_4.session_id,
+            pts,
+            dts: pts,
+            timescale: VideoSegment::TIMESCALE,
+            duration: VideoSegment::ticks(v1_4.duration) as u32,
+            frame_count: v1_4.frame_count,
+            flags: v1_4.flags,
+            data_length: v1_4.data_length,
+            data: v1_4.data.into_owned(),
+            capture_time_us: v1_4.capture_time_us,
+            send_time_us: v1_4.send_time_us,
+            sequence: v1_4.sequence,
+            rendition_id: v1_4.rendition_id,
+            codec: v1_4.codec,
+            receive_time: None,
+        });
+    }
+
+    // 1.3 之前的设备只发送 H.264
+    if version == ProtocolVersion::V1_3 {
+        let v1_3 = bincode::deserialize::<VideoSegmentV1_3>(data)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        let pts = VideoSegment::ticks(v1_3.timestamp);
+        return Ok(VideoSegment {
+            stream_type: v1_3.stream_type,
+            segment_id: v1_3.segment_id,
+            session_id: v1_3.session_id,
+            pts,
+            dts: pts,
+            timescale: VideoSegment::TIMESCALE,
+            duration: VideoSegment::ticks(v1_3.duration) as u32,
+            frame_count: v1_3.frame_count,
+            flags: v1_3.flags,
+            data_length: v1_3.data_length,
+            data: v1_3.data.into_owned(),
+            capture_time_us: v1_3.capture_time_us,
+            send_time_us: v1_3.send_time_us,
+            sequence: v1_3.sequence,
+            rendition_id: v1_3.rendition_id,
+            codec: VideoCodec::H264,
+            receive_time: None,
+        });
+    }
+
+    // 1.2 的分片不带码流ID，视为默认码流
+    if version == ProtocolVersion::V1_2 {
+        let v1_2 = bincode::deserialize::<VideoSegmentV1_2>(data)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        let pts = VideoSegment::ticks(v1_2.timestamp);
+        return Ok(VideoSegment {
+            stream_type: v1_2.stream_type,
+            segment_id: v1_2.segment_id,
+            session_id: v1_2.session_id,
+            pts,
+            dts: pts,
+            timescale: VideoSegment::TIMESCALE,
+            duration: VideoSegment::ticks(v1_2.duration) as u32,
+            frame_count: v1_2.frame_count,
+            flags: v1_2.flags,
+            data_length: v1_2.data_length,
+            data: v1_2.data.into_owned(),
+            capture_time_us: v1_2.capture_time_us,
+            send_time_us: v1_2.send_time_us,
+            sequence: v1_2.sequence,
+            rendition_id: 0,
+            codec: VideoCodec::H264,
+            receive_time: None,
+        });
+    }
+
+    // 1.0 与 1.1 的分片不带时间戳，缺失字段置零
+    let legacy = bincode::deserialize::<VideoSegmentV1_0>(data)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+    let pts = VideoSegment::ticks(legacy.timestamp);
+    Ok(VideoSegment {
+        stream_type: legacy.stream_type,
+        segment_id: legacy.segment_id,
+        session_id: legacy.session_id,
+        pts,
+        dts: pts,
+        timescale: VideoSegment::TIMESCALE,
+        duration: VideoSegment::ticks(legacy.duration) as u32,
+        frame_count: legacy.frame_count,
+        flags: legacy.flags,
+        data_length: legacy.data_length,
+        data: legacy.data.into_owned(),
+        capture_time_us: 0,
+        send_time_us: 0,
+        sequence: 0,
+        rendition_id: 0,
+        codec: VideoCodec::H264,
+        receive_time: None,
+    })
+}
+
+/// 会话媒体流前缀：单向流以此开头时，后续为连续的长度前缀分片帧，
+/// 否则整条流是一个旧版分片或协议消息。
+///
+/// 旧版流以 bincode 编码的结构体开头，不会与该前缀冲突。
+pub const MEDIA_STREAM_MAGIC: [u8; 4] = *b"VSMS";
+
+/// 关键帧媒体流前缀：帧格式与会话媒体流相同，只承载视频关键帧和解码配置
+pub const MEDIA_KEYFRAME_STREAM_MAGIC: [u8; 4] = *b"VSMK";
+
+/// 媒体流单帧的最大长度
+pub const MAX_MEDIA_FRAME_SIZE: usize = 10 * 1024 * 1024;
+
+/// 为按协商版本编码的分片加上帧头：u32 小端长度 + 分片
+pub fn encode_media_frame(body: &[u8]) -> Result<Vec<u8>> {
+    if body.len() > MAX_MEDIA_FRAME_SIZE {
+        return Err(VideoStreamError::ProtocolError(format!(
+            "Media frame too large: {} bytes",
+            body.len()
+        )));
+    }
+    let mut frame = Vec::with_capacity(4 + body.len());
+    frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    frame.extend_from_slice(body);
+    Ok(frame)
+}
+
+/// 解析媒体流帧头，返回分片长度
+pub fn media_frame_length(header: [u8; 4]) -> Result<usize> {
+    let length = u32::from_le_bytes(header) as usize;
+    if length > MAX_MEDIA_FRAME_SIZE {
+        return Err(VideoStreamError::ProtocolError(format!(
+            "Media frame too large: {} bytes",
+            length
+        )));
+    }
+    Ok(length)
+}
+
+fn check_segment_version(version: ProtocolVersion) -> Result<()> {
+    if version.major != ProtocolVersion::CURRENT.major
+        || version < ProtocolVersion::MIN_SUPPORTED
+        || version > ProtocolVersion::CURRENT
+    {
+        return Err(VideoStreamError::ProtocolError(format!(
+            "Unsupported protocol version: {}",
+            version
+        )));
+    }
+    Ok(())
+}
+
+/// 时钟同步请求（平台 → 设备）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockSyncRequest {
+    /// 平台发送时间 t0（平台时钟，Unix 微秒）
+    pub t0_us: u64,
+}
+
+/// 时钟同步响应（设备 → 平台）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockSyncResponse {
+    /// 原样回传的平台发送时间 t0
+    pub t0_us: u64,
+    /// 设备接收时间 t1（设备时钟）
+    pub t1_us: u64,
+    /// 设备发送时间 t2（设备时钟）
+    pub t2_us: u64,
 }
 
 /// 文件列表请求
@@ -22,6 +633,83 @@ pub struct FileListResponse {
     pub files: Vec<RecordingInfo>,
 }
 
+/// 协议 1.4 之前的录像信息布局（不含编码格式）
+#[derive(Serialize, Deserialize)]
+struct RecordingInfoV1_0 {
+    file_id: String,
+    device_id: String,
+    file_name: String,
+    file_path: String,
+    file_size: u64,
+    duration: f64,
+    format: String,
+    resolution: String,
+    bitrate: u64,
+    frame_rate: f64,
+    created_time: SystemTime,
+    modified_time: SystemTime,
+}
+
+impl FileListResponse {
+    /// 按协商版本编码文件列表
+    pub fn encode(&self, version: ProtocolVersion) -> Result<Vec<u8>> {
+        if version >= ProtocolVersion::V1_4 {
+            return bincode::serialize(self)
+                .map_err(|e| VideoStreamError::BincodeError(e.to_string()));
+        }
+
+        let legacy: Vec<RecordingInfoV1_0> = self
+            .files
+            .iter()
+            .map(|info| RecordingInfoV1_0 {
+                file_id: info.file_id.clone(),
+                device_id: info.device_id.clone(),
+                file_name: info.file_name.clone(),
+                file_path: info.file_path.clone(),
+                file_size: info.file_size,
+                duration: info.duration,
+                format: info.format.clone(),
+                resolution: info.resolution.clone(),
+                bitrate: info.bitrate,
+                frame_rate: info.frame_rate,
+                created_time: info.created_time,
+                modified_time: info.modified_time,
+            })
+            .collect();
+        bincode::serialize(&legacy).map_err(|e| VideoStreamError::BincodeError(e.to_string()))
+    }
+
+    /// 按协商版本解码文件列表，1.4 之前的录像视为 H.264
+    pub fn decode(version: ProtocolVersion, payload: &[u8]) -> Result<Self> {
+        if version >= ProtocolVersion::V1_4 {
+            return bincode::deserialize(payload)
+                .map_err(|e| VideoStreamError::BincodeError(e.to_string()));
+        }
+
+        let legacy = bincode::deserialize::<Vec<RecordingInfoV1_0>>(payload)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        let files = legacy
+            .into_iter()
+            .map(|info| RecordingInfo {
+                file_id: info.file_id,
+                device_id: info.device_id,
+                file_name: info.file_name,
+                file_path: info.file_path,
+                file_size: info.file_size,
+                duration: info.duration,
+                format: info.format,
+                codec: VideoCodec::H264,
+                resolution: info.resolution,
+                bitrate: info.bitrate,
+                frame_rate: info.frame_rate,
+                created_time: info.created_time,
+                modified_time: info.modified_time,
+            })
+            .collect();
+        Ok(Self { files })
+    }
+}
+
 /// 文件请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileRequest {
@@ -29,6 +717,8 @@ pub struct FileRequest {
     pub priority: u8,
     pub seek_position: Option<f64>,
     pub playback_rate: f64,
+    /// 精确定位：从 `seek_position` 之前的关键帧开始发送，目标帧之前的帧标记为 `DECODE_ONLY`
+    pub accurate_seek: bool,
 }
 
 /// 定位请求
@@ -81,6 +771,16 @@ pub struct StartLiveStreamRequest {
     pub target_latency_ms: u32,
     pub target_fps: u32,
     pub target_bitrate: usize,
+    /// 指定码流（协议 1.3 起；追加在末尾，旧设备解码时忽略）
+    pub rendition_id: Option<u8>,
+}
+
+/// 切换直通播放码流请求
+///
+/// 设备应在新码流的下一个关键帧处切换，切换后的第一个分片必须是关键帧。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwitchRenditionRequest {
+    pub rendition_id: u8,
 }
 
 /// 停止直通播放请求
@@ -146,3 +846,302 @@ pub struct GetKeyframeIndexResponse {
     pub success: bool,
     pub error_message: Option<String>,
 }
+
+/// 文件上传分块的建议大小
+pub const FILE_UPLOAD_CHUNK_SIZE: usize = 256 * 1024;
+
+/// 文件上传请求（设备 → 平台）
+///
+/// 平台按设备和 `sha256` 识别同一文件，断线重连后重新发起请求即可从已确认的偏移续传。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileUploadOffer {
+    pub file_name: String,
+    pub total_size: u64,
+    /// 整个文件的 SHA-256（小写十六进制）
+    pub sha256: String,
+}
+
+/// 文件上传分块（设备 → 平台）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileUploadChunk {
+    pub upload_id: uuid::Uuid,
+    /// 分块在文件中的起始偏移
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+/// 文件上传状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FileUploadStatus {
+    /// 继续从 `acked_offset` 发送
+    InProgress,
+    /// 文件已校验并存入平台存储
+    Complete,
+    /// 校验失败，已接收的数据被丢弃，需要从头上传
+    ChecksumMismatch,
+    /// 请求无效或上传不存在，需要重新发起上传请求
+    Rejected,
+}
+
+/// 文件上传确认（平台 → 设备），同时用于回复上传请求和分块
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileUploadAck {
+    pub upload_id: uuid::Uuid,
+    /// 平台已落盘的字节数，设备从这里继续发送
+    pub acked_offset: u64,
+    pub status: FileUploadStatus,
+    pub error_message: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device_info() -> DeviceInfo {
+        DeviceInfo {
+            device_id: "device_001".to_string(),
+            device_name: "Test Device".to_string(),
+            device_type: DeviceType::Simulator,
+            connection_status: ConnectionStatus::Online,
+            connection_time: SystemTime::now(),
+            last_heartbeat: SystemTime::now(),
+            capabilities: DeviceCapabilities {
+                max_resolution: "1920x1080".to_string(),
+                supported_formats: vec!["h264".to_string()],
+                max_bitrate: 10_000_000,
+                supports_playback_control: true,
+                supports_recording: false,
+                renditions: vec![RenditionInfo {
+                    id: 1,
+                    name: "sub".to_string(),
+                    width: 640,
+                    height: 360,
+                    bitrate: 800_000,
+                    fps: 30,
+                }],
+            },
+        }
+    }
+
+    /// 按协议 1.3 之前的布局编码设备能力
+    fn legacy_capabilities(info: &DeviceInfo) -> DeviceCapabilitiesV1_0 {
+        DeviceCapabilitiesV1_0 {
+            max_resolution: info.capabilities.max_resolution.clone(),
+            supported_formats: info.capabilities.supported_formats.clone(),
+            max_bitrate: info.capabilities.max_bitrate,
+            supports_playback_control: info.capabilities.supports_playback_control,
+            supports_recording: info.capabilities.supports_recording,
+        }
+    }
+
+    #[test]
+    fn test_version_range_negotiation() {
+        let platform = ProtocolVersionRange::supported();
+        let newer = ProtocolVersionRange::new(ProtocolVersion::V1_0, ProtocolVersion::new(1, 5));
+        let legacy = ProtocolVersionRange::new(ProtocolVersion::V1_0, ProtocolVersion::V1_0);
+        let future = ProtocolVersionRange::new(ProtocolVersion::new(2, 0), ProtocolVersion::new(2, 1));
+
+        assert_eq!(platform.negotiate(&newer), Some(ProtocolVersion::CURRENT));
+        assert_eq!(platform.negotiate(&legacy), Some(ProtocolVersion::V1_0));
+        assert_eq!(platform.negotiate(&future), None);
+    }
+
+    #[test]
+    fn test_decode_negotiated_session_start() {
+        let info = device_info();
+        let request = SessionStartRequest {
+            device_id: info.device_id.clone(),
+            device_name: info.device_name.clone(),
+            device_type: info.device_type.clone(),
+            capabilities: info.capabilities.clone(),
+            protocol_versions: ProtocolVersionRange::supported(),
+            features: FeatureFlags::ALL,
+        };
+
+        let payload = request.encode_payload().unwrap();
+        match decode_session_start(&payload).unwrap() {
+            SessionStartPayload::Negotiated(decoded) => {
+                assert_eq!(decoded.device_id, "device_001");
+                assert_eq!(decoded.protocol_versions, ProtocolVersionRange::supported());
+                assert_eq!(decoded.features, FeatureFlags::ALL);
+                assert_eq!(decoded.capabilities.rendition(1).unwrap().height, 360);
+            }
+            SessionStartPayload::Legacy(_) => panic!("expected negotiated payload"),
+        }
+    }
+
+    #[test]
+    fn test_decode_v1_1_session_start() {
+        #[derive(Serialize)]
+        struct RequestV1_1 {
+            device_id: String,
+            device_name: String,
+            device_type: DeviceType,
+            capabilities: DeviceCapabilitiesV1_0,
+            protocol_versions: ProtocolVersionRange,
+            features: u32,
+        }
+
+        let info = device_info();
+        let request = RequestV1_1 {
+            device_id: info.device_id.clone(),
+            device_name: info.device_name.clone(),
+            device_type: info.device_type.clone(),
+            capabilities: legacy_capabilities(&info),
+            protocol_versions: ProtocolVersionRange::new(ProtocolVersion::V1_0, ProtocolVersion::V1_2),
+            features: FeatureFlags::LIVE_STREAM,
+        };
+        let mut payload = SESSION_START_MAGIC.to_vec();
+        payload.extend(bincode::serialize(&request).unwrap());
+
+        match decode_session_start(&payload).unwrap() {
+            SessionStartPayload::Negotiated(decoded) => {
+                assert_eq!(decoded.device_id, "device_001");
+                assert_eq!(decoded.protocol_versions.max, ProtocolVersion::V1_2);
+                assert!(decoded.capabilities.renditions.is_empty());
+            }
+            SessionStartPayload::Legacy(_) => panic!("expected negotiated payload"),
+        }
+    }
+
+    #[test]
+    fn test_decode_legacy_session_start() {
+        #[derive(Serialize)]
+        struct DeviceInfoLegacy {
+            device_id: String,
+            device_name: String,
+            device_type: DeviceType,
+            connection_status: ConnectionStatus,
+            connection_time: SystemTime,
+            last_heartbeat: SystemTime,
+            capabilities: DeviceCapabilitiesV1_0,
+        }
+
+        let info = device_info();
+        let legacy = DeviceInfoLegacy {
+            device_id: info.device_id.clone(),
+            device_name: info.device_name.clone(),
+            device_type: info.device_type.clone(),
+            connection_status: info.connection_status.clone(),
+            connection_time: info.connection_time,
+            last_heartbeat: info.last_heartbeat,
+            capabilities: legacy_capabilities(&info),
+        };
+        let payload = bincode::serialize(&legacy).unwrap();
+        match decode_session_start(&payload).unwrap() {
+            SessionStartPayload::Legacy(device) => {
+                assert_eq!(device.device_id, "device_001");
+                assert!(device.capabilities.renditions.is_empty());
+            }
+            SessionStartPayload::Negotiated(_) => panic!("expected legacy payload"),
+        }
+    }
+
+    #[test]
+    fn test_decode_segment_rejects_unknown_major() {
+        let segment = VideoSegment::new(vec![0u8; 16], 90_000, 3_000, true);
+        let data = bincode::serialize(&segment).unwrap();
+
+        assert!(decode_segment(ProtocolVersion::CURRENT, &data).is_ok());
+        assert!(decode_segment(ProtocolVersion::new(2, 0), &data).is_err());
+        assert!(encode_segment(ProtocolVersion::new(2, 0), &segment).is_err());
+    }
+
+    #[test]
+    fn test_segment_roundtrip_per_version() {
+        let mut segment = VideoSegment::new(vec![7u8; 32], 225_000, 3_750, true);
+        segment.dts = 221_250;
+        segment.send_time_us = segment.capture_time_us + 1_000;
+        segment.sequence = 42;
+        segment.rendition_id = 1;
+        segment.codec = VideoCodec::H265;
+
+        let current = encode_segment(ProtocolVersion::CURRENT, &segment).unwrap();
+        let decoded = decode_segment(ProtocolVersion::CURRENT, &current).unwrap();
+        assert_eq!(decoded.sequence, 42);
+        assert_eq!(decoded.send_time_us, segment.send_time_us);
+        assert_eq!(decoded.capture_time_us, segment.capture_time_us);
+        assert_eq!(decoded.rendition_id, 1);
+        assert_eq!(decoded.codec, VideoCodec::H265);
+        assert_eq!((decoded.pts, decoded.dts, decoded.duration), (225_000, 221_250, 3_750));
+
+        // 1.4 布局的时间戳为秒，只保留显示时间戳
+        let v1_4 = encode_segment(ProtocolVersion::V1_4, &segment).unwrap();
+        let decoded = decode_segment(ProtocolVersion::V1_4, &v1_4).unwrap();
+        assert_eq!(decoded.codec, VideoCodec::H265);
+        assert_eq!((decoded.pts, decoded.dts, decoded.duration), (225_000, 225_000, 3_750));
+        assert_eq!(decoded.timescale, VideoSegment::TIMESCALE);
+
+        // 1.3 布局携带码流ID，不携带编码格式
+        let v1_3 = encode_segment(ProtocolVersion::V1_3, &segment).unwrap();
+        assert!(v1_3.len() < v1_4.len());
+        let decoded = decode_segment(ProtocolVersion::V1_3, &v1_3).unwrap();
+        assert_eq!(decoded.rendition_id, 1);
+        assert_eq!(decoded.codec, VideoCodec::H264);
+
+        // 1.2 布局保留时间戳，不携带码流ID
+        let v1_2 = encode_segment(ProtocolVersion::V1_2, &segment).unwrap();
+        assert!(v1_2.len() < v1_3.len());
+        let decoded = decode_segment(ProtocolVersion::V1_2, &v1_2).unwrap();
+        assert_eq!(decoded.sequence, 42);
+        assert_eq!(decoded.send_time_us, segment.send_time_us);
+        assert_eq!(decoded.rendition_id, 0);
+
+        // 旧版布局不携带时间戳，但数据必须完整保留
+        let legacy = encode_segment(ProtocolVersion::V1_1, &segment).unwrap();
+        assert!(legacy.len() < v1_2.len());
+        let decoded = decode_segment(ProtocolVersion::V1_0, &legacy).unwrap();
+        assert_eq!(decoded.segment_id, segment.segment_id);
+        assert_eq!(decoded.data, segment.data);
+        assert!(decoded.is_keyframe());
+        assert_eq!(decoded.sequence, 0);
+        assert_eq!(decoded.send_time_us, 0);
+    }
+
+    #[test]
+    fn test_media_frame_roundtrip() {
+        let mut segment = VideoSegment::new(vec![5u8; 64], 3_000, 3_000, false);
+        segment.sequence = 7;
+
+        let body = encode_segment(ProtocolVersion::CURRENT, &segment).unwrap();
+        let frame = encode_media_frame(&body).unwrap();
+        let length = media_frame_length(frame[..4].try_into().unwrap()).unwrap();
+        assert_eq!(length, frame.len() - 4);
+        let decoded = decode_segment(ProtocolVersion::CURRENT, &frame[4..]).unwrap();
+        assert_eq!(decoded.sequence, 7);
+        assert_eq!(decoded.data, segment.data);
+
+        assert!(media_frame_length((MAX_MEDIA_FRAME_SIZE as u32 + 1).to_le_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_file_list_per_version() {
+        let info = RecordingInfo {
+            file_id: "device_001_front.h265".to_string(),
+            device_id: "device_001".to_string(),
+            file_name: "front.h265".to_string(),
+            file_path: "/videos/front.h265".to_string(),
+            file_size: 1024,
+            duration: 10.0,
+            format: "h265".to_string(),
+            codec: VideoCodec::H265,
+            resolution: "1920x1080".to_string(),
+            bitrate: 4_000_000,
+            frame_rate: 25.0,
+            created_time: SystemTime::now(),
+            modified_time: SystemTime::now(),
+        };
+        let response = FileListResponse { files: vec![info] };
+
+        let current = response.encode(ProtocolVersion::CURRENT).unwrap();
+        let decoded = FileListResponse::decode(ProtocolVersion::CURRENT, &current).unwrap();
+        assert_eq!(decoded.files[0].codec, VideoCodec::H265);
+
+        // 1.3 设备的录像信息不带编码格式
+        let legacy = response.encode(ProtocolVersion::V1_3).unwrap();
+        let decoded = FileListResponse::decode(ProtocolVersion::V1_3, &legacy).unwrap();
+        assert_eq!(decoded.files[0].file_name, "front.h265");
+        assert_eq!(decoded.files[0].codec, VideoCodec::H264);
+        assert!(FileListResponse::decode(ProtocolVersion::CURRENT, &legacy).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+// 快进/快退（trick-play）调度
+//
+// 超过常规倍速或倒放时只发送关键帧：按关键帧时间表挑选要发送的关键帧，
+// 并把输出时间戳重写为连续递增的时间线，播放器按正常速度渲染即可看到均匀的扫描画面。
+//
+// 平台回放（PlaybackSource）和设备回放共用此调度逻辑。
+
+use crate::h264::scan_access_units;
+use crate::KeyframeEntry;
+
+/// 常规倍速上限，超过后只发关键帧
+pub const MAX_NORMAL_RATE: f64 = 4.0;
+/// 最小倍速（绝对值）
+pub const MIN_RATE: f64 = 0.25;
+/// 快进/快退最大倍速（绝对值）
+pub const MAX_TRICK_PLAY_RATE: f64 = 32.0;
+/// 每秒最多输出的关键帧数，关键帧密集时跳过部分关键帧
+pub const MAX_TRICK_PLAY_FPS: f64 = 8.0;
+
+/// 倍速是否在支持范围内（负数为倒放）
+pub fn is_valid_rate(rate: f64) -> bool {
+    (MIN_RATE..=MAX_TRICK_PLAY_RATE).contains(&rate.abs())
+}
+
+/// 是否需要以只发关键帧的方式播放
+pub fn is_trick_play_rate(rate: f64) -> bool {
+    !(0.0..=MAX_NORMAL_RATE).contains(&rate)
+}
+
+/// 调度出的一个输出关键帧
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrickPlayFrame {
+    /// 关键帧在时间表中的下标
+    pub index: usize,
+    /// 关键帧的原始媒体时间（秒）
+    pub media_time: f64,
+    /// 重写后的输出时间戳（秒）
+    pub timestamp: f64,
+    /// 输出显示时长（秒），即到下一个输出关键帧的间隔
+    pub duration: f64,
+}
+
+/// 关键帧扫描调度器
+#[derive(Debug, Clone)]
+pub struct TrickPlayScheduler {
+    /// 关键帧媒体时间（升序）
+    keyframe_times: Vec<f64>,
+    rate: f64,
+    /// 下一个要输出的关键帧
+    next: Option<usize>,
+    /// 最近输出的关键帧
+    last: Option<usize>,
+    output_timestamp: f64,
+}
+
+impl TrickPlayScheduler {
+    /// 创建调度器，从 `position` 处（不晚于该位置的最近关键帧）开始扫描
+    ///
+    /// `output_start` 为第一个输出关键帧的时间戳，通常接在常规播放的最后一个分片之后。
+    pub fn new(keyframe_times: Vec<f64>, rate: f64, position: f64, output_start: f64) -> Self {
+        let mut scheduler = Self {
+            keyframe_times,
+            rate,
+            next: None,
+            last: None,
+            output_timestamp: output_start,
+        };
+        scheduler.seek(position);
+        scheduler
+    }
+
+    /// 当前倍速
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// 修改倍速（包括方向），从当前位置继续扫描
+    pub fn set_rate(&mut self, rate: f64) {
+        // 换向时从最近输出的关键帧重新开始
+        if (rate > 0.0) != (self.rate > 0.0) && self.last.is_some() {
+            self.next = self.last;
+        }
+        self.rate = rate;
+    }
+
+    /// 跳到指定媒体时间
+    pub fn seek(&mut self, position: f64) {
+        self.next = match self.keyframe_times.iter().rposition(|&t| t <= position) {
+            Some(index) => Some(index),
+            None if self.keyframe_times.is_empty() => None,
+            None => Some(0),
+        };
+    }
+
+    /// 当前媒体位置（秒）
+    pub fn position(&self) -> f64 {
+        self.last
+            .or(self.next)
+            .map_or(0.0, |index| self.keyframe_times[index])
+    }
+
+    /// 最近输出的关键帧下标（尚未输出时为起始关键帧）
+    pub fn current_index(&self) -> Option<usize> {
+        self.last.or(self.next)
+    }
+
+    /// 下一个输出关键帧，扫描到文件首/尾时返回 None
+    pub fn next_frame(&mut self) -> Option<TrickPlayFrame> {
+        let index = self.next?;
+        let media_time = self.keyframe_times[index];
+        let speed = self.rate.abs();
+        // 两个输出关键帧之间至少间隔的媒体时长
+        let min_step = speed / MAX_TRICK_PLAY_FPS;
+
+        let following = if self.rate > 0.0 {
+            (index + 1..self.keyframe_times.len())
+                .find(|&i| self.keyframe_times[i] - media_time >= min_step)
+                .or_else(|| (index + 1 < self.keyframe_times.len()).then_some(self.keyframe_times.len() - 1))
+        } else {
+            (0..index)
+                .rev()
+                .find(|&i| media_time - self.keyframe_times[i] >= min_step)
+                .or_else(|| (index > 0).then_some(0))
+        };
+
+        let duration = following.map_or(1.0 / MAX_TRICK_PLAY_FPS, |i| {
+            (self.keyframe_times[i] - media_time).abs() / speed
+        });
+        let frame = TrickPlayFrame {
+            index,
+            media_time,
+            timestamp: self.output_timestamp,
+            duration,
+        };
+
+        self.output_timestamp += duration;
+        self.last = Some(index);
+        self.next = following;
+        Some(frame)
+    }
+}
+
+/// 扫描 H.264 Annex B 码流中的关键帧
+///
+/// 每个条目覆盖一个完整的 IDR 访问单元（含前导的 SPS/PPS/SEI），时间按帧序号和帧率推算。
+pub fn scan_h264_keyframes(data: &[u8], fps: f64) -> Vec<KeyframeEntry> {
+    scan_access_units(data, fps)
+        .into_iter()
+        .filter(|unit| unit.is_keyframe)
+        .map(|unit| KeyframeEntry {
+            timestamp: unit.timestamp,
+            file_offset: unit.offset,
+            frame_size: unit.size,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每 2 秒一个关键帧，共 60 秒
+    fn keyframe_times() -> Vec<f64> {
+        (0..30).map(|i| i as f64 * 2.0).collect()
+    }
+
+    #[test]
+    fn test_rate_classification() {
+        assert!(is_valid_rate(1.0));
+        assert!(is_valid_rate(32.0));
+        assert!(is_valid_rate(-8.0));
+        assert!(!is_valid_rate(0.1));
+        assert!(!is_valid_rate(64.0));
+        assert!(!is_valid_rate(0.0));
+
+        assert!(!is_trick_play_rate(4.0));
+        assert!(is_trick_play_rate(8.0));
+        assert!(is_trick_play_rate(-1.0));
+    }
+
+    #[test]
+    fn test_fast_forward_rewrites_timestamps() {
+        // 8x：关键帧间隔 2 秒，每个关键帧显示 0.25 秒
+        let mut scheduler = TrickPlayScheduler::new(keyframe_times(), 8.0, 5.0, 10.0);
+        let frames: Vec<TrickPlayFrame> = std::iter::from_fn(|| scheduler.next_frame()).take(3).collect();
+
+        assert_eq!(frames[0].media_time, 4.0);
+        assert_eq!(frames[1].media_time, 6.0);
+        assert_eq!(frames[0].timestamp, 10.0);
+        assert_eq!(frames[1].timestamp, 10.25);
+        assert_eq!(frames[2].timestamp, 10.5);
+        assert!(frames.iter().all(|f| (f.duration - 0.25).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_high_rate_skips_dense_keyframes() {
+        // 32x：每个输出关键帧至少推进 4 秒媒体时间
+        let mut scheduler = TrickPlayScheduler::new(keyframe_times(), 32.0, 0.0, 0.0);
+        let times: Vec<f64> = std::iter::from_fn(|| scheduler.next_frame())
+            .map(|f| f.media_time)
+            .collect();
+        assert_eq!(times[..3], [0.0, 4.0, 8.0]);
+        assert_eq!(*times.last().unwrap(), 58.0);
+    }
+
+    #[test]
+    fn test_reverse_scan_ends_at_start() {
+        let mut scheduler = TrickPlayScheduler::new(keyframe_times(), -4.0, 7.0, 0.0);
+        let frames: Vec<TrickPlayFrame> = std::iter::from_fn(|| scheduler.next_frame()).collect();
+
+        let times: Vec<f64> = frames.iter().map(|f| f.media_time).collect();
+        assert_eq!(times, vec![6.0, 4.0, 2.0, 0.0]);
+        // 输出时间戳仍然递增
+        assert!(frames.windows(2).all(|w| w[1].timestamp > w[0].timestamp));
+        assert_eq!(scheduler.position(), 0.0);
+    }
+
+    #[test]
+    fn test_direction_change_continues_from_position() {
+        let mut scheduler = TrickPlayScheduler::new(keyframe_times(), 8.0, 10.0, 0.0);
+        scheduler.next_frame();
+        scheduler.next_frame();
+        assert_eq!(scheduler.position(), 12.0);
+
+        scheduler.set_rate(-8.0);
+        assert_eq!(scheduler.next_frame().unwrap().media_time, 12.0);
+        assert_eq!(scheduler.next_frame().unwrap().media_time, 10.0);
+    }
+
+    /// 生成 H.264 码流：`gops` 个 GOP，每个 GOP 30 帧（30fps 下为 1 秒）
+    fn h264_stream(gops: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        for gop in 0..gops {
+            data.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, gop as u8, 0xaa]);
+            for _ in 1..30 {
+                data.extend_from_slice(&[0, 0, 0, 1, 0x41, 0x9a, 0x55]);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_scan_h264_keyframes() {
+        let data = h264_stream(3);
+        let keyframes = scan_h264_keyframes(&data, 30.0);
+
+        assert_eq!(keyframes.len(), 3);
+        let timestamps: Vec<f64> = keyframes.iter().map(|k| k.timestamp).collect();
+        assert_eq!(timestamps, vec![0.0, 1.0, 2.0]);
+
+        // 每个条目从 SPS 开始，到下一帧之前结束
+        for (gop, keyframe) in keyframes.iter().enumerate() {
+            let start = keyframe.file_offset as usize;
+            let unit = &data[start..start + keyframe.frame_size as usize];
+            assert_eq!(unit[4], 0x67);
+            assert_eq!(unit.len(), 24);
+            assert_eq!(unit[22], gop as u8);
+        }
+    }
+}
@@ -0,0 +1,689 @@
+// MPEG-TS 解复用与封装
+//
+// 解析 PAT/PMT 找到节目中的 H.264/H.265 视频和 AAC（ADTS）音频，按 PUSI 重组 PES 并取出 PTS/DTS，
+// 节目时钟取自 PCR。视频按一个 PES 一个访问单元转换为 Annex B 访问单元表，关键帧取自适配域的
+// 随机访问指示；音频 PES 拆分为 ADTS 帧。`TsMuxer` 做反向封装，供不支持 fMP4 的 HLS 客户端使用。
+
+use crate::aac;
+use crate::h264::{self, AccessUnit};
+use crate::{hevc, VideoCodec};
+use std::collections::HashMap;
+
+/// TS 包长度
+pub const PACKET_SIZE: usize = 188;
+/// TS 包同步字节
+pub const SYNC_BYTE: u8 = 0x47;
+/// PTS/DTS/PCR 基准时钟（90kHz）
+pub const TIMESCALE: u32 = 90_000;
+
+/// 流类型：AAC（ADTS）
+pub const STREAM_TYPE_AAC: u8 = 0x0F;
+/// 流类型：H.264
+pub const STREAM_TYPE_H264: u8 = 0x1B;
+/// 流类型：H.265
+pub const STREAM_TYPE_H265: u8 = 0x24;
+
+/// PAT 的 PID
+const PID_PAT: u16 = 0x0000;
+/// PTS/DTS 为 33 位，超过后回绕
+const TIMESTAMP_WRAP: u64 = 1 << 33;
+
+/// 数据是否为 188 字节包的 MPEG-TS（检查前三个包的同步字节）
+pub fn is_mpeg_ts(data: &[u8]) -> bool {
+    data.len() >= PACKET_SIZE
+        && data
+            .chunks_exact(PACKET_SIZE)
+            .take(3)
+            .all(|packet| packet[0] == SYNC_BYTE)
+}
+
+/// 重组后的 PES 包
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PesPacket {
+    /// 显示时间（90kHz，已展开 33 位回绕）
+    pub pts: Option<u64>,
+    /// 解码时间（90kHz，已展开 33 位回绕），与显示时间相同时省略
+    pub dts: Option<u64>,
+    /// 第一个 TS 包的适配域设置了随机访问指示
+    pub random_access: bool,
+    /// PES 负载
+    pub data: Vec<u8>,
+}
+
+/// PMT 中的一路基本流
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PesStream {
+    pub pid: u16,
+    pub stream_type: u8,
+    /// 按到达顺序排列的 PES 包
+    pub packets: Vec<PesPacket>,
+}
+
+impl PesStream {
+    /// 视频编码格式，非视频流返回 None
+    pub fn video_codec(&self) -> Option<VideoCodec> {
+        match self.stream_type {
+            STREAM_TYPE_H264 => Some(VideoCodec::H264),
+            STREAM_TYPE_H265 => Some(VideoCodec::H265),
+            _ => None,
+        }
+    }
+}
+
+/// 解复用后的单节目传输流
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransportStream {
+    /// 携带 PCR 的 PID
+    pub pcr_pid: Option<u16>,
+    /// 第一个 PCR（90kHz 基准部分）
+    pub first_pcr: Option<u64>,
+    /// PMT 中列出的基本流
+    pub streams: Vec<PesStream>,
+}
+
+/// 从传输流中取出的一个 AAC 帧
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFrame {
+    /// 显示时间（秒），与视频访问单元使用同一时间原点
+    pub timestamp: f64,
+    /// 含帧头的 ADTS 帧
+    pub data: Vec<u8>,
+}
+
+/// 解析传输流，找不到 PAT/PMT 时返回 None
+///
+/// 只处理第一个节目；同步字节错误的包和不在 PMT 中的 PID 直接跳过。
+pub fn parse(data: &[u8]) -> Option<TransportStream> {
+    if !is_mpeg_ts(data) {
+        return None;
+    }
+
+    let mut ts = TransportStream::default();
+    let mut pmt_pid = None;
+    // 正在重组的 PES：PID -> (随机访问指示, 已收到的数据)
+    let mut pending: HashMap<u16, (bool, Vec<u8>)> = HashMap::new();
+
+    for packet in data.chunks_exact(PACKET_SIZE) {
+        if packet[0] != SYNC_BYTE {
+            continue;
+        }
+        let unit_start = packet[1] & 0x40 != 0;
+        let pid = u16::from_be_bytes([packet[1] & 0x1F, packet[2]]);
+        let adaptation_control = (packet[3] >> 4) & 0x03;
+
+        let mut payload_start = 4;
+        let mut random_access = false;
+        if adaptation_control & 0x02 != 0 {
+            let length = packet[4] as usize;
+            if length > 0 {
+                let flags = packet[5];
+                random_access = flags & 0x40 != 0;
+                if flags & 0x10 != 0 && length >= 7 && ts.pcr_pid == Some(pid) && ts.first_pcr.is_none() {
+                    ts.first_pcr = Some(read_pcr(&packet[6..12]));
+                }
+            }
+            payload_start = 5 + length;
+        }
+        if adaptation_control & 0x01 == 0 || payload_start >= PACKET_SIZE {
+            continue;
+        }
+        let payload = &packet[payload_start..];
+
+        if pid == PID_PAT {
+            if unit_start {
+                pmt_pid = parse_pat(payload).or(pmt_pid);
+            }
+            continue;
+        }
+        if Some(pid) == pmt_pid {
+            if unit_start && ts.streams.is_empty() {
+                if let Some((pcr_pid, streams)) = parse_pmt(payload) {
+                    ts.pcr_pid = Some(pcr_pid);
+                    ts.streams = streams;
+                }
+            }
+            continue;
+        }
+        if !ts.streams.iter().any(|stream| stream.pid == pid) {
+            continue;
+        }
+
+        if unit_start {
+            if let Some((random_access, data)) = pending.remove(&pid) {
+                ts.push_pes(pid, &data, random_access);
+            }
+            pending.insert(pid, (random_access, payload.to_vec()));
+        } else if let Some((_, data)) = pending.get_mut(&pid) {
+            data.extend_from_slice(payload);
+        }
+    }
+
+    // 文件末尾的 PES 没有后继的 PUSI，按 PID 顺序收尾
+    let mut rest: Vec<_> = pending.into_iter().collect();
+    rest.sort_by_key(|(pid, _)| *pid);
+    for (pid, (random_access, data)) in rest {
+        ts.push_pes(pid, &data, random_access);
+    }
+
+    (!ts.streams.is_empty()).then_some(ts)
+}
+
+impl TransportStream {
+    /// 第一路 H.264/H.265 视频流
+    pub fn video(&self) -> Option<&PesStream> {
+        self.streams.iter().find(|stream| stream.video_codec().is_some())
+    }
+
+    /// 第一路 AAC 音频流
+    pub fn audio(&self) -> Option<&PesStream> {
+        self.streams.iter().find(|stream| stream.stream_type == STREAM_TYPE_AAC)
+    }
+
+    /// 视频编码格式
+    pub fn video_codec(&self) -> Option<VideoCodec> {
+        self.video().and_then(PesStream::video_codec)
+    }
+
+    /// 时间原点（90kHz）：音视频中最早的 PTS，都没有时取第一个 PCR
+    pub fn time_origin(&self) -> Option<u64> {
+        self.streams
+            .iter()
+            .filter(|stream| stream.video_codec().is_some() || stream.stream_type == STREAM_TYPE_AAC)
+            .flat_map(|stream| stream.packets.iter().filter_map(|pes| pes.pts))
+            .min()
+            .or(self.first_pcr)
+    }
+
+    /// 按 DTS 间隔计算的视频帧率
+    pub fn frame_rate(&self) -> Option<f64> {
+        let video = self.video()?;
+        let dts: Vec<u64> = video.packets.iter().filter_map(|pes| pes.dts.or(pes.pts)).collect();
+        let span = dts.last()?.checked_sub(*dts.first()?)?;
+        (span > 0).then(|| (dts.len() - 1) as f64 * TIMESCALE as f64 / span as f64)
+    }
+
+    /// 把视频流转换为 Annex B 码流，返回码流和按解码顺序排列的访问单元表
+    ///
+    /// 每个 PES 为一个访问单元，显示时间相对 `time_origin`。没有 PTS 的 PES 接在上一帧之后按
+    /// `assumed_fps` 推算；整个流都没有随机访问指示时按 NAL 类型识别关键帧。
+    pub fn to_annex_b(&self, assumed_fps: f64) -> (Vec<u8>, Vec<AccessUnit>) {
+        let Some(video) = self.video() else {
+            return (Vec::new(), Vec::new());
+        };
+        let codec = video.video_codec().unwrap_or_default();
+        let origin = self.time_origin().unwrap_or(0) as i64;
+        let frame_ticks = (TIMESCALE as f64 / assumed_fps).round() as u64;
+        let has_random_access = video.packets.iter().any(|pes| pes.random_access);
+
+        let packets: Vec<&PesPacket> = video.packets.iter().filter(|pes| !pes.data.is_empty()).collect();
+        let mut times = Vec::with_capacity(packets.len());
+        let mut next = origin as u64;
+        for pes in &packets {
+            let dts = pes.dts.or(pes.pts).unwrap_or(next);
+            times.push((pes.pts.unwrap_or(dts), dts));
+            next = dts + frame_ticks;
+        }
+
+        let seconds = |ticks: u64| (ticks as i64 - origin) as f64 / TIMESCALE as f64;
+        let mut stream = Vec::new();
+        let mut units = Vec::with_capacity(packets.len());
+        let mut last_duration = frame_ticks;
+        for (i, pes) in packets.iter().enumerate() {
+            let (pts, dts) = times[i];
+            let duration = match times.get(i + 1) {
+                Some(&(_, next_dts)) if next_dts > dts => next_dts - dts,
+                _ => last_duration,
+            };
+            last_duration = duration;
+
+            let is_keyframe = if has_random_access {
+                pes.random_access
+            } else {
+                contains_keyframe(codec, &pes.data)
+            };
+            units.push(AccessUnit {
+                timestamp: seconds(pts),
+                decode_timestamp: seconds(dts),
+                duration: duration as f64 / TIMESCALE as f64,
+                offset: stream.len() as u64,
+                size: pes.data.len() as u32,
+                is_keyframe,
+            });
+            stream.extend_from_slice(&pes.data);
+        }
+        (stream, units)
+    }
+
+    /// 音频流中的 ADTS 帧，时间相对 `time_origin`
+    ///
+    /// 一个 PES 可以包含多个 ADTS 帧，后续帧的时间按采样数累加。
+    pub fn audio_frames(&self) -> Vec<AudioFrame> {
+        let Some(audio) = self.audio() else {
+            return Vec::new();
+        };
+        let origin = self.time_origin().unwrap_or(0) as i64;
+
+        let mut frames = Vec::new();
+        let mut next = 0.0;
+        for pes in &audio.packets {
+            let mut timestamp = pes.pts.map_or(next, |pts| (pts as i64 - origin) as f64 / TIMESCALE as f64);
+            let mut rest = pes.data.as_slice();
+            while let Some((frame, len)) = aac::parse_adts_frame(rest) {
+                frames.push(AudioFrame {
+                    timestamp,
+                    data: rest[..len].to_vec(),
+                });
+                timestamp += frame.samples as f64 / frame.config.sample_rate() as f64;
+                rest = &rest[len..];
+            }
+            next = timestamp;
+        }
+        frames
+    }
+
+    /// 解析一个完整的 PES 并展开时间戳回绕
+    fn push_pes(&mut self, pid: u16, data: &[u8], random_access: bool) {
+        let Some(mut pes) = parse_pes(data, random_access) else {
+            return;
+        };
+        let Some(stream) = self.streams.iter_mut().find(|stream| stream.pid == pid) else {
+            return;
+        };
+        let reference = stream.packets.iter().rev().find_map(|prev| prev.dts.or(prev.pts));
+        if let Some(reference) = reference {
+            pes.dts = pes.dts.map(|dts| unwrap_timestamp(reference, dts));
+            pes.pts = pes.pts.map(|pts| unwrap_timestamp(reference, pts));
+        } else if let (Some(pts), Some(dts)) = (pes.pts, pes.dts) {
+            // 第一个 PES 的 PTS 可能已经回绕而 DTS 还没有
+            pes.pts = Some(unwrap_timestamp(dts, pts));
+        }
+        stream.packets.push(pes);
+    }
+}
+
+/// 访问单元中是否有 IDR（H.264）或 IRAP（H.265）条带
+fn contains_keyframe(codec: VideoCodec, data: &[u8]) -> bool {
+    h264::nal_units(data).into_iter().any(|nal| match codec {
+        VideoCodec::H264 => h264::nal_type(nal) == h264::NAL_IDR,
+        VideoCodec::H265 => hevc::is_irap(hevc::nal_type(nal)),
+    })
+}
+
+/// 取离 `reference` 最近的回绕值
+fn unwrap_timestamp(reference: u64, value: u64) -> u64 {
+    let candidate = (reference & !(TIMESTAMP_WRAP - 1)) | (value & (TIMESTAMP_WRAP - 1));
+    if candidate + TIMESTAMP_WRAP / 2 < reference {
+        candidate + TIMESTAMP_WRAP
+    } else if candidate > reference + TIMESTAMP_WRAP / 2 && candidate >= TIMESTAMP_WRAP {
+        candidate - TIMESTAMP_WRAP
+    } else {
+        candidate
+    }
+}
+
+/// PSI 段（跳过指针域），校验表 ID 并按段长度截断（不含 CRC）
+fn psi_section(payload: &[u8], table_id: u8) -> Option<&[u8]> {
+    let pointer = *payload.first()? as usize;
+    let section = payload.get(1 + pointer..)?;
+    if *section.first()? != table_id || section.len() < 3 {
+        return None;
+    }
+    let length = (((section[1] & 0x0F) as usize) << 8) | section[2] as usize;
+    section.get(..(3 + length).checked_sub(4)?)
+}
+
+/// 解析 PAT，返回第一个节目的 PMT PID
+fn parse_pat(payload: &[u8]) -> Option<u16> {
+    let section = psi_section(payload, 0x00)?;
+    section
+        .get(8..)?
+        .chunks_exact(4)
+        .find(|entry| u16::from_be_bytes([entry[0], entry[1]]) != 0)
+        .map(|entry| u16::from_be_bytes([entry[2] & 0x1F, entry[3]]))
+}
+
+/// 解析 PMT，返回 PCR PID 和基本流列表
+fn parse_pmt(payload: &[u8]) -> Option<(u16, Vec<PesStream>)> {
+    let section = psi_section(payload, 0x02)?;
+    let header = section.get(..12)?;
+    let pcr_pid = u16::from_be_bytes([header[8] & 0x1F, header[9]]);
+    let program_info_length = (((header[10] & 0x0F) as usize) << 8) | header[11] as usize;
+
+    let mut streams = Vec::new();
+    let mut pos = 12 + program_info_length;
+    while pos + 5 <= section.len() {
+        let entry = &section[pos..pos + 5];
+        streams.push(PesStream {
+            pid: u16::from_be_bytes([entry[1] & 0x1F, entry[2]]),
+            stream_type: entry[0],
+            packets: Vec::new(),
+        });
+        pos += 5 + ((((entry[3] & 0x0F) as usize) << 8) | entry[4] as usize);
+    }
+    Some((pcr_pid, streams))
+}
+
+/// 解析 PES 头，`PES_packet_length` 非 0 时截掉其后的填充
+fn parse_pes(data: &[u8], random_access: bool) -> Option<PesPacket> {
+    if data.len() < 9 || data[..3] != [0, 0, 1] {
+        return None;
+    }
+    let flags = data[7] >> 6;
+    let header_length = data[8] as usize;
+    let payload_start = 9 + header_length;
+    let packet_length = u16::from_be_bytes([data[4], data[5]]) as usize;
+    let end = match packet_length {
+        0 => data.len(),
+        length => (6 + length).min(data.len()),
+    };
+    if payload_start > end {
+        return None;
+    }
+
+    let pts = (flags & 0x02 != 0 && header_length >= 5).then(|| read_timestamp(&data[9..14]));
+    let dts = (flags == 0x03 && header_length >= 10).then(|| read_timestamp(&data[14..19]));
+    Some(PesPacket {
+        pts,
+        dts,
+        random_access,
+        data: data[payload_start..end].to_vec(),
+    })
+}
+
+/// 读取 5 字节的 PTS/DTS
+fn read_timestamp(b: &[u8]) -> u64 {
+    ((((b[0] >> 1) & 0x07) as u64) << 30)
+        | ((b[1] as u64) << 22)
+        | (((b[2] >> 1) as u64) << 15)
+        | ((b[3] as u64) << 7)
+        | (b[4] >> 1) as u64
+}
+
+/// 读取 PCR 的 90kHz 基准部分（忽略 27MHz 扩展）
+fn read_pcr(b: &[u8]) -> u64 {
+    ((b[0] as u64) << 25) | ((b[1] as u64) << 17) | ((b[2] as u64) << 9) | ((b[3] as u64) << 1) | (b[4] >> 7) as u64
+}
+
+/// MPEG-TS 封装器
+///
+/// 单节目：PMT PID 0x1000，视频 PID 0x100（兼作 PCR），音频 PID 0x101。每个视频 PES 的首包写 PCR，
+/// 关键帧设置随机访问指示。时间戳整体加 `TIMESTAMP_OFFSET`，保证 PCR 早于 DTS 且不为负。
+pub struct TsMuxer {
+    codec: VideoCodec,
+    has_audio: bool,
+    /// 各 PID 的连续计数器
+    continuity: HashMap<u16, u8>,
+}
+
+impl TsMuxer {
+    /// PMT 的 PID
+    pub const PID_PMT: u16 = 0x1000;
+    /// 视频 PID
+    pub const PID_VIDEO: u16 = 0x100;
+    /// 音频 PID
+    pub const PID_AUDIO: u16 = 0x101;
+    /// 时间戳偏移（90kHz，1.4 秒）
+    const TIMESTAMP_OFFSET: u64 = 126_000;
+    /// PCR 比 DTS 提前的量（90kHz，0.7 秒）
+    const PCR_DELAY: u64 = 63_000;
+
+    pub fn new(codec: VideoCodec, has_audio: bool) -> Self {
+        Self {
+            codec,
+            has_audio,
+            continuity: HashMap::new(),
+        }
+    }
+
+    /// 写入 PAT 和 PMT（每个 HLS 分片开头写一次）
+    pub fn write_tables(&mut self, out: &mut Vec<u8>) {
+        let mut pat = vec![0x00, 0x01, 0xC1, 0x00, 0x00, 0x00, 0x01];
+        pat.extend_from_slice(&(0xE000 | Self::PID_PMT).to_be_bytes());
+        self.write_section(PID_PAT, 0x00, &pat, out);
+
+        let stream_type = match self.codec {
+            VideoCodec::H264 => STREAM_TYPE_H264,
+            VideoCodec::H265 => STREAM_TYPE_H265,
+        };
+        let mut pmt = vec![0x00, 0x01, 0xC1, 0x00, 0x00];
+        pmt.extend_from_slice(&(0xE000 | Self::PID_VIDEO).to_be_bytes());
+        pmt.extend_from_slice(&[0xF0, 0x00]);
+        let mut streams = vec![(stream_type, Self::PID_VIDEO)];
+        if self.has_audio {
+            streams.push((STREAM_TYPE_AAC, Self::PID_AUDIO));
+        }
+        for (stream_type, pid) in streams {
+            pmt.push(stream_type);
+            pmt.extend_from_slice(&(0xE000 | pid).to_be_bytes());
+            pmt.extend_from_slice(&[0xF0, 0x00]);
+        }
+        self.write_section(Self::PID_PMT, 0x02, &pmt, out);
+    }
+
+    /// 写入一个视频访问单元（Annex B），时间单位为秒
+    pub fn write_video(&mut self, data: &[u8], pts: f64, dts: f64, is_keyframe: bool, out: &mut Vec<u8>) {
+        let pts = Self::ticks(pts);
+        let dts = Self::ticks(dts);
+        // 视频 PES 长度可以为 0（不限长度）
+        let pes = Self::pes(0xE0, data, pts, (dts != pts).then_some(dts), false);
+        let pcr = dts.saturating_sub(Self::PCR_DELAY);
+        self.write_packets(Self::PID_VIDEO, &pes, Some(pcr), is_keyframe, out);
+    }
+
+    /// 写入 ADTS 音频帧（可以是连续的多帧），时间单位为秒
+    pub fn write_audio(&mut self, adts: &[u8], pts: f64, out: &mut Vec<u8>) {
+        let pes = Self::pes(0xC0, adts, Self::ticks(pts), None, true);
+        self.write_packets(Self::PID_AUDIO, &pes, None, false, out);
+    }
+
+    /// 秒转换为 90kHz 时间戳，早于偏移量的解码时间（B 帧）截断为 0
+    fn ticks(seconds: f64) -> u64 {
+        ((seconds * TIMESCALE as f64).round() as i64 + Self::TIMESTAMP_OFFSET as i64).max(0) as u64
+    }
+
+    /// 构造 PES 包
+    fn pes(stream_id: u8, data: &[u8], pts: u64, dts: Option<u64>, bounded: bool) -> Vec<u8> {
+        let header_length = if dts.is_some() { 10 } else { 5 };
+        let packet_length = 3 + header_length + data.len();
+        let packet_length = if bounded && packet_length <= u16::MAX as usize { packet_length as u16 } else { 0 };
+
+        let mut pes = Vec::with_capacity(9 + header_length + data.len());
+        pes.extend_from_slice(&[0, 0, 1, stream_id]);
+        pes.extend_from_slice(&packet_length.to_be_bytes());
+        pes.push(0x80);
+        pes.push(if dts.is_some() { 0xC0 } else { 0x80 });
+        pes.push(header_length as u8);
+        match dts {
+            Some(dts) => {
+                write_timestamp(0x3, pts, &mut pes);
+                write_timestamp(0x1, dts, &mut pes);
+            }
+            None => write_timestamp(0x2, pts, &mut pes),
+        }
+        pes.extend_from_slice(data);
+        pes
+    }
+
+    /// 写入一个 PSI 段（单个 TS 包）
+    fn write_section(&mut self, pid: u16, table_id: u8, body: &[u8], out: &mut Vec<u8>) {
+        let length = body.len() + 4;
+        let mut section = vec![table_id, 0xB0 | (length >> 8) as u8, length as u8];
+        section.extend_from_slice(body);
+        section.extend_from_slice(&crc32_mpeg2(&section).to_be_bytes());
+
+        let mut payload = vec![0x00];
+        payload.extend(section);
+        payload.resize(PACKET_SIZE - 4, 0xFF);
+        let continuity = self.next_continuity(pid);
+        out.extend_from_slice(&[SYNC_BYTE, 0x40 | (pid >> 8) as u8, pid as u8, 0x10 | continuity]);
+        out.extend(payload);
+    }
+
+    /// 把 PES 切分为 TS 包，首包可带 PCR 和随机访问指示，末包用适配域填充
+    fn write_packets(&mut self, pid: u16, pes: &[u8], pcr: Option<u64>, random_access: bool, out: &mut Vec<u8>) {
+        let mut rest = pes;
+        let mut first = true;
+        while !rest.is_empty() {
+            // 适配域内容（不含长度字节）
+            let mut adaptation: Option<Vec<u8>> = None;
+            if first && (pcr.is_some() || random_access) {
+                let mut field = vec![(if random_access { 0x40 } else { 0 }) | (if pcr.is_some() { 0x10 } else { 0 })];
+                if let Some(pcr) = pcr {
+                    field.extend_from_slice(&[
+                        (pcr >> 25) as u8,
+                        (pcr >> 17) as u8,
+                        (pcr >> 9) as u8,
+                        (pcr >> 1) as u8,
+                        ((pcr & 1) << 7) as u8 | 0x7E,
+                        0x00,
+                    ]);
+                }
+                adaptation = Some(field);
+            }
+
+            let room = PACKET_SIZE - 4 - adaptation.as_ref().map_or(0, |field| 1 + field.len());
+            let take = rest.len().min(room);
+            let stuffing = room - take;
+            if stuffing > 0 {
+                match adaptation.as_mut() {
+                    Some(field) => field.extend(std::iter::repeat_n(0xFF, stuffing)),
+                    None if stuffing == 1 => adaptation = Some(Vec::new()),
+                    None => {
+                        let mut field = vec![0x00];
+                        field.extend(std::iter::repeat_n(0xFF, stuffing - 2));
+                        adaptation = Some(field);
+                    }
+                }
+            }
+
+            let continuity = self.next_continuity(pid);
+            let control = if adaptation.is_some() { 0x30 } else { 0x10 };
+            let unit_start = if first { 0x40 } else { 0x00 };
+            out.extend_from_slice(&[SYNC_BYTE, unit_start | (pid >> 8) as u8, pid as u8, control | continuity]);
+            if let Some(field) = adaptation {
+                out.push(field.len() as u8);
+                out.extend(field);
+            }
+            out.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            first = false;
+        }
+    }
+
+    fn next_continuity(&mut self, pid: u16) -> u8 {
+        let counter = self.continuity.entry(pid).or_insert(0);
+        let value = *counter;
+        *counter = (value + 1) & 0x0F;
+        value
+    }
+}
+
+/// 写入 5 字节的 PTS/DTS，`prefix` 为前 4 位标识（0x2 仅 PTS、0x3 PTS、0x1 DTS）
+fn write_timestamp(prefix: u8, ticks: u64, out: &mut Vec<u8>) {
+    let ticks = ticks & (TIMESTAMP_WRAP - 1);
+    out.extend_from_slice(&[
+        (prefix << 4) | (((ticks >> 29) as u8) & 0x0E) | 0x01,
+        (ticks >> 22) as u8,
+        (((ticks >> 14) as u8) & 0xFE) | 0x01,
+        (ticks >> 7) as u8,
+        (((ticks << 1) as u8) & 0xFE) | 0x01,
+    ]);
+}
+
+/// PSI 段使用的 CRC-32/MPEG-2
+fn crc32_mpeg2(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04C1_1DB7 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aac::AudioSpecificConfig;
+
+    fn h264_frame(keyframe: bool, index: u8) -> Vec<u8> {
+        let mut frame = Vec::new();
+        if keyframe {
+            frame.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+            frame.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+            frame.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, index]);
+        } else {
+            frame.extend_from_slice(&[0, 0, 0, 1, 0x41, 0x9a, index]);
+        }
+        // 大于一个 TS 包，覆盖跨包重组
+        frame.extend(std::iter::repeat_n(index, 300));
+        frame
+    }
+
+    #[test]
+    fn test_crc32_mpeg2() {
+        assert_eq!(crc32_mpeg2(b"123456789"), 0x0376_E6E7);
+    }
+
+    #[test]
+    fn test_mux_demux_roundtrip() {
+        let config = AudioSpecificConfig { object_type: 2, sample_rate_index: 3, channel_config: 2 };
+        let mut muxer = TsMuxer::new(VideoCodec::H264, true);
+        let mut data = Vec::new();
+        muxer.write_tables(&mut data);
+        for i in 0..6u8 {
+            let t = i as f64 / 25.0;
+            muxer.write_video(&h264_frame(i % 3 == 0, i), t, t, i % 3 == 0, &mut data);
+            muxer.write_audio(&aac::to_adts(&config, &[0x21, i]), t, &mut data);
+        }
+        assert_eq!(data.len() % PACKET_SIZE, 0);
+        assert!(is_mpeg_ts(&data));
+
+        let ts = parse(&data).unwrap();
+        assert_eq!(ts.pcr_pid, Some(TsMuxer::PID_VIDEO));
+        assert_eq!(ts.first_pcr, Some(TsMuxer::TIMESTAMP_OFFSET - TsMuxer::PCR_DELAY));
+        assert_eq!(ts.video_codec(), Some(VideoCodec::H264));
+        assert_eq!(ts.time_origin(), Some(TsMuxer::TIMESTAMP_OFFSET));
+        assert!((ts.frame_rate().unwrap() - 25.0).abs() < 1e-9);
+
+        let (stream, units) = ts.to_annex_b(30.0);
+        assert_eq!(units.len(), 6);
+        let keyframes: Vec<bool> = units.iter().map(|unit| unit.is_keyframe).collect();
+        assert_eq!(keyframes, [true, false, false, true, false, false]);
+        assert!((units[4].timestamp - 0.16).abs() < 1e-9);
+        assert!((units[5].duration - 0.04).abs() < 1e-9);
+        let unit = &units[3];
+        assert_eq!(&stream[unit.offset as usize..][..unit.size as usize], h264_frame(true, 3));
+
+        let audio = ts.audio_frames();
+        assert_eq!(audio.len(), 6);
+        assert!((audio[2].timestamp - 0.08).abs() < 1e-9);
+        assert_eq!(audio[2].data, aac::to_adts(&config, &[0x21, 2]));
+    }
+
+    #[test]
+    fn test_keyframes_without_random_access() {
+        // 随机访问指示缺失时按 IDR 识别关键帧
+        let mut data = Vec::new();
+        let mut muxer = TsMuxer::new(VideoCodec::H264, false);
+        muxer.write_tables(&mut data);
+        for i in 0..4u8 {
+            let t = i as f64 / 30.0;
+            muxer.write_video(&h264_frame(i == 2, i), t, t, false, &mut data);
+        }
+
+        let (_, units) = parse(&data).unwrap().to_annex_b(30.0);
+        let keyframes: Vec<bool> = units.iter().map(|unit| unit.is_keyframe).collect();
+        assert_eq!(keyframes, [false, false, true, false]);
+    }
+
+    #[test]
+    fn test_unwrap_timestamp() {
+        let near_wrap = TIMESTAMP_WRAP - 3000;
+        assert_eq!(unwrap_timestamp(near_wrap, 3000), TIMESTAMP_WRAP + 3000);
+        assert_eq!(unwrap_timestamp(TIMESTAMP_WRAP + 3000, near_wrap), near_wrap);
+        assert_eq!(unwrap_timestamp(9000, 6000), 6000);
+        assert!(parse(&[0u8; PACKET_SIZE]).is_none());
+    }
+}
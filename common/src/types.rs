@@ -8,32 +8,175 @@ pub struct VideoSegment {
     pub stream_type: u8,
     pub segment_id: Uuid,
     pub session_id: Uuid,  // 播放会话ID，用于分发到正确的订阅者
-    pub timestamp: f64,
-    pub duration: f64,
+    /// 显示时间戳（`timescale` 为单位，协议 1.5 起）
+    pub pts: i64,
+    /// 解码时间戳（含 B 帧时早于 `pts`，协议 1.5 起）
+    pub dts: i64,
+    /// 时间戳和时长的时间基（每秒刻度数，协议 1.5 起）
+    pub timescale: u32,
+    /// 分片时长（`timescale` 为单位）
+    pub duration: u32,
     pub frame_count: u32,
     pub flags: u8,
     pub data_length: u32,
     pub data: Vec<u8>,
+    /// 设备采集时间（设备时钟，Unix 微秒，协议 1.2 起）
+    pub capture_time_us: u64,
+    /// 设备发送时间（设备时钟，Unix 微秒，协议 1.2 起）
+    pub send_time_us: u64,
+    /// 设备端单调递增的发送序号（协议 1.2 起）
+    pub sequence: u64,
+    /// 分片所属码流（`RenditionInfo::id`，协议 1.3 起）
+    pub rendition_id: u8,
+    /// 视频编码格式（协议 1.4 起；音频分片忽略）
+    pub codec: VideoCodec,
+    /// 平台接收时间（仅本地使用，不参与传输）
+    #[serde(skip)]
+    pub receive_time: Option<SystemTime>,
 }
 
 impl VideoSegment {
-    pub fn new(data: Vec<u8>, timestamp: f64, is_keyframe: bool) -> Self {
+    /// 分片时间戳的默认时间基（90kHz，与 MPEG-TS/RTP 视频时钟一致）
+    pub const TIMESCALE: u32 = 90_000;
+
+    /// 创建单帧视频分片，`pts`/`duration` 以 `TIMESCALE` 为单位，解码时间戳与显示时间戳相同
+    pub fn new(data: Vec<u8>, pts: i64, duration: u32, is_keyframe: bool) -> Self {
         Self {
-            stream_type: 0x01, // 视频
+            stream_type: StreamType::VIDEO,
             segment_id: Uuid::new_v4(),
             session_id: Uuid::nil(),  // 默认为空，需要在发送前设置
-            timestamp,
-            duration: 0.033, // 约30fps
+            pts,
+            dts: pts,
+            timescale: Self::TIMESCALE,
+            duration,
             frame_count: 1,
             flags: if is_keyframe { SegmentFlags::IS_KEYFRAME } else { 0 },
             data_length: data.len() as u32,
             data,
+            capture_time_us: crate::utils::current_timestamp_us(),
+            send_time_us: 0,
+            sequence: 0,
+            rendition_id: 0,
+            codec: VideoCodec::H264,
+            receive_time: None,
+        }
+    }
+
+    /// 创建音频分片（ADTS 帧序列或原始 AAC 帧），时间戳与视频分片共用设备时间轴
+    pub fn new_audio(data: Vec<u8>, pts: i64, duration: u32, frame_count: u32) -> Self {
+        Self {
+            stream_type: StreamType::AUDIO,
+            frame_count,
+            ..Self::new(data, pts, duration, false)
+        }
+    }
+
+    /// 秒换算为 `TIMESCALE` 刻度（四舍五入）
+    pub fn ticks(seconds: f64) -> i64 {
+        (seconds * Self::TIMESCALE as f64).round() as i64
+    }
+
+    /// 显示时间戳（秒）
+    pub fn pts_secs(&self) -> f64 {
+        self.pts as f64 / self.timescale as f64
+    }
+
+    /// 解码时间戳（秒）
+    pub fn dts_secs(&self) -> f64 {
+        self.dts as f64 / self.timescale as f64
+    }
+
+    /// 分片时长（秒）
+    pub fn duration_secs(&self) -> f64 {
+        self.duration as f64 / self.timescale as f64
+    }
+
+    /// 把时间戳和时长换算到 `TIMESCALE` 时间基
+    pub fn normalize_timescale(&mut self) {
+        use crate::utils::rescale_timestamp;
+        if self.timescale == Self::TIMESCALE {
+            return;
         }
+        self.pts = rescale_timestamp(self.pts, self.timescale, Self::TIMESCALE);
+        self.dts = rescale_timestamp(self.dts, self.timescale, Self::TIMESCALE);
+        self.duration = rescale_timestamp(self.duration as i64, self.timescale, Self::TIMESCALE) as u32;
+        self.timescale = Self::TIMESCALE;
+    }
+
+    /// 把时间戳平移 `delta` 个刻度（PTS 与 DTS 同时平移，保持两者的差）
+    pub fn shift_timestamps(&mut self, delta: i64) {
+        self.pts += delta;
+        self.dts += delta;
+    }
+
+    /// 是否为音频分片
+    pub fn is_audio(&self) -> bool {
+        self.stream_type == StreamType::AUDIO
+    }
+
+    /// 是否携带解码配置（如 AudioSpecificConfig）而不是媒体数据
+    pub fn is_codec_config(&self) -> bool {
+        self.flags & SegmentFlags::CODEC_CONFIG != 0
     }
 
     pub fn is_keyframe(&self) -> bool {
         self.flags & SegmentFlags::IS_KEYFRAME != 0
     }
+
+    /// 是否为精确定位的预解码帧（只解码不显示）
+    pub fn is_decode_only(&self) -> bool {
+        self.flags & SegmentFlags::DECODE_ONLY != 0
+    }
+
+    /// 时间戳是否与上一个分片不连续（设备重启、编码器重建等）
+    pub fn is_discontinuity(&self) -> bool {
+        self.flags & SegmentFlags::DISCONTINUITY != 0
+    }
+
+    /// 是否为可丢弃的非参考视频帧（丢失后不影响后续帧解码）
+    pub fn is_disposable(&self) -> bool {
+        if self.is_audio() || self.is_keyframe() || self.is_codec_config() {
+            return false;
+        }
+        match self.codec {
+            VideoCodec::H264 => crate::h264::is_disposable(&self.data),
+            VideoCodec::H265 => crate::hevc::is_disposable(&self.data),
+        }
+    }
+}
+
+/// 视频编码格式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+}
+
+impl VideoCodec {
+    /// 编码名称，如 "h264"、"h265"
+    pub fn name(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "h264",
+            VideoCodec::H265 => "h265",
+        }
+    }
+
+    /// 按裸流文件扩展名识别编码格式（不区分大小写）
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "h264" | "264" => Some(VideoCodec::H264),
+            "h265" | "265" | "hevc" => Some(VideoCodec::H265),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
 /// 分片标志位
@@ -43,6 +186,20 @@ pub mod SegmentFlags {
     pub const IS_LAST_SEGMENT: u8 = 0b0000_0100;
     pub const REQUIRES_ACK: u8 = 0b0000_1000;
     pub const HIGH_PRIORITY: u8 = 0b0001_0000;
+    /// 精确定位的预解码帧：播放器需要解码但不显示
+    pub const DECODE_ONLY: u8 = 0b0010_0000;
+    /// 分片数据是解码配置（音频为 AudioSpecificConfig），需在后续原始帧之前送达
+    pub const CODEC_CONFIG: u8 = 0b0100_0000;
+    /// 时间戳不连续：该分片与上一个分片之间时间轴发生跳变，播放器需要重新对齐时间
+    pub const DISCONTINUITY: u8 = 0b1000_0000;
+}
+
+/// 分片流类型（`VideoSegment::stream_type`）
+#[allow(non_snake_case)]
+pub mod StreamType {
+    pub const VIDEO: u8 = 0x01;
+    /// AAC 音频：一个或多个 ADTS 帧；或者每个分片一个原始帧，此前先发送 CODEC_CONFIG 分片
+    pub const AUDIO: u8 = 0x02;
 }
 
 /// 协议消息
@@ -81,6 +238,13 @@ pub enum MessageType {
     GetKeyframeIndex = 0x14, // 获取关键帧索引
     SeekResponse = 0x15,     // Seek 操作响应
     KeyframeIndexResponse = 0x16, // 关键帧索引响应
+    SessionStartResponse = 0x17,  // 会话开始响应（协议版本协商结果）
+    ClockSync = 0x18,             // 时钟同步（NTP 式往返测量）
+    SwitchRendition = 0x19,       // 切换直通播放码流（主/子码流）
+    KeyframeRequest = 0x1A,       // 请求设备立即输出关键帧（PLI）
+    FileUploadOffer = 0x1B,       // 设备发起整文件上传（可续传）
+    FileUploadChunk = 0x1C,       // 文件上传分块（带偏移）
+    FileUploadAck = 0x1D,         // 文件上传确认（已落盘偏移与状态）
 }
 
 /// 设备信息
@@ -119,6 +283,29 @@ pub struct DeviceCapabilities {
     pub max_bitrate: u64,
     pub supports_playback_control: bool,
     pub supports_recording: bool,
+    /// 可用的直通播放码流（主码流、子码流等，协议 1.3 起）
+    pub renditions: Vec<RenditionInfo>,
+}
+
+impl DeviceCapabilities {
+    /// 按ID查找码流
+    pub fn rendition(&self, id: u8) -> Option<&RenditionInfo> {
+        self.renditions.iter().find(|r| r.id == id)
+    }
+}
+
+/// 码流（同一画面的一路编码输出）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenditionInfo {
+    /// 码流ID（分片中的 `rendition_id`）
+    pub id: u8,
+    /// 名称，如 "main"、"sub"
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// 码率（bps）
+    pub bitrate: u64,
+    pub fps: u32,
 }
 
 /// 录像信息
@@ -131,6 +318,8 @@ pub struct RecordingInfo {
     pub file_size: u64,
     pub duration: f64,
     pub format: String,
+    /// 视频编码格式（协议 1.4 起）
+    pub codec: VideoCodec,
     pub resolution: String,
     pub bitrate: u64,
     pub frame_rate: f64,
@@ -8,6 +8,25 @@ pub fn current_timestamp_ms() -> u64 {
         .as_millis() as u64
 }
 
+/// 获取当前时间戳（微秒）
+pub fn current_timestamp_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_micros() as u64
+}
+
+/// 把时间戳从 `from` 时间基换算到 `to` 时间基（四舍五入，中间结果不会溢出）
+pub fn rescale_timestamp(value: i64, from: u32, to: u32) -> i64 {
+    if from == to || from == 0 {
+        return value;
+    }
+    let scaled = value as i128 * to as i128;
+    let half = from as i128 / 2;
+    let rounded = if scaled >= 0 { scaled + half } else { scaled - half };
+    (rounded / from as i128) as i64
+}
+
 /// 格式化文件大小
 pub fn format_file_size(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -1,7 +1,15 @@
 use anyhow::Result;
+use common::RenditionInfo;
 use std::path::PathBuf;
 use crate::video::IndexOptimizationStrategy;
 
+/// 直通播放码流（码流参数 + 模拟该码流使用的 H.264 裸流文件）
+#[derive(Debug, Clone)]
+pub struct LiveRendition {
+    pub info: RenditionInfo,
+    pub file: PathBuf,
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub device_id: String,
@@ -25,6 +33,19 @@ pub struct Config {
     // 播放控制配置
     pub playback_speed_min: f32,
     pub playback_speed_max: f32,
+
+    // 直通播放码流配置（第一路为默认码流）
+    pub live_renditions: Vec<LiveRendition>,
+
+    // 直通播放音频（AAC ADTS 文件，None 表示不发送音频）
+    pub live_audio: Option<PathBuf>,
+
+    // 连接后上传到平台存储备份的录像文件（上传成功后不再重复上传）
+    pub backup_files: Vec<PathBuf>,
+
+    // 断网缓存目录（None 表示断网时不缓存直通分片）及容量上限
+    pub spool_dir: Option<PathBuf>,
+    pub spool_max_bytes: u64,
 }
 
 impl Config {
@@ -51,9 +72,56 @@ impl Config {
             // 播放控制配置（默认范围 0.25x - 4.0x）
             playback_speed_min: 0.25,
             playback_speed_max: 4.0,
+
+            // 直通播放码流配置（默认主码流 + 子码流）
+            live_renditions: Self::default_live_renditions(),
+
+            // 直通播放音频（默认文件存在时启用）
+            live_audio: Some(PathBuf::from("test-videos/sample_audio.aac")).filter(|path| path.exists()),
+
+            // 录像备份（默认不上传）
+            backup_files: Vec::new(),
+
+            // 断网缓存（默认 256MB）
+            spool_dir: Some(PathBuf::from("./spool")),
+            spool_max_bytes: 256 * 1024 * 1024,
         })
     }
     
+    /// 默认码流：720p 主码流和 360p 子码流
+    ///
+    /// 子码流文件不存在时复用主码流文件，仅用于演示码流切换流程。
+    fn default_live_renditions() -> Vec<LiveRendition> {
+        let main_file = PathBuf::from("test-videos/sample_720p_60fps.h264");
+        let sub_file = PathBuf::from("test-videos/sample_360p_30fps.h264");
+        let sub_file = if sub_file.exists() { sub_file } else { main_file.clone() };
+
+        vec![
+            LiveRendition {
+                info: RenditionInfo {
+                    id: 0,
+                    name: "main".to_string(),
+                    width: 1280,
+                    height: 720,
+                    bitrate: 2_000_000,
+                    fps: 30,
+                },
+                file: main_file,
+            },
+            LiveRendition {
+                info: RenditionInfo {
+                    id: 1,
+                    name: "sub".to_string(),
+                    width: 640,
+                    height: 360,
+                    bitrate: 600_000,
+                    fps: 30,
+                },
+                file: sub_file,
+            },
+        ]
+    }
+
     /// 验证配置的有效性
     pub fn validate(&self) -> Result<()> {
         // 验证播放速率范围
@@ -75,6 +143,11 @@ impl Config {
             anyhow::bail!("ffmpeg_timeout_seconds must be greater than 0");
         }
         
+        // 验证码流配置
+        if self.live_renditions.is_empty() {
+            anyhow::bail!("live_renditions must not be empty");
+        }
+
         // 验证视频目录存在
         if !self.video_dir.exists() {
             anyhow::bail!("video_dir does not exist: {:?}", self.video_dir);
@@ -149,6 +222,29 @@ impl Config {
         if let Ok(max_speed) = std::env::var("PLAYBACK_SPEED_MAX") {
             config.playback_speed_max = max_speed.parse()?;
         }
+
+        // 直通播放音频（空字符串表示禁用）
+        if let Ok(audio) = std::env::var("LIVE_AUDIO_FILE") {
+            config.live_audio = Some(PathBuf::from(audio)).filter(|path| !path.as_os_str().is_empty());
+        }
+
+        // 录像备份（逗号分隔的文件路径）
+        if let Ok(files) = std::env::var("BACKUP_FILES") {
+            config.backup_files = files
+                .split(',')
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+                .collect();
+        }
+
+        // 断网缓存（空字符串表示禁用）
+        if let Ok(dir) = std::env::var("SPOOL_DIR") {
+            config.spool_dir = Some(PathBuf::from(dir)).filter(|path| !path.as_os_str().is_empty());
+        }
+        if let Ok(max_mb) = std::env::var("SPOOL_MAX_MB") {
+            config.spool_max_bytes = max_mb.parse::<u64>()? * 1024 * 1024;
+        }
         
         // 验证配置
         config.validate()?;
@@ -180,6 +276,34 @@ impl Config {
         info!("");
         info!("=== Playback Control Configuration ===");
         info!("Speed Range: {}x - {}x", self.playback_speed_min, self.playback_speed_max);
+        info!("");
+        info!("=== Live Renditions ===");
+        for rendition in &self.live_renditions {
+            info!(
+                "#{} {}: {}x{} {}fps {} kbps ({:?})",
+                rendition.info.id,
+                rendition.info.name,
+                rendition.info.width,
+                rendition.info.height,
+                rendition.info.fps,
+                rendition.info.bitrate / 1000,
+                rendition.file
+            );
+        }
+        info!("Audio: {:?}", self.live_audio);
+        info!("");
+        info!("=== Outage Spool ===");
+        match &self.spool_dir {
+            Some(dir) => info!("{:?} (max {}MB)", dir, self.spool_max_bytes / 1024 / 1024),
+            None => info!("Disabled"),
+        }
+        if !self.backup_files.is_empty() {
+            info!("");
+            info!("=== Recording Backup ===");
+            for file in &self.backup_files {
+                info!("{:?}", file);
+            }
+        }
         info!("============================");
     }
 }
@@ -1,20 +1,27 @@
-use crate::quic::QuicClient;
+use crate::config::LiveRendition;
+use crate::quic::{FileUploader, MediaSender, QuicClient, SegmentEncoder};
+use crate::spool::{SegmentSpool, SpoolWrite};
 use crate::video::{VideoFile, VideoFormat};
 use crate::video::{
-    DefaultPlaybackController, DefaultTimelineManager, TimelineManager,
+    DefaultPlaybackController, DefaultTimelineManager, PlaybackController, TimelineManager,
     DefaultFFmpegParser, FFmpegParser, DefaultFileStreamReader, FileStreamReader,
     KeyframeIndex, IndexOptimizationStrategy, TimelineFileBuilder,
 };
+use common::utils::current_timestamp_us;
 use common::{
-    FileListResponse, MessageType, ProtocolMessage, RecordingInfo, Result, VideoSegment,
-    VideoStreamError,
+    ClockSyncRequest, ClockSyncResponse, FeatureFlags, FileListResponse, MessageType, ProtocolMessage,
+    RecordingInfo, Result, SegmentFlags, VideoSegment, VideoStreamError,
 };
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, Notify, RwLock};
 use tracing::{debug, error, info, warn};
 
+/// 无法解析访问单元时按块发送，每块按一帧计时（30fps，90kHz 刻度）
+const CHUNK_DURATION: u32 = 3_000;
+
 pub struct DeviceService {
     client: QuicClient,
     video_files: Vec<VideoFile>,
@@ -24,6 +31,32 @@ pub struct DeviceService {
     timeline_manager: Arc<DefaultTimelineManager>,
     ffmpeg_parser: Option<Arc<DefaultFFmpegParser>>,
     file_reader: Arc<DefaultFileStreamReader>,
+    live: LiveOptions,
+    live_streams: LiveStreams,
+    /// 尚未上传到平台备份的录像
+    pending_backups: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+/// 直通播放配置
+#[derive(Clone)]
+struct LiveOptions {
+    /// 可选码流（第一路为默认码流）
+    renditions: Arc<Vec<LiveRendition>>,
+    /// 直通播放音频文件
+    audio: Option<PathBuf>,
+    /// 平台不可达时缓存直通分片（None 表示不缓存）
+    spool: Option<Arc<SegmentSpool>>,
+}
+
+/// 进行中的直通播放（会话ID → 控制通道）
+type LiveStreams = Arc<Mutex<HashMap<uuid::Uuid, LiveStreamControl>>>;
+
+/// 直通播放任务的控制通道，移除后任务随之结束
+struct LiveStreamControl {
+    /// 码流切换
+    rendition: watch::Sender<u8>,
+    /// 关键帧请求
+    keyframe: Arc<Notify>,
 }
 
 impl DeviceService {
@@ -61,6 +94,17 @@ impl DeviceService {
         
         // 初始化文件读取器
         let file_reader = Arc::new(DefaultFileStreamReader::new());
+
+        // 初始化断网缓存
+        let spool = config.spool_dir.as_ref().and_then(|dir| {
+            match SegmentSpool::open(dir, config.spool_max_bytes) {
+                Ok(spool) => Some(Arc::new(spool)),
+                Err(e) => {
+                    warn!("Failed to open spool {:?}: {}, live segments will be lost during outages", dir, e);
+                    None
+                }
+            }
+        });
         
         info!("✓ DeviceService initialized with configuration:");
         info!("  - Keyframe index strategy: {:?}", config.keyframe_index_strategy);
@@ -77,7 +121,102 @@ impl DeviceService {
             timeline_manager,
             ffmpeg_parser,
             file_reader,
+            live: LiveOptions {
+                renditions: Arc::new(config.live_renditions),
+                audio: config.live_audio,
+                spool,
+            },
+            live_streams: Arc::new(Mutex::new(HashMap::new())),
+            pending_backups: Arc::new(Mutex::new(config.backup_files)),
+        }
+    }
+
+    /// 启动后台上传：先补传断网期间缓存的断档录像，再上传待备份的录像
+    ///
+    /// 连接中断时任务随之结束，重连后重新启动即可从平台确认的偏移续传。
+    fn spawn_background_uploads(&self) -> Option<tokio::task::JoinHandle<()>> {
+        // 连接已恢复：封存当前中断的缓存，仍在缓存的直通任务随之结束
+        let spool = self.live.spool.clone();
+        if let Some(spool) = &spool {
+            if let Err(e) = spool.seal() {
+                warn!("Failed to seal spool journal: {}", e);
+            }
+        }
+        let has_gaps = spool
+            .as_ref()
+            .is_some_and(|spool| spool.sealed_journals().is_ok_and(|journals| !journals.is_empty()));
+        if !has_gaps && self.pending_backups.lock().unwrap().is_empty() {
+            return None;
+        }
+        let Some(uploader) = self.client.file_uploader() else {
+            warn!("File upload unavailable on this connection, gap back-fill and recording backup postponed");
+            return None;
+        };
+        let pending = self.pending_backups.clone();
+
+        Some(tokio::spawn(async move {
+            if let Some(spool) = spool {
+                if !Self::backfill_spool(&uploader, &spool).await {
+                    return;
+                }
+            }
+
+            loop {
+                let Some(path) = pending.lock().unwrap().first().cloned() else {
+                    break;
+                };
+                match uploader.upload(&path).await {
+                    Ok(()) => info!("✓ Recording backed up: {:?}", path),
+                    // 连接问题：保留文件，重连后续传
+                    Err(VideoStreamError::QuicError(e)) => {
+                        warn!("Backup upload of {:?} interrupted: {}", path, e);
+                        break;
+                    }
+                    Err(e) => error!("Backup upload of {:?} failed, skipping: {}", path, e),
+                }
+                pending.lock().unwrap().retain(|pending_path| pending_path != &path);
+            }
+        }))
+    }
+
+    /// 把断网缓存整理成断档录像上传到平台，连接中断时返回 false
+    async fn backfill_spool(uploader: &FileUploader, spool: &SegmentSpool) -> bool {
+        let journals = match spool.sealed_journals() {
+            Ok(journals) => journals,
+            Err(e) => {
+                warn!("Failed to list spool journals: {}", e);
+                return true;
+            }
+        };
+
+        for journal in journals {
+            let recording = match spool.build_recording(&journal) {
+                Ok(recording) => recording,
+                Err(e) => {
+                    error!("Failed to build gap recording from {:?}: {}", journal, e);
+                    continue;
+                }
+            };
+            if let Some(path) = &recording {
+                match uploader.upload(path).await {
+                    Ok(()) => info!("✓ Gap recording back-filled: {:?}", path),
+                    Err(VideoStreamError::QuicError(e)) => {
+                        warn!("Gap back-fill of {:?} interrupted: {}", path, e);
+                        return false;
+                    }
+                    Err(e) => {
+                        // 保留日志，下次重连再试
+                        error!("Gap back-fill of {:?} failed: {}", path, e);
+                        let _ = spool.discard_recording(path);
+                        continue;
+                    }
+                }
+            }
+            if let Err(e) = spool.remove(&journal, recording.as_deref()) {
+                warn!("Failed to remove spool journal {:?}: {}", journal, e);
+            }
         }
+        true
     }
 
     fn spawn_control_message_handler(&self) -> tokio::task::JoinHandle<()> {
@@ -88,9 +227,21 @@ impl DeviceService {
             .clone();
         let video_dir = self.video_dir.clone();
         let device_id = self.device_id.clone();
+        let encoder = self.client.segment_encoder();
+        let live = self.live.clone();
+        let live_streams = self.live_streams.clone();
 
         tokio::spawn(async move {
-            if let Err(e) = Self::handle_control_messages(conn, video_dir, device_id).await {
+            if let Err(e) = Self::handle_control_messages(
+                conn,
+                video_dir,
+                device_id,
+                encoder,
+                live,
+                live_streams,
+            )
+            .await
+            {
                 error!("Control message handler error: {}", e);
             }
         })
@@ -99,6 +250,8 @@ impl DeviceService {
     pub async fn run(mut self) -> Result<()> {
         // 启动控制消息处理任务
         let mut control_task_handle = self.spawn_control_message_handler();
+        // 启动后台上传任务（断档录像补传、录像备份）
+        let mut upload_task_handle = self.spawn_background_uploads();
 
         // 启动心跳任务
         let mut heartbeat_interval = tokio::time::interval(tokio::time::Duration::from_secs(10));
@@ -113,8 +266,11 @@ impl DeviceService {
                 warn!("Connection lost, attempting to reconnect...");
                 reconnect_attempts += 1;
 
-                // 取消旧的控制消息处理任务
+                // 取消旧的控制消息处理和后台上传任务
                 control_task_handle.abort();
+                if let Some(handle) = upload_task_handle.take() {
+                    handle.abort();
+                }
 
                 // 计算重连延迟：指数退避，最大10秒
                 // 延迟序列：1s, 2s, 4s, 8s, 10s, 10s, ...
@@ -139,6 +295,8 @@ impl DeviceService {
                         // 重新启动控制消息处理任务
                         control_task_handle = self.spawn_control_message_handler();
                         info!("✓ Control message handler restarted");
+                        // 补传断网期间缓存的分片，未完成的上传从平台确认的偏移续传
+                        upload_task_handle = self.spawn_background_uploads();
                     }
                     Err(e) => {
                         warn!(
@@ -173,6 +331,9 @@ impl DeviceService {
         connection: quinn::Connection,
         video_dir: std::path::PathBuf,
         device_id: String,
+        encoder: SegmentEncoder,
+        live: LiveOptions,
+        live_streams: LiveStreams,
     ) -> Result<()> {
         loop {
             match connection.accept_bi().await {
@@ -180,13 +341,41 @@ impl DeviceService {
                     let dir = video_dir.clone();
                     let dev_id = device_id.clone();
                     let conn = connection.clone();
+                    let encoder = encoder.clone();
+                    let live = live.clone();
+                    let live_streams = live_streams.clone();
                     tokio::spawn(async move {
                         match recv.read_to_end(1024 * 1024).await {
                             Ok(buf) => {
+                                // 记录接收时间（时钟同步 t1）
+                                let received_at_us = current_timestamp_us();
                                 if let Ok(msg) = bincode::deserialize::<ProtocolMessage>(&buf) {
                                     debug!("Received control message: {:?}", msg.message_type);
 
                                     match msg.message_type {
+                                        MessageType::ClockSync => {
+                                            if let Ok(req) = bincode::deserialize::<ClockSyncRequest>(&msg.payload) {
+                                                let response = ClockSyncResponse {
+                                                    t0_us: req.t0_us,
+                                                    t1_us: received_at_us,
+                                                    t2_us: current_timestamp_us(),
+                                                };
+                                                if let Ok(response_data) = bincode::serialize(&response) {
+                                                    let response_msg = ProtocolMessage {
+                                                        message_type: MessageType::ClockSync,
+                                                        payload: response_data,
+                                                        sequence_number: msg.sequence_number,
+                                                        timestamp: SystemTime::now(),
+                                                        session_id: msg.session_id,
+                                                    };
+
+                                                    if let Ok(data) = bincode::serialize(&response_msg) {
+                                                        let _ = send.write_all(&data).await;
+                                                        let _ = send.finish().await;
+                                                    }
+                                                }
+                                            }
+                                        }
                                         MessageType::FileListQuery => {
                                             info!("📋 Received file list query");
                                             // 动态扫描视频目录
@@ -194,7 +383,7 @@ impl DeviceService {
                                             info!("📂 Found {} video file(s) in directory", files.len());
                                             
                                             if let Ok(response) =
-                                                Self::build_file_list_response(&files, &dev_id)
+                                                Self::build_file_list_response(&files, &dev_id, encoder.version())
                                             {
                                                 let response_msg = ProtocolMessage {
                                                     message_type: MessageType::FileListResponse,
@@ -234,6 +423,7 @@ impl DeviceService {
                                                         conn_clone,
                                                         file_req,
                                                         msg.session_id,
+                                                        encoder,
                                                     )
                                                     .await
                                                     {
@@ -253,10 +443,12 @@ impl DeviceService {
                                                 target_latency_ms: 100,
                                                 target_fps: 30,
                                                 target_bitrate: 2_000_000, // 2 Mbps
+                                                rendition_id: None,
                                             });
                                             
                                             info!("  FPS: {}", request.target_fps);
                                             info!("  Bitrate: {} Mbps", request.target_bitrate / 1_000_000);
+                                            info!("  Rendition: {:?}", request.rendition_id);
                                             
                                             // 发送确认响应
                                             let _ = send.write_all(b"OK").await;
@@ -269,6 +461,9 @@ impl DeviceService {
                                                     conn_clone,
                                                     request,
                                                     msg.session_id,
+                                                    encoder,
+                                                    live,
+                                                    live_streams,
                                                 )
                                                 .await
                                                 {
@@ -278,11 +473,50 @@ impl DeviceService {
                                         }
                                         MessageType::StopLiveStream => {
                                             info!("⏹️ Received stop live stream request");
-                                            // 停止逻辑通过关闭 receiver 通道自动实现
-                                            // 当前端停止接收时，发送任务会自动结束
+                                            // 移除码流切换通道，直通播放任务随之结束
+                                            live_streams.lock().unwrap().remove(&msg.session_id);
                                             let _ = send.write_all(b"OK").await;
                                             let _ = send.finish().await;
                                         }
+                                        MessageType::SwitchRendition => {
+                                            let accepted = match bincode::deserialize::<common::SwitchRenditionRequest>(&msg.payload) {
+                                                Ok(req) if live.renditions.iter().any(|r| r.info.id == req.rendition_id) => {
+                                                    info!("🔀 Received switch rendition request: #{} (session: {})", req.rendition_id, msg.session_id);
+                                                    live_streams
+                                                        .lock()
+                                                        .unwrap()
+                                                        .get(&msg.session_id)
+                                                        .is_some_and(|control| control.rendition.send(req.rendition_id).is_ok())
+                                                }
+                                                Ok(req) => {
+                                                    warn!("Unknown rendition requested: #{}", req.rendition_id);
+                                                    false
+                                                }
+                                                Err(e) => {
+                                                    warn!("Invalid switch rendition request: {}", e);
+                                                    false
+                                                }
+                                            };
+                                            let reply: &[u8] = if accepted { b"OK" } else { b"REJECTED" };
+                                            let _ = send.write_all(reply).await;
+                                            let _ = send.finish().await;
+                                        }
+                                        MessageType::KeyframeRequest => {
+                                            let control = live_streams.lock().unwrap().get(&msg.session_id).map(|c| c.keyframe.clone());
+                                            let reply: &[u8] = match control {
+                                                Some(keyframe) => {
+                                                    debug!("🔑 Received keyframe request (session: {})", msg.session_id);
+                                                    keyframe.notify_one();
+                                                    b"OK"
+                                                }
+                                                None => {
+                                                    warn!("Keyframe request for unknown live session: {}", msg.session_id);
+                                                    b"REJECTED"
+                                                }
+                                            };
+                                            let _ = send.write_all(reply).await;
+                                            let _ = send.finish().await;
+                                        }
                                         MessageType::SeekToKeyframe => {
                                             info!("⏩ Received seek to keyframe request");
                                             if let Ok(seek_req) = bincode::deserialize::<common::SeekToKeyframeRequest>(&msg.payload) {
@@ -382,6 +616,7 @@ impl DeviceService {
     fn build_file_list_response(
         video_files: &[VideoFile],
         device_id: &str,
+        version: common::ProtocolVersion,
     ) -> Result<Vec<u8>> {
         let recordings: Vec<RecordingInfo> = video_files
             .iter()
@@ -402,8 +637,11 @@ impl DeviceService {
                     duration: 10.0, // 估算
                     format: match vf.format {
                         VideoFormat::H264 => "h264".to_string(),
+                        VideoFormat::H265 => "h265".to_string(),
                         VideoFormat::MP4 => "mp4".to_string(),
+                        VideoFormat::MpegTs => "ts".to_string(),
                     },
+                    codec: vf.codec,
                     resolution: "1280x720".to_string(),
                     bitrate: 5_000_000,
                     frame_rate: 60.0,
@@ -413,17 +651,18 @@ impl DeviceService {
             })
             .collect();
 
-        let response = FileListResponse { files: recordings };
-        bincode::serialize(&response)
-            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))
+        // 1.4 之前的平台不认识录像信息中的编码字段
+        FileListResponse { files: recordings }.encode(version)
     }
 
     async fn handle_playback_request(
         connection: quinn::Connection,
         file_req: common::FileRequest,
         session_id: uuid::Uuid,
+        encoder: SegmentEncoder,
     ) -> Result<()> {
-        use crate::video::{LiveStreamGeneratorFile, VideoFileReader, VideoFormat};
+        use crate::video::{LiveStreamGeneratorFile, RecordingFrames, VideoFileReader};
+        use common::demux::Container;
         
         info!("🎬 Starting playback for: {} (session: {})", file_req.file_path, session_id);
 
@@ -451,14 +690,39 @@ impl DeviceService {
                   index.entries.len(), index.total_duration);
         }
 
-        // 检测文件格式
-        let reader = VideoFileReader::new(&file_path).await?;
-        let is_h264 = matches!(reader.format(), VideoFormat::H264);
-        drop(reader);
+        // 快进超过4x或倒放只发送关键帧，指定起点或 MP4 从关键帧开始逐帧发送，都需要访问单元表；
+        // 裸流从头播放时由生成器边读边切分，不必预先扫描整个文件
+        let strategy = DefaultPlaybackController::new().get_drop_frame_strategy(file_req.playback_rate);
+        let container = RecordingFrames::probe(&file_path).await?;
+        let indexed = container.filter(|&container| {
+            strategy.keep_key_frames_only || file_req.seek_position.is_some() || container != Container::AnnexB
+        });
+        let mut recording = match indexed {
+            Some(container) => Some(RecordingFrames::open(&file_path, container, 30.0).await?), // 默认 30fps
+            None => None,
+        };
+        let frame_aligned = match &recording {
+            Some(recording) => !recording.units.is_empty(),
+            None => container.is_some(),
+        };
+
+        // 会话的分片都通过同一条媒体流发送
+        let mut media = MediaSender::new(connection, encoder);
+
+        if let Some(recording) = recording.as_mut().filter(|_| frame_aligned) {
+            if strategy.keep_key_frames_only {
+                Self::stream_keyframes_only(&mut media, recording, &file_req, session_id).await?;
+                return media.finish().await;
+            }
+            if file_req.seek_position.is_some() || container == Some(Container::Mp4) {
+                Self::stream_from_position(&mut media, recording, &file_req, session_id).await?;
+                return media.finish().await;
+            }
+        }
 
-        if is_h264 {
-            // H.264 文件：使用 LiveStreamGeneratorFile 按 NAL unit 分割
-            info!("📹 H.264 file detected, using NAL unit streaming");
+        if frame_aligned {
+            // 裸流文件：使用 LiveStreamGeneratorFile 按访问单元分片
+            info!("📹 {} is frame aligned, using access unit streaming", file_name);
             
             let mut generator = LiveStreamGeneratorFile::new(
                 session_id,
@@ -473,67 +737,36 @@ impl DeviceService {
             info!("📤 Streaming H.264 file to platform...");
             let mut segment_count = 0;
             
-            while let Some(segment) = receiver.recv().await {
-                match connection.open_uni().await {
-                    Ok(mut stream) => {
-                        let data = bincode::serialize(&segment)
-                            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
-                        
-                        if let Err(e) = stream.write_all(&data).await {
-                            error!("Failed to write segment: {}", e);
-                            break;
-                        }
-                        
-                        if let Err(e) = stream.finish().await {
-                            error!("Failed to finish stream: {}", e);
-                            break;
-                        }
-                        
-                        segment_count += 1;
-                        if segment_count % 100 == 0 {
-                            info!("📦 Sent {} H.264 segments", segment_count);
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to open stream: {}", e);
-                        break;
-                    }
+            while let Some(mut segment) = receiver.recv().await {
+                if let Err(e) = media.send(&mut segment).await {
+                    error!("Failed to send segment: {}", e);
+                    break;
+                }
+
+                segment_count += 1;
+                if segment_count % 100 == 0 {
+                    info!("📦 Sent {} H.264 segments", segment_count);
                 }
             }
             
             info!("✓ H.264 playback completed: {} segments sent", segment_count);
         } else {
-            // MP4 或其他格式：使用简单的块读取
-            info!("📹 MP4/other format detected, using chunk streaming");
+            // 无法解析访问单元的格式：使用简单的块读取
+            info!("📹 Unrecognized format, using chunk streaming");
             
             let mut reader = VideoFileReader::new(&file_path).await?;
-            let mut timestamp = file_req.seek_position.unwrap_or(0.0);
+            let mut timestamp = VideoSegment::ticks(file_req.seek_position.unwrap_or(0.0));
             let mut segment_count = 0;
 
             info!("📤 Streaming file to platform...");
 
             while let Some(chunk) = reader.read_chunk().await? {
-                let mut segment = VideoSegment::new(chunk.clone(), timestamp, segment_count % 30 == 0);
+                let mut segment = VideoSegment::new(chunk.clone(), timestamp, CHUNK_DURATION, segment_count % 30 == 0);
                 segment.session_id = session_id;
-
-                let mut stream = connection.open_uni().await.map_err(|e| {
-                    VideoStreamError::QuicError(format!("Failed to open stream: {}", e))
-                })?;
-
-                let data = bincode::serialize(&segment)
-                    .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
-
-                stream
-                    .write_all(&data)
-                    .await
-                    .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
-                stream
-                    .finish()
-                    .await
-                    .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+                media.send(&mut segment).await?;
 
                 segment_count += 1;
-                timestamp += 0.033; // ~30fps
+                timestamp += CHUNK_DURATION as i64;
 
                 // 控制发送速率
                 tokio::time::sleep(tokio::time::Duration::from_millis(
@@ -544,74 +777,295 @@ impl DeviceService {
 
             info!("✓ Playback completed: {} segments sent", segment_count);
         }
-        
+
+        media.finish().await
+    }
+    
+    /// 快进/倒放：按关键帧时间表只发送关键帧，时间戳重写为连续的扫描时间线
+    async fn stream_keyframes_only(
+        media: &mut MediaSender,
+        recording: &mut crate::video::RecordingFrames,
+        file_req: &common::FileRequest,
+        session_id: uuid::Uuid,
+    ) -> Result<()> {
+        use common::trick_play::TrickPlayScheduler;
+
+        let keyframes = recording.keyframe_index();
+        if keyframes.is_empty() {
+            return Err(VideoStreamError::ProtocolError(format!("No keyframes in {}", file_req.file_path)));
+        }
+
+        let rate = file_req.playback_rate;
+        // 倒放未指定起点时从文件末尾开始
+        let position = file_req
+            .seek_position
+            .unwrap_or(if rate < 0.0 { f64::MAX } else { 0.0 });
+        let times = keyframes.iter().map(|k| k.timestamp).collect();
+        let mut scheduler = TrickPlayScheduler::new(times, rate, position, 0.0);
+
+        info!("⏩ Streaming keyframes only at {}x ({} keyframes)", rate, keyframes.len());
+        let mut segment_count = 0;
+
+        while let Some(frame) = scheduler.next_frame() {
+            let entry = &keyframes[frame.index];
+            let unit = recording.read_frame(entry.file_offset, entry.frame_size).await?;
+
+            let duration = VideoSegment::ticks(frame.duration) as u32;
+            let mut segment = VideoSegment::new(unit, VideoSegment::ticks(frame.timestamp), duration, true);
+            segment.session_id = session_id;
+            segment.codec = recording.codec;
+            media.send(&mut segment).await?;
+
+            segment_count += 1;
+            tokio::time::sleep(tokio::time::Duration::from_secs_f64(frame.duration)).await;
+        }
+
+        info!("✓ Keyframe-only playback completed: {} keyframes sent", segment_count);
         Ok(())
     }
     
+    /// 从指定位置开始逐帧发送
+    ///
+    /// 总是从目标之前的关键帧开始。精确定位时关键帧到目标帧之间的帧标记为 `DECODE_ONLY`
+    /// 并立即发送，播放器从目标帧开始显示；否则从关键帧开始正常播放。
+    async fn stream_from_position(
+        media: &mut MediaSender,
+        recording: &mut crate::video::RecordingFrames,
+        file_req: &common::FileRequest,
+        session_id: uuid::Uuid,
+    ) -> Result<()> {
+        use common::h264::plan_accurate_seek;
+
+        let units = std::mem::take(&mut recording.units);
+        let position = file_req.seek_position.unwrap_or(0.0);
+        let plan = plan_accurate_seek(&units, position)
+            .ok_or_else(|| VideoStreamError::ProtocolError(format!("No keyframes in {}", file_req.file_path)))?;
+        let preroll = if file_req.accurate_seek { plan.preroll() } else { plan.keyframe..plan.keyframe };
+
+        info!(
+            "📍 Streaming from {:.3}s (keyframe at {:.3}s, {} pre-roll frames)",
+            position,
+            units[plan.keyframe].timestamp,
+            preroll.len()
+        );
+        let mut segment_count = 0;
+
+        for (index, unit) in units.iter().enumerate().skip(plan.keyframe) {
+            let unit_data = recording.read_frame(unit.offset, unit.size).await?;
+            let duration = VideoSegment::ticks(unit.duration) as u32;
+            let mut segment = VideoSegment::new(unit_data, VideoSegment::ticks(unit.timestamp), duration, unit.is_keyframe);
+            segment.dts = VideoSegment::ticks(unit.decode_timestamp);
+            segment.session_id = session_id;
+            segment.codec = recording.codec;
+            let decode_only = preroll.contains(&index);
+            if decode_only {
+                segment.flags |= SegmentFlags::DECODE_ONLY;
+            }
+            media.send(&mut segment).await?;
+
+            segment_count += 1;
+            // 预解码帧不节流
+            if !decode_only {
+                tokio::time::sleep(tokio::time::Duration::from_secs_f64(unit.duration / file_req.playback_rate)).await;
+            }
+        }
+
+        info!("✓ Playback from position completed: {} frames sent", segment_count);
+        Ok(())
+    }
+
     async fn handle_live_stream_request(
         connection: quinn::Connection,
         request: common::StartLiveStreamRequest,
         session_id: uuid::Uuid,
+        encoder: SegmentEncoder,
+        live: LiveOptions,
+        live_streams: LiveStreams,
     ) -> Result<()> {
         use crate::video::LiveStreamGeneratorFile;
-        
+
+        let LiveOptions { renditions, audio: live_audio, spool } = live;
+
+        // 未指定码流或码流不存在时使用第一路（主码流）
+        let mut rendition = request
+            .rendition_id
+            .and_then(|id| renditions.iter().find(|r| r.info.id == id))
+            .or_else(|| renditions.first())
+            .ok_or_else(|| VideoStreamError::ProtocolError("No live rendition configured".to_string()))?
+            .clone();
+
         info!("🎬 Starting live stream (session: {})", session_id);
         info!("  FPS: {}", request.target_fps);
         info!("  Bitrate: {} Mbps", request.target_bitrate / 1_000_000);
+        info!("  Rendition: #{} {}", rendition.info.id, rendition.info.name);
         
-        // 使用H.264裸流文件
-        let h264_file = std::path::PathBuf::from("test-videos/sample_720p_60fps.h264");
-        
-        // 创建实时流生成器（从文件读取）
-        let mut generator = LiveStreamGeneratorFile::new(
+        let (switch_tx, mut switch_rx) = watch::channel(rendition.info.id);
+        let keyframe = Arc::new(Notify::new());
+        live_streams.lock().unwrap().insert(
             session_id,
-            request.target_fps,
-            request.target_bitrate,
-            h264_file,
-        ).map_err(|e| VideoStreamError::QuicError(format!("Failed to create generator: {}", e)))?;
-        
-        // 启动流
-        let mut receiver = generator.start_streaming().await
-            .map_err(|e| VideoStreamError::QuicError(format!("Failed to start streaming: {}", e)))?;
-        
-        info!("📤 Streaming live video to platform...");
+            LiveStreamControl {
+                rendition: switch_tx,
+                keyframe: Arc::clone(&keyframe),
+            },
+        );
         
+        // 音频独立于码流切换，时间戳与首个视频分片同从 0 开始
+        let mut audio_generator = match live_audio.filter(|_| encoder.supports(FeatureFlags::AUDIO)) {
+            Some(path) => Self::start_live_audio(&connection, &encoder, session_id, &path).await,
+            None => None,
+        };
+
+        let mut media = MediaSender::new(connection, encoder);
         let mut segment_count = 0;
+        // 切换码流后时间戳从上一路的末尾继续
+        let mut timestamp_offset = 0i64;
+        // 平台不可达时的中断编号，分片改写入磁盘缓存
+        let mut outage: Option<u64> = None;
         
-        // 接收并发送分片
-        while let Some(segment) = receiver.recv().await {
-            // 通过QUIC单向流发送分片
-            match connection.open_uni().await {
-                Ok(mut stream) => {
-                    let data = bincode::serialize(&segment)
-                        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
-                    
-                    if let Err(e) = stream.write_all(&data).await {
-                        error!("Failed to write segment: {}", e);
-                        break;
+        'rendition: loop {
+            // 创建实时流生成器（从当前码流的文件读取）
+            // 新生成器总是以 SPS+PPS+IDR 开始，保证切换后的第一个分片是关键帧
+            let mut generator = LiveStreamGeneratorFile::new(
+                session_id,
+                request.target_fps,
+                rendition.info.bitrate as usize,
+                &rendition.file,
+            ).map_err(|e| VideoStreamError::QuicError(format!("Failed to create generator: {}", e)))?;
+            generator.set_low_latency(request.quality_preference != "high_quality");
+            
+            // 启动流
+            let mut receiver = generator.start_streaming().await
+                .map_err(|e| VideoStreamError::QuicError(format!("Failed to start streaming: {}", e)))?;
+            
+            info!("📤 Streaming rendition #{} to platform...", rendition.info.id);
+            let mut next_timestamp = timestamp_offset;
+            
+            loop {
+                tokio::select! {
+                    changed = switch_rx.changed() => {
+                        if changed.is_err() {
+                            // 切换通道已移除（收到 StopLiveStream）
+                            generator.stop_streaming();
+                            break 'rendition;
+                        }
+                        let target = *switch_rx.borrow_and_update();
+                        if target == rendition.info.id {
+                            continue;
+                        }
+                        if let Some(next) = renditions.iter().find(|r| r.info.id == target) {
+                            info!(
+                                "🔀 Switching rendition #{} -> #{} (session: {})",
+                                rendition.info.id, target, session_id
+                            );
+                            generator.stop_streaming();
+                            rendition = next.clone();
+                            timestamp_offset = next_timestamp;
+                            continue 'rendition;
+                        }
                     }
-                    
-                    if let Err(e) = stream.finish().await {
-                        error!("Failed to finish stream: {}", e);
-                        break;
+                    _ = keyframe.notified() => {
+                        generator.request_keyframe();
                     }
-                    
-                    segment_count += 1;
-                    
-                    if segment_count % 30 == 0 {
-                        debug!("📤 Sent {} segments", segment_count);
+                    segment = receiver.recv() => {
+                        let Some(mut segment) = segment else {
+                            break 'rendition;
+                        };
+                        segment.rendition_id = rendition.info.id;
+                        segment.shift_timestamps(timestamp_offset);
+                        next_timestamp = segment.dts + segment.duration as i64;
+
+                        // 中断期间写入缓存，连接恢复（缓存封存）后结束
+                        if let (Some(id), Some(spool)) = (outage, &spool) {
+                            match spool.append(id, &segment) {
+                                Ok(SpoolWrite::Sealed) => break 'rendition,
+                                Ok(_) => {}
+                                Err(e) => {
+                                    error!("Failed to journal live segment: {}", e);
+                                    break 'rendition;
+                                }
+                            }
+                            continue;
+                        }
+                        
+                        // 通过会话媒体流发送分片
+                        if let Err(e) = media.send(&mut segment).await {
+                            error!("Failed to send live segment: {}", e);
+                            let Some(spool) = &spool else {
+                                break 'rendition;
+                            };
+                            let id = spool.outage();
+                            outage = Some(id);
+                            if let Err(e) = spool.append(id, &segment) {
+                                error!("Failed to journal live segment: {}", e);
+                                break 'rendition;
+                            }
+                            continue;
+                        }
+
+                        segment_count += 1;
+                        if segment_count % 30 == 0 {
+                            debug!("📤 Sent {} segments", segment_count);
+                        }
                     }
                 }
-                Err(e) => {
-                    error!("Failed to open uni stream: {}", e);
-                    break;
-                }
             }
         }
         
+        if let Some(generator) = audio_generator.as_mut() {
+            generator.stop_streaming();
+        }
+        live_streams.lock().unwrap().remove(&session_id);
         info!("✓ Live stream completed: {} segments sent", segment_count);
+        if outage.is_none() {
+            media.finish().await?;
+        }
         Ok(())
     }
+
+    /// 启动直通播放音频，失败时只记录警告，视频照常发送
+    async fn start_live_audio(
+        connection: &quinn::Connection,
+        encoder: &SegmentEncoder,
+        session_id: uuid::Uuid,
+        path: &std::path::Path,
+    ) -> Option<crate::video::LiveAudioGeneratorFile> {
+        let mut generator = match crate::video::LiveAudioGeneratorFile::new(session_id, path) {
+            Ok(generator) => generator,
+            Err(e) => {
+                warn!("Failed to create audio generator: {}", e);
+                return None;
+            }
+        };
+        match generator.start_streaming().await {
+            Ok(receiver) => {
+                tokio::spawn(Self::send_live_audio(MediaSender::new(connection.clone(), encoder.clone()), receiver));
+                Some(generator)
+            }
+            Err(e) => {
+                warn!("Failed to start live audio: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 通过会话的音频流发送直通播放的音频分片（分片 stream_type 为音频）
+    async fn send_live_audio(
+        mut media: MediaSender,
+        mut receiver: tokio::sync::mpsc::Receiver<VideoSegment>,
+    ) {
+        let mut segment_count = 0u64;
+        while let Some(mut segment) = receiver.recv().await {
+            if let Err(e) = media.send(&mut segment).await {
+                error!("Failed to send audio segment: {}", e);
+                return;
+            }
+            segment_count += 1;
+        }
+        if let Err(e) = media.finish().await {
+            warn!("Failed to finish audio stream: {}", e);
+        }
+        info!("✓ Live audio completed: {} segments sent", segment_count);
+    }
     
     /// 加载或构建关键帧索引
     async fn load_or_build_keyframe_index(video_path: &PathBuf) -> Option<KeyframeIndex> {
@@ -802,14 +1256,16 @@ impl DeviceService {
     async fn handle_set_playback_speed(
         request: common::SetPlaybackSpeedRequest,
     ) -> common::SetPlaybackSpeedResponse {
-        // 验证播放速率范围
-        if request.speed < 0.25 || request.speed > 4.0 {
+        // 验证播放速率范围（负数为倒放）
+        if !common::trick_play::is_valid_rate(request.speed as f64) {
             return common::SetPlaybackSpeedResponse {
                 speed: request.speed,
                 success: false,
                 error_message: Some(format!(
-                    "Invalid playback speed: {}. Must be between 0.25 and 4.0",
-                    request.speed
+                    "Invalid playback speed: {}. Must be between {} and {} (negative for reverse)",
+                    request.speed,
+                    common::trick_play::MIN_RATE,
+                    common::trick_play::MAX_TRICK_PLAY_RATE
                 )),
             };
         }
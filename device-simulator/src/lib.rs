@@ -2,5 +2,6 @@
 pub mod config;
 pub mod device_service;
 pub mod quic;
+pub mod spool;
 pub mod uploader;
 pub mod video;
@@ -1,5 +1,6 @@
 mod config;
 mod quic;
+mod spool;
 mod video;
 mod uploader;
 mod device_service;
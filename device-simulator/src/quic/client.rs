@@ -1,12 +1,23 @@
+use super::media::{MediaSender, UPLOAD_STREAM_PRIORITY};
 use crate::config::Config;
 use common::{
-    DeviceCapabilities, DeviceType, MessageType, ProtocolMessage, VideoSegment, Result,
-    VideoStreamError,
+    DeviceCapabilities, DeviceType, FeatureFlags, FileUploadAck, FileUploadChunk, FileUploadOffer,
+    FileUploadStatus, MessageType, NegotiatedProtocol,
+    ProtocolMessage, ProtocolVersion, ProtocolVersionRange, SessionStartRequest, SessionStartResponse,
+    VideoSegment, Result, VideoStreamError,
 };
 use quinn::{ClientConfig, Connection, Endpoint};
+use common::utils::current_timestamp_us;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::SeekFrom;
 use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::SystemTime;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tracing::{debug, info};
 use uuid::Uuid;
 
@@ -15,6 +26,146 @@ pub struct QuicClient {
     connection: Option<Connection>,
     config: Config,
     session_id: Uuid,
+    protocol: NegotiatedProtocol,
+    sequence: Arc<AtomicU64>,
+    /// `send_segment` 使用的媒体发送器（随连接重建）
+    media: Option<MediaSender>,
+}
+
+/// 分片编码器
+///
+/// 按协商的协议版本编码分片，并在发送前填写设备发送时间和单调递增的序号。
+#[derive(Clone)]
+pub struct SegmentEncoder {
+    protocol: NegotiatedProtocol,
+    sequence: Arc<AtomicU64>,
+}
+
+impl SegmentEncoder {
+    pub fn encode(&self, segment: &mut VideoSegment) -> Result<Vec<u8>> {
+        segment.sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        segment.send_time_us = current_timestamp_us();
+        common::encode_segment(self.protocol.version, segment)
+    }
+
+    /// 协商的协议版本
+    pub fn version(&self) -> ProtocolVersion {
+        self.protocol.version
+    }
+
+    /// 平台是否接受指定特性（协商结果）
+    pub fn supports(&self, feature: u32) -> bool {
+        self.protocol.supports(feature)
+    }
+}
+
+/// 整文件上传器
+///
+/// 平台确认的偏移即续传点：连接中断后用新连接的上传器再次上传同一文件即可从断点继续。
+#[derive(Clone)]
+pub struct FileUploader {
+    connection: Connection,
+    session_id: Uuid,
+}
+
+impl FileUploader {
+    /// 把整个录像文件上传到平台存储（`storage_root/<device_id>/`）
+    pub async fn upload(&self, path: &Path) -> Result<()> {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| VideoStreamError::InvalidParameter(format!("Invalid upload path: {:?}", path)))?
+            .to_string();
+        let mut file = File::open(path).await?;
+        let total_size = file.metadata().await?.len();
+        let sha256 = sha256_file(&mut file).await?;
+
+        let offer = FileUploadOffer {
+            file_name: file_name.clone(),
+            total_size,
+            sha256,
+        };
+        let mut ack = self.upload_request(MessageType::FileUploadOffer, &offer).await?;
+        if ack.acked_offset > 0 && ack.status == FileUploadStatus::InProgress {
+            info!("Resuming upload of {} at {}/{} bytes", file_name, ack.acked_offset, total_size);
+        }
+
+        let mut buffer = vec![0u8; common::FILE_UPLOAD_CHUNK_SIZE];
+        loop {
+            match ack.status {
+                FileUploadStatus::Complete => {
+                    info!("Uploaded {} ({} bytes)", file_name, total_size);
+                    return Ok(());
+                }
+                FileUploadStatus::InProgress => {}
+                status => {
+                    return Err(VideoStreamError::ProtocolError(format!(
+                        "Upload of {} failed ({:?}): {}",
+                        file_name,
+                        status,
+                        ack.error_message.unwrap_or_default()
+                    )));
+                }
+            }
+
+            let offset = ack.acked_offset;
+            let len = (total_size - offset).min(buffer.len() as u64) as usize;
+            file.seek(SeekFrom::Start(offset)).await?;
+            file.read_exact(&mut buffer[..len]).await?;
+
+            let chunk = FileUploadChunk {
+                upload_id: ack.upload_id,
+                offset,
+                data: buffer[..len].to_vec(),
+            };
+            ack = self.upload_request(MessageType::FileUploadChunk, &chunk).await?;
+            debug!("Upload of {}: {}/{} bytes acknowledged", file_name, ack.acked_offset, total_size);
+        }
+    }
+
+    /// 发送一条上传消息并等待平台确认
+    async fn upload_request<T: Serialize>(&self, message_type: MessageType, body: &T) -> Result<FileUploadAck> {
+        let payload = bincode::serialize(body)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        let message = ProtocolMessage {
+            message_type,
+            payload,
+            sequence_number: 0,
+            timestamp: SystemTime::now(),
+            session_id: self.session_id,
+        };
+        let data = bincode::serialize(&message)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+
+        let (mut send, mut recv) = self
+            .connection
+            .open_bi()
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        // 后台上传让出带宽给直通分片
+        let _ = send.set_priority(UPLOAD_STREAM_PRIORITY);
+        send.write_all(&data)
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        send.finish()
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+
+        let response = recv
+            .read_to_end(64 * 1024)
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        let reply = bincode::deserialize::<ProtocolMessage>(&response)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        if reply.message_type != MessageType::FileUploadAck {
+            return Err(VideoStreamError::ProtocolError(format!(
+                "Unexpected upload response: {:?}",
+                reply.message_type
+            )));
+        }
+        bincode::deserialize::<FileUploadAck>(&reply.payload)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))
+    }
 }
 
 impl QuicClient {
@@ -41,6 +192,9 @@ impl QuicClient {
             connection: None,
             config,
             session_id: Uuid::new_v4(),
+            protocol: NegotiatedProtocol::LEGACY,
+            sequence: Arc::new(AtomicU64::new(0)),
+            media: None,
         })
     }
 
@@ -58,6 +212,7 @@ impl QuicClient {
 
         info!("Connected to platform at {}", server_addr);
         self.connection = Some(connection);
+        self.media = None;
 
         // 发送SessionStart消息
         self.send_session_start().await?;
@@ -75,6 +230,7 @@ impl QuicClient {
     }
 
     pub fn disconnect(&mut self) {
+        self.media = None;
         if let Some(conn) = self.connection.take() {
             conn.close(0u32.into(), b"client disconnect");
         }
@@ -87,26 +243,34 @@ impl QuicClient {
     }
 
     async fn send_session_start(&mut self) -> Result<()> {
-        // 构造设备信息
-        let device_info = common::DeviceInfo {
+        let mut supported_formats = vec!["h264".to_string(), "mp4".to_string(), "ts".to_string()];
+        if self.config.live_audio.is_some() {
+            supported_formats.push("aac".to_string());
+        }
+
+        // 构造会话开始请求（携带支持的协议版本范围和特性）
+        let request = SessionStartRequest {
             device_id: self.config.device_id.clone(),
             device_name: self.config.device_name.clone(),
-            device_type: common::DeviceType::Simulator,
-            connection_status: common::ConnectionStatus::Online,
-            connection_time: SystemTime::now(),
-            last_heartbeat: SystemTime::now(),
-            capabilities: common::DeviceCapabilities {
+            device_type: DeviceType::Simulator,
+            capabilities: DeviceCapabilities {
                 max_resolution: "1920x1080".to_string(),
-                supported_formats: vec!["h264".to_string(), "mp4".to_string()],
+                supported_formats,
                 max_bitrate: 10_000_000,
                 supports_playback_control: true,
                 supports_recording: true,
+                renditions: self
+                    .config
+                    .live_renditions
+                    .iter()
+                    .map(|rendition| rendition.info.clone())
+                    .collect(),
             },
+            protocol_versions: ProtocolVersionRange::supported(),
+            features: FeatureFlags::ALL,
         };
 
-        // 序列化设备信息作为 payload
-        let payload = bincode::serialize(&device_info)
-            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+        let payload = request.encode_payload()?;
 
         let message = ProtocolMessage {
             message_type: MessageType::SessionStart,
@@ -137,37 +301,28 @@ impl QuicClient {
             .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
 
         // 等待响应
-        let mut response: Vec<u8> = Vec::new();
-        recv.read_to_end(1024)
+        let response = recv
+            .read_to_end(64 * 1024)
             .await
             .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
 
-        debug!("Session start response received");
+        self.protocol = parse_session_start_response(&response)?;
+        info!(
+            "Session started with protocol {} (features: {:#x})",
+            self.protocol.version, self.protocol.features
+        );
         Ok(())
     }
 
-    pub async fn send_segment(&mut self, segment: VideoSegment) -> Result<()> {
-        let conn = self
-            .connection
-            .as_ref()
-            .ok_or_else(|| VideoStreamError::ProtocolError("Not connected".to_string()))?;
-
-        let mut stream = conn
-            .open_uni()
-            .await
-            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
-
-        let data = bincode::serialize(&segment)
-            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
-
-        stream
-            .write_all(&data)
-            .await
-            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
-        stream
-            .finish()
-            .await
-            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+    pub async fn send_segment(&mut self, mut segment: VideoSegment) -> Result<()> {
+        if self.media.is_none() {
+            let conn = self
+                .connection
+                .clone()
+                .ok_or_else(|| VideoStreamError::ProtocolError("Not connected".to_string()))?;
+            self.media = Some(MediaSender::new(conn, self.segment_encoder()));
+        }
+        self.media.as_mut().unwrap().send(&mut segment).await?;
 
         debug!("Sent segment: {}", segment.segment_id);
         Ok(())
@@ -215,6 +370,71 @@ impl QuicClient {
     pub fn get_session_id(&self) -> Uuid {
         self.session_id
     }
+
+    /// 获取文件上传器（未连接或平台不接受文件上传时为 None）
+    pub fn file_uploader(&self) -> Option<FileUploader> {
+        if !self.protocol.supports(FeatureFlags::FILE_UPLOAD) {
+            return None;
+        }
+        Some(FileUploader {
+            connection: self.connection.clone()?,
+            session_id: self.session_id,
+        })
+    }
+
+    /// 获取按当前协商协议编码分片的编码器
+    pub fn segment_encoder(&self) -> SegmentEncoder {
+        SegmentEncoder {
+            protocol: self.protocol,
+            sequence: self.sequence.clone(),
+        }
+    }
+}
+
+/// 解析会话开始响应，旧版平台只回复 "OK"
+fn parse_session_start_response(data: &[u8]) -> Result<NegotiatedProtocol> {
+    if data == b"OK" {
+        debug!("Platform does not negotiate protocol version, falling back to 1.0");
+        return Ok(NegotiatedProtocol::LEGACY);
+    }
+
+    let message = bincode::deserialize::<ProtocolMessage>(data)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+    if message.message_type != MessageType::SessionStartResponse {
+        return Err(VideoStreamError::ProtocolError(format!(
+            "Unexpected session start response: {:?}",
+            message.message_type
+        )));
+    }
+
+    let response = bincode::deserialize::<SessionStartResponse>(&message.payload)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+    if !response.accepted {
+        return Err(VideoStreamError::ProtocolError(
+            response
+                .error_message
+                .unwrap_or_else(|| "Protocol version rejected by platform".to_string()),
+        ));
+    }
+
+    Ok(NegotiatedProtocol {
+        version: response.selected_version,
+        features: response.features,
+    })
+}
+
+/// 计算文件的 SHA-256（小写十六进制，与平台校验一致）
+async fn sha256_file(file: &mut File) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
 // 跳过服务器证书验证（仅用于Demo）
@@ -0,0 +1,151 @@
+// 会话媒体发送
+//
+// 平台支持 `MEDIA_STREAM` 时，每个会话的视频和音频各用一条长期单向流发送长度前缀分片，
+// 避免每个分片打开/关闭一条流的开销和并发流数量上限；平台支持 `KEYFRAME_STREAM` 时
+// 关键帧另走一条优先级更高的流；可丢弃的非参考帧在平台支持 `MEDIA_DATAGRAM` 且大小合适
+// 时作为 DATAGRAM 发送。旧平台退回每个分片一条单向流。
+
+use super::SegmentEncoder;
+use common::{
+    encode_media_frame, FeatureFlags, Result, VideoSegment, VideoStreamError, MEDIA_KEYFRAME_STREAM_MAGIC,
+    MEDIA_STREAM_MAGIC,
+};
+use quinn::{Connection, SendStream};
+
+// 流优先级（数值越大越先发送）：控制 > 音频 > 关键帧 > 普通帧 > 后台上传。
+// 控制信令使用默认优先级 0 的双向流。优先级作用于整条流，关键帧要越过已排队的普通帧
+// 只能放在独立的流上，平台再按分片序号把两条流合并回解码顺序。
+
+/// 音频流优先级
+const AUDIO_STREAM_PRIORITY: i32 = -1;
+/// 关键帧流优先级
+const KEYFRAME_STREAM_PRIORITY: i32 = -2;
+/// 普通帧视频流优先级
+const VIDEO_STREAM_PRIORITY: i32 = -3;
+/// 后台文件上传流优先级
+pub(super) const UPLOAD_STREAM_PRIORITY: i32 = -4;
+
+/// 会话媒体发送器
+///
+/// 每个发送任务持有一个；流在第一个分片到达时打开，`finish` 时正常结束。
+pub struct MediaSender {
+    connection: Connection,
+    encoder: SegmentEncoder,
+    video: Option<SendStream>,
+    keyframes: Option<SendStream>,
+    audio: Option<SendStream>,
+}
+
+impl MediaSender {
+    pub fn new(connection: Connection, encoder: SegmentEncoder) -> Self {
+        Self {
+            connection,
+            encoder,
+            video: None,
+            keyframes: None,
+            audio: None,
+        }
+    }
+
+    /// 发送一个分片（发送前由编码器填写发送时间和序号）
+    pub async fn send(&mut self, segment: &mut VideoSegment) -> Result<()> {
+        let data = self.encoder.encode(segment)?;
+        if !self.encoder.supports(FeatureFlags::MEDIA_STREAM) {
+            return self.send_on_new_stream(&data).await;
+        }
+        if self.try_send_datagram(segment, &data) {
+            return Ok(());
+        }
+
+        let frame = encode_media_frame(&data)?;
+        let stream = if segment.is_audio() {
+            self.audio_stream().await?
+        } else if (segment.is_keyframe() || segment.is_codec_config())
+            && self.encoder.supports(FeatureFlags::KEYFRAME_STREAM)
+        {
+            self.keyframe_stream().await?
+        } else {
+            self.video_stream().await?
+        };
+        stream
+            .write_all(&frame)
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))
+    }
+
+    /// 正常结束会话的媒体流
+    pub async fn finish(&mut self) -> Result<()> {
+        for mut stream in [self.video.take(), self.keyframes.take(), self.audio.take()].into_iter().flatten() {
+            stream
+                .finish()
+                .await
+                .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// 非参考帧尝试以 DATAGRAM 发送，不适用或发送失败时返回 false 改走流
+    fn try_send_datagram(&self, segment: &VideoSegment, data: &[u8]) -> bool {
+        if !self.encoder.supports(FeatureFlags::MEDIA_DATAGRAM) || !segment.is_disposable() {
+            return false;
+        }
+        let fits = self.connection.max_datagram_size().is_some_and(|max| data.len() <= max);
+        fits && self.connection.send_datagram(data.to_vec().into()).is_ok()
+    }
+
+    async fn video_stream(&mut self) -> Result<&mut SendStream> {
+        if self.video.is_none() {
+            self.video = Some(self.open_media_stream(VIDEO_STREAM_PRIORITY, MEDIA_STREAM_MAGIC).await?);
+        }
+        Ok(self.video.as_mut().unwrap())
+    }
+
+    async fn keyframe_stream(&mut self) -> Result<&mut SendStream> {
+        if self.keyframes.is_none() {
+            self.keyframes = Some(
+                self.open_media_stream(KEYFRAME_STREAM_PRIORITY, MEDIA_KEYFRAME_STREAM_MAGIC)
+                    .await?,
+            );
+        }
+        Ok(self.keyframes.as_mut().unwrap())
+    }
+
+    async fn audio_stream(&mut self) -> Result<&mut SendStream> {
+        if self.audio.is_none() {
+            self.audio = Some(self.open_media_stream(AUDIO_STREAM_PRIORITY, MEDIA_STREAM_MAGIC).await?);
+        }
+        Ok(self.audio.as_mut().unwrap())
+    }
+
+    async fn open_media_stream(&self, priority: i32, magic: [u8; 4]) -> Result<SendStream> {
+        let mut stream = self
+            .connection
+            .open_uni()
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        let _ = stream.set_priority(priority);
+        stream
+            .write_all(&magic)
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        Ok(stream)
+    }
+
+    /// 旧版平台：每个分片一条单向流
+    async fn send_on_new_stream(&self, data: &[u8]) -> Result<()> {
+        let mut stream = self
+            .connection
+            .open_uni()
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        stream
+            .write_all(data)
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        stream
+            .finish()
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
 mod client;
+mod media;
 
-pub use client::QuicClient;
+pub use client::{FileUploader, QuicClient, SegmentEncoder};
+pub use media::MediaSender;
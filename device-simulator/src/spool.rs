@@ -0,0 +1,332 @@
+// 断网缓存（store-and-forward）
+//
+// 平台不可达时把直通播放的视频分片写入磁盘日志，重连后整理成“断档录像”
+// 以低于直通流量的优先级上传，补齐广域网中断造成的录像缺口。
+
+use common::VideoSegment;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// 正在写入的日志扩展名
+const ACTIVE_EXTENSION: &str = "journal";
+/// 已封存、等待补传的日志扩展名
+const SEALED_EXTENSION: &str = "gap";
+
+/// 写入结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpoolWrite {
+    /// 已写入日志
+    Written,
+    /// 缓存已满或尚未遇到关键帧，分片被丢弃
+    Dropped,
+    /// 此次中断已结束（连接已恢复），应停止写入
+    Sealed,
+}
+
+/// 有界磁盘缓存
+///
+/// 每次中断写一个日志文件，重连时封存；目录总大小（含整理出、尚未删除的断档录像）
+/// 超过上限后丢弃新分片，保证已缓存的录像从关键帧开始连续可解码。
+pub struct SegmentSpool {
+    dir: PathBuf,
+    max_bytes: u64,
+    inner: Mutex<SpoolInner>,
+}
+
+struct SpoolInner {
+    /// 当前日志（路径、写入器、所属会话）
+    journal: Option<(PathBuf, BufWriter<File>, Uuid)>,
+    /// 缓存目录占用的字节数
+    used_bytes: u64,
+    /// 已整理出的断档录像及其大小（删除时从占用量中扣除）
+    recordings: HashMap<PathBuf, u64>,
+    /// 中断编号，封存时递增
+    outage: u64,
+    /// 缓存已满后等待下一次中断重新开始
+    full: bool,
+    /// 本次中断丢弃的分片数
+    dropped: u64,
+}
+
+impl SegmentSpool {
+    /// 打开缓存目录，上次进程退出时未封存的日志直接封存
+    ///
+    /// 上次补传中断时留下的断档录像直接删除，补传时会从日志重新整理。
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut used_bytes = 0;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(ACTIVE_EXTENSION) => {
+                    fs::rename(&path, path.with_extension(SEALED_EXTENSION))?;
+                }
+                Some(SEALED_EXTENSION) => {}
+                _ => {
+                    if path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("gap_")) {
+                        fs::remove_file(&path)?;
+                    }
+                    continue;
+                }
+            }
+            used_bytes += fs::metadata(path.with_extension(SEALED_EXTENSION))?.len();
+        }
+        if used_bytes > 0 {
+            info!("Spool {:?} holds {} bytes of unsent live segments", dir, used_bytes);
+        }
+
+        Ok(Self {
+            dir,
+            max_bytes,
+            inner: Mutex::new(SpoolInner {
+                journal: None,
+                used_bytes,
+                recordings: HashMap::new(),
+                outage: 0,
+                full: false,
+                dropped: 0,
+            }),
+        })
+    }
+
+    /// 当前中断编号，写入时携带，封存后旧编号的写入返回 `Sealed`
+    pub fn outage(&self) -> u64 {
+        self.inner.lock().unwrap().outage
+    }
+
+    /// 写入一个视频分片
+    ///
+    /// 日志总是从关键帧开始；音频和解码配置分片不写入，一次中断只缓存第一个写入的会话。
+    pub fn append(&self, outage: u64, segment: &VideoSegment) -> io::Result<SpoolWrite> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.outage != outage {
+            return Ok(SpoolWrite::Sealed);
+        }
+        if segment.is_audio() || segment.is_codec_config() {
+            return Ok(SpoolWrite::Dropped);
+        }
+        if inner
+            .journal
+            .as_ref()
+            .is_some_and(|(_, _, session_id)| *session_id != segment.session_id)
+        {
+            return Ok(SpoolWrite::Dropped);
+        }
+
+        let record = bincode::serialize(segment)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let size = 4 + record.len() as u64;
+        if inner.full || inner.used_bytes + size > self.max_bytes {
+            if !inner.full {
+                warn!("Spool {:?} full ({} bytes), dropping live segments", self.dir, inner.used_bytes);
+                inner.full = true;
+            }
+            inner.dropped += 1;
+            return Ok(SpoolWrite::Dropped);
+        }
+
+        if inner.journal.is_none() {
+            if !segment.is_keyframe() {
+                inner.dropped += 1;
+                return Ok(SpoolWrite::Dropped);
+            }
+            let started_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let path = self
+                .dir
+                .join(format!("gap_{}_{}", started_ms, segment.session_id.simple()))
+                .with_extension(ACTIVE_EXTENSION);
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            info!("Platform unreachable, journaling live segments to {:?}", path);
+            inner.journal = Some((path, BufWriter::new(file), segment.session_id));
+        }
+
+        let (_, writer, _) = inner.journal.as_mut().unwrap();
+        writer.write_all(&(record.len() as u32).to_le_bytes())?;
+        writer.write_all(&record)?;
+        inner.used_bytes += size;
+        Ok(SpoolWrite::Written)
+    }
+
+    /// 结束当前中断：封存日志，之后旧中断编号的写入返回 `Sealed`
+    pub fn seal(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.outage += 1;
+        inner.full = false;
+        let dropped = std::mem::take(&mut inner.dropped);
+
+        if let Some((path, mut writer, _)) = inner.journal.take() {
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+            fs::rename(&path, path.with_extension(SEALED_EXTENSION))?;
+            info!("Sealed spool journal {:?} ({} segments dropped)", path, dropped);
+        }
+        Ok(())
+    }
+
+    /// 已封存、等待补传的日志（按中断开始时间排序）
+    pub fn sealed_journals(&self) -> io::Result<Vec<PathBuf>> {
+        let mut journals: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(SEALED_EXTENSION))
+            .collect();
+        journals.sort();
+        Ok(journals)
+    }
+
+    /// 把日志整理为断档录像（裸流文件，与日志同名），返回录像路径
+    ///
+    /// 录像计入缓存占用量，直到 `remove` 或 `discard_recording` 删除。日志为空时返回 None。
+    pub fn build_recording(&self, journal: &Path) -> io::Result<Option<PathBuf>> {
+        let mut reader = BufReader::new(File::open(journal)?);
+        let mut recording: Option<(PathBuf, BufWriter<File>)> = None;
+        let mut len_buf = [0u8; 4];
+
+        loop {
+            match reader.read_exact(&mut len_buf) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            let mut record = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+            // 进程崩溃时最后一条记录可能不完整，丢弃即可
+            if reader.read_exact(&mut record).is_err() {
+                break;
+            }
+            let Ok(segment) = bincode::deserialize::<VideoSegment>(&record) else {
+                break;
+            };
+
+            if recording.is_none() {
+                let path = journal.with_extension(segment.codec.name());
+                recording = Some((path.clone(), BufWriter::new(File::create(&path)?)));
+            }
+            let (_, writer) = recording.as_mut().unwrap();
+            writer.write_all(&segment.data)?;
+        }
+
+        match recording {
+            Some((path, mut writer)) => {
+                writer.flush()?;
+                let size = fs::metadata(&path)?.len();
+                let mut inner = self.inner.lock().unwrap();
+                // 同一日志重新整理时覆盖了之前的录像，先扣除旧的占用
+                let previous = inner.recordings.insert(path.clone(), size).unwrap_or(0);
+                inner.used_bytes = inner.used_bytes.saturating_sub(previous) + size;
+                Ok(Some(path))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 删除整理出的录像（补传失败时），保留日志下次重连再试
+    pub fn discard_recording(&self, recording: &Path) -> io::Result<()> {
+        fs::remove_file(recording)?;
+        self.release_recording(recording);
+        Ok(())
+    }
+
+    fn release_recording(&self, recording: &Path) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(size) = inner.recordings.remove(recording) {
+            inner.used_bytes = inner.used_bytes.saturating_sub(size);
+        }
+    }
+
+    /// 补传完成后删除日志（以及整理出的录像）
+    pub fn remove(&self, journal: &Path, recording: Option<&Path>) -> io::Result<()> {
+        let size = fs::metadata(journal)?.len();
+        fs::remove_file(journal)?;
+        if let Some(recording) = recording {
+            fs::remove_file(recording)?;
+            self.release_recording(recording);
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.used_bytes = inner.used_bytes.saturating_sub(size);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spool_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("spool_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn segment(data: &[u8], is_keyframe: bool) -> VideoSegment {
+        VideoSegment::new(data.to_vec(), 0, 3_000, is_keyframe)
+    }
+
+    #[test]
+    fn test_spool_journal_to_recording() {
+        let dir = spool_dir("recording");
+        let spool = SegmentSpool::open(&dir, 1024 * 1024).unwrap();
+        let outage = spool.outage();
+
+        // 日志从关键帧开始
+        assert_eq!(spool.append(outage, &segment(&[9], false)).unwrap(), SpoolWrite::Dropped);
+        assert_eq!(spool.append(outage, &segment(&[0, 0, 0, 1, 0x65], true)).unwrap(), SpoolWrite::Written);
+        assert_eq!(spool.append(outage, &segment(&[0, 0, 0, 1, 0x41], false)).unwrap(), SpoolWrite::Written);
+        assert!(spool.sealed_journals().unwrap().is_empty());
+
+        spool.seal().unwrap();
+        assert_eq!(spool.append(outage, &segment(&[1], true)).unwrap(), SpoolWrite::Sealed);
+
+        let journals = spool.sealed_journals().unwrap();
+        assert_eq!(journals.len(), 1);
+        let journal_bytes = spool.inner.lock().unwrap().used_bytes;
+        let recording = spool.build_recording(&journals[0]).unwrap().unwrap();
+        assert_eq!(recording.extension().unwrap(), "h264");
+        assert_eq!(fs::read(&recording).unwrap(), vec![0, 0, 0, 1, 0x65, 0, 0, 0, 1, 0x41]);
+
+        // 录像计入占用量，重新整理不重复计入
+        assert_eq!(spool.inner.lock().unwrap().used_bytes, journal_bytes + 10);
+        spool.discard_recording(&recording).unwrap();
+        assert_eq!(spool.inner.lock().unwrap().used_bytes, journal_bytes);
+        spool.build_recording(&journals[0]).unwrap().unwrap();
+        let recording = spool.build_recording(&journals[0]).unwrap().unwrap();
+        assert_eq!(spool.inner.lock().unwrap().used_bytes, journal_bytes + 10);
+
+        spool.remove(&journals[0], Some(&recording)).unwrap();
+        assert!(spool.sealed_journals().unwrap().is_empty());
+        assert_eq!(spool.inner.lock().unwrap().used_bytes, 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spool_bounded() {
+        let dir = spool_dir("bounded");
+        let spool = SegmentSpool::open(&dir, 2_000).unwrap();
+        let outage = spool.outage();
+
+        let mut written = 0;
+        for i in 0..20 {
+            if spool.append(outage, &segment(&[0u8; 200], i == 0)).unwrap() == SpoolWrite::Written {
+                written += 1;
+            }
+        }
+        assert!(written > 0 && written < 20);
+        assert!(spool.inner.lock().unwrap().used_bytes <= 2_000);
+
+        // 重新打开时未封存的日志被封存，占用量保留
+        drop(spool);
+        let reopened = SegmentSpool::open(&dir, 2_000).unwrap();
+        assert_eq!(reopened.sealed_journals().unwrap().len(), 1);
+        assert!(reopened.inner.lock().unwrap().used_bytes > 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
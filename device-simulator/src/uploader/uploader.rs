@@ -61,16 +61,16 @@ impl Uploader {
         let start_time = Instant::now();
         let mut segment_count = 0;
         let mut total_bytes = 0u64;
-        let mut timestamp = 0.0;
+        let mut timestamp = 0i64;
 
         while let Some(chunk) = reader.read_chunk().await? {
-            let segment = VideoSegment::new(chunk.clone(), timestamp, segment_count % 30 == 0);
+            let segment = VideoSegment::new(chunk.clone(), timestamp, 3_000, segment_count % 30 == 0);
             
             self.client.send_segment(segment).await?;
             
             segment_count += 1;
             total_bytes += chunk.len() as u64;
-            timestamp += 0.033; // ~30fps
+            timestamp += 3_000; // ~30fps（90kHz 刻度）
 
             // 模拟实时传输速率
             tokio::time::sleep(tokio::time::Duration::from_millis(33)).await;
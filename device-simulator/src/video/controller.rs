@@ -212,7 +212,7 @@ impl PlaybackController for DefaultPlaybackController {
     }
 
     async fn set_playback_rate(&mut self, rate: f64) -> Result<(), PlaybackError> {
-        if rate <= 0.0 || rate > 10.0 {
+        if !common::trick_play::is_valid_rate(rate) {
             return Err(PlaybackError::InvalidPlaybackRate { rate });
         }
 
@@ -231,6 +231,13 @@ impl PlaybackController for DefaultPlaybackController {
 
     fn get_drop_frame_strategy(&self, rate: f64) -> DropFrameStrategy {
         match rate {
+            // 倒放只能从关键帧开始解码
+            r if r < 0.0 => DropFrameStrategy {
+                drop_b_frames: true,
+                drop_p_frames: true,
+                keep_key_frames_only: true,
+                adaptive_dropping: false,
+            },
             r if r <= 1.0 => DropFrameStrategy {
                 drop_b_frames: false,
                 drop_p_frames: false,
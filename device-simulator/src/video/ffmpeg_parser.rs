@@ -131,7 +131,7 @@ impl DefaultFFmpegParser {
                     .split_whitespace()
                     .find(|s| s.contains('x') && s.chars().next().unwrap_or('a').is_ascii_digit())
                 {
-                    let res_parts: Vec<&str> = res_match.split('x').collect();
+                    let res_parts: Vec<&str> = res_match.trim_end_matches(',').split('x').collect();
                     if res_parts.len() == 2 {
                         if let (Ok(w), Ok(h)) = (res_parts[0].parse::<u32>(), res_parts[1].parse::<u32>()) {
                             width = w;
@@ -0,0 +1,191 @@
+// 实时音频生成器模块（文件版本）
+//
+// 从 AAC ADTS 文件（或 MPEG-TS 中的 AAC 音频流）读取音频帧，按采样时长实时发送。
+// 时间戳从 0 开始按采样数累加，与同时启动的视频生成器共用时间轴。
+
+use common::aac::{self, AudioSpecificConfig};
+use common::ts;
+use common::VideoSegment;
+use std::path::Path;
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// 每个音频分片包含的 AAC 帧数（48kHz 时约 43ms）
+const FRAMES_PER_SEGMENT: usize = 2;
+
+/// 一个待发送的音频分片
+struct AudioChunk {
+    /// 连续的 ADTS 帧（含帧头）
+    data: Vec<u8>,
+    /// 采样数
+    samples: u32,
+    /// 帧数
+    frames: u32,
+}
+
+/// 实时音频生成器（文件版本）
+pub struct LiveAudioGeneratorFile {
+    session_id: Uuid,
+    file_path: std::path::PathBuf,
+    stop_signal: Option<tokio::sync::watch::Sender<bool>>,
+}
+
+impl LiveAudioGeneratorFile {
+    /// 创建实时音频生成器
+    pub fn new(
+        session_id: Uuid,
+        file_path: impl AsRef<Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let file_path = file_path.as_ref().to_path_buf();
+
+        if !file_path.exists() {
+            return Err(format!("AAC file not found: {:?}", file_path).into());
+        }
+
+        info!("🎙️ Creating live audio generator (FILE MODE)");
+        info!("  Session ID: {}", session_id);
+        info!("  File: {:?}", file_path);
+
+        Ok(Self {
+            session_id,
+            file_path,
+            stop_signal: None,
+        })
+    }
+
+    /// 启动实时音频流
+    pub async fn start_streaming(
+        &mut self,
+    ) -> Result<mpsc::Receiver<VideoSegment>, Box<dyn std::error::Error>> {
+        if self.stop_signal.is_some() {
+            return Err("Stream already running".into());
+        }
+
+        let mut data = tokio::fs::read(&self.file_path).await?;
+        // MPEG-TS 取出音频流中的 ADTS 帧
+        if ts::is_mpeg_ts(&data) {
+            data = ts::parse(&data)
+                .map(|transport| transport.audio_frames().into_iter().flat_map(|frame| frame.data).collect())
+                .unwrap_or_default();
+        }
+        let (config, chunks) = Self::split_chunks(&data)
+            .ok_or_else(|| format!("No ADTS frames found in {:?}", self.file_path))?;
+        info!(
+            "✓ Loaded AAC file: {} bytes, {} {} Hz, {} channels",
+            data.len(),
+            config.codec(),
+            config.sample_rate(),
+            config.channel_config
+        );
+
+        let (tx, rx) = mpsc::channel(100);
+        let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+        self.stop_signal = Some(stop_tx);
+
+        let session_id = self.session_id;
+        tokio::spawn(async move {
+            Self::stream_chunks(session_id, config, chunks, tx, stop_rx).await;
+            info!("✓ Audio streaming completed");
+        });
+
+        Ok(rx)
+    }
+
+    /// 停止实时音频流
+    pub fn stop_streaming(&mut self) {
+        if let Some(stop_tx) = self.stop_signal.take() {
+            let _ = stop_tx.send(true);
+            info!("⏹️ Stop signal sent to audio streaming task");
+        }
+    }
+
+    /// 把 ADTS 文件切分为分片，返回首帧的解码配置
+    fn split_chunks(data: &[u8]) -> Option<(AudioSpecificConfig, Vec<AudioChunk>)> {
+        let mut frames = Vec::new();
+        let mut rest = data;
+        while let Some((frame, len)) = aac::parse_adts_frame(rest) {
+            frames.push((frame.samples, &rest[..len]));
+            rest = &rest[len..];
+        }
+        let config = aac::parse_adts_frame(data)?.0.config;
+
+        let chunks = frames
+            .chunks(FRAMES_PER_SEGMENT)
+            .map(|group| AudioChunk {
+                data: group.iter().flat_map(|(_, frame)| frame.iter().copied()).collect(),
+                samples: group.iter().map(|(samples, _)| samples).sum(),
+                frames: group.len() as u32,
+            })
+            .collect();
+        Some((config, chunks))
+    }
+
+    async fn stream_chunks(
+        session_id: Uuid,
+        config: AudioSpecificConfig,
+        chunks: Vec<AudioChunk>,
+        tx: mpsc::Sender<VideoSegment>,
+        stop_rx: tokio::sync::watch::Receiver<bool>,
+    ) {
+        let sample_rate = config.sample_rate() as u64;
+        let segment_duration = Duration::from_secs_f64(
+            FRAMES_PER_SEGMENT as f64 * config.frame_duration(),
+        );
+        let mut interval_timer = interval(segment_duration);
+        // 按累计采样数计算时间戳，长时间运行也不会漂移
+        let mut total_samples = 0u64;
+
+        // 循环播放文件
+        for chunk in chunks.iter().cycle() {
+            if *stop_rx.borrow() {
+                info!("⏹️ Stop signal received, ending audio stream");
+                break;
+            }
+
+            interval_timer.tick().await;
+
+            let pts = total_samples * VideoSegment::TIMESCALE as u64 / sample_rate;
+            let end = (total_samples + chunk.samples as u64) * VideoSegment::TIMESCALE as u64 / sample_rate;
+            let mut segment = VideoSegment::new_audio(
+                chunk.data.clone(),
+                pts as i64,
+                (end - pts) as u32,
+                chunk.frames,
+            );
+            segment.session_id = session_id;
+            total_samples += chunk.samples as u64;
+
+            if tx.send(segment).await.is_err() {
+                warn!("⚠️ Receiver dropped, stopping audio stream");
+                break;
+            }
+        }
+        debug!("Audio stream sent {} samples", total_samples);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunks() {
+        let config = AudioSpecificConfig { object_type: 2, sample_rate_index: 4, channel_config: 1 };
+        let mut data = Vec::new();
+        for i in 0..5u8 {
+            data.extend(aac::to_adts(&config, &[0x21, i]));
+        }
+
+        let (detected, chunks) = LiveAudioGeneratorFile::split_chunks(&data).unwrap();
+        assert_eq!(detected, config);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].frames, 2);
+        assert_eq!(chunks[0].samples, 2 * aac::SAMPLES_PER_FRAME);
+        assert_eq!(chunks[0].data, data[..18]);
+        assert_eq!(chunks[2].frames, 1);
+
+        assert!(LiveAudioGeneratorFile::split_chunks(&[0x00, 0x00, 0x01, 0x67]).is_none());
+    }
+}
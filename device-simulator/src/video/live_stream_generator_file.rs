@@ -1,15 +1,165 @@
 // 实时流生成器模块（文件版本）
 //
-// 从真实的H.264文件读取数据并流式传输
+// 从真实的H.264/H.265文件读取数据，按访问单元流式传输。裸流按块读取并增量切分访问单元，
+// 多 GB 的录像也不必整体载入内存；MP4/MPEG-TS 解复用后按样本表的帧时长发送。
+// 每个 IDR/IRAP 帧前都带参数集；低延迟模式每个分片一帧，分片时间戳与帧内容一一对应。
 
-use common::VideoSegment;
-use tokio::sync::mpsc;
-use tokio::time::{interval, Duration};
-use uuid::Uuid;
-use tracing::{debug, info, warn};
+use common::demux::{AccessUnitSplitter, Container, ElementaryStream, EncodedFrame, ParameterSets};
+use common::{h264, mp4, ts, SegmentFlags, VideoCodec, VideoSegment};
+use std::collections::VecDeque;
 use std::path::Path;
+use std::sync::Arc;
 use tokio::fs::File;
-use tokio::io::{AsyncReadExt, BufReader};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::{mpsc, Notify};
+use tokio::time::{sleep_until, Duration, Instant};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// 每次从文件读取的块大小
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+/// 识别容器、编码和帧率时读取的文件头长度
+const PROBE_SIZE: usize = 4096;
+/// 非低延迟模式每个分片最多包含的帧数
+const BATCH_FRAMES_PER_SEGMENT: usize = 5;
+/// 请求关键帧时最多跳过的帧数（文件中没有后续关键帧时放弃）
+const MAX_KEYFRAME_SKIP_FRAMES: usize = 10_000;
+
+/// 从文件读出的一帧
+struct SourceFrame {
+    /// Annex B 数据
+    data: Vec<u8>,
+    is_keyframe: bool,
+    /// 时长（`VideoSegment::TIMESCALE` 刻度）
+    duration: u32,
+    /// 显示时间戳相对解码时间戳的偏移（有 B 帧时非零）
+    composition_offset: i64,
+}
+
+/// 帧来源
+enum FrameSource {
+    /// Annex B 裸流：按块读取文件，增量切分访问单元
+    AnnexB {
+        file: File,
+        splitter: AccessUnitSplitter,
+        ready: VecDeque<EncodedFrame>,
+        eof: bool,
+        /// 本轮播放已读出的帧数（为 0 时到达文件末尾说明文件中没有帧）
+        frames_read: u64,
+        frame_duration: u32,
+    },
+    /// MP4/MPEG-TS：解复用后的访问单元表
+    Demuxed { stream: ElementaryStream, next: usize },
+}
+
+impl FrameSource {
+    /// 打开文件，返回帧来源和视频编码格式
+    async fn open(file_path: &Path, fps: f64) -> Result<(Self, VideoCodec), Box<dyn std::error::Error>> {
+        let mut file = File::open(file_path).await?;
+        let mut probe = vec![0u8; PROBE_SIZE];
+        let mut probed = 0;
+        while probed < PROBE_SIZE {
+            let n = file.read(&mut probe[probed..]).await?;
+            if n == 0 {
+                break;
+            }
+            probed += n;
+        }
+        probe.truncate(probed);
+
+        // 容器格式需要整体解复用（样本表/PES 可能分散在整个文件中）
+        if mp4::is_mp4(&probe) || ts::is_mpeg_ts(&probe) {
+            let data = tokio::fs::read(file_path).await?;
+            let stream = tokio::task::spawn_blocking(move || ElementaryStream::parse(data, fps)).await?;
+            info!(
+                "✓ Loaded {} file ({:?}): {} frames, {:.2} fps",
+                stream.codec,
+                stream.container,
+                stream.units.len(),
+                stream.frame_rate
+            );
+            let codec = stream.codec;
+            return Ok((Self::Demuxed { stream, next: 0 }, codec));
+        }
+
+        let codec = common::demux::detect_codec(&probe);
+        let frame_rate = match codec {
+            VideoCodec::H264 => h264::detect_frame_rate(&probe).unwrap_or(fps),
+            VideoCodec::H265 => fps,
+        };
+        info!("✓ Streaming {} file ({:?}) at {:.2} fps", codec, Container::AnnexB, frame_rate);
+
+        let mut splitter = AccessUnitSplitter::new(codec);
+        let mut ready: VecDeque<EncodedFrame> = splitter.push(&probe).into();
+        let eof = probed < PROBE_SIZE;
+        if eof {
+            ready.extend(splitter.finish());
+        }
+        let source = Self::AnnexB {
+            file,
+            splitter,
+            ready,
+            eof,
+            frames_read: 0,
+            frame_duration: VideoSegment::ticks(1.0 / frame_rate) as u32,
+        };
+        Ok((source, codec))
+    }
+
+    /// 读取下一帧，文件结束时回到开头循环播放；文件中没有帧时返回 None
+    async fn next_frame(&mut self) -> std::io::Result<Option<SourceFrame>> {
+        match self {
+            Self::AnnexB { file, splitter, ready, eof, frames_read, frame_duration } => {
+                let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+                loop {
+                    if let Some(frame) = ready.pop_front() {
+                        *frames_read += 1;
+                        return Ok(Some(SourceFrame {
+                            data: frame.data,
+                            is_keyframe: frame.is_keyframe,
+                            duration: *frame_duration,
+                            composition_offset: 0,
+                        }));
+                    }
+                    if *eof {
+                        if *frames_read == 0 {
+                            return Ok(None);
+                        }
+                        info!("🔄 Looping file playback");
+                        file.seek(std::io::SeekFrom::Start(0)).await?;
+                        *splitter = AccessUnitSplitter::new(splitter.codec());
+                        *eof = false;
+                        *frames_read = 0;
+                        continue;
+                    }
+                    let n = file.read(&mut buffer).await?;
+                    if n == 0 {
+                        *eof = true;
+                        ready.extend(splitter.finish());
+                    } else {
+                        ready.extend(splitter.push(&buffer[..n]));
+                    }
+                }
+            }
+            Self::Demuxed { stream, next } => {
+                let Some(unit) = stream.units.get(*next).copied() else {
+                    return Ok(None);
+                };
+                *next += 1;
+                if *next == stream.units.len() {
+                    info!("🔄 Looping file playback");
+                    *next = 0;
+                }
+                Ok(Some(SourceFrame {
+                    data: stream.unit_data(&unit).to_vec(),
+                    is_keyframe: unit.is_keyframe,
+                    duration: VideoSegment::ticks(unit.duration) as u32,
+                    composition_offset: VideoSegment::ticks(unit.timestamp) - VideoSegment::ticks(unit.decode_timestamp),
+                }))
+            }
+        }
+    }
+}
 
 /// 实时流生成器（文件版本）
 pub struct LiveStreamGeneratorFile {
@@ -19,10 +169,16 @@ pub struct LiveStreamGeneratorFile {
     file_path: std::path::PathBuf,
     is_running: bool,
     stop_signal: Option<tokio::sync::watch::Sender<bool>>,
+    /// 下一个分片强制从参数集+关键帧开始
+    force_keyframe: Arc<Notify>,
+    /// 每个分片最多包含的帧数（低延迟模式为 1）
+    frames_per_segment: usize,
 }
 
 impl LiveStreamGeneratorFile {
     /// 创建实时流生成器
+    ///
+    /// `fps` 只在码流中没有帧率信息（H.264 VUI、MP4/TS 时间戳）时使用。
     pub fn new(
         session_id: Uuid,
         fps: u32,
@@ -30,17 +186,17 @@ impl LiveStreamGeneratorFile {
         file_path: impl AsRef<Path>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let file_path = file_path.as_ref().to_path_buf();
-        
+
         if !file_path.exists() {
-            return Err(format!("H.264 file not found: {:?}", file_path).into());
+            return Err(format!("Video file not found: {:?}", file_path).into());
         }
-        
+
         info!("🎥 Creating live stream generator (FILE MODE)");
         info!("  Session ID: {}", session_id);
         info!("  FPS: {}", fps);
         info!("  Bitrate: {} Mbps", bitrate / 1_000_000);
         info!("  File: {:?}", file_path);
-        
+
         Ok(Self {
             session_id,
             fps,
@@ -48,9 +204,16 @@ impl LiveStreamGeneratorFile {
             file_path,
             is_running: false,
             stop_signal: None,
+            force_keyframe: Arc::new(Notify::new()),
+            frames_per_segment: 1,
         })
     }
-    
+
+    /// 设置低延迟模式（默认开启）：开启时每个分片一帧，关闭时连续的非关键帧合并为一个分片
+    pub fn set_low_latency(&mut self, low_latency: bool) {
+        self.frames_per_segment = if low_latency { 1 } else { BATCH_FRAMES_PER_SEGMENT };
+    }
+
     /// 启动实时流
     pub async fn start_streaming(
         &mut self,
@@ -58,293 +221,205 @@ impl LiveStreamGeneratorFile {
         if self.is_running {
             return Err("Stream already running".into());
         }
-        
+
         self.is_running = true;
         let (tx, rx) = mpsc::channel(100);
-        
+
         // 创建停止信号通道
         let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
         self.stop_signal = Some(stop_tx);
-        
+
         info!("🚀 Starting live stream from file...");
-        
+
         // 启动文件读取任务
         self.spawn_file_task(tx, stop_rx).await?;
-        
+
         Ok(rx)
     }
-    
+
     async fn spawn_file_task(
         &mut self,
         tx: mpsc::Sender<VideoSegment>,
         stop_rx: tokio::sync::watch::Receiver<bool>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let session_id = self.session_id;
-        let fps = self.fps;
-        let frame_duration = Duration::from_secs_f64(1.0 / fps as f64);
+        let fps = self.fps as f64;
+        let frames_per_segment = self.frames_per_segment;
         let file_path = self.file_path.clone();
-        
+        let force_keyframe = Arc::clone(&self.force_keyframe);
+
         tokio::spawn(async move {
-            match Self::stream_file(session_id, fps, frame_duration, file_path, tx, stop_rx).await {
+            match Self::stream_file(session_id, fps, frames_per_segment, file_path, force_keyframe, tx, stop_rx).await {
                 Ok(_) => info!("✓ File streaming completed"),
                 Err(e) => warn!("⚠️ File streaming error: {}", e),
             }
         });
-        
+
         Ok(())
     }
-    
+
     async fn stream_file(
         session_id: Uuid,
-        fps: u32,
-        frame_duration: Duration,
+        fps: f64,
+        frames_per_segment: usize,
         file_path: std::path::PathBuf,
+        force_keyframe: Arc<Notify>,
         tx: mpsc::Sender<VideoSegment>,
         mut stop_rx: tokio::sync::watch::Receiver<bool>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let file = File::open(&file_path).await?;
-        let mut reader = BufReader::new(file);
-        
-        let mut interval_timer = interval(frame_duration);
-        let mut frame_count = 0u64;
-        let mut timestamp = 0.0f64;
-        let frame_duration_secs = frame_duration.as_secs_f64();
-        
-        // 读取整个文件到内存（对于小文件）
-        let mut file_data = Vec::new();
-        reader.read_to_end(&mut file_data).await?;
-        
-        info!("✓ Loaded H.264 file: {} bytes", file_data.len());
-        
-        // 查找NAL单元
-        let nal_units = Self::find_nal_units(&file_data);
-        info!("✓ Found {} NAL units", nal_units.len());
-        
-        if nal_units.is_empty() {
-            return Err("No NAL units found in file".into());
-        }
-        
-        // 循环发送NAL单元
-        // 策略：将连续的NAL单元组合成一个分片，直到遇到下一个关键帧或达到目标大小
-        let mut nal_index = 0;
-        let target_segment_size = 50000; // 目标分片大小：50KB
-        
-        loop {
+        let (mut source, codec) = FrameSource::open(&file_path, fps).await?;
+
+        // 最近一组参数集，补到不带参数集的关键帧前面
+        let mut parameter_sets: Option<ParameterSets> = None;
+        // 合并分片时遇到的关键帧留给下一个分片开头
+        let mut carry: Option<SourceFrame> = None;
+
+        let start = Instant::now();
+        // 下一帧的解码时间戳（90kHz 刻度，按帧时长累加）
+        let mut media_time = 0i64;
+        let mut segment_count = 0u64;
+        // 平台请求了关键帧，下一个分片从下一个关键帧开始
+        let mut skip_to_keyframe = false;
+
+        'segments: loop {
             // 检查停止信号
             if *stop_rx.borrow() {
                 info!("⏹️ Stop signal received, ending stream");
                 break;
             }
-            
-            interval_timer.tick().await;
-            
-            // 收集NAL单元直到达到目标大小或遇到关键帧
-            let mut segment_data = Vec::new();
-            let mut segment_has_keyframe = false;
-            let start_nal_index = nal_index;
-            
-            // 第一个NAL单元
-            let first_nal = &nal_units[nal_index];
-            let first_nal_type = Self::get_nal_type(first_nal);
-            segment_data.extend_from_slice(first_nal);
-            segment_has_keyframe = Self::is_keyframe_nal(first_nal);
-            nal_index += 1;
-            
-            // 如果第一个是SPS，继续添加所有SPS、PPS和第一个IDR
-            if first_nal_type == 7 {
-                // 添加所有SPS
-                while nal_index < nal_units.len() && Self::get_nal_type(&nal_units[nal_index]) == 7 {
-                    segment_data.extend_from_slice(&nal_units[nal_index]);
-                    nal_index += 1;
-                }
-                // 添加所有PPS
-                while nal_index < nal_units.len() && Self::get_nal_type(&nal_units[nal_index]) == 8 {
-                    segment_data.extend_from_slice(&nal_units[nal_index]);
-                    segment_has_keyframe = true;
-                    nal_index += 1;
+
+            // 平台请求关键帧（PLI）：文件内容无法重新编码，跳过当前 GOP 剩余的帧，
+            // 从下一个关键帧继续发送。时间戳保持连续，不重放已发送的内容
+            if std::mem::take(&mut skip_to_keyframe) && carry.is_none() {
+                let mut skipped = 0usize;
+                while skipped < MAX_KEYFRAME_SKIP_FRAMES {
+                    match source.next_frame().await? {
+                        Some(frame) if frame.is_keyframe => {
+                            carry = Some(frame);
+                            break;
+                        }
+                        Some(frame) => {
+                            if let Some(found) = ParameterSets::find(codec, &frame.data) {
+                                parameter_sets = Some(found);
+                            }
+                            skipped += 1;
+                        }
+                        None => break,
+                    }
                 }
-                // 添加第一个IDR
-                if nal_index < nal_units.len() && Self::get_nal_type(&nal_units[nal_index]) == 5 {
-                    segment_data.extend_from_slice(&nal_units[nal_index]);
-                    segment_has_keyframe = true;
-                    nal_index += 1;
+                info!(
+                    "🔑 Forcing keyframe at segment {}: skipped {} frames",
+                    segment_count, skipped
+                );
+            }
+
+            // 收集一个分片的帧：分片总是从关键帧或单个非关键帧序列开始，不跨越关键帧
+            let mut frames: Vec<SourceFrame> = Vec::with_capacity(frames_per_segment);
+            while frames.len() < frames_per_segment {
+                let frame = match carry.take() {
+                    Some(frame) => frame,
+                    None => match source.next_frame().await? {
+                        Some(frame) => frame,
+                        None => break,
+                    },
+                };
+                if frame.is_keyframe && !frames.is_empty() {
+                    carry = Some(frame);
+                    break;
                 }
-                
-                info!("📦 Sending SPS+PPS+IDR segment: {} bytes", segment_data.len());
-            } else {
-                // 继续添加NAL单元直到达到目标大小
-                while segment_data.len() < target_segment_size && nal_index < nal_units.len() {
-                    let next_nal = &nal_units[nal_index];
-                    let next_nal_type = Self::get_nal_type(next_nal);
-                    
-                    // 如果遇到SPS，停止（下一个分片从SPS开始）
-                    if next_nal_type == 7 {
-                        break;
-                    }
-                    
-                    segment_data.extend_from_slice(next_nal);
-                    if Self::is_keyframe_nal(next_nal) {
-                        segment_has_keyframe = true;
+                frames.push(frame);
+            }
+            if frames.is_empty() {
+                return Err("No access units found in file".into());
+            }
+
+            let is_keyframe = frames[0].is_keyframe;
+            let duration: u32 = frames.iter().map(|frame| frame.duration).sum();
+            let mut segment_data = Vec::with_capacity(frames.iter().map(|frame| frame.data.len()).sum());
+            for frame in &frames {
+                match ParameterSets::find(codec, &frame.data) {
+                    Some(found) => parameter_sets = Some(found),
+                    None if frame.is_keyframe => {
+                        if let Some(parameter_sets) = &parameter_sets {
+                            segment_data.extend(parameter_sets.to_annex_b());
+                        }
                     }
-                    nal_index += 1;
+                    None => {}
                 }
+                segment_data.extend_from_slice(&frame.data);
             }
-            
-            // 每30帧（1秒）重新发送SPS+PPS以支持新加入的客户端
-            if frame_count > 0 && frame_count % 30 == 0 {
-                info!("🔄 Resending SPS/PPS for new clients at frame {}", frame_count);
-                // 重置到开头，下一帧将发送SPS+PPS+IDR
-                nal_index = 0;
+
+            // 按帧时间实时发送：分片在最后一帧的时间点可用
+            let last_frame_time = media_time + (duration - frames[frames.len() - 1].duration) as i64;
+            let deadline = Duration::from_micros((last_frame_time * 1_000_000 / VideoSegment::TIMESCALE as i64) as u64);
+            // 请求在分片发出前处理：尚未发出的非关键帧丢弃，时间戳不前进；
+            // 待发的分片本身是关键帧时直接满足请求
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = stop_rx.changed() => continue 'segments,
+                    _ = force_keyframe.notified() => {
+                        if !is_keyframe {
+                            skip_to_keyframe = true;
+                            continue 'segments;
+                        }
+                    }
+                    _ = sleep_until(start + deadline) => break,
+                }
             }
-            
+
             // 记录前几个分片的信息
-            if frame_count < 5 {
-                info!("  Segment #{}: size={}, keyframe={}, NALs={}-{}", 
-                      frame_count, segment_data.len(), segment_has_keyframe, 
-                      start_nal_index, nal_index - 1);
+            if segment_count < 5 {
+                info!(
+                    "  Segment #{}: {} frames, size={}, keyframe={}",
+                    segment_count, frames.len(), segment_data.len(), is_keyframe
+                );
             }
-            
+
             let segment = VideoSegment {
                 stream_type: 0x01, // 视频
                 segment_id: Uuid::new_v4(),
                 session_id,
-                timestamp,
-                duration: frame_duration_secs,
-                frame_count: 1,
-                flags: if segment_has_keyframe { 1 } else { 0 },
+                pts: media_time + frames[0].composition_offset,
+                dts: media_time,
+                timescale: VideoSegment::TIMESCALE,
+                duration,
+                frame_count: frames.len() as u32,
+                flags: if is_keyframe { SegmentFlags::IS_KEYFRAME } else { 0 },
                 data_length: segment_data.len() as u32,
                 data: segment_data,
+                capture_time_us: common::utils::current_timestamp_us(),
+                send_time_us: 0, // 发送时填写
+                sequence: 0,
+                rendition_id: 0, // 由直通播放任务按当前码流填写
+                codec,
+                receive_time: None,
             };
-            
-            if frame_count % 30 == 0 {
+
+            if segment_count.is_multiple_of(30) {
                 debug!(
-                    "📤 Sending segment #{}: {:.2}s, {} bytes, keyframe: {}",
-                    frame_count, timestamp, segment.data.len(), segment_has_keyframe
+                    "📤 Sending segment #{}: {:.3}s, {} bytes, keyframe: {}",
+                    segment_count, segment.pts_secs(), segment.data.len(), is_keyframe
                 );
             }
-            
+
             if tx.send(segment).await.is_err() {
                 warn!("⚠️ Receiver dropped, stopping stream");
                 break;
             }
-            
-            frame_count += 1;
-            timestamp += frame_duration_secs;
-            
-            // 循环播放
-            if nal_index >= nal_units.len() {
-                info!("🔄 Looping file playback");
-                nal_index = 0;
-            }
+
+            segment_count += 1;
+            media_time += duration as i64;
         }
-        
+
         Ok(())
     }
-    
-    /// 查找文件中的所有NAL单元，并重新排序确保SPS/PPS在前
-    fn find_nal_units(data: &[u8]) -> Vec<Vec<u8>> {
-        let mut nal_units = Vec::new();
-        let mut sps_units = Vec::new();
-        let mut pps_units = Vec::new();
-        let mut idr_units = Vec::new();
-        let mut other_units = Vec::new();
-        
-        let mut i = 0;
-        
-        while i < data.len() {
-            // 查找起始码 (0x00 0x00 0x00 0x01 或 0x00 0x00 0x01)
-            if i + 3 < data.len() && data[i] == 0x00 && data[i+1] == 0x00 {
-                let start_code_len = if data[i+2] == 0x00 && data[i+3] == 0x01 {
-                    4
-                } else if data[i+2] == 0x01 {
-                    3
-                } else {
-                    i += 1;
-                    continue;
-                };
-                
-                // 找到起始码，查找下一个起始码
-                let nal_start = i;
-                i += start_code_len;
-                
-                // 查找下一个NAL单元的起始码
-                let mut nal_end = data.len();
-                let mut j = i;
-                while j < data.len() - 3 {
-                    if data[j] == 0x00 && data[j+1] == 0x00 {
-                        if (data[j+2] == 0x00 && j + 3 < data.len() && data[j+3] == 0x01) ||
-                           data[j+2] == 0x01 {
-                            nal_end = j;
-                            break;
-                        }
-                    }
-                    j += 1;
-                }
-                
-                // 提取NAL单元（包含起始码）
-                if nal_end > nal_start {
-                    let nal_data = data[nal_start..nal_end].to_vec();
-                    
-                    // 根据NAL类型分类
-                    let nal_type = Self::get_nal_type(&nal_data);
-                    match nal_type {
-                        7 => sps_units.push(nal_data), // SPS
-                        8 => pps_units.push(nal_data), // PPS
-                        5 => idr_units.push(nal_data), // IDR
-                        _ => other_units.push(nal_data),
-                    }
-                }
-                
-                i = nal_end;
-            } else {
-                i += 1;
-            }
-        }
-        
-        // 重新排序：SPS -> PPS -> IDR -> 其他
-        // 这样确保第一批数据包含完整的初始化信息
-        info!("  NAL unit classification:");
-        info!("    SPS: {}", sps_units.len());
-        info!("    PPS: {}", pps_units.len());
-        info!("    IDR: {}", idr_units.len());
-        info!("    Other: {}", other_units.len());
-        
-        nal_units.extend(sps_units);
-        nal_units.extend(pps_units);
-        nal_units.extend(idr_units);
-        nal_units.extend(other_units);
-        
-        nal_units
-    }
-    
-    /// 获取NAL单元类型
-    fn get_nal_type(nal_data: &[u8]) -> u8 {
-        let start = if nal_data.len() >= 4 && nal_data[0] == 0x00 && nal_data[1] == 0x00 && 
-                       nal_data[2] == 0x00 && nal_data[3] == 0x01 {
-            4
-        } else if nal_data.len() >= 3 && nal_data[0] == 0x00 && nal_data[1] == 0x00 && 
-                  nal_data[2] == 0x01 {
-            3
-        } else {
-            0
-        };
-        
-        if start < nal_data.len() {
-            nal_data[start] & 0x1F
-        } else {
-            0
-        }
-    }
-    
-    /// 检查NAL单元是否是关键帧
-    fn is_keyframe_nal(nal_data: &[u8]) -> bool {
-        let nal_type = Self::get_nal_type(nal_data);
-        // NAL type 5 = IDR (关键帧)
-        // NAL type 7 = SPS
-        // NAL type 8 = PPS
-        nal_type == 5 || nal_type == 7 || nal_type == 8
+
+    /// 请求下一个分片输出关键帧
+    pub fn request_keyframe(&self) {
+        self.force_keyframe.notify_one();
     }
-    
+
     /// 停止实时流
     pub fn stop_streaming(&mut self) {
         self.is_running = false;
@@ -354,3 +429,95 @@ impl LiveStreamGeneratorFile {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 两个 GOP，每个 GOP 4 帧，参数集只出现在第一个 IDR 前
+    fn h264_stream() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+        data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+        for frame in 0..8u8 {
+            if frame % 4 == 0 {
+                data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, frame]);
+            } else {
+                data.extend_from_slice(&[0, 0, 0, 1, 0x41, 0x9a, frame]);
+            }
+        }
+        data
+    }
+
+    async fn collect_segments(low_latency: bool, count: usize) -> Vec<VideoSegment> {
+        let path = std::env::temp_dir().join(format!("live_stream_{}.h264", Uuid::new_v4()));
+        std::fs::write(&path, h264_stream()).unwrap();
+
+        let mut generator = LiveStreamGeneratorFile::new(Uuid::new_v4(), 1000, 1_000_000, &path).unwrap();
+        generator.set_low_latency(low_latency);
+        let mut receiver = generator.start_streaming().await.unwrap();
+        let mut segments = Vec::new();
+        while segments.len() < count {
+            segments.push(receiver.recv().await.unwrap());
+        }
+        generator.stop_streaming();
+        std::fs::remove_file(&path).unwrap();
+        segments
+    }
+
+    #[tokio::test]
+    async fn test_one_frame_per_segment() {
+        let segments = collect_segments(true, 10).await;
+
+        // 每个分片一帧，时间戳按帧时长递增，文件结束后循环
+        assert!(segments.iter().all(|s| s.frame_count == 1));
+        let keyframes: Vec<bool> = segments.iter().map(|s| s.is_keyframe()).collect();
+        assert_eq!(keyframes, [true, false, false, false, true, false, false, false, true, false]);
+        assert_eq!((segments[3].pts, segments[3].dts, segments[3].duration), (270, 270, 90));
+
+        // 第二个 GOP 和循环后的 IDR 都带上参数集
+        for index in [4, 8] {
+            let parameter_sets = ParameterSets::find(VideoCodec::H264, &segments[index].data);
+            assert!(parameter_sets.is_some(), "segment {}", index);
+        }
+        assert_eq!(segments[1].data, [0, 0, 0, 1, 0x41, 0x9a, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_keyframe_request_skips_to_next_gop() {
+        let path = std::env::temp_dir().join(format!("live_stream_{}.h264", Uuid::new_v4()));
+        std::fs::write(&path, h264_stream()).unwrap();
+
+        let mut generator = LiveStreamGeneratorFile::new(Uuid::new_v4(), 20, 1_000_000, &path).unwrap();
+        let mut receiver = generator.start_streaming().await.unwrap();
+        let first = receiver.recv().await.unwrap();
+        // 帧 1 的发送时间在 50ms 之后，请求总是在它发出前处理
+        generator.request_keyframe();
+        let mut segments = vec![first];
+        for _ in 0..3 {
+            segments.push(receiver.recv().await.unwrap());
+        }
+        generator.stop_streaming();
+        std::fs::remove_file(&path).unwrap();
+
+        // 等待发送的帧 1 被丢弃，直接跳到第二个 GOP 的 IDR，不重放第一个 IDR
+        let frames: Vec<u8> = segments.iter().map(|s| *s.data.last().unwrap()).collect();
+        assert_eq!(frames, [0, 4, 5, 6]);
+        assert!(segments[1].is_keyframe());
+        assert!(ParameterSets::find(VideoCodec::H264, &segments[1].data).is_some());
+
+        // 时间戳保持连续
+        let dts: Vec<i64> = segments.iter().map(|s| s.dts).collect();
+        assert_eq!(dts, [0, 4_500, 9_000, 13_500]);
+    }
+
+    #[tokio::test]
+    async fn test_batched_segments_stop_at_keyframes() {
+        let segments = collect_segments(false, 4).await;
+
+        let frame_counts: Vec<u32> = segments.iter().map(|s| s.frame_count).collect();
+        assert_eq!(frame_counts, [4, 4, 4, 4]);
+        assert!(segments.iter().all(|s| s.is_keyframe()));
+        assert_eq!((segments[1].pts, segments[1].duration), (360, 360));
+    }
+}
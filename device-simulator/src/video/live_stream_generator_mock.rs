@@ -73,8 +73,8 @@ impl LiveStreamGenerator {
         tokio::spawn(async move {
             let mut interval_timer = interval(frame_duration);
             let mut frame_count = 0u64;
-            let mut timestamp = 0.0f64;
-            let frame_duration_secs = frame_duration.as_secs_f64();
+            // 时间戳按帧序号计算（90kHz 刻度），长时间运行也不会漂移
+            let frame_ticks = VideoSegment::TIMESCALE / fps;
             
             info!("✓ Mock stream generator started");
             info!("  Frame size: {} bytes", bytes_per_frame);
@@ -132,18 +132,26 @@ impl LiveStreamGenerator {
                     stream_type: 0x01, // 视频
                     segment_id: Uuid::new_v4(),
                     session_id,
-                    timestamp,
-                    duration: frame_duration_secs,
+                    pts: frame_count as i64 * frame_ticks as i64,
+                    dts: frame_count as i64 * frame_ticks as i64,
+                    timescale: VideoSegment::TIMESCALE,
+                    duration: frame_ticks,
                     frame_count: 1,
                     flags: if is_keyframe { 1 } else { 0 },
                     data_length: mock_data.len() as u32,
                     data: mock_data,
+                    capture_time_us: common::utils::current_timestamp_us(),
+                    send_time_us: 0, // 发送时填写
+                    sequence: 0,
+                    rendition_id: 0,
+                    codec: common::VideoCodec::H264,
+                    receive_time: None,
                 };
                 
                 if frame_count % 30 == 0 {
                     debug!(
                         "📤 Sending mock segment #{}: {:.2}s, {} bytes, keyframe: {}",
-                        frame_count, timestamp, segment.data.len(), is_keyframe
+                        frame_count, segment.pts_secs(), segment.data.len(), is_keyframe
                     );
                 }
                 
@@ -153,7 +161,6 @@ impl LiveStreamGenerator {
                 }
                 
                 frame_count += 1;
-                timestamp += frame_duration_secs;
             }
             
             info!("✓ Mock stream generator stopped (total frames: {})", frame_count);
@@ -3,6 +3,8 @@ mod reader;
 // 使用文件版本（从真实H.264文件读取）
 mod live_stream_generator_file;
 pub use live_stream_generator_file::LiveStreamGeneratorFile;
+mod live_audio_generator_file;
+pub use live_audio_generator_file::LiveAudioGeneratorFile;
 
 // 使用模拟版本进行快速测试（无需FFmpeg依赖）
 mod live_stream_generator_mock;
@@ -27,7 +29,7 @@ pub mod controller;
 #[cfg(test)]
 mod types_test;
 
-pub use reader::{scan_video_files, VideoFile, VideoFileReader, VideoFormat};
+pub use reader::{scan_video_files, RecordingFrames, VideoFile, VideoFileReader, VideoFormat};
 
 // Re-export commonly used types
 pub use types::{
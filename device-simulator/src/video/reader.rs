@@ -1,5 +1,9 @@
-use common::Result;
+use common::demux::{Container, ElementaryStream};
+use common::h264::AccessUnit;
+use common::{h264, mp4, ts, KeyframeEntry, Result, VideoCodec};
+use std::io::{BufRead, SeekFrom};
 use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tracing::debug;
 use walkdir::WalkDir;
 
@@ -8,36 +12,35 @@ pub struct VideoFile {
     pub path: PathBuf,
     pub name: String,
     pub format: VideoFormat,
+    /// 视频编码格式（MP4/TS 按容器中的视频轨道识别）
+    pub codec: VideoCodec,
     pub size: u64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum VideoFormat {
     H264,
+    H265,
     MP4,
+    MpegTs,
 }
 
 pub struct VideoFileReader {
     file: tokio::fs::File,
-    format: VideoFormat,
     chunk_size: usize,
 }
 
 impl VideoFileReader {
     pub async fn new(path: &Path) -> Result<Self> {
         let file = tokio::fs::File::open(path).await?;
-        let format = detect_format(path);
         
         Ok(Self {
             file,
-            format,
             chunk_size: 256 * 1024, // 256KB chunks
         })
     }
 
     pub async fn read_chunk(&mut self) -> Result<Option<Vec<u8>>> {
-        use tokio::io::AsyncReadExt;
-        
         let mut buffer = vec![0u8; self.chunk_size];
         let n = self.file.read(&mut buffer).await?;
         
@@ -48,9 +51,109 @@ impl VideoFileReader {
         buffer.truncate(n);
         Ok(Some(buffer))
     }
+}
+
+/// 识别容器时读取的文件头长度
+const PROBE_SIZE: usize = 4096;
+
+/// 回放用的录像：访问单元表和帧数据
+///
+/// Annex B 裸流在阻塞线程池中按块扫描建立访问单元表，帧数据按偏移从文件读取，不整体载入内存；
+/// MP4/MPEG-TS 的样本表分散在整个文件中，同样在阻塞线程池中整体解复用。
+pub struct RecordingFrames {
+    pub codec: VideoCodec,
+    /// 访问单元表，无法解析时为空
+    pub units: Vec<AccessUnit>,
+    data: FrameData,
+}
+
+enum FrameData {
+    /// 解复用得到的 Annex B 基本流
+    Demuxed(Vec<u8>),
+    /// 裸流文件，偏移即文件偏移
+    File(tokio::fs::File),
+}
+
+impl RecordingFrames {
+    /// 读取文件头识别容器；裸流文件头中找不到 NAL 时返回 None
+    pub async fn probe(path: &Path) -> Result<Option<Container>> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut probe = Vec::with_capacity(PROBE_SIZE);
+        (&mut file).take(PROBE_SIZE as u64).read_to_end(&mut probe).await?;
+
+        Ok(if mp4::is_mp4(&probe) {
+            Some(Container::Mp4)
+        } else if ts::is_mpeg_ts(&probe) {
+            Some(Container::MpegTs)
+        } else if !h264::nal_units(&probe).is_empty() {
+            Some(Container::AnnexB)
+        } else {
+            None
+        })
+    }
+
+    /// 建立访问单元表，`assumed_fps` 在码流中没有帧率信息时使用
+    pub async fn open(path: &Path, container: Container, assumed_fps: f64) -> Result<Self> {
+        let path = path.to_path_buf();
+
+        if container != Container::AnnexB {
+            let stream = tokio::task::spawn_blocking(move || {
+                std::fs::read(path).map(|data| ElementaryStream::parse(data, assumed_fps))
+            })
+            .await
+            .map_err(std::io::Error::other)??;
+            return Ok(Self {
+                codec: stream.codec,
+                units: stream.units,
+                data: FrameData::Demuxed(stream.data),
+            });
+        }
+
+        let scan_path = path.clone();
+        let (codec, units) = tokio::task::spawn_blocking(move || -> std::io::Result<_> {
+            let mut reader = std::io::BufReader::with_capacity(PROBE_SIZE, std::fs::File::open(scan_path)?);
+            let head = reader.fill_buf()?;
+            let codec = common::demux::detect_codec(head);
+            let frame_rate = match codec {
+                VideoCodec::H264 => h264::detect_frame_rate(head).unwrap_or(assumed_fps),
+                VideoCodec::H265 => assumed_fps,
+            };
+            Ok((codec, common::demux::index_annex_b(reader, codec, frame_rate)?))
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+
+        Ok(Self {
+            codec,
+            units,
+            data: FrameData::File(tokio::fs::File::open(path).await?),
+        })
+    }
 
-    pub fn format(&self) -> &VideoFormat {
-        &self.format
+    /// 关键帧索引，偏移与 `read_frame` 一致
+    pub fn keyframe_index(&self) -> Vec<KeyframeEntry> {
+        self.units
+            .iter()
+            .filter(|unit| unit.is_keyframe)
+            .map(|unit| KeyframeEntry {
+                timestamp: unit.timestamp,
+                file_offset: unit.offset,
+                frame_size: unit.size,
+            })
+            .collect()
+    }
+
+    /// 读取一帧的 Annex B 数据
+    pub async fn read_frame(&mut self, offset: u64, size: u32) -> Result<Vec<u8>> {
+        match &mut self.data {
+            FrameData::Demuxed(data) => Ok(data[offset as usize..][..size as usize].to_vec()),
+            FrameData::File(file) => {
+                file.seek(SeekFrom::Start(offset)).await?;
+                let mut frame = vec![0u8; size as usize];
+                file.read_exact(&mut frame).await?;
+                Ok(frame)
+            }
+        }
     }
 }
 
@@ -73,10 +176,11 @@ pub fn scan_video_files(dir: &Path) -> Result<Vec<VideoFile>> {
         let path = entry.path();
         if let Some(ext) = path.extension() {
             let ext_str = ext.to_string_lossy().to_lowercase();
-            if ext_str == "h264" || ext_str == "264" || ext_str == "mp4" {
+            if Container::from_extension(&ext_str).is_some() {
                 let metadata = std::fs::metadata(path)?;
                 let format = detect_format(path);
-                
+                let codec = detect_codec(path, &format);
+
                 files.push(VideoFile {
                     path: path.to_path_buf(),
                     name: path
@@ -85,6 +189,7 @@ pub fn scan_video_files(dir: &Path) -> Result<Vec<VideoFile>> {
                         .unwrap_or("unknown")
                         .to_string(),
                     format,
+                    codec,
                     size: metadata.len(),
                 });
             }
@@ -99,10 +204,57 @@ fn detect_format(path: &Path) -> VideoFormat {
         let ext_str = ext.to_string_lossy().to_lowercase();
         match ext_str.as_str() {
             "h264" | "264" => VideoFormat::H264,
+            "h265" | "265" | "hevc" => VideoFormat::H265,
             "mp4" => VideoFormat::MP4,
+            "ts" | "m2ts" | "mts" => VideoFormat::MpegTs,
             _ => VideoFormat::H264,
         }
     } else {
         VideoFormat::H264
     }
 }
+
+fn detect_codec(path: &Path, format: &VideoFormat) -> VideoCodec {
+    match format {
+        VideoFormat::H264 => VideoCodec::H264,
+        VideoFormat::H265 => VideoCodec::H265,
+        VideoFormat::MP4 | VideoFormat::MpegTs => std::fs::read(path)
+            .ok()
+            .and_then(|data| common::demux::probe_codec(&data))
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recording_frames_read_annex_b_from_file() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+        data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+        for frame in 0..6u8 {
+            let nal_header = if frame % 3 == 0 { 0x65 } else { 0x41 };
+            data.extend_from_slice(&[0, 0, 0, 1, nal_header, 0x88, frame]);
+        }
+        let path = std::env::temp_dir().join(format!("recording_frames_{}.h264", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(RecordingFrames::probe(&path).await.unwrap(), Some(Container::AnnexB));
+        let mut recording = RecordingFrames::open(&path, Container::AnnexB, 25.0).await.unwrap();
+        assert_eq!(recording.units, h264::scan_access_units(&data, 25.0));
+
+        // 关键帧按偏移从文件读取，第一个关键帧带参数集
+        let keyframes = recording.keyframe_index();
+        assert_eq!(keyframes.len(), 2);
+        let first = recording.read_frame(keyframes[0].file_offset, keyframes[0].frame_size).await.unwrap();
+        assert_eq!(first, data[..23]);
+        let second = recording.read_frame(keyframes[1].file_offset, keyframes[1].frame_size).await.unwrap();
+        assert_eq!(second, [0, 0, 0, 1, 0x65, 0x88, 3]);
+
+        std::fs::write(&path, b"not a recording").unwrap();
+        assert_eq!(RecordingFrames::probe(&path).await.unwrap(), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
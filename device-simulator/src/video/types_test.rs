@@ -99,13 +99,19 @@ mod tests {
 
     #[test]
     fn test_drop_frame_strategy() {
-        let strategy1 = DropFrameStrategy::DropNone;
-        let strategy2 = DropFrameStrategy::DropNonKeyframes;
-        let strategy3 = DropFrameStrategy::DropByRate(0.5);
-        let strategy4 = DropFrameStrategy::Adaptive;
+        let strategy1 = DropFrameStrategy {
+            drop_b_frames: false,
+            drop_p_frames: false,
+            keep_key_frames_only: false,
+            adaptive_dropping: false,
+        };
+        let strategy2 = DropFrameStrategy {
+            keep_key_frames_only: true,
+            ..strategy1.clone()
+        };
 
-        assert_eq!(strategy1, DropFrameStrategy::DropNone);
-        assert_eq!(strategy2, DropFrameStrategy::DropNonKeyframes);
+        assert_eq!(strategy1, strategy1.clone());
+        assert!(strategy2.keep_key_frames_only);
         assert_ne!(strategy1, strategy2);
     }
 
@@ -5,6 +5,7 @@ use tracing::{info, error, Level};
 use tracing_subscriber;
 
 use video_streaming_uploader::on_demand_uploader::OnDemandUploader;
+use video_streaming_uploader::spool::SegmentSpool;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -42,6 +43,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .help("Directory to scan for video files")
                 .required(false)
         )
+        .arg(
+            Arg::new("spool-dir")
+                .long("spool-dir")
+                .value_name("SPOOL_DIR")
+                .help("Directory for journaling live segments while the platform is unreachable")
+                .required(false)
+        )
+        .arg(
+            Arg::new("spool-max-mb")
+                .long("spool-max-mb")
+                .value_name("MB")
+                .help("Maximum size of the spool directory in MB")
+                .default_value("512")
+        )
         .get_matches();
 
     let server_addr: SocketAddr = matches.get_one::<String>("server")
@@ -51,6 +66,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 创建按需上传管理器
     let mut uploader = OnDemandUploader::new();
+    if let Some(spool_dir) = matches.get_one::<String>("spool-dir") {
+        let max_mb: u64 = matches.get_one::<String>("spool-max-mb")
+            .unwrap()
+            .parse()
+            .expect("Invalid spool size");
+        let spool = SegmentSpool::open(spool_dir, max_mb * 1024 * 1024)?;
+        uploader = uploader.with_spool(spool);
+        info!("Live segment spool enabled at {} ({} MB)", spool_dir, max_mb);
+    }
 
     info!("Starting video streaming client...");
     info!("Connecting to platform server at: {}", server_addr);
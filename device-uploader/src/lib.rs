@@ -27,6 +27,7 @@ pub mod serde_helpers;
 pub mod transmission_config;
 pub mod mock_server;
 pub mod on_demand_uploader;
+pub mod spool;
 pub mod live_encoder;
 pub mod h264_encoder;
 
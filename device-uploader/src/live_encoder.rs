@@ -1,12 +1,15 @@
 use std::sync::Arc;
 use std::time::{SystemTime, Instant};
 use std::process::{Stdio};
-use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio::sync::{mpsc, Mutex, Notify, RwLock};
 use tokio::io::{AsyncReadExt, BufReader};
 use tokio::process::Command as TokioCommand;
 use tracing::{info, warn, error, debug};
 use uuid::Uuid;
 
+use common::demux::AccessUnitSplitter;
+use common::VideoCodec;
+
 use crate::types::{VideoSegment, Segment, SegmentMetadata};
 use crate::errors::TransportError;
 use crate::mock_server::LiveStreamQuality;
@@ -18,10 +21,10 @@ pub struct LiveH264Encoder {
     config: LiveEncoderConfig,
     /// 编码状态
     encoding_state: Arc<RwLock<EncodingState>>,
-    /// 输出数据发送器
-    output_sender: Option<mpsc::Sender<Vec<u8>>>,
+    /// 输出数据发送器（由完整访问单元组成的数据, 是否为强制关键帧后的第一块）
+    output_sender: Option<mpsc::Sender<(Vec<u8>, bool)>>,
     /// 输出数据接收器
-    output_receiver: Option<mpsc::Receiver<Vec<u8>>>,
+    output_receiver: Option<mpsc::Receiver<(Vec<u8>, bool)>>,
     /// 时间戳生成器
     timestamp_generator: TimestampGenerator,
     /// 性能统计
@@ -30,6 +33,10 @@ pub struct LiveH264Encoder {
     encoding_task: Option<tokio::task::JoinHandle<()>>,
     /// FFmpeg进程
     ffmpeg_process: Option<tokio::process::Child>,
+    /// 关键帧请求（平台 KeyframeRequest）
+    keyframe_request: Arc<Notify>,
+    /// 当前 GOP 起始的分片序号
+    gop_start: u64,
 }
 
 /// 编码器配置
@@ -118,6 +125,8 @@ impl LiveH264Encoder {
             })),
             encoding_task: None,
             ffmpeg_process: None,
+            keyframe_request: Arc::new(Notify::new()),
+            gop_start: 0,
         }
     }
 
@@ -145,6 +154,7 @@ impl LiveH264Encoder {
         let stats = self.stats.clone();
         let config = self.config.clone();
         let sender = self.output_sender.as_ref().unwrap().clone();
+        let keyframe_request = self.keyframe_request.clone();
 
         let stream_id_clone = stream_id.clone();
         let task = tokio::spawn(async move {
@@ -154,6 +164,7 @@ impl LiveH264Encoder {
                 sender,
                 encoding_state,
                 stats,
+                keyframe_request,
             ).await {
                 error!("FFmpeg encoding loop failed: {}", e);
             }
@@ -163,6 +174,7 @@ impl LiveH264Encoder {
 
         // 重置时间戳生成器
         self.timestamp_generator.reset();
+        self.gop_start = 0;
 
         info!("FFmpeg live encoding started successfully for stream: {}", stream_id);
         Ok(())
@@ -203,13 +215,23 @@ impl LiveH264Encoder {
     /// 获取下一个编码分片
     pub async fn get_next_segment(&mut self) -> Option<Segment> {
         if let Some(receiver) = &mut self.output_receiver {
-            if let Ok(data) = receiver.try_recv() {
-                return Some(self.create_video_segment(data).await);
+            if let Ok((data, forced_keyframe)) = receiver.try_recv() {
+                return Some(self.create_video_segment(data, forced_keyframe).await);
             }
         }
         None
     }
 
+    /// 请求下一帧输出关键帧（IDR）
+    pub fn request_keyframe(&self) {
+        self.keyframe_request.notify_one();
+    }
+
+    /// 关键帧请求句柄，编码器被传输任务持有时用于从控制消息处理中请求关键帧
+    pub fn keyframe_requester(&self) -> Arc<Notify> {
+        self.keyframe_request.clone()
+    }
+
     /// 获取当前编码状态
     pub async fn get_encoding_state(&self) -> EncodingState {
         self.encoding_state.read().await.clone()
@@ -224,91 +246,21 @@ impl LiveH264Encoder {
     async fn ffmpeg_encoding_loop(
         stream_id: String,
         config: LiveEncoderConfig,
-        sender: mpsc::Sender<Vec<u8>>,
+        sender: mpsc::Sender<(Vec<u8>, bool)>,
         encoding_state: Arc<RwLock<EncodingState>>,
         stats: Arc<Mutex<EncodingStats>>,
+        keyframe_request: Arc<Notify>,
     ) -> Result<(), TransportError> {
         info!("Starting FFmpeg encoding loop for stream: {}", stream_id);
         
-        // 构建FFmpeg命令
-        let mut ffmpeg_cmd = TokioCommand::new("ffmpeg");
-        
-        // 输入配置 - 使用avfoundation录制屏幕
-        ffmpeg_cmd
-            .arg("-f").arg("avfoundation")
-            .arg("-i").arg("4") // macOS屏幕序号4
-            .arg("-r").arg(config.quality.fps.to_string()) // 帧率
-            .arg("-s").arg(format!("{}x{}", config.quality.width, config.quality.height)) // 分辨率
-            
-            // H.264编码配置
-            .arg("-c:v").arg("libx264")
-            .arg("-preset").arg("ultrafast") // 最快编码速度
-            .arg("-tune").arg("zerolatency") // 零延迟调优
-            .arg("-profile:v").arg("baseline") // Baseline Profile
-            .arg("-level").arg("3.1") // Level 3.1
-            .arg("-pix_fmt").arg("yuv420p") // 像素格式
-            .arg("-b:v").arg(format!("{}k", config.quality.bitrate_kbps)) // 码率
-            .arg("-g").arg(config.quality.keyframe_interval.to_string()) // GOP大小
-            
-            // 时间戳叠加 (如果启用)
-            .arg("-vf").arg(if config.timestamp_overlay {
-                format!("drawtext=text='%{{pts\\:hms}} | Frame\\: %{{n}} | {}x{} | {}fps':fontcolor=yellow:fontsize=24:box=1:boxcolor=black@0.5:x=10:y=10", 
-                    config.quality.width, config.quality.height, config.quality.fps)
-            } else {
-                "null".to_string()
-            })
-            
-            // 输出配置
-            .arg("-f").arg("h264") // 输出格式为原始H.264
-            .arg("-") // 输出到stdout
-            
-            // 其他选项
-            .arg("-y") // 覆盖输出文件
-            .arg("-loglevel").arg("error"); // 只显示错误日志
+        let (mut child, stdout) = Self::spawn_ffmpeg(&stream_id, &config)?;
 
-        // 配置进程
-        ffmpeg_cmd
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .stdin(Stdio::null());
-
-        info!("Starting FFmpeg process with command: {:?}", ffmpeg_cmd);
-        
-        // 启动FFmpeg进程
-        let mut child = ffmpeg_cmd.spawn()
-            .map_err(|e| TransportError::ConfigurationError { 
-                message: format!("Failed to start FFmpeg: {}. Make sure FFmpeg is installed and avfoundation is available.", e) 
-            })?;
-
-        let stdout = child.stdout.take()
-            .ok_or_else(|| TransportError::ConfigurationError { 
-                message: "Failed to get FFmpeg stdout".to_string() 
-            })?;
-
-        let stderr = child.stderr.take()
-            .ok_or_else(|| TransportError::ConfigurationError { 
-                message: "Failed to get FFmpeg stderr".to_string() 
-            })?;
-
-        // 启动错误日志监控
-        let stream_id_clone = stream_id.clone();
-        tokio::spawn(async move {
-            let mut stderr_reader = BufReader::new(stderr);
-            let mut buffer = Vec::new();
-            while let Ok(n) = stderr_reader.read_buf(&mut buffer).await {
-                if n == 0 { break; }
-                let stderr_output = String::from_utf8_lossy(&buffer);
-                if !stderr_output.trim().is_empty() {
-                    warn!("FFmpeg stderr ({}): {}", stream_id_clone, stderr_output.trim());
-                }
-                buffer.clear();
-            }
-        });
-
-        // 读取H.264数据流
+        // 读取H.264数据流，只转发完整的访问单元，正在输出的那一帧留在切分器中
         let mut stdout_reader = BufReader::new(stdout);
+        let mut splitter = AccessUnitSplitter::new(VideoCodec::H264);
         let mut buffer = vec![0u8; 64 * 1024]; // 64KB缓冲区
         let mut frame_count = 0u64;
+        let mut forced_keyframe = false;
         let start_time = Instant::now();
 
         loop {
@@ -320,21 +272,47 @@ impl LiveH264Encoder {
                 }
             }
 
-            // 读取数据
-            match stdout_reader.read(&mut buffer).await {
+            // 读取数据（等待期间响应关键帧请求）
+            let read = tokio::select! {
+                result = stdout_reader.read(&mut buffer) => result,
+                _ = keyframe_request.notified() => {
+                    // FFmpeg 命令行无法在运行中插入 IDR：重启编码进程，新进程的第一帧必然是 IDR。
+                    // 重启的频率受平台限制：平台把限流间隔内多个订阅者的关键帧请求合并为一次
+                    info!("Keyframe requested, restarting FFmpeg for stream: {}", stream_id);
+                    if let Err(e) = child.kill().await {
+                        warn!("Failed to kill FFmpeg process: {}", e);
+                    }
+                    let (new_child, new_stdout) = Self::spawn_ffmpeg(&stream_id, &config)?;
+                    child = new_child;
+                    stdout_reader = BufReader::new(new_stdout);
+                    // 旧进程未输出完的访问单元丢弃，不能拼接在新进程的 SPS/PPS/IDR 前面
+                    splitter = AccessUnitSplitter::new(VideoCodec::H264);
+                    forced_keyframe = true;
+                    continue;
+                }
+            };
+
+            match read {
                 Ok(0) => {
                     info!("FFmpeg process ended (EOF)");
+                    if let Some(frame) = splitter.finish() {
+                        let _ = sender.send((frame.data, std::mem::take(&mut forced_keyframe))).await;
+                    }
                     break;
                 }
                 Ok(n) => {
-                    let data = buffer[..n].to_vec();
+                    let frames = splitter.push(&buffer[..n]);
+                    if frames.is_empty() {
+                        continue;
+                    }
+                    let data: Vec<u8> = frames.into_iter().flat_map(|frame| frame.data).collect();
                     
                     // 更新统计信息
                     frame_count += 1;
                     {
                         let mut state = encoding_state.write().await;
                         state.frames_encoded = frame_count;
-                        state.bytes_encoded += n as u64;
+                        state.bytes_encoded += data.len() as u64;
                         
                         if let Some(start_time) = state.start_time {
                             if let Ok(elapsed) = SystemTime::now().duration_since(start_time) {
@@ -349,12 +327,12 @@ impl LiveH264Encoder {
                         let elapsed_secs = start_time.elapsed().as_secs_f64();
                         if elapsed_secs > 0.0 {
                             stats_guard.encoding_fps = frame_count as f64 / elapsed_secs;
-                            stats_guard.bitrate_kbps = (frame_count * n as u64 * 8) as f64 / elapsed_secs / 1000.0;
+                            stats_guard.bitrate_kbps = (frame_count * data.len() as u64 * 8) as f64 / elapsed_secs / 1000.0;
                         }
                     }
 
                     // 发送数据
-                    if let Err(e) = sender.send(data).await {
+                    if let Err(e) = sender.send((data, std::mem::take(&mut forced_keyframe))).await {
                         error!("Failed to send H.264 data: {}", e);
                         break;
                     }
@@ -392,9 +370,99 @@ impl LiveH264Encoder {
         Ok(())
     }
 
+    /// 启动FFmpeg编码进程，返回进程及其H.264输出
+    fn spawn_ffmpeg(
+        stream_id: &str,
+        config: &LiveEncoderConfig,
+    ) -> Result<(tokio::process::Child, tokio::process::ChildStdout), TransportError> {
+        // 构建FFmpeg命令
+        let mut ffmpeg_cmd = TokioCommand::new("ffmpeg");
+        
+        // 输入配置 - 使用avfoundation录制屏幕
+        ffmpeg_cmd
+            .arg("-f").arg("avfoundation")
+            .arg("-i").arg("4") // macOS屏幕序号4
+            .arg("-r").arg(config.quality.fps.to_string()) // 帧率
+            .arg("-s").arg(format!("{}x{}", config.quality.width, config.quality.height)) // 分辨率
+            
+            // H.264编码配置
+            .arg("-c:v").arg("libx264")
+            .arg("-preset").arg("ultrafast") // 最快编码速度
+            .arg("-tune").arg("zerolatency") // 零延迟调优
+            .arg("-profile:v").arg("baseline") // Baseline Profile
+            .arg("-level").arg("3.1") // Level 3.1
+            .arg("-pix_fmt").arg("yuv420p") // 像素格式
+            .arg("-b:v").arg(format!("{}k", config.quality.bitrate_kbps)) // 码率
+            .arg("-g").arg(config.quality.keyframe_interval.to_string()) // GOP大小
+            
+            // 时间戳叠加 (如果启用)
+            .arg("-vf").arg(if config.timestamp_overlay {
+                format!("drawtext=text='%{{pts\\:hms}} | Frame\\: %{{n}} | {}x{} | {}fps':fontcolor=yellow:fontsize=24:box=1:boxcolor=black@0.5:x=10:y=10", 
+                    config.quality.width, config.quality.height, config.quality.fps)
+            } else {
+                "null".to_string()
+            })
+            
+            // 输出配置
+            .arg("-f").arg("h264") // 输出格式为原始H.264
+            .arg("-") // 输出到stdout
+            
+            // 其他选项
+            .arg("-y") // 覆盖输出文件
+            .arg("-loglevel").arg("error"); // 只显示错误日志
+
+        // 配置进程
+        ffmpeg_cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdin(Stdio::null());
+
+        info!("Starting FFmpeg process with command: {:?}", ffmpeg_cmd);
+        
+        // 启动FFmpeg进程
+        let mut child = ffmpeg_cmd.spawn()
+            .map_err(|e| TransportError::ConfigurationError { 
+                message: format!("Failed to start FFmpeg: {}. Make sure FFmpeg is installed and avfoundation is available.", e) 
+            })?;
+
+        let stdout = child.stdout.take()
+            .ok_or_else(|| TransportError::ConfigurationError { 
+                message: "Failed to get FFmpeg stdout".to_string() 
+            })?;
+
+        let stderr = child.stderr.take()
+            .ok_or_else(|| TransportError::ConfigurationError { 
+                message: "Failed to get FFmpeg stderr".to_string() 
+            })?;
+
+        // 启动错误日志监控
+        let stream_id_clone = stream_id.to_string();
+        tokio::spawn(async move {
+            let mut stderr_reader = BufReader::new(stderr);
+            let mut buffer = Vec::new();
+            while let Ok(n) = stderr_reader.read_buf(&mut buffer).await {
+                if n == 0 { break; }
+                let stderr_output = String::from_utf8_lossy(&buffer);
+                if !stderr_output.trim().is_empty() {
+                    warn!("FFmpeg stderr ({}): {}", stream_id_clone, stderr_output.trim());
+                }
+                buffer.clear();
+            }
+        });
+
+        Ok((child, stdout))
+    }
+
     /// 创建视频分片
-    async fn create_video_segment(&mut self, data: Vec<u8>) -> Segment {
+    ///
+    /// 强制关键帧后的第一块数据标记为关键帧，并从这里重新计算 GOP
+    async fn create_video_segment(&mut self, data: Vec<u8>, forced_keyframe: bool) -> Segment {
         let timestamp = self.timestamp_generator.next_timestamp();
+        if forced_keyframe {
+            self.gop_start = self.timestamp_generator.frame_count - 1;
+        }
+        let gop_position = self.timestamp_generator.frame_count - self.gop_start;
+        let is_key_frame = gop_position % self.config.quality.keyframe_interval as u64 == 1;
         let current_ms = self.timestamp_generator.current_timestamp_ms();
         
         // 打印时间戳用于延迟对比
@@ -411,7 +479,7 @@ impl LiveH264Encoder {
             timestamp,
             duration: 1.0 / self.config.quality.fps as f64,
             frame_count: 1,
-            is_key_frame: self.timestamp_generator.frame_count % self.config.quality.keyframe_interval as u64 == 1,
+            is_key_frame,
             metadata: SegmentMetadata {
                 frame_indices: vec![self.timestamp_generator.frame_count as usize],
                 key_frame_positions: vec![],
@@ -15,6 +15,7 @@ use crate::types::{
     StatusCode, StreamType, QUICConnection
 };
 use crate::errors::TransportError;
+use crate::transport::SEGMENT_FLAG_GAP_BACKFILL;
 
 /// 模拟平台服务器，用于接收和处理视频流上传
 pub struct MockPlatformServer {
@@ -549,6 +550,26 @@ impl MockPlatformServer {
 
         // 解析分片数据
         let segment = Self::parse_segment_data(&data[1..], stream_type)?;
+
+        // 断档录像单独落盘，不混入当前直播文件
+        if segment.gap_backfill {
+            let gap_path = PathBuf::from("recv_videos")
+                .join("gap")
+                .join(format!("{}.h264", session_id));
+            if let Err(e) = Self::write_segment_to_file(
+                &gap_path,
+                &segment.data,
+                segment.timestamp,
+                stream_type,
+                receive_duration,
+            ).await {
+                error!("Failed to write gap back-fill segment: {}", e);
+            } else {
+                info!("Received gap back-fill segment {:.3}s ({} bytes) -> {:?}",
+                      segment.timestamp, segment.data.len(), gap_path);
+            }
+            return Ok(());
+        }
         
         // 记录接收到的分片并写入文件
         let mut sessions_guard = sessions.lock().await;
@@ -698,7 +719,9 @@ impl MockPlatformServer {
                     data[28], data[29], data[30], data[31],
                 ]);
 
-                // 跳过帧数(4字节) + 标志(1字节)
+                // 跳过帧数(4字节)，解析标志(1字节)
+                let gap_backfill = data[36] & SEGMENT_FLAG_GAP_BACKFILL != 0;
+
                 // 解析数据长度 (4字节)
                 let data_length = u32::from_be_bytes([
                     data[37], data[38], data[39], data[40],
@@ -716,6 +739,7 @@ impl MockPlatformServer {
                     timestamp,
                     duration,
                     data: segment_data,
+                    gap_backfill,
                 })
             }
             StreamType::Audio => {
@@ -759,6 +783,7 @@ impl MockPlatformServer {
                     timestamp,
                     duration,
                     data: segment_data,
+                    gap_backfill: false,
                 })
             }
         }
@@ -1169,6 +1194,8 @@ struct SegmentData {
     timestamp: f64,
     duration: f64,
     data: Vec<u8>,
+    /// 断网期间缓存、重连后补传的断档录像
+    gap_backfill: bool,
 }
 
 /// 文件请求载荷
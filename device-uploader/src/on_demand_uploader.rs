@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::SystemTime;
-use tokio::sync::{Mutex, mpsc, RwLock};
+use tokio::sync::{Mutex, mpsc, Notify, RwLock};
 use tracing::{info, warn, error, debug};
 use uuid::Uuid;
 
@@ -21,6 +21,7 @@ use crate::timeline_manager::{TimelineManager, CacheStats};
 use crate::ffmpeg_cli_parser::TimelineData;
 use crate::mock_server::{FileRequestPayload, PlaybackCommand, LiveStreamQuality};
 use crate::live_encoder::{LiveH264Encoder, LiveEncoderConfig, OutputFormat};
+use crate::spool::{SegmentSpool, SpoolWrite};
 
 /// 按需上传管理器 - 等待平台请求后才开始上传
 pub struct OnDemandUploader {
@@ -50,8 +51,15 @@ pub struct OnDemandUploader {
     live_encoder: Arc<Mutex<Option<LiveH264Encoder>>>,
     /// 活跃的直播会话
     live_sessions: Arc<RwLock<HashMap<String, LiveSession>>>,
+    /// 直播会话的关键帧请求句柄
+    live_keyframe_requesters: LiveKeyframeRequesters,
+    /// 断网缓存，平台不可达时缓存直播分片
+    spool: Option<Arc<SegmentSpool>>,
 }
 
+/// 直播会话ID → 编码器关键帧请求句柄
+type LiveKeyframeRequesters = Arc<RwLock<HashMap<Uuid, Arc<Notify>>>>;
+
 /// 上传会话信息
 #[derive(Debug, Clone)]
 pub struct UploadSession {
@@ -141,6 +149,9 @@ pub enum PlatformMessage {
         session_id: Uuid,
         filter: Option<String>,
     },
+    KeyframeRequest {
+        session_id: Uuid,
+    },
 }
 
 impl OnDemandUploader {
@@ -160,9 +171,17 @@ impl OnDemandUploader {
             server_connection: None,
             live_encoder: Arc::new(Mutex::new(None)),
             live_sessions: Arc::new(RwLock::new(HashMap::new())),
+            live_keyframe_requesters: Arc::new(RwLock::new(HashMap::new())),
+            spool: None,
         }
     }
 
+    /// 启用断网缓存：直播分片发送失败时写入磁盘，重连后作为断档录像补传
+    pub fn with_spool(mut self, spool: SegmentSpool) -> Self {
+        self.spool = Some(Arc::new(spool));
+        self
+    }
+
     /// 连接到平台服务器
     pub async fn connect_to_platform(&mut self, server_addr: std::net::SocketAddr) -> Result<(), TransportError> {
         info!("Connecting to platform server at {}", server_addr);
@@ -177,6 +196,14 @@ impl OnDemandUploader {
             }
         ).await?;
 
+        // 补传上次运行（或上次连接）遗留的断档录像
+        if let Some(spool) = &self.spool {
+            if let Err(e) = spool.seal() {
+                warn!("Failed to seal spool journal: {}", e);
+            }
+            Self::spawn_backfill(spool.clone(), self.transport.clone(), connection.clone());
+        }
+
         self.server_connection = Some(connection);
         
         // 启动消息处理循环
@@ -333,6 +360,8 @@ impl OnDemandUploader {
         let transport = self.transport.clone();
         let controller = self.controller.clone();
         let monitor = self.monitor.clone();
+        let live_keyframe_requesters = self.live_keyframe_requesters.clone();
+        let spool = self.spool.clone();
 
         if let Some(mut receiver) = self.control_receiver.take() {
             let connection = self.server_connection.clone().unwrap();
@@ -349,6 +378,8 @@ impl OnDemandUploader {
                         controller.clone(),
                         monitor.clone(),
                         connection.clone(),
+                        live_keyframe_requesters.clone(),
+                        spool.clone(),
                     ).await {
                         error!("Error processing platform message: {}", e);
                     }
@@ -527,6 +558,17 @@ impl OnDemandUploader {
                     })?;
             }
             
+            MessageType::KeyframeRequest => {
+                let platform_msg = PlatformMessage::KeyframeRequest {
+                    session_id: message.session_id,
+                };
+                
+                sender.send(platform_msg).await
+                    .map_err(|e| TransportError::NetworkError { 
+                        message: format!("Failed to forward keyframe request: {}", e) 
+                    })?;
+            }
+            
             MessageType::SessionEnd => {
                 let platform_msg = PlatformMessage::SessionEnd {
                     session_id: message.session_id,
@@ -611,6 +653,8 @@ impl OnDemandUploader {
         controller: Arc<Mutex<DefaultPlaybackController>>,
         monitor: Arc<Mutex<DefaultPerformanceMonitor>>,
         connection: QUICConnection,
+        live_keyframe_requesters: LiveKeyframeRequesters,
+        spool: Option<Arc<SegmentSpool>>,
     ) -> Result<(), UploadManagerError> {
         match message {
             PlatformMessage::FileRequest { 
@@ -798,6 +842,8 @@ impl OnDemandUploader {
                             active_sessions.clone(),
                             transport.clone(),
                             connection.clone(),
+                            live_keyframe_requesters.clone(),
+                            spool.clone(),
                         ).await?;
                     }
                     
@@ -818,6 +864,16 @@ impl OnDemandUploader {
                 }
             }
             
+            PlatformMessage::KeyframeRequest { session_id } => {
+                match live_keyframe_requesters.read().await.get(&session_id) {
+                    Some(requester) => {
+                        debug!("Keyframe requested for live session {}", session_id);
+                        requester.notify_one();
+                    }
+                    None => warn!("Keyframe request for unknown live session: {}", session_id),
+                }
+            }
+            
             PlatformMessage::SessionEnd { session_id } => {
                 info!("Ending session: {}", session_id);
                 active_sessions.write().await.remove(&session_id);
@@ -1885,6 +1941,8 @@ impl OnDemandUploader {
         active_sessions: Arc<RwLock<HashMap<Uuid, UploadSession>>>,
         transport: Arc<DefaultQUICTransport>,
         connection: QUICConnection,
+        live_keyframe_requesters: LiveKeyframeRequesters,
+        spool: Option<Arc<SegmentSpool>>,
     ) -> Result<(), UploadManagerError> {
         info!("Starting live stream {} for session {}", stream_id, session_id);
         
@@ -1904,6 +1962,10 @@ impl OnDemandUploader {
         match encoder.start_encoding(stream_id.clone()).await {
             Ok(_) => {
                 info!("Live encoder started successfully for stream: {}", stream_id);
+                live_keyframe_requesters
+                    .write()
+                    .await
+                    .insert(session_id, encoder.keyframe_requester());
                 
                 // 启动传输任务
                 tokio::spawn(async move {
@@ -1914,7 +1976,9 @@ impl OnDemandUploader {
                         active_sessions,
                         transport,
                         connection,
+                        spool,
                     ).await;
+                    live_keyframe_requesters.write().await.remove(&session_id);
                 });
                 
                 Ok(())
@@ -1948,9 +2012,13 @@ impl OnDemandUploader {
         active_sessions: Arc<RwLock<HashMap<Uuid, UploadSession>>>,
         transport: Arc<DefaultQUICTransport>,
         mut connection: QUICConnection,
+        spool: Option<Arc<SegmentSpool>>,
     ) {
         info!("Starting live stream transmission loop for stream: {}", stream_id);
         
+        // 当前中断编号，None 表示平台可达
+        let mut spool_outage: Option<u64> = None;
+        
         let mut frame_count = 0u64;
         let mut total_bytes = 0u64;
         let start_time = std::time::Instant::now();
@@ -1980,6 +2048,12 @@ impl OnDemandUploader {
                     crate::types::Segment::Audio(a) => a.data.len(),
                 };
                 
+                // 启用断网缓存时保留视频分片副本，发送失败后写入缓存
+                let spooled_copy = match (&spool, &segment) {
+                    (Some(_), crate::types::Segment::Video(v)) => Some(v.clone()),
+                    _ => None,
+                };
+                
                 // 发送分片
                 match transport.send_segment(&mut connection, segment).await {
                     Ok(_) => {
@@ -1987,6 +2061,15 @@ impl OnDemandUploader {
                         total_bytes += segment_size as u64;
                         last_activity = std::time::Instant::now();
                         
+                        // 平台恢复可达：封存本次中断的日志并在后台补传
+                        if let (Some(spool), Some(_)) = (&spool, spool_outage.take()) {
+                            info!("Platform reachable again, back-filling spooled segments for stream {}", stream_id);
+                            if let Err(e) = spool.seal() {
+                                warn!("Failed to seal spool journal: {}", e);
+                            }
+                            Self::spawn_backfill(spool.clone(), transport.clone(), connection.clone());
+                        }
+                        
                         // 每10帧打印一次统计信息（更频繁的日志）
                         if frame_count % 10 == 0 {
                             let elapsed = start_time.elapsed();
@@ -1999,6 +2082,19 @@ impl OnDemandUploader {
                     }
                     Err(e) => {
                         error!("Failed to send live stream segment: {}", e);
+                        if let (Some(spool), Some(video)) = (&spool, spooled_copy) {
+                            let outage = *spool_outage.get_or_insert_with(|| spool.outage());
+                            match spool.append(outage, &video) {
+                                Ok(SpoolWrite::Written) | Ok(SpoolWrite::Dropped) => {}
+                                Ok(SpoolWrite::Sealed) => {
+                                    // 其他直播流已封存了这次中断，开始新的中断
+                                    let outage = spool.outage();
+                                    spool_outage = Some(outage);
+                                    let _ = spool.append(outage, &video);
+                                }
+                                Err(e) => warn!("Failed to spool live segment: {}", e),
+                            }
+                        }
                         // 不要立即退出，尝试继续
                         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                     }
@@ -2031,6 +2127,61 @@ impl OnDemandUploader {
               stream_id, frame_count, elapsed.as_secs_f64(), fps, mbps);
     }
     
+    /// 在后台补传已封存的断档录像，失败时保留日志等待下次重连
+    fn spawn_backfill(
+        spool: Arc<SegmentSpool>,
+        transport: Arc<DefaultQUICTransport>,
+        mut connection: QUICConnection,
+    ) {
+        if !spool.begin_backfill() {
+            debug!("Back-fill already running, skipping");
+            return;
+        }
+        
+        tokio::spawn(async move {
+            Self::backfill_journals(&spool, &transport, &mut connection).await;
+            spool.end_backfill();
+        });
+    }
+    
+    /// 按中断顺序逐个补传日志，整份日志发送成功后才删除
+    async fn backfill_journals(
+        spool: &SegmentSpool,
+        transport: &DefaultQUICTransport,
+        connection: &mut QUICConnection,
+    ) {
+        let journals = match spool.sealed_journals() {
+            Ok(journals) => journals,
+            Err(e) => {
+                warn!("Failed to list spool journals: {}", e);
+                return;
+            }
+        };
+        
+        for journal in journals {
+            let segments = match spool.read_journal(&journal) {
+                Ok(segments) => segments,
+                Err(e) => {
+                    warn!("Failed to read spool journal {:?}: {}", journal, e);
+                    continue;
+                }
+            };
+            
+            let segment_count = segments.len();
+            for segment in segments {
+                if let Err(e) = transport.send_backfill_segment(connection, segment).await {
+                    warn!("Back-fill of {:?} interrupted, keeping journal: {}", journal, e);
+                    return;
+                }
+            }
+            
+            info!("Back-filled {} segments from {:?}", segment_count, journal);
+            if let Err(e) = spool.remove(&journal) {
+                warn!("Failed to remove spool journal {:?}: {}", journal, e);
+            }
+        }
+    }
+    
     /// 标记会话错误
     async fn mark_session_error(
         session_id: Uuid,
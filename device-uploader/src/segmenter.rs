@@ -237,32 +237,20 @@ impl DefaultVideoSegmenter {
     }
     
     /// 公开方法：解析MP4帧
+    ///
+    /// 按视频轨道的样本表（moov/stbl 或分片 moof/trun）返回每个样本的偏移和关键帧标志，
+    /// 只返回完整位于缓冲区内的样本；找不到视频轨道时把整个缓冲区作为一个关键帧。
     pub fn parse_mp4_frames(&self, buffer: &[u8]) -> Vec<(usize, bool)> {
-        let mut frames = Vec::new();
-        let mut i = 0;
-        
-        while i < buffer.len().saturating_sub(8) {
-            // Read box size (4 bytes, big-endian)
-            let box_size = u32::from_be_bytes([
-                buffer[i], buffer[i + 1], buffer[i + 2], buffer[i + 3]
-            ]) as usize;
-            
-            if box_size == 0 || i + box_size > buffer.len() {
-                break;
-            }
-            
-            // Read box type (4 bytes)
-            let box_type = &buffer[i + 4..i + 8];
-            
-            // Check for video sample boxes
-            if box_type == b"mdat" || box_type == b"moof" {
-                // Assume this contains video frames
-                // In a real implementation, we'd parse the actual frame structure
-                frames.push((i, i == 0)); // First frame is key frame
-            }
-            
-            i += box_size;
-        }
+        let mut frames: Vec<(usize, bool)> = common::mp4::parse_video_track(buffer)
+            .map(|track| {
+                track
+                    .samples
+                    .iter()
+                    .filter(|sample| sample.offset + sample.size as u64 <= buffer.len() as u64)
+                    .map(|sample| (sample.offset as usize, sample.is_keyframe))
+                    .collect()
+            })
+            .unwrap_or_default();
         
         if frames.is_empty() {
             frames.push((0, true)); // Fallback: treat entire buffer as one key frame
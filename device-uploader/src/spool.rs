@@ -0,0 +1,324 @@
+// 断网缓存（store-and-forward）
+//
+// 平台不可达时把直播视频分片写入磁盘日志，重连后作为“断档录像”以低于直播流量的
+// 优先级补传（见 `DefaultQUICTransport::send_backfill_segment`），补齐广域网中断
+// 造成的录像缺口。
+
+use crate::transport::SEGMENT_FLAG_KEY_FRAME;
+use crate::types::{SegmentMetadata, VideoSegment};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// 正在写入的日志扩展名
+const ACTIVE_EXTENSION: &str = "journal";
+/// 已封存、等待补传的日志扩展名
+const SEALED_EXTENSION: &str = "gap";
+/// 记录头：ID(16) + 时间戳(8) + 持续时间(8) + 帧数(4) + 标志(1)
+const RECORD_HEADER_LEN: usize = 37;
+
+/// 写入结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpoolWrite {
+    /// 已写入日志
+    Written,
+    /// 缓存已满或尚未遇到关键帧，分片被丢弃
+    Dropped,
+    /// 此次中断已结束（连接已恢复），应停止写入
+    Sealed,
+}
+
+/// 有界磁盘缓存
+///
+/// 每次中断写一个日志文件，重连时封存；目录总大小超过上限后丢弃新分片，
+/// 保证已缓存的录像从关键帧开始连续可解码。
+pub struct SegmentSpool {
+    dir: PathBuf,
+    max_bytes: u64,
+    inner: Mutex<SpoolInner>,
+    /// 同一时间只允许一个补传任务，避免同一日志被重复发送
+    backfilling: AtomicBool,
+}
+
+struct SpoolInner {
+    /// 当前日志（路径、写入器）
+    journal: Option<(PathBuf, BufWriter<File>)>,
+    /// 缓存目录占用的字节数
+    used_bytes: u64,
+    /// 中断编号，封存时递增
+    outage: u64,
+    /// 缓存已满后等待下一次中断重新开始
+    full: bool,
+    /// 本次中断丢弃的分片数
+    dropped: u64,
+}
+
+impl SegmentSpool {
+    /// 打开缓存目录，上次进程退出时未封存的日志直接封存
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut used_bytes = 0;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(ACTIVE_EXTENSION) => {
+                    fs::rename(&path, path.with_extension(SEALED_EXTENSION))?;
+                }
+                Some(SEALED_EXTENSION) => {}
+                _ => continue,
+            }
+            used_bytes += fs::metadata(path.with_extension(SEALED_EXTENSION))?.len();
+        }
+        if used_bytes > 0 {
+            info!("Spool {:?} holds {} bytes of unsent live segments", dir, used_bytes);
+        }
+
+        Ok(Self {
+            dir,
+            max_bytes,
+            inner: Mutex::new(SpoolInner {
+                journal: None,
+                used_bytes,
+                outage: 0,
+                full: false,
+                dropped: 0,
+            }),
+            backfilling: AtomicBool::new(false),
+        })
+    }
+
+    /// 当前中断编号，写入时携带，封存后旧编号的写入返回 `Sealed`
+    pub fn outage(&self) -> u64 {
+        self.inner.lock().unwrap().outage
+    }
+
+    /// 写入一个视频分片，日志总是从关键帧开始
+    pub fn append(&self, outage: u64, segment: &VideoSegment) -> io::Result<SpoolWrite> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.outage != outage {
+            return Ok(SpoolWrite::Sealed);
+        }
+
+        let record = encode_record(segment);
+        let size = 4 + record.len() as u64;
+        if inner.full || inner.used_bytes + size > self.max_bytes {
+            if !inner.full {
+                warn!("Spool {:?} full ({} bytes), dropping live segments", self.dir, inner.used_bytes);
+                inner.full = true;
+            }
+            inner.dropped += 1;
+            return Ok(SpoolWrite::Dropped);
+        }
+
+        if inner.journal.is_none() {
+            if !segment.is_key_frame {
+                inner.dropped += 1;
+                return Ok(SpoolWrite::Dropped);
+            }
+            let started_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let path = self
+                .dir
+                .join(format!("gap_{}", started_ms))
+                .with_extension(ACTIVE_EXTENSION);
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            info!("Platform unreachable, journaling live segments to {:?}", path);
+            inner.journal = Some((path, BufWriter::new(file)));
+        }
+
+        let (_, writer) = inner.journal.as_mut().unwrap();
+        writer.write_all(&(record.len() as u32).to_le_bytes())?;
+        writer.write_all(&record)?;
+        inner.used_bytes += size;
+        Ok(SpoolWrite::Written)
+    }
+
+    /// 结束当前中断：封存日志，之后旧中断编号的写入返回 `Sealed`
+    pub fn seal(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.outage += 1;
+        inner.full = false;
+        let dropped = std::mem::take(&mut inner.dropped);
+
+        if let Some((path, mut writer)) = inner.journal.take() {
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+            fs::rename(&path, path.with_extension(SEALED_EXTENSION))?;
+            info!("Sealed spool journal {:?} ({} segments dropped)", path, dropped);
+        }
+        Ok(())
+    }
+
+    /// 已封存、等待补传的日志（按中断开始时间排序）
+    pub fn sealed_journals(&self) -> io::Result<Vec<PathBuf>> {
+        let mut journals: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(SEALED_EXTENSION))
+            .collect();
+        journals.sort();
+        Ok(journals)
+    }
+
+    /// 读出日志中的视频分片（断档录像），按写入顺序排列
+    pub fn read_journal(&self, journal: &Path) -> io::Result<Vec<VideoSegment>> {
+        let mut reader = BufReader::new(File::open(journal)?);
+        let mut segments = Vec::new();
+        let mut len_buf = [0u8; 4];
+
+        loop {
+            match reader.read_exact(&mut len_buf) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            let mut record = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+            // 进程崩溃时最后一条记录可能不完整，丢弃即可
+            if reader.read_exact(&mut record).is_err() {
+                break;
+            }
+            let Some(segment) = decode_record(&record) else {
+                break;
+            };
+            segments.push(segment);
+        }
+        Ok(segments)
+    }
+
+    /// 开始补传，已有补传任务在运行时返回 false
+    pub fn begin_backfill(&self) -> bool {
+        !self.backfilling.swap(true, Ordering::AcqRel)
+    }
+
+    /// 补传任务结束（无论成功与否）
+    pub fn end_backfill(&self) {
+        self.backfilling.store(false, Ordering::Release);
+    }
+
+    /// 补传完成后删除日志
+    pub fn remove(&self, journal: &Path) -> io::Result<()> {
+        let size = fs::metadata(journal)?.len();
+        fs::remove_file(journal)?;
+        let mut inner = self.inner.lock().unwrap();
+        inner.used_bytes = inner.used_bytes.saturating_sub(size);
+        Ok(())
+    }
+}
+
+/// 日志记录：与视频分片的传输格式相同（不含流类型和数据长度），数据直接跟在记录头后
+fn encode_record(segment: &VideoSegment) -> Vec<u8> {
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN + segment.data.len());
+    record.extend_from_slice(segment.id.as_bytes());
+    record.extend_from_slice(&segment.timestamp.to_be_bytes());
+    record.extend_from_slice(&segment.duration.to_be_bytes());
+    record.extend_from_slice(&(segment.frame_count as u32).to_be_bytes());
+    record.push(if segment.is_key_frame { SEGMENT_FLAG_KEY_FRAME } else { 0x00 });
+    record.extend_from_slice(&segment.data);
+    record
+}
+
+fn decode_record(record: &[u8]) -> Option<VideoSegment> {
+    if record.len() < RECORD_HEADER_LEN {
+        return None;
+    }
+    Some(VideoSegment {
+        id: Uuid::from_slice(&record[0..16]).ok()?,
+        timestamp: f64::from_be_bytes(record[16..24].try_into().ok()?),
+        duration: f64::from_be_bytes(record[24..32].try_into().ok()?),
+        frame_count: u32::from_be_bytes(record[32..36].try_into().ok()?) as usize,
+        is_key_frame: record[36] & SEGMENT_FLAG_KEY_FRAME != 0,
+        data: record[RECORD_HEADER_LEN..].to_vec(),
+        metadata: SegmentMetadata {
+            frame_indices: Vec::new(),
+            key_frame_positions: Vec::new(),
+            encoding_params: HashMap::new(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(data: &[u8], timestamp: f64, is_key_frame: bool) -> VideoSegment {
+        VideoSegment {
+            id: Uuid::new_v4(),
+            data: data.to_vec(),
+            timestamp,
+            duration: 0.04,
+            frame_count: 1,
+            is_key_frame,
+            metadata: SegmentMetadata {
+                frame_indices: vec![0],
+                key_frame_positions: if is_key_frame { vec![0] } else { Vec::new() },
+                encoding_params: HashMap::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_spool_journal_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = SegmentSpool::open(dir.path(), 1024 * 1024).unwrap();
+        let outage = spool.outage();
+
+        // 日志从关键帧开始
+        assert_eq!(spool.append(outage, &segment(&[9], 0.0, false)).unwrap(), SpoolWrite::Dropped);
+        let keyframe = segment(&[0, 0, 0, 1, 0x65], 0.04, true);
+        assert_eq!(spool.append(outage, &keyframe).unwrap(), SpoolWrite::Written);
+        assert_eq!(spool.append(outage, &segment(&[0, 0, 0, 1, 0x41], 0.08, false)).unwrap(), SpoolWrite::Written);
+        assert!(spool.sealed_journals().unwrap().is_empty());
+
+        spool.seal().unwrap();
+        assert_eq!(spool.append(outage, &segment(&[1], 0.12, true)).unwrap(), SpoolWrite::Sealed);
+
+        let journals = spool.sealed_journals().unwrap();
+        assert_eq!(journals.len(), 1);
+        let segments = spool.read_journal(&journals[0]).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].id, keyframe.id);
+        assert!(segments[0].is_key_frame);
+        assert_eq!(segments[0].timestamp, 0.04);
+        assert_eq!(segments[1].data, vec![0, 0, 0, 1, 0x41]);
+
+        spool.remove(&journals[0]).unwrap();
+        assert!(spool.sealed_journals().unwrap().is_empty());
+        assert_eq!(spool.inner.lock().unwrap().used_bytes, 0);
+
+        assert!(spool.begin_backfill());
+        assert!(!spool.begin_backfill());
+        spool.end_backfill();
+        assert!(spool.begin_backfill());
+    }
+
+    #[test]
+    fn test_spool_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = SegmentSpool::open(dir.path(), 2_000).unwrap();
+        let outage = spool.outage();
+
+        let mut written = 0;
+        for i in 0..20 {
+            if spool.append(outage, &segment(&[0u8; 200], i as f64, i == 0)).unwrap() == SpoolWrite::Written {
+                written += 1;
+            }
+        }
+        assert!(written > 0 && written < 20);
+        assert!(spool.inner.lock().unwrap().used_bytes <= 2_000);
+
+        // 重新打开时未封存的日志被封存，占用量保留
+        drop(spool);
+        let reopened = SegmentSpool::open(dir.path(), 2_000).unwrap();
+        assert_eq!(reopened.sealed_journals().unwrap().len(), 1);
+        assert!(reopened.inner.lock().unwrap().used_bytes > 0);
+    }
+}
@@ -8,6 +8,17 @@ use crate::errors::TransportError;
 use std::time::{Duration, SystemTime, Instant};
 use tokio::sync::Mutex;
 
+/// Video segment flag: segment is a key frame
+pub const SEGMENT_FLAG_KEY_FRAME: u8 = 0x01;
+/// Video segment flag: segment was spooled during an outage and is back-filled as a gap recording
+pub const SEGMENT_FLAG_GAP_BACKFILL: u8 = 0x02;
+
+/// Stream priority for gap back-fill (live segments use the default priority 0)
+const BACKFILL_STREAM_PRIORITY: i32 = -1;
+/// Offset of the flags byte in a serialized video segment:
+/// type(1) + id(16) + timestamp(8) + duration(8) + frame count(4)
+const VIDEO_FLAGS_OFFSET: usize = 37;
+
 #[async_trait]
 pub trait QUICTransport {
     async fn connect(
@@ -126,6 +137,44 @@ impl DefaultQUICTransport {
 
         Ok(endpoint_guard.as_ref().unwrap().clone())
     }
+
+    /// Send a spooled live segment as part of a gap recording
+    ///
+    /// The segment keeps its original timestamp and is flagged with
+    /// `SEGMENT_FLAG_GAP_BACKFILL`. Its stream is scheduled below live
+    /// traffic, so back-fill only uses bandwidth the live stream leaves idle.
+    pub async fn send_backfill_segment(
+        &self,
+        connection: &mut QUICConnection,
+        segment: crate::types::VideoSegment,
+    ) -> Result<(), TransportError> {
+        let mut send_stream = connection.inner
+            .open_uni()
+            .await
+            .map_err(|_e| TransportError::StreamCreationFailed {
+                reason: "Failed to open stream for back-fill segment".to_string()
+            })?;
+        let _ = send_stream.set_priority(BACKFILL_STREAM_PRIORITY);
+
+        let mut segment_data = self.serialize_segment(&Segment::Video(segment))?;
+        segment_data[VIDEO_FLAGS_OFFSET] |= SEGMENT_FLAG_GAP_BACKFILL;
+
+        send_stream
+            .write_all(&segment_data)
+            .await
+            .map_err(|_e| TransportError::ConnectionLost {
+                reason: "Failed to write back-fill segment data".to_string()
+            })?;
+
+        send_stream
+            .finish()
+            .await
+            .map_err(|_e| TransportError::ConnectionLost {
+                reason: "Failed to finish back-fill stream".to_string()
+            })?;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -535,7 +584,7 @@ impl DefaultQUICTransport {
                 buffer.extend_from_slice(&(video_segment.frame_count as u32).to_be_bytes());
                 
                 // Flags (1 byte)
-                let flags = if video_segment.is_key_frame { 0x01 } else { 0x00 };
+                let flags = if video_segment.is_key_frame { SEGMENT_FLAG_KEY_FRAME } else { 0x00 };
                 buffer.push(flags);
                 
                 // Data length (4 bytes, big-endian)
@@ -275,6 +275,7 @@ pub enum MessageType {
     FileListQuery = 0x0D,
     FileListResponse = 0x0E,
     LiveStreamControl = 0x0F,
+    KeyframeRequest = 0x10,
 }
 
 // Protocol version information
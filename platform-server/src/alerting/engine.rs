@@ -0,0 +1,670 @@
+// 告警规则引擎
+//
+// 每个 (规则, 对象) 组合维护一个状态机：
+//
+// ```
+// 正常 → 待触发(条件持续 for_secs) → 触发中 → (恢复条件持续 recover_secs) → 正常
+// ```
+//
+// 只有状态切换时才产生事件，同一告警在恢复之前不会重复通知
+// （除非规则设置了 repeat_secs 且告警未被确认）。静默期内的事件被丢弃，
+// 但告警状态照常跟踪。待触发事件只推送给实时订阅（SSE），
+// 外部通知渠道只接收触发与触发后的恢复。
+
+use super::rules::{AlertRule, Metric, Severity, Subject};
+use super::sinks::AlertSink;
+use super::sources::AlertSources;
+use anyhow::{bail, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// 每个通知渠道最多排队的事件数，渠道长时间阻塞时超出的事件丢弃
+const SINK_QUEUE_CAPACITY: usize = 1024;
+
+/// 一个指标采样
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub subject: Subject,
+    pub metric: Metric,
+    pub value: f64,
+}
+
+/// 活动告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveAlert {
+    pub id: Uuid,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub severity: Severity,
+    /// 告警对象，如 `session:<uuid>`、`device:<id>`
+    pub subject: String,
+    pub metric: Metric,
+    /// 最近一次的指标值
+    pub value: f64,
+    pub threshold: f64,
+    /// 触发时间（Unix 秒）
+    pub started_at: u64,
+    pub acknowledged: bool,
+    pub silenced: bool,
+}
+
+impl ActiveAlert {
+    /// 会话告警对应的会话ID
+    pub fn session_id(&self) -> Option<Uuid> {
+        self.subject
+            .strip_prefix("session:")
+            .and_then(|id| id.parse().ok())
+    }
+}
+
+/// 通知状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Pending,
+    Firing,
+    Resolved,
+}
+
+/// 发送给通知渠道的告警事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertEvent {
+    pub status: AlertStatus,
+    /// 此前最近一次发出的状态（首次发出时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<AlertStatus>,
+    /// 事件时间（Unix 秒）
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub alert: ActiveAlert,
+}
+
+impl AlertEvent {
+    /// 是否为状态切换（重复通知不算）
+    pub fn is_transition(&self) -> bool {
+        self.previous != Some(self.status)
+    }
+
+    /// 是否需要外部通知：触发（含重复通知）以及已通知告警的恢复
+    pub fn is_notification(&self) -> bool {
+        match self.status {
+            AlertStatus::Pending => false,
+            AlertStatus::Firing => true,
+            AlertStatus::Resolved => self.previous == Some(AlertStatus::Firing),
+        }
+    }
+}
+
+/// 静默规则（规则ID与对象均未指定时匹配所有告警）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Silence {
+    pub id: Uuid,
+    pub rule_id: Option<String>,
+    pub subject: Option<String>,
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub comment: String,
+}
+
+impl Silence {
+    fn matches(&self, rule_id: &str, subject: &str, now: u64) -> bool {
+        now >= self.starts_at
+            && now < self.ends_at
+            && self.rule_id.as_deref().is_none_or(|id| id == rule_id)
+            && self.subject.as_deref().is_none_or(|s| s == subject)
+    }
+}
+
+enum RuleState {
+    /// 条件已满足，等待持续时间
+    Pending {
+        since: u64,
+        alert: ActiveAlert,
+        /// 是否已发出待触发事件
+        announced: bool,
+    },
+    /// 已触发
+    Firing {
+        alert: ActiveAlert,
+        /// 恢复条件开始满足的时间
+        clear_since: Option<u64>,
+        /// 最近一次通知时间（静默期间触发的告警尚未通知）
+        last_notified: Option<u64>,
+        /// 最近一次发出的状态
+        announced: Option<AlertStatus>,
+    },
+}
+
+/// 通知渠道及其发送队列
+///
+/// 每个渠道由一个常驻任务按入队顺序逐条发送，慢渠道（如超时的 Webhook）不会让
+/// 后一次评估的恢复事件先于触发事件送达。
+struct SinkQueue {
+    sink: Arc<dyn AlertSink>,
+    queue: mpsc::Sender<AlertEvent>,
+}
+
+impl SinkQueue {
+    fn spawn(sink: Arc<dyn AlertSink>) -> Self {
+        let (queue, mut events) = mpsc::channel::<AlertEvent>(SINK_QUEUE_CAPACITY);
+        let worker = Arc::clone(&sink);
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if let Err(e) = worker.send(&event).await {
+                    warn!("Alert sink {} failed: {:#}", worker.name(), e);
+                }
+            }
+        });
+        Self { sink, queue }
+    }
+}
+
+/// 告警规则引擎
+pub struct AlertEngine {
+    rules: RwLock<Vec<AlertRule>>,
+    states: Mutex<HashMap<(String, Subject), RuleState>>,
+    silences: DashMap<Uuid, Silence>,
+    sinks: Vec<SinkQueue>,
+}
+
+impl AlertEngine {
+    /// 创建引擎；有通知渠道时为每个渠道启动发送任务，需在 Tokio 运行时中调用
+    pub fn new(rules: Vec<AlertRule>, sinks: Vec<Arc<dyn AlertSink>>) -> Result<Self> {
+        let mut ids = HashSet::new();
+        for rule in &rules {
+            rule.validate()?;
+            if !ids.insert(rule.id.as_str()) {
+                bail!("Duplicate alert rule id: {}", rule.id);
+            }
+        }
+        info!(
+            "Alert engine started with {} rules and {} sinks",
+            rules.len(),
+            sinks.len()
+        );
+
+        Ok(Self {
+            rules: RwLock::new(rules),
+            states: Mutex::new(HashMap::new()),
+            silences: DashMap::new(),
+            sinks: sinks.into_iter().map(SinkQueue::spawn).collect(),
+        })
+    }
+
+    /// 当前规则
+    pub fn rules(&self) -> Vec<AlertRule> {
+        self.rules.read().unwrap().clone()
+    }
+
+    /// 当前活动告警（按级别从高到低）
+    pub fn active_alerts(&self) -> Vec<ActiveAlert> {
+        let states = self.states.lock().unwrap();
+        let mut alerts: Vec<_> = states
+            .values()
+            .filter_map(|state| match state {
+                RuleState::Firing { alert, .. } => Some(alert.clone()),
+                RuleState::Pending { .. } => None,
+            })
+            .collect();
+        alerts.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then(a.started_at.cmp(&b.started_at))
+        });
+        alerts
+    }
+
+    /// 确认告警（确认后不再重复通知），返回告警是否存在
+    pub fn acknowledge(&self, alert_id: &Uuid) -> bool {
+        let mut states = self.states.lock().unwrap();
+        for state in states.values_mut() {
+            if let RuleState::Firing { alert, .. } = state {
+                if alert.id == *alert_id {
+                    alert.acknowledged = true;
+                    info!("Alert {} ({}) acknowledged", alert.id, alert.rule_id);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// 添加静默
+    pub fn add_silence(
+        &self,
+        rule_id: Option<String>,
+        subject: Option<String>,
+        duration: Duration,
+        comment: String,
+    ) -> Silence {
+        let now = unix_now();
+        let silence = Silence {
+            id: Uuid::new_v4(),
+            rule_id,
+            subject,
+            starts_at: now,
+            ends_at: now + duration.as_secs(),
+            comment,
+        };
+        info!("Added alert silence: {:?}", silence);
+        self.silences.insert(silence.id, silence.clone());
+        silence
+    }
+
+    /// 当前静默
+    pub fn silences(&self) -> Vec<Silence> {
+        let mut silences: Vec<_> = self.silences.iter().map(|e| e.value().clone()).collect();
+        silences.sort_by_key(|s| s.starts_at);
+        silences
+    }
+
+    /// 删除静默，返回此前是否存在
+    pub fn remove_silence(&self, silence_id: &Uuid) -> bool {
+        self.silences.remove(silence_id).is_some()
+    }
+
+    fn is_silenced(&self, rule_id: &str, subject: &str, now: u64) -> bool {
+        self.silences
+            .iter()
+            .any(|entry| entry.matches(rule_id, subject, now))
+    }
+
+    /// 用一批采样评估所有规则，返回状态切换与重复通知事件
+    pub fn evaluate(&self, samples: &[Sample]) -> Vec<AlertEvent> {
+        self.evaluate_at(unix_now(), samples)
+    }
+
+    fn evaluate_at(&self, now: u64, samples: &[Sample]) -> Vec<AlertEvent> {
+        self.silences.retain(|_, silence| silence.ends_at > now);
+
+        let rules = self.rules.read().unwrap();
+        let mut states = self.states.lock().unwrap();
+        let mut events = Vec::new();
+        let mut seen = HashSet::new();
+
+        for rule in rules.iter() {
+            for sample in samples.iter().filter(|s| s.metric == rule.metric) {
+                let key = (rule.id.clone(), sample.subject.clone());
+                seen.insert(key.clone());
+                let subject = sample.subject.to_string();
+                let silenced = self.is_silenced(&rule.id, &subject, now);
+                let breached = rule.is_breached(sample.value);
+
+                match states.remove(&key) {
+                    None if breached => {
+                        let alert = ActiveAlert {
+                            id: Uuid::new_v4(),
+                            rule_id: rule.id.clone(),
+                            rule_name: rule.name.clone(),
+                            severity: rule.severity,
+                            subject,
+                            metric: rule.metric,
+                            value: sample.value,
+                            threshold: rule.threshold,
+                            started_at: now,
+                            acknowledged: false,
+                            silenced,
+                        };
+                        // 立即触发的规则不单独发出待触发事件
+                        let announced = rule.for_secs > 0 && !silenced;
+                        if announced {
+                            events.push(AlertEvent {
+                                status: AlertStatus::Pending,
+                                previous: None,
+                                timestamp: now,
+                                alert: alert.clone(),
+                            });
+                        }
+                        states.insert(
+                            key.clone(),
+                            RuleState::Pending {
+                                since: now,
+                                alert,
+                                announced,
+                            },
+                        );
+                    }
+                    None => continue,
+                    Some(RuleState::Pending {
+                        mut alert,
+                        announced,
+                        ..
+                    }) if !breached => {
+                        if announced && !silenced {
+                            alert.value = sample.value;
+                            events.push(AlertEvent {
+                                status: AlertStatus::Resolved,
+                                previous: Some(AlertStatus::Pending),
+                                timestamp: now,
+                                alert,
+                            });
+                        }
+                        continue;
+                    }
+                    Some(state) => {
+                        states.insert(key.clone(), state);
+                    }
+                }
+
+                let state = states.get_mut(&key).unwrap();
+                if let RuleState::Pending {
+                    since,
+                    alert,
+                    announced,
+                } = state
+                {
+                    if now.saturating_sub(*since) < rule.for_secs {
+                        continue;
+                    }
+                    let mut alert = alert.clone();
+                    alert.started_at = now;
+                    warn!(
+                        "Alert firing: {} on {} (value {}, threshold {})",
+                        alert.rule_id, alert.subject, sample.value, alert.threshold
+                    );
+                    let announced = announced.then_some(AlertStatus::Pending);
+                    *state = RuleState::Firing {
+                        alert,
+                        clear_since: None,
+                        last_notified: None,
+                        announced,
+                    };
+                }
+
+                let RuleState::Firing {
+                    alert,
+                    clear_since,
+                    last_notified,
+                    announced,
+                } = state
+                else {
+                    continue;
+                };
+                alert.value = sample.value;
+                alert.silenced = silenced;
+
+                if rule.is_recovered(sample.value) {
+                    let since = *clear_since.get_or_insert(now);
+                    if now.saturating_sub(since) >= rule.recover_secs {
+                        let previous = *announced;
+                        if let Some(RuleState::Firing { alert, .. }) = states.remove(&key) {
+                            info!("Alert resolved: {} on {}", alert.rule_id, alert.subject);
+                            if previous.is_some() && !silenced {
+                                events.push(AlertEvent {
+                                    status: AlertStatus::Resolved,
+                                    previous,
+                                    timestamp: now,
+                                    alert,
+                                });
+                            }
+                        }
+                        continue;
+                    }
+                } else {
+                    *clear_since = None;
+                }
+
+                let due = match (*last_notified, rule.repeat_secs) {
+                    (None, _) => true,
+                    (Some(last), Some(repeat)) => {
+                        !alert.acknowledged && now.saturating_sub(last) >= repeat
+                    }
+                    (Some(_), None) => false,
+                };
+                if due && !silenced {
+                    *last_notified = Some(now);
+                    events.push(AlertEvent {
+                        status: AlertStatus::Firing,
+                        previous: announced.replace(AlertStatus::Firing),
+                        timestamp: now,
+                        alert: alert.clone(),
+                    });
+                }
+            }
+        }
+
+        // 对象已消失（会话结束、设备注销）：直接恢复
+        let gone: Vec<_> = states
+            .keys()
+            .filter(|key| !seen.contains(*key))
+            .cloned()
+            .collect();
+        for key in gone {
+            let (alert, previous) = match states.remove(&key) {
+                Some(RuleState::Pending {
+                    alert,
+                    announced: true,
+                    ..
+                }) => (alert, AlertStatus::Pending),
+                Some(RuleState::Firing {
+                    alert,
+                    announced: Some(previous),
+                    ..
+                }) => {
+                    info!("Alert resolved (subject gone): {} on {}", alert.rule_id, alert.subject);
+                    (alert, previous)
+                }
+                _ => continue,
+            };
+            if !alert.silenced {
+                events.push(AlertEvent {
+                    status: AlertStatus::Resolved,
+                    previous: Some(previous),
+                    timestamp: now,
+                    alert,
+                });
+            }
+        }
+
+        events
+    }
+
+    /// 把事件放入各渠道的发送队列，单个渠道失败或阻塞不影响其他渠道与规则评估；
+    /// 同一渠道按调用顺序收到事件
+    pub fn dispatch(&self, events: Vec<AlertEvent>) {
+        for SinkQueue { sink, queue } in &self.sinks {
+            for event in events.iter().filter(|event| sink.accepts(event)) {
+                if let Err(e) = queue.try_send(event.clone()) {
+                    warn!("Alert sink {} queue unavailable, dropping event: {}", sink.name(), e);
+                }
+            }
+        }
+    }
+
+    /// 启动定期评估任务
+    pub fn spawn_evaluation_task(
+        self: Arc<Self>,
+        sources: AlertSources,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let samples = sources.collect();
+                let events = self.evaluate(&samples);
+                if !events.is_empty() {
+                    debug!("Dispatching {} alert notifications", events.len());
+                }
+                self.dispatch(events);
+            }
+        })
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::rules::Comparison;
+
+    fn latency_rule() -> AlertRule {
+        AlertRule {
+            id: "p95".to_string(),
+            name: "p95 high".to_string(),
+            severity: Severity::Warning,
+            metric: Metric::SessionP95LatencyMs,
+            comparison: Comparison::Above,
+            threshold: 200.0,
+            recover_threshold: Some(150.0),
+            for_secs: 10,
+            recover_secs: 5,
+            repeat_secs: None,
+        }
+    }
+
+    fn sample(subject: &Subject, value: f64) -> Vec<Sample> {
+        vec![Sample {
+            subject: subject.clone(),
+            metric: Metric::SessionP95LatencyMs,
+            value,
+        }]
+    }
+
+    #[test]
+    fn test_sustained_condition_and_hysteresis() {
+        let engine = AlertEngine::new(vec![latency_rule()], Vec::new()).unwrap();
+        let subject = Subject::Session(Uuid::new_v4());
+
+        // 持续不足 10 秒不触发，中途恢复则重新计时；待触发与取消只推送给实时订阅
+        let statuses = |events: Vec<AlertEvent>| {
+            events
+                .iter()
+                .map(|e| (e.previous, e.status, e.is_notification()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            statuses(engine.evaluate_at(100, &sample(&subject, 300.0))),
+            vec![(None, AlertStatus::Pending, false)]
+        );
+        assert_eq!(
+            statuses(engine.evaluate_at(105, &sample(&subject, 100.0))),
+            vec![(Some(AlertStatus::Pending), AlertStatus::Resolved, false)]
+        );
+        assert_eq!(engine.evaluate_at(106, &sample(&subject, 300.0)).len(), 1);
+        assert!(engine.evaluate_at(115, &sample(&subject, 300.0)).is_empty());
+
+        let events = engine.evaluate_at(116, &sample(&subject, 310.0));
+        assert_eq!(
+            statuses(events),
+            vec![(Some(AlertStatus::Pending), AlertStatus::Firing, true)]
+        );
+        assert_eq!(engine.active_alerts().len(), 1);
+
+        // 触发后不重复通知；介于恢复阈值与触发阈值之间不算恢复
+        for t in 117..140 {
+            assert!(engine.evaluate_at(t, &sample(&subject, 180.0)).is_empty());
+        }
+        assert_eq!(engine.active_alerts().len(), 1);
+
+        // 低于恢复阈值并持续 5 秒后恢复
+        assert!(engine.evaluate_at(140, &sample(&subject, 120.0)).is_empty());
+        let events = engine.evaluate_at(145, &sample(&subject, 120.0));
+        assert_eq!(
+            statuses(events),
+            vec![(Some(AlertStatus::Firing), AlertStatus::Resolved, true)]
+        );
+        assert!(engine.active_alerts().is_empty());
+    }
+
+    #[test]
+    fn test_silence_ack_and_repeat() {
+        let mut rule = latency_rule();
+        rule.for_secs = 0;
+        rule.repeat_secs = Some(60);
+        let engine = AlertEngine::new(vec![rule], Vec::new()).unwrap();
+        let subject = Subject::Session(Uuid::new_v4());
+
+        // 静默期间触发不通知
+        let silence = engine.add_silence(
+            Some("p95".to_string()),
+            Some(subject.to_string()),
+            Duration::from_secs(30),
+            "maintenance".to_string(),
+        );
+        let now = silence.starts_at;
+        assert!(engine.evaluate_at(now, &sample(&subject, 300.0)).is_empty());
+        assert!(engine.active_alerts()[0].silenced);
+
+        // 静默解除后补发一次
+        assert!(engine.remove_silence(&silence.id));
+        assert_eq!(engine.evaluate_at(now + 1, &sample(&subject, 300.0)).len(), 1);
+
+        // 未确认时按间隔重复通知，确认后停止
+        assert!(engine.evaluate_at(now + 30, &sample(&subject, 300.0)).is_empty());
+        let events = engine.evaluate_at(now + 61, &sample(&subject, 300.0));
+        assert_eq!(events.len(), 1);
+        assert!(events[0].is_notification() && !events[0].is_transition());
+        let alert_id = engine.active_alerts()[0].id;
+        assert!(engine.acknowledge(&alert_id));
+        assert!(engine.evaluate_at(now + 200, &sample(&subject, 300.0)).is_empty());
+
+        // 会话结束后告警恢复
+        let events = engine.evaluate_at(now + 201, &[]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, AlertStatus::Resolved);
+    }
+
+    /// 触发事件发送得比恢复事件慢的渠道
+    struct SlowFiringSink {
+        received: std::sync::Mutex<Vec<AlertStatus>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AlertSink for SlowFiringSink {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        async fn send(&self, event: &AlertEvent) -> Result<()> {
+            if event.status == AlertStatus::Firing {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            self.received.lock().unwrap().push(event.status);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_preserves_order_per_sink() {
+        let mut rule = latency_rule();
+        rule.for_secs = 0;
+        let sink = Arc::new(SlowFiringSink {
+            received: std::sync::Mutex::new(Vec::new()),
+        });
+        let engine = AlertEngine::new(vec![rule], vec![sink.clone() as Arc<dyn AlertSink>]).unwrap();
+        let subject = Subject::Session(Uuid::new_v4());
+
+        // 两次评估分别产生触发和恢复，慢渠道仍按顺序收到
+        engine.dispatch(engine.evaluate_at(100, &sample(&subject, 300.0)));
+        engine.dispatch(engine.evaluate_at(101, &[]));
+
+        for _ in 0..100 {
+            if sink.received.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            *sink.received.lock().unwrap(),
+            vec![AlertStatus::Firing, AlertStatus::Resolved]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_rule_ids_rejected() {
+        assert!(AlertEngine::new(vec![latency_rule(), latency_rule()], Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+// 告警规则引擎
+//
+// 在延迟统计与设备在线状态之上按规则评估告警：持续条件窗口、恢复迟滞、
+// 去重、静默与确认，并通过 webhook、syslog 与 JSONL 日志发送通知。
+
+mod engine;
+mod rules;
+mod sinks;
+mod sources;
+
+pub use engine::{ActiveAlert, AlertEngine, AlertEvent, Silence};
+pub use rules::{default_rules, AlertRule};
+pub use sinks::{AlertSink, BroadcastSink, JsonlSink, SyslogSink, WebhookSink};
+pub use sources::AlertSources;
+
+use crate::latency::AlertBroadcaster;
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// 告警配置
+#[derive(Debug, Clone)]
+pub struct AlertingConfig {
+    /// 规则文件（JSON 数组），不存在时使用默认规则
+    pub rules_path: Option<PathBuf>,
+    /// 评估间隔
+    pub evaluation_interval: Duration,
+    /// JSONL 告警日志
+    pub jsonl_path: Option<PathBuf>,
+    /// Webhook 地址
+    pub webhooks: Vec<String>,
+    /// Syslog 服务器（UDP）
+    pub syslog_addr: Option<SocketAddr>,
+}
+
+impl Default for AlertingConfig {
+    fn default() -> Self {
+        Self {
+            rules_path: Some(PathBuf::from("data/alert_rules.json")),
+            evaluation_interval: Duration::from_secs(1),
+            jsonl_path: Some(PathBuf::from("data/alerts.jsonl")),
+            webhooks: Vec::new(),
+            syslog_addr: None,
+        }
+    }
+}
+
+impl AlertingConfig {
+    /// 读取规则文件，文件不存在时返回默认规则
+    pub fn load_rules(&self) -> Result<Vec<AlertRule>> {
+        match &self.rules_path {
+            Some(path) if path.exists() => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let rules = serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                info!("Loaded alert rules from {}", path.display());
+                Ok(rules)
+            }
+            _ => Ok(default_rules()),
+        }
+    }
+
+    /// 按配置创建通知渠道
+    pub fn build_sinks(&self) -> Result<Vec<Arc<dyn AlertSink>>> {
+        let mut sinks: Vec<Arc<dyn AlertSink>> = Vec::new();
+        if let Some(path) = &self.jsonl_path {
+            sinks.push(Arc::new(JsonlSink::new(path)));
+        }
+        for url in &self.webhooks {
+            sinks.push(Arc::new(WebhookSink::new(url.clone())?));
+        }
+        if let Some(addr) = self.syslog_addr {
+            sinks.push(Arc::new(SyslogSink::new(addr)));
+        }
+        Ok(sinks)
+    }
+
+    /// 创建告警引擎，状态切换同时推送给延迟告警 SSE 订阅者
+    pub fn build_engine(&self, broadcaster: Arc<AlertBroadcaster>) -> Result<AlertEngine> {
+        let mut sinks = self.build_sinks()?;
+        sinks.push(Arc::new(BroadcastSink::new(broadcaster)));
+        AlertEngine::new(self.load_rules()?, sinks)
+    }
+}
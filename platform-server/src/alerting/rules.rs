@@ -0,0 +1,243 @@
+// 告警规则定义
+//
+// 每条规则针对一个指标：指标越过阈值并持续 `for_secs` 后触发，
+// 回到恢复阈值以内并持续 `recover_secs` 后恢复（迟滞）。
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+/// 告警级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// 规则可引用的指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// 会话处理延迟 P95（毫秒）
+    SessionP95LatencyMs,
+    /// 会话平均处理延迟（毫秒）
+    SessionAverageLatencyMs,
+    /// 会话端到端延迟 P95（毫秒，来自客户端播放确认）
+    SessionEndToEndP95Ms,
+    /// 会话丢包率（0~1）
+    SessionPacketLossRate,
+    /// 会话吞吐量（Mbps）
+    SessionThroughputMbps,
+    /// 设备离线（离线为 1，在线为 0）
+    DeviceOffline,
+    /// 设备平均传输延迟（毫秒）
+    DeviceTransmissionLatencyMs,
+    /// 设备到达抖动（毫秒）
+    DeviceJitterMs,
+}
+
+/// 比较方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Comparison {
+    /// 大于阈值时告警
+    Above,
+    /// 小于阈值时告警
+    Below,
+}
+
+/// 告警对象
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Subject {
+    Session(Uuid),
+    Device(String),
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subject::Session(session_id) => write!(f, "session:{}", session_id),
+            Subject::Device(device_id) => write!(f, "device:{}", device_id),
+        }
+    }
+}
+
+/// 告警规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// 规则ID（唯一）
+    pub id: String,
+    /// 规则名称
+    pub name: String,
+    pub severity: Severity,
+    pub metric: Metric,
+    pub comparison: Comparison,
+    /// 触发阈值
+    pub threshold: f64,
+    /// 恢复阈值（默认等于触发阈值）
+    #[serde(default)]
+    pub recover_threshold: Option<f64>,
+    /// 条件需持续的秒数
+    #[serde(default)]
+    pub for_secs: u64,
+    /// 恢复条件需持续的秒数
+    #[serde(default)]
+    pub recover_secs: u64,
+    /// 未确认时重复通知的间隔（秒），不设置则只通知一次
+    #[serde(default)]
+    pub repeat_secs: Option<u64>,
+}
+
+impl AlertRule {
+    /// 检查规则配置
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty() {
+            bail!("Alert rule id must not be empty");
+        }
+        if !self.threshold.is_finite() {
+            bail!("Alert rule {} has an invalid threshold", self.id);
+        }
+        let recover = self.recover_threshold();
+        let consistent = match self.comparison {
+            Comparison::Above => recover <= self.threshold,
+            Comparison::Below => recover >= self.threshold,
+        };
+        if !consistent {
+            bail!(
+                "Alert rule {}: recover threshold {} is on the wrong side of {}",
+                self.id,
+                recover,
+                self.threshold
+            );
+        }
+        Ok(())
+    }
+
+    pub fn recover_threshold(&self) -> f64 {
+        self.recover_threshold.unwrap_or(self.threshold)
+    }
+
+    /// 指标值是否满足告警条件
+    pub fn is_breached(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::Above => value > self.threshold,
+            Comparison::Below => value < self.threshold,
+        }
+    }
+
+    /// 指标值是否已回到恢复阈值以内
+    pub fn is_recovered(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::Above => value <= self.recover_threshold(),
+            Comparison::Below => value >= self.recover_threshold(),
+        }
+    }
+}
+
+/// 默认规则
+pub fn default_rules() -> Vec<AlertRule> {
+    let rule = |id: &str, name: &str, severity, metric, threshold, recover, for_secs| AlertRule {
+        id: id.to_string(),
+        name: name.to_string(),
+        severity,
+        metric,
+        comparison: Comparison::Above,
+        threshold,
+        recover_threshold: Some(recover),
+        for_secs,
+        recover_secs: 10,
+        repeat_secs: None,
+    };
+
+    vec![
+        rule(
+            "session_p95_latency",
+            "Session p95 latency high",
+            Severity::Warning,
+            Metric::SessionP95LatencyMs,
+            200.0,
+            150.0,
+            10,
+        ),
+        rule(
+            "session_p95_latency_critical",
+            "Session p95 latency critical",
+            Severity::Critical,
+            Metric::SessionP95LatencyMs,
+            500.0,
+            400.0,
+            10,
+        ),
+        rule(
+            "session_end_to_end_p95",
+            "Session end-to-end p95 latency high",
+            Severity::Warning,
+            Metric::SessionEndToEndP95Ms,
+            400.0,
+            300.0,
+            10,
+        ),
+        rule(
+            "session_packet_loss",
+            "Session packet loss high",
+            Severity::Warning,
+            Metric::SessionPacketLossRate,
+            0.05,
+            0.02,
+            10,
+        ),
+        rule(
+            "device_jitter",
+            "Device jitter high",
+            Severity::Warning,
+            Metric::DeviceJitterMs,
+            50.0,
+            30.0,
+            10,
+        ),
+        rule(
+            "device_offline",
+            "Device offline",
+            Severity::Critical,
+            Metric::DeviceOffline,
+            0.5,
+            0.5,
+            30,
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hysteresis_thresholds() {
+        let rule = &default_rules()[0];
+        assert!(rule.is_breached(201.0));
+        assert!(!rule.is_breached(180.0));
+        assert!(!rule.is_recovered(180.0));
+        assert!(rule.is_recovered(150.0));
+
+        for rule in default_rules() {
+            rule.validate().unwrap();
+        }
+
+        let mut invalid = rule.clone();
+        invalid.recover_threshold = Some(250.0);
+        assert!(invalid.validate().is_err());
+    }
+}
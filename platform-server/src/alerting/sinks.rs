@@ -0,0 +1,310 @@
+// 告警通知渠道
+//
+// - `WebhookSink`：向 HTTP 端点 POST JSON 格式的告警事件
+// - `SyslogSink`：按 RFC 5424 通过 UDP 发送到 syslog 服务器
+// - `JsonlSink`：追加写入 JSON Lines 告警日志
+// - `BroadcastSink`：把状态切换推送给延迟告警 SSE 订阅者
+
+use super::engine::{AlertEvent, AlertStatus};
+use super::rules::Severity;
+use crate::latency::AlertBroadcaster;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+
+/// 告警通知渠道
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    /// 渠道名称（用于日志）
+    fn name(&self) -> &str;
+
+    /// 是否接收该事件（默认只接收触发与触发后的恢复）
+    fn accepts(&self, event: &AlertEvent) -> bool {
+        event.is_notification()
+    }
+
+    /// 发送一条告警事件
+    async fn send(&self, event: &AlertEvent) -> Result<()>;
+}
+
+/// Webhook 通知
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()?;
+        Ok(Self {
+            url: url.into(),
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    async fn send(&self, event: &AlertEvent) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(event)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Syslog 通知（RFC 5424 over UDP，facility local0）
+pub struct SyslogSink {
+    target: SocketAddr,
+    socket: Mutex<Option<UdpSocket>>,
+}
+
+impl SyslogSink {
+    pub fn new(target: SocketAddr) -> Self {
+        Self {
+            target,
+            socket: Mutex::new(None),
+        }
+    }
+
+    fn format(event: &AlertEvent) -> String {
+        const LOCAL0: u8 = 16;
+        let severity = match (event.status, event.alert.severity) {
+            (AlertStatus::Pending, _) => 6,  // informational
+            (AlertStatus::Resolved, _) => 5, // notice
+            (AlertStatus::Firing, Severity::Critical) => 2,
+            (AlertStatus::Firing, Severity::Warning) => 4,
+            (AlertStatus::Firing, Severity::Info) => 6,
+        };
+        let timestamp = chrono::DateTime::from_timestamp(event.timestamp as i64, 0)
+            .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+            .unwrap_or_else(|| "-".to_string());
+        let status = match event.status {
+            AlertStatus::Pending => "PENDING",
+            AlertStatus::Firing => "FIRING",
+            AlertStatus::Resolved => "RESOLVED",
+        };
+
+        format!(
+            "<{}>1 {} - platform-server - ALERT - [{}] {} {} {} value={} threshold={} id={}",
+            LOCAL0 * 8 + severity,
+            timestamp,
+            status,
+            event.alert.severity.as_str(),
+            event.alert.rule_name,
+            event.alert.subject,
+            event.alert.value,
+            event.alert.threshold,
+            event.alert.id,
+        )
+    }
+}
+
+#[async_trait]
+impl AlertSink for SyslogSink {
+    fn name(&self) -> &str {
+        "syslog"
+    }
+
+    async fn send(&self, event: &AlertEvent) -> Result<()> {
+        let mut socket = self.socket.lock().await;
+        if socket.is_none() {
+            let bind: SocketAddr = if self.target.is_ipv4() {
+                "0.0.0.0:0".parse()?
+            } else {
+                "[::]:0".parse()?
+            };
+            *socket = Some(UdpSocket::bind(bind).await?);
+        }
+        let message = Self::format(event);
+        socket
+            .as_ref()
+            .unwrap()
+            .send_to(message.as_bytes(), self.target)
+            .await?;
+        Ok(())
+    }
+}
+
+/// JSON Lines 告警日志
+pub struct JsonlSink {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl JsonlSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for JsonlSink {
+    fn name(&self) -> &str {
+        "jsonl"
+    }
+
+    async fn send(&self, event: &AlertEvent) -> Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        let _guard = self.lock.lock().await;
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.write_all(&line).await?;
+        // tokio 的 File 在后台线程写入，必须 flush 才能保证写入完成
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// 实时告警推送（SSE）
+///
+/// 只推送状态切换（待触发 → 触发 → 恢复），不推送重复通知。
+pub struct BroadcastSink {
+    broadcaster: Arc<AlertBroadcaster>,
+}
+
+impl BroadcastSink {
+    pub fn new(broadcaster: Arc<AlertBroadcaster>) -> Self {
+        Self { broadcaster }
+    }
+}
+
+#[async_trait]
+impl AlertSink for BroadcastSink {
+    fn name(&self) -> &str {
+        "sse"
+    }
+
+    fn accepts(&self, event: &AlertEvent) -> bool {
+        event.is_transition()
+    }
+
+    async fn send(&self, event: &AlertEvent) -> Result<()> {
+        self.broadcaster.broadcast_alert_event(event.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::engine::ActiveAlert;
+    use crate::alerting::rules::Metric;
+    use tokio::io::AsyncReadExt;
+    use uuid::Uuid;
+
+    fn event() -> AlertEvent {
+        AlertEvent {
+            status: AlertStatus::Firing,
+            previous: None,
+            timestamp: 1_700_000_000,
+            alert: ActiveAlert {
+                id: Uuid::new_v4(),
+                rule_id: "device_offline".to_string(),
+                rule_name: "Device offline".to_string(),
+                severity: Severity::Critical,
+                subject: "device:camera_01".to_string(),
+                metric: Metric::DeviceOffline,
+                value: 1.0,
+                threshold: 0.5,
+                started_at: 1_700_000_000,
+                acknowledged: false,
+                silenced: false,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_sink_posts_json() {
+        // 本地 HTTP 桩：读取一个请求并返回 204
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let length = text[..header_end]
+                        .lines()
+                        .find_map(|line| {
+                            let line = line.to_ascii_lowercase();
+                            line.strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let sink = WebhookSink::new(format!("http://{}/hooks/alerts", addr)).unwrap();
+        sink.send(&event()).await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hooks/alerts HTTP/1.1"));
+        let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["status"], "firing");
+        assert_eq!(json["rule_id"], "device_offline");
+        assert_eq!(json["severity"], "critical");
+    }
+
+    #[tokio::test]
+    async fn test_syslog_and_jsonl_sinks() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let sink = SyslogSink::new(receiver.local_addr().unwrap());
+        sink.send(&event()).await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = receiver.recv(&mut buf).await.unwrap();
+        let message = std::str::from_utf8(&buf[..n]).unwrap();
+        assert!(message.starts_with("<130>1 2023-11-14T22:13:20Z - platform-server - ALERT - [FIRING]"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alerts").join("alerts.jsonl");
+        let sink = JsonlSink::new(&path);
+        sink.send(&event()).await.unwrap();
+        sink.send(&event()).await.unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        let first: AlertEvent = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(first.alert.subject, "device:camera_01");
+    }
+}
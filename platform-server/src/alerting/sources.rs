@@ -0,0 +1,83 @@
+// 告警指标采集
+//
+// 每次评估时从延迟统计、设备传输统计与设备在线状态读取当前值。
+
+use super::engine::Sample;
+use super::rules::{Metric, Subject};
+use crate::device::DeviceManager;
+use crate::latency::{EndToEndLatencyMonitor, LatencyStatisticsManager};
+use common::ConnectionStatus;
+use std::sync::Arc;
+
+/// 告警规则的数据来源
+#[derive(Clone)]
+pub struct AlertSources {
+    stats_manager: Arc<LatencyStatisticsManager>,
+    latency_monitor: Arc<EndToEndLatencyMonitor>,
+    device_manager: DeviceManager,
+}
+
+impl AlertSources {
+    pub fn new(
+        stats_manager: Arc<LatencyStatisticsManager>,
+        latency_monitor: Arc<EndToEndLatencyMonitor>,
+        device_manager: DeviceManager,
+    ) -> Self {
+        Self {
+            stats_manager,
+            latency_monitor,
+            device_manager,
+        }
+    }
+
+    /// 采集所有对象的当前指标
+    pub fn collect(&self) -> Vec<Sample> {
+        let mut samples = Vec::new();
+
+        for stats in self.stats_manager.get_all_statistics() {
+            let subject = Subject::Session(stats.session_id);
+            let mut push = |metric, value| {
+                samples.push(Sample {
+                    subject: subject.clone(),
+                    metric,
+                    value,
+                })
+            };
+            // 尚无样本的会话只有丢包与吞吐量有意义
+            if stats.total_segments > 0 {
+                push(Metric::SessionP95LatencyMs, stats.p95_latency_ms as f64);
+                push(Metric::SessionAverageLatencyMs, stats.average_latency_ms);
+                push(Metric::SessionThroughputMbps, stats.throughput_mbps);
+            }
+            if stats.acked_segments > 0 {
+                push(Metric::SessionEndToEndP95Ms, stats.end_to_end_p95_ms as f64);
+            }
+            push(Metric::SessionPacketLossRate, stats.packet_loss_rate);
+        }
+
+        for device in self.device_manager.get_all_devices() {
+            let offline = device.connection_status != ConnectionStatus::Online;
+            samples.push(Sample {
+                subject: Subject::Device(device.device_id),
+                metric: Metric::DeviceOffline,
+                value: if offline { 1.0 } else { 0.0 },
+            });
+        }
+
+        for stats in self.latency_monitor.get_all_device_transmission() {
+            let subject = Subject::Device(stats.device_id);
+            samples.push(Sample {
+                subject: subject.clone(),
+                metric: Metric::DeviceTransmissionLatencyMs,
+                value: stats.avg_latency_ms,
+            });
+            samples.push(Sample {
+                subject,
+                metric: Metric::DeviceJitterMs,
+                value: stats.jitter_ms,
+            });
+        }
+
+        samples
+    }
+}
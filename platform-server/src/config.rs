@@ -1,3 +1,5 @@
+use crate::alerting::AlertingConfig;
+use crate::latency::HistoryConfig;
 use anyhow::Result;
 use std::path::PathBuf;
 
@@ -10,6 +12,12 @@ pub struct Config {
     pub storage_root: PathBuf,
     pub max_connections: usize,
     pub buffer_size: usize,
+    /// 延迟统计历史存储
+    pub latency_history: HistoryConfig,
+    /// 延迟阈值配置文件（全局默认值与设备覆盖）
+    pub latency_thresholds_path: PathBuf,
+    /// 告警规则与通知渠道
+    pub alerting: AlertingConfig,
 }
 
 impl Config {
@@ -22,6 +30,9 @@ impl Config {
             storage_root: PathBuf::from("../device-simulator/test-videos"),
             max_connections: 1000,
             buffer_size: 1024 * 1024, // 1MB
+            latency_history: HistoryConfig::default(),
+            latency_thresholds_path: PathBuf::from("data/latency_thresholds.json"),
+            alerting: AlertingConfig::default(),
         })
     }
 }
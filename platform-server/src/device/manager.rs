@@ -1,14 +1,36 @@
-use common::{DeviceInfo, ConnectionStatus, VideoStreamError, Result};
+use common::{
+    DeviceInfo, ConnectionStatus, MessageType, NegotiatedProtocol, ProtocolMessage,
+    VideoStreamError, Result,
+};
 use dashmap::DashMap;
 use quinn::Connection;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use tracing::{info, warn};
+use uuid::Uuid;
+
+/// 设备 QUIC 连接统计
+#[derive(Debug, Clone)]
+pub struct QuicConnectionStats {
+    pub device_id: String,
+    /// 平滑往返时延
+    pub rtt: Duration,
+    /// 拥塞窗口（字节）
+    pub cwnd: u64,
+    pub congestion_events: u64,
+    pub sent_packets: u64,
+    pub lost_packets: u64,
+    /// UDP 接收字节数
+    pub rx_bytes: u64,
+    /// UDP 发送字节数
+    pub tx_bytes: u64,
+}
 
 #[derive(Clone)]
 pub struct DeviceManager {
     devices: Arc<DashMap<String, DeviceInfo>>,
     connections: Arc<DashMap<String, Connection>>,
+    protocols: Arc<DashMap<String, NegotiatedProtocol>>,
 }
 
 impl DeviceManager {
@@ -16,6 +38,7 @@ impl DeviceManager {
         Self {
             devices: Arc::new(DashMap::new()),
             connections: Arc::new(DashMap::new()),
+            protocols: Arc::new(DashMap::new()),
         }
     }
 
@@ -36,10 +59,83 @@ impl DeviceManager {
         self.connections.get(device_id).map(|c| c.value().clone())
     }
 
+    /// 通过双向流向设备发送控制信令，返回设备的回复
+    pub async fn send_control_message(
+        &self,
+        device_id: &str,
+        session_id: Uuid,
+        message_type: MessageType,
+        payload: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let connection = self
+            .get_connection(device_id)
+            .ok_or_else(|| VideoStreamError::DeviceNotFound(device_id.to_string()))?;
+
+        let message = ProtocolMessage {
+            message_type,
+            payload,
+            sequence_number: 0,
+            timestamp: SystemTime::now(),
+            session_id,
+        };
+        let data = bincode::serialize(&message)
+            .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+
+        let (mut send, mut recv) = connection
+            .open_bi()
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        send.write_all(&data)
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        send.finish()
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+
+        recv.read_to_end(64 * 1024)
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))
+    }
+
+    /// 获取仍处于打开状态的设备连接的 QUIC 统计
+    pub fn connection_stats(&self) -> Vec<QuicConnectionStats> {
+        self.connections
+            .iter()
+            .filter(|entry| entry.value().close_reason().is_none())
+            .map(|entry| {
+                let stats = entry.value().stats();
+                QuicConnectionStats {
+                    device_id: entry.key().clone(),
+                    rtt: stats.path.rtt,
+                    cwnd: stats.path.cwnd,
+                    congestion_events: stats.path.congestion_events,
+                    sent_packets: stats.path.sent_packets,
+                    lost_packets: stats.path.lost_packets,
+                    rx_bytes: stats.udp_rx.bytes,
+                    tx_bytes: stats.udp_tx.bytes,
+                }
+            })
+            .collect()
+    }
+
+    /// 保存设备协商的协议参数
+    pub fn store_protocol(&self, device_id: String, protocol: NegotiatedProtocol) {
+        self.protocols.insert(device_id, protocol);
+    }
+
+    /// 获取设备协商的协议参数（未协商的旧版设备按 1.0 处理）
+    pub fn get_protocol(&self, device_id: &str) -> NegotiatedProtocol {
+        self.protocols
+            .get(device_id)
+            .map(|p| *p.value())
+            .unwrap_or(NegotiatedProtocol::LEGACY)
+    }
+
     /// 注销设备
     pub fn unregister_device(&self, device_id: &str) -> Result<()> {
         info!("Unregistering device: {}", device_id);
         self.devices.remove(device_id);
+        self.protocols.remove(device_id);
         Ok(())
     }
 
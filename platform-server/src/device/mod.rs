@@ -1,5 +1,5 @@
 mod manager;
 mod registry;
 
-pub use manager::DeviceManager;
+pub use manager::{DeviceManager, QuicConnectionStats};
 pub use registry::DeviceRegistry;
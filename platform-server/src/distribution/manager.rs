@@ -1,26 +1,53 @@
-use common::{VideoSegment, Result};
+use common::aac::{self, AudioSpecificConfig};
+use common::{RenditionInfo, VideoSegment, Result};
 use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tracing::{debug, info};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 type SegmentSender = broadcast::Sender<VideoSegment>;
 
+/// 同一会话两次关键帧请求的最小间隔（期间的请求合并到上一次）
+const KEYFRAME_REQUEST_MIN_INTERVAL: Duration = Duration::from_millis(500);
+
 struct SessionData {
     sender: SegmentSender,
     last_keyframe: Option<VideoSegment>,
+    /// 设备声明的码流
+    renditions: Vec<RenditionInfo>,
+    /// 正在分发的码流
+    active_rendition: Option<u8>,
+    /// 请求切换到的码流（在其关键帧到达前继续分发当前码流）
+    target_rendition: Option<u8>,
+    /// 关键帧请求转发通道（设备支持 KeyframeRequest 时设置）
+    keyframe_tx: Option<mpsc::UnboundedSender<()>>,
+    /// 上一次转发关键帧请求的时间
+    last_keyframe_request: Option<Instant>,
+    /// 设备下发的音频解码配置（原始 AAC 帧据此补上 ADTS 帧头）
+    audio_config: Option<AudioSpecificConfig>,
 }
 
 #[derive(Clone)]
 pub struct DistributionManager {
     sessions: Arc<DashMap<Uuid, SessionData>>,
+    /// 客户端接收落后被跳过的分片数
+    client_lagged: Arc<AtomicU64>,
+    /// 流处理器数据源接收落后被跳过的分片数
+    source_lagged: Arc<AtomicU64>,
+    /// 直通数据源检测到的设备时间戳不连续次数
+    timestamp_discontinuities: Arc<AtomicU64>,
 }
 
 impl DistributionManager {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(DashMap::new()),
+            client_lagged: Arc::new(AtomicU64::new(0)),
+            source_lagged: Arc::new(AtomicU64::new(0)),
+            timestamp_discontinuities: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -30,17 +57,126 @@ impl DistributionManager {
         let session_data = SessionData {
             sender: tx,
             last_keyframe: None,
+            renditions: Vec::new(),
+            active_rendition: None,
+            target_rendition: None,
+            keyframe_tx: None,
+            last_keyframe_request: None,
+            audio_config: None,
         };
         self.sessions.insert(session_id, session_data);
         debug!("Created distribution session: {}", session_id);
         rx
     }
 
+    /// 设置会话可用的码流及初始码流
+    pub fn set_renditions(&self, session_id: &Uuid, renditions: Vec<RenditionInfo>, initial: u8) {
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.renditions = renditions;
+            session.target_rendition = Some(initial);
+        }
+    }
+
+    /// 请求切换码流，切换在目标码流的第一个关键帧处生效
+    pub fn set_target_rendition(&self, session_id: &Uuid, rendition_id: u8) {
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.target_rendition = Some(rendition_id);
+        }
+    }
+
+    /// 设置关键帧请求转发通道
+    pub fn set_keyframe_requester(&self, session_id: &Uuid, keyframe_tx: mpsc::UnboundedSender<()>) {
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.keyframe_tx = Some(keyframe_tx);
+        }
+    }
+
+    /// 请求设备立即输出关键帧，返回请求是否转发给了设备
+    ///
+    /// 多个订阅者（新加入的观看端、解码出错的客户端）的请求在限流间隔内合并为一次，
+    /// 设备输出的那个关键帧会分发给所有订阅者。
+    pub fn request_keyframe(&self, session_id: &Uuid) -> bool {
+        self.request_keyframe_at(session_id, Instant::now())
+    }
+
+    fn request_keyframe_at(&self, session_id: &Uuid, now: Instant) -> bool {
+        let Some(mut session) = self.sessions.get_mut(session_id) else {
+            return false;
+        };
+        let Some(keyframe_tx) = session.keyframe_tx.clone() else {
+            return false;
+        };
+        if session
+            .last_keyframe_request
+            .is_some_and(|last| now.duration_since(last) < KEYFRAME_REQUEST_MIN_INTERVAL)
+        {
+            debug!("Keyframe request coalesced (session: {})", session_id);
+            return false;
+        }
+        if keyframe_tx.send(()).is_err() {
+            session.keyframe_tx = None;
+            return false;
+        }
+        session.last_keyframe_request = Some(now);
+        true
+    }
+
+    /// 获取会话的码流信息
+    pub fn get_rendition(&self, session_id: &Uuid, rendition_id: u8) -> Option<RenditionInfo> {
+        self.sessions.get(session_id).and_then(|session| {
+            session
+                .renditions
+                .iter()
+                .find(|r| r.id == rendition_id)
+                .cloned()
+        })
+    }
+
+    /// 获取会话的音频解码配置（设备尚未下发时为 None）
+    pub fn audio_config(&self, session_id: &Uuid) -> Option<AudioSpecificConfig> {
+        self.sessions.get(session_id).and_then(|session| session.audio_config)
+    }
+
     /// 分发视频分片到会话
+    ///
+    /// 码流只在关键帧处切换：不属于当前码流的分片在切换目标的关键帧到达前被丢弃，
+    /// 避免客户端解码器收到缺少参考帧的数据。音频不区分码流，直接随视频分发。
     pub fn distribute_segment(&self, session_id: &Uuid, segment: VideoSegment) -> Result<()> {
         if let Some(mut session) = self.sessions.get_mut(session_id) {
+            if segment.is_audio() {
+                if let Some(segment) = Self::normalize_audio(session_id, &mut session, segment) {
+                    let _ = session.sender.send(segment);
+                }
+                return Ok(());
+            }
+
+            let is_keyframe = segment.is_keyframe();
+            match session.active_rendition {
+                Some(active) if active == segment.rendition_id => {}
+                Some(active)
+                    if is_keyframe
+                        && session
+                            .target_rendition
+                            .is_none_or(|target| target == segment.rendition_id) =>
+                {
+                    info!(
+                        "Session {} switched rendition #{} -> #{}",
+                        session_id, active, segment.rendition_id
+                    );
+                    session.active_rendition = Some(segment.rendition_id);
+                }
+                Some(active) => {
+                    debug!(
+                        "Dropping rendition #{} segment while on #{} (session: {})",
+                        segment.rendition_id, active, session_id
+                    );
+                    return Ok(());
+                }
+                None => session.active_rendition = Some(segment.rendition_id),
+            }
+
             // 如果是关键帧，缓存它
-            if segment.flags & 0x01 != 0 {
+            if is_keyframe {
                 info!("Caching keyframe for session {}: {} bytes", session_id, segment.data.len());
                 session.last_keyframe = Some(segment.clone());
             }
@@ -49,6 +185,37 @@ impl DistributionManager {
         Ok(())
     }
 
+    /// 统一音频分片为 ADTS 格式
+    ///
+    /// 解码配置分片只更新会话的 AudioSpecificConfig，不转发；收到配置之前的原始帧无法解码，直接丢弃。
+    fn normalize_audio(session_id: &Uuid, session: &mut SessionData, mut segment: VideoSegment) -> Option<VideoSegment> {
+        if segment.is_codec_config() {
+            match AudioSpecificConfig::parse(&segment.data) {
+                Some(config) => {
+                    info!(
+                        "Session {} audio config: {} {} Hz, {} channels",
+                        session_id, config.codec(), config.sample_rate(), config.channel_config
+                    );
+                    session.audio_config = Some(config);
+                }
+                None => warn!("Invalid AudioSpecificConfig for session {}", session_id),
+            }
+            return None;
+        }
+
+        if aac::is_adts(&segment.data) {
+            return Some(segment);
+        }
+
+        let Some(config) = session.audio_config else {
+            debug!("Dropping raw AAC segment before audio config (session: {})", session_id);
+            return None;
+        };
+        segment.data = aac::to_adts(&config, &segment.data);
+        segment.data_length = segment.data.len() as u32;
+        Some(segment)
+    }
+
     /// 获取会话接收器（新订阅者会先收到最近的关键帧）
     pub fn get_receiver(&self, session_id: &Uuid) -> Option<broadcast::Receiver<VideoSegment>> {
         self.sessions.get(session_id).map(|session| {
@@ -65,6 +232,11 @@ impl DistributionManager {
         })
     }
 
+    /// 会话是否仍在分发
+    pub fn has_session(&self, session_id: &Uuid) -> bool {
+        self.sessions.contains_key(session_id)
+    }
+
     /// 关闭会话
     pub fn close_session(&self, session_id: &Uuid) {
         self.sessions.remove(session_id);
@@ -75,4 +247,139 @@ impl DistributionManager {
     pub fn active_sessions(&self) -> usize {
         self.sessions.len()
     }
+
+    /// 获取每个会话的订阅者数
+    pub fn subscriber_counts(&self) -> Vec<(Uuid, usize)> {
+        self.sessions
+            .iter()
+            .map(|entry| (*entry.key(), entry.sender.receiver_count()))
+            .collect()
+    }
+
+    /// 记录客户端因接收落后而跳过的分片
+    pub fn record_client_lag(&self, skipped: u64) {
+        self.client_lagged.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    /// 数据源落后计数器（由 LiveStreamSource 累加）
+    pub fn source_lag_counter(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.source_lagged)
+    }
+
+    /// 客户端落后累计跳过的分片数
+    pub fn client_lagged_segments(&self) -> u64 {
+        self.client_lagged.load(Ordering::Relaxed)
+    }
+
+    /// 数据源落后累计跳过的分片数
+    pub fn source_lagged_segments(&self) -> u64 {
+        self.source_lagged.load(Ordering::Relaxed)
+    }
+
+    /// 时间戳不连续计数器（由 LiveStreamSource 累加）
+    pub fn discontinuity_counter(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.timestamp_discontinuities)
+    }
+
+    /// 累计检测到的时间戳不连续次数
+    pub fn timestamp_discontinuities(&self) -> u64 {
+        self.timestamp_discontinuities.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(rendition_id: u8, timestamp: f64, is_keyframe: bool) -> VideoSegment {
+        let mut segment = VideoSegment::new(vec![0u8; 16], VideoSegment::ticks(timestamp), 3_000, is_keyframe);
+        segment.rendition_id = rendition_id;
+        segment
+    }
+
+    #[test]
+    fn test_rendition_switch_waits_for_keyframe() {
+        let manager = DistributionManager::new();
+        let session_id = Uuid::new_v4();
+        let mut rx = manager.create_session(session_id);
+        manager.set_renditions(&session_id, Vec::new(), 0);
+
+        manager.distribute_segment(&session_id, segment(0, 0.0, true)).unwrap();
+        manager.set_target_rendition(&session_id, 1);
+
+        // 新码流的非关键帧和旧码流的迟到分片
+        manager.distribute_segment(&session_id, segment(1, 0.1, false)).unwrap();
+        manager.distribute_segment(&session_id, segment(0, 0.2, false)).unwrap();
+        manager.distribute_segment(&session_id, segment(1, 0.3, true)).unwrap();
+        // 切换后旧码流的关键帧不再生效
+        manager.distribute_segment(&session_id, segment(0, 0.4, true)).unwrap();
+        manager.distribute_segment(&session_id, segment(1, 0.5, false)).unwrap();
+
+        let received: Vec<(u8, f64)> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|s| (s.rendition_id, s.pts_secs()))
+            .collect();
+        assert_eq!(received, vec![(0, 0.0), (0, 0.2), (1, 0.3), (1, 0.5)]);
+    }
+
+    #[test]
+    fn test_audio_bypasses_rendition_switch() {
+        let manager = DistributionManager::new();
+        let session_id = Uuid::new_v4();
+        let mut rx = manager.create_session(session_id);
+
+        let config = AudioSpecificConfig { object_type: 2, sample_rate_index: 3, channel_config: 2 };
+        manager.distribute_segment(&session_id, segment(0, 0.0, true)).unwrap();
+        manager.set_target_rendition(&session_id, 1);
+
+        // 配置之前的原始帧被丢弃，配置分片本身不转发
+        let raw = VideoSegment::new_audio(vec![0x21, 0x10, 0x04], 0, 1_920, 1);
+        manager.distribute_segment(&session_id, raw.clone()).unwrap();
+        let mut config_segment = VideoSegment::new_audio(config.to_bytes().to_vec(), 0, 0, 0);
+        config_segment.flags |= common::SegmentFlags::CODEC_CONFIG;
+        manager.distribute_segment(&session_id, config_segment).unwrap();
+
+        // 等待码流切换期间音频照常分发，原始帧补上 ADTS 帧头
+        let mut audio = raw;
+        audio.rendition_id = 1;
+        manager.distribute_segment(&session_id, audio).unwrap();
+        let adts = VideoSegment::new_audio(aac::to_adts(&config, &[0x21, 0x10, 0x04]), 1_920, 1_920, 1);
+        manager.distribute_segment(&session_id, adts).unwrap();
+
+        let received: Vec<VideoSegment> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(received.len(), 3);
+        assert!(!received[0].is_audio());
+        for audio in &received[1..] {
+            assert!(audio.is_audio());
+            assert_eq!(aac::adts_frames(&audio.data)[0].payload, &[0x21, 0x10, 0x04]);
+        }
+    }
+
+    #[test]
+    fn test_keyframe_requests_are_coalesced() {
+        let manager = DistributionManager::new();
+        let session_id = Uuid::new_v4();
+        let _rx = manager.create_session(session_id);
+
+        // 设备不支持关键帧请求
+        assert!(!manager.request_keyframe(&session_id));
+
+        let (keyframe_tx, mut keyframe_rx) = mpsc::unbounded_channel();
+        manager.set_keyframe_requester(&session_id, keyframe_tx);
+
+        // 同一时刻多个订阅者的请求只转发一次
+        let start = Instant::now();
+        assert!(manager.request_keyframe_at(&session_id, start));
+        for i in 1..5 {
+            assert!(!manager.request_keyframe_at(&session_id, start + KEYFRAME_REQUEST_MIN_INTERVAL * i / 5));
+        }
+        assert!(manager.request_keyframe_at(&session_id, start + KEYFRAME_REQUEST_MIN_INTERVAL));
+
+        let forwarded = std::iter::from_fn(|| keyframe_rx.try_recv().ok()).count();
+        assert_eq!(forwarded, 2);
+
+        // 转发任务结束后不再转发
+        drop(keyframe_rx);
+        assert!(!manager.request_keyframe_at(&session_id, start + KEYFRAME_REQUEST_MIN_INTERVAL * 3));
+        assert!(!manager.request_keyframe(&Uuid::new_v4()));
+    }
 }
@@ -0,0 +1,173 @@
+// 告警API处理器
+//
+// 本模块提供活动告警查询、确认与静默管理的HTTP API端点
+
+use crate::alerting::{ActiveAlert, AlertEngine, AlertRule, Silence};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+use uuid::Uuid;
+
+/// API响应结构
+#[derive(Serialize)]
+pub struct ApiResponse<T> {
+    status: String,
+    data: Option<T>,
+    error: Option<String>,
+}
+
+impl<T> ApiResponse<T> {
+    fn success(data: T) -> Self {
+        Self {
+            status: "success".to_string(),
+            data: Some(data),
+            error: None,
+        }
+    }
+}
+
+/// 应用状态
+pub type AlertingAppState = Arc<AlertEngine>;
+
+/// 获取活动告警
+///
+/// GET /api/v1/alerting/alerts
+pub async fn get_active_alerts(
+    State(engine): State<AlertingAppState>,
+) -> Json<ApiResponse<Vec<ActiveAlert>>> {
+    Json(ApiResponse::success(engine.active_alerts()))
+}
+
+/// 确认告警（确认后不再重复通知）
+///
+/// POST /api/v1/alerting/alerts/{alert_id}/ack
+pub async fn acknowledge_alert(
+    Path(alert_id): Path<Uuid>,
+    State(engine): State<AlertingAppState>,
+) -> StatusCode {
+    if engine.acknowledge(&alert_id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// 获取告警规则
+///
+/// GET /api/v1/alerting/rules
+pub async fn get_alert_rules(
+    State(engine): State<AlertingAppState>,
+) -> Json<ApiResponse<Vec<AlertRule>>> {
+    Json(ApiResponse::success(engine.rules()))
+}
+
+/// 获取静默列表
+///
+/// GET /api/v1/alerting/silences
+pub async fn get_silences(
+    State(engine): State<AlertingAppState>,
+) -> Json<ApiResponse<Vec<Silence>>> {
+    Json(ApiResponse::success(engine.silences()))
+}
+
+/// 创建静默请求
+#[derive(Debug, Deserialize)]
+pub struct CreateSilenceRequest {
+    /// 规则ID（不指定则匹配所有规则）
+    pub rule_id: Option<String>,
+    /// 告警对象，如 `device:camera_01`（不指定则匹配所有对象）
+    pub subject: Option<String>,
+    /// 静默时长（秒）
+    pub duration_secs: u64,
+    #[serde(default)]
+    pub comment: String,
+}
+
+/// 创建静默
+///
+/// POST /api/v1/alerting/silences
+pub async fn create_silence(
+    State(engine): State<AlertingAppState>,
+    Json(req): Json<CreateSilenceRequest>,
+) -> Result<Json<ApiResponse<Silence>>, StatusCode> {
+    info!("Creating alert silence: {:?}", req);
+    if req.duration_secs == 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let silence = engine.add_silence(
+        req.rule_id,
+        req.subject,
+        Duration::from_secs(req.duration_secs),
+        req.comment,
+    );
+    Ok(Json(ApiResponse::success(silence)))
+}
+
+/// 删除静默
+///
+/// DELETE /api/v1/alerting/silences/{silence_id}
+pub async fn delete_silence(
+    Path(silence_id): Path<Uuid>,
+    State(engine): State<AlertingAppState>,
+) -> StatusCode {
+    if engine.remove_silence(&silence_id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::default_rules;
+
+    #[tokio::test]
+    async fn test_silence_endpoints() {
+        let engine = Arc::new(AlertEngine::new(default_rules(), Vec::new()).unwrap());
+
+        let invalid = create_silence(
+            State(engine.clone()),
+            Json(CreateSilenceRequest {
+                rule_id: None,
+                subject: None,
+                duration_secs: 0,
+                comment: String::new(),
+            }),
+        )
+        .await;
+        assert!(matches!(invalid, Err(StatusCode::BAD_REQUEST)));
+
+        let silence = create_silence(
+            State(engine.clone()),
+            Json(CreateSilenceRequest {
+                rule_id: Some("device_offline".to_string()),
+                subject: Some("device:camera_01".to_string()),
+                duration_secs: 600,
+                comment: "planned maintenance".to_string(),
+            }),
+        )
+        .await
+        .unwrap()
+        .0
+        .data
+        .unwrap();
+
+        assert_eq!(get_silences(State(engine.clone())).await.0.data.unwrap().len(), 1);
+        assert_eq!(
+            delete_silence(Path(silence.id), State(engine.clone())).await,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            acknowledge_alert(Path(Uuid::new_v4()), State(engine)).await,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
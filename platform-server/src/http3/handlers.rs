@@ -48,6 +48,16 @@ pub async fn health_check() -> Json<ApiResponse<String>> {
     Json(ApiResponse::success("OK".to_string()))
 }
 
+/// Prometheus 指标
+///
+/// GET /metrics
+pub async fn prometheus_metrics(
+    State((device_manager, _, distribution_manager, _, handler)): State<AppState>,
+) -> impl axum::response::IntoResponse {
+    let body = crate::metrics::render_metrics(&device_manager, &distribution_manager, &handler).await;
+    ([(axum::http::header::CONTENT_TYPE, crate::metrics::CONTENT_TYPE)], body)
+}
+
 /// 获取设备列表
 pub async fn get_devices(
     State((device_manager, _, _, _, _)): State<AppState>,
@@ -116,7 +126,8 @@ pub async fn get_recordings(
         bincode::deserialize(&response_buf).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     if response_msg.message_type == MessageType::FileListResponse {
-        let file_list: FileListResponse = bincode::deserialize(&response_msg.payload)
+        let version = device_manager.get_protocol(&device_id).version;
+        let file_list = FileListResponse::decode(version, &response_msg.payload)
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
         Ok(Json(ApiResponse::success(file_list.files)))
     } else {
@@ -133,6 +144,8 @@ pub struct StartLiveStreamRequest {
 pub struct StartLiveStreamResponse {
     session_id: String,
     stream_url: String,
+    /// fMP4 输出（视频 + AAC 音频轨道），可直接送入 MSE
+    fmp4_url: String,
 }
 
 /// 开始直通播放
@@ -152,6 +165,7 @@ pub async fn start_live_stream(
     let response = StartLiveStreamResponse {
         session_id: session_id.to_string(),
         stream_url: format!("/api/v1/stream/{}/segments", session_id),
+        fmp4_url: format!("/api/v1/stream/{}/fmp4", session_id),
     };
 
     Ok(Json(ApiResponse::success(response)))
@@ -160,10 +174,14 @@ pub async fn start_live_stream(
 /// 停止流
 pub async fn stop_stream(
     Path(session_id): Path<String>,
-    State((_, _, distribution_manager, _, _)): State<AppState>,
+    State((_, _, distribution_manager, _, handler)): State<AppState>,
 ) -> StatusCode {
     if let Ok(uuid) = Uuid::parse_str(&session_id) {
         distribution_manager.close_session(&uuid);
+        // 同时结束统一流会话，使延迟统计与历史汇总正常收尾
+        if handler.has_session(&uuid) {
+            let _ = handler.stop_stream(uuid).await;
+        }
         StatusCode::NO_CONTENT
     } else {
         StatusCode::BAD_REQUEST
@@ -175,6 +193,11 @@ pub struct StartPlaybackRequest {
     file_id: String,
     client_id: String,
     start_position: Option<f64>,
+    /// 播放速率（超过4x或负数时设备只发送关键帧）
+    playback_rate: Option<f64>,
+    /// 精确定位到 `start_position`（默认按关键帧定位）
+    #[serde(default)]
+    accurate_seek: bool,
 }
 
 #[derive(Serialize)]
@@ -204,6 +227,12 @@ pub async fn start_playback(
     
     let device_id = format!("{}_{}", parts[0], parts[1]);
     tracing::info!("Extracted device_id: {}", device_id);
+
+    let playback_rate = req.playback_rate.unwrap_or(1.0);
+    if !common::trick_play::is_valid_rate(playback_rate) {
+        tracing::warn!("Invalid playback rate: {}", playback_rate);
+        return Err(StatusCode::BAD_REQUEST);
+    }
     
     // 获取设备连接
     let connection = device_manager
@@ -219,7 +248,8 @@ pub async fn start_playback(
         file_path: file_id.to_string(),
         priority: 1,
         seek_position: req.start_position,
-        playback_rate: 1.0,
+        playback_rate,
+        accurate_seek: req.accurate_seek,
     };
 
     let file_req_data =
@@ -306,10 +336,16 @@ pub async fn get_playback_segments(
     
     tracing::info!("✓ SSE stream started for session: {}", session_id);
 
+    // 新观看端请求设备立即输出关键帧，无需等待下一个 GOP
+    if distribution_manager.request_keyframe(&uuid) {
+        tracing::debug!("Requested keyframe for new subscriber (session: {})", session_id);
+    }
+
     // 创建 SSE 流
     let stream = async_stream::stream! {
         tracing::info!("📺 SSE stream loop started");
         let mut count = 0;
+        let mut current_rendition = None;
         loop {
             match receiver.recv().await {
                 Ok(segment) => {
@@ -317,14 +353,49 @@ pub async fn get_playback_segments(
                     if count % 10 == 0 {
                         tracing::debug!("📦 Sent {} segments via SSE", count);
                     }
+
+                    // 音频分片（ADTS）走单独的事件类型，不影响视频解码器
+                    if segment.is_audio() {
+                        let audio_json = serde_json::json!({
+                            "segment_id": segment.segment_id,
+                            "pts": segment.pts,
+                            "dts": segment.dts,
+                            "timescale": segment.timescale,
+                            "duration": segment.duration,
+                            "frame_count": segment.frame_count,
+                            "data": base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &segment.data),
+                        });
+                        if let Ok(json) = serde_json::to_string(&audio_json) {
+                            yield Ok(axum::response::sse::Event::default().event("audio").data(json));
+                        }
+                        continue;
+                    }
+
+                    // 码流变化（含首个分片）时先通知客户端新的分辨率
+                    if current_rendition != Some(segment.rendition_id) {
+                        current_rendition = Some(segment.rendition_id);
+                        if let Some(rendition) = distribution_manager.get_rendition(&uuid, segment.rendition_id) {
+                            if let Ok(json) = serde_json::to_string(&rendition) {
+                                yield Ok(axum::response::sse::Event::default().event("rendition").data(json));
+                            }
+                        }
+                    }
                     
                     // 创建包含 base64 编码数据的 JSON 对象
                     let segment_json = serde_json::json!({
                         "segment_id": segment.segment_id,
                         "session_id": segment.session_id,
-                        "timestamp": segment.timestamp,
+                        "pts": segment.pts,
+                        "dts": segment.dts,
+                        "timescale": segment.timescale,
                         "duration": segment.duration,
+                        "frame_count": segment.frame_count,
                         "flags": segment.flags,
+                        "rendition_id": segment.rendition_id,
+                        "codec": segment.codec,
+                        // 关键帧携带参数集时给出 WebCodecs 编码字符串，前端据此配置解码器
+                        "codec_string": common::demux::ParameterSets::find(segment.codec, &segment.data)
+                            .and_then(|parameter_sets| parameter_sets.codec_string()),
                         "data": base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &segment.data),
                         "data_length": segment.data.len()
                     });
@@ -333,6 +404,11 @@ pub async fn get_playback_segments(
                         yield Ok(axum::response::sse::Event::default().data(json));
                     }
                 }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    // 客户端消费过慢，跳过积压的分片继续推送
+                    tracing::warn!("SSE client lagged, skipped {} segments (session: {})", skipped, uuid);
+                    distribution_manager.record_client_lag(skipped);
+                }
                 Err(e) => {
                     tracing::info!("SSE stream ended: {:?}, total segments: {}", e, count);
                     break;
@@ -348,12 +424,83 @@ pub async fn get_playback_segments(
     ))
 }
 
+/// 获取直通会话的 fMP4 流
+///
+/// 第一个关键帧到达后输出初始化分片，之后每个分片一个 moof/mdat。会话已有音频解码配置时
+/// 初始化分片包含 AAC 音频轨道（track 2），音视频 `tfdt` 来自同一设备时间轴。
+pub async fn get_live_fmp4(
+    Path(session_id): Path<String>,
+    State((_, _, distribution_manager, _, _)): State<AppState>,
+) -> Result<axum::response::Response, StatusCode> {
+    use crate::streaming::source::{SegmentFormat, SegmentSourceType};
+    use crate::streaming::LiveFMP4Muxer;
+
+    let uuid = Uuid::parse_str(&session_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let mut receiver = distribution_manager
+        .get_receiver(&uuid)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    tracing::info!("fMP4 stream started for session: {}", session_id);
+
+    // 新观看端请求设备立即输出关键帧
+    distribution_manager.request_keyframe(&uuid);
+
+    let stream = async_stream::stream! {
+        let mut muxer = LiveFMP4Muxer::new();
+        loop {
+            match receiver.recv().await {
+                Ok(mut segment) => {
+                    if segment.is_codec_config() {
+                        continue;
+                    }
+                    segment.normalize_timescale();
+                    let format = if segment.is_audio() {
+                        SegmentFormat::Aac
+                    } else {
+                        SegmentFormat::raw_video(segment.codec)
+                    };
+                    let is_keyframe = segment.is_keyframe();
+                    let source_segment = crate::streaming::VideoSegment {
+                        segment_id: segment.segment_id,
+                        pts: segment.pts,
+                        dts: segment.dts,
+                        duration: segment.duration,
+                        data: segment.data,
+                        is_keyframe,
+                        decode_only: false,
+                        discontinuity: false,
+                        format,
+                        source_type: SegmentSourceType::Live,
+                        receive_time: segment.receive_time,
+                        forward_time: None,
+                        device_send_time: None,
+                    };
+                    match muxer.push(source_segment, distribution_manager.audio_config(&uuid)) {
+                        Ok(data) if data.is_empty() => {}
+                        Ok(data) => yield Ok::<_, std::convert::Infallible>(bytes::Bytes::from(data)),
+                        Err(e) => tracing::warn!("fMP4 conversion failed (session: {}): {}", uuid, e),
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("fMP4 client lagged, skipped {} segments (session: {})", skipped, uuid);
+                    distribution_manager.record_client_lag(skipped);
+                }
+                Err(_) => break,
+            }
+        }
+    };
+
+    axum::response::Response::builder()
+        .header(axum::http::header::CONTENT_TYPE, "video/mp4")
+        .body(axum::body::Body::from_stream(stream))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
 
 // ========== 统一流API端点 ==========
 
 use crate::streaming::{
-    LiveStreamSource, PlaybackSource, StreamConfig, UnifiedStreamHandler,
-    FileStreamReader, FileReaderConfig,
+    LiveStreamSource, PlaybackSource, RenditionSelector, StreamConfig, UnifiedStreamHandler,
+    ViewerLimits,
 };
 use std::sync::Arc;
 
@@ -390,6 +537,9 @@ pub struct StreamConfigRequest {
     pub low_latency_mode: Option<bool>,
     /// 目标延迟（毫秒）
     pub target_latency_ms: Option<u32>,
+    /// 直通播放码流上限（最大画面高度/码率，移动端据此自动选择子码流）
+    #[serde(flatten)]
+    pub viewer_limits: ViewerLimits,
 }
 
 /// 统一流启动响应
@@ -417,6 +567,13 @@ pub async fn unified_stream_start(
     // 解析流模式
     let mode = req.mode.to_lowercase();
     
+    // 观看端码流上限
+    let viewer_limits = req
+        .config
+        .as_ref()
+        .map(|cfg| cfg.viewer_limits)
+        .unwrap_or_default();
+
     // 创建流配置
     let config = if let Some(cfg) = req.config {
         StreamConfig {
@@ -447,6 +604,15 @@ pub async fn unified_stream_start(
                 return Err(StatusCode::SERVICE_UNAVAILABLE);
             }
 
+            // 已协商的设备必须声明支持直通播放（旧版设备不做特性检查）
+            let protocol = device_manager.get_protocol(&device_id);
+            if protocol.version >= common::ProtocolVersion::V1_1
+                && !protocol.supports(common::FeatureFlags::LIVE_STREAM)
+            {
+                tracing::warn!("Device {} does not support live streaming", device_id);
+                return Err(StatusCode::NOT_IMPLEMENTED);
+            }
+
             // 获取设备连接
             let connection = device_manager
                 .get_connection(&device_id)
@@ -457,12 +623,30 @@ pub async fn unified_stream_start(
 
             tracing::info!("🎥 Starting live stream for device: {} (session: {})", device_id, session_id);
 
+            // 多码流设备：按观看端上限选择初始码流
+            let rendition_selector = if protocol.supports(common::FeatureFlags::RENDITION_SWITCH) {
+                device_manager
+                    .get_device(&device_id)
+                    .ok()
+                    .and_then(|device| RenditionSelector::new(device.capabilities.renditions, viewer_limits))
+            } else {
+                None
+            };
+            let initial_rendition = rendition_selector.as_ref().map(|selector| selector.current().clone());
+            if let Some(rendition) = &initial_rendition {
+                tracing::info!(
+                    "Selected rendition #{} {} ({}x{}) for session {}",
+                    rendition.id, rendition.name, rendition.width, rendition.height, session_id
+                );
+            }
+
             // 构建StartLiveStream请求
             let live_request = StartLiveStreamRequest {
                 quality_preference: "low_latency".to_string(),
                 target_latency_ms: config.target_latency_ms,
-                target_fps: 30,
-                target_bitrate: 2_000_000, // 2 Mbps
+                target_fps: initial_rendition.as_ref().map_or(30, |r| r.fps),
+                target_bitrate: initial_rendition.as_ref().map_or(2_000_000, |r| r.bitrate as usize), // 默认 2 Mbps
+                rendition_id: initial_rendition.as_ref().map(|r| r.id),
             };
 
             let request_data = bincode::serialize(&live_request)
@@ -524,7 +708,39 @@ pub async fn unified_stream_start(
             // 使用DistributionManager创建会话并获取接收器
             let segment_rx = distribution_manager.create_session(session_id);
             
-            let live_source = LiveStreamSource::new(device_id, segment_rx);
+            let mut live_source = LiveStreamSource::new(device_id.clone(), segment_rx)
+                .with_clock_sync(handler.get_latency_monitor().get_clock_sync())
+                .with_lag_counter(distribution_manager.source_lag_counter())
+                .with_discontinuity_counter(distribution_manager.discontinuity_counter());
+
+            if let Some(selector) = rendition_selector {
+                distribution_manager.set_renditions(
+                    &session_id,
+                    selector.renditions().to_vec(),
+                    selector.current().id,
+                );
+                let (switch_tx, switch_rx) = tokio::sync::mpsc::unbounded_channel();
+                tokio::spawn(forward_rendition_switches(
+                    device_manager.clone(),
+                    distribution_manager.clone(),
+                    device_id.clone(),
+                    session_id,
+                    switch_rx,
+                ));
+                live_source = live_source.with_rendition_selector(selector, switch_tx);
+            }
+
+            if protocol.supports(common::FeatureFlags::KEYFRAME_REQUEST) {
+                let (keyframe_tx, keyframe_rx) = tokio::sync::mpsc::unbounded_channel();
+                distribution_manager.set_keyframe_requester(&session_id, keyframe_tx);
+                tokio::spawn(forward_keyframe_requests(
+                    device_manager.clone(),
+                    device_id.clone(),
+                    session_id,
+                    keyframe_rx,
+                ));
+                live_source = live_source.with_keyframe_requests(distribution_manager.clone(), session_id);
+            }
             Box::new(live_source)
         }
         "playback" => {
@@ -592,6 +808,59 @@ pub async fn unified_stream_start(
     Ok(Json(ApiResponse::success(response)))
 }
 
+/// 把码流选择器的切换决定转发给设备，直到数据源结束
+async fn forward_rendition_switches(
+    device_manager: DeviceManager,
+    distribution_manager: DistributionManager,
+    device_id: String,
+    session_id: Uuid,
+    mut switch_rx: tokio::sync::mpsc::UnboundedReceiver<u8>,
+) {
+    while let Some(rendition_id) = switch_rx.recv().await {
+        let payload = match bincode::serialize(&common::SwitchRenditionRequest { rendition_id }) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::error!("Failed to serialize switch rendition request: {}", e);
+                continue;
+            }
+        };
+
+        // 先设定分发目标，设备的新码流关键帧到达时即切换
+        distribution_manager.set_target_rendition(&session_id, rendition_id);
+        match device_manager
+            .send_control_message(&device_id, session_id, common::MessageType::SwitchRendition, payload)
+            .await
+        {
+            Ok(reply) if reply == b"OK" => {
+                tracing::info!("Device {} switching to rendition #{} (session: {})", device_id, rendition_id, session_id);
+            }
+            Ok(_) => tracing::warn!("Device {} rejected rendition #{} (session: {})", device_id, rendition_id, session_id),
+            Err(e) => tracing::warn!("Failed to switch rendition on device {}: {}", device_id, e),
+        }
+    }
+}
+
+/// 把分发会话合并后的关键帧请求转发给设备，直到分发会话关闭
+async fn forward_keyframe_requests(
+    device_manager: DeviceManager,
+    device_id: String,
+    session_id: Uuid,
+    mut keyframe_rx: tokio::sync::mpsc::UnboundedReceiver<()>,
+) {
+    while keyframe_rx.recv().await.is_some() {
+        match device_manager
+            .send_control_message(&device_id, session_id, common::MessageType::KeyframeRequest, Vec::new())
+            .await
+        {
+            Ok(reply) if reply == b"OK" => {
+                tracing::debug!("Device {} forcing keyframe (session: {})", device_id, session_id);
+            }
+            Ok(_) => tracing::warn!("Device {} rejected keyframe request (session: {})", device_id, session_id),
+            Err(e) => tracing::warn!("Failed to request keyframe from device {}: {}", device_id, e),
+        }
+    }
+}
+
 /// 流控制请求
 #[derive(Debug, Deserialize)]
 pub struct StreamControlRequest {
@@ -599,6 +868,9 @@ pub struct StreamControlRequest {
     pub command: String,
     /// 定位位置（秒，用于seek命令）
     pub position: Option<f64>,
+    /// 精确定位到帧（用于seek命令，默认按关键帧定位）
+    #[serde(default)]
+    pub accurate: bool,
     /// 播放速率（用于set_rate命令）
     pub rate: Option<f64>,
 }
@@ -636,7 +908,7 @@ pub async fn unified_stream_control(
         }
         "seek" => {
             let position = req.position.ok_or(StatusCode::BAD_REQUEST)?;
-            handler.seek_stream(session_id, position).await
+            handler.seek_stream(session_id, position, req.accurate).await
         }
         "set_rate" => {
             let rate = req.rate.ok_or(StatusCode::BAD_REQUEST)?;
@@ -734,3 +1006,223 @@ pub async fn unified_stream_status(
 
     Ok(Json(ApiResponse::success(response)))
 }
+
+// ========== 客户端播放确认 ==========
+
+use crate::latency::{PlaybackAckBatch, PlaybackAckRecorder, PlaybackAckSummary, MAX_ACKS_PER_BATCH};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::Response;
+
+/// 批量上报播放确认
+///
+/// POST /api/v1/stream/{session_id}/acks
+///
+/// 前端渲染分片后批量上报分片ID与渲染时间，用于测量分发延迟和端到端延迟。
+pub async fn post_playback_acks(
+    Path(session_id): Path<Uuid>,
+    State((_, _, _, _, handler)): State<AppState>,
+    Json(batch): Json<PlaybackAckBatch>,
+) -> Result<Json<ApiResponse<PlaybackAckSummary>>, StatusCode> {
+    let received_at = std::time::SystemTime::now();
+
+    if !handler.has_session(&session_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if batch.acks.len() > MAX_ACKS_PER_BATCH {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let summary = handler
+        .get_ack_recorder()
+        .record(session_id, &batch, received_at);
+    Ok(Json(ApiResponse::success(summary)))
+}
+
+// ========== 客户端播放遥测 ==========
+
+use crate::streaming::{ClientTelemetryReport, TelemetryDecision};
+
+/// 上报客户端播放遥测
+///
+/// POST /api/v1/stream/{session_id}/telemetry
+///
+/// 前端周期性上报缓冲时长、丢帧数、解码耗时和卡顿事件，平台据此调整发送速率、
+/// 请求关键帧或丢弃非参考帧。
+pub async fn post_client_telemetry(
+    Path(session_id): Path<Uuid>,
+    State((_, _, _, _, handler)): State<AppState>,
+    Json(report): Json<ClientTelemetryReport>,
+) -> Result<Json<ApiResponse<TelemetryDecision>>, StatusCode> {
+    report.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let decision = handler
+        .record_client_telemetry(session_id, report)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(Json(ApiResponse::success(decision)))
+}
+
+/// 客户端 WebSocket 消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ClientSocketMessage {
+    /// 批量播放确认（字段同 POST /acks 请求体）
+    PlaybackAck(PlaybackAckBatch),
+    /// 播放遥测（字段同 POST /telemetry 请求体）
+    Telemetry(ClientTelemetryReport),
+    /// 心跳
+    Ping,
+}
+
+/// 服务端 WebSocket 消息
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+enum ServerSocketMessage {
+    /// 播放确认处理结果
+    AckResult(PlaybackAckSummary),
+    /// 遥测处理结果
+    TelemetryResult(TelemetryDecision),
+    /// 心跳响应
+    Pong,
+    /// 错误
+    Error { message: String },
+}
+
+/// 播放确认 WebSocket
+///
+/// GET /api/v1/stream/{session_id}/ws
+///
+/// 长连接版本的播放确认与遥测通道，适合高帧率场景下频繁上报。
+pub async fn playback_ack_socket(
+    ws: WebSocketUpgrade,
+    Path(session_id): Path<Uuid>,
+    State((_, _, _, _, handler)): State<AppState>,
+) -> Result<Response, StatusCode> {
+    if !handler.has_session(&session_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(ws.on_upgrade(move |socket| handle_ack_socket(socket, session_id, handler)))
+}
+
+async fn handle_ack_socket(mut socket: WebSocket, session_id: Uuid, handler: Arc<UnifiedStreamHandler>) {
+    let recorder = handler.get_ack_recorder();
+    tracing::info!("Playback ack socket opened for session {}", session_id);
+
+    while let Some(message) = socket.recv().await {
+        let text = match message {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(_) => break,
+            Ok(_) => continue,
+        };
+
+        let reply = handle_socket_message(&text, session_id, &recorder, &handler);
+        let payload = match serde_json::to_string(&reply) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::error!("Failed to serialize socket reply: {}", e);
+                continue;
+            }
+        };
+        if socket.send(Message::Text(payload)).await.is_err() {
+            break;
+        }
+    }
+
+    tracing::info!("Playback ack socket closed for session {}", session_id);
+}
+
+fn handle_socket_message(
+    text: &str,
+    session_id: Uuid,
+    recorder: &PlaybackAckRecorder,
+    handler: &UnifiedStreamHandler,
+) -> ServerSocketMessage {
+    let received_at = std::time::SystemTime::now();
+
+    match serde_json::from_str::<ClientSocketMessage>(text) {
+        Ok(ClientSocketMessage::PlaybackAck(batch)) => {
+            if batch.acks.len() > MAX_ACKS_PER_BATCH {
+                return ServerSocketMessage::Error {
+                    message: format!("Too many acks in one batch (max {})", MAX_ACKS_PER_BATCH),
+                };
+            }
+            ServerSocketMessage::AckResult(recorder.record(session_id, &batch, received_at))
+        }
+        Ok(ClientSocketMessage::Telemetry(report)) => {
+            if let Err(message) = report.validate() {
+                return ServerSocketMessage::Error { message };
+            }
+            match handler.record_client_telemetry(session_id, report) {
+                Ok(decision) => ServerSocketMessage::TelemetryResult(decision),
+                Err(e) => ServerSocketMessage::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+        Ok(ClientSocketMessage::Ping) => ServerSocketMessage::Pong,
+        Err(e) => ServerSocketMessage::Error {
+            message: format!("Invalid message: {}", e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latency::{EndToEndLatencyMonitor, LatencyStatisticsManager};
+
+    #[test]
+    fn test_socket_playback_ack_message() {
+        let handler = UnifiedStreamHandler::new();
+        let monitor = Arc::new(EndToEndLatencyMonitor::with_defaults());
+        let recorder = PlaybackAckRecorder::new(
+            Arc::clone(&monitor),
+            Arc::new(LatencyStatisticsManager::new()),
+        );
+        let session_id = Uuid::new_v4();
+        let segment_id = Uuid::new_v4();
+        monitor.record_platform_forward(segment_id, std::time::SystemTime::now());
+
+        let text = format!(
+            r#"{{"type":"PlaybackAck","acks":[{{"segment_id":"{}","rendered_at_ms":1000}}],"sent_at_ms":1010}}"#,
+            segment_id
+        );
+        let reply = serde_json::to_value(handle_socket_message(&text, session_id, &recorder, &handler)).unwrap();
+        assert_eq!(reply["type"], "AckResult");
+        assert_eq!(reply["accepted"], 1);
+        assert_eq!(reply["unmatched"], 0);
+
+        let reply = serde_json::to_value(handle_socket_message(r#"{"type":"Ping"}"#, session_id, &recorder, &handler)).unwrap();
+        assert_eq!(reply["type"], "Pong");
+
+        let reply = serde_json::to_value(handle_socket_message("not json", session_id, &recorder, &handler)).unwrap();
+        assert_eq!(reply["type"], "Error");
+    }
+
+    #[tokio::test]
+    async fn test_socket_telemetry_message() {
+        use crate::streaming::handler::tests::TestSource;
+        use crate::streaming::StreamConfig;
+
+        let handler = UnifiedStreamHandler::new();
+        let recorder = handler.get_ack_recorder();
+        let text = r#"{"type":"Telemetry","buffered_ms":0,"stall_count":1,"stalled":true}"#;
+
+        let reply = serde_json::to_value(handle_socket_message(text, Uuid::new_v4(), &recorder, &handler)).unwrap();
+        assert_eq!(reply["type"], "Error");
+
+        let session_id = handler
+            .start_stream(Box::new(TestSource::new(0)), StreamConfig::default())
+            .await
+            .unwrap();
+        let reply = serde_json::to_value(handle_socket_message(text, session_id, &recorder, &handler)).unwrap();
+        assert_eq!(reply["type"], "TelemetryResult");
+        assert_eq!(reply["request_keyframe"], true);
+
+        let stats = handler.get_session_stats(session_id).await.unwrap();
+        assert_eq!(stats.client.reports, 1);
+        assert_eq!(stats.client.stall_count, 1);
+        let latency = handler.get_stats_manager().get_statistics(&session_id).unwrap();
+        assert!(latency.client.stalled);
+    }
+}
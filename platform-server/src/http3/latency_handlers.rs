@@ -3,11 +3,12 @@
 // 本模块提供延迟监控相关的HTTP API端点
 
 use crate::latency::{
-    AlertBroadcaster, AlertMessage, EndToEndLatencyMonitor, LatencyStatistics,
-    LatencyStatisticsManager,
+    AlertBroadcaster, DeviceTransmissionStats, EndToEndLatencyMonitor,
+    HistoryQuery, LatencySeries, LatencyStatistics, LatencyStatisticsManager, LatencyThresholds,
+    Resolution, SeriesKey, ThresholdConfigSnapshot, ThresholdOverride,
 };
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{
         sse::{Event, KeepAlive},
@@ -104,11 +105,127 @@ pub async fn get_segment_breakdown(
     }
 }
 
+/// 获取所有设备的传输延迟统计（设备→平台）
+///
+/// GET /api/v1/latency/devices
+pub async fn get_all_device_transmission(
+    State((monitor, _, _)): State<LatencyAppState>,
+) -> Json<ApiResponse<Vec<DeviceTransmissionStats>>> {
+    info!("Getting transmission statistics for all devices");
+    Json(ApiResponse::success(monitor.get_all_device_transmission()))
+}
+
+/// 获取设备的传输延迟统计（含时钟偏移估计与抖动）
+///
+/// GET /api/v1/latency/devices/{device_id}
+pub async fn get_device_transmission(
+    Path(device_id): Path<String>,
+    State((monitor, _, _)): State<LatencyAppState>,
+) -> Result<Json<ApiResponse<DeviceTransmissionStats>>, StatusCode> {
+    info!("Getting transmission statistics for device {}", device_id);
+
+    match monitor.get_device_transmission(&device_id) {
+        Some(stats) => Ok(Json(ApiResponse::success(stats))),
+        None => {
+            error!("Transmission statistics not found for device {}", device_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+    }
+}
+
+/// 历史查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryParams {
+    /// 粒度：1s / 1m / 1h（默认 1m）
+    pub resolution: Option<String>,
+    /// 起始时间（Unix 秒，默认结束时间前 1 小时）
+    pub from: Option<u64>,
+    /// 结束时间（Unix 秒，不含，默认当前时间）
+    pub to: Option<u64>,
+    /// 步长（秒，默认等于粒度）
+    pub step: Option<u64>,
+}
+
+impl HistoryParams {
+    fn into_query(self, key: SeriesKey) -> Result<HistoryQuery, StatusCode> {
+        let resolution = match self.resolution.as_deref() {
+            Some(resolution) => resolution.parse::<Resolution>().map_err(|e| {
+                error!("Invalid history resolution: {}", e);
+                StatusCode::BAD_REQUEST
+            })?,
+            None => Resolution::Minute,
+        };
+        // 查询区间为左闭右开，默认结束时间取下一秒以包含当前桶
+        let to = self.to.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                + 1
+        });
+        let from = self.from.unwrap_or_else(|| to.saturating_sub(3600));
+
+        Ok(HistoryQuery {
+            key,
+            resolution,
+            from,
+            to,
+            step: self.step,
+        })
+    }
+}
+
+async fn query_history(
+    stats_manager: Arc<LatencyStatisticsManager>,
+    query: HistoryQuery,
+) -> Result<Json<ApiResponse<LatencySeries>>, StatusCode> {
+    let history = stats_manager.history().ok_or_else(|| {
+        error!("Latency history is not enabled");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+
+    let series = tokio::task::spawn_blocking(move || history.query(&query))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            error!("Latency history query failed: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok(Json(ApiResponse::success(series)))
+}
+
+/// 查询会话的延迟历史
+///
+/// GET /api/v1/latency/history/sessions/{session_id}?resolution=1m&from=..&to=..&step=..
+pub async fn get_session_history(
+    Path(session_id): Path<Uuid>,
+    Query(params): Query<HistoryParams>,
+    State((_, stats_manager, _)): State<LatencyAppState>,
+) -> Result<Json<ApiResponse<LatencySeries>>, StatusCode> {
+    info!("Querying latency history for session {}: {:?}", session_id, params);
+    let query = params.into_query(SeriesKey::Session(session_id))?;
+    query_history(stats_manager, query).await
+}
+
+/// 查询设备的延迟历史（合并该设备的所有直通会话）
+///
+/// GET /api/v1/latency/history/devices/{device_id}?resolution=1h&from=..&to=..
+pub async fn get_device_history(
+    Path(device_id): Path<String>,
+    Query(params): Query<HistoryParams>,
+    State((_, stats_manager, _)): State<LatencyAppState>,
+) -> Result<Json<ApiResponse<LatencySeries>>, StatusCode> {
+    info!("Querying latency history for device {}: {:?}", device_id, params);
+    let query = params.into_query(SeriesKey::Device(device_id))?;
+    query_history(stats_manager, query).await
+}
+
 /// 订阅延迟告警（SSE）
 ///
 /// GET /api/v1/latency/alerts
 ///
-/// 返回一个SSE流，实时推送延迟告警和统计更新
+/// 返回一个SSE流，实时推送告警状态切换（待触发 → 触发 → 恢复）和统计更新
 pub async fn subscribe_alerts(
     State((_, _, broadcaster)): State<LatencyAppState>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
@@ -152,26 +269,7 @@ pub async fn subscribe_session_alerts(
     let event_stream = stream.filter_map(move |result| match result {
         Ok(message) => {
             // 过滤只属于该会话的消息
-            let matches = match &message {
-                AlertMessage::LatencyAlert {
-                    session_id: msg_session_id,
-                    ..
-                }
-                | AlertMessage::StatisticsUpdate {
-                    session_id: msg_session_id,
-                    ..
-                }
-                | AlertMessage::SessionStarted {
-                    session_id: msg_session_id,
-                    ..
-                }
-                | AlertMessage::SessionEnded {
-                    session_id: msg_session_id,
-                    ..
-                } => *msg_session_id == session_id,
-            };
-
-            if matches {
+            if message.session_id() == Some(session_id) {
                 debug!(
                     "Broadcasting alert message for session {}: {:?}",
                     session_id, message
@@ -209,22 +307,139 @@ pub struct LatencyConfig {
     pub end_to_end_threshold_ms: Option<u64>,
 }
 
-/// 更新延迟监控配置
+impl From<LatencyConfig> for ThresholdOverride {
+    fn from(config: LatencyConfig) -> Self {
+        Self {
+            transmission_ms: config.transmission_threshold_ms,
+            processing_ms: config.processing_threshold_ms,
+            distribution_ms: config.distribution_threshold_ms,
+            end_to_end_ms: config.end_to_end_threshold_ms,
+        }
+    }
+}
+
+/// 获取延迟阈值配置（全局默认值与所有覆盖）
+///
+/// GET /api/v1/latency/config
+pub async fn get_latency_config(
+    State((monitor, _, _)): State<LatencyAppState>,
+) -> Json<ApiResponse<ThresholdConfigSnapshot>> {
+    Json(ApiResponse::success(monitor.get_threshold_config().snapshot()))
+}
+
+/// 更新全局默认阈值，立即生效
 ///
 /// PUT /api/v1/latency/config
 ///
-/// 注意：此端点用于演示，实际实现需要重新创建监控器实例
+/// 未提供的字段保持不变，返回更新后的默认阈值。
 pub async fn update_latency_config(
-    State(_state): State<LatencyAppState>,
+    State((monitor, _, _)): State<LatencyAppState>,
     Json(config): Json<LatencyConfig>,
-) -> Json<ApiResponse<String>> {
+) -> Result<Json<ApiResponse<LatencyThresholds>>, StatusCode> {
     info!("Updating latency monitoring configuration: {:?}", config);
 
-    // 在实际实现中，这里应该更新监控器的阈值配置
-    // 由于当前设计中阈值在创建时设置，这里只返回成功消息
-    Json(ApiResponse::success(
-        "Configuration update received (note: requires restart to apply)".to_string(),
-    ))
+    let thresholds = monitor
+        .get_threshold_config()
+        .update_defaults(config.into())
+        .map_err(|e| {
+            error!("Failed to update latency thresholds: {:#}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok(Json(ApiResponse::success(thresholds)))
+}
+
+/// 生效阈值查询参数
+#[derive(Debug, Deserialize)]
+pub struct EffectiveThresholdParams {
+    pub session_id: Option<Uuid>,
+    pub device_id: Option<String>,
+}
+
+/// 获取会话/设备的生效阈值
+///
+/// GET /api/v1/latency/config/effective?session_id=..&device_id=..
+pub async fn get_effective_latency_config(
+    Query(params): Query<EffectiveThresholdParams>,
+    State((monitor, _, _)): State<LatencyAppState>,
+) -> Json<ApiResponse<LatencyThresholds>> {
+    let thresholds = monitor
+        .get_threshold_config()
+        .effective(params.session_id.as_ref(), params.device_id.as_deref());
+    Json(ApiResponse::success(thresholds))
+}
+
+/// 设置设备阈值覆盖（持久化）
+///
+/// PUT /api/v1/latency/config/devices/{device_id}
+pub async fn set_device_latency_config(
+    Path(device_id): Path<String>,
+    State((monitor, _, _)): State<LatencyAppState>,
+    Json(config): Json<LatencyConfig>,
+) -> Result<Json<ApiResponse<LatencyThresholds>>, StatusCode> {
+    let thresholds = monitor.get_threshold_config();
+    thresholds
+        .set_device_override(&device_id, config.into())
+        .map_err(|e| {
+            error!("Failed to set latency thresholds for device {}: {:#}", device_id, e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok(Json(ApiResponse::success(
+        thresholds.effective(None, Some(&device_id)),
+    )))
+}
+
+/// 删除设备阈值覆盖
+///
+/// DELETE /api/v1/latency/config/devices/{device_id}
+pub async fn delete_device_latency_config(
+    Path(device_id): Path<String>,
+    State((monitor, _, _)): State<LatencyAppState>,
+) -> StatusCode {
+    match monitor.get_threshold_config().remove_device_override(&device_id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("Failed to remove latency thresholds for device {}: {:#}", device_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// 设置会话阈值覆盖（会话结束后清除，不持久化）
+///
+/// PUT /api/v1/latency/config/sessions/{session_id}
+pub async fn set_session_latency_config(
+    Path(session_id): Path<Uuid>,
+    State((monitor, _, _)): State<LatencyAppState>,
+    Json(config): Json<LatencyConfig>,
+) -> Result<Json<ApiResponse<LatencyThresholds>>, StatusCode> {
+    let thresholds = monitor.get_threshold_config();
+    thresholds
+        .set_session_override(session_id, config.into())
+        .map_err(|e| {
+            error!("Failed to set latency thresholds for session {}: {:#}", session_id, e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok(Json(ApiResponse::success(
+        thresholds.effective(Some(&session_id), None),
+    )))
+}
+
+/// 删除会话阈值覆盖
+///
+/// DELETE /api/v1/latency/config/sessions/{session_id}
+pub async fn delete_session_latency_config(
+    Path(session_id): Path<Uuid>,
+    State((monitor, _, _)): State<LatencyAppState>,
+) -> StatusCode {
+    if monitor.get_threshold_config().remove_session_override(&session_id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
 }
 
 /// 健康检查端点
@@ -274,6 +489,139 @@ mod tests {
         assert_eq!(result.0.data.as_ref().unwrap().len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_get_device_transmission() {
+        let monitor = Arc::new(EndToEndLatencyMonitor::with_defaults());
+        let stats_manager = Arc::new(LatencyStatisticsManager::new());
+        let broadcaster = Arc::new(AlertBroadcaster::with_defaults());
+
+        let send = std::time::SystemTime::now();
+        monitor.record_device_transmission("device_001", 0, None, send, send + Duration::from_millis(8));
+
+        let state = (monitor, stats_manager, broadcaster);
+
+        let result = get_device_transmission(Path("device_001".to_string()), State(state.clone())).await;
+        assert_eq!(result.unwrap().0.data.unwrap().last_latency_ms, 8.0);
+
+        let missing = get_device_transmission(Path("unknown".to_string()), State(state)).await;
+        assert!(matches!(missing, Err(StatusCode::NOT_FOUND)));
+    }
+
+    #[tokio::test]
+    async fn test_get_history() {
+        let monitor = Arc::new(EndToEndLatencyMonitor::with_defaults());
+        let broadcaster = Arc::new(AlertBroadcaster::with_defaults());
+
+        let without_history = (
+            monitor.clone(),
+            Arc::new(LatencyStatisticsManager::new()),
+            broadcaster.clone(),
+        );
+        let result = get_session_history(
+            Path(Uuid::new_v4()),
+            Query(HistoryParams::default()),
+            State(without_history),
+        )
+        .await;
+        assert!(matches!(result, Err(StatusCode::SERVICE_UNAVAILABLE)));
+
+        let history = Arc::new(
+            crate::latency::LatencyHistory::open_in_memory(Default::default()).unwrap(),
+        );
+        let stats_manager = Arc::new(LatencyStatisticsManager::with_history(history.clone()));
+        let session_id = Uuid::new_v4();
+        stats_manager.start_device_session(session_id, Some("device_001".to_string()));
+        stats_manager.record_segment_latency(&session_id, Duration::from_millis(40), 1024);
+        stats_manager.stop_session(&session_id);
+        history.flush().unwrap();
+
+        let state = (monitor, stats_manager, broadcaster);
+        let params = HistoryParams {
+            resolution: Some("1s".to_string()),
+            ..Default::default()
+        };
+        let series = get_device_history(
+            Path("device_001".to_string()),
+            Query(params),
+            State(state.clone()),
+        )
+        .await
+        .unwrap()
+        .0
+        .data
+        .unwrap();
+        assert_eq!(series.points.iter().map(|p| p.segments).sum::<u64>(), 1);
+
+        let invalid = HistoryParams {
+            resolution: Some("5m".to_string()),
+            ..Default::default()
+        };
+        let result = get_session_history(Path(session_id), Query(invalid), State(state)).await;
+        assert!(matches!(result, Err(StatusCode::BAD_REQUEST)));
+    }
+
+    #[tokio::test]
+    async fn test_latency_config_endpoints() {
+        let monitor = Arc::new(EndToEndLatencyMonitor::with_defaults());
+        let stats_manager = Arc::new(LatencyStatisticsManager::new());
+        let broadcaster = Arc::new(AlertBroadcaster::with_defaults());
+        let state = (monitor, stats_manager, broadcaster);
+
+        let updated = update_latency_config(
+            State(state.clone()),
+            Json(LatencyConfig {
+                transmission_threshold_ms: None,
+                processing_threshold_ms: Some(20),
+                distribution_threshold_ms: None,
+                end_to_end_threshold_ms: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.0.data.unwrap().processing_ms, 20);
+
+        let device = set_device_latency_config(
+            Path("camera_01".to_string()),
+            State(state.clone()),
+            Json(LatencyConfig {
+                transmission_threshold_ms: Some(500),
+                processing_threshold_ms: None,
+                distribution_threshold_ms: None,
+                end_to_end_threshold_ms: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let effective = device.0.data.unwrap();
+        assert_eq!(effective.transmission_ms, 500);
+        assert_eq!(effective.processing_ms, 20);
+
+        let snapshot = get_latency_config(State(state.clone())).await.0.data.unwrap();
+        assert_eq!(snapshot.devices.len(), 1);
+
+        let invalid = set_session_latency_config(
+            Path(Uuid::new_v4()),
+            State(state.clone()),
+            Json(LatencyConfig {
+                transmission_threshold_ms: Some(0),
+                processing_threshold_ms: None,
+                distribution_threshold_ms: None,
+                end_to_end_threshold_ms: None,
+            }),
+        )
+        .await;
+        assert!(matches!(invalid, Err(StatusCode::BAD_REQUEST)));
+
+        assert_eq!(
+            delete_device_latency_config(Path("camera_01".to_string()), State(state.clone())).await,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            delete_device_latency_config(Path("camera_01".to_string()), State(state)).await,
+            StatusCode::NOT_FOUND
+        );
+    }
+
     #[tokio::test]
     async fn test_health_check() {
         let result = latency_health_check().await;
@@ -1,3 +1,4 @@
+mod alerting_handlers;
 mod handlers;
 mod latency_handlers;
 mod routes;
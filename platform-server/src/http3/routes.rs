@@ -1,3 +1,4 @@
+use crate::alerting::AlertEngine;
 use crate::device::DeviceManager;
 use crate::distribution::DistributionManager;
 use crate::latency::LatencyMonitor;
@@ -16,6 +17,7 @@ pub fn create_router(
     distribution_manager: DistributionManager,
     latency_monitor: LatencyMonitor,
     stream_handler: Arc<UnifiedStreamHandler>,
+    alert_engine: Arc<AlertEngine>,
 ) -> Router {
     // 创建延迟监控状态
     let latency_state = (
@@ -36,13 +38,63 @@ pub fn create_router(
             "/segments/:segment_id/breakdown",
             get(super::latency_handlers::get_segment_breakdown),
         )
+        .route("/devices", get(super::latency_handlers::get_all_device_transmission))
+        .route(
+            "/devices/:device_id",
+            get(super::latency_handlers::get_device_transmission),
+        )
+        .route(
+            "/history/sessions/:session_id",
+            get(super::latency_handlers::get_session_history),
+        )
+        .route(
+            "/history/devices/:device_id",
+            get(super::latency_handlers::get_device_history),
+        )
         .route("/alerts", get(super::latency_handlers::subscribe_alerts))
         .route(
             "/sessions/:session_id/alerts",
             get(super::latency_handlers::subscribe_session_alerts),
         )
-        .route("/config", put(super::latency_handlers::update_latency_config))
+        .route(
+            "/config",
+            get(super::latency_handlers::get_latency_config)
+                .put(super::latency_handlers::update_latency_config),
+        )
+        .route(
+            "/config/effective",
+            get(super::latency_handlers::get_effective_latency_config),
+        )
+        .route(
+            "/config/devices/:device_id",
+            put(super::latency_handlers::set_device_latency_config)
+                .delete(super::latency_handlers::delete_device_latency_config),
+        )
+        .route(
+            "/config/sessions/:session_id",
+            put(super::latency_handlers::set_session_latency_config)
+                .delete(super::latency_handlers::delete_session_latency_config),
+        )
         .with_state(latency_state);
+
+    // 告警路由
+    let alerting_routes = Router::new()
+        .route("/alerts", get(super::alerting_handlers::get_active_alerts))
+        .route(
+            "/alerts/:alert_id/ack",
+            post(super::alerting_handlers::acknowledge_alert),
+        )
+        .route("/rules", get(super::alerting_handlers::get_alert_rules))
+        .route(
+            "/silences",
+            get(super::alerting_handlers::get_silences)
+                .post(super::alerting_handlers::create_silence),
+        )
+        .route(
+            "/silences/:silence_id",
+            delete(super::alerting_handlers::delete_silence),
+        )
+        .with_state(alert_engine);
     
     // 主路由
     Router::new()
@@ -65,10 +117,26 @@ pub fn create_router(
             "/api/v1/stream/:session_id/segments",
             get(super::handlers::get_playback_segments),
         )
+        .route(
+            "/api/v1/stream/:session_id/fmp4",
+            get(super::handlers::get_live_fmp4),
+        )
         .route(
             "/api/v1/stream/:session_id/control",
             post(super::handlers::playback_control),
         )
+        .route(
+            "/api/v1/stream/:session_id/acks",
+            post(super::handlers::post_playback_acks),
+        )
+        .route(
+            "/api/v1/stream/:session_id/telemetry",
+            post(super::handlers::post_client_telemetry),
+        )
+        .route(
+            "/api/v1/stream/:session_id/ws",
+            get(super::handlers::playback_ack_socket),
+        )
         
         // 直通播放
         .route(
@@ -100,11 +168,35 @@ pub fn create_router(
             get(super::streaming::stream_recording_file),
         )
         
+        // 单帧与缩略图（无需建立流会话）
+        .route(
+            "/api/v1/recordings/:file_id/frames",
+            get(super::streaming::get_recording_frame),
+        )
+        .route(
+            "/api/v1/recordings/:file_id/thumbnails",
+            get(super::streaming::get_recording_thumbnails),
+        )
+
+        // HLS（MPEG-TS 分片，供不支持 fMP4 的客户端）
+        .route(
+            "/api/v1/recordings/:file_id/playlist.m3u8",
+            get(super::streaming::get_recording_playlist),
+        )
+        .route(
+            "/api/v1/recordings/:file_id/segment.ts",
+            get(super::streaming::get_recording_ts_segment),
+        )
+        
         // 健康检查
         .route("/health", get(super::handlers::health_check))
         
+        // Prometheus 指标
+        .route("/metrics", get(super::handlers::prometheus_metrics))
+        
         // 嵌套延迟监控路由
         .nest("/api/v1/latency", latency_routes)
+        .nest("/api/v1/alerting", alerting_routes)
         
         // 添加主状态
         .with_state((
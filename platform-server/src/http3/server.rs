@@ -1,3 +1,4 @@
+use crate::alerting::AlertEngine;
 use crate::device::DeviceManager;
 use crate::distribution::DistributionManager;
 use crate::latency::LatencyMonitor;
@@ -16,6 +17,7 @@ pub struct Http3Server {
     distribution_manager: DistributionManager,
     latency_monitor: LatencyMonitor,
     stream_handler: Arc<UnifiedStreamHandler>,
+    alert_engine: Arc<AlertEngine>,
 }
 
 impl Http3Server {
@@ -25,6 +27,8 @@ impl Http3Server {
         recording_manager: RecordingManager,
         distribution_manager: DistributionManager,
         latency_monitor: LatencyMonitor,
+        stream_handler: Arc<UnifiedStreamHandler>,
+        alert_engine: Arc<AlertEngine>,
     ) -> Self {
         Self {
             addr,
@@ -32,7 +36,8 @@ impl Http3Server {
             recording_manager,
             distribution_manager,
             latency_monitor,
-            stream_handler: Arc::new(UnifiedStreamHandler::new()),
+            stream_handler,
+            alert_engine,
         }
     }
     
@@ -50,6 +55,7 @@ impl Http3Server {
             self.distribution_manager.clone(),
             self.latency_monitor.clone(),
             self.stream_handler.clone(),
+            self.alert_engine.clone(),
         );
 
         let listener = tokio::net::TcpListener::bind(self.addr).await?;
@@ -31,12 +31,21 @@ use crate::streaming::{UnifiedStreamHandler, VideoSegment};
 pub struct SseSegmentData {
     /// 分片ID
     pub segment_id: String,
-    /// 时间戳（秒）
-    pub timestamp: f64,
-    /// 时长（秒）
-    pub duration: f64,
+    /// 显示时间戳（`timescale` 刻度）
+    pub pts: i64,
+    /// 解码时间戳（`timescale` 刻度）
+    pub dts: i64,
+    /// 时间戳和时长的时间基
+    pub timescale: u32,
+    /// 时长（`timescale` 刻度）
+    pub duration: u32,
     /// 是否为关键帧
     pub is_keyframe: bool,
+    /// 是否为精确定位的预解码帧（只解码不显示）
+    pub decode_only: bool,
+    /// 时间线不连续点（类似 `#EXT-X-DISCONTINUITY`），客户端应重置解码时间线
+    #[serde(default)]
+    pub discontinuity: bool,
     /// 分片格式
     pub format: String,
     /// Base64编码的数据
@@ -49,9 +58,13 @@ impl From<VideoSegment> for SseSegmentData {
         
         Self {
             segment_id: segment.segment_id.to_string(),
-            timestamp: segment.timestamp,
+            pts: segment.pts,
+            dts: segment.dts,
+            timescale: VideoSegment::TIMESCALE,
             duration: segment.duration,
             is_keyframe: segment.is_keyframe,
+            decode_only: segment.decode_only,
+            discontinuity: segment.discontinuity,
             format: format!("{:?}", segment.format),
             data: general_purpose::STANDARD.encode(&segment.data),
         }
@@ -166,29 +179,36 @@ fn create_sse_stream(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::streaming::source::{SegmentFormat, SegmentSourceType, StreamConfig};
+    use crate::streaming::source::{SegmentFormat, SegmentSourceType};
+    use crate::streaming::StreamConfig;
     use crate::streaming::handler::tests::TestSource;
 
     #[tokio::test]
     async fn test_sse_segment_data_conversion() {
         let segment = VideoSegment {
             segment_id: Uuid::new_v4(),
-            timestamp: 1.5,
-            duration: 0.033,
+            pts: 135_000,
+            dts: 132_000,
+            duration: 3_000,
             data: vec![1, 2, 3, 4, 5],
             is_keyframe: true,
+            decode_only: false,
+            discontinuity: false,
             format: SegmentFormat::H264Raw,
             source_type: SegmentSourceType::Live,
             receive_time: None,
             forward_time: None,
+            device_send_time: None,
         };
 
         let sse_data = SseSegmentData::from(segment.clone());
 
         assert_eq!(sse_data.segment_id, segment.segment_id.to_string());
-        assert_eq!(sse_data.timestamp, 1.5);
-        assert_eq!(sse_data.duration, 0.033);
+        assert_eq!((sse_data.pts, sse_data.dts), (135_000, 132_000));
+        assert_eq!(sse_data.timescale, 90_000);
+        assert_eq!(sse_data.duration, 3_000);
         assert_eq!(sse_data.is_keyframe, true);
+        assert!(!sse_data.discontinuity);
         assert!(!sse_data.data.is_empty());
     }
 
@@ -207,7 +227,7 @@ mod tests {
         let receiver = handler.subscribe(session_id).await.unwrap();
 
         // 创建SSE流
-        let mut stream = create_sse_stream(receiver, session_id);
+        let mut stream = Box::pin(create_sse_stream(receiver, session_id));
 
         // 接收一些事件
         for i in 0..3 {
@@ -1,12 +1,28 @@
+use crate::streaming::source::SegmentSourceType;
+use crate::streaming::{FMP4Converter, FMP4ConverterConfig, SegmentFormat, VideoSegment};
 use axum::{
     body::Body,
-    extract::{Path, State},
+    extract::{Path, Query},
     http::{header, HeaderMap, StatusCode},
-    response::{IntoResponse, Response},
+    response::Response,
 };
+use common::demux::{ElementaryStream, ParameterSets};
+use common::h264::{self, AccessUnit};
+use common::ts::TsMuxer;
+use common::VideoCodec;
+use serde::Deserialize;
+use std::path::PathBuf;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
 use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+/// 录像没有时间信息，按默认帧率推算帧时间
+const DEFAULT_FRAME_RATE: f64 = 30.0;
+/// 缩略图默认数量
+const DEFAULT_THUMBNAILS: usize = 10;
+/// 缩略图最多数量
+const MAX_THUMBNAILS: usize = 50;
 
 /// 流式传输录像文件（支持 HTTP Range 请求）
 pub async fn stream_recording_file(
@@ -15,6 +31,32 @@ pub async fn stream_recording_file(
 ) -> Result<Response, StatusCode> {
     tracing::info!("📹 Stream request for file_id: {}", file_id);
     
+    let file_path = find_recording_file(&file_id)?;
+    
+    tracing::info!("Found file at: {:?}", file_path);
+
+    // 获取文件元数据
+    let metadata = tokio::fs::metadata(&file_path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let file_size = metadata.len();
+
+    // 检查是否有 Range 请求
+    if let Some(range_header) = headers.get(header::RANGE) {
+        // 解析 Range 头
+        if let Ok(range_str) = range_header.to_str() {
+            if let Some(range) = parse_range(range_str, file_size) {
+                return serve_range(&file_path, range, file_size).await;
+            }
+        }
+    }
+
+    // 没有 Range 请求，返回完整文件
+    serve_full_file(&file_path, file_size).await
+}
+
+/// 按 file_id 查找录像文件
+fn find_recording_file(file_id: &str) -> Result<PathBuf, StatusCode> {
     // 从 file_id 中提取文件名（格式: device_001_filename）
     // 分割成最多3部分：device, 001, filename
     let parts: Vec<&str> = file_id.splitn(3, '_').collect();
@@ -32,40 +74,373 @@ pub async fn stream_recording_file(
     // 简化实现：直接从 test-videos 目录读取
     // 尝试多个可能的路径
     let possible_paths = vec![
-        std::path::PathBuf::from("device-simulator/test-videos").join(file_name),
-        std::path::PathBuf::from("../device-simulator/test-videos").join(file_name),
-        std::path::PathBuf::from("./test-videos").join(file_name),
+        PathBuf::from("device-simulator/test-videos").join(file_name),
+        PathBuf::from("../device-simulator/test-videos").join(file_name),
+        PathBuf::from("./test-videos").join(file_name),
     ];
     
-    let file_path = possible_paths
+    possible_paths
         .iter()
         .find(|p| p.exists())
+        .cloned()
         .ok_or_else(|| {
             tracing::error!("File not found in any path: {}", file_name);
             tracing::error!("Tried paths: {:?}", possible_paths);
             StatusCode::NOT_FOUND
-        })?;
-    
-    tracing::info!("Found file at: {:?}", file_path);
+        })
+}
 
-    // 获取文件元数据
-    let metadata = tokio::fs::metadata(&file_path)
+/// 单帧/缩略图输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameFormat {
+    /// Annex B 裸流（与录像编码相同）
+    Raw,
+    /// 带初始化分片的小型 fMP4，浏览器可直接解码
+    FMP4,
+}
+
+impl FrameFormat {
+    fn parse(format: Option<&str>) -> Result<Self, StatusCode> {
+        match format {
+            None | Some("raw") | Some("h264") | Some("h265") => Ok(Self::Raw),
+            Some("fmp4") | Some("mp4") => Ok(Self::FMP4),
+            Some(other) => {
+                tracing::warn!("Unsupported frame format: {}", other);
+                Err(StatusCode::BAD_REQUEST)
+            }
+        }
+    }
+
+    fn content_type(self, codec: VideoCodec) -> &'static str {
+        match (self, codec) {
+            (Self::Raw, VideoCodec::H264) => "video/h264",
+            (Self::Raw, VideoCodec::H265) => "video/h265",
+            (Self::FMP4, _) => "video/mp4",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FrameQuery {
+    /// 目标时间（秒），返回显示时间离它最近的帧
+    at: f64,
+    /// 在最近帧基础上前后移动的帧数（上一帧/下一帧按钮）
+    #[serde(default)]
+    step: i64,
+    /// 输出格式：raw（默认，也接受 h264/h265）或 fmp4
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ThumbnailQuery {
+    /// 缩略图数量（默认10，最多50）
+    count: Option<usize>,
+    /// 输出格式：raw（默认，也接受 h264/h265）或 fmp4
+    format: Option<String>,
+}
+
+/// 读取录像并解析访问单元（MP4 解复用为 Annex B 基本流）
+async fn load_stream(file_path: &std::path::Path) -> Result<ElementaryStream, StatusCode> {
+    let data = tokio::fs::read(file_path)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let file_size = metadata.len();
+    let stream = ElementaryStream::parse(data, DEFAULT_FRAME_RATE);
+    if stream.units.is_empty() {
+        tracing::warn!("No video frames in {:?}", file_path);
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    Ok(stream)
+}
 
-    // 检查是否有 Range 请求
-    if let Some(range_header) = headers.get(header::RANGE) {
-        // 解析 Range 头
-        if let Ok(range_str) = range_header.to_str() {
-            if let Some(range) = parse_range(range_str, file_size) {
-                return serve_range(&file_path, range, file_size).await;
-            }
+/// 取帧所属 GOP 的参数集（关键帧中没有时取文件中第一组）
+fn gop_parameter_sets(stream: &ElementaryStream, index: usize) -> Option<ParameterSets> {
+    stream.units[..=index]
+        .iter()
+        .rev()
+        .find(|unit| unit.is_keyframe)
+        .and_then(|keyframe| ParameterSets::find(stream.codec, stream.unit_data(keyframe)))
+        .or_else(|| stream.parameter_sets())
+}
+
+/// 单个访问单元，缺少参数集时在前面补上所属 GOP 的参数集
+fn frame_with_parameter_sets(stream: &ElementaryStream, index: usize) -> Vec<u8> {
+    let frame = stream.unit_data(&stream.units[index]);
+    let mut output = Vec::with_capacity(frame.len() + 64);
+    if ParameterSets::find(stream.codec, frame).is_none() {
+        if let Some(parameter_sets) = gop_parameter_sets(stream, index) {
+            output.extend(parameter_sets.to_annex_b());
         }
     }
+    output.extend_from_slice(frame);
+    output
+}
 
-    // 没有 Range 请求，返回完整文件
-    serve_full_file(&file_path, file_size).await
+/// 把若干访问单元封装为 fMP4（初始化分片 + 每帧一个媒体分片）
+///
+/// `presentation_start` 为第一个显示帧的时间，之前的帧只解码不显示。
+fn frames_to_fmp4(
+    stream: &ElementaryStream,
+    indices: &[usize],
+    presentation_start: Option<f64>,
+) -> Result<Vec<u8>, StatusCode> {
+    let first = *indices.first().ok_or(StatusCode::NOT_FOUND)?;
+    let parameter_sets = gop_parameter_sets(stream, first).ok_or_else(|| {
+        tracing::warn!("No parameter sets found for frame {}", first);
+        StatusCode::UNPROCESSABLE_ENTITY
+    })?;
+    let mut config = FMP4ConverterConfig {
+        frame_rate: DEFAULT_FRAME_RATE,
+        ..FMP4ConverterConfig::default()
+    };
+    if let Some((width, height)) = parameter_sets.resolution() {
+        config.width = width as u16;
+        config.height = height as u16;
+    }
+
+    let mut converter = FMP4Converter::new(config);
+    converter.set_parameter_sets(parameter_sets);
+    converter.set_presentation_offset(presentation_start);
+    let mut output = converter
+        .generate_init_segment()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for &index in indices {
+        let unit = &stream.units[index];
+        let segment = VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: VideoSegment::ticks(unit.timestamp),
+            dts: VideoSegment::ticks(unit.decode_timestamp),
+            duration: VideoSegment::ticks(unit.duration) as u32,
+            data: stream.unit_data(unit).to_vec(),
+            is_keyframe: unit.is_keyframe,
+            decode_only: presentation_start.is_some_and(|start| unit.timestamp < start),
+            discontinuity: false,
+            format: SegmentFormat::raw_video(stream.codec),
+            source_type: SegmentSourceType::Playback,
+            receive_time: None,
+            forward_time: None,
+            device_send_time: None,
+        };
+        let fragment = converter
+            .convert_segment(segment)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        output.extend_from_slice(&fragment.data);
+    }
+    Ok(output)
+}
+
+/// 获取单帧
+///
+/// GET /api/v1/recordings/{file_id}/frames?at=12.5&step=1&format=fmp4
+///
+/// 返回显示时间离 `at` 最近的帧（`step` 前后移动）。raw 格式返回带参数集的单个访问单元；
+/// fmp4 格式从所属 GOP 的关键帧开始封装，用编辑列表跳过之前的帧，浏览器解码后即为目标帧。
+pub async fn get_recording_frame(
+    Path(file_id): Path<String>,
+    Query(query): Query<FrameQuery>,
+) -> Result<Response, StatusCode> {
+    let format = FrameFormat::parse(query.format.as_deref())?;
+    if !query.at.is_finite() || query.at < 0.0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let file_path = find_recording_file(&file_id)?;
+    let stream = load_stream(&file_path).await?;
+    let units = &stream.units;
+
+    let nearest = h264::nearest_access_unit(units, query.at).ok_or(StatusCode::NOT_FOUND)?;
+    let index = (nearest as i64)
+        .saturating_add(query.step)
+        .clamp(0, units.len() as i64 - 1) as usize;
+    let unit = units[index];
+    tracing::debug!(
+        "Frame request for {} at {:.3}s (step {}): frame #{} at {:.3}s",
+        file_id,
+        query.at,
+        query.step,
+        index,
+        unit.timestamp
+    );
+
+    let body = match format {
+        FrameFormat::Raw => frame_with_parameter_sets(&stream, index),
+        FrameFormat::FMP4 => {
+            let plan = h264::plan_accurate_seek(units, unit.timestamp).ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+            let indices: Vec<usize> = (plan.keyframe..=plan.target).collect();
+            frames_to_fmp4(&stream, &indices, Some(unit.timestamp))?
+        }
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, format.content_type(stream.codec))
+        .header(header::CONTENT_LENGTH, body.len())
+        .header("X-Frame-Index", index)
+        .header("X-Frame-Count", units.len())
+        .header("X-Frame-Timestamp", format!("{:.6}", unit.timestamp))
+        .header("X-Keyframe", unit.is_keyframe.to_string())
+        .body(Body::from(body))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// 获取缩略图条
+///
+/// GET /api/v1/recordings/{file_id}/thumbnails?count=10&format=fmp4
+///
+/// 把录像时长均分为 `count` 段，取每段起点之前最近的关键帧（相邻位置落在同一关键帧时只返回一次）。
+/// 关键帧的显示时间在 `X-Frame-Timestamps` 响应头中按顺序列出。
+pub async fn get_recording_thumbnails(
+    Path(file_id): Path<String>,
+    Query(query): Query<ThumbnailQuery>,
+) -> Result<Response, StatusCode> {
+    let format = FrameFormat::parse(query.format.as_deref())?;
+    let count = query.count.unwrap_or(DEFAULT_THUMBNAILS).clamp(1, MAX_THUMBNAILS);
+    let file_path = find_recording_file(&file_id)?;
+    let stream = load_stream(&file_path).await?;
+    let units = &stream.units;
+
+    let keyframes: Vec<usize> = (0..units.len()).filter(|&i| units[i].is_keyframe).collect();
+    if keyframes.is_empty() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    let duration = stream.duration();
+    let mut indices: Vec<usize> = (0..count)
+        .map(|i| {
+            let position = duration * i as f64 / count as f64;
+            let k = keyframes.partition_point(|&k| units[k].timestamp <= position);
+            keyframes[k.saturating_sub(1)]
+        })
+        .collect();
+    indices.dedup();
+
+    let timestamps: Vec<String> = indices
+        .iter()
+        .map(|&i| format!("{:.6}", units[i].timestamp))
+        .collect();
+    tracing::debug!("Thumbnail strip for {}: {} keyframes", file_id, indices.len());
+
+    let body = match format {
+        FrameFormat::Raw => indices
+            .iter()
+            .flat_map(|&i| frame_with_parameter_sets(&stream, i))
+            .collect(),
+        FrameFormat::FMP4 => frames_to_fmp4(&stream, &indices, None)?,
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, format.content_type(stream.codec))
+        .header(header::CONTENT_LENGTH, body.len())
+        .header("X-Frame-Timestamps", timestamps.join(","))
+        .body(Body::from(body))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// HLS 分片的目标时长（秒），分片在达到目标时长后的第一个关键帧处切分
+const HLS_TARGET_DURATION: f64 = 6.0;
+
+#[derive(Deserialize)]
+pub struct TsSegmentQuery {
+    /// 第一个访问单元的下标（应为关键帧）
+    from: usize,
+    /// 结束访问单元的下标（不含）
+    to: usize,
+}
+
+/// 获取 HLS 播放列表（MPEG-TS 分片）
+///
+/// GET /api/v1/recordings/{file_id}/playlist.m3u8
+///
+/// 供不支持 fMP4 的 HLS 客户端使用。分片按关键帧切分，分片地址为相对路径的 `segment.ts`。
+pub async fn get_recording_playlist(Path(file_id): Path<String>) -> Result<Response, StatusCode> {
+    let file_path = find_recording_file(&file_id)?;
+    let stream = load_stream(&file_path).await?;
+    let playlist = hls_playlist(&stream.units).ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+    tracing::debug!("HLS playlist for {}: {} frames", file_id, stream.units.len());
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/vnd.apple.mpegurl")
+        .body(Body::from(playlist))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// 获取 MPEG-TS 分片
+///
+/// GET /api/v1/recordings/{file_id}/segment.ts?from=0&to=180
+pub async fn get_recording_ts_segment(
+    Path(file_id): Path<String>,
+    Query(query): Query<TsSegmentQuery>,
+) -> Result<Response, StatusCode> {
+    let file_path = find_recording_file(&file_id)?;
+    let stream = load_stream(&file_path).await?;
+    if query.from >= query.to || query.to > stream.units.len() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let body = frames_to_ts(&stream, query.from..query.to);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "video/mp2t")
+        .header(header::CONTENT_LENGTH, body.len())
+        .body(Body::from(body))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// 按关键帧切分 HLS 分片，返回 (起始下标, 结束下标, 时长)
+fn hls_segments(units: &[AccessUnit]) -> Vec<(usize, usize, f64)> {
+    // 按解码时间差计算时长，避免逐帧累加的浮点误差
+    let span = |from: usize, to: usize| {
+        let last = &units[to - 1];
+        last.decode_timestamp + last.duration - units[from].decode_timestamp
+    };
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (i, unit) in units.iter().enumerate() {
+        if unit.is_keyframe && i > start && unit.decode_timestamp - units[start].decode_timestamp >= HLS_TARGET_DURATION {
+            segments.push((start, i, span(start, i)));
+            start = i;
+        }
+    }
+    if start < units.len() {
+        segments.push((start, units.len(), span(start, units.len())));
+    }
+    segments
+}
+
+/// 生成点播 HLS 播放列表，第一帧不是关键帧时返回 None
+fn hls_playlist(units: &[AccessUnit]) -> Option<String> {
+    if !units.first()?.is_keyframe {
+        return None;
+    }
+    let segments = hls_segments(units);
+    let target = segments.iter().map(|&(_, _, duration)| duration).fold(0.0, f64::max).ceil() as u64;
+
+    let mut playlist = format!(
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n",
+        target.max(1)
+    );
+    for (from, to, duration) in segments {
+        playlist.push_str(&format!("#EXTINF:{:.3},\nsegment.ts?from={}&to={}\n", duration, from, to));
+    }
+    playlist.push_str("#EXT-X-ENDLIST\n");
+    Some(playlist)
+}
+
+/// 把一段访问单元封装为 MPEG-TS，时间戳沿用录像时间轴，相邻分片可以无缝衔接
+fn frames_to_ts(stream: &ElementaryStream, range: std::ops::Range<usize>) -> Vec<u8> {
+    let mut muxer = TsMuxer::new(stream.codec, false);
+    let mut output = Vec::new();
+    muxer.write_tables(&mut output);
+    for index in range {
+        let unit = &stream.units[index];
+        let frame = if unit.is_keyframe {
+            frame_with_parameter_sets(stream, index)
+        } else {
+            stream.unit_data(unit).to_vec()
+        };
+        muxer.write_video(&frame, unit.timestamp, unit.decode_timestamp, unit.is_keyframe, &mut output);
+    }
+    output
 }
 
 /// 解析 Range 头（格式: bytes=start-end）
@@ -100,6 +475,8 @@ fn get_content_type(file_path: &std::path::Path) -> &'static str {
     match file_path.extension().and_then(|s| s.to_str()) {
         Some("mp4") => "video/mp4",
         Some("h264") | Some("264") => "video/h264",
+        Some("h265") | Some("265") | Some("hevc") => "video/h265",
+        Some("ts") | Some("m2ts") | Some("mts") => "video/mp2t",
         Some("webm") => "video/webm",
         _ => "application/octet-stream",
     }
@@ -170,3 +547,101 @@ async fn serve_full_file(
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 两个 GOP，每个 GOP 15 帧，只有关键帧前带参数集
+    fn h264_stream() -> Vec<u8> {
+        let mut data = Vec::new();
+        for gop in 0..2u8 {
+            data.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, gop, 0xaa]);
+            for frame in 1..15u8 {
+                data.extend_from_slice(&[0, 0, 0, 1, 0x41, 0x9a, frame]);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_frame_format_parse() {
+        assert_eq!(FrameFormat::parse(None), Ok(FrameFormat::Raw));
+        assert_eq!(FrameFormat::parse(Some("h265")), Ok(FrameFormat::Raw));
+        assert_eq!(FrameFormat::parse(Some("fmp4")), Ok(FrameFormat::FMP4));
+        assert_eq!(FrameFormat::Raw.content_type(VideoCodec::H265), "video/h265");
+        assert_eq!(FrameFormat::parse(Some("gif")), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_hls_playlist() {
+        // 10 秒、每 2 秒一个关键帧：6 秒后的第一个关键帧处切分
+        let units: Vec<AccessUnit> = (0..300)
+            .map(|i| AccessUnit {
+                timestamp: i as f64 / 30.0,
+                decode_timestamp: i as f64 / 30.0,
+                duration: 1.0 / 30.0,
+                offset: i as u64,
+                size: 1,
+                is_keyframe: i % 60 == 0,
+            })
+            .collect();
+        let segments: Vec<(usize, usize)> = hls_segments(&units).iter().map(|&(from, to, _)| (from, to)).collect();
+        assert_eq!(segments, [(0, 180), (180, 300)]);
+
+        let playlist = hls_playlist(&units).unwrap();
+        assert!(playlist.starts_with("#EXTM3U\n"));
+        assert!(playlist.contains("#EXT-X-TARGETDURATION:6\n"));
+        assert!(playlist.contains("#EXTINF:6.000,\nsegment.ts?from=0&to=180\n"));
+        assert!(playlist.contains("#EXTINF:4.000,\nsegment.ts?from=180&to=300\n"));
+        assert!(playlist.ends_with("#EXT-X-ENDLIST\n"));
+        assert!(hls_playlist(&units[1..]).is_none());
+    }
+
+    #[test]
+    fn test_frames_to_ts() {
+        let stream = ElementaryStream::parse(h264_stream(), DEFAULT_FRAME_RATE);
+        let ts = frames_to_ts(&stream, 15..20);
+
+        // 解复用后与原访问单元一致，时间沿用录像时间轴
+        let demuxed = ElementaryStream::parse(ts, DEFAULT_FRAME_RATE);
+        assert_eq!(demuxed.container, common::demux::Container::MpegTs);
+        assert_eq!(demuxed.units.len(), 5);
+        assert!(demuxed.units[0].is_keyframe);
+        assert_eq!(demuxed.unit_data(&demuxed.units[1]), stream.unit_data(&stream.units[16]));
+        assert!((demuxed.units[0].duration - 1.0 / 30.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_frame_with_parameter_sets() {
+        let stream = ElementaryStream::parse(h264_stream(), DEFAULT_FRAME_RATE);
+
+        // 非关键帧补上所属 GOP 的 SPS/PPS
+        let frame = frame_with_parameter_sets(&stream, 20);
+        let nals = h264::nal_units(&frame);
+        let types: Vec<u8> = nals.iter().map(|nal| h264::nal_type(nal)).collect();
+        assert_eq!(types, vec![h264::NAL_SPS, h264::NAL_PPS, 1]);
+        assert_eq!(nals[2], &[0x41, 0x9a, 5]);
+
+        // 关键帧本身带参数集，原样返回
+        let keyframe = frame_with_parameter_sets(&stream, 15);
+        assert_eq!(keyframe.as_slice(), stream.unit_data(&stream.units[15]));
+    }
+
+    #[test]
+    fn test_frames_to_fmp4() {
+        let stream = ElementaryStream::parse(h264_stream(), DEFAULT_FRAME_RATE);
+
+        // 从关键帧开始封装到目标帧，编辑列表指向目标帧
+        let indices: Vec<usize> = (15..=18).collect();
+        let fmp4 = frames_to_fmp4(&stream, &indices, Some(stream.units[18].timestamp)).unwrap();
+        assert_eq!(&fmp4[4..8], b"ftyp");
+        assert_eq!(fmp4.windows(4).filter(|w| *w == b"moof").count(), 4);
+        assert!(fmp4.windows(4).any(|w| w == b"elst"));
+
+        let thumbnails = frames_to_fmp4(&stream, &[0, 15], None).unwrap();
+        assert!(!thumbnails.windows(4).any(|w| w == b"elst"));
+    }
+}
@@ -1,8 +1,10 @@
 // 延迟告警广播器
 //
 // 本模块实现了延迟告警的广播功能，通过WebSocket将告警推送到前端。
+// 告警来自规则引擎，只推送状态切换，不再逐个分片推送。
 
-use crate::latency::{LatencyAlertType, LatencyStatistics};
+use crate::alerting::AlertEvent;
+use crate::latency::LatencyStatistics;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -14,11 +16,11 @@ use uuid::Uuid;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum AlertMessage {
-    /// 延迟告警
-    LatencyAlert {
-        session_id: Uuid,
-        alert: LatencyAlertType,
-        timestamp: u64,
+    /// 告警状态切换（待触发 → 触发 → 恢复）
+    AlertStateChanged {
+        /// 会话告警的会话ID（设备告警为空）
+        session_id: Option<Uuid>,
+        event: AlertEvent,
     },
     /// 统计更新
     StatisticsUpdate {
@@ -32,6 +34,18 @@ pub enum AlertMessage {
     SessionEnded { session_id: Uuid, timestamp: u64 },
 }
 
+impl AlertMessage {
+    /// 消息所属会话
+    pub fn session_id(&self) -> Option<Uuid> {
+        match self {
+            AlertMessage::AlertStateChanged { session_id, .. } => *session_id,
+            AlertMessage::StatisticsUpdate { session_id, .. }
+            | AlertMessage::SessionStarted { session_id, .. }
+            | AlertMessage::SessionEnded { session_id, .. } => Some(*session_id),
+        }
+    }
+}
+
 /// 告警广播器
 ///
 /// 管理告警消息的广播，支持多个客户端订阅。
@@ -94,26 +108,19 @@ impl AlertBroadcaster {
         }
     }
 
-    /// 广播延迟告警
-    pub fn broadcast_latency_alert(&self, session_id: Uuid, alert: LatencyAlertType) {
-        let message = AlertMessage::LatencyAlert {
-            session_id,
-            alert,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+    /// 广播告警状态切换
+    pub fn broadcast_alert_event(&self, event: AlertEvent) {
+        let message = AlertMessage::AlertStateChanged {
+            session_id: event.alert.session_id(),
+            event,
         };
 
         match self.tx.send(message) {
             Ok(count) => {
-                debug!(
-                    "Broadcasted latency alert for session {} to {} subscribers",
-                    session_id, count
-                );
+                debug!("Broadcasted alert state change to {} subscribers", count);
             }
             Err(e) => {
-                warn!("Failed to broadcast latency alert: {}", e);
+                warn!("Failed to broadcast alert state change: {}", e);
             }
         }
     }
@@ -223,17 +230,9 @@ impl AlertFilter {
 
     /// 检查消息是否通过过滤器
     pub fn matches(&self, message: &AlertMessage) -> bool {
-        if let Some(filter_session_id) = self.session_id {
-            match message {
-                AlertMessage::LatencyAlert { session_id, .. }
-                | AlertMessage::StatisticsUpdate { session_id, .. }
-                | AlertMessage::SessionStarted { session_id, .. }
-                | AlertMessage::SessionEnded { session_id, .. } => {
-                    *session_id == filter_session_id
-                }
-            }
-        } else {
-            true
+        match self.session_id {
+            Some(filter_session_id) => message.session_id() == Some(filter_session_id),
+            None => true,
         }
     }
 }
@@ -247,7 +246,6 @@ impl Default for AlertFilter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::latency::LatencyAlertType;
 
     #[tokio::test]
     async fn test_broadcaster_creation() {
@@ -263,43 +261,39 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_broadcast_latency_alert() {
+    async fn test_broadcast_alert_event() {
         let broadcaster = AlertBroadcaster::with_defaults();
         let mut rx = broadcaster.subscribe();
 
         let session_id = Uuid::new_v4();
-        let segment_id = Uuid::new_v4();
-        let alert = LatencyAlertType::TransmissionLatency {
-            segment_id,
-            latency_ms: 150,
-            threshold_ms: 100,
-        };
-
-        broadcaster.broadcast_latency_alert(session_id, alert.clone());
+        let event: AlertEvent = serde_json::from_value(serde_json::json!({
+            "status": "firing",
+            "previous": "pending",
+            "timestamp": 0,
+            "id": Uuid::new_v4(),
+            "rule_id": "session_p95_latency",
+            "rule_name": "Session P95 latency high",
+            "severity": "warning",
+            "subject": format!("session:{}", session_id),
+            "metric": "session_p95_latency_ms",
+            "value": 300.0,
+            "threshold": 200.0,
+            "started_at": 0,
+            "acknowledged": false,
+            "silenced": false,
+        }))
+        .unwrap();
+
+        broadcaster.broadcast_alert_event(event.clone());
 
         let message = rx.recv().await.unwrap();
+        assert_eq!(message.session_id(), Some(session_id));
         match message {
-            AlertMessage::LatencyAlert {
-                session_id: msg_session_id,
-                alert: msg_alert,
-                ..
-            } => {
-                assert_eq!(msg_session_id, session_id);
-                match (alert, msg_alert) {
-                    (
-                        LatencyAlertType::TransmissionLatency {
-                            segment_id: id1, ..
-                        },
-                        LatencyAlertType::TransmissionLatency {
-                            segment_id: id2, ..
-                        },
-                    ) => {
-                        assert_eq!(id1, id2);
-                    }
-                    _ => panic!("Alert type mismatch"),
-                }
+            AlertMessage::AlertStateChanged { event: received, .. } => {
+                assert_eq!(received.alert.id, event.alert.id);
+                assert_eq!(received.status, event.status);
             }
-            _ => panic!("Expected LatencyAlert message"),
+            _ => panic!("Expected AlertStateChanged message"),
         }
     }
 
@@ -324,6 +318,12 @@ mod tests {
             p99_latency_ms: 78,
             throughput_mbps: 5.2,
             packet_loss_rate: 0.01,
+            acked_segments: 0,
+            end_to_end_avg_ms: 0.0,
+            end_to_end_p95_ms: 0,
+            distribution_avg_ms: 0.0,
+            distribution_p95_ms: 0,
+            client: Default::default(),
         };
 
         broadcaster.broadcast_statistics_update(session_id, statistics.clone());
@@ -0,0 +1,218 @@
+// 设备时钟同步
+//
+// 通过控制流上的 NTP 式往返交换估算设备时钟与平台时钟的偏移，
+// 用于把分片中的设备时间戳换算为平台时间。
+//
+// ```
+// 平台 t0 ──ClockSync──▶ 设备 t1
+// 平台 t3 ◀──响应────── 设备 t2
+//
+// 往返时延 rtt    = (t3 - t0) - (t2 - t1)
+// 时钟偏移 offset = ((t1 - t0) + (t2 - t3)) / 2   （设备时钟 - 平台时钟）
+// ```
+//
+// 每台设备保留最近若干个样本，取往返时延最小的样本作为偏移估计，
+// 以降低网络排队对估计值的影响。
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// 每台设备保留的时钟样本数
+const CLOCK_SAMPLE_WINDOW: usize = 8;
+
+/// 单次时钟同步样本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSample {
+    /// 时钟偏移（设备时钟 - 平台时钟，微秒）
+    pub offset_us: i64,
+    /// 往返时延（微秒）
+    pub rtt_us: u64,
+}
+
+impl ClockSample {
+    /// 根据一次往返交换的四个时间戳计算样本
+    ///
+    /// t0/t3 为平台时钟，t1/t2 为设备时钟。时间戳不合理时返回 None。
+    pub fn from_exchange(t0_us: u64, t1_us: u64, t2_us: u64, t3_us: u64) -> Option<Self> {
+        if t3_us < t0_us || t2_us < t1_us {
+            return None;
+        }
+
+        let round_trip = (t3_us - t0_us) as i64;
+        let device_hold = (t2_us - t1_us) as i64;
+        let rtt_us = (round_trip - device_hold).max(0) as u64;
+        let offset_us = ((t1_us as i64 - t0_us as i64) + (t2_us as i64 - t3_us as i64)) / 2;
+
+        Some(Self { offset_us, rtt_us })
+    }
+}
+
+/// 设备时钟偏移估计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockOffsetEstimate {
+    /// 时钟偏移（设备时钟 - 平台时钟，微秒）
+    pub offset_us: i64,
+    /// 所选样本的往返时延（微秒）
+    pub rtt_us: u64,
+    /// 窗口内样本数
+    pub samples: usize,
+    /// 最近一次更新时间
+    pub updated_at: SystemTime,
+}
+
+#[derive(Debug)]
+struct DeviceClock {
+    /// 采样所用连接（quinn `stable_id`），设备重连后旧连接不能清除新连接的数据
+    connection_id: usize,
+    samples: VecDeque<ClockSample>,
+    updated_at: SystemTime,
+}
+
+/// 设备时钟同步管理器
+#[derive(Clone, Default)]
+pub struct ClockSyncManager {
+    devices: Arc<DashMap<String, DeviceClock>>,
+}
+
+impl ClockSyncManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次时钟同步样本
+    ///
+    /// 来自新连接的样本替换旧连接的数据（设备重连后时钟可能已变化）。
+    pub fn record_sample(&self, device_id: &str, connection_id: usize, sample: ClockSample) {
+        let mut clock = self
+            .devices
+            .entry(device_id.to_string())
+            .or_insert_with(|| DeviceClock {
+                connection_id,
+                samples: VecDeque::with_capacity(CLOCK_SAMPLE_WINDOW),
+                updated_at: SystemTime::now(),
+            });
+        if clock.connection_id != connection_id {
+            clock.connection_id = connection_id;
+            clock.samples.clear();
+        }
+
+        if clock.samples.len() >= CLOCK_SAMPLE_WINDOW {
+            clock.samples.pop_front();
+        }
+        clock.samples.push_back(sample);
+        clock.updated_at = SystemTime::now();
+
+        debug!(
+            "Clock sample for device {}: offset={}us, rtt={}us",
+            device_id, sample.offset_us, sample.rtt_us
+        );
+    }
+
+    /// 获取设备的时钟偏移估计（取往返时延最小的样本）
+    pub fn get_estimate(&self, device_id: &str) -> Option<ClockOffsetEstimate> {
+        let clock = self.devices.get(device_id)?;
+        let best = clock.samples.iter().min_by_key(|s| s.rtt_us)?;
+
+        Some(ClockOffsetEstimate {
+            offset_us: best.offset_us,
+            rtt_us: best.rtt_us,
+            samples: clock.samples.len(),
+            updated_at: clock.updated_at,
+        })
+    }
+
+    /// 将设备时间戳（Unix 微秒）换算为平台时间
+    ///
+    /// 设备尚未完成时钟同步时返回 None。
+    pub fn to_platform_time(&self, device_id: &str, device_time_us: u64) -> Option<SystemTime> {
+        let estimate = self.get_estimate(device_id)?;
+        let platform_us = device_time_us as i64 - estimate.offset_us;
+        if platform_us < 0 {
+            return None;
+        }
+        Some(UNIX_EPOCH + Duration::from_micros(platform_us as u64))
+    }
+
+    /// 移除设备在指定连接上的时钟数据（设备已用新连接重连时保留）
+    pub fn remove_device(&self, device_id: &str, connection_id: usize) {
+        self.devices
+            .remove_if(device_id, |_, clock| clock.connection_id == connection_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_from_exchange() {
+        // 设备时钟快 5ms，单程 2ms，设备处理 1ms
+        let t0 = 1_000_000;
+        let t1 = t0 + 2_000 + 5_000;
+        let t2 = t1 + 1_000;
+        let t3 = t0 + 2_000 + 1_000 + 2_000;
+
+        let sample = ClockSample::from_exchange(t0, t1, t2, t3).unwrap();
+        assert_eq!(sample.offset_us, 5_000);
+        assert_eq!(sample.rtt_us, 4_000);
+
+        // 非法时间戳
+        assert!(ClockSample::from_exchange(t3, t1, t2, t0).is_none());
+    }
+
+    #[test]
+    fn test_estimate_prefers_lowest_rtt() {
+        let manager = ClockSyncManager::new();
+        manager.record_sample("dev", 1, ClockSample { offset_us: 9_000, rtt_us: 20_000 });
+        manager.record_sample("dev", 1, ClockSample { offset_us: 5_000, rtt_us: 2_000 });
+        manager.record_sample("dev", 1, ClockSample { offset_us: 7_000, rtt_us: 10_000 });
+
+        let estimate = manager.get_estimate("dev").unwrap();
+        assert_eq!(estimate.offset_us, 5_000);
+        assert_eq!(estimate.samples, 3);
+        assert!(manager.get_estimate("other").is_none());
+    }
+
+    #[test]
+    fn test_sample_window_is_bounded() {
+        let manager = ClockSyncManager::new();
+        manager.record_sample("dev", 1, ClockSample { offset_us: 1, rtt_us: 1 });
+        for _ in 0..CLOCK_SAMPLE_WINDOW {
+            manager.record_sample("dev", 1, ClockSample { offset_us: 100, rtt_us: 50 });
+        }
+
+        // 最早的低 RTT 样本已被淘汰
+        let estimate = manager.get_estimate("dev").unwrap();
+        assert_eq!(estimate.samples, CLOCK_SAMPLE_WINDOW);
+        assert_eq!(estimate.offset_us, 100);
+    }
+
+    #[test]
+    fn test_to_platform_time() {
+        let manager = ClockSyncManager::new();
+        assert!(manager.to_platform_time("dev", 10_000_000).is_none());
+
+        manager.record_sample("dev", 1, ClockSample { offset_us: 3_000, rtt_us: 100 });
+        let platform = manager.to_platform_time("dev", 10_000_000).unwrap();
+        assert_eq!(platform, UNIX_EPOCH + Duration::from_micros(9_997_000));
+    }
+
+    #[test]
+    fn test_reconnect_keeps_new_connection_clock() {
+        let manager = ClockSyncManager::new();
+        manager.record_sample("dev", 1, ClockSample { offset_us: 3_000, rtt_us: 100 });
+        // 设备重连：新连接的样本替换旧数据
+        manager.record_sample("dev", 2, ClockSample { offset_us: 8_000, rtt_us: 400 });
+        assert_eq!(manager.get_estimate("dev").unwrap().samples, 1);
+
+        // 旧连接关闭不影响新连接
+        manager.remove_device("dev", 1);
+        assert_eq!(manager.get_estimate("dev").unwrap().offset_us, 8_000);
+        manager.remove_device("dev", 2);
+        assert!(manager.get_estimate("dev").is_none());
+    }
+}
@@ -15,7 +15,12 @@
 // - 分发延迟 = T4 - T3
 // - 端到端延迟 = T4 - T1
 // ```
+//
+// T1 由设备时钟给出，需先通过 `ClockSyncManager` 换算为平台时钟。
 
+use super::clock_sync::ClockSyncManager;
+use super::thresholds::ThresholdConfig;
+use crate::metrics::{LatencyStage, StageHistograms};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
@@ -25,7 +30,7 @@ use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 /// 延迟阈值配置
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LatencyThresholds {
     /// 传输延迟阈值（设备→平台）
     pub transmission_ms: u64,
@@ -78,32 +83,28 @@ pub enum LatencyAlertType {
 }
 
 /// 延迟告警管理器
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct LatencyAlertManager {
     alerts: Arc<DashMap<Uuid, Vec<LatencyAlertType>>>,
-    thresholds: LatencyThresholds,
 }
 
 impl LatencyAlertManager {
-    pub fn new(thresholds: LatencyThresholds) -> Self {
-        Self {
-            alerts: Arc::new(DashMap::new()),
-            thresholds,
-        }
+    pub fn new() -> Self {
+        Self::default()
     }
 
     /// 触发传输延迟告警
-    pub fn trigger_transmission_alert(&self, segment_id: Uuid, latency: Duration) {
+    pub fn trigger_transmission_alert(&self, segment_id: Uuid, latency: Duration, threshold_ms: u64) {
         let latency_ms = latency.as_millis() as u64;
         warn!(
             "Transmission latency alert: segment={}, latency={}ms, threshold={}ms",
-            segment_id, latency_ms, self.thresholds.transmission_ms
+            segment_id, latency_ms, threshold_ms
         );
 
         let alert = LatencyAlertType::TransmissionLatency {
             segment_id,
             latency_ms,
-            threshold_ms: self.thresholds.transmission_ms,
+            threshold_ms,
         };
 
         self.alerts
@@ -113,17 +114,17 @@ impl LatencyAlertManager {
     }
 
     /// 触发处理延迟告警
-    pub fn trigger_processing_alert(&self, segment_id: Uuid, latency: Duration) {
+    pub fn trigger_processing_alert(&self, segment_id: Uuid, latency: Duration, threshold_ms: u64) {
         let latency_ms = latency.as_millis() as u64;
         warn!(
             "Processing latency alert: segment={}, latency={}ms, threshold={}ms",
-            segment_id, latency_ms, self.thresholds.processing_ms
+            segment_id, latency_ms, threshold_ms
         );
 
         let alert = LatencyAlertType::ProcessingLatency {
             segment_id,
             latency_ms,
-            threshold_ms: self.thresholds.processing_ms,
+            threshold_ms,
         };
 
         self.alerts
@@ -133,17 +134,17 @@ impl LatencyAlertManager {
     }
 
     /// 触发分发延迟告警
-    pub fn trigger_distribution_alert(&self, segment_id: Uuid, latency: Duration) {
+    pub fn trigger_distribution_alert(&self, segment_id: Uuid, latency: Duration, threshold_ms: u64) {
         let latency_ms = latency.as_millis() as u64;
         warn!(
             "Distribution latency alert: segment={}, latency={}ms, threshold={}ms",
-            segment_id, latency_ms, self.thresholds.distribution_ms
+            segment_id, latency_ms, threshold_ms
         );
 
         let alert = LatencyAlertType::DistributionLatency {
             segment_id,
             latency_ms,
-            threshold_ms: self.thresholds.distribution_ms,
+            threshold_ms,
         };
 
         self.alerts
@@ -153,17 +154,17 @@ impl LatencyAlertManager {
     }
 
     /// 触发端到端延迟告警
-    pub fn trigger_end_to_end_alert(&self, segment_id: Uuid, latency: Duration) {
+    pub fn trigger_end_to_end_alert(&self, segment_id: Uuid, latency: Duration, threshold_ms: u64) {
         let latency_ms = latency.as_millis() as u64;
         warn!(
             "End-to-end latency alert: segment={}, latency={}ms, threshold={}ms",
-            segment_id, latency_ms, self.thresholds.end_to_end_ms
+            segment_id, latency_ms, threshold_ms
         );
 
         let alert = LatencyAlertType::EndToEndLatency {
             segment_id,
             latency_ms,
-            threshold_ms: self.thresholds.end_to_end_ms,
+            threshold_ms,
         };
 
         self.alerts
@@ -196,6 +197,44 @@ struct LatencyMeasurement {
     end_to_end_latency: Option<Duration>,
 }
 
+/// 设备传输统计（设备→平台）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeviceTransmissionStats {
+    /// 设备ID
+    pub device_id: String,
+    /// 样本数
+    pub sample_count: u64,
+    /// 最近一次传输延迟（毫秒）
+    pub last_latency_ms: f64,
+    /// 平均传输延迟（毫秒）
+    pub avg_latency_ms: f64,
+    /// 最小传输延迟（毫秒）
+    pub min_latency_ms: f64,
+    /// 最大传输延迟（毫秒）
+    pub max_latency_ms: f64,
+    /// 到达抖动（毫秒，RFC 3550 平滑估计）
+    pub jitter_ms: f64,
+    /// 平均设备端采集到发送耗时（毫秒）
+    pub avg_capture_to_send_ms: f64,
+    /// 乱序到达的分片数
+    pub out_of_order: u64,
+    /// 序号缺口累计（可能丢失的分片数）
+    pub sequence_gaps: u64,
+    /// 当前时钟偏移估计（设备时钟 - 平台时钟，微秒）
+    pub clock_offset_us: Option<i64>,
+    /// 时钟偏移估计对应的往返时延（微秒）
+    pub clock_rtt_us: Option<u64>,
+}
+
+/// 设备传输统计的内部状态
+#[derive(Debug, Default)]
+struct DeviceTransmissionState {
+    stats: DeviceTransmissionStats,
+    last_transit_us: Option<i64>,
+    last_sequence: Option<u64>,
+    capture_to_send_samples: u64,
+}
+
 /// 端到端延迟监控器
 ///
 /// 追踪视频分片从设备端到前端播放的完整延迟链路。
@@ -213,24 +252,67 @@ pub struct EndToEndLatencyMonitor {
     measurements: Arc<DashMap<Uuid, LatencyMeasurement>>,
     /// 延迟告警管理器
     latency_alerts: LatencyAlertManager,
-    /// 延迟阈值配置
-    thresholds: LatencyThresholds,
+    /// 延迟阈值配置（可热更新）
+    thresholds: ThresholdConfig,
+    /// 分片 → 所属会话，用于查找会话/设备阈值覆盖
+    segment_sessions: Arc<DashMap<Uuid, Uuid>>,
+    /// 设备时钟同步
+    clock_sync: ClockSyncManager,
+    /// 每台设备的传输统计
+    device_transmission: Arc<DashMap<String, DeviceTransmissionState>>,
+    /// 各阶段延迟直方图（用于指标导出）
+    histograms: Arc<StageHistograms>,
 }
 
 impl EndToEndLatencyMonitor {
     /// 创建新的端到端延迟监控器
     pub fn new(thresholds: LatencyThresholds) -> Self {
+        Self::with_threshold_config(ThresholdConfig::new(thresholds))
+    }
+
+    /// 使用共享的阈值配置创建监控器
+    pub fn with_threshold_config(thresholds: ThresholdConfig) -> Self {
         Self {
             device_timestamps: Arc::new(DashMap::new()),
             platform_receive_timestamps: Arc::new(DashMap::new()),
             platform_forward_timestamps: Arc::new(DashMap::new()),
             client_play_timestamps: Arc::new(DashMap::new()),
             measurements: Arc::new(DashMap::new()),
-            latency_alerts: LatencyAlertManager::new(thresholds.clone()),
+            latency_alerts: LatencyAlertManager::new(),
             thresholds,
+            segment_sessions: Arc::new(DashMap::new()),
+            clock_sync: ClockSyncManager::new(),
+            device_transmission: Arc::new(DashMap::new()),
+            histograms: Arc::new(StageHistograms::new()),
         }
     }
 
+    /// 获取各阶段延迟直方图
+    pub fn get_histograms(&self) -> Arc<StageHistograms> {
+        Arc::clone(&self.histograms)
+    }
+
+    /// 获取阈值配置
+    pub fn get_threshold_config(&self) -> ThresholdConfig {
+        self.thresholds.clone()
+    }
+
+    /// 记录分片所属会话，使会话与设备的阈值覆盖对该分片生效
+    pub fn assign_segment(&self, segment_id: Uuid, session_id: Uuid) {
+        self.segment_sessions.insert(segment_id, session_id);
+    }
+
+    /// 分片的生效阈值
+    fn thresholds_for(&self, segment_id: &Uuid) -> LatencyThresholds {
+        let session_id = self.segment_sessions.get(segment_id).map(|entry| *entry.value());
+        self.thresholds.effective(session_id.as_ref(), None)
+    }
+
+    /// 获取设备时钟同步管理器
+    pub fn get_clock_sync(&self) -> ClockSyncManager {
+        self.clock_sync.clone()
+    }
+
     /// 使用默认阈值创建监控器
     pub fn with_defaults() -> Self {
         Self::new(LatencyThresholds::default())
@@ -256,6 +338,8 @@ impl EndToEndLatencyMonitor {
                     segment_id,
                     transmission_latency.as_millis()
                 );
+                self.histograms
+                    .observe(LatencyStage::Transmission, transmission_latency);
 
                 // 更新测量数据
                 self.measurements
@@ -269,9 +353,10 @@ impl EndToEndLatencyMonitor {
                     .transmission_latency = Some(transmission_latency);
 
                 // 检查传输延迟阈值
-                if transmission_latency.as_millis() as u64 > self.thresholds.transmission_ms {
+                let threshold_ms = self.thresholds_for(&segment_id).transmission_ms;
+                if transmission_latency.as_millis() as u64 > threshold_ms {
                     self.latency_alerts
-                        .trigger_transmission_alert(segment_id, transmission_latency);
+                        .trigger_transmission_alert(segment_id, transmission_latency, threshold_ms);
                 }
             }
         }
@@ -291,6 +376,8 @@ impl EndToEndLatencyMonitor {
                     segment_id,
                     processing_latency.as_millis()
                 );
+                self.histograms
+                    .observe(LatencyStage::Processing, processing_latency);
 
                 // 更新测量数据
                 if let Some(mut measurement) = self.measurements.get_mut(&segment_id) {
@@ -298,18 +385,22 @@ impl EndToEndLatencyMonitor {
                 }
 
                 // 检查处理延迟阈值
-                if processing_latency.as_millis() as u64 > self.thresholds.processing_ms {
+                let threshold_ms = self.thresholds_for(&segment_id).processing_ms;
+                if processing_latency.as_millis() as u64 > threshold_ms {
                     self.latency_alerts
-                        .trigger_processing_alert(segment_id, processing_latency);
+                        .trigger_processing_alert(segment_id, processing_latency, threshold_ms);
                 }
             }
         }
     }
 
     /// 记录客户端播放时间戳 (T4)
-    pub fn record_client_play(&self, segment_id: Uuid, timestamp: SystemTime) {
+    ///
+    /// 返回本次计算出的分发延迟与端到端延迟（缺少对应时间戳时为 None）。
+    pub fn record_client_play(&self, segment_id: Uuid, timestamp: SystemTime) -> ClientPlayLatency {
         debug!("Recording client play time for segment {}", segment_id);
         self.client_play_timestamps.insert(segment_id, timestamp);
+        let mut result = ClientPlayLatency::default();
 
         // 计算分发延迟 (T4 - T3)
         if let Some(forward_time) = self.platform_forward_timestamps.get(&segment_id) {
@@ -319,16 +410,19 @@ impl EndToEndLatencyMonitor {
                     segment_id,
                     distribution_latency.as_millis()
                 );
+                self.histograms
+                    .observe(LatencyStage::Distribution, distribution_latency);
 
-                // 更新测量数据
-                if let Some(mut measurement) = self.measurements.get_mut(&segment_id) {
-                    measurement.distribution_latency = Some(distribution_latency);
-                }
+                // 更新测量数据（回放分片没有传输阶段，测量记录可能尚未创建）
+                self.measurement_entry(segment_id).distribution_latency =
+                    Some(distribution_latency);
+                result.distribution = Some(distribution_latency);
 
                 // 检查分发延迟阈值
-                if distribution_latency.as_millis() as u64 > self.thresholds.distribution_ms {
+                let threshold_ms = self.thresholds_for(&segment_id).distribution_ms;
+                if distribution_latency.as_millis() as u64 > threshold_ms {
                     self.latency_alerts
-                        .trigger_distribution_alert(segment_id, distribution_latency);
+                        .trigger_distribution_alert(segment_id, distribution_latency, threshold_ms);
                 }
             }
         }
@@ -341,22 +435,39 @@ impl EndToEndLatencyMonitor {
                     segment_id,
                     end_to_end_latency.as_millis()
                 );
+                self.histograms.observe(LatencyStage::EndToEnd, end_to_end_latency);
 
                 // 更新测量数据
-                if let Some(mut measurement) = self.measurements.get_mut(&segment_id) {
-                    measurement.end_to_end_latency = Some(end_to_end_latency);
-                }
+                self.measurement_entry(segment_id).end_to_end_latency = Some(end_to_end_latency);
+                result.end_to_end = Some(end_to_end_latency);
 
                 // 检查端到端延迟阈值
-                if end_to_end_latency.as_millis() as u64 > self.thresholds.end_to_end_ms {
+                let threshold_ms = self.thresholds_for(&segment_id).end_to_end_ms;
+                if end_to_end_latency.as_millis() as u64 > threshold_ms {
                     self.latency_alerts
-                        .trigger_end_to_end_alert(segment_id, end_to_end_latency);
+                        .trigger_end_to_end_alert(segment_id, end_to_end_latency, threshold_ms);
                 }
 
                 // 记录成功的端到端测量
                 self.record_successful_measurement(segment_id, end_to_end_latency);
             }
         }
+
+        result
+    }
+
+    fn measurement_entry(
+        &self,
+        segment_id: Uuid,
+    ) -> dashmap::mapref::one::RefMut<'_, Uuid, LatencyMeasurement> {
+        self.measurements
+            .entry(segment_id)
+            .or_insert_with(|| LatencyMeasurement {
+                transmission_latency: None,
+                processing_latency: None,
+                distribution_latency: None,
+                end_to_end_latency: None,
+            })
     }
 
     /// 记录成功的端到端测量
@@ -377,14 +488,118 @@ impl EndToEndLatencyMonitor {
             processing_latency_ms: m.processing_latency.map(|d| d.as_millis() as u64),
             distribution_latency_ms: m.distribution_latency.map(|d| d.as_millis() as u64),
             end_to_end_latency_ms: m.end_to_end_latency.map(|d| d.as_millis() as u64),
+            alerts: self.get_alerts(segment_id).unwrap_or_default(),
         })
     }
 
+    /// 分片是否已由平台转发
+    pub fn is_forwarded(&self, segment_id: &Uuid) -> bool {
+        self.platform_forward_timestamps.contains_key(segment_id)
+    }
+
     /// 获取分片的告警
     pub fn get_alerts(&self, segment_id: &Uuid) -> Option<Vec<LatencyAlertType>> {
         self.latency_alerts.get_alerts(segment_id)
     }
 
+    /// 记录设备分片的传输时间，更新每台设备的延迟与抖动统计
+    ///
+    /// `device_send_time` 必须已换算为平台时钟。返回此分片揭示的新序号缺口数（丢失的分片数）。
+    pub fn record_device_transmission(
+        &self,
+        device_id: &str,
+        sequence: u64,
+        capture_to_send: Option<Duration>,
+        device_send_time: SystemTime,
+        receive_time: SystemTime,
+    ) -> u64 {
+        // 换算误差可能使接收时间早于发送时间，使用有符号差值
+        let transit_us = match receive_time.duration_since(device_send_time) {
+            Ok(d) => d.as_micros() as i64,
+            Err(e) => -(e.duration().as_micros() as i64),
+        };
+        let latency_ms = transit_us.max(0) as f64 / 1000.0;
+
+        let mut state = self
+            .device_transmission
+            .entry(device_id.to_string())
+            .or_insert_with(|| DeviceTransmissionState {
+                stats: DeviceTransmissionStats {
+                    device_id: device_id.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        let state = &mut *state;
+
+        // 序号检查：乱序与缺口
+        let mut gaps = 0;
+        match state.last_sequence {
+            Some(last) if sequence <= last => state.stats.out_of_order += 1,
+            Some(last) => {
+                gaps = sequence - last - 1;
+                state.stats.sequence_gaps += gaps;
+                state.last_sequence = Some(sequence);
+            }
+            None => state.last_sequence = Some(sequence),
+        }
+
+        // 抖动：J += (|D| - J) / 16
+        if let Some(last_transit) = state.last_transit_us {
+            let d_ms = (transit_us - last_transit).abs() as f64 / 1000.0;
+            state.stats.jitter_ms += (d_ms - state.stats.jitter_ms) / 16.0;
+        }
+        state.last_transit_us = Some(transit_us);
+
+        let stats = &mut state.stats;
+        stats.sample_count += 1;
+        stats.last_latency_ms = latency_ms;
+        if stats.sample_count == 1 {
+            stats.min_latency_ms = latency_ms;
+            stats.max_latency_ms = latency_ms;
+            stats.avg_latency_ms = latency_ms;
+        } else {
+            stats.min_latency_ms = stats.min_latency_ms.min(latency_ms);
+            stats.max_latency_ms = stats.max_latency_ms.max(latency_ms);
+            stats.avg_latency_ms +=
+                (latency_ms - stats.avg_latency_ms) / stats.sample_count as f64;
+        }
+
+        if let Some(capture_to_send) = capture_to_send {
+            state.capture_to_send_samples += 1;
+            let ms = capture_to_send.as_micros() as f64 / 1000.0;
+            state.stats.avg_capture_to_send_ms +=
+                (ms - state.stats.avg_capture_to_send_ms) / state.capture_to_send_samples as f64;
+        }
+        gaps
+    }
+
+    /// 获取设备的传输统计
+    pub fn get_device_transmission(&self, device_id: &str) -> Option<DeviceTransmissionStats> {
+        let mut stats = self.device_transmission.get(device_id)?.stats.clone();
+        self.fill_clock_estimate(&mut stats);
+        Some(stats)
+    }
+
+    /// 获取所有设备的传输统计
+    pub fn get_all_device_transmission(&self) -> Vec<DeviceTransmissionStats> {
+        self.device_transmission
+            .iter()
+            .map(|entry| {
+                let mut stats = entry.value().stats.clone();
+                self.fill_clock_estimate(&mut stats);
+                stats
+            })
+            .collect()
+    }
+
+    fn fill_clock_estimate(&self, stats: &mut DeviceTransmissionStats) {
+        if let Some(estimate) = self.clock_sync.get_estimate(&stats.device_id) {
+            stats.clock_offset_us = Some(estimate.offset_us);
+            stats.clock_rtt_us = Some(estimate.rtt_us);
+        }
+    }
+
     /// 清理分片的监控数据
     pub fn cleanup_segment(&self, segment_id: &Uuid) {
         self.device_timestamps.remove(segment_id);
@@ -392,10 +607,20 @@ impl EndToEndLatencyMonitor {
         self.platform_forward_timestamps.remove(segment_id);
         self.client_play_timestamps.remove(segment_id);
         self.measurements.remove(segment_id);
+        self.segment_sessions.remove(segment_id);
         self.latency_alerts.clear_alerts(segment_id);
     }
 }
 
+/// 客户端播放确认时计算出的延迟
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientPlayLatency {
+    /// 分发延迟（平台转发→前端播放）
+    pub distribution: Option<Duration>,
+    /// 端到端延迟（设备发送→前端播放）
+    pub end_to_end: Option<Duration>,
+}
+
 /// 延迟分解数据（用于API返回）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LatencyBreakdown {
@@ -407,6 +632,9 @@ pub struct LatencyBreakdown {
     pub distribution_latency_ms: Option<u64>,
     /// 端到端延迟（毫秒）
     pub end_to_end_latency_ms: Option<u64>,
+    /// 该分片超过阈值的阶段
+    #[serde(default)]
+    pub alerts: Vec<LatencyAlertType>,
 }
 
 #[cfg(test)]
@@ -447,17 +675,11 @@ mod tests {
 
     #[test]
     fn test_latency_alert_manager() {
-        let thresholds = LatencyThresholds {
-            transmission_ms: 50,
-            processing_ms: 10,
-            distribution_ms: 10,
-            end_to_end_ms: 100,
-        };
-        let alert_manager = LatencyAlertManager::new(thresholds);
+        let alert_manager = LatencyAlertManager::new();
         let segment_id = Uuid::new_v4();
 
         // 触发告警
-        alert_manager.trigger_transmission_alert(segment_id, Duration::from_millis(100));
+        alert_manager.trigger_transmission_alert(segment_id, Duration::from_millis(100), 50);
 
         // 验证告警
         let alerts = alert_manager.get_alerts(&segment_id);
@@ -505,4 +727,109 @@ mod tests {
         assert!(m.distribution_latency_ms.unwrap() >= 10);
         assert!(m.end_to_end_latency_ms.unwrap() >= 35);
     }
+
+    #[test]
+    fn test_client_play_on_playback_segment() {
+        let monitor = EndToEndLatencyMonitor::with_defaults();
+        let segment_id = Uuid::new_v4();
+
+        // 回放分片没有设备发送时间
+        let t2 = SystemTime::now();
+        monitor.record_platform_receive(segment_id, t2);
+        let t3 = t2 + Duration::from_millis(3);
+        monitor.record_platform_forward(segment_id, t3);
+
+        let latency = monitor.record_client_play(segment_id, t3 + Duration::from_millis(40));
+        assert_eq!(latency.distribution, Some(Duration::from_millis(40)));
+        assert!(latency.end_to_end.is_none());
+
+        let m = monitor.get_measurement(&segment_id).unwrap();
+        assert_eq!(m.distribution_latency_ms, Some(40));
+
+        // 未知分片
+        assert_eq!(
+            monitor.record_client_play(Uuid::new_v4(), t3),
+            ClientPlayLatency::default()
+        );
+    }
+
+    #[test]
+    fn test_threshold_overrides_apply_immediately() {
+        let monitor = EndToEndLatencyMonitor::with_defaults();
+        let config = monitor.get_threshold_config();
+        let session_id = Uuid::new_v4();
+        config.bind_session(session_id, Some("camera_01".to_string()));
+        config
+            .set_device_override(
+                "camera_01",
+                crate::latency::ThresholdOverride {
+                    transmission_ms: Some(300),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // 150ms 超过默认阈值，但未超过设备覆盖
+        let t1 = SystemTime::now();
+        let first = Uuid::new_v4();
+        monitor.assign_segment(first, session_id);
+        monitor.record_device_send(first, t1);
+        monitor.record_platform_receive(first, t1 + Duration::from_millis(150));
+        assert!(monitor.get_alerts(&first).is_none());
+
+        // 未关联会话的分片使用全局默认阈值
+        let unassigned = Uuid::new_v4();
+        monitor.record_device_send(unassigned, t1);
+        monitor.record_platform_receive(unassigned, t1 + Duration::from_millis(150));
+        assert!(monitor.get_alerts(&unassigned).is_some());
+        assert_eq!(monitor.get_measurement(&unassigned).unwrap().alerts.len(), 1);
+
+        // 删除覆盖后立即按默认阈值告警
+        config.remove_device_override("camera_01").unwrap();
+        let second = Uuid::new_v4();
+        monitor.assign_segment(second, session_id);
+        monitor.record_device_send(second, t1);
+        monitor.record_platform_receive(second, t1 + Duration::from_millis(150));
+        match monitor.get_alerts(&second).unwrap().as_slice() {
+            [LatencyAlertType::TransmissionLatency { threshold_ms, .. }] => {
+                assert_eq!(*threshold_ms, 100)
+            }
+            other => panic!("unexpected alerts: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_device_transmission_latency_and_jitter() {
+        let monitor = EndToEndLatencyMonitor::with_defaults();
+        let base = SystemTime::now();
+
+        // 传输延迟依次为 10ms、14ms、10ms，第 3 个序号缺失
+        let transits = [(0u64, 10u64), (1, 14), (3, 10)];
+        let mut gaps = Vec::new();
+        for (i, (seq, transit_ms)) in transits.iter().enumerate() {
+            let send = base + Duration::from_millis(i as u64 * 33);
+            let receive = send + Duration::from_millis(*transit_ms);
+            gaps.push(monitor.record_device_transmission(
+                "device_001",
+                *seq,
+                Some(Duration::from_millis(2)),
+                send,
+                receive,
+            ));
+        }
+        assert_eq!(gaps, vec![0, 0, 1]);
+        // 乱序到达
+        assert_eq!(monitor.record_device_transmission("device_001", 2, None, base, base + Duration::from_millis(12)), 0);
+
+        let stats = monitor.get_device_transmission("device_001").unwrap();
+        assert_eq!(stats.sample_count, 4);
+        assert_eq!(stats.min_latency_ms, 10.0);
+        assert_eq!(stats.max_latency_ms, 14.0);
+        assert_eq!(stats.sequence_gaps, 1);
+        assert_eq!(stats.out_of_order, 1);
+        assert!(stats.jitter_ms > 0.0);
+        assert!((stats.avg_capture_to_send_ms - 2.0).abs() < f64::EPSILON);
+        assert!(stats.clock_offset_us.is_none());
+        assert_eq!(monitor.get_all_device_transmission().len(), 1);
+    }
 }
@@ -0,0 +1,702 @@
+// 延迟统计历史
+//
+// 按 1s / 1m / 1h 三种粒度汇总每个会话的延迟、吞吐量与丢包，写入嵌入式 SQLite 数据库，
+// 各粒度分别按配置时长保留，平台重启后仍可查询。
+//
+// 每行数据保存一个可合并的延迟草图（LatencySketch），查询时可以按任意步长合并相邻行，
+// 也可以跨会话合并为设备维度的序列。
+//
+// ```
+// record_segment ──▶ 内存中的当前桶（每个会话、每种粒度一个）
+//                        │ 桶结束
+//                        ▼
+// flush（每秒）  ──▶ latency_rollups 表 ──▶ query
+// ```
+
+use super::sketch::LatencySketch;
+use anyhow::{bail, Context, Result};
+use dashmap::DashMap;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+/// 单次查询允许返回的最大数据点数
+const MAX_QUERY_POINTS: u64 = 10_000;
+
+/// 过期数据清理间隔（秒）
+const PRUNE_INTERVAL_SECS: u64 = 60;
+
+/// 汇总粒度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Resolution {
+    #[serde(rename = "1s")]
+    Second,
+    #[serde(rename = "1m")]
+    Minute,
+    #[serde(rename = "1h")]
+    Hour,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 3] = [Resolution::Second, Resolution::Minute, Resolution::Hour];
+
+    /// 粒度（秒）
+    pub fn secs(&self) -> u64 {
+        match self {
+            Resolution::Second => 1,
+            Resolution::Minute => 60,
+            Resolution::Hour => 3600,
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Resolution::Second => 0,
+            Resolution::Minute => 1,
+            Resolution::Hour => 2,
+        }
+    }
+
+    fn align(&self, unix_secs: u64) -> u64 {
+        unix_secs - unix_secs % self.secs()
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "1s" => Ok(Resolution::Second),
+            "1m" => Ok(Resolution::Minute),
+            "1h" => Ok(Resolution::Hour),
+            other => bail!("Unknown resolution: {} (expected 1s, 1m or 1h)", other),
+        }
+    }
+}
+
+/// 历史存储配置
+#[derive(Debug, Clone)]
+pub struct HistoryConfig {
+    /// 数据库文件路径
+    pub path: PathBuf,
+    /// 1s 粒度保留时长
+    pub retention_second: Duration,
+    /// 1m 粒度保留时长
+    pub retention_minute: Duration,
+    /// 1h 粒度保留时长
+    pub retention_hour: Duration,
+}
+
+impl HistoryConfig {
+    fn retention(&self, resolution: Resolution) -> Duration {
+        match resolution {
+            Resolution::Second => self.retention_second,
+            Resolution::Minute => self.retention_minute,
+            Resolution::Hour => self.retention_hour,
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("data/latency_history.db"),
+            retention_second: Duration::from_secs(24 * 3600),
+            retention_minute: Duration::from_secs(30 * 24 * 3600),
+            retention_hour: Duration::from_secs(365 * 24 * 3600),
+        }
+    }
+}
+
+/// 单个时间桶的汇总
+#[derive(Debug, Clone)]
+struct Bucket {
+    start: u64,
+    segments: u64,
+    bytes: u64,
+    lost: u64,
+    latency_sum_us: u64,
+    sketch: LatencySketch,
+}
+
+impl Bucket {
+    fn new(start: u64) -> Self {
+        Self {
+            start,
+            segments: 0,
+            bytes: 0,
+            lost: 0,
+            latency_sum_us: 0,
+            sketch: LatencySketch::new(),
+        }
+    }
+}
+
+/// 会话的当前汇总状态
+struct SessionRollup {
+    device_id: Option<String>,
+    open: [Option<Bucket>; 3],
+    ended: bool,
+}
+
+impl SessionRollup {
+    /// 取出当前桶；桶已过期时先将其移入待写入列表
+    fn bucket(
+        &mut self,
+        session_id: Uuid,
+        resolution: Resolution,
+        now: u64,
+        pending: &mut Vec<RollupRow>,
+    ) -> &mut Bucket {
+        let start = resolution.align(now);
+        let slot = &mut self.open[resolution.index()];
+        if slot.as_ref().is_some_and(|bucket| bucket.start != start) {
+            let closed = slot.take().unwrap();
+            pending.push(RollupRow::new(resolution, session_id, &self.device_id, closed));
+        }
+        slot.get_or_insert_with(|| Bucket::new(start))
+    }
+
+    /// 关闭已过期（或会话结束时所有）的桶
+    fn close_stale(&mut self, session_id: Uuid, now: u64, pending: &mut Vec<RollupRow>) {
+        for resolution in Resolution::ALL {
+            let slot = &mut self.open[resolution.index()];
+            let stale = slot
+                .as_ref()
+                .is_some_and(|bucket| self.ended || bucket.start != resolution.align(now));
+            if stale {
+                let closed = slot.take().unwrap();
+                pending.push(RollupRow::new(resolution, session_id, &self.device_id, closed));
+            }
+        }
+    }
+}
+
+/// 待写入数据库的一行
+#[derive(Debug, Clone)]
+struct RollupRow {
+    resolution: Resolution,
+    session_id: Uuid,
+    device_id: Option<String>,
+    bucket: Bucket,
+}
+
+impl RollupRow {
+    fn new(resolution: Resolution, session_id: Uuid, device_id: &Option<String>, bucket: Bucket) -> Self {
+        Self {
+            resolution,
+            session_id,
+            device_id: device_id.clone(),
+            bucket,
+        }
+    }
+}
+
+/// 查询的序列维度
+#[derive(Debug, Clone)]
+pub enum SeriesKey {
+    Session(Uuid),
+    Device(String),
+}
+
+/// 历史查询参数
+#[derive(Debug, Clone)]
+pub struct HistoryQuery {
+    pub key: SeriesKey,
+    pub resolution: Resolution,
+    /// 起始时间（Unix 秒，包含）
+    pub from: u64,
+    /// 结束时间（Unix 秒，不包含）
+    pub to: u64,
+    /// 步长（秒），默认等于粒度，向上取整为粒度的整数倍
+    pub step: Option<u64>,
+}
+
+/// 时间序列中的一个点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesPoint {
+    /// 桶起始时间（Unix 秒）
+    pub timestamp: u64,
+    /// 分片数
+    pub segments: u64,
+    /// 丢失分片数
+    pub lost_segments: u64,
+    /// 平均延迟（毫秒）
+    pub average_latency_ms: f64,
+    /// P50延迟（毫秒）
+    pub p50_latency_ms: f64,
+    /// P95延迟（毫秒）
+    pub p95_latency_ms: f64,
+    /// P99延迟（毫秒）
+    pub p99_latency_ms: f64,
+    /// 吞吐量（Mbps）
+    pub throughput_mbps: f64,
+    /// 丢包率（0.0-1.0）
+    pub packet_loss_rate: f64,
+}
+
+/// 查询结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencySeries {
+    pub resolution: Resolution,
+    pub step_secs: u64,
+    pub points: Vec<SeriesPoint>,
+}
+
+/// 延迟统计历史
+pub struct LatencyHistory {
+    conn: Mutex<Connection>,
+    config: HistoryConfig,
+    sessions: DashMap<Uuid, SessionRollup>,
+    /// 已结束、等待写入的桶
+    pending: Mutex<Vec<RollupRow>>,
+    /// 上次写入未结束桶的分钟
+    last_checkpoint_minute: AtomicU64,
+    /// 上次清理过期数据的时间
+    last_prune: AtomicU64,
+}
+
+impl LatencyHistory {
+    /// 打开（或创建）历史数据库
+    pub fn open(config: HistoryConfig) -> Result<Self> {
+        if let Some(parent) = config.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let conn = Connection::open(&config.path)
+            .with_context(|| format!("Failed to open {}", config.path.display()))?;
+        info!("Latency history stored at {}", config.path.display());
+        Self::with_connection(conn, config)
+    }
+
+    /// 使用内存数据库（不持久化）
+    pub fn open_in_memory(config: HistoryConfig) -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?, config)
+    }
+
+    fn with_connection(conn: Connection, config: HistoryConfig) -> Result<Self> {
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             CREATE TABLE IF NOT EXISTS latency_rollups (
+                 resolution     INTEGER NOT NULL,
+                 bucket_start   INTEGER NOT NULL,
+                 session_id     TEXT    NOT NULL,
+                 device_id      TEXT,
+                 segments       INTEGER NOT NULL,
+                 bytes          INTEGER NOT NULL,
+                 lost           INTEGER NOT NULL,
+                 latency_sum_us INTEGER NOT NULL,
+                 sketch         BLOB    NOT NULL,
+                 PRIMARY KEY (resolution, session_id, bucket_start)
+             );
+             CREATE INDEX IF NOT EXISTS idx_latency_rollups_device
+                 ON latency_rollups (resolution, device_id, bucket_start);
+             CREATE INDEX IF NOT EXISTS idx_latency_rollups_time
+                 ON latency_rollups (resolution, bucket_start);",
+        )?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            config,
+            sessions: DashMap::new(),
+            pending: Mutex::new(Vec::new()),
+            last_checkpoint_minute: AtomicU64::new(0),
+            last_prune: AtomicU64::new(0),
+        })
+    }
+
+    /// 开始记录会话
+    pub fn start_session(&self, session_id: Uuid, device_id: Option<String>) {
+        self.sessions.insert(
+            session_id,
+            SessionRollup {
+                device_id,
+                open: [None, None, None],
+                ended: false,
+            },
+        );
+    }
+
+    /// 结束会话（未结束的桶在下次 flush 时写入）
+    pub fn end_session(&self, session_id: &Uuid) {
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.ended = true;
+        }
+    }
+
+    /// 记录分片延迟
+    pub fn record_segment(&self, session_id: &Uuid, latency: Duration, bytes: usize) {
+        self.record_segment_at(session_id, latency, bytes, unix_now());
+    }
+
+    /// 记录丢失的分片
+    pub fn record_lost(&self, session_id: &Uuid, count: u64) {
+        self.record_lost_at(session_id, count, unix_now());
+    }
+
+    fn record_segment_at(&self, session_id: &Uuid, latency: Duration, bytes: usize, now: u64) {
+        self.update(session_id, now, |bucket| {
+            bucket.segments += 1;
+            bucket.bytes += bytes as u64;
+            bucket.latency_sum_us += latency.as_micros() as u64;
+            bucket.sketch.insert(latency);
+        });
+    }
+
+    fn record_lost_at(&self, session_id: &Uuid, count: u64, now: u64) {
+        self.update(session_id, now, |bucket| bucket.lost += count);
+    }
+
+    fn update(&self, session_id: &Uuid, now: u64, apply: impl Fn(&mut Bucket)) {
+        let Some(mut session) = self.sessions.get_mut(session_id) else {
+            return;
+        };
+        let mut closed = Vec::new();
+        for resolution in Resolution::ALL {
+            apply(session.bucket(*session_id, resolution, now, &mut closed));
+        }
+        drop(session);
+
+        if !closed.is_empty() {
+            self.pending.lock().unwrap().extend(closed);
+        }
+    }
+
+    /// 写入已结束的桶并清理过期数据
+    pub fn flush(&self) -> Result<()> {
+        self.flush_at(unix_now())
+    }
+
+    fn flush_at(&self, now: u64) -> Result<()> {
+        let mut rows = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        let mut ended = Vec::new();
+        for mut entry in self.sessions.iter_mut() {
+            let session_id = *entry.key();
+            entry.close_stale(session_id, now, &mut rows);
+            if entry.ended {
+                ended.push(session_id);
+            }
+        }
+        for session_id in ended {
+            self.sessions.remove(&session_id);
+        }
+
+        // 每分钟把未结束的分钟/小时桶写入一次，重启时最多丢失一分钟的粗粒度数据
+        let minute = now / 60;
+        if self.last_checkpoint_minute.swap(minute, Ordering::Relaxed) != minute {
+            for entry in self.sessions.iter() {
+                for resolution in [Resolution::Minute, Resolution::Hour] {
+                    if let Some(bucket) = &entry.open[resolution.index()] {
+                        rows.push(RollupRow::new(
+                            resolution,
+                            *entry.key(),
+                            &entry.device_id,
+                            bucket.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if !rows.is_empty() {
+            self.write_rows(&rows)?;
+            debug!("Flushed {} latency rollup rows", rows.len());
+        }
+
+        if now.saturating_sub(self.last_prune.load(Ordering::Relaxed)) >= PRUNE_INTERVAL_SECS {
+            self.last_prune.store(now, Ordering::Relaxed);
+            self.prune(now)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_rows(&self, rows: &[RollupRow]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO latency_rollups
+                 (resolution, bucket_start, session_id, device_id, segments, bytes, lost, latency_sum_us, sketch)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for row in rows {
+                let bucket = &row.bucket;
+                stmt.execute(params![
+                    row.resolution.secs() as i64,
+                    bucket.start as i64,
+                    row.session_id.to_string(),
+                    row.device_id,
+                    bucket.segments as i64,
+                    bucket.bytes as i64,
+                    bucket.lost as i64,
+                    bucket.latency_sum_us as i64,
+                    bincode::serialize(&bucket.sketch)?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 删除超过保留时长的数据
+    fn prune(&self, now: u64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        for resolution in Resolution::ALL {
+            let cutoff = now.saturating_sub(self.config.retention(resolution).as_secs());
+            let removed = conn.execute(
+                "DELETE FROM latency_rollups WHERE resolution = ?1 AND bucket_start < ?2",
+                params![resolution.secs() as i64, cutoff as i64],
+            )?;
+            if removed > 0 {
+                debug!("Pruned {} expired {:?} latency rollups", removed, resolution);
+            }
+        }
+        Ok(())
+    }
+
+    /// 查询时间序列
+    pub fn query(&self, query: &HistoryQuery) -> Result<LatencySeries> {
+        let resolution_secs = query.resolution.secs();
+        let step = query
+            .step
+            .unwrap_or(resolution_secs)
+            .max(resolution_secs)
+            .div_ceil(resolution_secs)
+            * resolution_secs;
+        if query.to <= query.from {
+            bail!("Query range is empty");
+        }
+        if (query.to - query.from) / step > MAX_QUERY_POINTS {
+            bail!("Query would return more than {} points; increase step", MAX_QUERY_POINTS);
+        }
+
+        let (column, key) = match &query.key {
+            SeriesKey::Session(session_id) => ("session_id", session_id.to_string()),
+            SeriesKey::Device(device_id) => ("device_id", device_id.clone()),
+        };
+        let sql = format!(
+            "SELECT bucket_start, segments, bytes, lost, latency_sum_us, sketch
+             FROM latency_rollups
+             WHERE resolution = ?1 AND {} = ?2 AND bucket_start >= ?3 AND bucket_start < ?4
+             ORDER BY bucket_start",
+            column
+        );
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let mut rows = stmt.query(params![
+            resolution_secs as i64,
+            key,
+            query.from as i64,
+            query.to as i64
+        ])?;
+
+        let mut points: Vec<(u64, Bucket)> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let start = row.get::<_, i64>(0)? as u64;
+            let aligned = start - start % step;
+            let sketch: LatencySketch = bincode::deserialize(&row.get::<_, Vec<u8>>(5)?)?;
+
+            if points.last().map(|(t, _)| *t) != Some(aligned) {
+                points.push((aligned, Bucket::new(aligned)));
+            }
+            let bucket = &mut points.last_mut().unwrap().1;
+            bucket.segments += row.get::<_, i64>(1)? as u64;
+            bucket.bytes += row.get::<_, i64>(2)? as u64;
+            bucket.lost += row.get::<_, i64>(3)? as u64;
+            bucket.latency_sum_us += row.get::<_, i64>(4)? as u64;
+            bucket.sketch.merge(&sketch);
+        }
+
+        Ok(LatencySeries {
+            resolution: query.resolution,
+            step_secs: step,
+            points: points
+                .into_iter()
+                .map(|(timestamp, bucket)| to_point(timestamp, &bucket, step))
+                .collect(),
+        })
+    }
+
+    /// 启动定期写入任务
+    pub fn spawn_flush_task(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                let history = Arc::clone(&self);
+                match tokio::task::spawn_blocking(move || history.flush()).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!("Failed to flush latency history: {}", e),
+                    Err(e) => error!("Latency history flush task panicked: {}", e),
+                }
+            }
+        })
+    }
+}
+
+fn to_point(timestamp: u64, bucket: &Bucket, step: u64) -> SeriesPoint {
+    let expected = bucket.segments + bucket.lost;
+    SeriesPoint {
+        timestamp,
+        segments: bucket.segments,
+        lost_segments: bucket.lost,
+        average_latency_ms: if bucket.segments > 0 {
+            bucket.latency_sum_us as f64 / bucket.segments as f64 / 1000.0
+        } else {
+            0.0
+        },
+        p50_latency_ms: bucket.sketch.quantile_ms(0.50),
+        p95_latency_ms: bucket.sketch.quantile_ms(0.95),
+        p99_latency_ms: bucket.sketch.quantile_ms(0.99),
+        throughput_mbps: bucket.bytes as f64 * 8.0 / step as f64 / 1_000_000.0,
+        packet_loss_rate: if expected > 0 {
+            bucket.lost as f64 / expected as f64
+        } else {
+            0.0
+        },
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_query(session_id: Uuid, resolution: Resolution, from: u64, to: u64) -> HistoryQuery {
+        HistoryQuery {
+            key: SeriesKey::Session(session_id),
+            resolution,
+            from,
+            to,
+            step: None,
+        }
+    }
+
+    #[test]
+    fn test_rollups_written_when_buckets_close() {
+        let history = LatencyHistory::open_in_memory(HistoryConfig::default()).unwrap();
+        let session_id = Uuid::new_v4();
+        history.start_session(session_id, Some("camera_7".to_string()));
+
+        let t0 = 1_700_000_040; // 分钟起点
+        for ms in [10, 20, 30] {
+            history.record_segment_at(&session_id, Duration::from_millis(ms), 125_000, t0);
+        }
+        history.record_lost_at(&session_id, 1, t0);
+        history.record_segment_at(&session_id, Duration::from_millis(50), 125_000, t0 + 1);
+        history.flush_at(t0 + 2).unwrap();
+
+        let series = history
+            .query(&session_query(session_id, Resolution::Second, t0, t0 + 10))
+            .unwrap();
+        assert_eq!(series.points.len(), 2);
+        let first = &series.points[0];
+        assert_eq!(first.timestamp, t0);
+        assert_eq!(first.segments, 3);
+        assert_eq!(first.average_latency_ms, 20.0);
+        assert!((first.p50_latency_ms - 20.0).abs() < 0.5);
+        assert!((first.throughput_mbps - 3.0).abs() < 1e-9);
+        assert_eq!(first.packet_loss_rate, 0.25);
+
+        // 分钟桶尚未结束，但已在本分钟的检查点写入
+        let minutes = history
+            .query(&session_query(session_id, Resolution::Minute, t0, t0 + 60))
+            .unwrap();
+        assert_eq!(minutes.points.len(), 1);
+        assert_eq!(minutes.points[0].segments, 4);
+    }
+
+    #[test]
+    fn test_device_series_and_step_merge() {
+        let history = LatencyHistory::open_in_memory(HistoryConfig::default()).unwrap();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        history.start_session(a, Some("camera_7".to_string()));
+        history.start_session(b, Some("camera_7".to_string()));
+
+        let t0 = 1_700_000_000;
+        for offset in 0..10 {
+            history.record_segment_at(&a, Duration::from_millis(10), 1000, t0 + offset);
+            history.record_segment_at(&b, Duration::from_millis(100), 1000, t0 + offset);
+        }
+        history.end_session(&a);
+        history.end_session(&b);
+        history.flush_at(t0 + 10).unwrap();
+        assert!(history.sessions.is_empty());
+
+        let series = history
+            .query(&HistoryQuery {
+                key: SeriesKey::Device("camera_7".to_string()),
+                resolution: Resolution::Second,
+                from: t0,
+                to: t0 + 10,
+                step: Some(5),
+            })
+            .unwrap();
+        assert_eq!(series.step_secs, 5);
+        assert_eq!(series.points.len(), 2);
+        assert_eq!(series.points[0].segments, 10);
+        assert!((series.points[0].p95_latency_ms - 100.0).abs() < 2.0);
+        assert!((series.points[0].p50_latency_ms - 10.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_history_survives_reopen_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = HistoryConfig {
+            path: dir.path().join("history.db"),
+            retention_second: Duration::from_secs(60),
+            ..HistoryConfig::default()
+        };
+        let session_id = Uuid::new_v4();
+        let t0 = 1_700_000_000;
+
+        {
+            let history = LatencyHistory::open(config.clone()).unwrap();
+            history.start_session(session_id, None);
+            history.record_segment_at(&session_id, Duration::from_millis(15), 100, t0);
+            history.end_session(&session_id);
+            history.flush_at(t0 + 1).unwrap();
+        }
+
+        let history = LatencyHistory::open(config).unwrap();
+        let query = session_query(session_id, Resolution::Second, t0, t0 + 1);
+        assert_eq!(history.query(&query).unwrap().points.len(), 1);
+
+        // 超过 1s 粒度的保留时长后被清理，1m 粒度仍保留
+        history.flush_at(t0 + 120).unwrap();
+        assert!(history.query(&query).unwrap().points.is_empty());
+        let minutes = session_query(session_id, Resolution::Minute, t0 - 60, t0 + 60);
+        assert_eq!(history.query(&minutes).unwrap().points.len(), 1);
+    }
+
+    #[test]
+    fn test_query_validation() {
+        let history = LatencyHistory::open_in_memory(HistoryConfig::default()).unwrap();
+        let session_id = Uuid::new_v4();
+        assert!(history
+            .query(&session_query(session_id, Resolution::Second, 10, 10))
+            .is_err());
+        assert!(history
+            .query(&session_query(session_id, Resolution::Second, 0, 1_000_000))
+            .is_err());
+        assert_eq!("1m".parse::<Resolution>().unwrap(), Resolution::Minute);
+        assert!("5m".parse::<Resolution>().is_err());
+    }
+}
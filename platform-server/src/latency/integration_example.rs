@@ -64,13 +64,12 @@ impl MonitoredStreamHandler {
         let receive_time = SystemTime::now();
         segment.receive_time = Some(receive_time);
 
-        // 如果分片有设备端时间戳，记录到监控器
-        // 注意：实际实现中，设备端时间戳应该在分片中携带
-        // 这里假设segment.timestamp可以转换为SystemTime
-        let device_send_time = SystemTime::UNIX_EPOCH
-            + std::time::Duration::from_secs_f64(segment.timestamp);
-        self.latency_monitor
-            .record_device_send(segment.segment_id, device_send_time);
+        // 如果分片有设备端发送时间（已换算为平台时钟），记录到监控器；
+        // 分片的 PTS/DTS 是媒体时间，不能当作墙上时钟使用
+        if let Some(device_send_time) = segment.device_send_time {
+            self.latency_monitor
+                .record_device_send(segment.segment_id, device_send_time);
+        }
 
         // 记录平台端接收时间
         self.latency_monitor
@@ -260,14 +259,18 @@ mod tests {
 
         let segment = VideoSegment {
             segment_id: Uuid::new_v4(),
-            timestamp: 1.0,
-            duration: 0.033,
+            pts: 90_000,
+            dts: 90_000,
+            duration: 3_000,
             data: vec![0u8; 1024],
             is_keyframe: true,
+            decode_only: false,
+            discontinuity: false,
             format: crate::streaming::SegmentFormat::FMP4,
             source_type: crate::streaming::SegmentSourceType::Live,
             receive_time: None,
             forward_time: None,
+            device_send_time: None,
         };
 
         // 处理接收
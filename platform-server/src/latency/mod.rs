@@ -1,12 +1,25 @@
 mod alert_broadcaster;
+mod clock_sync;
 mod end_to_end_monitor;
+mod history;
 mod monitor;
+mod playback_ack;
+mod sketch;
 mod statistics;
+mod thresholds;
 
-pub use alert_broadcaster::{AlertBroadcaster, AlertFilter, AlertMessage};
+pub use alert_broadcaster::{AlertBroadcaster, AlertFilter};
+pub use clock_sync::{ClockSample, ClockSyncManager};
 pub use end_to_end_monitor::{
-    EndToEndLatencyMonitor, LatencyAlertManager, LatencyAlertType, LatencyBreakdown,
-    LatencyThresholds,
+    DeviceTransmissionStats, EndToEndLatencyMonitor,
+    LatencyBreakdown, LatencyThresholds,
+};
+pub use history::{
+    HistoryConfig, HistoryQuery, LatencyHistory, LatencySeries, Resolution, SeriesKey,
 };
 pub use monitor::LatencyMonitor;
-pub use statistics::{LatencyStatistics, LatencyStatisticsManager};
+pub use playback_ack::{
+    PlaybackAckBatch, PlaybackAckRecorder, PlaybackAckSummary, MAX_ACKS_PER_BATCH,
+};
+pub use statistics::{ClientPlaybackStats, LatencyStatistics, LatencyStatisticsManager};
+pub use thresholds::{ThresholdConfigSnapshot, ThresholdOverride};
@@ -0,0 +1,199 @@
+// 客户端播放确认
+//
+// 浏览器渲染分片后批量上报 (segment_id, 渲染时间)，平台据此记录客户端播放时间 (T4)，
+// 得到实测的分发延迟与端到端延迟，而不是按转发时间估算。
+//
+// 浏览器时钟与平台时钟不一定一致，因此批次可以携带客户端发送时间 `sent_at_ms`：
+// 平台以收到批次的时间为基准，减去 (sent_at_ms - rendered_at_ms) 还原渲染时间，
+// 只依赖客户端时钟的相对差值。未携带时直接把 `rendered_at_ms` 当作平台时间。
+
+use super::{EndToEndLatencyMonitor, LatencyStatisticsManager};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+use uuid::Uuid;
+
+/// 单个批次允许的最大确认数
+pub const MAX_ACKS_PER_BATCH: usize = 256;
+
+/// 单个分片的播放确认
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackAck {
+    /// 分片ID
+    pub segment_id: Uuid,
+    /// 渲染时间（客户端时钟，Unix 毫秒）
+    pub rendered_at_ms: u64,
+}
+
+/// 批量播放确认
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackAckBatch {
+    pub acks: Vec<PlaybackAck>,
+    /// 批次发送时间（客户端时钟，Unix 毫秒）
+    #[serde(default)]
+    pub sent_at_ms: Option<u64>,
+}
+
+impl PlaybackAckBatch {
+    /// 将确认中的渲染时间换算为平台时间
+    pub fn render_time(&self, ack: &PlaybackAck, received_at: SystemTime) -> SystemTime {
+        match self.sent_at_ms {
+            Some(sent_at_ms) => {
+                let age = Duration::from_millis(sent_at_ms.saturating_sub(ack.rendered_at_ms));
+                received_at.checked_sub(age).unwrap_or(received_at)
+            }
+            // 渲染时间不可能晚于平台收到确认的时间
+            None => (UNIX_EPOCH + Duration::from_millis(ack.rendered_at_ms)).min(received_at),
+        }
+    }
+}
+
+/// 批量确认的处理结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaybackAckSummary {
+    /// 已记录的确认数
+    pub accepted: usize,
+    /// 找不到转发记录的确认数（分片未知或监控数据已清理）
+    pub unmatched: usize,
+}
+
+/// 播放确认记录器
+///
+/// 把客户端确认写入端到端延迟监控和会话统计，播放阶段的告警由规则引擎按会话统计评估。
+#[derive(Clone)]
+pub struct PlaybackAckRecorder {
+    latency_monitor: Arc<EndToEndLatencyMonitor>,
+    stats_manager: Arc<LatencyStatisticsManager>,
+}
+
+impl PlaybackAckRecorder {
+    pub fn new(
+        latency_monitor: Arc<EndToEndLatencyMonitor>,
+        stats_manager: Arc<LatencyStatisticsManager>,
+    ) -> Self {
+        Self {
+            latency_monitor,
+            stats_manager,
+        }
+    }
+
+    /// 记录一批播放确认
+    ///
+    /// `received_at` 为平台收到该批次的时间。
+    pub fn record(
+        &self,
+        session_id: Uuid,
+        batch: &PlaybackAckBatch,
+        received_at: SystemTime,
+    ) -> PlaybackAckSummary {
+        let mut summary = PlaybackAckSummary::default();
+
+        for ack in &batch.acks {
+            // 只接受平台转发过的分片，避免未知ID堆积在监控数据中
+            if !self.latency_monitor.is_forwarded(&ack.segment_id) {
+                summary.unmatched += 1;
+                continue;
+            }
+
+            let render_time = batch.render_time(ack, received_at);
+            let latency = self
+                .latency_monitor
+                .record_client_play(ack.segment_id, render_time);
+            self.stats_manager
+                .record_client_ack(&session_id, latency.distribution, latency.end_to_end);
+            summary.accepted += 1;
+        }
+
+        debug!(
+            "Playback acks for session {}: accepted={}, unmatched={}",
+            session_id, summary.accepted, summary.unmatched
+        );
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder() -> (PlaybackAckRecorder, Arc<EndToEndLatencyMonitor>, Arc<LatencyStatisticsManager>) {
+        let monitor = Arc::new(EndToEndLatencyMonitor::with_defaults());
+        let stats = Arc::new(LatencyStatisticsManager::new());
+        let recorder = PlaybackAckRecorder::new(
+            Arc::clone(&monitor),
+            Arc::clone(&stats),
+        );
+        (recorder, monitor, stats)
+    }
+
+    #[test]
+    fn test_render_time_uses_client_relative_age() {
+        let received_at = UNIX_EPOCH + Duration::from_secs(1_000);
+        let ack = PlaybackAck {
+            segment_id: Uuid::new_v4(),
+            rendered_at_ms: 5_000,
+        };
+
+        // 客户端时钟与平台相差很大，只使用相对差值
+        let batch = PlaybackAckBatch {
+            acks: vec![ack.clone()],
+            sent_at_ms: Some(5_250),
+        };
+        assert_eq!(
+            batch.render_time(&ack, received_at),
+            received_at - Duration::from_millis(250)
+        );
+
+        // 未携带发送时间时，渲染时间不会晚于接收时间
+        let batch = PlaybackAckBatch {
+            acks: vec![ack.clone()],
+            sent_at_ms: None,
+        };
+        assert_eq!(
+            batch.render_time(&ack, received_at),
+            UNIX_EPOCH + Duration::from_millis(5_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_feeds_monitor_and_statistics() {
+        let (recorder, monitor, stats) = recorder();
+        let session_id = Uuid::new_v4();
+        stats.start_session(session_id);
+
+        let segment_id = Uuid::new_v4();
+        let t1 = SystemTime::now() - Duration::from_millis(500);
+        monitor.record_device_send(segment_id, t1);
+        monitor.record_platform_receive(segment_id, t1 + Duration::from_millis(10));
+        let t3 = t1 + Duration::from_millis(12);
+        monitor.record_platform_forward(segment_id, t3);
+
+        let batch = PlaybackAckBatch {
+            acks: vec![
+                PlaybackAck {
+                    segment_id,
+                    rendered_at_ms: 10_000,
+                },
+                PlaybackAck {
+                    segment_id: Uuid::new_v4(),
+                    rendered_at_ms: 10_000,
+                },
+            ],
+            sent_at_ms: Some(10_000),
+        };
+        let received_at = t3 + Duration::from_millis(300);
+        let summary = recorder.record(session_id, &batch, received_at);
+        assert_eq!(summary, PlaybackAckSummary { accepted: 1, unmatched: 1 });
+
+        let m = monitor.get_measurement(&segment_id).unwrap();
+        assert_eq!(m.distribution_latency_ms, Some(300));
+        assert_eq!(m.end_to_end_latency_ms, Some(312));
+
+        let s = stats.get_statistics(&session_id).unwrap();
+        assert_eq!(s.acked_segments, 1);
+        assert_eq!(s.distribution_p95_ms, 300);
+        assert_eq!(s.end_to_end_p95_ms, 312);
+    }
+}
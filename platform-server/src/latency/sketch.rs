@@ -0,0 +1,115 @@
+// 可合并的延迟草图
+//
+// 按对数间隔分桶（相对误差约 1%），桶计数可直接相加，
+// 因此不同时间段、不同会话的草图可以合并后再计算百分位数。
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// 相邻桶边界的比值
+const GAMMA: f64 = 1.02;
+
+/// 延迟草图
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySketch {
+    /// 桶索引 → 计数
+    bins: BTreeMap<u16, u64>,
+    /// 总计数
+    count: u64,
+}
+
+impl LatencySketch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一个延迟值
+    pub fn insert(&mut self, latency: Duration) {
+        *self.bins.entry(bin_index(latency)).or_insert(0) += 1;
+        self.count += 1;
+    }
+
+    /// 合并另一个草图
+    pub fn merge(&mut self, other: &LatencySketch) {
+        for (index, count) in &other.bins {
+            *self.bins.entry(*index).or_insert(0) += count;
+        }
+        self.count += other.count;
+    }
+
+    /// 估算百分位延迟（毫秒），草图为空时返回 0
+    pub fn quantile_ms(&self, quantile: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        let rank = ((self.count as f64 * quantile).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (index, count) in &self.bins {
+            seen += count;
+            if seen >= rank {
+                return bin_value_us(*index) / 1000.0;
+            }
+        }
+        0.0
+    }
+}
+
+fn bin_index(latency: Duration) -> u16 {
+    let micros = latency.as_micros() as f64;
+    if micros <= 1.0 {
+        return 0;
+    }
+    (micros.ln() / GAMMA.ln()).ceil().min(u16::MAX as f64) as u16
+}
+
+/// 桶的代表值（微秒），取上下边界的调和中点
+fn bin_value_us(index: u16) -> f64 {
+    if index == 0 {
+        return 1.0;
+    }
+    2.0 * GAMMA.powi(index as i32) / (GAMMA + 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantiles_within_relative_error() {
+        let mut sketch = LatencySketch::new();
+        for ms in 1..=1000 {
+            sketch.insert(Duration::from_millis(ms));
+        }
+
+        for (quantile, expected) in [(0.5, 500.0), (0.95, 950.0), (0.99, 990.0)] {
+            let estimate = sketch.quantile_ms(quantile);
+            assert!(
+                (estimate - expected).abs() / expected < 0.02,
+                "p{} = {}",
+                quantile,
+                estimate
+            );
+        }
+        assert_eq!(LatencySketch::new().quantile_ms(0.5), 0.0);
+    }
+
+    #[test]
+    fn test_merge_matches_combined_inserts() {
+        let mut a = LatencySketch::new();
+        let mut b = LatencySketch::new();
+        let mut combined = LatencySketch::new();
+        for ms in [3, 7, 20, 45] {
+            a.insert(Duration::from_millis(ms));
+            combined.insert(Duration::from_millis(ms));
+        }
+        for ms in [8, 90, 150] {
+            b.insert(Duration::from_millis(ms));
+            combined.insert(Duration::from_millis(ms));
+        }
+
+        a.merge(&b);
+        assert_eq!(a, combined);
+    }
+}
@@ -5,7 +5,11 @@
 // - P50、P95、P99延迟百分位数
 // - 吞吐量统计
 // - 丢包率统计
+// - 基于客户端播放确认的实测端到端/分发延迟
+// - 客户端上报的缓冲、丢帧、解码耗时与卡顿统计
+// - 可选的历史持久化（见 history 模块）
 
+use super::history::LatencyHistory;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
@@ -50,6 +54,40 @@ pub struct LatencyStatistics {
     pub throughput_mbps: f64,
     /// 丢包率（0.0-1.0）
     pub packet_loss_rate: f64,
+    /// 客户端已确认播放的分片数
+    pub acked_segments: u64,
+    /// 实测端到端平均延迟（毫秒，来自客户端播放确认）
+    pub end_to_end_avg_ms: f64,
+    /// 实测端到端P95延迟（毫秒）
+    pub end_to_end_p95_ms: u64,
+    /// 实测分发平均延迟（毫秒，来自客户端播放确认）
+    pub distribution_avg_ms: f64,
+    /// 实测分发P95延迟（毫秒）
+    pub distribution_p95_ms: u64,
+    /// 客户端播放状态（来自客户端遥测上报）
+    #[serde(default)]
+    pub client: ClientPlaybackStats,
+}
+
+/// 客户端播放状态统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClientPlaybackStats {
+    /// 遥测上报次数
+    pub reports: u64,
+    /// 客户端缓冲时长（毫秒，最近一次上报）
+    pub buffered_ms: u64,
+    /// 客户端累计丢帧数
+    pub dropped_frames: u64,
+    /// 平均解码耗时（毫秒/帧，平滑值）
+    pub decode_time_ms: f64,
+    /// 累计卡顿次数
+    pub stall_count: u64,
+    /// 累计卡顿时长（毫秒）
+    pub stall_duration_ms: u64,
+    /// 当前是否处于卡顿
+    pub stalled: bool,
+    /// 实测交付吞吐量（Mbps，客户端未上报接收字节数时为0）
+    pub delivery_mbps: f64,
 }
 
 /// 会话统计数据（内部使用）
@@ -62,6 +100,42 @@ struct SessionStats {
     expected_segments: u64,
     lost_segments: u64,
     latency_history: VecDeque<Duration>,
+    acked_segments: u64,
+    end_to_end_history: VecDeque<Duration>,
+    distribution_history: VecDeque<Duration>,
+    client: ClientPlaybackStats,
+}
+
+/// 向固定窗口追加测量值
+fn push_window(window: &mut VecDeque<Duration>, latency: Duration) {
+    if window.len() >= STATS_WINDOW_SIZE {
+        window.pop_front();
+    }
+    window.push_back(latency);
+}
+
+/// 计算窗口平均值（毫秒）
+fn window_average_ms(window: &VecDeque<Duration>) -> f64 {
+    if window.is_empty() {
+        return 0.0;
+    }
+
+    let sum: Duration = window.iter().sum();
+    let avg_duration = sum / window.len() as u32;
+    avg_duration.as_secs_f64() * 1000.0
+}
+
+/// 计算窗口百分位值（毫秒）
+fn window_percentile_ms(window: &VecDeque<Duration>, percentile: f64) -> u64 {
+    if window.is_empty() {
+        return 0;
+    }
+
+    let mut sorted: Vec<u64> = window.iter().map(|d| d.as_millis() as u64).collect();
+    sorted.sort_unstable();
+
+    let index = ((sorted.len() as f64 * percentile).ceil() as usize).saturating_sub(1);
+    sorted.get(index).copied().unwrap_or(0)
 }
 
 impl SessionStats {
@@ -75,27 +149,34 @@ impl SessionStats {
             expected_segments: 0,
             lost_segments: 0,
             latency_history: VecDeque::with_capacity(STATS_WINDOW_SIZE),
+            acked_segments: 0,
+            end_to_end_history: VecDeque::new(),
+            distribution_history: VecDeque::new(),
+            client: ClientPlaybackStats::default(),
         }
     }
 
     /// 添加延迟测量
     fn add_latency(&mut self, latency: Duration) {
-        if self.latency_history.len() >= STATS_WINDOW_SIZE {
-            self.latency_history.pop_front();
+        push_window(&mut self.latency_history, latency);
+        self.last_update = Instant::now();
+    }
+
+    /// 添加客户端播放确认的实测延迟
+    fn add_client_ack(&mut self, distribution: Option<Duration>, end_to_end: Option<Duration>) {
+        self.acked_segments += 1;
+        if let Some(latency) = distribution {
+            push_window(&mut self.distribution_history, latency);
+        }
+        if let Some(latency) = end_to_end {
+            push_window(&mut self.end_to_end_history, latency);
         }
-        self.latency_history.push_back(latency);
         self.last_update = Instant::now();
     }
 
     /// 计算平均延迟
     fn average_latency(&self) -> f64 {
-        if self.latency_history.is_empty() {
-            return 0.0;
-        }
-
-        let sum: Duration = self.latency_history.iter().sum();
-        let avg_duration = sum / self.latency_history.len() as u32;
-        avg_duration.as_secs_f64() * 1000.0
+        window_average_ms(&self.latency_history)
     }
 
     /// 计算最小延迟
@@ -118,25 +199,13 @@ impl SessionStats {
 
     /// 计算百分位延迟
     fn percentile_latency(&self, percentile: f64) -> u64 {
-        if self.latency_history.is_empty() {
-            return 0;
-        }
-
-        let mut sorted: Vec<u64> = self
-            .latency_history
-            .iter()
-            .map(|d| d.as_millis() as u64)
-            .collect();
-        sorted.sort_unstable();
-
-        let index = ((sorted.len() as f64 * percentile).ceil() as usize).saturating_sub(1);
-        sorted.get(index).copied().unwrap_or(0)
+        window_percentile_ms(&self.latency_history, percentile)
     }
 
     /// 计算吞吐量（Mbps）
     fn throughput_mbps(&self) -> f64 {
         let elapsed = self.last_update.duration_since(self.start_time);
-        if elapsed.as_secs() == 0 {
+        if elapsed.is_zero() {
             return 0.0;
         }
 
@@ -174,6 +243,12 @@ impl SessionStats {
             p99_latency_ms: self.percentile_latency(0.99),
             throughput_mbps: self.throughput_mbps(),
             packet_loss_rate: self.packet_loss_rate(),
+            acked_segments: self.acked_segments,
+            end_to_end_avg_ms: window_average_ms(&self.end_to_end_history),
+            end_to_end_p95_ms: window_percentile_ms(&self.end_to_end_history, 0.95),
+            distribution_avg_ms: window_average_ms(&self.distribution_history),
+            distribution_p95_ms: window_percentile_ms(&self.distribution_history, 0.95),
+            client: self.client.clone(),
         }
     }
 }
@@ -184,6 +259,7 @@ impl SessionStats {
 #[derive(Clone)]
 pub struct LatencyStatisticsManager {
     sessions: Arc<DashMap<Uuid, SessionStats>>,
+    history: Option<Arc<LatencyHistory>>,
 }
 
 impl LatencyStatisticsManager {
@@ -191,14 +267,36 @@ impl LatencyStatisticsManager {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(DashMap::new()),
+            history: None,
+        }
+    }
+
+    /// 创建同时写入历史存储的统计管理器
+    pub fn with_history(history: Arc<LatencyHistory>) -> Self {
+        Self {
+            sessions: Arc::new(DashMap::new()),
+            history: Some(history),
         }
     }
 
+    /// 获取历史存储
+    pub fn history(&self) -> Option<Arc<LatencyHistory>> {
+        self.history.clone()
+    }
+
     /// 开始统计会话
     pub fn start_session(&self, session_id: Uuid) {
+        self.start_device_session(session_id, None);
+    }
+
+    /// 开始统计会话，并记录会话所属设备（用于按设备查询历史）
+    pub fn start_device_session(&self, session_id: Uuid, device_id: Option<String>) {
         info!("Starting latency statistics for session {}", session_id);
         let stats = SessionStats::new(session_id);
         self.sessions.insert(session_id, stats);
+        if let Some(history) = &self.history {
+            history.start_session(session_id, device_id);
+        }
     }
 
     /// 记录分片延迟
@@ -214,6 +312,10 @@ impl LatencyStatisticsManager {
             stats.total_bytes += segment_size as u64;
             stats.expected_segments += 1;
 
+            if let Some(history) = &self.history {
+                history.record_segment(session_id, latency, segment_size);
+            }
+
             debug!(
                 "Recorded latency for session {}: {}ms",
                 session_id,
@@ -222,12 +324,39 @@ impl LatencyStatisticsManager {
         }
     }
 
-    /// 记录丢失的分片
-    pub fn record_lost_segment(&self, session_id: &Uuid) {
+    /// 记录丢失的分片（设备分片序号的缺口）
+    pub fn record_lost_segments(&self, session_id: &Uuid, count: u64) {
         if let Some(mut stats) = self.sessions.get_mut(session_id) {
-            stats.lost_segments += 1;
-            stats.expected_segments += 1;
-            debug!("Recorded lost segment for session {}", session_id);
+            stats.lost_segments += count;
+            stats.expected_segments += count;
+            if let Some(history) = &self.history {
+                history.record_lost(session_id, count);
+            }
+            debug!("Recorded {} lost segments for session {}", count, session_id);
+        }
+    }
+
+    /// 记录客户端播放确认的实测延迟
+    pub fn record_client_ack(
+        &self,
+        session_id: &Uuid,
+        distribution: Option<Duration>,
+        end_to_end: Option<Duration>,
+    ) {
+        if let Some(mut stats) = self.sessions.get_mut(session_id) {
+            stats.add_client_ack(distribution, end_to_end);
+            debug!(
+                "Recorded client ack for session {}: distribution={:?}, end_to_end={:?}",
+                session_id, distribution, end_to_end
+            );
+        }
+    }
+
+    /// 记录客户端上报的播放状态
+    pub fn record_client_playback(&self, session_id: &Uuid, client: ClientPlaybackStats) {
+        if let Some(mut stats) = self.sessions.get_mut(session_id) {
+            stats.client = client;
+            stats.last_update = Instant::now();
         }
     }
 
@@ -250,6 +379,9 @@ impl LatencyStatisticsManager {
     pub fn stop_session(&self, session_id: &Uuid) {
         info!("Stopping latency statistics for session {}", session_id);
         self.sessions.remove(session_id);
+        if let Some(history) = &self.history {
+            history.end_session(session_id);
+        }
     }
 
     /// 清理所有会话
@@ -398,7 +530,7 @@ mod tests {
 
         // 记录一些正常分片和丢失分片
         manager.record_segment_latency(&session_id, Duration::from_millis(50), 1024);
-        manager.record_lost_segment(&session_id);
+        manager.record_lost_segments(&session_id, 1);
         manager.record_segment_latency(&session_id, Duration::from_millis(60), 1024);
 
         let stats = manager.get_statistics(&session_id).unwrap();
@@ -406,6 +538,28 @@ mod tests {
         assert!(stats.packet_loss_rate > 0.0);
     }
 
+    #[test]
+    fn test_client_ack_statistics() {
+        let manager = LatencyStatisticsManager::new();
+        let session_id = Uuid::new_v4();
+        manager.start_session(session_id);
+
+        manager.record_client_ack(
+            &session_id,
+            Some(Duration::from_millis(20)),
+            Some(Duration::from_millis(120)),
+        );
+        manager.record_client_ack(&session_id, Some(Duration::from_millis(40)), None);
+
+        let stats = manager.get_statistics(&session_id).unwrap();
+        assert_eq!(stats.acked_segments, 2);
+        assert_eq!(stats.distribution_avg_ms, 30.0);
+        assert_eq!(stats.distribution_p95_ms, 40);
+        assert_eq!(stats.end_to_end_avg_ms, 120.0);
+        // 客户端确认不计入平台侧分片统计
+        assert_eq!(stats.total_segments, 0);
+    }
+
     #[test]
     fn test_window_size_limit() {
         let session_id = Uuid::new_v4();
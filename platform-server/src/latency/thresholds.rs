@@ -0,0 +1,388 @@
+// 可热更新的延迟阈值配置
+//
+// 生效阈值按 会话覆盖 > 设备覆盖 > 全局默认 逐项合并。
+// 全局默认值与设备覆盖可持久化到 JSON 文件，重启后恢复；
+// 会话覆盖只在会话存续期间有效：会话ID在平台重启后不会复用，持久化没有意义。
+
+use super::end_to_end_monitor::LatencyThresholds;
+use anyhow::{bail, Context, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// 阈值覆盖（未设置的项沿用上一级配置）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThresholdOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transmission_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processing_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distribution_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_to_end_ms: Option<u64>,
+}
+
+impl ThresholdOverride {
+    /// 所有项均未设置
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 阈值必须大于 0
+    pub fn validate(&self) -> Result<()> {
+        let values = [
+            self.transmission_ms,
+            self.processing_ms,
+            self.distribution_ms,
+            self.end_to_end_ms,
+        ];
+        if values.contains(&Some(0)) {
+            bail!("Latency thresholds must be greater than 0");
+        }
+        Ok(())
+    }
+
+    fn apply(&self, thresholds: &mut LatencyThresholds) {
+        if let Some(ms) = self.transmission_ms {
+            thresholds.transmission_ms = ms;
+        }
+        if let Some(ms) = self.processing_ms {
+            thresholds.processing_ms = ms;
+        }
+        if let Some(ms) = self.distribution_ms {
+            thresholds.distribution_ms = ms;
+        }
+        if let Some(ms) = self.end_to_end_ms {
+            thresholds.end_to_end_ms = ms;
+        }
+    }
+}
+
+/// 当前阈值配置的快照（用于API返回）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThresholdConfigSnapshot {
+    /// 全局默认阈值
+    pub defaults: LatencyThresholds,
+    /// 设备覆盖
+    pub devices: BTreeMap<String, ThresholdOverride>,
+    /// 会话覆盖（不持久化，会话结束即清除）
+    pub sessions: BTreeMap<Uuid, ThresholdOverride>,
+}
+
+/// 持久化文件内容（不含会话覆盖）
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedThresholds {
+    defaults: Option<LatencyThresholds>,
+    #[serde(default)]
+    devices: BTreeMap<String, ThresholdOverride>,
+}
+
+struct ThresholdConfigInner {
+    defaults: RwLock<LatencyThresholds>,
+    devices: DashMap<String, ThresholdOverride>,
+    sessions: DashMap<Uuid, ThresholdOverride>,
+    /// 会话 → 设备（直通会话），用于查找设备覆盖
+    session_devices: DashMap<Uuid, String>,
+    /// 持久化文件路径，同时串行化写入
+    store: Mutex<Option<PathBuf>>,
+}
+
+/// 延迟阈值配置
+///
+/// 克隆后共享同一份配置，修改立即对所有持有者生效。
+#[derive(Clone)]
+pub struct ThresholdConfig {
+    inner: Arc<ThresholdConfigInner>,
+}
+
+impl ThresholdConfig {
+    pub fn new(defaults: LatencyThresholds) -> Self {
+        Self {
+            inner: Arc::new(ThresholdConfigInner {
+                defaults: RwLock::new(defaults),
+                devices: DashMap::new(),
+                sessions: DashMap::new(),
+                session_devices: DashMap::new(),
+                store: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// 从文件恢复全局默认值与设备覆盖，之后的修改都会写回该文件
+    ///
+    /// 文件不存在时以当前配置创建。
+    pub fn enable_persistence(&self, path: impl Into<PathBuf>) -> Result<()> {
+        let path = path.into();
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let persisted: PersistedThresholds = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+            if let Some(defaults) = persisted.defaults {
+                *self.inner.defaults.write().unwrap() = defaults;
+            }
+            for (device_id, threshold_override) in persisted.devices {
+                self.inner.devices.insert(device_id, threshold_override);
+            }
+            info!(
+                "Loaded latency thresholds from {} ({} device overrides)",
+                path.display(),
+                self.inner.devices.len()
+            );
+        }
+
+        *self.inner.store.lock().unwrap() = Some(path);
+        self.save()
+    }
+
+    /// 全局默认阈值
+    pub fn defaults(&self) -> LatencyThresholds {
+        *self.inner.defaults.read().unwrap()
+    }
+
+    /// 更新全局默认阈值，返回更新后的值
+    pub fn update_defaults(&self, update: ThresholdOverride) -> Result<LatencyThresholds> {
+        update.validate()?;
+        let defaults = {
+            let mut defaults = self.inner.defaults.write().unwrap();
+            update.apply(&mut defaults);
+            *defaults
+        };
+        info!("Updated default latency thresholds: {:?}", defaults);
+        self.save()?;
+        Ok(defaults)
+    }
+
+    /// 设置设备覆盖（空覆盖等同于删除）
+    pub fn set_device_override(&self, device_id: &str, update: ThresholdOverride) -> Result<()> {
+        update.validate()?;
+        if update.is_empty() {
+            self.inner.devices.remove(device_id);
+        } else {
+            self.inner.devices.insert(device_id.to_string(), update);
+        }
+        info!("Set latency threshold override for device {}: {:?}", device_id, update);
+        self.save()
+    }
+
+    /// 删除设备覆盖，返回此前是否存在
+    pub fn remove_device_override(&self, device_id: &str) -> Result<bool> {
+        let removed = self.inner.devices.remove(device_id).is_some();
+        if removed {
+            info!("Removed latency threshold override for device {}", device_id);
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// 设置会话覆盖（空覆盖等同于删除）
+    ///
+    /// 会话覆盖不写入持久化文件，在会话结束（`unbind_session`）或平台重启后失效。
+    pub fn set_session_override(&self, session_id: Uuid, update: ThresholdOverride) -> Result<()> {
+        update.validate()?;
+        if update.is_empty() {
+            self.inner.sessions.remove(&session_id);
+        } else {
+            self.inner.sessions.insert(session_id, update);
+        }
+        info!("Set latency threshold override for session {}: {:?}", session_id, update);
+        Ok(())
+    }
+
+    /// 删除会话覆盖，返回此前是否存在
+    pub fn remove_session_override(&self, session_id: &Uuid) -> bool {
+        self.inner.sessions.remove(session_id).is_some()
+    }
+
+    /// 关联会话与设备（直通会话），使设备覆盖对该会话生效
+    pub fn bind_session(&self, session_id: Uuid, device_id: Option<String>) {
+        if let Some(device_id) = device_id {
+            self.inner.session_devices.insert(session_id, device_id);
+        }
+    }
+
+    /// 会话结束时清除关联与会话覆盖
+    pub fn unbind_session(&self, session_id: &Uuid) {
+        self.inner.session_devices.remove(session_id);
+        self.inner.sessions.remove(session_id);
+    }
+
+    /// 计算生效阈值
+    ///
+    /// 未指定设备时，使用会话关联的设备。
+    pub fn effective(&self, session_id: Option<&Uuid>, device_id: Option<&str>) -> LatencyThresholds {
+        let mut thresholds = self.defaults();
+
+        let bound_device = match (device_id, session_id) {
+            (None, Some(session_id)) => self
+                .inner
+                .session_devices
+                .get(session_id)
+                .map(|entry| entry.value().clone()),
+            _ => None,
+        };
+        if let Some(device_id) = device_id.or(bound_device.as_deref()) {
+            if let Some(device_override) = self.inner.devices.get(device_id) {
+                device_override.apply(&mut thresholds);
+            }
+        }
+
+        if let Some(session_id) = session_id {
+            if let Some(session_override) = self.inner.sessions.get(session_id) {
+                session_override.apply(&mut thresholds);
+            }
+        }
+
+        thresholds
+    }
+
+    /// 当前配置快照
+    pub fn snapshot(&self) -> ThresholdConfigSnapshot {
+        ThresholdConfigSnapshot {
+            defaults: self.defaults(),
+            devices: self
+                .inner
+                .devices
+                .iter()
+                .map(|entry| (entry.key().clone(), *entry.value()))
+                .collect(),
+            sessions: self
+                .inner
+                .sessions
+                .iter()
+                .map(|entry| (*entry.key(), *entry.value()))
+                .collect(),
+        }
+    }
+
+    /// 写回持久化文件（未启用持久化时不做任何事）
+    fn save(&self) -> Result<()> {
+        let store = self.inner.store.lock().unwrap();
+        let Some(path) = store.as_ref() else {
+            return Ok(());
+        };
+
+        let persisted = PersistedThresholds {
+            defaults: Some(self.defaults()),
+            devices: self.snapshot().devices,
+        };
+        write_atomically(path, &serde_json::to_vec_pretty(&persisted)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        debug!("Saved latency thresholds to {}", path.display());
+        Ok(())
+    }
+}
+
+impl Default for ThresholdConfig {
+    fn default() -> Self {
+        Self::new(LatencyThresholds::default())
+    }
+}
+
+/// 先写临时文件再重命名，避免写入中断留下半个文件
+fn write_atomically(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_precedence() {
+        let config = ThresholdConfig::default();
+        let session_id = Uuid::new_v4();
+        config.bind_session(session_id, Some("camera_01".to_string()));
+
+        config
+            .set_device_override(
+                "camera_01",
+                ThresholdOverride {
+                    transmission_ms: Some(400),
+                    end_to_end_ms: Some(600),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        config
+            .set_session_override(
+                session_id,
+                ThresholdOverride {
+                    end_to_end_ms: Some(800),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let effective = config.effective(Some(&session_id), None);
+        assert_eq!(effective.transmission_ms, 400);
+        assert_eq!(effective.processing_ms, 50);
+        assert_eq!(effective.end_to_end_ms, 800);
+
+        // 其他设备只受全局默认值影响
+        assert_eq!(config.effective(None, Some("camera_02")), LatencyThresholds::default());
+
+        // 会话结束后清除会话覆盖
+        config.unbind_session(&session_id);
+        assert!(config.snapshot().sessions.is_empty());
+        assert_eq!(config.effective(Some(&session_id), None).end_to_end_ms, 200);
+
+        assert!(config
+            .update_defaults(ThresholdOverride {
+                processing_ms: Some(0),
+                ..Default::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_persistence_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("thresholds.json");
+
+        let config = ThresholdConfig::default();
+        config.enable_persistence(&path).unwrap();
+        config
+            .update_defaults(ThresholdOverride {
+                processing_ms: Some(30),
+                ..Default::default()
+            })
+            .unwrap();
+        config
+            .set_device_override(
+                "camera_01",
+                ThresholdOverride {
+                    transmission_ms: Some(500),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        config
+            .set_session_override(
+                Uuid::new_v4(),
+                ThresholdOverride {
+                    end_to_end_ms: Some(900),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let restored = ThresholdConfig::default();
+        restored.enable_persistence(&path).unwrap();
+        let snapshot = restored.snapshot();
+        assert_eq!(snapshot.defaults.processing_ms, 30);
+        assert_eq!(snapshot.devices["camera_01"].transmission_ms, Some(500));
+        assert!(snapshot.sessions.is_empty());
+    }
+}
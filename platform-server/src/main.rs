@@ -1,8 +1,10 @@
+mod alerting;
 mod config;
 mod device;
 mod distribution;
 mod http3;
 mod latency;
+mod metrics;
 mod protocol;
 mod quic;
 mod recording;
@@ -31,6 +33,29 @@ async fn main() -> Result<()> {
     let recording_manager = recording::RecordingManager::new(config.storage_root.clone());
     let distribution_manager = distribution::DistributionManager::new();
     let latency_monitor = latency::LatencyMonitor::new();
+    let history = latency::LatencyHistory::open(config.latency_history.clone()).or_else(|e| {
+        tracing::warn!("Failed to open latency history, keeping it in memory: {:#}", e);
+        latency::LatencyHistory::open_in_memory(config.latency_history.clone())
+    });
+    let stream_handler = match history {
+        Ok(history) => {
+            let history = std::sync::Arc::new(history);
+            history.clone().spawn_flush_task();
+            std::sync::Arc::new(streaming::UnifiedStreamHandler::with_latency_history(history))
+        }
+        Err(e) => {
+            tracing::warn!("Latency history disabled: {:#}", e);
+            std::sync::Arc::new(streaming::UnifiedStreamHandler::new())
+        }
+    };
+    if let Err(e) = stream_handler
+        .get_latency_monitor()
+        .get_threshold_config()
+        .enable_persistence(&config.latency_thresholds_path)
+    {
+        tracing::warn!("Latency threshold overrides will not persist: {:#}", e);
+    }
+    let alert_engine = std::sync::Arc::new(config.alerting.build_engine(stream_handler.get_alert_broadcaster())?);
 
     info!("✓ Managers initialized");
 
@@ -41,6 +66,8 @@ async fn main() -> Result<()> {
         device_manager.clone(),
         recording_manager.clone(),
         distribution_manager.clone(),
+        stream_handler.get_latency_monitor(),
+        stream_handler.get_stats_manager(),
     )?;
 
     info!("✓ QUIC server listening on {}", quic_addr);
@@ -53,6 +80,8 @@ async fn main() -> Result<()> {
         recording_manager.clone(),
         distribution_manager.clone(),
         latency_monitor.clone(),
+        stream_handler.clone(),
+        alert_engine.clone(),
     );
 
     info!("✓ HTTP3 server listening on {}", http3_addr);
@@ -84,6 +113,18 @@ async fn main() -> Result<()> {
     
     info!("✓ Latency monitoring statistics update task started");
 
+    // 启动告警规则评估任务
+    alert_engine.spawn_evaluation_task(
+        alerting::AlertSources::new(
+            stream_handler.get_stats_manager(),
+            stream_handler.get_latency_monitor(),
+            device_manager.clone(),
+        ),
+        config.alerting.evaluation_interval,
+    );
+
+    info!("✓ Alert rule evaluation task started");
+
     info!("✅ Platform server ready!");
 
     // 并发运行两个服务器
@@ -0,0 +1,295 @@
+// 指标采集
+//
+// 抓取时从各管理器读取当前状态并编码为 Prometheus 文本格式，
+// 不额外维护指标副本。
+
+use super::encoder::{Labels, TextEncoder};
+use super::histogram::LatencyStage;
+use crate::device::{DeviceManager, QuicConnectionStats};
+use crate::distribution::DistributionManager;
+use crate::streaming::UnifiedStreamHandler;
+
+/// 采集所有指标并编码为 Prometheus 文本格式
+pub async fn render_metrics(
+    device_manager: &DeviceManager,
+    distribution_manager: &DistributionManager,
+    stream_handler: &UnifiedStreamHandler,
+) -> String {
+    let mut encoder = TextEncoder::new();
+
+    encode_latency(&mut encoder, stream_handler);
+    encode_sessions(&mut encoder, distribution_manager, stream_handler).await;
+    encode_devices(&mut encoder, device_manager, stream_handler);
+    encode_quic(&mut encoder, device_manager);
+
+    encoder.finish()
+}
+
+fn session_label(session_id: &uuid::Uuid) -> Labels<'static> {
+    vec![("session_id", session_id.to_string())]
+}
+
+fn device_label(device_id: &str) -> Labels<'static> {
+    vec![("device_id", device_id.to_string())]
+}
+
+/// 各阶段延迟直方图
+fn encode_latency(encoder: &mut TextEncoder, stream_handler: &UnifiedStreamHandler) {
+    let histograms = stream_handler.get_latency_monitor().get_histograms();
+    let samples: Vec<_> = LatencyStage::ALL
+        .iter()
+        .map(|stage| {
+            (
+                vec![("stage", stage.as_str().to_string())],
+                histograms.get(*stage).snapshot(),
+            )
+        })
+        .collect();
+
+    encoder.histogram(
+        "platform_latency_seconds",
+        "Segment latency per pipeline stage (transmission, processing, distribution, end_to_end).",
+        &samples,
+    );
+}
+
+/// 会话、订阅者、吞吐量与丢包
+async fn encode_sessions(
+    encoder: &mut TextEncoder,
+    distribution_manager: &DistributionManager,
+    stream_handler: &UnifiedStreamHandler,
+) {
+    encoder.gauge(
+        "platform_active_sessions",
+        "Active sessions by component.",
+        &[
+            (
+                vec![("component", "stream_handler".to_string())],
+                stream_handler.active_sessions() as f64,
+            ),
+            (
+                vec![("component", "distribution".to_string())],
+                distribution_manager.active_sessions() as f64,
+            ),
+        ],
+    );
+
+    let subscribers: Vec<_> = distribution_manager
+        .subscriber_counts()
+        .iter()
+        .map(|(session_id, count)| (session_label(session_id), *count as f64))
+        .collect();
+    encoder.gauge(
+        "platform_session_subscribers",
+        "Receivers subscribed to a distribution session.",
+        &subscribers,
+    );
+
+    let clients: Vec<_> = stream_handler
+        .session_client_counts()
+        .await
+        .iter()
+        .map(|(session_id, count)| (session_label(session_id), *count as f64))
+        .collect();
+    encoder.gauge(
+        "platform_stream_clients",
+        "Clients subscribed to a stream handler session.",
+        &clients,
+    );
+
+    let statistics = stream_handler.get_stats_manager().get_all_statistics();
+    let per_session = |value: fn(&crate::latency::LatencyStatistics) -> f64| -> Vec<_> {
+        statistics
+            .iter()
+            .map(|stats| (session_label(&stats.session_id), value(stats)))
+            .collect()
+    };
+    encoder.gauge(
+        "platform_stream_throughput_mbps",
+        "Forwarding throughput per session in Mbit/s.",
+        &per_session(|s| s.throughput_mbps),
+    );
+    encoder.gauge(
+        "platform_stream_packet_loss_ratio",
+        "Lost segments (device sequence gaps) / expected segments per session.",
+        &per_session(|s| s.packet_loss_rate),
+    );
+    encoder.counter(
+        "platform_stream_segments_total",
+        "Segments forwarded per session.",
+        &per_session(|s| s.total_segments as f64),
+    );
+    encoder.counter(
+        "platform_stream_bytes_total",
+        "Bytes forwarded per session.",
+        &per_session(|s| s.total_bytes as f64),
+    );
+
+    encoder.counter(
+        "platform_segments_dropped_total",
+        "Segments skipped because a receiver fell behind its broadcast channel.",
+        &[
+            (
+                vec![("reason", "client_lag".to_string())],
+                distribution_manager.client_lagged_segments() as f64,
+            ),
+            (
+                vec![("reason", "source_lag".to_string())],
+                distribution_manager.source_lagged_segments() as f64,
+            ),
+        ],
+    );
+    encoder.counter(
+        "platform_timestamp_discontinuities_total",
+        "Device timestamp discontinuities rebased by live sources.",
+        &[(vec![], distribution_manager.timestamp_discontinuities() as f64)],
+    );
+}
+
+/// 设备在线状态与传输抖动
+fn encode_devices(
+    encoder: &mut TextEncoder,
+    device_manager: &DeviceManager,
+    stream_handler: &UnifiedStreamHandler,
+) {
+    encoder.gauge(
+        "platform_devices",
+        "Registered devices by connection status.",
+        &[
+            (
+                vec![("status", "online".to_string())],
+                device_manager.get_online_devices().len() as f64,
+            ),
+            (
+                vec![("status", "registered".to_string())],
+                device_manager.get_all_devices().len() as f64,
+            ),
+        ],
+    );
+
+    let transmission = stream_handler
+        .get_latency_monitor()
+        .get_all_device_transmission();
+    let jitter: Vec<_> = transmission
+        .iter()
+        .map(|stats| (device_label(&stats.device_id), stats.jitter_ms / 1000.0))
+        .collect();
+    encoder.gauge(
+        "platform_device_jitter_seconds",
+        "Interarrival jitter of device segments (RFC 3550 estimate).",
+        &jitter,
+    );
+    let gaps: Vec<_> = transmission
+        .iter()
+        .map(|stats| (device_label(&stats.device_id), stats.sequence_gaps as f64))
+        .collect();
+    encoder.counter(
+        "platform_device_sequence_gaps_total",
+        "Missing segment sequence numbers observed per device.",
+        &gaps,
+    );
+}
+
+/// QUIC 连接统计（来自 quinn）
+fn encode_quic(encoder: &mut TextEncoder, device_manager: &DeviceManager) {
+    let connections = device_manager.connection_stats();
+
+    encoder.gauge(
+        "platform_quic_connections",
+        "Open QUIC connections from devices.",
+        &[(vec![], connections.len() as f64)],
+    );
+
+    let per_device = |value: fn(&QuicConnectionStats) -> f64| -> Vec<_> {
+        connections
+            .iter()
+            .map(|stats| (device_label(&stats.device_id), value(stats)))
+            .collect()
+    };
+    encoder.gauge(
+        "platform_quic_rtt_seconds",
+        "Smoothed QUIC round-trip time.",
+        &per_device(|s| s.rtt.as_secs_f64()),
+    );
+    encoder.gauge(
+        "platform_quic_cwnd_bytes",
+        "QUIC congestion window.",
+        &per_device(|s| s.cwnd as f64),
+    );
+    encoder.counter(
+        "platform_quic_congestion_events_total",
+        "QUIC congestion events.",
+        &per_device(|s| s.congestion_events as f64),
+    );
+    encoder.counter(
+        "platform_quic_sent_packets_total",
+        "QUIC packets sent.",
+        &per_device(|s| s.sent_packets as f64),
+    );
+    encoder.counter(
+        "platform_quic_lost_packets_total",
+        "QUIC packets declared lost.",
+        &per_device(|s| s.lost_packets as f64),
+    );
+    encoder.counter(
+        "platform_quic_rx_bytes_total",
+        "UDP bytes received on the QUIC connection.",
+        &per_device(|s| s.rx_bytes as f64),
+    );
+    encoder.counter(
+        "platform_quic_tx_bytes_total",
+        "UDP bytes sent on the QUIC connection.",
+        &per_device(|s| s.tx_bytes as f64),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_render_metrics() {
+        let device_manager = DeviceManager::new();
+        let distribution_manager = DistributionManager::new();
+        let stream_handler = UnifiedStreamHandler::new();
+
+        let session_id = Uuid::new_v4();
+        let _rx = distribution_manager.create_session(session_id);
+        distribution_manager.record_client_lag(4);
+
+        let monitor = stream_handler.get_latency_monitor();
+        let segment_id = Uuid::new_v4();
+        let now = SystemTime::now();
+        monitor.record_platform_receive(segment_id, now);
+        monitor.record_platform_forward(segment_id, now + Duration::from_millis(2));
+
+        // 设备序号缺口计入会话丢包率
+        let stats_manager = stream_handler.get_stats_manager();
+        stats_manager.start_session(session_id);
+        stats_manager.record_segment_latency(&session_id, Duration::from_millis(5), 1024);
+        stats_manager.record_lost_segments(&session_id, 1);
+
+        let text = render_metrics(&device_manager, &distribution_manager, &stream_handler).await;
+
+        assert!(text.contains("# TYPE platform_latency_seconds histogram"));
+        assert!(text.contains(
+            "platform_latency_seconds_count{stage=\"processing\"} 1\n"
+        ));
+        assert!(text.contains(
+            "platform_latency_seconds_count{stage=\"end_to_end\"} 0\n"
+        ));
+        assert!(text.contains(&format!(
+            "platform_session_subscribers{{session_id=\"{}\"}} 1\n",
+            session_id
+        )));
+        assert!(text.contains(&format!(
+            "platform_stream_packet_loss_ratio{{session_id=\"{}\"}} 0.5\n",
+            session_id
+        )));
+        assert!(text.contains("platform_segments_dropped_total{reason=\"client_lag\"} 4\n"));
+        assert!(text.contains("platform_devices{status=\"online\"} 0\n"));
+        assert!(text.contains("platform_quic_connections 0\n"));
+    }
+}
@@ -0,0 +1,151 @@
+// Prometheus 文本格式编码
+//
+// 按 Prometheus exposition format 0.0.4 输出指标族：
+// 每个指标族先输出 `# HELP` 与 `# TYPE`，随后输出样本。
+
+use super::histogram::HistogramSnapshot;
+use std::fmt::Write;
+
+/// Prometheus 文本格式的 Content-Type
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 指标标签
+pub type Labels<'a> = Vec<(&'a str, String)>;
+
+/// 文本格式编码器
+#[derive(Default)]
+pub struct TextEncoder {
+    output: String,
+}
+
+impl TextEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输出 gauge 指标族
+    pub fn gauge(&mut self, name: &str, help: &str, samples: &[(Labels<'_>, f64)]) {
+        self.family(name, help, "gauge", samples);
+    }
+
+    /// 输出 counter 指标族（名称需以 `_total` 结尾）
+    pub fn counter(&mut self, name: &str, help: &str, samples: &[(Labels<'_>, f64)]) {
+        self.family(name, help, "counter", samples);
+    }
+
+    /// 输出 histogram 指标族
+    pub fn histogram(&mut self, name: &str, help: &str, samples: &[(Labels<'_>, HistogramSnapshot)]) {
+        self.header(name, help, "histogram");
+        for (labels, snapshot) in samples {
+            for (bound, count) in &snapshot.buckets {
+                let mut bucket_labels = labels.clone();
+                bucket_labels.push(("le", format_value(*bound)));
+                self.sample(&format!("{}_bucket", name), &bucket_labels, *count as f64);
+            }
+            let mut inf_labels = labels.clone();
+            inf_labels.push(("le", "+Inf".to_string()));
+            self.sample(&format!("{}_bucket", name), &inf_labels, snapshot.count as f64);
+            self.sample(&format!("{}_sum", name), labels, snapshot.sum);
+            self.sample(&format!("{}_count", name), labels, snapshot.count as f64);
+        }
+    }
+
+    /// 完成编码
+    pub fn finish(self) -> String {
+        self.output
+    }
+
+    fn family(&mut self, name: &str, help: &str, kind: &str, samples: &[(Labels<'_>, f64)]) {
+        self.header(name, help, kind);
+        for (labels, value) in samples {
+            self.sample(name, labels, *value);
+        }
+    }
+
+    fn header(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.output, "# HELP {} {}", name, escape_help(help));
+        let _ = writeln!(self.output, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, String)], value: f64) {
+        self.output.push_str(name);
+        if !labels.is_empty() {
+            self.output.push('{');
+            for (i, (key, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.output.push(',');
+                }
+                let _ = write!(self.output, "{}=\"{}\"", key, escape_label(value));
+            }
+            self.output.push('}');
+        }
+        let _ = writeln!(self.output, " {}", format_value(value));
+    }
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_families() {
+        let mut encoder = TextEncoder::new();
+        encoder.gauge("platform_devices_online", "Online devices", &[(vec![], 3.0)]);
+        encoder.counter(
+            "platform_segments_dropped_total",
+            "Dropped segments",
+            &[(vec![("reason", "client \"lag\"".to_string())], 7.0)],
+        );
+        encoder.histogram(
+            "platform_latency_seconds",
+            "Latency",
+            &[(
+                vec![("stage", "processing".to_string())],
+                HistogramSnapshot {
+                    buckets: vec![(0.005, 1), (0.01, 2)],
+                    sum: 0.012,
+                    count: 3,
+                },
+            )],
+        );
+
+        let text = encoder.finish();
+        let expected = "\
+# HELP platform_devices_online Online devices
+# TYPE platform_devices_online gauge
+platform_devices_online 3
+# HELP platform_segments_dropped_total Dropped segments
+# TYPE platform_segments_dropped_total counter
+platform_segments_dropped_total{reason=\"client \\\"lag\\\"\"} 7
+# HELP platform_latency_seconds Latency
+# TYPE platform_latency_seconds histogram
+platform_latency_seconds_bucket{stage=\"processing\",le=\"0.005\"} 1
+platform_latency_seconds_bucket{stage=\"processing\",le=\"0.01\"} 2
+platform_latency_seconds_bucket{stage=\"processing\",le=\"+Inf\"} 3
+platform_latency_seconds_sum{stage=\"processing\"} 0.012
+platform_latency_seconds_count{stage=\"processing\"} 3
+";
+        assert_eq!(text, expected);
+    }
+}
@@ -0,0 +1,162 @@
+// 延迟直方图
+//
+// 固定桶边界的累积直方图，使用原子计数，可在热路径上无锁记录。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// 延迟直方图桶上界（秒）
+pub const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0,
+];
+
+/// 延迟直方图
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    /// 各桶计数（非累积）
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    /// 观测值总和（微秒）
+    sum_us: AtomicU64,
+    /// 观测次数
+    count: AtomicU64,
+}
+
+/// 直方图快照
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSnapshot {
+    /// (桶上界, 累积计数)
+    pub buckets: Vec<(f64, u64)>,
+    /// 观测值总和（秒）
+    pub sum: f64,
+    /// 观测次数
+    pub count: u64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            sum_us: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录一次观测
+    pub fn observe(&self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.sum_us
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 获取快照（桶计数转换为累积值）
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = LATENCY_BUCKETS
+            .iter()
+            .zip(self.buckets.iter())
+            .map(|(bound, bucket)| {
+                cumulative += bucket.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+
+        HistogramSnapshot {
+            buckets,
+            sum: self.sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+            count: self.count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 延迟链路阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyStage {
+    /// 设备→平台
+    Transmission,
+    /// 平台接收→转发
+    Processing,
+    /// 平台转发→前端播放
+    Distribution,
+    /// 设备发送→前端播放
+    EndToEnd,
+}
+
+impl LatencyStage {
+    pub const ALL: [LatencyStage; 4] = [
+        LatencyStage::Transmission,
+        LatencyStage::Processing,
+        LatencyStage::Distribution,
+        LatencyStage::EndToEnd,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LatencyStage::Transmission => "transmission",
+            LatencyStage::Processing => "processing",
+            LatencyStage::Distribution => "distribution",
+            LatencyStage::EndToEnd => "end_to_end",
+        }
+    }
+}
+
+/// 各阶段的延迟直方图
+#[derive(Debug, Default)]
+pub struct StageHistograms {
+    transmission: LatencyHistogram,
+    processing: LatencyHistogram,
+    distribution: LatencyHistogram,
+    end_to_end: LatencyHistogram,
+}
+
+impl StageHistograms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, stage: LatencyStage) -> &LatencyHistogram {
+        match stage {
+            LatencyStage::Transmission => &self.transmission,
+            LatencyStage::Processing => &self.processing,
+            LatencyStage::Distribution => &self.distribution,
+            LatencyStage::EndToEnd => &self.end_to_end,
+        }
+    }
+
+    pub fn observe(&self, stage: LatencyStage, latency: Duration) {
+        self.get(stage).observe(latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_cumulative_buckets() {
+        let histogram = LatencyHistogram::new();
+        histogram.observe(Duration::from_micros(500));
+        histogram.observe(Duration::from_millis(5));
+        histogram.observe(Duration::from_millis(80));
+        histogram.observe(Duration::from_secs(30));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 4);
+        assert_eq!(snapshot.buckets[0], (0.001, 1));
+        // 边界值落入当前桶
+        assert_eq!(snapshot.buckets[1], (0.005, 2));
+        assert_eq!(snapshot.buckets[5], (0.1, 3));
+        // 超出最大边界的观测只计入 +Inf
+        assert_eq!(snapshot.buckets.last().unwrap().1, 3);
+        assert!((snapshot.sum - 30.0855).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,11 @@
+// Prometheus 指标导出
+//
+// 通过 GET /metrics 以 Prometheus 文本格式导出流、延迟、设备与 QUIC 连接指标。
+
+mod collector;
+mod encoder;
+mod histogram;
+
+pub use collector::render_metrics;
+pub use encoder::CONTENT_TYPE;
+pub use histogram::{LatencyStage, StageHistograms};
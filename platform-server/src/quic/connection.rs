@@ -1,47 +1,254 @@
 use crate::device::DeviceManager;
 use crate::distribution::DistributionManager;
+use crate::latency::{ClockSample, EndToEndLatencyMonitor, LatencyStatisticsManager};
 use crate::recording::RecordingManager;
+use super::reorder::{MediaLane, MediaReorder};
+use common::utils::current_timestamp_us;
 use common::{
-    DeviceCapabilities, DeviceInfo, DeviceType, ConnectionStatus, MessageType, 
-    ProtocolMessage, VideoSegment, Result, VideoStreamError,
+    ClockSyncRequest, ClockSyncResponse, DeviceCapabilities, DeviceInfo, DeviceType,
+    ConnectionStatus, FeatureFlags, FileUploadAck, FileUploadChunk, FileUploadOffer,
+    FileUploadStatus, MessageType, NegotiatedProtocol, ProtocolMessage, MAX_MEDIA_FRAME_SIZE,
+    MEDIA_KEYFRAME_STREAM_MAGIC, MEDIA_STREAM_MAGIC, media_frame_length,
+    ProtocolVersionRange, SessionStartPayload, SessionStartRequest, SessionStartResponse,
+    Result, VideoSegment, VideoStreamError,
 };
-use quinn::Connection;
-use std::time::SystemTime;
+use quinn::{Connection, ReadExactError, RecvStream, SendStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// 启动时连续进行的时钟同步次数
+const CLOCK_SYNC_BURST: usize = 4;
+/// 周期性时钟同步间隔
+const CLOCK_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 连接级状态（由 SessionStart 填充，供单向流处理使用）
+#[derive(Debug, Clone)]
+struct ConnectionState {
+    protocol: NegotiatedProtocol,
+    device_id: String,
+}
+
 pub async fn handle_connection(
     connection: Connection,
     device_manager: DeviceManager,
-    _recording_manager: RecordingManager,
+    recording_manager: RecordingManager,
     distribution_manager: DistributionManager,
+    latency_monitor: Arc<EndToEndLatencyMonitor>,
+    stats_manager: Arc<LatencyStatisticsManager>,
 ) -> Result<()> {
     let session_id = Uuid::new_v4();
     info!("Handling connection with session: {}", session_id);
 
+    // SessionStart 之前按旧版协议处理
+    let state = Arc::new(RwLock::new(ConnectionState {
+        protocol: NegotiatedProtocol::LEGACY,
+        device_id: format!("device_{}", session_id),
+    }));
+
     // 处理双向流（控制信令）
     let conn_clone = connection.clone();
     let device_mgr_clone = device_manager.clone();
+    let state_clone = state.clone();
+    let monitor_clone = latency_monitor.clone();
     tokio::spawn(async move {
-        if let Err(e) = handle_bi_streams(conn_clone, device_mgr_clone, session_id).await {
+        if let Err(e) = handle_bi_streams(
+            conn_clone,
+            device_mgr_clone,
+            recording_manager,
+            session_id,
+            state_clone,
+            monitor_clone,
+        )
+        .await
+        {
             error!("Bi-stream error: {}", e);
         }
     });
 
     // 处理单向流（视频数据）
-    handle_uni_streams(connection, device_manager, distribution_manager, session_id).await
+    handle_uni_streams(
+        connection,
+        device_manager,
+        distribution_manager,
+        session_id,
+        state,
+        latency_monitor,
+        stats_manager,
+    )
+    .await
+}
+
+/// 根据设备支持的版本范围选定协议版本
+fn negotiate_session(request: &SessionStartRequest) -> SessionStartResponse {
+    let supported = ProtocolVersionRange::supported();
+    match supported.negotiate(&request.protocol_versions) {
+        Some(version) => SessionStartResponse {
+            accepted: true,
+            selected_version: version,
+            supported_versions: supported,
+            features: request.features & FeatureFlags::ALL,
+            error_message: None,
+        },
+        None => SessionStartResponse {
+            accepted: false,
+            selected_version: supported.max,
+            supported_versions: supported,
+            features: 0,
+            error_message: Some(format!(
+                "No common protocol version: device supports {}-{}, platform supports {}-{}",
+                request.protocol_versions.min, request.protocol_versions.max, supported.min, supported.max
+            )),
+        },
+    }
+}
+
+/// 无法解析设备信息时使用的默认设备
+fn fallback_device(session_id: Uuid) -> DeviceInfo {
+    DeviceInfo {
+        device_id: format!("device_{}", session_id),
+        device_name: "Unknown Device".to_string(),
+        device_type: DeviceType::Simulator,
+        connection_status: ConnectionStatus::Online,
+        connection_time: SystemTime::now(),
+        last_heartbeat: SystemTime::now(),
+        capabilities: DeviceCapabilities {
+            max_resolution: "1920x1080".to_string(),
+            supported_formats: vec!["h264".to_string(), "mp4".to_string()],
+            max_bitrate: 10_000_000,
+            supports_playback_control: true,
+            supports_recording: true,
+            renditions: Vec::new(),
+        },
+    }
+}
+
+async fn send_session_response(
+    send: &mut SendStream,
+    session_id: Uuid,
+    response: &SessionStartResponse,
+) -> Result<()> {
+    let payload = bincode::serialize(response)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+    let message = ProtocolMessage {
+        message_type: MessageType::SessionStartResponse,
+        payload,
+        sequence_number: 0,
+        timestamp: SystemTime::now(),
+        session_id,
+    };
+    let data = bincode::serialize(&message)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+
+    send.write_all(&data)
+        .await
+        .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+    send.finish()
+        .await
+        .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+    Ok(())
+}
+
+/// 与设备进行一次 NTP 式时钟同步交换
+async fn exchange_clock_sync(connection: &Connection, session_id: Uuid) -> Result<ClockSample> {
+    let t0_us = current_timestamp_us();
+    let payload = bincode::serialize(&ClockSyncRequest { t0_us })
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+    let message = ProtocolMessage {
+        message_type: MessageType::ClockSync,
+        payload,
+        sequence_number: 0,
+        timestamp: SystemTime::now(),
+        session_id,
+    };
+    let data = bincode::serialize(&message)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+
+    let (mut send, mut recv) = connection
+        .open_bi()
+        .await
+        .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+    send.write_all(&data)
+        .await
+        .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+    send.finish()
+        .await
+        .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+
+    let buf = recv
+        .read_to_end(64 * 1024)
+        .await
+        .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+    let t3_us = current_timestamp_us();
+
+    let reply = bincode::deserialize::<ProtocolMessage>(&buf)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+    let response = bincode::deserialize::<ClockSyncResponse>(&reply.payload)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+    if response.t0_us != t0_us {
+        return Err(VideoStreamError::ProtocolError("Clock sync response mismatch".to_string()));
+    }
+
+    ClockSample::from_exchange(t0_us, response.t1_us, response.t2_us, t3_us)
+        .ok_or_else(|| VideoStreamError::ProtocolError("Invalid clock sync timestamps".to_string()))
+}
+
+/// 周期性估算设备时钟偏移，直到连接关闭
+async fn run_clock_sync(
+    connection: Connection,
+    device_id: String,
+    session_id: Uuid,
+    latency_monitor: Arc<EndToEndLatencyMonitor>,
+) {
+    let clock_sync = latency_monitor.get_clock_sync();
+    let mut round = 0usize;
+
+    while connection.close_reason().is_none() {
+        match exchange_clock_sync(&connection, session_id).await {
+            Ok(sample) => clock_sync.record_sample(&device_id, connection.stable_id(), sample),
+            Err(e) => warn!("Clock sync with device {} failed: {}", device_id, e),
+        }
+
+        round += 1;
+        if round == CLOCK_SYNC_BURST {
+            if let Some(estimate) = clock_sync.get_estimate(&device_id) {
+                info!(
+                    "Clock offset for device {}: {}us (rtt {}us)",
+                    device_id, estimate.offset_us, estimate.rtt_us
+                );
+            }
+        }
+
+        let delay = if round < CLOCK_SYNC_BURST {
+            Duration::from_millis(200)
+        } else {
+            CLOCK_SYNC_INTERVAL
+        };
+        tokio::time::sleep(delay).await;
+    }
+
+    clock_sync.remove_device(&device_id, connection.stable_id());
+    debug!("Clock sync stopped for device {}", device_id);
 }
 
 async fn handle_bi_streams(
     connection: Connection,
     device_manager: DeviceManager,
+    recording_manager: RecordingManager,
     session_id: Uuid,
+    state: Arc<RwLock<ConnectionState>>,
+    latency_monitor: Arc<EndToEndLatencyMonitor>,
 ) -> Result<()> {
     loop {
         match connection.accept_bi().await {
             Ok((mut send, mut recv)) => {
                 let device_mgr = device_manager.clone();
+                let recording_mgr = recording_manager.clone();
                 let conn = connection.clone();
+                let state = state.clone();
+                let latency_monitor = latency_monitor.clone();
                 tokio::spawn(async move {
                     // 读取消息
                     let buf = match recv.read_to_end(1024 * 1024).await {
@@ -60,58 +267,86 @@ async fn handle_bi_streams(
                             // 处理消息
                             match msg.message_type {
                                 MessageType::SessionStart => {
-                                    // 从 payload 中反序列化设备信息
-                                    match bincode::deserialize::<DeviceInfo>(&msg.payload) {
-                                        Ok(mut device) => {
-                                            // 更新连接状态和时间
-                                            device.connection_status = ConnectionStatus::Online;
-                                            device.connection_time = SystemTime::now();
-                                            device.last_heartbeat = SystemTime::now();
-                                            
-                                            let device_id = device.device_id.clone();
-                                            
-                                            // 注册设备
-                                            if let Err(e) = device_mgr.register_device(device) {
-                                                error!("Failed to register device: {}", e);
-                                            } else {
-                                                info!("✓ Device registered: {}", device_id);
+                                    let (device, response) =
+                                        match common::decode_session_start(&msg.payload) {
+                                            Ok(SessionStartPayload::Negotiated(request)) => {
+                                                let response = negotiate_session(&request);
+                                                (request.into_device_info(), Some(response))
+                                            }
+                                            Ok(SessionStartPayload::Legacy(device)) => {
+                                                info!("Legacy device without version negotiation: {}", device.device_id);
+                                                (device, None)
                                             }
-                                            
-                                            // 保存连接
-                                            device_mgr.store_connection(device_id, conn);
-                                            
-                                            // 发送响应
-                                            let response = b"OK";
-                                            let _ = send.write_all(response).await;
+                                            Err(e) => {
+                                                error!("Failed to deserialize device info: {}", e);
+                                                // 使用默认设备信息作为后备
+                                                (fallback_device(session_id), None)
+                                            }
+                                        };
+
+                                    let protocol = match &response {
+                                        Some(resp) if !resp.accepted => {
+                                            warn!(
+                                                "Rejecting device {}: {}",
+                                                device.device_id,
+                                                resp.error_message.as_deref().unwrap_or("unsupported protocol")
+                                            );
+                                            let _ = send_session_response(&mut send, msg.session_id, resp).await;
+                                            conn.close(1u32.into(), b"unsupported protocol version");
+                                            return;
                                         }
-                                        Err(e) => {
-                                            error!("Failed to deserialize device info: {}", e);
-                                            // 使用默认设备信息作为后备
-                                            let device_id = format!("device_{}", session_id);
-                                            let device = DeviceInfo {
-                                                device_id: device_id.clone(),
-                                                device_name: "Unknown Device".to_string(),
-                                                device_type: DeviceType::Simulator,
-                                                connection_status: ConnectionStatus::Online,
-                                                connection_time: SystemTime::now(),
-                                                last_heartbeat: SystemTime::now(),
-                                                capabilities: DeviceCapabilities {
-                                                    max_resolution: "1920x1080".to_string(),
-                                                    supported_formats: vec!["h264".to_string(), "mp4".to_string()],
-                                                    max_bitrate: 10_000_000,
-                                                    supports_playback_control: true,
-                                                    supports_recording: true,
-                                                },
-                                            };
-                                            
-                                            if let Err(e) = device_mgr.register_device(device) {
-                                                error!("Failed to register fallback device: {}", e);
+                                        Some(resp) => NegotiatedProtocol {
+                                            version: resp.selected_version,
+                                            features: resp.features,
+                                        },
+                                        None => NegotiatedProtocol::LEGACY,
+                                    };
+                                    *state.write().await = ConnectionState {
+                                        protocol,
+                                        device_id: device.device_id.clone(),
+                                    };
+
+                                    let mut device = device;
+                                    // 更新连接状态和时间
+                                    device.connection_status = ConnectionStatus::Online;
+                                    device.connection_time = SystemTime::now();
+                                    device.last_heartbeat = SystemTime::now();
+
+                                    let device_id = device.device_id.clone();
+
+                                    // 注册设备
+                                    if let Err(e) = device_mgr.register_device(device) {
+                                        error!("Failed to register device: {}", e);
+                                    } else {
+                                        info!("✓ Device registered: {} (protocol {})", device_id, protocol.version);
+                                    }
+
+                                    // 保存连接
+                                    device_mgr.store_protocol(device_id.clone(), protocol);
+                                    device_mgr.store_connection(device_id.clone(), conn.clone());
+
+                                    // 发送响应：协商设备回复选定版本，旧版设备保持 "OK"
+                                    match &response {
+                                        Some(resp) => {
+                                            if let Err(e) = send_session_response(&mut send, msg.session_id, resp).await {
+                                                error!("Failed to send session start response: {}", e);
                                             }
-                                            
-                                            device_mgr.store_connection(device_id, conn);
-                                            let response = b"OK";
-                                            let _ = send.write_all(response).await;
                                         }
+                                        None => {
+                                            let _ = send.write_all(b"OK").await;
+                                        }
+                                    }
+
+                                    // 支持时钟同步的设备：估算时钟偏移以计算真实传输延迟
+                                    if protocol.supports(FeatureFlags::CLOCK_SYNC) {
+                                        tokio::spawn(run_clock_sync(conn, device_id, session_id, latency_monitor));
+                                    }
+                                }
+                                MessageType::FileUploadOffer | MessageType::FileUploadChunk => {
+                                    let device_id = state.read().await.device_id.clone();
+                                    let ack = handle_upload_message(&recording_mgr, &device_id, &msg).await;
+                                    if let Err(e) = send_upload_ack(&mut send, msg.session_id, &ack).await {
+                                        error!("Failed to send upload ack: {}", e);
                                     }
                                 }
                                 _ => {
@@ -134,57 +369,87 @@ async fn handle_bi_streams(
     Ok(())
 }
 
+/// 处理设备文件上传请求或分块，出错时回复 `Rejected`
+async fn handle_upload_message(
+    recording_manager: &RecordingManager,
+    device_id: &str,
+    msg: &ProtocolMessage,
+) -> FileUploadAck {
+    let uploads = recording_manager.uploads();
+    let (upload_id, result) = if msg.message_type == MessageType::FileUploadOffer {
+        match bincode::deserialize::<FileUploadOffer>(&msg.payload) {
+            Ok(offer) => (Uuid::nil(), uploads.offer(device_id, &offer).await),
+            Err(e) => (Uuid::nil(), Err(VideoStreamError::BincodeError(e.to_string()))),
+        }
+    } else {
+        match bincode::deserialize::<FileUploadChunk>(&msg.payload) {
+            Ok(chunk) => (chunk.upload_id, uploads.write_chunk(device_id, &chunk).await),
+            Err(e) => (Uuid::nil(), Err(VideoStreamError::BincodeError(e.to_string()))),
+        }
+    };
+
+    result.unwrap_or_else(|e| {
+        warn!("Upload from device {} rejected: {}", device_id, e);
+        FileUploadAck {
+            upload_id,
+            acked_offset: 0,
+            status: FileUploadStatus::Rejected,
+            error_message: Some(e.to_string()),
+        }
+    })
+}
+
+async fn send_upload_ack(send: &mut SendStream, session_id: Uuid, ack: &FileUploadAck) -> Result<()> {
+    let payload = bincode::serialize(ack)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+    let message = ProtocolMessage {
+        message_type: MessageType::FileUploadAck,
+        payload,
+        sequence_number: 0,
+        timestamp: SystemTime::now(),
+        session_id,
+    };
+    let data = bincode::serialize(&message)
+        .map_err(|e| VideoStreamError::BincodeError(e.to_string()))?;
+
+    send.write_all(&data)
+        .await
+        .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+    send.finish()
+        .await
+        .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+    Ok(())
+}
+
 async fn handle_uni_streams(
     connection: Connection,
     device_manager: DeviceManager,
     distribution_manager: DistributionManager,
     session_id: Uuid,
+    state: Arc<RwLock<ConnectionState>>,
+    latency_monitor: Arc<EndToEndLatencyMonitor>,
+    stats_manager: Arc<LatencyStatisticsManager>,
 ) -> Result<()> {
-    // 不在这里创建会话，会话由 start_playback 创建
-    let device_id = format!("device_{}", session_id);
+    let receiver = SegmentReceiver {
+        device_manager: device_manager.clone(),
+        distribution_manager: distribution_manager.clone(),
+        state: state.clone(),
+        latency_monitor,
+        stats_manager,
+        reorder: Arc::new(Mutex::new(MediaReorder::default())),
+    };
 
+    // 非参考帧可能以 DATAGRAM 到达
+    let datagram_task = tokio::spawn(receiver.clone().receive_datagrams(connection.clone()));
+
+    // 不在这里创建会话，会话由 start_playback 创建
     loop {
         match connection.accept_uni().await {
-            Ok(mut recv) => {
-                let dist_mgr = distribution_manager.clone();
-                let dev_mgr = device_manager.clone();
-                let dev_id = device_id.clone();
+            Ok(recv) => {
+                let receiver = receiver.clone();
                 tokio::spawn(async move {
-                    match recv.read_to_end(10 * 1024 * 1024).await {
-                        Ok(buf) => {
-                            debug!("Received {} bytes", buf.len());
-                            
-                            // 尝试解析为协议消息（心跳等）
-                            if let Ok(msg) = bincode::deserialize::<ProtocolMessage>(&buf) {
-                                match msg.message_type {
-                                    MessageType::Heartbeat => {
-                                        debug!("Received heartbeat from device: {}", dev_id);
-                                        // 更新设备心跳时间
-                                        let _ = dev_mgr.update_heartbeat(&dev_id);
-                                        return;
-                                    }
-                                    _ => {
-                                        debug!("Received protocol message: {:?}", msg.message_type);
-                                    }
-                                }
-                            }
-                            
-                            // 尝试解析为视频分片
-                            match bincode::deserialize::<VideoSegment>(&buf) {
-                                Ok(segment) => {
-                                    let seg_session_id = segment.session_id;
-                                    debug!("Received segment: {} for session: {}", segment.segment_id, seg_session_id);
-                                    // 使用分片中的 session_id 来分发（而不是连接的 session_id）
-                                    let _ = dist_mgr.distribute_segment(&seg_session_id, segment);
-                                }
-                                Err(e) => {
-                                    debug!("Failed to deserialize as segment: {}", e);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed to read stream: {}", e);
-                        }
+                    if let Err(e) = receiver.receive_stream(recv).await {
+                        error!("Failed to read stream: {}", e);
                     }
                 });
             }
@@ -194,7 +459,224 @@ async fn handle_uni_streams(
             }
         }
     }
+    datagram_task.abort();
 
     distribution_manager.close_session(&session_id);
+
+    // 连接断开后标记设备离线（设备已用新连接重连时保持在线）
+    let device_id = state.read().await.device_id.clone();
+    let replaced = device_manager
+        .get_connection(&device_id)
+        .is_some_and(|current| current.stable_id() != connection.stable_id());
+    if !replaced && device_manager.set_device_offline(&device_id).is_ok() {
+        info!("Device {} disconnected, marked offline", device_id);
+    }
     Ok(())
 }
+
+/// 设备上行分片的接收与分发
+#[derive(Clone)]
+struct SegmentReceiver {
+    device_manager: DeviceManager,
+    distribution_manager: DistributionManager,
+    state: Arc<RwLock<ConnectionState>>,
+    latency_monitor: Arc<EndToEndLatencyMonitor>,
+    stats_manager: Arc<LatencyStatisticsManager>,
+    /// 关键帧流分片和 DATAGRAM 帧可能先于普通帧到达，分发前按会话重排
+    reorder: Arc<Mutex<MediaReorder>>,
+}
+
+impl SegmentReceiver {
+    /// 读取一条单向流：会话媒体流逐帧分发，旧版流整条读出后按单个消息处理
+    async fn receive_stream(&self, mut recv: RecvStream) -> Result<()> {
+        let mut prefix = [0u8; 4];
+        recv.read_exact(&mut prefix)
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        if prefix == MEDIA_STREAM_MAGIC {
+            return self.receive_media_stream(recv, MediaLane::Stream).await;
+        }
+        if prefix == MEDIA_KEYFRAME_STREAM_MAGIC {
+            return self.receive_media_stream(recv, MediaLane::KeyframeStream).await;
+        }
+
+        let rest = recv
+            .read_to_end(MAX_MEDIA_FRAME_SIZE)
+            .await
+            .map_err(|e| VideoStreamError::QuicError(e.to_string()))?;
+        let receive_time = SystemTime::now();
+        let mut buf = prefix.to_vec();
+        buf.extend_from_slice(&rest);
+        debug!("Received {} bytes", buf.len());
+
+        // 尝试解析为协议消息（心跳等）
+        if let Ok(msg) = bincode::deserialize::<ProtocolMessage>(&buf) {
+            match msg.message_type {
+                MessageType::Heartbeat => {
+                    let device_id = self.state.read().await.device_id.clone();
+                    debug!("Received heartbeat from device: {}", device_id);
+                    // 更新设备心跳时间
+                    let _ = self.device_manager.update_heartbeat(&device_id);
+                    return Ok(());
+                }
+                _ => {
+                    debug!("Received protocol message: {:?}", msg.message_type);
+                }
+            }
+        }
+
+        self.deliver(&buf, receive_time, MediaLane::Stream).await;
+        Ok(())
+    }
+
+    /// 读取会话媒体流中连续的长度前缀分片，设备结束流时返回
+    async fn receive_media_stream(&self, mut recv: RecvStream, lane: MediaLane) -> Result<()> {
+        let mut frames = 0u64;
+        let mut video_session = None;
+        let result = loop {
+            let mut header = [0u8; 4];
+            match recv.read_exact(&mut header).await {
+                Ok(()) => {}
+                Err(ReadExactError::FinishedEarly) => break Ok(()),
+                Err(e) => break Err(VideoStreamError::QuicError(e.to_string())),
+            }
+            let mut frame = match media_frame_length(header) {
+                Ok(length) => vec![0u8; length],
+                Err(e) => break Err(e),
+            };
+            if let Err(e) = recv.read_exact(&mut frame).await {
+                break Err(VideoStreamError::QuicError(e.to_string()));
+            }
+            if let Some(session_id) = self.deliver(&frame, SystemTime::now(), lane).await {
+                video_session = Some(session_id);
+            }
+            frames += 1;
+        };
+        debug!("Media stream finished after {} segments", frames);
+
+        // 普通帧流结束即会话结束（停止播放或流被重置），丢弃该会话暂存的分片
+        if lane == MediaLane::Stream {
+            if let Some(session_id) = video_session {
+                self.end_session(&session_id);
+            }
+        }
+        result
+    }
+
+    /// 接收以 DATAGRAM 发送的非参考帧，连接关闭时返回
+    async fn receive_datagrams(self, connection: Connection) {
+        while let Ok(datagram) = connection.read_datagram().await {
+            self.deliver(&datagram, SystemTime::now(), MediaLane::Datagram).await;
+        }
+    }
+
+    /// 释放会话的重排状态
+    fn end_session(&self, session_id: &Uuid) {
+        let dropped = self.reorder.lock().unwrap().remove_session(session_id);
+        if dropped > 0 {
+            debug!("Session {} ended with {} held segments dropped", session_id, dropped);
+        }
+    }
+
+    /// 按协商版本解析分片，重排后分发到所属会话，返回视频分片所属的会话
+    async fn deliver(&self, buf: &[u8], receive_time: SystemTime, lane: MediaLane) -> Option<Uuid> {
+        let ConnectionState { protocol, device_id } = self.state.read().await.clone();
+        match common::decode_segment(protocol.version, buf) {
+            Ok(mut segment) => {
+                segment.receive_time = Some(receive_time);
+                let seg_session_id = segment.session_id;
+                // 序号缺口计为揭示缺口的分片所属会话的丢包
+                let lost = record_transmission(&self.latency_monitor, &device_id, &segment, receive_time);
+                if lost > 0 {
+                    self.stats_manager.record_lost_segments(&seg_session_id, lost);
+                }
+
+                debug!("Received segment: {} for session: {}", segment.segment_id, seg_session_id);
+                // 平台已关闭的会话（停止播放）不再暂存分片
+                if !self.distribution_manager.has_session(&seg_session_id) {
+                    self.end_session(&seg_session_id);
+                    return None;
+                }
+                let is_video = !segment.is_audio();
+                let ready = self.reorder.lock().unwrap().push(segment, lane);
+                // 使用分片中的 session_id 来分发（而不是连接的 session_id）
+                for segment in ready {
+                    let _ = self.distribution_manager.distribute_segment(&seg_session_id, segment);
+                }
+                is_video.then_some(seg_session_id)
+            }
+            Err(e) => {
+                debug!("Failed to deserialize as segment: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// 根据分片携带的设备时间戳记录设备→平台传输延迟，返回新发现的序号缺口数
+fn record_transmission(
+    latency_monitor: &EndToEndLatencyMonitor,
+    device_id: &str,
+    segment: &VideoSegment,
+    receive_time: SystemTime,
+) -> u64 {
+    // 旧版设备不携带发送时间
+    if segment.send_time_us == 0 {
+        return 0;
+    }
+
+    let Some(send_time) = latency_monitor
+        .get_clock_sync()
+        .to_platform_time(device_id, segment.send_time_us)
+    else {
+        return 0;
+    };
+
+    let capture_to_send = (segment.capture_time_us > 0)
+        .then(|| segment.send_time_us.checked_sub(segment.capture_time_us))
+        .flatten()
+        .map(Duration::from_micros);
+
+    latency_monitor.record_device_transmission(
+        device_id,
+        segment.sequence,
+        capture_to_send,
+        send_time,
+        receive_time,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::ProtocolVersion;
+
+    fn request(min: ProtocolVersion, max: ProtocolVersion) -> SessionStartRequest {
+        let device = fallback_device(Uuid::new_v4());
+        SessionStartRequest {
+            device_id: device.device_id,
+            device_name: device.device_name,
+            device_type: device.device_type,
+            capabilities: device.capabilities,
+            protocol_versions: ProtocolVersionRange::new(min, max),
+            features: FeatureFlags::LIVE_STREAM | 0x8000_0000,
+        }
+    }
+
+    #[test]
+    fn test_negotiate_selects_highest_common_version() {
+        let response = negotiate_session(&request(ProtocolVersion::V1_0, ProtocolVersion::new(1, 9)));
+        assert!(response.accepted);
+        assert_eq!(response.selected_version, ProtocolVersion::CURRENT);
+        // 未知特性位被屏蔽
+        assert_eq!(response.features, FeatureFlags::LIVE_STREAM);
+    }
+
+    #[test]
+    fn test_negotiate_rejects_disjoint_range() {
+        let response = negotiate_session(&request(ProtocolVersion::new(2, 0), ProtocolVersion::new(2, 3)));
+        assert!(!response.accepted);
+        assert_eq!(response.features, 0);
+        assert!(response.error_message.is_some());
+    }
+}
@@ -1,4 +1,5 @@
 mod server;
 mod connection;
+mod reorder;
 
 pub use server::QuicServer;
@@ -0,0 +1,217 @@
+// 会话视频分片的重排
+//
+// 设备的视频分片经三条路径到达：普通帧所在的会话媒体流、优先级更高的关键帧流，以及
+// 以 DATAGRAM 发送的非参考帧。后两者可能先于序号更小的普通帧到达，直接分发会让直播源
+// 看到时间戳倒退并误判为不连续。这里按会话暂存关键帧流分片和 DATAGRAM 帧，等到序号更大
+// 的普通帧到达时按序号一起放出；落后于已分发分片的 DATAGRAM 帧是可丢弃的，直接丢弃。
+
+use common::VideoSegment;
+use std::collections::{BTreeMap, HashMap};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// 每个会话最多暂存的分片，视频流长时间阻塞时超出的 DATAGRAM 帧直接丢弃
+const MAX_HELD_SEGMENTS: usize = 16;
+
+/// 分片到达的路径
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum MediaLane {
+    /// 会话媒体流（普通帧、音频）或旧版每分片一条流
+    Stream,
+    /// 关键帧流（`MEDIA_KEYFRAME_STREAM_MAGIC`）
+    KeyframeStream,
+    /// QUIC DATAGRAM（可丢弃的非参考帧）
+    Datagram,
+}
+
+/// 按会话把三条路径的视频分片合并回序号顺序
+#[derive(Debug, Default)]
+pub(super) struct MediaReorder {
+    sessions: HashMap<Uuid, SessionOrder>,
+}
+
+#[derive(Debug, Default)]
+struct SessionOrder {
+    /// 已分发视频分片的最大序号
+    last_sequence: Option<u64>,
+    /// 暂存的关键帧流分片和 DATAGRAM 帧（序号 → 分片）
+    held: BTreeMap<u64, VideoSegment>,
+}
+
+impl SessionOrder {
+    /// 放出序号小于 `sequence` 的暂存分片
+    fn release_before(&mut self, sequence: u64) -> Vec<VideoSegment> {
+        let later = self.held.split_off(&sequence);
+        let ready: Vec<VideoSegment> = std::mem::replace(&mut self.held, later).into_values().collect();
+        if let Some(last) = ready.last() {
+            self.advance(last.sequence);
+        }
+        ready
+    }
+
+    fn advance(&mut self, sequence: u64) {
+        self.last_sequence = Some(self.last_sequence.map_or(sequence, |last| last.max(sequence)));
+    }
+
+    fn is_late(&self, sequence: u64) -> bool {
+        self.last_sequence.is_some_and(|last| sequence < last)
+    }
+}
+
+impl MediaReorder {
+    /// 放入一个分片，返回可按序分发的分片
+    pub(super) fn push(&mut self, segment: VideoSegment, lane: MediaLane) -> Vec<VideoSegment> {
+        // 音频走独立的流，不参与视频重排
+        if segment.is_audio() {
+            return vec![segment];
+        }
+
+        let order = self.sessions.entry(segment.session_id).or_default();
+        let sequence = segment.sequence;
+
+        match lane {
+            MediaLane::Datagram => {
+                if order.is_late(sequence) {
+                    debug!("Dropping late datagram segment #{} for session {}", sequence, segment.session_id);
+                } else if order.held.len() >= MAX_HELD_SEGMENTS {
+                    debug!("Reorder buffer full, dropping datagram segment #{}", sequence);
+                } else {
+                    order.held.insert(sequence, segment);
+                }
+                Vec::new()
+            }
+            MediaLane::KeyframeStream => {
+                if order.is_late(sequence) {
+                    // 之后的普通帧已经分发，关键帧仍然要送给解码器重新同步
+                    warn!("Late keyframe #{} for session {}", sequence, segment.session_id);
+                    return vec![segment];
+                }
+                // 新的关键帧到达时之前暂存的分片不再等待普通帧（如全 I 帧码流）
+                let ready = order.release_before(sequence);
+                order.held.insert(sequence, segment);
+                ready
+            }
+            MediaLane::Stream => {
+                if order.is_late(sequence) {
+                    // 只在新关键帧放出了更早的关键帧后出现，所属 GOP 已被跳过
+                    debug!("Dropping stale segment #{} for session {}", sequence, segment.session_id);
+                    return Vec::new();
+                }
+                let mut ready = order.release_before(sequence);
+                order.advance(sequence);
+                ready.push(segment);
+                ready
+            }
+        }
+    }
+
+    /// 会话结束时释放其重排状态，返回丢弃的暂存分片数
+    pub(super) fn remove_session(&mut self, session_id: &Uuid) -> usize {
+        self.sessions
+            .remove(session_id)
+            .map_or(0, |order| order.held.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(session_id: Uuid, sequence: u64) -> VideoSegment {
+        let mut segment = VideoSegment::new(vec![0, 0, 0, 1, 0x01], sequence as i64 * 3000, 3000, false);
+        segment.session_id = session_id;
+        segment.sequence = sequence;
+        segment
+    }
+
+    fn sequences(segments: &[VideoSegment]) -> Vec<u64> {
+        segments.iter().map(|segment| segment.sequence).collect()
+    }
+
+    #[test]
+    fn test_out_of_order_datagrams_wait_for_stream() {
+        let session = Uuid::new_v4();
+        let mut reorder = MediaReorder::default();
+
+        assert_eq!(sequences(&reorder.push(segment(session, 1), MediaLane::Stream)), vec![1]);
+
+        // DATAGRAM 帧 3、2 先于流上的分片 4 到达，且彼此乱序
+        assert!(reorder.push(segment(session, 3), MediaLane::Datagram).is_empty());
+        assert!(reorder.push(segment(session, 2), MediaLane::Datagram).is_empty());
+        assert_eq!(sequences(&reorder.push(segment(session, 4), MediaLane::Stream)), vec![2, 3, 4]);
+
+        // 比已分发分片更早的 DATAGRAM 帧被丢弃
+        assert!(reorder.push(segment(session, 3), MediaLane::Datagram).is_empty());
+
+        // 序号更大的 DATAGRAM 帧等待下一个流上分片
+        assert!(reorder.push(segment(session, 6), MediaLane::Datagram).is_empty());
+        assert_eq!(sequences(&reorder.push(segment(session, 5), MediaLane::Stream)), vec![5]);
+        assert_eq!(sequences(&reorder.push(segment(session, 7), MediaLane::Stream)), vec![6, 7]);
+
+        // 分发结果的解码时间戳单调递增
+        let mut dts = Vec::new();
+        assert!(reorder.push(segment(session, 9), MediaLane::Datagram).is_empty());
+        assert!(reorder.push(segment(session, 8), MediaLane::Datagram).is_empty());
+        dts.extend(reorder.push(segment(session, 10), MediaLane::Stream).iter().map(|segment| segment.dts));
+        assert!(dts.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_keyframe_stream_merged_by_sequence() {
+        let session = Uuid::new_v4();
+        let mut reorder = MediaReorder::default();
+
+        assert_eq!(sequences(&reorder.push(segment(session, 1), MediaLane::Stream)), vec![1]);
+
+        // 关键帧 3 越过排队的普通帧 2 先到达，等普通帧到齐后按序放出
+        assert!(reorder.push(segment(session, 3), MediaLane::KeyframeStream).is_empty());
+        assert_eq!(sequences(&reorder.push(segment(session, 2), MediaLane::Stream)), vec![2]);
+        assert_eq!(sequences(&reorder.push(segment(session, 4), MediaLane::Stream)), vec![3, 4]);
+
+        // 全 I 帧码流：下一个关键帧放出上一个
+        assert!(reorder.push(segment(session, 5), MediaLane::KeyframeStream).is_empty());
+        assert_eq!(sequences(&reorder.push(segment(session, 7), MediaLane::KeyframeStream)), vec![5]);
+
+        // 关键帧 5 之前的普通帧已过时，5 和 7 之间的普通帧照常分发
+        assert!(reorder.push(segment(session, 4), MediaLane::Stream).is_empty());
+        assert_eq!(sequences(&reorder.push(segment(session, 6), MediaLane::Stream)), vec![6]);
+        assert_eq!(sequences(&reorder.push(segment(session, 8), MediaLane::Stream)), vec![7, 8]);
+    }
+
+    #[test]
+    fn test_remove_session_drops_held_segments() {
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let mut reorder = MediaReorder::default();
+
+        assert_eq!(sequences(&reorder.push(segment(first, 1), MediaLane::Stream)), vec![1]);
+        assert!(reorder.push(segment(first, 2), MediaLane::KeyframeStream).is_empty());
+        assert!(reorder.push(segment(first, 3), MediaLane::Datagram).is_empty());
+        assert!(reorder.push(segment(second, 4), MediaLane::Datagram).is_empty());
+
+        assert_eq!(reorder.remove_session(&first), 2);
+        assert_eq!(reorder.remove_session(&first), 0);
+        assert_eq!(reorder.sessions.len(), 1);
+
+        // 同一 ID 重新出现时从空状态开始，不会放出已丢弃的分片
+        assert_eq!(sequences(&reorder.push(segment(first, 5), MediaLane::Stream)), vec![5]);
+        assert_eq!(sequences(&reorder.push(segment(second, 6), MediaLane::Stream)), vec![4, 6]);
+    }
+
+    #[test]
+    fn test_reorder_is_per_session_and_skips_audio() {
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let mut reorder = MediaReorder::default();
+
+        assert!(reorder.push(segment(first, 2), MediaLane::Datagram).is_empty());
+        // 其他会话的流上分片不会放出本会话暂存的帧
+        assert_eq!(sequences(&reorder.push(segment(second, 3), MediaLane::Stream)), vec![3]);
+        assert_eq!(sequences(&reorder.push(segment(first, 4), MediaLane::Stream)), vec![2, 4]);
+
+        let mut audio = VideoSegment::new_audio(vec![0xFF, 0xF1], 0, 1920, 1);
+        audio.session_id = first;
+        audio.sequence = 1;
+        assert_eq!(reorder.push(audio, MediaLane::Stream).len(), 1);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::device::DeviceManager;
 use crate::distribution::DistributionManager;
+use crate::latency::{EndToEndLatencyMonitor, LatencyStatisticsManager};
 use crate::recording::RecordingManager;
 use common::{Result, VideoStreamError};
 use quinn::{Endpoint, ServerConfig};
@@ -12,6 +13,8 @@ pub struct QuicServer {
     device_manager: DeviceManager,
     recording_manager: RecordingManager,
     distribution_manager: DistributionManager,
+    latency_monitor: Arc<EndToEndLatencyMonitor>,
+    stats_manager: Arc<LatencyStatisticsManager>,
 }
 
 impl QuicServer {
@@ -20,6 +23,8 @@ impl QuicServer {
         device_manager: DeviceManager,
         recording_manager: RecordingManager,
         distribution_manager: DistributionManager,
+        latency_monitor: Arc<EndToEndLatencyMonitor>,
+        stats_manager: Arc<LatencyStatisticsManager>,
     ) -> Result<Self> {
         // 创建自签名证书
         let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
@@ -51,6 +56,8 @@ impl QuicServer {
             device_manager,
             recording_manager,
             distribution_manager,
+            latency_monitor,
+            stats_manager,
         })
     }
 
@@ -61,6 +68,8 @@ impl QuicServer {
             let device_manager = self.device_manager.clone();
             let recording_manager = self.recording_manager.clone();
             let distribution_manager = self.distribution_manager.clone();
+            let latency_monitor = self.latency_monitor.clone();
+            let stats_manager = self.stats_manager.clone();
 
             tokio::spawn(async move {
                 match conn.await {
@@ -71,6 +80,8 @@ impl QuicServer {
                             device_manager,
                             recording_manager,
                             distribution_manager,
+                            latency_monitor,
+                            stats_manager,
                         )
                         .await
                         {
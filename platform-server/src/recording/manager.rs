@@ -5,12 +5,14 @@ use std::sync::Arc;
 use tracing::info;
 
 use super::scanner::RecordingScanner;
+use super::upload::UploadManager;
 
 #[derive(Clone)]
 pub struct RecordingManager {
     storage_root: PathBuf,
     cache: Arc<DashMap<String, RecordingInfo>>,
     scanner: Arc<RecordingScanner>,
+    uploads: UploadManager,
 }
 
 impl RecordingManager {
@@ -20,6 +22,7 @@ impl RecordingManager {
         let scanner = Arc::new(RecordingScanner::new(storage_root.clone()));
         
         Self {
+            uploads: UploadManager::new(storage_root.clone()),
             storage_root,
             cache: Arc::new(DashMap::new()),
             scanner,
@@ -45,6 +48,11 @@ impl RecordingManager {
         self.cache.insert(recording.file_id.clone(), recording);
     }
 
+    /// 设备整文件上传接收器
+    pub fn uploads(&self) -> &UploadManager {
+        &self.uploads
+    }
+
     /// 获取录像文件路径
     pub fn get_recording_path(&self, device_id: &str, file_name: &str) -> PathBuf {
         self.storage_root.join(device_id).join(file_name)
@@ -1,5 +1,6 @@
 mod manager;
 mod scanner;
+mod upload;
 
 pub use manager::RecordingManager;
 pub use scanner::RecordingScanner;
@@ -1,4 +1,5 @@
-use common::{RecordingInfo, Result};
+use common::demux::{self, Container};
+use common::{RecordingInfo, Result, VideoCodec};
 use std::path::PathBuf;
 use std::time::SystemTime;
 use tracing::{debug, warn};
@@ -59,7 +60,7 @@ impl RecordingScanner {
             if let Some(ext) = file_path.extension() {
                 let ext_str = ext.to_string_lossy().to_lowercase();
                 debug!("File extension: {}", ext_str);
-                if ext_str == "h264" || ext_str == "mp4" || ext_str == "264" {
+                if Container::from_extension(&ext_str).is_some() {
                     debug!("Parsing video file: {:?}", file_path);
                     match self.parse_recording(device_id, file_path).await {
                         Ok(info) => {
@@ -88,6 +89,15 @@ impl RecordingScanner {
             .and_then(|e| e.to_str())
             .unwrap_or("unknown")
             .to_string();
+        // 裸流按扩展名识别编码，MP4/TS 按容器中的视频轨道识别
+        let codec = match VideoCodec::from_extension(&format) {
+            Some(codec) => codec,
+            None => tokio::fs::read(path)
+                .await
+                .ok()
+                .and_then(|data| demux::probe_codec(&data))
+                .unwrap_or_default(),
+        };
 
         Ok(RecordingInfo {
             file_id,
@@ -97,6 +107,7 @@ impl RecordingScanner {
             file_size: metadata.len(),
             duration: 0.0, // 需要解析视频文件获取
             format,
+            codec,
             resolution: "1920x1080".to_string(), // 默认值
             bitrate: 5000000,
             frame_rate: 30.0,
@@ -105,3 +116,37 @@ impl RecordingScanner {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scan_recognizes_codecs() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["front.h264", "back.265", "side.hevc", "notes.txt"] {
+            std::fs::write(dir.path().join(name), [0u8, 0, 0, 1]).unwrap();
+        }
+        // MPEG-TS 按 PMT 中的流类型识别编码
+        let mut muxer = common::ts::TsMuxer::new(VideoCodec::H265, false);
+        let mut ts = Vec::new();
+        muxer.write_tables(&mut ts);
+        muxer.write_video(&[0, 0, 0, 1, 0x26, 0x01, 0xaf], 0.0, 0.0, true, &mut ts);
+        std::fs::write(dir.path().join("dvr.ts"), ts).unwrap();
+
+        let scanner = RecordingScanner::new(dir.path().to_path_buf());
+        let mut recordings = scanner.scan_device_recordings("device_001").await.unwrap();
+        recordings.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+        let codecs: Vec<_> = recordings.iter().map(|r| (r.file_name.as_str(), r.codec)).collect();
+        assert_eq!(
+            codecs,
+            vec![
+                ("back.265", VideoCodec::H265),
+                ("dvr.ts", VideoCodec::H265),
+                ("front.h264", VideoCodec::H264),
+                ("side.hevc", VideoCodec::H265),
+            ]
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use common::{
+    FileUploadAck, FileUploadChunk, FileUploadOffer, FileUploadStatus, Result, VideoStreamError,
+};
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// 未完成上传的临时目录（位于设备目录下，录像扫描不会识别 `.part` 文件）
+const PARTIAL_DIR: &str = ".uploads";
+
+/// 进行中的上传
+struct Upload {
+    device_id: String,
+    file_name: String,
+    total_size: u64,
+    sha256: String,
+    /// 已落盘并确认的字节数（同时串行化同一上传的分块写入）
+    offset: Mutex<u64>,
+}
+
+/// 设备整文件上传接收器
+///
+/// 分块按偏移顺序写入 `storage_root/<device_id>/.uploads/<sha256>.part`，每个分块落盘后才确认，
+/// 断线（甚至平台重启）后设备重新发起上传即可从已确认的偏移续传。全部接收后校验 SHA-256，
+/// 再原子地重命名为 `storage_root/<device_id>/<file_name>`。
+#[derive(Clone)]
+pub struct UploadManager {
+    storage_root: PathBuf,
+    uploads: Arc<DashMap<Uuid, Arc<Upload>>>,
+}
+
+impl UploadManager {
+    pub fn new(storage_root: PathBuf) -> Self {
+        Self {
+            storage_root,
+            uploads: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// 处理上传请求，返回续传偏移
+    pub async fn offer(&self, device_id: &str, offer: &FileUploadOffer) -> Result<FileUploadAck> {
+        let file_name = sanitize_file_name(&offer.file_name)?;
+        let device_dir = sanitize_file_name(device_id)?;
+        let sha256 = offer.sha256.to_ascii_lowercase();
+        if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(VideoStreamError::InvalidParameter(format!("Invalid SHA-256: {}", offer.sha256)));
+        }
+
+        // 同一文件已有进行中的上传（例如设备断线重连），沿用原上传
+        let existing = self
+            .uploads
+            .iter()
+            .find(|entry| entry.device_id == device_id && entry.sha256 == sha256)
+            .map(|entry| (*entry.key(), entry.value().clone()));
+        if let Some((upload_id, upload)) = existing {
+            let offset = *upload.offset.lock().await;
+            info!("Resuming upload {} of {} for device {} at {} bytes", upload_id, file_name, device_id, offset);
+            return Ok(ack(upload_id, offset, FileUploadStatus::InProgress));
+        }
+
+        let upload_id = Uuid::new_v4();
+        let target = self.storage_root.join(&device_dir).join(&file_name);
+        if file_matches(&target, offer.total_size, &sha256).await {
+            info!("Upload of {} for device {} already stored", file_name, device_id);
+            return Ok(ack(upload_id, offer.total_size, FileUploadStatus::Complete));
+        }
+
+        // 平台重启后按临时文件长度续传
+        let partial = self.partial_path(&device_dir, &sha256);
+        let mut offset = fs::metadata(&partial).await.map(|m| m.len()).unwrap_or(0);
+        if offset > offer.total_size {
+            warn!("Discarding oversized partial upload {:?}", partial);
+            fs::remove_file(&partial).await?;
+            offset = 0;
+        }
+
+        info!(
+            "Accepted upload {} of {} ({} bytes) for device {}, resuming at {}",
+            upload_id, file_name, offer.total_size, device_id, offset
+        );
+        self.uploads.insert(
+            upload_id,
+            Arc::new(Upload {
+                device_id: device_id.to_string(),
+                file_name,
+                total_size: offer.total_size,
+                sha256,
+                offset: Mutex::new(offset),
+            }),
+        );
+
+        // 空文件或临时文件已完整：直接校验
+        if offset == offer.total_size {
+            return self.finish(upload_id).await;
+        }
+        Ok(ack(upload_id, offset, FileUploadStatus::InProgress))
+    }
+
+    /// 写入一个分块，返回已确认的偏移
+    ///
+    /// 重复的分块只确认不写入；超前的分块被忽略，设备应从确认的偏移重新发送。
+    pub async fn write_chunk(&self, device_id: &str, chunk: &FileUploadChunk) -> Result<FileUploadAck> {
+        let Some(upload) = self.uploads.get(&chunk.upload_id).map(|entry| entry.value().clone()) else {
+            return Ok(FileUploadAck {
+                error_message: Some(format!("Unknown upload: {}", chunk.upload_id)),
+                ..ack(chunk.upload_id, 0, FileUploadStatus::Rejected)
+            });
+        };
+        if upload.device_id != device_id {
+            return Err(VideoStreamError::InvalidParameter(format!(
+                "Upload {} does not belong to device {}",
+                chunk.upload_id, device_id
+            )));
+        }
+
+        let mut offset = upload.offset.lock().await;
+        let end = chunk.offset + chunk.data.len() as u64;
+        if end > upload.total_size {
+            return Ok(FileUploadAck {
+                error_message: Some(format!("Chunk ends at {} beyond file size {}", end, upload.total_size)),
+                ..ack(chunk.upload_id, *offset, FileUploadStatus::Rejected)
+            });
+        }
+        if chunk.offset > *offset {
+            debug!(
+                "Upload {}: chunk at {} ahead of acknowledged offset {}",
+                chunk.upload_id, chunk.offset, *offset
+            );
+            return Ok(ack(chunk.upload_id, *offset, FileUploadStatus::InProgress));
+        }
+
+        // 只追加尚未接收的部分
+        if end > *offset {
+            let skip = (*offset - chunk.offset) as usize;
+            let partial = self.partial_path(&upload.device_id, &upload.sha256);
+            if let Some(parent) = partial.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(&partial).await?;
+            file.write_all(&chunk.data[skip..]).await?;
+            file.sync_data().await?;
+            *offset = end;
+        }
+
+        if *offset < upload.total_size {
+            return Ok(ack(chunk.upload_id, *offset, FileUploadStatus::InProgress));
+        }
+        drop(offset);
+        self.finish(chunk.upload_id).await
+    }
+
+    /// 校验已接收完整的文件并放入设备目录
+    async fn finish(&self, upload_id: Uuid) -> Result<FileUploadAck> {
+        let Some((_, upload)) = self.uploads.remove(&upload_id) else {
+            return Ok(ack(upload_id, 0, FileUploadStatus::Rejected));
+        };
+        // 等待仍在写入的分块
+        let _offset = upload.offset.lock().await;
+        let partial = self.partial_path(&upload.device_id, &upload.sha256);
+        if let Some(parent) = partial.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        // 空文件不会收到分块，这里补建临时文件
+        if upload.total_size == 0 {
+            File::create(&partial).await?;
+        }
+
+        let actual = sha256_file(&partial).await?;
+        if actual != upload.sha256 {
+            warn!(
+                "Upload {} of {} for device {} failed checksum: expected {}, got {}",
+                upload_id, upload.file_name, upload.device_id, upload.sha256, actual
+            );
+            fs::remove_file(&partial).await?;
+            return Ok(FileUploadAck {
+                error_message: Some(format!("SHA-256 mismatch: got {}", actual)),
+                ..ack(upload_id, 0, FileUploadStatus::ChecksumMismatch)
+            });
+        }
+
+        // 临时文件与目标在同一目录树下，重命名是原子的
+        let target = self.storage_root.join(&upload.device_id).join(&upload.file_name);
+        fs::rename(&partial, &target).await?;
+        info!(
+            "Upload {} complete: stored {} bytes at {:?}",
+            upload_id, upload.total_size, target
+        );
+        Ok(ack(upload_id, upload.total_size, FileUploadStatus::Complete))
+    }
+
+    fn partial_path(&self, device_id: &str, sha256: &str) -> PathBuf {
+        self.storage_root
+            .join(device_id)
+            .join(PARTIAL_DIR)
+            .join(format!("{}.part", sha256))
+    }
+}
+
+fn ack(upload_id: Uuid, acked_offset: u64, status: FileUploadStatus) -> FileUploadAck {
+    FileUploadAck {
+        upload_id,
+        acked_offset,
+        status,
+        error_message: None,
+    }
+}
+
+/// 只接受单级文件名，防止写出设备目录
+fn sanitize_file_name(name: &str) -> Result<String> {
+    let valid = Path::new(name)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .filter(|n| *n == name && !n.starts_with('.'));
+    valid
+        .map(str::to_string)
+        .ok_or_else(|| VideoStreamError::InvalidParameter(format!("Invalid file name: {}", name)))
+}
+
+/// 计算文件的 SHA-256（小写十六进制）
+async fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 目标文件已存在且内容一致
+async fn file_matches(path: &Path, size: u64, sha256: &str) -> bool {
+    match fs::metadata(path).await {
+        Ok(metadata) if metadata.len() == size => {
+            sha256_file(path).await.is_ok_and(|actual| actual == sha256)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer_for(name: &str, data: &[u8]) -> FileUploadOffer {
+        FileUploadOffer {
+            file_name: name.to_string(),
+            total_size: data.len() as u64,
+            sha256: format!("{:x}", Sha256::digest(data)),
+        }
+    }
+
+    fn chunk(upload_id: Uuid, offset: usize, data: &[u8]) -> FileUploadChunk {
+        FileUploadChunk {
+            upload_id,
+            offset: offset as u64,
+            data: data.to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_complete() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = UploadManager::new(dir.path().to_path_buf());
+        let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+
+        let accepted = manager.offer("cam1", &offer_for("clip.mp4", &data)).await.unwrap();
+        assert_eq!((accepted.status, accepted.acked_offset), (FileUploadStatus::InProgress, 0));
+
+        let first = manager.write_chunk("cam1", &chunk(accepted.upload_id, 0, &data[..400])).await.unwrap();
+        assert_eq!(first.acked_offset, 400);
+        // 重传的分块只确认，超前的分块被忽略
+        let duplicate = manager.write_chunk("cam1", &chunk(accepted.upload_id, 0, &data[..400])).await.unwrap();
+        assert_eq!(duplicate.acked_offset, 400);
+        let ahead = manager.write_chunk("cam1", &chunk(accepted.upload_id, 800, &data[800..])).await.unwrap();
+        assert_eq!(ahead.acked_offset, 400);
+
+        // 与已接收部分重叠的分块只追加新数据
+        let done = manager.write_chunk("cam1", &chunk(accepted.upload_id, 200, &data[200..])).await.unwrap();
+        assert_eq!((done.status, done.acked_offset), (FileUploadStatus::Complete, 1000));
+        assert_eq!(std::fs::read(dir.path().join("cam1").join("clip.mp4")).unwrap(), data);
+        assert!(manager.uploads.is_empty());
+
+        // 再次上传同一文件直接完成
+        let again = manager.offer("cam1", &offer_for("clip.mp4", &data)).await.unwrap();
+        assert_eq!(again.status, FileUploadStatus::Complete);
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumes_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = vec![7u8; 600];
+        let offer = offer_for("clip.h264", &data);
+
+        let manager = UploadManager::new(dir.path().to_path_buf());
+        let accepted = manager.offer("cam1", &offer).await.unwrap();
+        manager.write_chunk("cam1", &chunk(accepted.upload_id, 0, &data[..250])).await.unwrap();
+
+        // 同一进程内重连沿用原上传
+        let resumed = manager.offer("cam1", &offer).await.unwrap();
+        assert_eq!((resumed.upload_id, resumed.acked_offset), (accepted.upload_id, 250));
+
+        // 平台重启后按临时文件续传，旧上传ID失效
+        let restarted = UploadManager::new(dir.path().to_path_buf());
+        let stale = restarted.write_chunk("cam1", &chunk(accepted.upload_id, 250, &data[250..])).await.unwrap();
+        assert_eq!(stale.status, FileUploadStatus::Rejected);
+        let resumed = restarted.offer("cam1", &offer).await.unwrap();
+        assert_eq!(resumed.acked_offset, 250);
+        let done = restarted.write_chunk("cam1", &chunk(resumed.upload_id, 250, &data[250..])).await.unwrap();
+        assert_eq!(done.status, FileUploadStatus::Complete);
+        assert_eq!(std::fs::read(dir.path().join("cam1").join("clip.h264")).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_upload_checksum_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = UploadManager::new(dir.path().to_path_buf());
+        let data = vec![1u8; 100];
+
+        let accepted = manager.offer("cam1", &offer_for("clip.ts", &data)).await.unwrap();
+        let result = manager.write_chunk("cam1", &chunk(accepted.upload_id, 0, &[2u8; 100])).await.unwrap();
+        assert_eq!((result.status, result.acked_offset), (FileUploadStatus::ChecksumMismatch, 0));
+        assert!(!dir.path().join("cam1").join("clip.ts").exists());
+        assert!(!manager.partial_path("cam1", &offer_for("", &data).sha256).exists());
+    }
+
+    #[tokio::test]
+    async fn test_upload_rejects_invalid_offer() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = UploadManager::new(dir.path().to_path_buf());
+        let data = vec![0u8; 10];
+
+        for name in ["../escape.mp4", "sub/clip.mp4", ".hidden", ""] {
+            assert!(manager.offer("cam1", &offer_for(name, &data)).await.is_err(), "{}", name);
+        }
+        assert!(manager.offer("../cam1", &offer_for("clip.mp4", &data)).await.is_err());
+
+        let mut offer = offer_for("clip.mp4", &data);
+        offer.sha256 = "not-a-hash".to_string();
+        assert!(manager.offer("cam1", &offer).await.is_err());
+
+        // 上传只属于发起的设备
+        let accepted = manager.offer("cam1", &offer_for("clip.mp4", &data)).await.unwrap();
+        assert!(manager.write_chunk("cam2", &chunk(accepted.upload_id, 0, &data)).await.is_err());
+    }
+}
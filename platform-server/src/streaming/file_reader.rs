@@ -4,13 +4,17 @@
 //
 // # 特性
 //
-// - 小分片读取（8KB-32KB）降低延迟
+// - H.264/H.265裸流按访问单元分片，时间戳由帧序号和检测到的帧率计算
+// - MP4解复用为Annex B基本流后同样按访问单元分片，时间戳取自样本表
+// - 打开时构建关键帧索引，按时间定位无需调用方提供总时长
+// - 无法解析访问单元的文件按小分片（8KB-32KB）读取
 // - 异步IO，非阻塞操作
 // - 速率控制，支持0.25x-4x倍速
-// - 精确定位，支持任意时间位置
-// - 零拷贝优化
 
 use super::source::{SegmentFormat, SegmentSourceType, StreamError, VideoSegment};
+use common::demux::{Container, ElementaryStream};
+use common::h264::AccessUnit;
+use common::{KeyframeEntry, VideoCodec};
 use std::path::{Path, PathBuf};
 use tokio::fs::File;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
@@ -35,25 +39,20 @@ pub enum SeekTarget {
     Position(f64),
     /// 字节偏移量
     Offset(u64),
-    /// 时间位置（秒）
-    Time {
-        /// 目标时间（秒）
-        seconds: f64,
-        /// 视频总时长（秒）
-        duration: f64,
-    },
+    /// 时间位置（秒），定位到之前最近的关键帧
+    Time(f64),
 }
 
 /// 文件流式读取器配置
 #[derive(Debug, Clone)]
 pub struct FileReaderConfig {
-    /// 分片大小（字节）
+    /// 分片大小（字节，仅用于无法解析访问单元的文件）
     pub segment_size: usize,
     /// 播放速率
     pub playback_rate: f64,
-    /// 分片格式
+    /// 分片格式（仅用于无法解析访问单元的文件）
     pub format: SegmentFormat,
-    /// 假设的帧率（用于时间戳计算）
+    /// 假设的帧率（SPS中没有帧率信息时使用）
     pub assumed_fps: f64,
 }
 
@@ -74,11 +73,11 @@ impl Default for FileReaderConfig {
 ///
 /// # 特性
 ///
-/// - **小分片读取**: 使用8KB-32KB小分片，降低首帧延迟
+/// - **按帧分片**: H.264裸流每个访问单元一个分片，时间戳与帧对齐
+/// - **关键帧索引**: 打开文件时构建，支撑按时间定位
 /// - **速率控制**: 根据播放速率控制分片发送间隔
 /// - **异步IO**: 完全异步，不阻塞事件循环
-/// - **精确定位**: 支持字节级精确定位
-/// - **零拷贝**: 最小化内存复制
+/// - **小分片回退**: 无法解析访问单元的文件使用8KB-32KB小分片
 ///
 /// # 示例
 ///
@@ -96,8 +95,8 @@ impl Default for FileReaderConfig {
 ///     println!("Read segment: {} bytes", segment.data.len());
 /// }
 ///
-/// // 定位到50%位置
-/// reader.seek_to_position(0.5).await?;
+/// // 定位到30秒
+/// reader.seek_to_time(30.0).await?;
 ///
 /// // 设置2倍速
 /// reader.set_playback_rate(2.0)?;
@@ -107,7 +106,7 @@ pub struct FileStreamReader {
     file: File,
     /// 文件路径
     file_path: PathBuf,
-    /// 文件大小（字节）
+    /// 文件大小（字节，MP4为解复用后的基本流大小）
     file_size: u64,
     /// 当前偏移量（字节）
     current_offset: u64,
@@ -117,6 +116,18 @@ pub struct FileStreamReader {
     segments_read: u64,
     /// 开始时间（用于速率控制）
     start_time: std::time::Instant,
+    /// 访问单元表（为空时按小分片读取）
+    access_units: Vec<AccessUnit>,
+    /// 关键帧在访问单元表中的下标
+    keyframes: Vec<usize>,
+    /// 下一个要读取的访问单元
+    next_unit: usize,
+    /// 帧率（从SPS或MP4样本表检测，失败时为假设帧率）
+    frame_rate: f64,
+    /// MP4解复用后的Annex B基本流（访问单元偏移指向这里而不是文件）
+    demuxed: Option<Vec<u8>>,
+    /// 视频编码格式
+    codec: VideoCodec,
 }
 
 impl std::fmt::Debug for FileStreamReader {
@@ -127,6 +138,10 @@ impl std::fmt::Debug for FileStreamReader {
             .field("current_offset", &self.current_offset)
             .field("config", &self.config)
             .field("segments_read", &self.segments_read)
+            .field("access_units", &self.access_units.len())
+            .field("keyframes", &self.keyframes.len())
+            .field("frame_rate", &self.frame_rate)
+            .field("codec", &self.codec)
             .finish()
     }
 }
@@ -170,11 +185,33 @@ impl FileStreamReader {
             StreamError::FileNotAccessible(e.to_string())
         })?;
 
-        let file_size = metadata.len();
+        let mut file_size = metadata.len();
+
+        // 解析访问单元并构建关键帧索引
+        let data = tokio::fs::read(&file_path).await.map_err(|e| {
+            warn!("Failed to read file {:?}: {}", file_path, e);
+            StreamError::FileReadError(e.to_string())
+        })?;
+        let stream = ElementaryStream::parse(data, config.assumed_fps);
+        let frame_rate = stream.frame_rate;
+        let codec = stream.codec;
+        let access_units = stream.units;
+        // MP4按基本流读取，裸流直接读文件
+        let demuxed = (stream.container == Container::Mp4 && !access_units.is_empty()).then_some(stream.data);
+        if let Some(ref demuxed) = demuxed {
+            file_size = demuxed.len() as u64;
+        }
+        let keyframes: Vec<usize> = (0..access_units.len())
+            .filter(|&i| access_units[i].is_keyframe)
+            .collect();
 
         debug!(
-            "File opened successfully: {:?}, size: {} bytes",
-            file_path, file_size
+            "File opened successfully: {:?}, size: {} bytes, {} frames, {} keyframes, {:.2} fps",
+            file_path,
+            file_size,
+            access_units.len(),
+            keyframes.len(),
+            frame_rate
         );
 
         Ok(Self {
@@ -185,9 +222,73 @@ impl FileStreamReader {
             config,
             segments_read: 0,
             start_time: std::time::Instant::now(),
+            access_units,
+            keyframes,
+            next_unit: 0,
+            frame_rate,
+            demuxed,
+            codec,
         })
     }
 
+    /// 是否按访问单元分片（文件是可解析的H.264裸流或MP4）
+    pub fn is_frame_aligned(&self) -> bool {
+        !self.access_units.is_empty()
+    }
+
+    /// 获取帧率
+    pub fn frame_rate(&self) -> f64 {
+        self.frame_rate
+    }
+
+    /// 获取总时长（秒）
+    ///
+    /// 按访问单元分片时为帧数/帧率（MP4帧率按样本平均时长计算）；小分片读取时每个分片按一帧计算。
+    pub fn duration(&self) -> f64 {
+        let frames = if self.is_frame_aligned() {
+            self.access_units.len() as u64
+        } else {
+            self.file_size.div_ceil(self.config.segment_size as u64)
+        };
+        frames as f64 / self.frame_rate
+    }
+
+    /// 获取视频编码格式
+    pub fn codec(&self) -> VideoCodec {
+        self.codec
+    }
+
+    /// 获取访问单元表（无法解析访问单元时为空）
+    pub fn access_units(&self) -> &[AccessUnit] {
+        &self.access_units
+    }
+
+    /// 获取当前读取位置（秒）
+    pub fn position(&self) -> f64 {
+        if self.is_frame_aligned() {
+            self.access_units
+                .get(self.next_unit)
+                .map_or_else(|| self.duration(), |u| u.timestamp)
+        } else {
+            (self.current_offset / self.config.segment_size as u64) as f64 / self.frame_rate
+        }
+    }
+
+    /// 获取关键帧索引
+    pub fn keyframe_index(&self) -> Vec<KeyframeEntry> {
+        self.keyframes
+            .iter()
+            .map(|&i| {
+                let unit = &self.access_units[i];
+                KeyframeEntry {
+                    timestamp: unit.timestamp,
+                    file_offset: unit.offset,
+                    frame_size: unit.size,
+                }
+            })
+            .collect()
+    }
+
     /// 读取下一个视频分片
     ///
     /// # 返回
@@ -196,6 +297,10 @@ impl FileStreamReader {
     /// - `Ok(None)`: 文件已读取完毕
     /// - `Err(error)`: 读取错误
     pub async fn read_segment(&mut self) -> Result<Option<VideoSegment>, StreamError> {
+        if self.is_frame_aligned() {
+            return self.read_access_units().await;
+        }
+
         // 检查是否已到文件末尾
         if self.current_offset >= self.file_size {
             debug!("Reached end of file: {:?}", self.file_path);
@@ -225,36 +330,132 @@ impl FileStreamReader {
         self.current_offset += bytes_read as u64;
         self.segments_read += 1;
 
-        // 计算时间戳（基于文件进度和假设的帧率）
-        let progress = self.current_offset as f64 / self.file_size as f64;
-        let timestamp = self.calculate_timestamp(progress);
-
-        // 计算分片时长（基于分片大小和假设的帧率）
-        let duration = self.calculate_duration(bytes_read);
+        // 无法解析帧边界，每个分片按一帧计算时间
+        let duration = VideoSegment::ticks(1.0 / self.frame_rate);
+        let timestamp = (self.segments_read - 1) as i64 * duration;
 
         // 检测关键帧（简化版本：每30个分片标记为关键帧）
         let is_keyframe = self.segments_read % 30 == 1;
 
         let segment = VideoSegment {
             segment_id: Uuid::new_v4(),
-            timestamp,
-            duration,
+            pts: timestamp,
+            dts: timestamp,
+            duration: duration as u32,
             data: buffer,
             is_keyframe,
+            decode_only: false,
+            discontinuity: false,
             format: self.config.format,
             source_type: SegmentSourceType::Playback,
             receive_time: Some(std::time::SystemTime::now()),
             forward_time: None,
+            device_send_time: None,
         };
 
         debug!(
             "Read segment {}: {} bytes at {:.3}s (offset: {}/{})",
-            segment.segment_id, bytes_read, timestamp, self.current_offset, self.file_size
+            segment.segment_id, bytes_read, segment.pts_secs(), self.current_offset, self.file_size
         );
 
         Ok(Some(segment))
     }
 
+    /// 读取下一个访问单元
+    async fn read_access_units(&mut self) -> Result<Option<VideoSegment>, StreamError> {
+        let start = self.next_unit;
+        if start >= self.access_units.len() {
+            debug!("Reached end of file: {:?}", self.file_path);
+            return Ok(None);
+        }
+
+        let end = start + 1;
+        let first = self.access_units[start];
+        let data_end = first.offset + first.size as u64;
+        // 按解码时间计算时长，MP4中帧时长可以不固定
+        let decode_end = self
+            .access_units
+            .get(end)
+            .map_or(first.decode_timestamp + first.duration, |u| u.decode_timestamp);
+
+        let buffer = if let Some(ref demuxed) = self.demuxed {
+            demuxed[first.offset as usize..data_end as usize].to_vec()
+        } else {
+            if self.current_offset != first.offset {
+                self.file
+                    .seek(std::io::SeekFrom::Start(first.offset))
+                    .await
+                    .map_err(|e| StreamError::FileReadError(e.to_string()))?;
+            }
+            let mut buffer = vec![0u8; (data_end - first.offset) as usize];
+            self.file.read_exact(&mut buffer).await.map_err(|e| {
+                warn!("Failed to read from file {:?}: {}", self.file_path, e);
+                StreamError::FileReadError(e.to_string())
+            })?;
+            buffer
+        };
+
+        self.current_offset = data_end;
+        self.next_unit = end;
+        self.segments_read += 1;
+
+        let segment = VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: VideoSegment::ticks(first.timestamp),
+            dts: VideoSegment::ticks(first.decode_timestamp),
+            duration: (VideoSegment::ticks(decode_end) - VideoSegment::ticks(first.decode_timestamp)) as u32,
+            data: buffer,
+            is_keyframe: first.is_keyframe,
+            decode_only: false,
+            discontinuity: false,
+            // 访问单元总是Annex B（MP4已解复用）
+            format: SegmentFormat::raw_video(self.codec),
+            source_type: SegmentSourceType::Playback,
+            receive_time: Some(std::time::SystemTime::now()),
+            forward_time: None,
+            device_send_time: None,
+        };
+
+        debug!(
+            "Read frame {} ({} bytes) at {:.3}s (offset: {}/{})",
+            start,
+            segment.data.len(),
+            segment.pts_secs(),
+            self.current_offset,
+            self.file_size
+        );
+
+        Ok(Some(segment))
+    }
+
+    /// 读取指定访问单元的数据（不改变顺序读取位置）
+    pub async fn read_unit(&mut self, unit: &AccessUnit) -> Result<Vec<u8>, StreamError> {
+        let start = unit.offset as usize;
+        let end = start + unit.size as usize;
+        if let Some(ref demuxed) = self.demuxed {
+            return demuxed
+                .get(start..end)
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| StreamError::FileReadError("Read beyond end of stream".to_string()));
+        }
+
+        self.file
+            .seek(std::io::SeekFrom::Start(unit.offset))
+            .await
+            .map_err(|e| StreamError::FileReadError(e.to_string()))?;
+        let mut buffer = vec![0u8; unit.size as usize];
+        let result = self.file.read_exact(&mut buffer).await;
+        self.file
+            .seek(std::io::SeekFrom::Start(self.current_offset))
+            .await
+            .map_err(|e| StreamError::FileReadError(e.to_string()))?;
+        result.map_err(|e| {
+            warn!("Failed to read from file {:?}: {}", self.file_path, e);
+            StreamError::FileReadError(e.to_string())
+        })?;
+        Ok(buffer)
+    }
+
     /// 读取下一个视频分片（带速率控制）
     ///
     /// 此方法会根据播放速率自动控制分片发送间隔，确保播放速度符合预期。
@@ -285,7 +486,7 @@ impl FileStreamReader {
 
         if let Some(ref seg) = segment {
             // 计算应该等待的时间
-            let delay = self.calculate_rate_controlled_delay(seg.duration);
+            let delay = self.calculate_rate_controlled_delay(seg.duration_secs());
 
             if delay > std::time::Duration::ZERO {
                 debug!(
@@ -396,7 +597,16 @@ impl FileStreamReader {
     ///
     /// 成功返回Ok(())，失败返回错误
     pub async fn seek_to_offset(&mut self, offset: u64) -> Result<(), StreamError> {
-        let target_offset = offset.min(self.file_size);
+        let mut target_offset = offset.min(self.file_size);
+
+        // 按访问单元分片时对齐到不早于该偏移的第一个帧边界
+        if self.is_frame_aligned() {
+            self.next_unit = self.access_units.partition_point(|u| u.offset < target_offset);
+            target_offset = self
+                .access_units
+                .get(self.next_unit)
+                .map_or(self.file_size, |u| u.offset);
+        }
 
         debug!(
             "Seeking to offset: {} (file: {:?})",
@@ -420,30 +630,41 @@ impl FileStreamReader {
 
     /// 定位到指定的时间位置（秒）
     ///
+    /// 按关键帧索引定位到不晚于目标时间的最近关键帧；无法解析访问单元的文件
+    /// 按每个分片一帧换算为分片位置。
+    ///
     /// # 参数
     ///
     /// - `time_seconds`: 时间位置（秒）
-    /// - `duration_seconds`: 视频总时长（秒）
     ///
     /// # 返回
     ///
     /// 成功返回Ok(())，失败返回错误
-    pub async fn seek_to_time(
-        &mut self,
-        time_seconds: f64,
-        duration_seconds: f64,
-    ) -> Result<(), StreamError> {
-        if time_seconds < 0.0 || time_seconds > duration_seconds {
+    pub async fn seek_to_time(&mut self, time_seconds: f64) -> Result<(), StreamError> {
+        if !(0.0..=self.duration()).contains(&time_seconds) {
             return Err(StreamError::InvalidSeekPosition(time_seconds));
         }
 
-        let position = if duration_seconds > 0.0 {
-            time_seconds / duration_seconds
-        } else {
-            0.0
-        };
+        if !self.is_frame_aligned() {
+            let segment = (time_seconds * self.frame_rate).round() as u64;
+            return self.seek_to_offset(segment * self.config.segment_size as u64).await;
+        }
 
-        self.seek_to_position(position).await
+        let offset = match self
+            .keyframes
+            .iter()
+            .rev()
+            .find(|&&k| self.access_units[k].timestamp <= time_seconds)
+        {
+            Some(&k) => self.access_units[k].offset,
+            // 没有关键帧（或目标早于第一个关键帧）时从头开始
+            None => 0,
+        };
+        debug!(
+            "Seeking to {:.3}s via keyframe index (offset: {})",
+            time_seconds, offset
+        );
+        self.seek_to_offset(offset).await
     }
 
     /// 统一的定位方法
@@ -465,7 +686,7 @@ impl FileStreamReader {
     /// reader.seek_to(SeekTarget::Position(0.5)).await?;
     ///
     /// // 定位到30秒
-    /// reader.seek_to(SeekTarget::Time { seconds: 30.0, duration: 100.0 }).await?;
+    /// reader.seek_to(SeekTarget::Time(30.0)).await?;
     ///
     /// // 定位到字节偏移量
     /// reader.seek_to(SeekTarget::Offset(12345)).await?;
@@ -474,7 +695,7 @@ impl FileStreamReader {
         match target {
             SeekTarget::Position(position) => self.seek_to_position(position).await,
             SeekTarget::Offset(offset) => self.seek_to_offset(offset).await,
-            SeekTarget::Time { seconds, duration } => self.seek_to_time(seconds, duration).await,
+            SeekTarget::Time(seconds) => self.seek_to_time(seconds).await,
         }
     }
 
@@ -568,23 +789,6 @@ impl FileStreamReader {
         &self.file_path
     }
 
-    /// 计算时间戳（基于进度）
-    fn calculate_timestamp(&self, progress: f64) -> f64 {
-        // 简化版本：假设视频时长与文件大小成正比
-        // 实际实现应该解析视频文件头获取真实时长
-        let estimated_duration = self.file_size as f64 / (1024.0 * 1024.0) * 10.0; // 假设1MB=10秒
-        progress * estimated_duration
-    }
-
-    /// 计算分片时长（基于分片大小）
-    fn calculate_duration(&self, bytes: usize) -> f64 {
-        // 简化计算：假设固定的帧时长
-        // 对于30fps视频，每帧约0.033秒
-        // 假设每个分片包含1帧的数据
-        let frames_per_segment = 1.0;
-        frames_per_segment / self.config.assumed_fps
-    }
-
     /// 重置读取器到文件开始
     pub async fn reset(&mut self) -> Result<(), StreamError> {
         debug!("Resetting file reader: {:?}", self.file_path);
@@ -610,6 +814,26 @@ mod tests {
         file
     }
 
+    /// 生成 H.264 裸流文件：`gops` 个 GOP，每个 GOP 30 帧（默认 30fps 下为 1 秒），
+    /// 非关键帧大小不一（模拟 VBR）
+    async fn create_h264_file(gops: usize) -> NamedTempFile {
+        let mut data = Vec::new();
+        for gop in 0..gops {
+            data.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, gop as u8]);
+            data.extend_from_slice(&vec![0xaa; 2000]);
+            for frame in 1..30 {
+                data.extend_from_slice(&[0, 0, 0, 1, 0x41, 0x9a]);
+                data.extend_from_slice(&vec![0x55; frame * 20]);
+            }
+        }
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
     #[tokio::test]
     async fn test_file_reader_creation() {
         let temp_file = create_test_file(100000).await;
@@ -715,21 +939,101 @@ mod tests {
 
     #[tokio::test]
     async fn test_file_reader_seek_time() {
-        let temp_file = create_test_file(100000).await;
+        let temp_file = create_h264_file(4).await;
         let mut reader = FileStreamReader::new(
             temp_file.path().to_path_buf(),
             FileReaderConfig::default(),
         )
         .await
         .unwrap();
+        assert_eq!(reader.duration(), 4.0);
 
-        // 定位到30秒（总时长100秒）
-        reader.seek_to_time(30.0, 100.0).await.unwrap();
-        assert_eq!(reader.get_progress(), 0.3);
+        // 定位到2.5秒：从2秒处的关键帧开始
+        reader.seek_to_time(2.5).await.unwrap();
+        let segment = reader.read_segment().await.unwrap().unwrap();
+        assert!(segment.is_keyframe);
+        assert_eq!(segment.pts, 180_000);
+        assert_eq!(segment.data[22], 2);
 
         // 无效时间
-        assert!(reader.seek_to_time(-10.0, 100.0).await.is_err());
-        assert!(reader.seek_to_time(150.0, 100.0).await.is_err());
+        assert!(reader.seek_to_time(-10.0).await.is_err());
+        assert!(reader.seek_to_time(150.0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_reader_access_unit_segments() {
+        let temp_file = create_h264_file(2).await;
+        let mut reader = FileStreamReader::new(
+            temp_file.path().to_path_buf(),
+            FileReaderConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert!(reader.is_frame_aligned());
+        assert_eq!(reader.frame_rate(), 30.0);
+
+        // 每个分片一帧，时间戳按帧序号递增，与帧大小无关
+        let mut segments = Vec::new();
+        while let Some(segment) = reader.read_segment().await.unwrap() {
+            segments.push(segment);
+        }
+        assert_eq!(segments.len(), 60);
+        assert!(segments[0].is_keyframe && segments[30].is_keyframe);
+        assert!(!segments[1].is_keyframe);
+        assert_eq!(segments[1].data.len(), 6 + 20);
+        assert_eq!(segments[30].pts, 90_000);
+        assert_eq!(segments[45].pts, 135_000);
+        assert!(segments.iter().all(|s| s.duration == 3_000 && s.dts == s.pts));
+        assert_eq!(reader.get_progress(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_file_reader_mp4_segments() {
+        use crate::streaming::fmp4_converter::{FMP4Converter, FMP4ConverterConfig};
+
+        // 把H.264裸流封装为分片MP4，每帧一个moof/mdat
+        let h264_file = create_h264_file(2).await;
+        let stream = ElementaryStream::parse(std::fs::read(h264_file.path()).unwrap(), 30.0);
+        let mut converter = FMP4Converter::new(FMP4ConverterConfig::default());
+        converter.set_parameter_sets(stream.parameter_sets().unwrap());
+        let mut mp4 = converter.generate_init_segment().unwrap();
+        for unit in &stream.units {
+            let segment = VideoSegment {
+                segment_id: Uuid::new_v4(),
+                pts: VideoSegment::ticks(unit.timestamp),
+                dts: VideoSegment::ticks(unit.decode_timestamp),
+                duration: VideoSegment::ticks(unit.duration) as u32,
+                data: stream.unit_data(unit).to_vec(),
+                is_keyframe: unit.is_keyframe,
+                decode_only: false,
+                discontinuity: false,
+                format: SegmentFormat::H264Raw,
+                source_type: SegmentSourceType::Playback,
+                receive_time: None,
+                forward_time: None,
+                device_send_time: None,
+            };
+            mp4.extend(converter.convert_segment(segment).unwrap().data);
+        }
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&mp4).unwrap();
+        file.flush().unwrap();
+
+        let mut reader = FileStreamReader::new(file.path().to_path_buf(), FileReaderConfig::default())
+            .await
+            .unwrap();
+        assert!(reader.is_frame_aligned());
+        assert!((reader.frame_rate() - 30.0).abs() < 0.01);
+        assert_eq!(reader.keyframe_index().len(), 2);
+
+        // 分片是解复用后的Annex B访问单元，关键帧带SPS/PPS
+        reader.seek_to_time(1.2).await.unwrap();
+        let segment = reader.read_segment().await.unwrap().unwrap();
+        assert_eq!(segment.format, SegmentFormat::H264Raw);
+        assert!(segment.is_keyframe);
+        assert_eq!(segment.pts, 90_000);
+        assert_eq!(&segment.data[..5], &[0, 0, 0, 1, 0x67]);
+        assert_eq!(segment.data, stream.unit_data(&stream.units[30]));
     }
 
     #[tokio::test]
@@ -1058,50 +1362,27 @@ mod tests {
 
     #[tokio::test]
     async fn test_seek_to_time() {
-        let temp_file = create_test_file(100000).await;
+        let temp_file = create_h264_file(4).await;
         let mut reader = FileStreamReader::new(
             temp_file.path().to_path_buf(),
             FileReaderConfig::default(),
         )
         .await
         .unwrap();
+        let index = reader.keyframe_index();
 
-        // 测试定位到不同时间位置
-        reader
-            .seek_to(SeekTarget::Time {
-                seconds: 25.0,
-                duration: 100.0,
-            })
-            .await
-            .unwrap();
-        assert_eq!(reader.get_progress(), 0.25);
+        // 测试定位到不同时间位置（对齐到之前最近的关键帧）
+        reader.seek_to(SeekTarget::Time(1.0)).await.unwrap();
+        assert_eq!(reader.get_offset(), index[1].file_offset);
 
-        reader
-            .seek_to(SeekTarget::Time {
-                seconds: 60.0,
-                duration: 100.0,
-            })
-            .await
-            .unwrap();
-        assert_eq!(reader.get_progress(), 0.6);
+        reader.seek_to(SeekTarget::Time(3.9)).await.unwrap();
+        assert_eq!(reader.get_offset(), index[3].file_offset);
 
-        reader
-            .seek_to(SeekTarget::Time {
-                seconds: 0.0,
-                duration: 100.0,
-            })
-            .await
-            .unwrap();
+        reader.seek_to(SeekTarget::Time(0.0)).await.unwrap();
         assert_eq!(reader.get_offset(), 0);
 
-        reader
-            .seek_to(SeekTarget::Time {
-                seconds: 100.0,
-                duration: 100.0,
-            })
-            .await
-            .unwrap();
-        assert_eq!(reader.get_offset(), 100000);
+        reader.seek_to(SeekTarget::Time(4.0)).await.unwrap();
+        assert_eq!(reader.get_offset(), index[3].file_offset);
     }
 
     #[tokio::test]
@@ -1165,7 +1446,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_seek_to_invalid_time() {
-        let temp_file = create_test_file(100000).await;
+        let temp_file = create_h264_file(4).await;
         let mut reader = FileStreamReader::new(
             temp_file.path().to_path_buf(),
             FileReaderConfig::default(),
@@ -1175,10 +1456,7 @@ mod tests {
 
         // 测试无效时间（负数）
         let result = reader
-            .seek_to(SeekTarget::Time {
-                seconds: -10.0,
-                duration: 100.0,
-            })
+            .seek_to(SeekTarget::Time(-10.0))
             .await;
         assert!(result.is_err());
         assert!(matches!(
@@ -1188,10 +1466,7 @@ mod tests {
 
         // 测试无效时间（超过总时长）
         let result = reader
-            .seek_to(SeekTarget::Time {
-                seconds: 150.0,
-                duration: 100.0,
-            })
+            .seek_to(SeekTarget::Time(150.0))
             .await;
         assert!(result.is_err());
         assert!(matches!(
@@ -1254,40 +1529,36 @@ mod tests {
 
     #[tokio::test]
     async fn test_seek_to_with_different_target_types() {
-        let temp_file = create_test_file(100000).await;
+        let temp_file = create_h264_file(2).await;
         let mut reader = FileStreamReader::new(
             temp_file.path().to_path_buf(),
             FileReaderConfig::default(),
         )
         .await
         .unwrap();
+        let keyframe = reader.keyframe_index()[1].file_offset;
 
         // 使用Position定位
-        reader.seek_to(SeekTarget::Position(0.25)).await.unwrap();
+        let position = keyframe as f64 / reader.get_file_size() as f64;
+        reader.seek_to(SeekTarget::Position(position)).await.unwrap();
         let pos1 = reader.get_offset();
 
         // 使用Offset定位到相同位置
-        reader.seek_to(SeekTarget::Offset(25000)).await.unwrap();
+        reader.seek_to(SeekTarget::Offset(keyframe)).await.unwrap();
         let pos2 = reader.get_offset();
 
         // 使用Time定位到相同位置
-        reader
-            .seek_to(SeekTarget::Time {
-                seconds: 25.0,
-                duration: 100.0,
-            })
-            .await
-            .unwrap();
+        reader.seek_to(SeekTarget::Time(1.0)).await.unwrap();
         let pos3 = reader.get_offset();
 
         // 三种方式应该定位到相同位置
-        assert_eq!(pos1, 25000);
-        assert_eq!(pos2, 25000);
-        assert_eq!(pos3, 25000);
+        assert_eq!(pos1, keyframe);
+        assert_eq!(pos2, keyframe);
+        assert_eq!(pos3, keyframe);
     }
 
     #[tokio::test]
-    async fn test_seek_to_zero_duration() {
+    async fn test_seek_to_time_chunk_fallback() {
         let temp_file = create_test_file(100000).await;
         let mut reader = FileStreamReader::new(
             temp_file.path().to_path_buf(),
@@ -1296,14 +1567,8 @@ mod tests {
         .await
         .unwrap();
 
-        // 测试总时长为0的情况
-        reader
-            .seek_to(SeekTarget::Time {
-                seconds: 0.0,
-                duration: 0.0,
-            })
-            .await
-            .unwrap();
+        // 无法解析帧的文件按分片换算时间，定位到0秒
+        reader.seek_to(SeekTarget::Time(0.0)).await.unwrap();
         assert_eq!(reader.get_offset(), 0);
     }
 }
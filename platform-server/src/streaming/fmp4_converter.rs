@@ -1,16 +1,22 @@
 // 统一低延迟视频流传输系统 - fMP4转换器实现
 //
-// 本模块实现了H.264裸流到fMP4格式的转换。
+// 本模块实现了H.264/H.265裸流到fMP4格式的转换。
 //
 // # 特性
 //
-// - 生成fMP4初始化分片（init segment）
+// - 生成fMP4初始化分片（init segment），H.264写入avc1/avcC，H.265写入hvc1/hvcC
 // - 转换媒体分片（media segment）
 // - 保持时间戳和关键帧信息
 // - 支持MSE播放器
+// - 精确定位时通过编辑列表（elst）跳过预解码帧
+// - 可选的AAC音频轨道（mp4a/esds），与视频共用分片时间轴
 
 use super::source::{SegmentFormat, SegmentSourceType, StreamError, VideoSegment};
 use bytes::{BufMut, BytesMut};
+use common::aac::{self, AudioSpecificConfig};
+use common::demux::{self, ParameterSets};
+use common::utils::rescale_timestamp;
+use common::{h264, hevc, VideoCodec};
 use tracing::{debug, warn};
 use uuid::Uuid;
 
@@ -48,11 +54,14 @@ pub enum BoxType {
     Mvhd = 0x6d766864, // 'mvhd'
     Trak = 0x7472616b, // 'trak'
     Tkhd = 0x746b6864, // 'tkhd'
+    Edts = 0x65647473, // 'edts'
+    Elst = 0x656c7374, // 'elst'
     Mdia = 0x6d646961, // 'mdia'
     Mdhd = 0x6d646864, // 'mdhd'
     Hdlr = 0x68646c72, // 'hdlr'
     Minf = 0x6d696e66, // 'minf'
     Vmhd = 0x766d6864, // 'vmhd'
+    Smhd = 0x736d6864, // 'smhd'
     Dinf = 0x64696e66, // 'dinf'
     Dref = 0x64726566, // 'dref'
     Stbl = 0x7374626c, // 'stbl'
@@ -70,8 +79,31 @@ pub enum BoxType {
     Trun = 0x7472756e, // 'trun'
     Avc1 = 0x61766331, // 'avc1'
     AvcC = 0x61766343, // 'avcC'
+    Hvc1 = 0x68766331, // 'hvc1'
+    HvcC = 0x68766343, // 'hvcC'
+    Mp4a = 0x6d703461, // 'mp4a'
+    Esds = 0x65736473, // 'esds'
 }
 
+/// 轨道类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Track {
+    Video,
+    Audio,
+}
+
+impl Track {
+    fn id(self) -> u32 {
+        match self {
+            Track::Video => 1,
+            Track::Audio => 2,
+        }
+    }
+}
+
+/// trun中的一个样本：(时长, 大小, 标志, 显示时间相对解码时间的偏移)
+type Sample = (u32, u32, u32, i32);
+
 /// fMP4转换器配置
 #[derive(Debug, Clone)]
 pub struct FMP4ConverterConfig {
@@ -98,7 +130,7 @@ impl Default for FMP4ConverterConfig {
 
 /// fMP4转换器
 ///
-/// 将H.264裸流转换为fMP4格式，用于MSE播放器。
+/// 将H.264/H.265裸流转换为fMP4格式，用于MSE播放器。
 ///
 /// # 示例
 ///
@@ -115,6 +147,12 @@ impl Default for FMP4ConverterConfig {
 pub struct FMP4Converter {
     config: FMP4ConverterConfig,
     sequence_number: u32,
+    /// 呈现起点（秒），精确定位时为目标帧的媒体时间
+    presentation_offset: Option<f64>,
+    /// 参数集，设置后写入avcC/hvcC并把样本转换为长度前缀格式
+    parameter_sets: Option<ParameterSets>,
+    /// AAC解码配置，设置后初始化分片包含音频轨道
+    audio_config: Option<AudioSpecificConfig>,
 }
 
 impl FMP4Converter {
@@ -124,9 +162,40 @@ impl FMP4Converter {
         Self {
             config,
             sequence_number: 0,
+            presentation_offset: None,
+            parameter_sets: None,
+            audio_config: None,
         }
     }
 
+    /// 设置参数集（不含起始码）
+    ///
+    /// 设置后初始化分片写入完整的avcC（H.264）或hvcC（H.265），媒体分片中的NAL转换为
+    /// 4字节长度前缀，生成的fMP4可直接由浏览器解码。
+    pub fn set_parameter_sets(&mut self, parameter_sets: ParameterSets) {
+        self.parameter_sets = Some(parameter_sets);
+    }
+
+    /// 设置音频轨道的解码配置
+    ///
+    /// 设置后初始化分片增加第二个轨道（track 2，mp4a/esds，时间刻度为采样率），
+    /// AAC分片转换为该轨道的媒体分片。两个轨道的 `tfdt` 都由分片时间戳换算，
+    /// 设备端同一时间轴上的音视频在播放器中保持对齐。需要重新生成初始化分片。
+    pub fn set_audio_config(&mut self, config: Option<AudioSpecificConfig>) {
+        debug!("Setting fMP4 audio config: {:?}", config);
+        self.audio_config = config;
+    }
+
+    /// 设置呈现起点（秒）
+    ///
+    /// 精确定位时从前一个关键帧开始发送，分片的 `tfdt` 仍是媒体时间。初始化分片中写入
+    /// 编辑列表（elst），`media_time` 指向目标帧，播放器从目标帧开始显示，之前的预解码帧
+    /// 只解码不显示。设置后需要重新生成并追加初始化分片。
+    pub fn set_presentation_offset(&mut self, offset: Option<f64>) {
+        debug!("Setting fMP4 presentation offset: {:?}", offset);
+        self.presentation_offset = offset;
+    }
+
     /// 生成初始化分片（init segment）
     ///
     /// 初始化分片包含ftyp和moov box，用于初始化MSE播放器。
@@ -149,48 +218,88 @@ impl FMP4Converter {
         Ok(buffer.to_vec())
     }
 
-    /// 转换H.264分片为fMP4媒体分片
+    /// 转换H.264/H.265/AAC分片为fMP4媒体分片
     ///
     /// # 参数
     ///
-    /// - `segment`: H.264视频分片
+    /// - `segment`: 裸流视频分片或AAC音频分片
     ///
     /// # 返回
     ///
     /// 返回fMP4格式的分片或错误
     pub fn convert_segment(&mut self, segment: VideoSegment) -> Result<VideoSegment, StreamError> {
-        if segment.format != SegmentFormat::H264Raw {
-            return Err(StreamError::Internal(
-                "Only H264Raw format can be converted to fMP4".to_string(),
-            ));
-        }
-
-        debug!(
-            "Converting H.264 segment {} to fMP4 (size: {} bytes)",
-            segment.segment_id,
-            segment.data.len()
-        );
+        let (track, samples, mdat) = match segment.format {
+            SegmentFormat::H264Raw | SegmentFormat::H265Raw => {
+                debug!(
+                    "Converting {:?} segment {} to fMP4 (size: {} bytes)",
+                    segment.format,
+                    segment.segment_id,
+                    segment.data.len()
+                );
+                let sample = self.sample_data(&segment);
+                let timescale = self.config.timescale;
+                let duration = rescale_timestamp(segment.duration as i64, VideoSegment::TIMESCALE, timescale) as u32;
+                // 有 B 帧时显示时间晚于解码时间，写入 trun 的 composition time offset
+                let composition_offset =
+                    rescale_timestamp(segment.pts - segment.dts, VideoSegment::TIMESCALE, timescale) as i32;
+                // sample_flags：关键帧不依赖其他帧，非关键帧依赖其他帧且不是同步样本
+                let flags = if segment.is_keyframe { 0x0200_0000 } else { 0x0101_0000 };
+                (Track::Video, vec![(duration, sample.len() as u32, flags, composition_offset)], sample)
+            }
+            SegmentFormat::Aac => {
+                if self.audio_config.is_none() {
+                    return Err(StreamError::Internal(
+                        "Audio track is not configured".to_string(),
+                    ));
+                }
+                let frames = aac::adts_frames(&segment.data);
+                if frames.is_empty() {
+                    return Err(StreamError::SegmentCorrupted);
+                }
+                debug!(
+                    "Converting AAC segment {} to fMP4 ({} frames)",
+                    segment.segment_id,
+                    frames.len()
+                );
+                // 去掉ADTS帧头，每帧一个样本
+                let samples = frames
+                    .iter()
+                    .map(|frame| (frame.samples, frame.payload.len() as u32, 0x0200_0000, 0))
+                    .collect();
+                let mdat = frames.iter().flat_map(|frame| frame.payload.iter().copied()).collect();
+                (Track::Audio, samples, mdat)
+            }
+            _ => {
+                return Err(StreamError::Internal(
+                    "Only raw video and AAC formats can be converted to fMP4".to_string(),
+                ));
+            }
+        };
 
         let mut buffer = BytesMut::new();
 
         // 写入moof box
-        self.write_moof_box(&mut buffer, &segment)?;
+        self.write_moof_box(&mut buffer, track, &segment, &samples)?;
 
         // 写入mdat box
-        self.write_mdat_box(&mut buffer, &segment)?;
+        self.write_box(&mut buffer, BoxType::Mdat, &mdat);
 
         self.sequence_number += 1;
 
         let fmp4_segment = VideoSegment {
             segment_id: segment.segment_id,
-            timestamp: segment.timestamp,
+            pts: segment.pts,
+            dts: segment.dts,
             duration: segment.duration,
             data: buffer.to_vec(),
             is_keyframe: segment.is_keyframe,
+            decode_only: segment.decode_only,
+            discontinuity: segment.discontinuity,
             format: SegmentFormat::FMP4,
             source_type: segment.source_type,
             receive_time: segment.receive_time,
             forward_time: segment.forward_time,
+            device_send_time: segment.device_send_time,
         };
 
         debug!(
@@ -223,7 +332,10 @@ impl FMP4Converter {
         self.write_mvhd_box(&mut moov_data)?;
 
         // trak box
-        self.write_trak_box(&mut moov_data)?;
+        self.write_trak_box(&mut moov_data, Track::Video)?;
+        if self.audio_config.is_some() {
+            self.write_trak_box(&mut moov_data, Track::Audio)?;
+        }
 
         // mvex box
         self.write_mvex_box(&mut moov_data)?;
@@ -263,41 +375,52 @@ impl FMP4Converter {
             data.put_u32(0);
         }
         
-        data.put_u32(2); // next_track_ID
+        data.put_u32(if self.audio_config.is_some() { 3 } else { 2 }); // next_track_ID
 
         self.write_box(buffer, BoxType::Mvhd, &data);
         Ok(())
     }
 
+    /// 轨道的时间刻度：视频使用配置值，音频使用采样率
+    fn track_timescale(&self, track: Track) -> u32 {
+        match (track, &self.audio_config) {
+            (Track::Audio, Some(config)) => config.sample_rate(),
+            _ => self.config.timescale,
+        }
+    }
+
     /// 写入trak box（track）
-    fn write_trak_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
+    fn write_trak_box(&self, buffer: &mut BytesMut, track: Track) -> Result<(), StreamError> {
         let mut trak_data = BytesMut::new();
 
         // tkhd box
-        self.write_tkhd_box(&mut trak_data)?;
+        self.write_tkhd_box(&mut trak_data, track)?;
+
+        // edts box（精确定位）
+        self.write_edts_box(&mut trak_data, track)?;
 
         // mdia box
-        self.write_mdia_box(&mut trak_data)?;
+        self.write_mdia_box(&mut trak_data, track)?;
 
         self.write_box(buffer, BoxType::Trak, &trak_data);
         Ok(())
     }
 
     /// 写入tkhd box（track header）
-    fn write_tkhd_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
+    fn write_tkhd_box(&self, buffer: &mut BytesMut, track: Track) -> Result<(), StreamError> {
         let mut data = BytesMut::new();
         
         data.put_u8(1); // version
         data.put_u24(0x000007); // flags (track enabled, in movie, in preview)
         data.put_u64(0); // creation_time
         data.put_u64(0); // modification_time
-        data.put_u32(1); // track_ID
+        data.put_u32(track.id()); // track_ID
         data.put_u32(0); // reserved
         data.put_u64(0); // duration
         data.put_u64(0); // reserved
         data.put_u16(0); // layer
         data.put_u16(0); // alternate_group
-        data.put_u16(0); // volume
+        data.put_u16(if track == Track::Audio { 0x0100 } else { 0 }); // volume
         data.put_u16(0); // reserved
         
         // matrix
@@ -311,39 +434,64 @@ impl FMP4Converter {
         data.put_u32(0);
         data.put_u32(0x40000000);
         
-        data.put_u32((self.config.width as u32) << 16); // width
-        data.put_u32((self.config.height as u32) << 16); // height
+        let (width, height) = match track {
+            Track::Video => (self.config.width as u32, self.config.height as u32),
+            Track::Audio => (0, 0),
+        };
+        data.put_u32(width << 16); // width
+        data.put_u32(height << 16); // height
 
         self.write_box(buffer, BoxType::Tkhd, &data);
         Ok(())
     }
 
+    /// 写入edts box（edit list），没有设置呈现起点时不写入
+    fn write_edts_box(&self, buffer: &mut BytesMut, track: Track) -> Result<(), StreamError> {
+        let Some(offset) = self.presentation_offset else {
+            return Ok(());
+        };
+
+        let mut elst_data = BytesMut::new();
+        elst_data.put_u8(1); // version
+        elst_data.put_u24(0); // flags
+        elst_data.put_u32(1); // entry_count
+        elst_data.put_u64(0); // segment_duration（0表示到片段末尾）
+        elst_data.put_i64((offset * self.track_timescale(track) as f64).round() as i64); // media_time
+        elst_data.put_i16(1); // media_rate_integer
+        elst_data.put_i16(0); // media_rate_fraction
+
+        let mut edts_data = BytesMut::new();
+        self.write_box(&mut edts_data, BoxType::Elst, &elst_data);
+        self.write_box(buffer, BoxType::Edts, &edts_data);
+        Ok(())
+    }
+
     /// 写入mdia box（media）
-    fn write_mdia_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
+    fn write_mdia_box(&self, buffer: &mut BytesMut, track: Track) -> Result<(), StreamError> {
         let mut mdia_data = BytesMut::new();
 
         // mdhd box
-        self.write_mdhd_box(&mut mdia_data)?;
+        self.write_mdhd_box(&mut mdia_data, track)?;
 
         // hdlr box
-        self.write_hdlr_box(&mut mdia_data)?;
+        self.write_hdlr_box(&mut mdia_data, track)?;
 
         // minf box
-        self.write_minf_box(&mut mdia_data)?;
+        self.write_minf_box(&mut mdia_data, track)?;
 
         self.write_box(buffer, BoxType::Mdia, &mdia_data);
         Ok(())
     }
 
     /// 写入mdhd box（media header）
-    fn write_mdhd_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
+    fn write_mdhd_box(&self, buffer: &mut BytesMut, track: Track) -> Result<(), StreamError> {
         let mut data = BytesMut::new();
         
         data.put_u8(1); // version
         data.put_u24(0); // flags
         data.put_u64(0); // creation_time
         data.put_u64(0); // modification_time
-        data.put_u32(self.config.timescale); // timescale
+        data.put_u32(self.track_timescale(track)); // timescale
         data.put_u64(0); // duration
         data.put_u16(0x55c4); // language (und)
         data.put_u16(0); // pre_defined
@@ -353,34 +501,41 @@ impl FMP4Converter {
     }
 
     /// 写入hdlr box（handler）
-    fn write_hdlr_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
+    fn write_hdlr_box(&self, buffer: &mut BytesMut, track: Track) -> Result<(), StreamError> {
         let mut data = BytesMut::new();
         
         data.put_u8(0); // version
         data.put_u24(0); // flags
         data.put_u32(0); // pre_defined
-        data.extend_from_slice(b"vide"); // handler_type
+        let (handler_type, name): (&[u8; 4], &[u8]) = match track {
+            Track::Video => (b"vide", b"VideoHandler\0"),
+            Track::Audio => (b"soun", b"SoundHandler\0"),
+        };
+        data.extend_from_slice(handler_type); // handler_type
         data.put_u32(0); // reserved
         data.put_u32(0); // reserved
         data.put_u32(0); // reserved
-        data.extend_from_slice(b"VideoHandler\0"); // name
+        data.extend_from_slice(name); // name
 
         self.write_box(buffer, BoxType::Hdlr, &data);
         Ok(())
     }
 
     /// 写入minf box（media information）
-    fn write_minf_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
+    fn write_minf_box(&self, buffer: &mut BytesMut, track: Track) -> Result<(), StreamError> {
         let mut minf_data = BytesMut::new();
 
-        // vmhd box
-        self.write_vmhd_box(&mut minf_data)?;
+        // vmhd/smhd box
+        match track {
+            Track::Video => self.write_vmhd_box(&mut minf_data)?,
+            Track::Audio => self.write_smhd_box(&mut minf_data)?,
+        }
 
         // dinf box
         self.write_dinf_box(&mut minf_data)?;
 
         // stbl box
-        self.write_stbl_box(&mut minf_data)?;
+        self.write_stbl_box(&mut minf_data, track)?;
 
         self.write_box(buffer, BoxType::Minf, &minf_data);
         Ok(())
@@ -401,6 +556,19 @@ impl FMP4Converter {
         Ok(())
     }
 
+    /// 写入smhd box（sound media header）
+    fn write_smhd_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
+        let mut data = BytesMut::new();
+
+        data.put_u8(0); // version
+        data.put_u24(0); // flags
+        data.put_u16(0); // balance
+        data.put_u16(0); // reserved
+
+        self.write_box(buffer, BoxType::Smhd, &data);
+        Ok(())
+    }
+
     /// 写入dinf box（data information）
     fn write_dinf_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
         let mut dinf_data = BytesMut::new();
@@ -423,11 +591,14 @@ impl FMP4Converter {
     }
 
     /// 写入stbl box（sample table）
-    fn write_stbl_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
+    fn write_stbl_box(&self, buffer: &mut BytesMut, track: Track) -> Result<(), StreamError> {
         let mut stbl_data = BytesMut::new();
 
         // stsd box (sample description)
-        self.write_stsd_box(&mut stbl_data)?;
+        match (track, &self.audio_config) {
+            (Track::Audio, Some(config)) => self.write_audio_stsd_box(&mut stbl_data, config)?,
+            _ => self.write_stsd_box(&mut stbl_data)?,
+        }
 
         // stts box (time-to-sample)
         let mut stts_data = BytesMut::new();
@@ -470,43 +641,128 @@ impl FMP4Converter {
         stsd_data.put_u24(0); // flags
         stsd_data.put_u32(1); // entry_count
 
-        // avc1 box
-        let mut avc1_data = BytesMut::new();
-        avc1_data.put_u48(0); // reserved
-        avc1_data.put_u16(1); // data_reference_index
-        avc1_data.put_u16(0); // pre_defined
-        avc1_data.put_u16(0); // reserved
-        avc1_data.put_u32(0); // pre_defined
-        avc1_data.put_u32(0); // pre_defined
-        avc1_data.put_u32(0); // pre_defined
-        avc1_data.put_u16(self.config.width); // width
-        avc1_data.put_u16(self.config.height); // height
-        avc1_data.put_u32(0x00480000); // horizresolution
-        avc1_data.put_u32(0x00480000); // vertresolution
-        avc1_data.put_u32(0); // reserved
-        avc1_data.put_u16(1); // frame_count
+        // VisualSampleEntry 公共字段
+        let mut entry_data = BytesMut::new();
+        entry_data.put_u48(0); // reserved
+        entry_data.put_u16(1); // data_reference_index
+        entry_data.put_u16(0); // pre_defined
+        entry_data.put_u16(0); // reserved
+        entry_data.put_u32(0); // pre_defined
+        entry_data.put_u32(0); // pre_defined
+        entry_data.put_u32(0); // pre_defined
+        entry_data.put_u16(self.config.width); // width
+        entry_data.put_u16(self.config.height); // height
+        entry_data.put_u32(0x00480000); // horizresolution
+        entry_data.put_u32(0x00480000); // vertresolution
+        entry_data.put_u32(0); // reserved
+        entry_data.put_u16(1); // frame_count
         
         // compressorname (32 bytes)
-        avc1_data.put_u8(0);
+        entry_data.put_u8(0);
         for _ in 0..31 {
-            avc1_data.put_u8(0);
+            entry_data.put_u8(0);
         }
         
-        avc1_data.put_u16(0x0018); // depth
-        avc1_data.put_u16(0xffff); // pre_defined
+        entry_data.put_u16(0x0018); // depth
+        entry_data.put_u16(0xffff); // pre_defined
+
+        if let Some(ParameterSets::H265 { vps, sps, pps }) = &self.parameter_sets {
+            // hvc1 box：参数集只放在hvcC中
+            let hvcc_data = hevc::decoder_configuration(vps, sps, pps).ok_or_else(|| {
+                StreamError::Internal("Failed to parse H.265 SPS".to_string())
+            })?;
+            self.write_box(&mut entry_data, BoxType::HvcC, &hvcc_data);
+            self.write_box(&mut stsd_data, BoxType::Hvc1, &entry_data);
+            self.write_box(buffer, BoxType::Stsd, &stsd_data);
+            return Ok(());
+        }
 
-        // avcC box (简化版本)
+        // avcC box
         let mut avcc_data = BytesMut::new();
         avcc_data.put_u8(1); // configurationVersion
-        avcc_data.put_u8(0x64); // AVCProfileIndication (High)
-        avcc_data.put_u8(0x00); // profile_compatibility
-        avcc_data.put_u8(0x1f); // AVCLevelIndication
-        avcc_data.put_u8(0xff); // lengthSizeMinusOne
-        avcc_data.put_u8(0xe0); // numOfSequenceParameterSets
-        avcc_data.put_u8(0); // numOfPictureParameterSets
+        match &self.parameter_sets {
+            Some(ParameterSets::H264 { sps, pps }) => {
+                avcc_data.put_u8(sps.get(1).copied().unwrap_or(0x64)); // AVCProfileIndication
+                avcc_data.put_u8(sps.get(2).copied().unwrap_or(0x00)); // profile_compatibility
+                avcc_data.put_u8(sps.get(3).copied().unwrap_or(0x1f)); // AVCLevelIndication
+                avcc_data.put_u8(0xff); // lengthSizeMinusOne (4字节)
+                avcc_data.put_u8(0xe1); // numOfSequenceParameterSets (1)
+                avcc_data.put_u16(sps.len() as u16);
+                avcc_data.extend_from_slice(sps);
+                avcc_data.put_u8(1); // numOfPictureParameterSets
+                avcc_data.put_u16(pps.len() as u16);
+                avcc_data.extend_from_slice(pps);
+            }
+            _ => {
+                // 未设置参数集（简化版本）
+                avcc_data.put_u8(0x64); // AVCProfileIndication (High)
+                avcc_data.put_u8(0x00); // profile_compatibility
+                avcc_data.put_u8(0x1f); // AVCLevelIndication
+                avcc_data.put_u8(0xff); // lengthSizeMinusOne
+                avcc_data.put_u8(0xe0); // numOfSequenceParameterSets
+                avcc_data.put_u8(0); // numOfPictureParameterSets
+            }
+        }
         
-        self.write_box(&mut avc1_data, BoxType::AvcC, &avcc_data);
-        self.write_box(&mut stsd_data, BoxType::Avc1, &avc1_data);
+        self.write_box(&mut entry_data, BoxType::AvcC, &avcc_data);
+        self.write_box(&mut stsd_data, BoxType::Avc1, &entry_data);
+
+        self.write_box(buffer, BoxType::Stsd, &stsd_data);
+        Ok(())
+    }
+
+    /// 写入音频stsd box（mp4a + esds）
+    fn write_audio_stsd_box(&self, buffer: &mut BytesMut, config: &AudioSpecificConfig) -> Result<(), StreamError> {
+        let mut stsd_data = BytesMut::new();
+
+        stsd_data.put_u8(0); // version
+        stsd_data.put_u24(0); // flags
+        stsd_data.put_u32(1); // entry_count
+
+        // mp4a box
+        let mut mp4a_data = BytesMut::new();
+        mp4a_data.put_u48(0); // reserved
+        mp4a_data.put_u16(1); // data_reference_index
+        mp4a_data.put_u64(0); // reserved
+        // 声道配置7表示7.1声道
+        let channel_count = if config.channel_config == 7 { 8 } else { config.channel_config as u16 };
+        mp4a_data.put_u16(channel_count); // channelcount
+        mp4a_data.put_u16(16); // samplesize
+        mp4a_data.put_u16(0); // pre_defined
+        mp4a_data.put_u16(0); // reserved
+        mp4a_data.put_u32(config.sample_rate().min(0xffff) << 16); // samplerate (16.16)
+
+        // esds box：ES_Descriptor → DecoderConfigDescriptor → DecoderSpecificInfo(AudioSpecificConfig)
+        let asc = config.to_bytes();
+        let mut decoder_config = BytesMut::new();
+        decoder_config.put_u8(0x40); // objectTypeIndication (MPEG-4 Audio)
+        decoder_config.put_u8(0x15); // streamType (audio) << 2 | reserved
+        decoder_config.put_u24(0); // bufferSizeDB
+        decoder_config.put_u32(0); // maxBitrate
+        decoder_config.put_u32(0); // avgBitrate
+        decoder_config.put_u8(0x05); // DecoderSpecificInfo tag
+        decoder_config.put_u8(asc.len() as u8);
+        decoder_config.extend_from_slice(&asc);
+
+        let mut es_descriptor = BytesMut::new();
+        es_descriptor.put_u16(0); // ES_ID
+        es_descriptor.put_u8(0); // flags
+        es_descriptor.put_u8(0x04); // DecoderConfigDescriptor tag
+        es_descriptor.put_u8(decoder_config.len() as u8);
+        es_descriptor.extend_from_slice(&decoder_config);
+        es_descriptor.put_u8(0x06); // SLConfigDescriptor tag
+        es_descriptor.put_u8(1);
+        es_descriptor.put_u8(0x02); // predefined (MP4)
+
+        let mut esds_data = BytesMut::new();
+        esds_data.put_u8(0); // version
+        esds_data.put_u24(0); // flags
+        esds_data.put_u8(0x03); // ES_Descriptor tag
+        esds_data.put_u8(es_descriptor.len() as u8);
+        esds_data.extend_from_slice(&es_descriptor);
+
+        self.write_box(&mut mp4a_data, BoxType::Esds, &esds_data);
+        self.write_box(&mut stsd_data, BoxType::Mp4a, &mp4a_data);
 
         self.write_box(buffer, BoxType::Stsd, &stsd_data);
         Ok(())
@@ -516,23 +772,51 @@ impl FMP4Converter {
     fn write_mvex_box(&self, buffer: &mut BytesMut) -> Result<(), StreamError> {
         let mut mvex_data = BytesMut::new();
 
-        // trex box
-        let mut trex_data = BytesMut::new();
-        trex_data.put_u8(0); // version
-        trex_data.put_u24(0); // flags
-        trex_data.put_u32(1); // track_ID
-        trex_data.put_u32(1); // default_sample_description_index
-        trex_data.put_u32(0); // default_sample_duration
-        trex_data.put_u32(0); // default_sample_size
-        trex_data.put_u32(0); // default_sample_flags
-
-        self.write_box(&mut mvex_data, BoxType::Trex, &trex_data);
+        let tracks: &[Track] = if self.audio_config.is_some() {
+            &[Track::Video, Track::Audio]
+        } else {
+            &[Track::Video]
+        };
+        for track in tracks {
+            // trex box
+            let mut trex_data = BytesMut::new();
+            trex_data.put_u8(0); // version
+            trex_data.put_u24(0); // flags
+            trex_data.put_u32(track.id()); // track_ID
+            trex_data.put_u32(1); // default_sample_description_index
+            trex_data.put_u32(0); // default_sample_duration
+            trex_data.put_u32(0); // default_sample_size
+            trex_data.put_u32(0); // default_sample_flags
+            self.write_box(&mut mvex_data, BoxType::Trex, &trex_data);
+        }
+
         self.write_box(buffer, BoxType::Mvex, &mvex_data);
         Ok(())
     }
 
     /// 写入moof box（movie fragment）
-    fn write_moof_box(&self, buffer: &mut BytesMut, segment: &VideoSegment) -> Result<(), StreamError> {
+    fn write_moof_box(
+        &self,
+        buffer: &mut BytesMut,
+        track: Track,
+        segment: &VideoSegment,
+        samples: &[Sample],
+    ) -> Result<(), StreamError> {
+        // data_offset是mdat数据相对moof起点的偏移，先按0写入得到moof大小再回填
+        let mut moof = self.build_moof_box(track, segment, samples, 0)?;
+        let data_offset = moof.len() as u32 + 8;
+        moof = self.build_moof_box(track, segment, samples, data_offset)?;
+        buffer.extend_from_slice(&moof);
+        Ok(())
+    }
+
+    fn build_moof_box(
+        &self,
+        track: Track,
+        segment: &VideoSegment,
+        samples: &[Sample],
+        data_offset: u32,
+    ) -> Result<BytesMut, StreamError> {
         let mut moof_data = BytesMut::new();
 
         // mfhd box
@@ -543,25 +827,34 @@ impl FMP4Converter {
         self.write_box(&mut moof_data, BoxType::Mfhd, &mfhd_data);
 
         // traf box
-        self.write_traf_box(&mut moof_data, segment)?;
+        self.write_traf_box(&mut moof_data, track, segment, samples, data_offset)?;
 
-        self.write_box(buffer, BoxType::Moof, &moof_data);
-        Ok(())
+        let mut moof = BytesMut::new();
+        self.write_box(&mut moof, BoxType::Moof, &moof_data);
+        Ok(moof)
     }
 
     /// 写入traf box（track fragment）
-    fn write_traf_box(&self, buffer: &mut BytesMut, segment: &VideoSegment) -> Result<(), StreamError> {
+    fn write_traf_box(
+        &self,
+        buffer: &mut BytesMut,
+        track: Track,
+        segment: &VideoSegment,
+        samples: &[Sample],
+        data_offset: u32,
+    ) -> Result<(), StreamError> {
         let mut traf_data = BytesMut::new();
 
         // tfhd box
         let mut tfhd_data = BytesMut::new();
         tfhd_data.put_u8(0); // version
         tfhd_data.put_u24(0x020000); // flags (default-base-is-moof)
-        tfhd_data.put_u32(1); // track_ID
+        tfhd_data.put_u32(track.id()); // track_ID
         self.write_box(&mut traf_data, BoxType::Tfhd, &tfhd_data);
 
-        // tfdt box
-        let decode_time = (segment.timestamp * self.config.timescale as f64) as u64;
+        // tfdt box（按轨道时间刻度换算同一时间轴上的解码时间戳）
+        let decode_time =
+            rescale_timestamp(segment.dts, VideoSegment::TIMESCALE, self.track_timescale(track)).max(0) as u64;
         let mut tfdt_data = BytesMut::new();
         tfdt_data.put_u8(1); // version
         tfdt_data.put_u24(0); // flags
@@ -569,28 +862,53 @@ impl FMP4Converter {
         self.write_box(&mut traf_data, BoxType::Tfdt, &tfdt_data);
 
         // trun box
+        // 只有存在帧重排时才写入 composition time offset（version 1，有符号）
+        let has_composition_offsets = samples.iter().any(|&(_, _, _, offset)| offset != 0);
         let mut trun_data = BytesMut::new();
-        trun_data.put_u8(0); // version
-        trun_data.put_u24(0x000301); // flags (data-offset-present, sample-duration-present)
-        trun_data.put_u32(1); // sample_count
-        
-        // 计算data_offset (moof size + 8)
-        let moof_size = 8 + 16 + 8 + 20 + 8 + 20 + 8 + 20; // 估算
-        trun_data.put_u32(moof_size); // data_offset
-        
-        let sample_duration = (segment.duration * self.config.timescale as f64) as u32;
-        trun_data.put_u32(sample_duration); // sample_duration
-        
+        if has_composition_offsets {
+            trun_data.put_u8(1); // version
+            trun_data.put_u24(0x000F01); // flags (data-offset, sample-duration, sample-size, sample-flags, sample-composition-time-offset)
+        } else {
+            trun_data.put_u8(0); // version
+            trun_data.put_u24(0x000701); // flags (data-offset, sample-duration, sample-size, sample-flags)
+        }
+        trun_data.put_u32(samples.len() as u32); // sample_count
+        trun_data.put_u32(data_offset); // data_offset
+
+        for &(duration, size, flags, composition_offset) in samples {
+            trun_data.put_u32(duration); // sample_duration
+            trun_data.put_u32(size); // sample_size
+            trun_data.put_u32(flags); // sample_flags
+            if has_composition_offsets {
+                trun_data.put_i32(composition_offset); // sample_composition_time_offset
+            }
+        }
+
         self.write_box(&mut traf_data, BoxType::Trun, &trun_data);
 
         self.write_box(buffer, BoxType::Traf, &traf_data);
         Ok(())
     }
 
-    /// 写入mdat box（media data）
-    fn write_mdat_box(&self, buffer: &mut BytesMut, segment: &VideoSegment) -> Result<(), StreamError> {
-        self.write_box(buffer, BoxType::Mdat, &segment.data);
-        Ok(())
+    /// 生成样本数据
+    ///
+    /// 设置了参数集时把Annex B转换为4字节长度前缀格式（参数集和分隔符已在avcC/hvcC中，不再写入），
+    /// 否则原样写入。
+    fn sample_data(&self, segment: &VideoSegment) -> Vec<u8> {
+        let Some(parameter_sets) = &self.parameter_sets else {
+            return segment.data.clone();
+        };
+
+        let codec = parameter_sets.codec();
+        let mut sample = Vec::with_capacity(segment.data.len());
+        for nal in h264::nal_units(&segment.data) {
+            if demux::is_config_nal(codec, nal) {
+                continue;
+            }
+            sample.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+            sample.extend_from_slice(nal);
+        }
+        sample
     }
 
     /// 写入box
@@ -608,6 +926,85 @@ impl Default for FMP4Converter {
     }
 }
 
+/// 直通会话的fMP4封装
+///
+/// 收到带参数集的关键帧后才输出初始化分片；会话的音频解码配置随初始化分片写入第二个
+/// 轨道。参数集（码流切换）或音频配置变化时重新输出初始化分片。
+pub struct LiveFMP4Muxer {
+    converter: FMP4Converter,
+    parameter_sets: Option<ParameterSets>,
+    audio_config: Option<AudioSpecificConfig>,
+}
+
+impl LiveFMP4Muxer {
+    pub fn new() -> Self {
+        Self {
+            converter: FMP4Converter::default(),
+            parameter_sets: None,
+            audio_config: None,
+        }
+    }
+
+    /// 封装一个分片，返回需要追加到输出的字节（可能为空，或以初始化分片开头）
+    ///
+    /// `audio_config` 为会话当前的 AudioSpecificConfig。
+    pub fn push(
+        &mut self,
+        segment: VideoSegment,
+        audio_config: Option<AudioSpecificConfig>,
+    ) -> Result<Vec<u8>, StreamError> {
+        let mut reinit = false;
+        if audio_config != self.audio_config {
+            self.audio_config = audio_config;
+            self.converter.set_audio_config(audio_config);
+            reinit = true;
+        }
+
+        let codec = match segment.format {
+            SegmentFormat::H264Raw => Some(VideoCodec::H264),
+            SegmentFormat::H265Raw => Some(VideoCodec::H265),
+            _ => None,
+        };
+        if let Some(codec) = codec.filter(|_| segment.is_keyframe) {
+            if let Some(parameter_sets) = ParameterSets::find(codec, &segment.data) {
+                if self.parameter_sets.as_ref() != Some(&parameter_sets) {
+                    let mut config = FMP4ConverterConfig::default();
+                    if let Some((width, height)) = parameter_sets.resolution() {
+                        config.width = width as u16;
+                        config.height = height as u16;
+                    }
+                    self.converter = FMP4Converter::new(config);
+                    self.converter.set_parameter_sets(parameter_sets.clone());
+                    self.converter.set_audio_config(self.audio_config);
+                    self.parameter_sets = Some(parameter_sets);
+                    reinit = true;
+                }
+            }
+        }
+
+        // 第一个关键帧之前无法初始化解码器
+        if self.parameter_sets.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let mut output = Vec::new();
+        if reinit {
+            output.extend(self.converter.generate_init_segment()?);
+        }
+        if segment.format == SegmentFormat::Aac && self.audio_config.is_none() {
+            return Ok(output);
+        }
+        output.extend(self.converter.convert_segment(segment)?.data);
+        Ok(output)
+    }
+}
+
+impl Default for LiveFMP4Muxer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -636,22 +1033,28 @@ mod tests {
         
         let h264_segment = VideoSegment {
             segment_id: Uuid::new_v4(),
-            timestamp: 1.0,
-            duration: 0.033,
+            pts: 90_000,
+            dts: 90_000,
+            duration: 3_000,
             data: vec![0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f], // 简化的H.264数据
             is_keyframe: true,
+            decode_only: false,
+            discontinuity: false,
             format: SegmentFormat::H264Raw,
             source_type: SegmentSourceType::Live,
             receive_time: None,
             forward_time: None,
+            device_send_time: None,
         };
 
         let fmp4_segment = converter.convert_segment(h264_segment.clone()).unwrap();
         
         assert_eq!(fmp4_segment.segment_id, h264_segment.segment_id);
-        assert_eq!(fmp4_segment.timestamp, h264_segment.timestamp);
+        assert_eq!(fmp4_segment.pts, h264_segment.pts);
+        assert_eq!(fmp4_segment.dts, h264_segment.dts);
         assert_eq!(fmp4_segment.duration, h264_segment.duration);
         assert_eq!(fmp4_segment.is_keyframe, h264_segment.is_keyframe);
+        assert_eq!(fmp4_segment.decode_only, h264_segment.decode_only);
         assert_eq!(fmp4_segment.format, SegmentFormat::FMP4);
         assert!(!fmp4_segment.data.is_empty());
     }
@@ -662,14 +1065,18 @@ mod tests {
         
         let h264_segment = VideoSegment {
             segment_id: Uuid::new_v4(),
-            timestamp: 1.0,
-            duration: 0.033,
+            pts: 90_000,
+            dts: 90_000,
+            duration: 3_000,
             data: vec![0, 0, 0, 1, 0x67],
             is_keyframe: true,
+            decode_only: false,
+            discontinuity: false,
             format: SegmentFormat::H264Raw,
             source_type: SegmentSourceType::Live,
             receive_time: None,
             forward_time: None,
+            device_send_time: None,
         };
 
         converter.convert_segment(h264_segment.clone()).unwrap();
@@ -685,17 +1092,288 @@ mod tests {
         
         let mp4_segment = VideoSegment {
             segment_id: Uuid::new_v4(),
-            timestamp: 1.0,
-            duration: 0.033,
+            pts: 90_000,
+            dts: 90_000,
+            duration: 3_000,
             data: vec![1, 2, 3],
             is_keyframe: true,
+            decode_only: false,
+            discontinuity: false,
             format: SegmentFormat::MP4,
             source_type: SegmentSourceType::Playback,
             receive_time: None,
             forward_time: None,
+            device_send_time: None,
         };
 
         let result = converter.convert_segment(mp4_segment);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_presentation_offset_edit_list() {
+        let mut converter = FMP4Converter::new(FMP4ConverterConfig::default());
+        let init_segment = converter.generate_init_segment().unwrap();
+        assert!(!init_segment.windows(4).any(|w| w == b"elst"));
+
+        // 精确定位到1.5秒：elst的media_time指向目标帧
+        converter.set_presentation_offset(Some(1.5));
+        let init_segment = converter.generate_init_segment().unwrap();
+        let elst = init_segment.windows(4).position(|w| w == b"elst").unwrap();
+        let media_time = &init_segment[elst + 4 + 4 + 4 + 8..elst + 4 + 4 + 4 + 16];
+        assert_eq!(i64::from_be_bytes(media_time.try_into().unwrap()), 135_000);
+
+        // 预解码分片的tfdt仍为关键帧处的媒体时间
+        let preroll = VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: 90_000,
+            dts: 90_000,
+            duration: 3_000,
+            data: vec![0, 0, 0, 1, 0x65],
+            is_keyframe: true,
+            decode_only: true,
+            discontinuity: false,
+            format: SegmentFormat::H264Raw,
+            source_type: SegmentSourceType::Playback,
+            receive_time: None,
+            forward_time: None,
+            device_send_time: None,
+        };
+        let fmp4_segment = converter.convert_segment(preroll).unwrap();
+        assert!(fmp4_segment.decode_only);
+        let tfdt = fmp4_segment.data.windows(4).position(|w| w == b"tfdt").unwrap();
+        let decode_time = &fmp4_segment.data[tfdt + 8..tfdt + 16];
+        assert_eq!(u64::from_be_bytes(decode_time.try_into().unwrap()), 90_000);
+    }
+
+    #[test]
+    fn test_parameter_sets_length_prefixed_sample() {
+        let sps = vec![0x67, 0x42, 0x00, 0x1f];
+        let pps = vec![0x68, 0xce, 0x38, 0x80];
+        let mut converter = FMP4Converter::new(FMP4ConverterConfig::default());
+        converter.set_parameter_sets(ParameterSets::H264 { sps: sps.clone(), pps: pps.clone() });
+
+        // avcC携带SPS/PPS
+        let init_segment = converter.generate_init_segment().unwrap();
+        let avcc = init_segment.windows(4).position(|w| w == b"avcC").unwrap();
+        assert_eq!(&init_segment[avcc + 4..avcc + 8], &[1, 0x42, 0x00, 0x1f]);
+        assert_eq!(init_segment[avcc + 9], 0xe1);
+        assert_eq!(&init_segment[avcc + 12..avcc + 16], sps.as_slice());
+
+        let mut data = vec![0, 0, 0, 1];
+        data.extend_from_slice(&sps);
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(&pps);
+        data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, 0x84]);
+        let segment = VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: 0,
+            dts: 0,
+            duration: 3_000,
+            data,
+            is_keyframe: true,
+            decode_only: false,
+            discontinuity: false,
+            format: SegmentFormat::H264Raw,
+            source_type: SegmentSourceType::Playback,
+            receive_time: None,
+            forward_time: None,
+            device_send_time: None,
+        };
+        let fmp4 = converter.convert_segment(segment).unwrap().data;
+
+        // trun的data_offset指向mdat负载，样本只含长度前缀的IDR
+        let trun = fmp4.windows(4).position(|w| w == b"trun").unwrap();
+        let data_offset = u32::from_be_bytes(fmp4[trun + 12..trun + 16].try_into().unwrap()) as usize;
+        let sample_size = u32::from_be_bytes(fmp4[trun + 20..trun + 24].try_into().unwrap()) as usize;
+        assert_eq!(&fmp4[data_offset - 4..data_offset], b"mdat");
+        assert_eq!(&fmp4[data_offset..], &[0, 0, 0, 3, 0x65, 0x88, 0x84]);
+        assert_eq!(sample_size, 7);
+    }
+
+    #[test]
+    fn test_audio_track() {
+        let config = AudioSpecificConfig { object_type: 2, sample_rate_index: 3, channel_config: 2 };
+        let mut converter = FMP4Converter::new(FMP4ConverterConfig::default());
+
+        // 未配置音频轨道时拒绝AAC分片
+        let mut data = aac::to_adts(&config, &[0x21, 0x10, 0x04]);
+        data.extend(aac::to_adts(&config, &[0x21, 0x10, 0x05, 0x06]));
+        let audio = VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: 90_000,
+            dts: 90_000,
+            duration: 3_840,
+            data,
+            is_keyframe: false,
+            decode_only: false,
+            discontinuity: false,
+            format: SegmentFormat::Aac,
+            source_type: SegmentSourceType::Live,
+            receive_time: None,
+            forward_time: None,
+            device_send_time: None,
+        };
+        assert!(converter.convert_segment(audio.clone()).is_err());
+
+        // 初始化分片包含两个轨道，esds携带AudioSpecificConfig
+        converter.set_audio_config(Some(config));
+        let init_segment = converter.generate_init_segment().unwrap();
+        assert_eq!(init_segment.windows(4).filter(|w| w == b"trak").count(), 2);
+        assert_eq!(init_segment.windows(4).filter(|w| w == b"trex").count(), 2);
+        assert!(init_segment.windows(4).any(|w| w == b"soun"));
+        let esds = init_segment.windows(4).position(|w| w == b"esds").unwrap();
+        let asc = init_segment[esds..].windows(2).position(|w| w == [0x05, 0x02]).unwrap();
+        assert_eq!(&init_segment[esds + asc + 2..esds + asc + 4], &config.to_bytes());
+
+        // 音频分片：track 2，tfdt按采样率换算（与视频同为1.0秒），每个ADTS帧一个样本
+        let fmp4 = converter.convert_segment(audio).unwrap().data;
+        let tfhd = fmp4.windows(4).position(|w| w == b"tfhd").unwrap();
+        assert_eq!(u32::from_be_bytes(fmp4[tfhd + 8..tfhd + 12].try_into().unwrap()), 2);
+        let tfdt = fmp4.windows(4).position(|w| w == b"tfdt").unwrap();
+        assert_eq!(u64::from_be_bytes(fmp4[tfdt + 8..tfdt + 16].try_into().unwrap()), 48_000);
+        let trun = fmp4.windows(4).position(|w| w == b"trun").unwrap();
+        assert_eq!(u32::from_be_bytes(fmp4[trun + 8..trun + 12].try_into().unwrap()), 2);
+        let data_offset = u32::from_be_bytes(fmp4[trun + 12..trun + 16].try_into().unwrap()) as usize;
+        assert_eq!(&fmp4[data_offset..], &[0x21, 0x10, 0x04, 0x21, 0x10, 0x05, 0x06]);
+        assert_eq!(u32::from_be_bytes(fmp4[trun + 16..trun + 20].try_into().unwrap()), aac::SAMPLES_PER_FRAME);
+    }
+
+    #[test]
+    fn test_composition_time_offset() {
+        let mut converter = FMP4Converter::new(FMP4ConverterConfig::default());
+        let mut segment = VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: 96_000,
+            dts: 90_000,
+            duration: 3_000,
+            data: vec![0, 0, 0, 1, 0x41, 0x9a],
+            is_keyframe: false,
+            decode_only: false,
+            discontinuity: false,
+            format: SegmentFormat::H264Raw,
+            source_type: SegmentSourceType::Live,
+            receive_time: None,
+            forward_time: None,
+            device_send_time: None,
+        };
+
+        // 显示时间晚于解码时间：tfdt 取 DTS，trun 为 version 1 并携带 composition time offset
+        let fmp4 = converter.convert_segment(segment.clone()).unwrap().data;
+        let tfdt = fmp4.windows(4).position(|w| w == b"tfdt").unwrap();
+        assert_eq!(u64::from_be_bytes(fmp4[tfdt + 8..tfdt + 16].try_into().unwrap()), 90_000);
+        let trun = fmp4.windows(4).position(|w| w == b"trun").unwrap();
+        assert_eq!(&fmp4[trun + 4..trun + 8], &[1, 0x00, 0x0f, 0x01]);
+        assert_eq!(u32::from_be_bytes(fmp4[trun + 16..trun + 20].try_into().unwrap()), 3_000);
+        assert_eq!(i32::from_be_bytes(fmp4[trun + 28..trun + 32].try_into().unwrap()), 6_000);
+
+        // 没有帧重排时保持 version 0，不写偏移
+        segment.pts = segment.dts;
+        let fmp4 = converter.convert_segment(segment).unwrap().data;
+        let trun = fmp4.windows(4).position(|w| w == b"trun").unwrap();
+        assert_eq!(&fmp4[trun + 4..trun + 8], &[0, 0x00, 0x07, 0x01]);
+    }
+
+    #[test]
+    fn test_hevc_sample_entry() {
+        // Main profile、Level 3.1、1920x1080 的 SPS
+        let sps = vec![
+            0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00, 0x03, 0x00, 0x00, 0x03,
+            0x00, 0x5d, 0xa0, 0x03, 0xc0, 0x80, 0x11, 0x07, 0xcb, 0xc0,
+        ];
+        let (vps, pps) = (vec![0x40, 0x01, 0x0c, 0x01], vec![0x44, 0x01, 0xc1, 0x72]);
+        let mut converter = FMP4Converter::new(FMP4ConverterConfig::default());
+        converter.set_parameter_sets(ParameterSets::H265 { vps: vps.clone(), sps: sps.clone(), pps: pps.clone() });
+
+        // hvc1/hvcC携带VPS/SPS/PPS，不再写入avc1
+        let init_segment = converter.generate_init_segment().unwrap();
+        assert!(init_segment.windows(4).any(|w| w == b"hvc1"));
+        assert!(!init_segment.windows(4).any(|w| w == b"avc1"));
+        let hvcc = init_segment.windows(4).position(|w| w == b"hvcC").unwrap();
+        assert_eq!(init_segment[hvcc + 5], 0x01); // general_profile_idc
+        assert_eq!(init_segment[hvcc + 16], 93); // general_level_idc
+        assert!(init_segment.windows(sps.len()).any(|w| w == sps.as_slice()));
+
+        let mut data = Vec::new();
+        for nal in [&vps, &sps, &pps, &vec![0x26, 0x01, 0xaf, 0x09]] {
+            data.extend_from_slice(&[0, 0, 0, 1]);
+            data.extend_from_slice(nal);
+        }
+        let segment = VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: 0,
+            dts: 0,
+            duration: 3_600,
+            data,
+            is_keyframe: true,
+            decode_only: false,
+            discontinuity: false,
+            format: SegmentFormat::H265Raw,
+            source_type: SegmentSourceType::Playback,
+            receive_time: None,
+            forward_time: None,
+            device_send_time: None,
+        };
+        let fmp4 = converter.convert_segment(segment).unwrap().data;
+
+        // 样本只含长度前缀的IDR条带
+        let trun = fmp4.windows(4).position(|w| w == b"trun").unwrap();
+        let data_offset = u32::from_be_bytes(fmp4[trun + 12..trun + 16].try_into().unwrap()) as usize;
+        assert_eq!(&fmp4[data_offset..], &[0, 0, 0, 4, 0x26, 0x01, 0xaf, 0x09]);
+    }
+
+    #[test]
+    fn test_live_muxer_audio_track() {
+        let config = AudioSpecificConfig { object_type: 2, sample_rate_index: 3, channel_config: 2 };
+        let segment = |data: Vec<u8>, pts: i64, is_keyframe: bool, format: SegmentFormat| VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts,
+            dts: pts,
+            duration: 3_000,
+            data,
+            is_keyframe,
+            decode_only: false,
+            discontinuity: false,
+            format,
+            source_type: SegmentSourceType::Live,
+            receive_time: None,
+            forward_time: None,
+            device_send_time: None,
+        };
+        let audio = |pts| segment(aac::to_adts(&config, &[0x21, 0x10, 0x04]), pts, false, SegmentFormat::Aac);
+        let tfdt = |fmp4: &[u8]| {
+            let tfdt = fmp4.windows(4).position(|w| w == b"tfdt").unwrap();
+            u64::from_be_bytes(fmp4[tfdt + 8..tfdt + 16].try_into().unwrap())
+        };
+        let mut muxer = LiveFMP4Muxer::new();
+
+        // 第一个关键帧之前没有输出
+        assert!(muxer.push(audio(90_000), Some(config)).unwrap().is_empty());
+
+        // 关键帧：初始化分片（视频+音频两个轨道）加第一个视频分片
+        let keyframe = vec![0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f, 0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80, 0, 0, 0, 1, 0x65, 0x88];
+        let output = muxer.push(segment(keyframe, 90_000, true, SegmentFormat::H264Raw), Some(config)).unwrap();
+        assert_eq!(&output[4..8], b"ftyp");
+        assert_eq!(output.windows(4).filter(|w| w == b"trak").count(), 2);
+        let moof = output.windows(4).position(|w| w == b"moof").unwrap() - 4;
+        assert_eq!(tfdt(&output[moof..]), 90_000);
+
+        // 同一设备时间的音频分片：track 2，tfdt 按采样率换算后同为 1.0 秒
+        let output = muxer.push(audio(90_000), Some(config)).unwrap();
+        assert_eq!(&output[4..8], b"moof");
+        let tfhd = output.windows(4).position(|w| w == b"tfhd").unwrap();
+        assert_eq!(u32::from_be_bytes(output[tfhd + 8..tfhd + 12].try_into().unwrap()), 2);
+        assert_eq!(tfdt(&output), 48_000);
+
+        // 普通帧不重新输出初始化分片
+        let output = muxer.push(segment(vec![0, 0, 0, 1, 0x41, 0x9a], 93_000, false, SegmentFormat::H264Raw), Some(config)).unwrap();
+        assert_eq!(&output[4..8], b"moof");
+
+        // 会话没有音频配置时只有视频轨道，音频分片被跳过
+        let mut muxer = LiveFMP4Muxer::new();
+        let keyframe = vec![0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f, 0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80, 0, 0, 0, 1, 0x65, 0x88];
+        let output = muxer.push(segment(keyframe, 0, true, SegmentFormat::H264Raw), None).unwrap();
+        assert_eq!(output.windows(4).filter(|w| w == b"trak").count(), 1);
+        assert!(muxer.push(audio(0), None).unwrap().is_empty());
+    }
 }
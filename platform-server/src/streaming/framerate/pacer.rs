@@ -2,9 +2,23 @@
 //
 // 本模块实现了精确的发送速率控制功能，支持倍速播放和网络自适应调整。
 
+use crate::streaming::BufferConfig;
 use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
+/// 网络自适应系数上限（客户端缓冲低于下限时的追赶速度）
+const MAX_NETWORK_FACTOR: f64 = 1.5;
+/// 网络自适应系数下限（缓冲溢出或带宽不足时的退避速度）
+const MIN_NETWORK_FACTOR: f64 = 0.5;
+/// 每次调整允许的最大加速幅度，保证卡顿后平滑恢复而不是突发发送
+const MAX_SPEEDUP_STEP: f64 = 0.1;
+/// 每次调整允许的最大减速幅度（退避比加速更快）
+const MAX_SLOWDOWN_STEP: f64 = 0.2;
+/// 带宽与码率估计的指数平滑系数
+const EWMA_ALPHA: f64 = 0.3;
+/// 发送码率最多占用测得吞吐量的比例
+const BANDWIDTH_HEADROOM: f64 = 0.9;
+
 /// 帧率控制器
 /// 
 /// 负责控制视频分片的发送速率，确保播放速度正确。
@@ -18,6 +32,14 @@ pub struct FrameRatePacer {
     last_send_time: Option<Instant>,
     /// 基础帧间隔（微秒）
     base_frame_interval_us: u64,
+    /// 客户端缓冲区配置（网络自适应的调节区间）
+    buffer_config: BufferConfig,
+    /// 网络自适应系数（叠加在倍速之上，1.0为正常速度）
+    network_factor: f64,
+    /// 平滑后的传输吞吐量（Mbps）
+    bandwidth_mbps: Option<f64>,
+    /// 平滑后的媒体码率（Mbps，按1x速度计算）
+    media_bitrate_mbps: Option<f64>,
 }
 
 impl FrameRatePacer {
@@ -49,9 +71,35 @@ impl FrameRatePacer {
             playback_rate: 1.0,
             last_send_time: None,
             base_frame_interval_us,
+            buffer_config: BufferConfig::default(),
+            network_factor: 1.0,
+            bandwidth_mbps: None,
+            media_bitrate_mbps: None,
         }
     }
 
+    /// 设置客户端缓冲区配置
+    ///
+    /// 要求 `min_buffer_ms <= target_buffer_ms <= max_buffer_ms`。
+    pub fn set_buffer_config(&mut self, config: BufferConfig) -> Result<(), String> {
+        if config.min_buffer_ms > config.target_buffer_ms
+            || config.target_buffer_ms > config.max_buffer_ms
+        {
+            return Err(format!(
+                "Invalid buffer config: min={}ms, target={}ms, max={}ms",
+                config.min_buffer_ms, config.target_buffer_ms, config.max_buffer_ms
+            ));
+        }
+
+        self.buffer_config = config;
+        Ok(())
+    }
+
+    /// 获取网络自适应系数
+    pub fn network_factor(&self) -> f64 {
+        self.network_factor
+    }
+
     /// 获取目标帧率
     pub fn target_fps(&self) -> f64 {
         self.target_fps
@@ -69,8 +117,8 @@ impl FrameRatePacer {
 
     /// 计算下一帧的发送延迟
     /// 
-    /// 根据目标帧率、分片包含的帧数和播放速率计算发送延迟。
-    /// 公式：delay = (frames_in_segment / target_fps) / playback_rate 秒
+    /// 根据目标帧率、分片包含的帧数、播放速率和网络自适应系数计算发送延迟。
+    /// 公式：delay = (frames_in_segment / target_fps) / (playback_rate * network_factor) 秒
     /// 
     /// # 参数
     /// 
@@ -91,18 +139,19 @@ impl FrameRatePacer {
         // 计算基础间隔（秒）
         let base_interval_sec = frames_in_segment as f64 / self.target_fps;
         
-        // 应用倍速调整
-        let adjusted_interval_sec = base_interval_sec / self.playback_rate;
+        // 应用倍速和网络自适应调整
+        let adjusted_interval_sec = base_interval_sec / (self.playback_rate * self.network_factor);
         
         // 转换为Duration
         let delay = Duration::from_secs_f64(adjusted_interval_sec);
         
         debug!(
-            "Calculated send delay: {:.3}ms (frames={}, fps={:.2}, rate={:.2}x)",
+            "Calculated send delay: {:.3}ms (frames={}, fps={:.2}, rate={:.2}x, network={:.2}x)",
             delay.as_secs_f64() * 1000.0,
             frames_in_segment,
             self.target_fps,
-            self.playback_rate
+            self.playback_rate,
+            self.network_factor
         );
         
         delay
@@ -145,7 +194,8 @@ impl FrameRatePacer {
     /// 等待直到可以发送下一帧
     /// 
     /// 根据上次发送时间和计算的延迟，等待到正确的发送时间。
-    /// 如果已经超时（延迟已过），则不等待。
+    /// 如果已经超时（延迟已过），则不等待；下一帧从本次发送时间重新计时，
+    /// 不会为了追赶进度而连续突发发送。
     /// 
     /// # 参数
     /// 
@@ -193,15 +243,38 @@ impl FrameRatePacer {
         self.last_send_time = Some(Instant::now());
     }
 
+    /// 记录已发送分片的大小，用于估计媒体码率
+    ///
+    /// # 参数
+    ///
+    /// * `bytes` - 分片字节数
+    /// * `frames_in_segment` - 分片包含的帧数
+    pub fn record_segment(&mut self, bytes: usize, frames_in_segment: u32) {
+        if frames_in_segment == 0 || self.target_fps <= 0.0 {
+            return;
+        }
+
+        let duration_sec = frames_in_segment as f64 / self.target_fps;
+        let bitrate_mbps = bytes as f64 * 8.0 / duration_sec / 1_000_000.0;
+        self.media_bitrate_mbps = Some(Self::smooth(self.media_bitrate_mbps, bitrate_mbps));
+    }
+
     /// 根据网络条件调整发送速率
     /// 
-    /// 这是一个可选功能，用于在网络拥塞时动态调整发送速率。
-    /// 当前为占位实现，可在后续迭代中完善。
+    /// 按客户端上报的缓冲时长和测得的传输吞吐量调整网络自适应系数：
+    /// 
+    /// - 缓冲低于 `min_buffer_ms`：以最大追赶速度发送
+    /// - 缓冲在 `min_buffer_ms` 与 `target_buffer_ms` 之间：按差距比例加速
+    /// - 缓冲在 `target_buffer_ms` 与 `max_buffer_ms` 之间：按差距比例减速
+    /// - 缓冲超过 `max_buffer_ms`：以最低速度发送
+    /// - 发送码率超过吞吐量时：降到吞吐量允许的速度
+    /// 
+    /// 每次调整的幅度受限，卡顿后逐步加速恢复，不会突发发送。
     /// 
     /// # 参数
     /// 
-    /// * `bandwidth_mbps` - 可用带宽（Mbps）
-    /// * `buffer_ms` - 缓冲区大小（毫秒）
+    /// * `bandwidth_mbps` - 测得的传输吞吐量（Mbps），未知时传0
+    /// * `buffer_ms` - 客户端缓冲时长（毫秒）
     /// 
     /// # 示例
     /// 
@@ -210,20 +283,59 @@ impl FrameRatePacer {
     /// pacer.adjust_for_network(5.0, 100);
     /// ```
     pub fn adjust_for_network(&mut self, bandwidth_mbps: f64, buffer_ms: u64) {
-        // TODO: 实现网络自适应调整逻辑
-        // 
-        // 基本思路：
-        // 1. 如果带宽不足，降低发送速率
-        // 2. 如果缓冲区过低，增加发送速率
-        // 3. 如果缓冲区过高，降低发送速率
-        
+        if bandwidth_mbps.is_finite() && bandwidth_mbps > 0.0 {
+            self.bandwidth_mbps = Some(Self::smooth(self.bandwidth_mbps, bandwidth_mbps));
+        }
+
+        let mut target = self.buffer_factor(buffer_ms);
+
+        // 带宽约束：发送码率不超过吞吐量
+        if let (Some(bandwidth), Some(bitrate)) = (self.bandwidth_mbps, self.media_bitrate_mbps) {
+            if bitrate > 0.0 {
+                let sustainable = bandwidth * BANDWIDTH_HEADROOM / (bitrate * self.playback_rate);
+                target = target.min(sustainable);
+            }
+        }
+        let target = target.clamp(MIN_NETWORK_FACTOR, MAX_NETWORK_FACTOR);
+
+        let old_factor = self.network_factor;
+        let step = (target - old_factor).clamp(-MAX_SLOWDOWN_STEP, MAX_SPEEDUP_STEP);
+        self.network_factor = old_factor + step;
+
         debug!(
-            "Network adjustment (placeholder): bandwidth={:.2}Mbps, buffer={}ms",
-            bandwidth_mbps, buffer_ms
+            "Network adjustment: bandwidth={:.2}Mbps, buffer={}ms, factor {:.2} -> {:.2} (target {:.2})",
+            bandwidth_mbps, buffer_ms, old_factor, self.network_factor, target
         );
-        
-        // 占位实现：仅记录日志
-        // 实际实现应该根据网络条件调整playback_rate或target_fps
+    }
+
+    /// 按缓冲时长计算期望的网络自适应系数
+    fn buffer_factor(&self, buffer_ms: u64) -> f64 {
+        let min = self.buffer_config.min_buffer_ms as f64;
+        let target = self.buffer_config.target_buffer_ms as f64;
+        let max = self.buffer_config.max_buffer_ms as f64;
+        let buffer = buffer_ms as f64;
+
+        if buffer < min {
+            MAX_NETWORK_FACTOR
+        } else if buffer < target {
+            1.0 + (MAX_NETWORK_FACTOR - 1.0) * (target - buffer) / (target - min)
+        } else if buffer <= max {
+            if max > target {
+                1.0 - (1.0 - MIN_NETWORK_FACTOR) * (buffer - target) / (max - target)
+            } else {
+                1.0
+            }
+        } else {
+            MIN_NETWORK_FACTOR
+        }
+    }
+
+    /// 指数平滑
+    fn smooth(previous: Option<f64>, sample: f64) -> f64 {
+        match previous {
+            Some(value) => value + EWMA_ALPHA * (sample - value),
+            None => sample,
+        }
     }
 
     /// 更新目标帧率
@@ -457,6 +569,89 @@ mod tests {
         assert_eq!(pacer.playback_rate(), 2.0);
     }
 
+    #[test]
+    fn test_adjust_for_network_low_buffer_speeds_up_gradually() {
+        let mut pacer = FrameRatePacer::new(30.0);
+        assert_eq!(pacer.network_factor(), 1.0);
+
+        // 卡顿后缓冲为0：逐步加速而不是立即跳到最大速度
+        pacer.adjust_for_network(0.0, 0);
+        assert!((pacer.network_factor() - 1.1).abs() < 1e-9);
+        for _ in 0..10 {
+            pacer.adjust_for_network(0.0, 0);
+        }
+        assert!((pacer.network_factor() - MAX_NETWORK_FACTOR).abs() < 1e-9);
+
+        let delay_ms = pacer.calculate_send_delay(1).as_secs_f64() * 1000.0;
+        assert!((delay_ms - 1000.0 / 30.0 / 1.5).abs() < 1.0);
+
+        // 缓冲回到目标值后恢复正常速度
+        for _ in 0..10 {
+            pacer.adjust_for_network(0.0, 500);
+        }
+        assert!((pacer.network_factor() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_adjust_for_network_overfilled_buffer_backs_off() {
+        let mut pacer = FrameRatePacer::new(30.0);
+
+        // 目标与上限之间按比例减速
+        for _ in 0..10 {
+            pacer.adjust_for_network(0.0, 1250);
+        }
+        assert!((pacer.network_factor() - 0.75).abs() < 1e-9);
+
+        // 超过上限时降到最低速度
+        for _ in 0..10 {
+            pacer.adjust_for_network(0.0, 5000);
+        }
+        assert!((pacer.network_factor() - MIN_NETWORK_FACTOR).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_adjust_for_network_bandwidth_limit() {
+        let mut pacer = FrameRatePacer::new(30.0);
+
+        // 每帧12500字节 @ 30fps = 3Mbps
+        pacer.record_segment(12_500, 1);
+
+        // 吞吐量充足时不限制
+        pacer.adjust_for_network(10.0, 500);
+        assert!((pacer.network_factor() - 1.0).abs() < 1e-9);
+
+        // 吞吐量跌到2Mbps：即使缓冲不足也要退避到可持续速度
+        for _ in 0..30 {
+            pacer.adjust_for_network(2.0, 0);
+        }
+        let sustainable = 2.0 * BANDWIDTH_HEADROOM / 3.0;
+        assert!((pacer.network_factor() - sustainable).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_set_buffer_config() {
+        let mut pacer = FrameRatePacer::new(30.0);
+        assert!(pacer
+            .set_buffer_config(BufferConfig {
+                min_buffer_ms: 500,
+                target_buffer_ms: 200,
+                max_buffer_ms: 1000,
+            })
+            .is_err());
+
+        pacer
+            .set_buffer_config(BufferConfig {
+                min_buffer_ms: 200,
+                target_buffer_ms: 200,
+                max_buffer_ms: 200,
+            })
+            .unwrap();
+        pacer.adjust_for_network(0.0, 200);
+        assert_eq!(pacer.network_factor(), 1.0);
+        pacer.adjust_for_network(0.0, 100);
+        assert!(pacer.network_factor() > 1.0);
+    }
+
     #[test]
     fn test_delay_calculation_precision() {
         let pacer = FrameRatePacer::new(30.0);
@@ -10,9 +10,15 @@
 // - 延迟监控和统计
 // - 支持100+并发流会话
 
-use super::source::{SegmentSourceType, StreamError, StreamInfo, StreamSource, VideoSegment};
+use super::source::{
+    SegmentFormat, SegmentSourceType, StreamError, StreamInfo, StreamMode, StreamSource, VideoSegment,
+};
+use super::telemetry::{
+    is_non_reference_segment, ClientTelemetryReport, TelemetryController, TelemetryDecision,
+};
 use crate::latency::{
-    AlertBroadcaster, EndToEndLatencyMonitor, LatencyStatisticsManager, LatencyThresholds,
+    AlertBroadcaster, ClientPlaybackStats, EndToEndLatencyMonitor, LatencyHistory,
+    LatencyStatisticsManager, LatencyThresholds, PlaybackAckRecorder,
 };
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
@@ -94,6 +100,12 @@ pub struct StreamStats {
     pub throughput_mbps: f64,
     /// 丢包率
     pub packet_loss_rate: f64,
+    /// 客户端播放状态（来自客户端遥测上报）
+    pub client: ClientPlaybackStats,
+    /// 已发出的关键帧请求数
+    pub keyframe_requests: u64,
+    /// 因客户端解码压力丢弃的非参考帧分片数
+    pub dropped_non_reference_segments: u64,
     /// 延迟历史（用于计算百分位数）
     #[serde(skip)]
     latency_history: std::collections::VecDeque<f64>,
@@ -227,11 +239,22 @@ pub struct UnifiedStreamHandler {
     stats_manager: Arc<LatencyStatisticsManager>,
     /// 告警广播器
     alert_broadcaster: Arc<AlertBroadcaster>,
+    /// 客户端遥测控制器
+    telemetry: Arc<DashMap<Uuid, Arc<std::sync::Mutex<TelemetryController>>>>,
 }
 
 impl UnifiedStreamHandler {
     /// 创建新的统一流处理器
     pub fn new() -> Self {
+        Self::with_stats_manager(LatencyStatisticsManager::new())
+    }
+    
+    /// 创建将延迟统计持久化到历史存储的统一流处理器
+    pub fn with_latency_history(history: Arc<LatencyHistory>) -> Self {
+        Self::with_stats_manager(LatencyStatisticsManager::with_history(history))
+    }
+    
+    fn with_stats_manager(stats_manager: LatencyStatisticsManager) -> Self {
         debug!("Creating UnifiedStreamHandler");
         
         let thresholds = LatencyThresholds {
@@ -244,8 +267,9 @@ impl UnifiedStreamHandler {
         Self {
             sessions: Arc::new(DashMap::new()),
             latency_monitor: Arc::new(EndToEndLatencyMonitor::new(thresholds)),
-            stats_manager: Arc::new(LatencyStatisticsManager::new()),
+            stats_manager: Arc::new(stats_manager),
             alert_broadcaster: Arc::new(AlertBroadcaster::with_defaults()),
+            telemetry: Arc::new(DashMap::new()),
         }
     }
     
@@ -264,6 +288,40 @@ impl UnifiedStreamHandler {
         Arc::clone(&self.alert_broadcaster)
     }
     
+    /// 获取客户端播放确认记录器
+    pub fn get_ack_recorder(&self) -> PlaybackAckRecorder {
+        PlaybackAckRecorder::new(
+            Arc::clone(&self.latency_monitor),
+            Arc::clone(&self.stats_manager),
+        )
+    }
+    
+    /// 记录客户端遥测上报
+    ///
+    /// 更新客户端播放状态统计，并把网络反馈、关键帧请求和丢帧决策交给转发任务。
+    pub fn record_client_telemetry(
+        &self,
+        session_id: Uuid,
+        report: ClientTelemetryReport,
+    ) -> Result<TelemetryDecision, StreamError> {
+        let controller = self
+            .telemetry
+            .get(&session_id)
+            .map(|entry| Arc::clone(entry.value()))
+            .ok_or(StreamError::SessionNotFound)?;
+
+        let mut controller = controller.lock().unwrap();
+        let decision = controller.record(report, std::time::Instant::now());
+        self.stats_manager
+            .record_client_playback(&session_id, controller.stats());
+        Ok(decision)
+    }
+
+    /// 会话是否存在
+    pub fn has_session(&self, session_id: &Uuid) -> bool {
+        self.sessions.contains_key(session_id)
+    }
+    
     /// 获取所有活动会话ID
     pub fn get_active_sessions(&self) -> Vec<Uuid> {
         self.sessions.iter().map(|entry| *entry.key()).collect()
@@ -315,8 +373,15 @@ impl UnifiedStreamHandler {
 
         let mut session = StreamSession::new(session_id, source, config);
         
-        // 启动延迟监控
-        self.stats_manager.start_session(session_id);
+        // 启动延迟监控（直通会话按设备记录历史）
+        let device_id = match session.get_info().mode {
+            StreamMode::Live { device_id } => Some(device_id),
+            StreamMode::Playback { .. } => None,
+        };
+        self.latency_monitor
+            .get_threshold_config()
+            .bind_session(session_id, device_id.clone());
+        self.stats_manager.start_device_session(session_id, device_id);
         self.alert_broadcaster.broadcast_session_started(session_id);
         
         // 启动转发任务
@@ -344,6 +409,13 @@ impl UnifiedStreamHandler {
             Box::new(DummySource::new()),
         );
         
+        // 客户端遥测：缓冲配置交给数据源，反馈通过 watch 通道送入转发任务
+        source.set_buffer_config(&session.config.buffer_config);
+        let (controller, mut feedback_rx) =
+            TelemetryController::new(session.config.buffer_config.clone());
+        let telemetry = Arc::new(std::sync::Mutex::new(controller));
+        self.telemetry.insert(session_id, Arc::clone(&telemetry));
+
         let segment_sender = session.segment_sender.clone();
         let alert_sender = session.alert_sender.clone();
         let stats = session.stats.clone();
@@ -353,15 +425,48 @@ impl UnifiedStreamHandler {
         // 克隆延迟监控组件
         let latency_monitor = Arc::clone(&self.latency_monitor);
         let stats_manager = Arc::clone(&self.stats_manager);
-        let alert_broadcaster = Arc::clone(&self.alert_broadcaster);
 
         let task = tokio::spawn(async move {
             debug!("Forwarding task started for session: {}", session_id);
+            let mut keyframe_requests_seen = 0;
+            let mut drop_non_reference = false;
 
             loop {
+                // 应用客户端遥测反馈
+                if feedback_rx.has_changed().unwrap_or(false) {
+                    let feedback = feedback_rx.borrow_and_update().clone();
+                    if let Some(network) = &feedback.network {
+                        source.apply_network_feedback(network);
+                    }
+                    drop_non_reference = feedback.drop_non_reference;
+                    if feedback.keyframe_requests != keyframe_requests_seen {
+                        keyframe_requests_seen = feedback.keyframe_requests;
+                        match source.request_keyframe().await {
+                            Ok(()) => stats.write().await.keyframe_requests += 1,
+                            Err(StreamError::OperationNotSupported) => {
+                                debug!("Source does not support keyframe requests (session: {})", session_id);
+                            }
+                            Err(e) => warn!("Keyframe request failed for session {}: {}", session_id, e),
+                        }
+                    }
+                }
+
                 // 获取下一个分片
                 match source.next_segment().await {
+                    Ok(Some(segment)) if drop_non_reference && is_non_reference_segment(&segment) => {
+                        // 客户端解码跟不上：丢弃非参考帧，不影响后续帧解码
+                        stats.write().await.dropped_non_reference_segments += 1;
+                        debug!(
+                            "Dropped non-reference segment {} (session: {})",
+                            segment.segment_id, session_id
+                        );
+                    }
                     Ok(Some(mut segment)) => {
+                        // 帧间隔按视频分片估计
+                        if segment.format != SegmentFormat::Aac {
+                            telemetry.lock().unwrap().observe_segment(segment.duration_secs());
+                        }
+
                         // 记录接收时间（如果还没有记录）
                         let receive_time = if let Some(t) = segment.receive_time {
                             t
@@ -372,12 +477,13 @@ impl UnifiedStreamHandler {
                         };
                         
                         // 根据分片来源类型记录延迟监控时间戳
+                        latency_monitor.assign_segment(segment.segment_id, session_id);
                         match segment.source_type {
                             SegmentSourceType::Live => {
                                 // 直通播放：记录完整的延迟链路
-                                // 对于真实的直通播放，设备端应该在分片中携带真实的发送时间戳
-                                // 目前使用接收时间作为近似值
-                                let device_send_time = receive_time;
+                                // 设备发送时间已按时钟偏移换算为平台时钟；
+                                // 旧版设备或尚未完成时钟同步时退化为接收时间
+                                let device_send_time = segment.device_send_time.unwrap_or(receive_time);
                                 latency_monitor.record_device_send(segment.segment_id, device_send_time);
                                 latency_monitor.record_platform_receive(segment.segment_id, receive_time);
                             }
@@ -436,13 +542,6 @@ impl UnifiedStreamHandler {
                             );
                         }
                         
-                        // 检查并广播延迟告警
-                        if let Some(alerts) = latency_monitor.get_alerts(&segment.segment_id) {
-                            for alert in alerts {
-                                alert_broadcaster.broadcast_latency_alert(session_id, alert);
-                            }
-                        }
-                        
                         // 如果处理延迟超过5ms，记录警告
                         if processing_latency_ms > 5.0 {
                             warn!(
@@ -560,7 +659,9 @@ impl UnifiedStreamHandler {
             }
             
             // 停止延迟监控
+            self.telemetry.remove(&session_id);
             self.stats_manager.stop_session(&session_id);
+            self.latency_monitor.get_threshold_config().unbind_session(&session_id);
             self.alert_broadcaster.broadcast_session_ended(session_id);
             
             debug!("Stream session stopped: {}", session_id);
@@ -615,12 +716,20 @@ impl UnifiedStreamHandler {
     ///
     /// - `session_id`: 会话ID
     /// - `position`: 目标位置（秒）
-    pub async fn seek_stream(&self, session_id: Uuid, position: f64) -> Result<(), StreamError> {
-        debug!("Seeking stream session {} to position: {:.3}s", session_id, position);
+    /// - `accurate`: 精确定位到帧（否则定位到之前最近的关键帧）
+    pub async fn seek_stream(&self, session_id: Uuid, position: f64, accurate: bool) -> Result<(), StreamError> {
+        debug!(
+            "Seeking stream session {} to position: {:.3}s (accurate: {})",
+            session_id, position, accurate
+        );
 
         if let Some(session_lock) = self.sessions.get(&session_id) {
             let mut session = session_lock.write().await;
-            session.source.seek(position).await?;
+            if accurate {
+                session.source.seek_accurate(position).await?;
+            } else {
+                session.source.seek(position).await?;
+            }
             debug!("Stream session seeked: {}", session_id);
             Ok(())
         } else {
@@ -718,8 +827,11 @@ impl UnifiedStreamHandler {
     pub async fn get_session_stats(&self, session_id: Uuid) -> Result<StreamStats, StreamError> {
         if let Some(session_lock) = self.sessions.get(&session_id) {
             let session = session_lock.read().await;
-            let stats = session.stats.read().await;
-            Ok(stats.clone())
+            let mut stats = session.stats.read().await.clone();
+            if let Some(controller) = self.telemetry.get(&session_id) {
+                stats.client = controller.lock().unwrap().stats();
+            }
+            Ok(stats)
         } else {
             warn!("Session not found: {}", session_id);
             Err(StreamError::SessionNotFound)
@@ -739,6 +851,21 @@ impl UnifiedStreamHandler {
     pub fn active_sessions(&self) -> usize {
         self.sessions.len()
     }
+
+    /// 获取每个会话的订阅客户端数
+    pub async fn session_client_counts(&self) -> Vec<(Uuid, usize)> {
+        let sessions: Vec<_> = self
+            .sessions
+            .iter()
+            .map(|entry| (*entry.key(), Arc::clone(entry.value())))
+            .collect();
+
+        let mut counts = Vec::with_capacity(sessions.len());
+        for (session_id, session_lock) in sessions {
+            counts.push((session_id, session_lock.read().await.active_clients()));
+        }
+        counts
+    }
 }
 
 impl Default for UnifiedStreamHandler {
@@ -804,30 +931,34 @@ impl StreamSource for DummySource {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::streaming::source::{SegmentFormat, StreamMode, StreamState};
 
     /// 测试用数据源
-    struct TestSource {
+    pub(crate) struct TestSource {
         segments: Vec<VideoSegment>,
         index: usize,
         paused: bool,
     }
 
     impl TestSource {
-        fn new(count: usize) -> Self {
+        pub(crate) fn new(count: usize) -> Self {
             let segments = (0..count)
                 .map(|i| VideoSegment {
                     segment_id: Uuid::new_v4(),
-                    timestamp: i as f64 * 0.033,
-                    duration: 0.033,
+                    pts: i as i64 * 3_000,
+                    dts: i as i64 * 3_000,
+                    duration: 3_000,
                     data: vec![0u8; 1024],
                     is_keyframe: i % 30 == 0,
+                    decode_only: false,
+                    discontinuity: false,
                     format: SegmentFormat::H264Raw,
                     source_type: SegmentSourceType::Live,
                     receive_time: None,
                     forward_time: None,
+                    device_send_time: None,
                 })
                 .collect();
 
@@ -1057,6 +1188,9 @@ impl StreamStats {
             p99_latency_ms: 0.0,
             throughput_mbps: 0.0,
             packet_loss_rate: 0.0,
+            client: ClientPlaybackStats::default(),
+            keyframe_requests: 0,
+            dropped_non_reference_segments: 0,
             latency_history: VecDeque::with_capacity(1000), // 保留最近1000个样本
             start_time: Some(SystemTime::now()),
         }
@@ -8,16 +8,25 @@
 // - 支持暂停/恢复功能
 // - 不支持定位和倍速（直通播放特性）
 // - 零缓冲转发，最低延迟
+// - 多码流设备按客户端反馈切换码流
 
-use super::framerate::FrameRateDetector;
+use super::framerate::{FrameRateDetector, TimestampManager};
+use super::handler::BufferConfig;
+use super::rendition::RenditionSelector;
+use super::telemetry::NetworkFeedback;
 use super::source::{
     SegmentFormat, SegmentSourceType, StreamError, StreamInfo, StreamMode, StreamSource, StreamState,
 };
 use super::source::VideoSegment as SourceVideoSegment;
+use crate::distribution::DistributionManager;
+use crate::latency::ClockSyncManager;
 use async_trait::async_trait;
-use std::time::SystemTime;
-use tokio::sync::broadcast;
-use tracing::{debug, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
 
 // 使用common中的VideoSegment（从QUIC接收）
 use common::VideoSegment as CommonVideoSegment;
@@ -35,6 +44,26 @@ enum SourceState {
     Stopped,
 }
 
+/// 单条轨道（视频或音频）的输出时间线
+struct TrackTimeline {
+    /// 设备时间戳跳变检测
+    timestamps: TimestampManager,
+    /// 下一个输出分片应有的DTS（上一个输出分片的DTS + 时长）
+    next_dts: Option<i64>,
+    /// 另一条轨道已为同一次跳变重新编排时间线，本轨道沿用其偏移
+    pending_rebase: bool,
+}
+
+impl TrackTimeline {
+    fn new() -> Self {
+        Self {
+            timestamps: TimestampManager::new(30.0),
+            next_dts: None,
+            pending_rebase: false,
+        }
+    }
+}
+
 /// 直通播放数据源
 ///
 /// 从QUIC连接接收实时视频分片，实现极低延迟的直通播放。
@@ -80,6 +109,26 @@ pub struct LiveStreamSource {
     bitrate: Option<u64>,
     /// 帧率检测器
     frame_rate_detector: FrameRateDetector,
+    /// 设备时钟同步（用于换算设备发送时间）
+    clock_sync: Option<ClockSyncManager>,
+    /// 接收落后跳过的分片计数
+    lag_counter: Option<Arc<AtomicU64>>,
+    /// 码流选择器与切换请求通道（设备声明了多路码流时使用）
+    rendition_control: Option<(RenditionSelector, mpsc::UnboundedSender<u8>)>,
+    /// 当前接收到的码流ID
+    active_rendition: Option<u8>,
+    /// 关键帧请求入口（分发会话合并限流后转发给设备）
+    keyframe_requests: Option<(DistributionManager, Uuid)>,
+    /// 视频轨道输出时间线
+    video_timeline: TrackTimeline,
+    /// 音频轨道输出时间线
+    audio_timeline: TrackTimeline,
+    /// 设备时间戳到输出时间戳的偏移（`TIMESCALE` 刻度，音视频共用以保持同步）
+    timestamp_offset: i64,
+    /// 检测到的时间戳不连续次数
+    discontinuities: u64,
+    /// 时间戳不连续次数的外部计数器
+    discontinuity_counter: Option<Arc<AtomicU64>>,
 }
 
 impl LiveStreamSource {
@@ -104,7 +153,127 @@ impl LiveStreamSource {
             frame_rate: None,
             bitrate: None,
             frame_rate_detector: FrameRateDetector::new(),
+            clock_sync: None,
+            lag_counter: None,
+            rendition_control: None,
+            active_rendition: None,
+            keyframe_requests: None,
+            video_timeline: TrackTimeline::new(),
+            audio_timeline: TrackTimeline::new(),
+            timestamp_offset: 0,
+            discontinuities: 0,
+            discontinuity_counter: None,
+        }
+    }
+
+    /// 使用设备时钟同步换算分片中的设备发送时间
+    pub fn with_clock_sync(mut self, clock_sync: ClockSyncManager) -> Self {
+        self.clock_sync = Some(clock_sync);
+        self
+    }
+
+    /// 将接收落后跳过的分片数累加到外部计数器
+    pub fn with_lag_counter(mut self, counter: Arc<AtomicU64>) -> Self {
+        self.lag_counter = Some(counter);
+        self
+    }
+
+    /// 将检测到的时间戳不连续次数累加到外部计数器
+    pub fn with_discontinuity_counter(mut self, counter: Arc<AtomicU64>) -> Self {
+        self.discontinuity_counter = Some(counter);
+        self
+    }
+
+    /// 启用码流选择
+    ///
+    /// 选择器决定切换时，目标码流ID通过 `switch_tx` 交给调用方转发给设备。
+    pub fn with_rendition_selector(
+        mut self,
+        selector: RenditionSelector,
+        switch_tx: mpsc::UnboundedSender<u8>,
+    ) -> Self {
+        let rendition = selector.current();
+        self.resolution = Some((rendition.width, rendition.height));
+        self.bitrate = Some(rendition.bitrate);
+        self.rendition_control = Some((selector, switch_tx));
+        self
+    }
+
+    /// 启用关键帧请求，经分发会话限流后转发给设备
+    pub fn with_keyframe_requests(mut self, distribution: DistributionManager, session_id: Uuid) -> Self {
+        self.keyframe_requests = Some((distribution, session_id));
+        self
+    }
+
+    /// 分片码流变化时更新流信息
+    fn observe_rendition(&mut self, rendition_id: u8) {
+        if self.active_rendition == Some(rendition_id) {
+            return;
+        }
+        self.active_rendition = Some(rendition_id);
+
+        let Some((selector, _)) = &self.rendition_control else {
+            return;
+        };
+        if let Some(rendition) = selector.rendition(rendition_id) {
+            info!(
+                "Live stream for device {} now on rendition #{} {} ({}x{})",
+                self.device_id, rendition.id, rendition.name, rendition.width, rendition.height
+            );
+            self.resolution = Some((rendition.width, rendition.height));
+            self.bitrate = Some(rendition.bitrate);
+        }
+    }
+
+    /// 检测设备时间戳不连续并重新编排输出时间戳
+    ///
+    /// 设备重启、编码器重建或时钟跳变会让时间戳倒退或大幅跳跃，MSE 会因此停滞。
+    /// 检测到跳变（或设备标记了不连续）时，后续分片接在上一个输出分片之后，
+    /// 输出时间线保持单调。返回该分片是否为需要通知客户端的不连续点。
+    fn rebase_timestamps(&mut self, segment: &mut CommonVideoSegment) -> bool {
+        let (track, other) = if segment.is_audio() {
+            (&mut self.audio_timeline, &mut self.video_timeline)
+        } else {
+            (&mut self.video_timeline, &mut self.audio_timeline)
+        };
+
+        // 解码配置分片可能是缓存的旧分片，不参与跳变检测
+        let dts_us = common::utils::rescale_timestamp(segment.dts, segment.timescale, 1_000_000).max(0) as u64;
+        let jumped = !segment.is_codec_config() && track.timestamps.handle_discontinuity(dts_us);
+
+        let mut rebased = false;
+        if jumped || segment.is_discontinuity() {
+            if track.pending_rebase {
+                track.pending_rebase = false;
+            } else if let Some(next_dts) = track.next_dts {
+                let offset = next_dts - segment.dts;
+                self.discontinuities += 1;
+                if let Some(counter) = &self.discontinuity_counter {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+                warn!(
+                    "Timestamp discontinuity #{} on device {} ({}): device dts {:.3}s, expected {:.3}s, offset {} -> {}",
+                    self.discontinuities,
+                    self.device_id,
+                    if segment.is_audio() { "audio" } else { "video" },
+                    segment.dts_secs(),
+                    next_dts as f64 / CommonVideoSegment::TIMESCALE as f64,
+                    self.timestamp_offset,
+                    offset
+                );
+                self.timestamp_offset = offset;
+                other.pending_rebase = true;
+                rebased = true;
+            }
+        } else {
+            track.pending_rebase = false;
+        }
+
+        segment.shift_timestamps(self.timestamp_offset);
+        if !segment.is_codec_config() {
+            track.next_dts = Some(segment.dts + segment.duration as i64);
         }
+        rebased || segment.is_discontinuity()
     }
 
     /// 设置流信息
@@ -161,7 +330,7 @@ impl StreamSource for LiveStreamSource {
 
         // 从QUIC接收器获取分片
         match self.quic_receiver.recv().await {
-            Ok(common_segment) => {
+            Ok(mut common_segment) => {
                 // 如果暂停，丢弃分片但继续接收（避免缓冲区溢出）
                 if self.is_paused() {
                     debug!(
@@ -172,40 +341,66 @@ impl StreamSource for LiveStreamSource {
                     return self.next_segment().await;
                 }
 
-                // 更新当前位置
-                self.current_position = common_segment.timestamp;
-
-                // 添加时间戳样本用于帧率检测
-                let pts_us = (common_segment.timestamp * 1_000_000.0) as u64;
-                let receive_time = SystemTime::now();
-                self.frame_rate_detector.add_timestamp_sample(pts_us, receive_time);
-                
-                // 更新检测到的帧率
-                if let Some(detected_fps) = self.frame_rate_detector.get_fps() {
-                    if self.frame_rate.is_none() || 
-                       (self.frame_rate.unwrap() - detected_fps).abs() > 1.0 {
-                        self.frame_rate = Some(detected_fps);
-                        debug!("Updated frame rate for device {}: {:.2} fps", 
-                               self.device_id, detected_fps);
+                // 设备可以声明任意时间基，平台内部统一使用 90kHz
+                common_segment.normalize_timescale();
+                let discontinuity = self.rebase_timestamps(&mut common_segment);
+
+                // 优先使用QUIC层记录的接收时间
+                let receive_time = common_segment.receive_time.unwrap_or_else(SystemTime::now);
+
+                // 位置、码流和帧率只按视频分片统计
+                if !common_segment.is_audio() {
+                    self.current_position = common_segment.pts_secs();
+                    self.observe_rendition(common_segment.rendition_id);
+
+                    // 添加时间戳样本用于帧率检测
+                    let pts_us = common::utils::rescale_timestamp(common_segment.pts, common_segment.timescale, 1_000_000).max(0) as u64;
+                    self.frame_rate_detector.add_timestamp_sample(pts_us, receive_time);
+
+                    // 更新检测到的帧率
+                    if let Some(detected_fps) = self.frame_rate_detector.get_fps() {
+                        if self.frame_rate.is_none() ||
+                           (self.frame_rate.unwrap() - detected_fps).abs() > 1.0 {
+                            self.frame_rate = Some(detected_fps);
+                            debug!("Updated frame rate for device {}: {:.2} fps",
+                                   self.device_id, detected_fps);
+                        }
                     }
                 }
 
                 debug!(
                     "Received live segment: {} at {:.3}s",
-                    common_segment.segment_id, common_segment.timestamp
+                    common_segment.segment_id, common_segment.pts_secs()
                 );
 
+                // 设备发送时间换算为平台时钟（旧版设备或尚未完成时钟同步时为None）
+                let device_send_time = match &self.clock_sync {
+                    Some(clock_sync) if common_segment.send_time_us > 0 => {
+                        clock_sync.to_platform_time(&self.device_id, common_segment.send_time_us)
+                    }
+                    _ => None,
+                };
+
                 // 转换common::VideoSegment到source::VideoSegment
+                let format = if common_segment.is_audio() {
+                    SegmentFormat::Aac
+                } else {
+                    SegmentFormat::raw_video(common_segment.codec)
+                };
                 let source_segment = SourceVideoSegment {
                     segment_id: common_segment.segment_id,
-                    timestamp: common_segment.timestamp,
+                    pts: common_segment.pts,
+                    dts: common_segment.dts,
                     duration: common_segment.duration,
                     data: common_segment.data,
                     is_keyframe: common_segment.flags & 0x01 != 0,
-                    format: SegmentFormat::H264Raw,
+                    decode_only: common_segment.flags & common::SegmentFlags::DECODE_ONLY != 0,
+                    discontinuity,
+                    format,
                     source_type: SegmentSourceType::Live,
-                    receive_time: Some(std::time::SystemTime::now()),
+                    receive_time: Some(receive_time),
                     forward_time: None,
+                    device_send_time,
                 };
 
                 Ok(Some(source_segment))
@@ -216,6 +411,9 @@ impl StreamSource for LiveStreamSource {
                     "LiveStreamSource lagged, skipped {} segments for device: {}",
                     skipped, self.device_id
                 );
+                if let Some(counter) = &self.lag_counter {
+                    counter.fetch_add(skipped, Ordering::Relaxed);
+                }
                 // 继续接收下一个分片
                 self.next_segment().await
             }
@@ -283,6 +481,19 @@ impl StreamSource for LiveStreamSource {
     /// 恢复后，继续接收和转发视频分片。
     async fn resume(&mut self) -> Result<(), StreamError> {
         if self.state == SourceState::Paused {
+            // 丢弃暂停期间缓冲在接收器中的分片
+            let mut dropped = 0;
+            loop {
+                match self.quic_receiver.try_recv() {
+                    Ok(_) => dropped += 1,
+                    Err(broadcast::error::TryRecvError::Lagged(skipped)) => dropped += skipped,
+                    Err(_) => break,
+                }
+            }
+            if dropped > 0 {
+                debug!("Dropped {} segments buffered while paused", dropped);
+            }
+
             self.state = SourceState::Running;
             debug!("LiveStreamSource resumed for device: {}", self.device_id);
             Ok(())
@@ -322,26 +533,48 @@ impl StreamSource for LiveStreamSource {
             playback_rate: 1.0, // 直通播放固定为1.0x
         }
     }
+
+    /// 设置客户端缓冲区配置（用于码流选择）
+    fn set_buffer_config(&mut self, config: &BufferConfig) {
+        if let Some((selector, _)) = &mut self.rendition_control {
+            selector.set_buffer_config(config.clone());
+        }
+    }
+
+    /// 按客户端缓冲和交付吞吐量选择码流
+    fn apply_network_feedback(&mut self, feedback: &NetworkFeedback) {
+        let Some((selector, switch_tx)) = &mut self.rendition_control else {
+            return;
+        };
+        if let Some(rendition_id) = selector.on_feedback(feedback, Instant::now()) {
+            if switch_tx.send(rendition_id).is_err() {
+                warn!(
+                    "Rendition switch channel closed (device: {})",
+                    self.device_id
+                );
+            }
+        }
+    }
+
+    /// 请求设备立即输出关键帧（与其他订阅者的请求合并）
+    async fn request_keyframe(&mut self) -> Result<(), StreamError> {
+        let Some((distribution, session_id)) = &self.keyframe_requests else {
+            return Err(StreamError::OperationNotSupported);
+        };
+        if distribution.request_keyframe(session_id) {
+            debug!("Keyframe requested from device {}", self.device_id);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use tokio::sync::broadcast;
-    use uuid::Uuid;
-
-    fn create_test_segment(timestamp: f64) -> VideoSegment {
-        VideoSegment {
-            segment_id: Uuid::new_v4(),
-            timestamp,
-            duration: 0.033,
-            data: vec![0u8; 1024],
-            is_keyframe: false,
-            format: SegmentFormat::H264Raw,
-            source_type: SegmentSourceType::Live,
-            receive_time: None,
-            forward_time: None,
-        }
+
+    fn create_test_segment(timestamp: f64) -> CommonVideoSegment {
+        CommonVideoSegment::new(vec![0u8; 1024], CommonVideoSegment::ticks(timestamp), 3_000, false)
     }
 
     #[tokio::test]
@@ -370,10 +603,170 @@ mod tests {
         assert!(segment.is_some());
         
         let received = segment.unwrap();
-        assert_eq!(received.timestamp, 1.0);
+        assert_eq!(received.pts_secs(), 1.0);
         assert_eq!(source.state, SourceState::Running);
     }
 
+    #[tokio::test]
+    async fn test_live_source_normalizes_timescale() {
+        let (tx, rx) = broadcast::channel(100);
+        let mut source = LiveStreamSource::new("device_001".to_string(), rx);
+
+        // 设备按毫秒声明时间戳，含 B 帧时 DTS 早于 PTS
+        let mut segment = create_test_segment(0.0);
+        segment.timescale = 1_000;
+        segment.pts = 1_500;
+        segment.dts = 1_460;
+        segment.duration = 40;
+        tx.send(segment).unwrap();
+
+        let received = source.next_segment().await.unwrap().unwrap();
+        assert_eq!((received.pts, received.dts, received.duration), (135_000, 131_400, 3_600));
+        assert_eq!(source.get_info().current_position, 1.5);
+    }
+
+    #[tokio::test]
+    async fn test_live_source_rebases_discontinuity() {
+        let (tx, rx) = broadcast::channel(100);
+        let counter = Arc::new(AtomicU64::new(0));
+        let mut source = LiveStreamSource::new("device_001".to_string(), rx)
+            .with_discontinuity_counter(counter.clone());
+
+        // 设备重启后时间戳从 0 重新开始，随后又向前跳了一分钟
+        for timestamp in [10.0, 10.1, 0.0, 0.1, 60.1, 60.2] {
+            tx.send(create_test_segment(timestamp)).unwrap();
+        }
+        // 音频随后跟上同一次跳变，沿用视频的偏移
+        tx.send(CommonVideoSegment::new_audio(vec![0xFF, 0xF1], CommonVideoSegment::ticks(60.2), 1_920, 1)).unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..7 {
+            received.push(source.next_segment().await.unwrap().unwrap());
+        }
+
+        let video_pts: Vec<i64> = received[..6].iter().map(|segment| segment.pts).collect();
+        assert_eq!(video_pts, vec![900_000, 909_000, 912_000, 921_000, 924_000, 933_000]);
+        let flagged: Vec<bool> = received.iter().map(|segment| segment.discontinuity).collect();
+        assert_eq!(flagged, vec![false, false, true, false, true, false, false]);
+        assert_eq!(received[6].pts, 933_000);
+        assert_eq!(counter.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_live_source_device_flagged_discontinuity() {
+        let (tx, rx) = broadcast::channel(100);
+        let counter = Arc::new(AtomicU64::new(0));
+        let mut source = LiveStreamSource::new("device_001".to_string(), rx)
+            .with_discontinuity_counter(counter.clone());
+
+        tx.send(create_test_segment(1.0)).unwrap();
+        // 编码器重建：时间戳看似连续但设备标记了不连续
+        let mut segment = create_test_segment(1.5);
+        segment.flags |= common::SegmentFlags::DISCONTINUITY;
+        tx.send(segment).unwrap();
+
+        source.next_segment().await.unwrap().unwrap();
+        let received = source.next_segment().await.unwrap().unwrap();
+        assert!(received.discontinuity);
+        assert_eq!(received.pts, CommonVideoSegment::ticks(1.0) + 3_000);
+        assert_eq!(counter.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_live_source_audio_segment() {
+        let (tx, rx) = broadcast::channel(100);
+        let mut source = LiveStreamSource::new("device_001".to_string(), rx);
+
+        tx.send(create_test_segment(1.0)).unwrap();
+        tx.send(CommonVideoSegment::new_audio(vec![0xFF, 0xF1], 99_000, 1_920, 1)).unwrap();
+
+        let video = source.next_segment().await.unwrap().unwrap();
+        assert_eq!(video.format, SegmentFormat::H264Raw);
+        let audio = source.next_segment().await.unwrap().unwrap();
+        assert_eq!(audio.format, SegmentFormat::Aac);
+        assert_eq!(audio.pts_secs(), 1.1);
+        // 播放位置只跟随视频
+        assert_eq!(source.get_info().current_position, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_live_source_converts_device_send_time() {
+        use crate::latency::ClockSample;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let clock_sync = ClockSyncManager::new();
+        // 设备时钟比平台快 2ms
+        clock_sync.record_sample("device_001", 0, ClockSample { offset_us: 2_000, rtt_us: 500 });
+
+        let (tx, rx) = broadcast::channel(100);
+        let mut source = LiveStreamSource::new("device_001".to_string(), rx)
+            .with_clock_sync(clock_sync);
+
+        let mut synced = create_test_segment(1.0);
+        synced.send_time_us = 5_000_000;
+        tx.send(synced).unwrap();
+        // 旧版设备不携带发送时间
+        let mut legacy = create_test_segment(2.0);
+        legacy.send_time_us = 0;
+        tx.send(legacy).unwrap();
+
+        let received = source.next_segment().await.unwrap().unwrap();
+        assert_eq!(
+            received.device_send_time,
+            Some(UNIX_EPOCH + Duration::from_micros(4_998_000))
+        );
+        let received = source.next_segment().await.unwrap().unwrap();
+        assert!(received.device_send_time.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_live_source_rendition_switch() {
+        use crate::streaming::rendition::ViewerLimits;
+        use common::RenditionInfo;
+
+        let renditions = vec![
+            RenditionInfo {
+                id: 0,
+                name: "main".to_string(),
+                width: 1280,
+                height: 720,
+                bitrate: 2_000_000,
+                fps: 30,
+            },
+            RenditionInfo {
+                id: 1,
+                name: "sub".to_string(),
+                width: 640,
+                height: 360,
+                bitrate: 600_000,
+                fps: 30,
+            },
+        ];
+        let selector = RenditionSelector::new(renditions, ViewerLimits::default()).unwrap();
+        let (switch_tx, mut switch_rx) = mpsc::unbounded_channel();
+
+        let (tx, rx) = broadcast::channel(100);
+        let mut source = LiveStreamSource::new("device_001".to_string(), rx)
+            .with_rendition_selector(selector, switch_tx);
+        assert_eq!(source.get_info().resolution, Some((1280, 720)));
+
+        // 起播阶段的反馈不触发切换
+        let congested = NetworkFeedback {
+            buffered_ms: 0,
+            delivery_mbps: 0.5,
+        };
+        source.apply_network_feedback(&congested);
+        assert!(switch_rx.try_recv().is_err());
+
+        // 收到子码流的分片后更新分辨率
+        let mut segment = CommonVideoSegment::new(vec![0u8; 64], 90_000, 3_000, true);
+        segment.rendition_id = 1;
+        tx.send(segment).unwrap();
+        source.next_segment().await.unwrap().unwrap();
+        assert_eq!(source.get_info().resolution, Some((640, 360)));
+        assert_eq!(source.get_info().bitrate, Some(600_000));
+    }
+
     #[tokio::test]
     async fn test_live_source_pause_resume() {
         let (tx, rx) = broadcast::channel(100);
@@ -457,6 +850,6 @@ mod tests {
         
         // 应该接收到恢复后的分片
         let segment = result.unwrap().unwrap();
-        assert_eq!(segment.timestamp, 5.0);
+        assert_eq!(segment.pts_secs(), 5.0);
     }
 }
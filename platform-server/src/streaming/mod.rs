@@ -7,6 +7,7 @@
 // - `StreamSource`: 统一的数据源抽象接口
 // - `LiveStreamSource`: 直通播放数据源实现
 // - `PlaybackSource`: 录像回放数据源实现
+// - `RenditionSelector`: 直通播放多码流选择
 // - `UnifiedStreamHandler`: 统一流处理器
 // - `FileStreamReader`: 文件流式读取器
 //
@@ -24,16 +25,19 @@ pub mod framerate;
 pub mod handler;
 pub mod live_source;
 pub mod playback_source;
+pub mod rendition;
 pub mod source;
+pub mod telemetry;
 
 // 重新导出核心类型
 pub use error::{ErrorRecoveryPolicy, RetryStrategy, StreamError};
-pub use file_reader::{FileReaderConfig, FileStreamReader};
-pub use fmp4_converter::{FMP4Converter, FMP4ConverterConfig};
+pub use fmp4_converter::{FMP4Converter, FMP4ConverterConfig, LiveFMP4Muxer};
 pub use framerate::{DetectionMethod, FrameRateDetector, FrameRateInfo};
 pub use handler::{BufferConfig, LatencyAlert, StreamConfig, StreamStats, UnifiedStreamHandler};
 pub use live_source::LiveStreamSource;
 pub use playback_source::PlaybackSource;
+pub use rendition::{RenditionSelector, ViewerLimits};
 pub use source::{
     SegmentFormat, StreamInfo, StreamMode, StreamSource, StreamState, VideoSegment,
 };
+pub use telemetry::{ClientTelemetryReport, TelemetryDecision};
@@ -6,18 +6,26 @@
 //
 // - 从FileStreamReader获取录像分片
 // - 支持完整的播放控制（暂停、恢复、定位、倍速）
-// - 小分片读取（8KB-32KB）实现低延迟
+// - 可解析的录像按访问单元分片，其他文件按小分片（8KB-32KB）读取
 // - 速率控制支持0.25x-4x倍速
+// - 超过4x的快进和倒放按关键帧索引只发送关键帧（trick-play）
+// - 精确定位：从前一个关键帧开始发送，目标帧之前的帧标记为只解码不显示
+// - 快进/倒放和精确定位复用FileStreamReader打开时构建的访问单元表和关键帧索引
 
+use super::file_reader::{FileReaderConfig, FileStreamReader};
 use super::framerate::{FrameRateDetector, FrameRatePacer};
+use super::handler::BufferConfig;
+use super::telemetry::NetworkFeedback;
 use super::source::{
     SegmentFormat, SegmentSourceType, StreamError, StreamInfo, StreamMode, StreamSource, StreamState, VideoSegment,
 };
 use async_trait::async_trait;
+use common::h264::{self, AccessUnit};
+use common::trick_play::{self, TrickPlayScheduler};
+use common::KeyframeEntry;
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::time::SystemTime;
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tracing::{debug, warn};
 use uuid::Uuid;
 
@@ -36,110 +44,15 @@ enum SourceState {
     Stopped,
 }
 
-/// 文件流式读取器（简化版本，完整版本在file_reader.rs）
-///
-/// 这是一个临时实现，用于支持PlaybackSource的基本功能。
-/// 完整的FileStreamReader将在任务2.1中实现。
-struct SimpleFileReader {
-    file: File,
-    file_path: PathBuf,
-    file_size: u64,
-    current_offset: u64,
-    segment_size: usize,
-    playback_rate: f64,
-}
-
-impl SimpleFileReader {
-    async fn new(file_path: PathBuf) -> Result<Self, StreamError> {
-        let file = File::open(&file_path)
-            .await
-            .map_err(|e| StreamError::FileNotFound(e.to_string()))?;
-
-        let metadata = file
-            .metadata()
-            .await
-            .map_err(|e| StreamError::FileReadError(e.to_string()))?;
-
-        Ok(Self {
-            file,
-            file_path,
-            file_size: metadata.len(),
-            current_offset: 0,
-            segment_size: 8192, // 8KB默认分片大小
-            playback_rate: 1.0,
-        })
-    }
-
-    async fn read_segment(&mut self) -> Result<Option<VideoSegment>, StreamError> {
-        if self.current_offset >= self.file_size {
-            return Ok(None);
-        }
-
-        let mut buffer = vec![0u8; self.segment_size];
-        let bytes_read = self
-            .file
-            .read(&mut buffer)
-            .await
-            .map_err(|e| StreamError::FileReadError(e.to_string()))?;
-
-        if bytes_read == 0 {
-            return Ok(None);
-        }
-
-        buffer.truncate(bytes_read);
-        self.current_offset += bytes_read as u64;
-
-        // 计算时间戳（简化版本）
-        let timestamp = (self.current_offset as f64 / self.file_size as f64) * 100.0;
-
-        Ok(Some(VideoSegment {
-            segment_id: Uuid::new_v4(),
-            timestamp,
-            duration: 0.033, // 假设30fps
-            data: buffer,
-            is_keyframe: false,
-            format: SegmentFormat::MP4,
-            source_type: SegmentSourceType::Playback,
-            receive_time: Some(SystemTime::now()), // 设置读取时间
-            forward_time: None,
-        }))
-    }
-
-    async fn seek_to(&mut self, position: f64) -> Result<(), StreamError> {
-        // 简化的定位实现：按比例定位
-        let target_offset = ((position / 100.0) * self.file_size as f64) as u64;
-        let target_offset = target_offset.min(self.file_size);
-
-        self.file
-            .seek(std::io::SeekFrom::Start(target_offset))
-            .await
-            .map_err(|e| StreamError::FileReadError(e.to_string()))?;
-
-        self.current_offset = target_offset;
-        Ok(())
-    }
-
-    fn set_rate(&mut self, rate: f64) {
-        self.playback_rate = rate;
-    }
-
-    fn get_progress(&self) -> f64 {
-        if self.file_size == 0 {
-            0.0
-        } else {
-            (self.current_offset as f64 / self.file_size as f64) * 100.0
-        }
-    }
-}
-
 /// 录像回放数据源
 ///
 /// 从文件系统读取视频文件，实现低延迟的录像回放。
 ///
 /// # 特性
 ///
-/// - **小分片读取**: 使用8KB-32KB小分片降低延迟
+/// - **按帧分片**: 可解析的录像每帧一个分片，其他文件使用8KB-32KB小分片
 /// - **速率控制**: 支持0.25x-4x倍速播放
+/// - **快进/倒放**: 最高32x的快进和倒放，只发送关键帧
 /// - **精确定位**: 支持定位到任意时间位置
 /// - **暂停/恢复**: 支持暂停和恢复播放
 /// - **低延迟**: 录像回放延迟<200ms
@@ -170,7 +83,7 @@ pub struct PlaybackSource {
     /// 文件ID
     file_id: String,
     /// 文件读取器
-    file_reader: SimpleFileReader,
+    file_reader: FileStreamReader,
     /// 播放速率
     playback_rate: f64,
     /// 当前状态
@@ -187,6 +100,16 @@ pub struct PlaybackSource {
     frame_rate_detector: FrameRateDetector,
     /// 帧率控制器
     frame_rate_pacer: Option<FrameRatePacer>,
+    /// 客户端缓冲区配置（pacer初始化时应用）
+    buffer_config: BufferConfig,
+    /// 关键帧索引（取自文件读取器）
+    keyframe_index: Vec<KeyframeEntry>,
+    /// 精确定位待发送的预解码帧
+    preroll: VecDeque<AccessUnit>,
+    /// 快进/倒放调度器（只发关键帧时使用）
+    trick_play: Option<TrickPlayScheduler>,
+    /// 已输出分片的时间线末尾，切换播放方式时输出时间戳从这里继续
+    output_end: f64,
 }
 
 impl PlaybackSource {
@@ -203,7 +126,9 @@ impl PlaybackSource {
     pub async fn new(file_id: String, file_path: PathBuf) -> Result<Self, StreamError> {
         debug!("Creating PlaybackSource for file: {:?}", file_path);
 
-        let file_reader = SimpleFileReader::new(file_path).await?;
+        let file_reader = FileStreamReader::new(file_path, FileReaderConfig::default()).await?;
+        let keyframe_index = file_reader.keyframe_index();
+        let duration = file_reader.duration();
 
         Ok(Self {
             file_id,
@@ -213,9 +138,14 @@ impl PlaybackSource {
             resolution: None,
             frame_rate: None,
             bitrate: None,
-            duration: Some(100.0), // 假设100秒时长
+            duration: Some(duration),
             frame_rate_detector: FrameRateDetector::new(),
             frame_rate_pacer: None, // 将在检测到帧率后初始化
+            buffer_config: BufferConfig::default(),
+            keyframe_index,
+            preroll: VecDeque::new(),
+            trick_play: None,
+            output_end: 0.0,
         })
     }
 
@@ -255,14 +185,137 @@ impl PlaybackSource {
         self.state == SourceState::Paused
     }
 
-    /// 验证播放速率
+    /// 验证播放速率（负数为倒放）
     fn validate_rate(rate: f64) -> Result<(), StreamError> {
-        if rate < 0.25 || rate > 4.0 {
+        if !trick_play::is_valid_rate(rate) {
             return Err(StreamError::InvalidPlaybackRate(rate));
         }
         Ok(())
     }
 
+    /// 获取访问单元表，文件无法解析访问单元时不支持
+    fn access_units(&self) -> Result<&[AccessUnit], StreamError> {
+        match self.file_reader.access_units() {
+            [] => Err(StreamError::OperationNotSupported),
+            units => Ok(units),
+        }
+    }
+
+    /// 获取关键帧索引，文件没有关键帧时不支持
+    fn keyframes(&self) -> Result<&[KeyframeEntry], StreamError> {
+        match self.keyframe_index.as_slice() {
+            [] => Err(StreamError::OperationNotSupported),
+            keyframes => Ok(keyframes),
+        }
+    }
+
+    /// 进入或调整快进/倒放
+    async fn enter_trick_play(&mut self, rate: f64) -> Result<(), StreamError> {
+        if let Some(scheduler) = &mut self.trick_play {
+            scheduler.set_rate(rate);
+            return Ok(());
+        }
+
+        let current_offset = self.file_reader.get_offset();
+        let keyframes = self.keyframes()?;
+        // 从当前读取位置之前最近的关键帧开始扫描
+        let position = keyframes
+            .iter()
+            .rev()
+            .find(|k| k.file_offset <= current_offset)
+            .map_or(0.0, |k| k.timestamp);
+        let times = keyframes.iter().map(|k| k.timestamp).collect();
+        self.trick_play = Some(TrickPlayScheduler::new(times, rate, position, self.output_end));
+        Ok(())
+    }
+
+    /// 退出快进/倒放，从扫描停下的关键帧继续常规播放
+    async fn leave_trick_play(&mut self) -> Result<(), StreamError> {
+        let Some(scheduler) = self.trick_play.take() else {
+            return Ok(());
+        };
+        let offset = scheduler
+            .current_index()
+            .and_then(|index| self.keyframe_index.get(index))
+            .map(|k| k.file_offset);
+        if let Some(offset) = offset {
+            self.file_reader.seek_to_offset(offset).await?;
+        }
+        Ok(())
+    }
+
+    /// 快进/倒放：读取下一个调度的关键帧
+    async fn next_trick_play_segment(&mut self) -> Result<Option<VideoSegment>, StreamError> {
+        let Some(frame) = self.trick_play.as_mut().and_then(|s| s.next_frame()) else {
+            debug!("Trick-play reached the end of file: {}", self.file_id);
+            self.state = SourceState::Stopped;
+            return Ok(None);
+        };
+        let units = self.file_reader.access_units();
+        let Some(unit) = self
+            .keyframe_index
+            .get(frame.index)
+            .and_then(|k| units.get(units.partition_point(|u| u.offset < k.file_offset)))
+            .copied()
+        else {
+            return Err(StreamError::Internal("Keyframe index out of range".to_string()));
+        };
+
+        let data = self.file_reader.read_unit(&unit).await?;
+
+        debug!(
+            "Trick-play keyframe at {:.3}s -> output {:.3}s ({:.2}x)",
+            frame.media_time,
+            frame.timestamp,
+            self.playback_rate
+        );
+
+        // 关键帧按重写后的时长发送，播放器以正常速度渲染
+        tokio::time::sleep(tokio::time::Duration::from_secs_f64(frame.duration)).await;
+        self.output_end = frame.timestamp + frame.duration;
+
+        Ok(Some(VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: VideoSegment::ticks(frame.timestamp),
+            dts: VideoSegment::ticks(frame.timestamp),
+            duration: VideoSegment::ticks(frame.duration) as u32,
+            data,
+            is_keyframe: true,
+            decode_only: false,
+            discontinuity: false,
+            format: SegmentFormat::raw_video(self.file_reader.codec()),
+            source_type: SegmentSourceType::Playback,
+            receive_time: Some(SystemTime::now()),
+            forward_time: None,
+            device_send_time: None,
+        }))
+    }
+
+    /// 精确定位：发送下一个预解码帧
+    ///
+    /// 预解码帧不按帧率节流，尽快送达播放器以便从目标帧开始显示。
+    async fn next_preroll_segment(&mut self, unit: AccessUnit) -> Result<Option<VideoSegment>, StreamError> {
+        let data = self.file_reader.read_unit(&unit).await?;
+        let duration = unit.duration;
+        self.output_end = unit.timestamp + duration;
+
+        Ok(Some(VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: VideoSegment::ticks(unit.timestamp),
+            dts: VideoSegment::ticks(unit.decode_timestamp),
+            duration: VideoSegment::ticks(duration) as u32,
+            data,
+            is_keyframe: unit.is_keyframe,
+            decode_only: true,
+            discontinuity: false,
+            format: SegmentFormat::raw_video(self.file_reader.codec()),
+            source_type: SegmentSourceType::Playback,
+            receive_time: Some(SystemTime::now()),
+            forward_time: None,
+            device_send_time: None,
+        }))
+    }
+
     /// 验证定位位置
     fn validate_position(&self, position: f64) -> Result<(), StreamError> {
         if position < 0.0 {
@@ -305,11 +358,19 @@ impl StreamSource for PlaybackSource {
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         }
 
+        if self.trick_play.is_some() {
+            return self.next_trick_play_segment().await;
+        }
+
+        if let Some(unit) = self.preroll.pop_front() {
+            return self.next_preroll_segment(unit).await;
+        }
+
         // 从文件读取器获取分片
         match self.file_reader.read_segment().await {
             Ok(Some(segment)) => {
                 // 添加时间戳样本用于帧率检测
-                let pts_us = (segment.timestamp * 1_000_000.0) as u64;
+                let pts_us = (segment.pts.max(0) as u64) * 1_000_000 / VideoSegment::TIMESCALE as u64;
                 let receive_time = SystemTime::now();
                 self.frame_rate_detector.add_timestamp_sample(pts_us, receive_time);
                 
@@ -331,6 +392,9 @@ impl StreamSource for PlaybackSource {
                             if let Err(e) = pacer.set_playback_rate(self.playback_rate) {
                                 warn!("Failed to set playback rate: {}", e);
                             }
+                            if let Err(e) = pacer.set_buffer_config(self.buffer_config.clone()) {
+                                warn!("Failed to set buffer config: {}", e);
+                            }
                             self.frame_rate_pacer = Some(pacer);
                             debug!("Initialized FrameRatePacer for file {}", self.file_id);
                         }
@@ -339,21 +403,23 @@ impl StreamSource for PlaybackSource {
 
                 debug!(
                     "Read playback segment: {} at {:.3}s",
-                    segment.segment_id, segment.timestamp
+                    segment.segment_id, segment.pts_secs()
                 );
 
                 // 使用FrameRatePacer控制发送速率
                 if let Some(ref mut pacer) = self.frame_rate_pacer {
                     // 假设每个分片包含1帧（简化实现）
+                    pacer.record_segment(segment.data.len(), 1);
                     pacer.wait_for_next_frame(1).await;
                 } else {
                     // 如果pacer还未初始化，使用简单的延迟控制（回退方案）
                     if self.playback_rate != 1.0 {
-                        let delay = (segment.duration / self.playback_rate * 1000.0) as u64;
+                        let delay = (segment.duration_secs() / self.playback_rate * 1000.0) as u64;
                         tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
                     }
                 }
 
+                self.output_end = segment.pts_secs() + segment.duration_secs();
                 Ok(Some(segment))
             }
             Ok(None) => {
@@ -386,10 +452,18 @@ impl StreamSource for PlaybackSource {
             position, self.file_id
         );
 
+        self.preroll.clear();
+
+        // 快进/倒放中按关键帧时间表定位
+        if let Some(scheduler) = &mut self.trick_play {
+            scheduler.seek(position);
+            return Ok(());
+        }
+
         let old_state = self.state.clone();
         self.state = SourceState::Seeking;
 
-        match self.file_reader.seek_to(position).await {
+        match self.file_reader.seek_to_time(position).await {
             Ok(()) => {
                 self.state = old_state;
                 debug!("Seek completed for file: {}", self.file_id);
@@ -403,16 +477,47 @@ impl StreamSource for PlaybackSource {
         }
     }
 
+    /// 精确定位到指定时间位置
+    ///
+    /// 从目标之前的关键帧开始发送，关键帧到目标帧之间的帧标记为 `decode_only`。
+    /// 快进/倒放中只发关键帧，退化为按关键帧定位。
+    async fn seek_accurate(&mut self, position: f64) -> Result<(), StreamError> {
+        if self.trick_play.is_some() {
+            return self.seek(position).await;
+        }
+        self.validate_position(position)?;
+
+        let units = self.access_units()?;
+        let Some(plan) = h264::plan_accurate_seek(units, position) else {
+            return Err(StreamError::InvalidSeekPosition(position));
+        };
+        let preroll: VecDeque<AccessUnit> = units[plan.preroll()].iter().copied().collect();
+        let target = units[plan.target];
+
+        debug!(
+            "Accurate seek to {:.3}s for file {}: keyframe at {:.3}s, {} pre-roll frames",
+            position,
+            self.file_id,
+            units[plan.keyframe].timestamp,
+            preroll.len()
+        );
+
+        // 预解码帧发送完后从目标帧继续顺序读取
+        self.file_reader.seek_to_offset(target.offset).await?;
+        self.preroll = preroll;
+        Ok(())
+    }
+
     /// 设置播放速率
     ///
     /// # 参数
     ///
-    /// - `rate`: 播放速率（0.25x - 4.0x）
+    /// - `rate`: 播放速率（0.25x - 4.0x 常规播放；超过4x或负数为只发关键帧的快进/倒放，最高32x）
     ///
     /// # 返回
     ///
     /// - `Ok(())`: 设置成功
-    /// - `Err(error)`: 速率无效
+    /// - `Err(error)`: 速率无效，或文件没有可用的关键帧索引
     async fn set_rate(&mut self, rate: f64) -> Result<(), StreamError> {
         Self::validate_rate(rate)?;
 
@@ -421,16 +526,22 @@ impl StreamSource for PlaybackSource {
             rate, self.file_id
         );
 
-        self.playback_rate = rate;
-        self.file_reader.set_rate(rate);
-        
-        // 更新FrameRatePacer的倍速
-        if let Some(ref mut pacer) = self.frame_rate_pacer {
-            if let Err(e) = pacer.set_playback_rate(rate) {
-                warn!("Failed to update pacer playback rate: {}", e);
+        if trick_play::is_trick_play_rate(rate) {
+            self.preroll.clear();
+            self.enter_trick_play(rate).await?;
+        } else {
+            self.leave_trick_play().await?;
+
+            // 更新FrameRatePacer的倍速
+            if let Some(ref mut pacer) = self.frame_rate_pacer {
+                if let Err(e) = pacer.set_playback_rate(rate) {
+                    warn!("Failed to update pacer playback rate: {}", e);
+                }
             }
         }
 
+        self.playback_rate = rate;
+
         Ok(())
     }
 
@@ -488,10 +599,30 @@ impl StreamSource for PlaybackSource {
             frame_rate: self.frame_rate,
             bitrate: self.bitrate,
             duration: self.duration,
-            current_position: self.file_reader.get_progress(),
+            current_position: match &self.trick_play {
+                Some(scheduler) => scheduler.position(),
+                None => self.file_reader.position(),
+            },
             playback_rate: self.playback_rate,
         }
     }
+
+    /// 设置客户端缓冲区配置
+    fn set_buffer_config(&mut self, config: &BufferConfig) {
+        self.buffer_config = config.clone();
+        if let Some(ref mut pacer) = self.frame_rate_pacer {
+            if let Err(e) = pacer.set_buffer_config(config.clone()) {
+                warn!("Failed to update pacer buffer config: {}", e);
+            }
+        }
+    }
+
+    /// 按客户端缓冲和交付吞吐量调整发送速率
+    fn apply_network_feedback(&mut self, feedback: &NetworkFeedback) {
+        if let Some(ref mut pacer) = self.frame_rate_pacer {
+            pacer.adjust_for_network(feedback.delivery_mbps, feedback.buffered_ms);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -500,6 +631,20 @@ mod tests {
     use std::io::Write;
     use tempfile::NamedTempFile;
 
+    /// 生成 H.264 码流：`gops` 个 GOP，每个 GOP 30 帧（30fps 下为 1 秒）
+    fn h264_stream(gops: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        for gop in 0..gops {
+            data.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1f]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x38, 0x80]);
+            data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, gop as u8, 0xaa]);
+            for _ in 1..30 {
+                data.extend_from_slice(&[0, 0, 0, 1, 0x41, 0x9a, 0x55]);
+            }
+        }
+        data
+    }
+
     async fn create_test_file() -> NamedTempFile {
         let mut file = NamedTempFile::new().unwrap();
         // 写入一些测试数据
@@ -580,10 +725,16 @@ mod tests {
 
         // 测试无效速率
         assert!(matches!(
-            source.set_rate(5.0).await,
+            source.set_rate(64.0).await,
             Err(StreamError::InvalidPlaybackRate(_))
         ));
 
+        // 快进需要关键帧索引，非 H.264 文件不支持
+        assert!(matches!(
+            source.set_rate(8.0).await,
+            Err(StreamError::OperationNotSupported)
+        ));
+
         assert!(matches!(
             source.set_rate(0.1).await,
             Err(StreamError::InvalidPlaybackRate(_))
@@ -592,16 +743,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_playback_source_seek() {
-        let temp_file = create_test_file().await;
-        let mut source = PlaybackSource::new(
-            "rec_001".to_string(),
-            temp_file.path().to_path_buf(),
-        )
-        .await
-        .unwrap();
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&h264_stream(3)).unwrap();
+        let mut source = PlaybackSource::new("rec_001".to_string(), file.path().to_path_buf())
+            .await
+            .unwrap();
+        assert_eq!(source.get_info().duration, Some(3.0));
 
         // 测试有效定位
-        assert!(source.seek(50.0).await.is_ok());
+        assert!(source.seek(1.5).await.is_ok());
+        assert_eq!(source.get_info().current_position, 1.0);
 
         // 测试无效定位
         assert!(matches!(
@@ -645,4 +796,73 @@ mod tests {
         assert_eq!(info.bitrate, Some(5_000_000));
         assert_eq!(info.duration, Some(100.0));
     }
+
+    #[tokio::test]
+    async fn test_playback_source_trick_play() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&h264_stream(20)).unwrap();
+        let mut source = PlaybackSource::new("rec_001".to_string(), file.path().to_path_buf())
+            .await
+            .unwrap();
+
+        // 16x 快进：每秒一个关键帧，每个输出关键帧推进 2 秒
+        source.set_rate(16.0).await.unwrap();
+        let mut timestamps = Vec::new();
+        for _ in 0..3 {
+            let segment = source.next_segment().await.unwrap().unwrap();
+            assert!(segment.is_keyframe);
+            assert_eq!(segment.data[4], 0x67);
+            timestamps.push(segment.pts);
+        }
+        assert_eq!(timestamps, vec![0, 11_250, 22_500]);
+        assert_eq!(source.get_info().current_position, 4.0);
+
+        // 倒放：从当前位置往回扫描，输出时间戳继续递增
+        source.set_rate(-8.0).await.unwrap();
+        let back: Vec<VideoSegment> = vec![
+            source.next_segment().await.unwrap().unwrap(),
+            source.next_segment().await.unwrap().unwrap(),
+        ];
+        assert_eq!(back[0].data[22], 4);
+        assert_eq!(back[1].data[22], 3);
+        assert!(back[0].pts >= 33_750);
+        assert!(back[1].pts > back[0].pts);
+
+        // 恢复常规播放：从最近的关键帧继续读取
+        source.set_rate(1.0).await.unwrap();
+        let segment = source.next_segment().await.unwrap().unwrap();
+        assert_eq!(segment.data[4], 0x67);
+    }
+
+    #[tokio::test]
+    async fn test_playback_source_accurate_seek() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&h264_stream(3)).unwrap();
+        let mut source = PlaybackSource::new("rec_001".to_string(), file.path().to_path_buf())
+            .await
+            .unwrap();
+
+        // 1.5 秒：从 1.0 秒的关键帧开始，前 15 帧只解码不显示
+        source.seek_accurate(1.5).await.unwrap();
+        let mut preroll = Vec::new();
+        loop {
+            let segment = source.next_segment().await.unwrap().unwrap();
+            if !segment.decode_only {
+                // 第一个显示的分片从目标帧开始
+                assert_eq!(&segment.data[..5], &[0, 0, 0, 1, 0x41]);
+                break;
+            }
+            preroll.push(segment);
+        }
+        assert_eq!(preroll.len(), 15);
+        assert!(preroll[0].is_keyframe);
+        assert_eq!(preroll[0].data[4], 0x67);
+        assert_eq!(preroll[0].pts, 90_000);
+        assert_eq!(preroll[14].pts, 132_000);
+
+        // 普通定位丢弃未发送的预解码帧
+        source.seek_accurate(2.5).await.unwrap();
+        source.seek(0.0).await.unwrap();
+        assert!(!source.next_segment().await.unwrap().unwrap().decode_only);
+    }
 }
@@ -0,0 +1,283 @@
+// 直通播放码流选择
+//
+// 设备可以同时提供多路码流（主码流、子码流等），平台为每个观看会话选择一路：
+// - 起播时按客户端声明的分辨率/码率上限选择最高的一路（移动端自动选中子码流）
+// - 播放中根据客户端遥测的缓冲时长和实测交付吞吐量升降码流
+//
+// 切换只在新码流的关键帧处生效，由设备和分发层共同保证。
+
+use super::handler::BufferConfig;
+use super::telemetry::NetworkFeedback;
+use common::RenditionInfo;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// 两次切换的最小间隔（起播阶段同样适用，等待客户端缓冲建立）
+const SWITCH_MIN_INTERVAL: Duration = Duration::from_secs(5);
+/// 升码流前需要持续健康的时长
+const UPSWITCH_HOLD: Duration = Duration::from_secs(15);
+/// 降码流时目标码率占实测吞吐量的比例
+const DOWNSWITCH_HEADROOM: f64 = 0.8;
+/// 实测吞吐量达到当前码率的该比例视为完整交付
+const DELIVERY_HEALTHY_RATIO: f64 = 0.9;
+
+/// 观看端限制（来自启动请求）
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ViewerLimits {
+    /// 最大画面高度（像素）
+    pub max_height: Option<u32>,
+    /// 最大码率（bps）
+    pub max_bitrate: Option<u64>,
+}
+
+impl ViewerLimits {
+    fn allows(&self, rendition: &RenditionInfo) -> bool {
+        self.max_height.is_none_or(|max| rendition.height <= max)
+            && self.max_bitrate.is_none_or(|max| rendition.bitrate <= max)
+    }
+}
+
+/// 单个观看会话的码流选择器
+#[derive(Debug, Clone)]
+pub struct RenditionSelector {
+    /// 按码率升序排列
+    renditions: Vec<RenditionInfo>,
+    limits: ViewerLimits,
+    buffer_config: BufferConfig,
+    current: usize,
+    last_switch: Option<Instant>,
+    healthy_since: Option<Instant>,
+}
+
+impl RenditionSelector {
+    /// 创建选择器，设备未声明码流时返回 None
+    ///
+    /// 初始码流为限制范围内码率最高的一路；没有满足限制的码流时选最低的一路。
+    pub fn new(mut renditions: Vec<RenditionInfo>, limits: ViewerLimits) -> Option<Self> {
+        if renditions.is_empty() {
+            return None;
+        }
+        renditions.sort_by_key(|r| r.bitrate);
+        let current = renditions.iter().rposition(|r| limits.allows(r)).unwrap_or(0);
+
+        Some(Self {
+            renditions,
+            limits,
+            buffer_config: BufferConfig::default(),
+            current,
+            last_switch: None,
+            healthy_since: None,
+        })
+    }
+
+    /// 当前选定的码流
+    pub fn current(&self) -> &RenditionInfo {
+        &self.renditions[self.current]
+    }
+
+    /// 按ID查找码流
+    pub fn rendition(&self, id: u8) -> Option<&RenditionInfo> {
+        self.renditions.iter().find(|r| r.id == id)
+    }
+
+    /// 全部码流（按码率升序）
+    pub fn renditions(&self) -> &[RenditionInfo] {
+        &self.renditions
+    }
+
+    /// 设置客户端缓冲区配置
+    pub fn set_buffer_config(&mut self, config: BufferConfig) {
+        self.buffer_config = config;
+    }
+
+    /// 根据客户端网络反馈决定是否切换，需要切换时返回目标码流ID
+    pub fn on_feedback(&mut self, feedback: &NetworkFeedback, now: Instant) -> Option<u8> {
+        let current = self.current();
+        let current_bps = current.bitrate as f64;
+        let delivery_bps = feedback.delivery_mbps * 1_000_000.0;
+        let low_buffer = feedback.buffered_ms < self.buffer_config.min_buffer_ms as u64;
+
+        // 缓冲不足且交付不及当前码率（吞吐量未知时只看缓冲）视为拥塞
+        let congested = low_buffer && (delivery_bps <= 0.0 || delivery_bps < current_bps);
+        let healthy = !low_buffer && delivery_bps >= current_bps * DELIVERY_HEALTHY_RATIO;
+
+        if healthy {
+            self.healthy_since.get_or_insert(now);
+        } else {
+            self.healthy_since = None;
+        }
+
+        // 起播或刚切换后不做决定
+        let last_switch = *self.last_switch.get_or_insert(now);
+        if now.duration_since(last_switch) < SWITCH_MIN_INTERVAL {
+            return None;
+        }
+
+        let target = if congested {
+            self.downswitch_target(delivery_bps)?
+        } else if self
+            .healthy_since
+            .is_some_and(|since| now.duration_since(since) >= UPSWITCH_HOLD)
+        {
+            self.upswitch_target()?
+        } else {
+            return None;
+        };
+
+        let from = self.current().id;
+        self.current = target;
+        self.last_switch = Some(now);
+        self.healthy_since = None;
+
+        let to = self.current();
+        info!(
+            "Rendition switch #{} -> #{} ({}x{}, {} kbps): buffered={}ms, delivery={:.2}Mbps",
+            from,
+            to.id,
+            to.width,
+            to.height,
+            to.bitrate / 1000,
+            feedback.buffered_ms,
+            feedback.delivery_mbps
+        );
+        Some(to.id)
+    }
+
+    /// 降码流目标：实测吞吐量能承载的最高一路，吞吐量未知时降一档
+    fn downswitch_target(&self, delivery_bps: f64) -> Option<usize> {
+        if self.current == 0 {
+            return None;
+        }
+        if delivery_bps <= 0.0 {
+            return Some(self.current - 1);
+        }
+        let budget = delivery_bps * DOWNSWITCH_HEADROOM;
+        Some(
+            self.renditions[..self.current]
+                .iter()
+                .rposition(|r| r.bitrate as f64 <= budget)
+                .unwrap_or(0),
+        )
+    }
+
+    /// 升码流目标：限制范围内的下一档
+    fn upswitch_target(&self) -> Option<usize> {
+        let next = self.current + 1;
+        self.renditions
+            .get(next)
+            .filter(|r| self.limits.allows(r))
+            .map(|_| next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rendition(id: u8, height: u32, bitrate: u64) -> RenditionInfo {
+        RenditionInfo {
+            id,
+            name: format!("r{}", id),
+            width: height * 16 / 9,
+            height,
+            bitrate,
+            fps: 30,
+        }
+    }
+
+    fn renditions() -> Vec<RenditionInfo> {
+        vec![
+            rendition(0, 1080, 4_000_000),
+            rendition(1, 360, 600_000),
+            rendition(2, 720, 2_000_000),
+        ]
+    }
+
+    fn feedback(buffered_ms: u64, delivery_mbps: f64) -> NetworkFeedback {
+        NetworkFeedback {
+            buffered_ms,
+            delivery_mbps,
+        }
+    }
+
+    #[test]
+    fn test_initial_selection_respects_limits() {
+        assert!(RenditionSelector::new(Vec::new(), ViewerLimits::default()).is_none());
+
+        let selector = RenditionSelector::new(renditions(), ViewerLimits::default()).unwrap();
+        assert_eq!(selector.current().id, 0);
+
+        // 移动端：屏幕高度限制选中子码流
+        let mobile = ViewerLimits {
+            max_height: Some(480),
+            max_bitrate: None,
+        };
+        let selector = RenditionSelector::new(renditions(), mobile).unwrap();
+        assert_eq!(selector.current().id, 1);
+
+        // 没有满足限制的码流时选最低一路
+        let tiny = ViewerLimits {
+            max_height: None,
+            max_bitrate: Some(100_000),
+        };
+        let selector = RenditionSelector::new(renditions(), tiny).unwrap();
+        assert_eq!(selector.current().id, 1);
+    }
+
+    #[test]
+    fn test_downswitch_on_congestion_uses_throughput() {
+        let mut selector = RenditionSelector::new(renditions(), ViewerLimits::default()).unwrap();
+        let start = Instant::now();
+
+        // 起播阶段不切换
+        assert_eq!(selector.on_feedback(&feedback(0, 1.0), start), None);
+
+        // 1Mbps 只能承载子码流
+        let later = start + SWITCH_MIN_INTERVAL;
+        assert_eq!(selector.on_feedback(&feedback(20, 1.0), later), Some(1));
+        assert_eq!(selector.current().id, 1);
+
+        // 已是最低一路
+        let later = later + SWITCH_MIN_INTERVAL;
+        assert_eq!(selector.on_feedback(&feedback(0, 0.2), later), None);
+    }
+
+    #[test]
+    fn test_downswitch_without_throughput_steps_one_level() {
+        let mut selector = RenditionSelector::new(renditions(), ViewerLimits::default()).unwrap();
+        let start = Instant::now();
+        selector.on_feedback(&feedback(500, 0.0), start);
+
+        assert_eq!(selector.on_feedback(&feedback(0, 0.0), start + SWITCH_MIN_INTERVAL), Some(2));
+    }
+
+    #[test]
+    fn test_upswitch_after_sustained_health() {
+        let limits = ViewerLimits {
+            max_height: Some(720),
+            max_bitrate: None,
+        };
+        let mut selector = RenditionSelector::new(renditions(), limits).unwrap();
+        assert_eq!(selector.current().id, 2);
+
+        let start = Instant::now();
+        selector.on_feedback(&feedback(0, 0.5), start);
+        assert_eq!(selector.on_feedback(&feedback(0, 0.5), start + SWITCH_MIN_INTERVAL), Some(1));
+
+        // 健康但未持续足够时间
+        let mut now = start + SWITCH_MIN_INTERVAL;
+        for _ in 0..10 {
+            now += Duration::from_secs(1);
+            assert_eq!(selector.on_feedback(&feedback(300, 0.6), now), None);
+        }
+
+        // 持续健康后升一档，但不超过观看端限制
+        now += UPSWITCH_HOLD;
+        assert_eq!(selector.on_feedback(&feedback(300, 0.6), now), Some(2));
+        now += SWITCH_MIN_INTERVAL + UPSWITCH_HOLD;
+        selector.on_feedback(&feedback(300, 2.0), now);
+        now += UPSWITCH_HOLD;
+        assert_eq!(selector.on_feedback(&feedback(300, 2.0), now), None);
+    }
+}
@@ -3,7 +3,10 @@
 // 本模块定义了统一的数据源抽象接口，用于支持直通播放和录像回放两种模式。
 // 通过trait抽象，实现了代码复用和一致的流处理逻辑。
 
+use super::handler::BufferConfig;
+use super::telemetry::NetworkFeedback;
 use async_trait::async_trait;
+use common::VideoCodec;
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
 use uuid::Uuid;
@@ -17,6 +20,20 @@ pub enum SegmentFormat {
     FMP4,
     /// 标准MP4格式
     MP4,
+    /// AAC音频（ADTS帧）
+    Aac,
+    /// H.265裸流
+    H265Raw,
+}
+
+impl SegmentFormat {
+    /// 视频编码格式对应的裸流分片格式
+    pub fn raw_video(codec: VideoCodec) -> Self {
+        match codec {
+            VideoCodec::H264 => SegmentFormat::H264Raw,
+            VideoCodec::H265 => SegmentFormat::H265Raw,
+        }
+    }
 }
 
 /// 分片来源类型
@@ -33,14 +50,22 @@ pub enum SegmentSourceType {
 pub struct VideoSegment {
     /// 分片唯一标识符
     pub segment_id: Uuid,
-    /// 相对时间戳（秒）
-    pub timestamp: f64,
-    /// 分片时长（秒）
-    pub duration: f64,
+    /// 显示时间戳（`TIMESCALE` 刻度，相对会话起点）
+    pub pts: i64,
+    /// 解码时间戳（`TIMESCALE` 刻度，含 B 帧时早于 `pts`）
+    pub dts: i64,
+    /// 分片时长（`TIMESCALE` 刻度）
+    pub duration: u32,
     /// 视频数据
     pub data: Vec<u8>,
     /// 是否为关键帧
     pub is_keyframe: bool,
+    /// 精确定位的预解码帧：需要解码但不显示
+    #[serde(default)]
+    pub decode_only: bool,
+    /// 时间线不连续点：此分片之前的时间戳已被重新编排，客户端应重置解码时间线
+    #[serde(default)]
+    pub discontinuity: bool,
     /// 分片格式
     pub format: SegmentFormat,
     /// 分片来源类型
@@ -51,6 +76,29 @@ pub struct VideoSegment {
     /// 转发时间（用于延迟计算）
     #[serde(skip)]
     pub forward_time: Option<SystemTime>,
+    /// 设备发送时间（已换算为平台时钟，仅直通播放）
+    #[serde(skip)]
+    pub device_send_time: Option<SystemTime>,
+}
+
+impl VideoSegment {
+    /// 分片时间戳的时间基（90kHz，与设备分片一致）
+    pub const TIMESCALE: u32 = common::VideoSegment::TIMESCALE;
+
+    /// 秒换算为 `TIMESCALE` 刻度（四舍五入）
+    pub fn ticks(seconds: f64) -> i64 {
+        common::VideoSegment::ticks(seconds)
+    }
+
+    /// 显示时间戳（秒）
+    pub fn pts_secs(&self) -> f64 {
+        self.pts as f64 / Self::TIMESCALE as f64
+    }
+
+    /// 分片时长（秒）
+    pub fn duration_secs(&self) -> f64 {
+        self.duration as f64 / Self::TIMESCALE as f64
+    }
 }
 
 /// 流模式
@@ -198,6 +246,14 @@ pub trait StreamSource: Send + Sync {
     /// 
     /// 直通播放模式不支持此操作，会返回 `StreamError::OperationNotSupported`
     async fn seek(&mut self, position: f64) -> Result<(), StreamError>;
+
+    /// 精确定位到指定时间位置
+    ///
+    /// 从目标之前的关键帧开始发送，目标帧之前的分片标记为 `decode_only`，
+    /// 播放器从目标帧开始显示。默认退化为按关键帧定位的 `seek`。
+    async fn seek_accurate(&mut self, position: f64) -> Result<(), StreamError> {
+        self.seek(position).await
+    }
     
     /// 设置播放速率
     /// 
@@ -222,6 +278,24 @@ pub trait StreamSource: Send + Sync {
     /// 
     /// 返回当前流的详细信息，包括模式、状态、分辨率、帧率等
     fn get_info(&self) -> StreamInfo;
+
+    /// 设置客户端缓冲区配置
+    ///
+    /// 默认忽略，需要按客户端缓冲调整发送速率的数据源可覆盖此方法。
+    fn set_buffer_config(&mut self, _config: &BufferConfig) {}
+
+    /// 应用客户端网络反馈（缓冲时长与实测交付吞吐量）
+    ///
+    /// 默认忽略，直通播放的发送速率由设备决定。
+    fn apply_network_feedback(&mut self, _feedback: &NetworkFeedback) {}
+
+    /// 请求关键帧
+    ///
+    /// 客户端卡顿或大量丢帧后调用。不支持的数据源返回
+    /// `StreamError::OperationNotSupported`。
+    async fn request_keyframe(&mut self) -> Result<(), StreamError> {
+        Err(StreamError::OperationNotSupported)
+    }
 }
 
 #[cfg(test)]
@@ -232,18 +306,23 @@ mod tests {
     fn test_video_segment_creation() {
         let segment = VideoSegment {
             segment_id: Uuid::new_v4(),
-            timestamp: 1.5,
-            duration: 0.033,
+            pts: 135_000,
+            dts: 132_000,
+            duration: 3_000,
             data: vec![0u8; 1024],
             is_keyframe: true,
+            decode_only: false,
+            discontinuity: false,
             format: SegmentFormat::FMP4,
             source_type: SegmentSourceType::Live,
             receive_time: Some(SystemTime::now()),
             forward_time: None,
+            device_send_time: None,
         };
         
-        assert_eq!(segment.timestamp, 1.5);
-        assert_eq!(segment.duration, 0.033);
+        assert_eq!(segment.pts_secs(), 1.5);
+        assert!(segment.dts < segment.pts);
+        assert!((segment.duration_secs() - 1.0 / 30.0).abs() < 1e-9);
         assert_eq!(segment.is_keyframe, true);
         assert_eq!(segment.format, SegmentFormat::FMP4);
         assert!(segment.receive_time.is_some());
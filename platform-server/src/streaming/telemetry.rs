@@ -0,0 +1,370 @@
+// 客户端播放遥测
+//
+// 客户端周期性上报缓冲时长、丢帧数、解码耗时和卡顿事件，本模块据此：
+// - 为帧率控制器提供网络反馈（缓冲时长与实测交付吞吐量）
+// - 决定何时向数据源请求关键帧
+// - 决定何时丢弃非参考帧以减轻客户端解码压力
+
+use super::handler::BufferConfig;
+use super::source::{SegmentFormat, VideoSegment};
+use crate::latency::ClientPlaybackStats;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{debug, info};
+
+/// 两次关键帧请求的最小间隔
+const KEYFRAME_REQUEST_MIN_INTERVAL: Duration = Duration::from_secs(2);
+/// 单个上报周期内丢帧达到该值时请求关键帧
+const KEYFRAME_DROPPED_FRAMES: u64 = 15;
+/// 单个上报周期内丢帧达到该值时开始丢弃非参考帧
+const DROP_ENTER_DROPPED_FRAMES: u64 = 5;
+/// 解码耗时低于帧间隔的该比例时才退出丢帧模式（迟滞）
+const DROP_EXIT_DECODE_RATIO: f64 = 0.8;
+/// 解码耗时的指数平滑系数
+const DECODE_TIME_ALPHA: f64 = 0.3;
+/// 未检测到帧间隔时使用的默认值（30fps）
+const DEFAULT_FRAME_INTERVAL_MS: f64 = 1000.0 / 30.0;
+
+/// 客户端遥测上报
+///
+/// 计数类字段为会话开始以来的累计值，丢失个别上报不影响统计。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientTelemetryReport {
+    /// 已缓冲的待播放时长（毫秒）
+    pub buffered_ms: u64,
+    /// 累计丢帧数
+    #[serde(default)]
+    pub dropped_frames: u64,
+    /// 上报周期内的平均解码耗时（毫秒/帧）
+    #[serde(default)]
+    pub decode_time_ms: Option<f64>,
+    /// 累计卡顿次数
+    #[serde(default)]
+    pub stall_count: u64,
+    /// 累计卡顿时长（毫秒）
+    #[serde(default)]
+    pub stall_duration_ms: u64,
+    /// 当前是否处于卡顿
+    #[serde(default)]
+    pub stalled: bool,
+    /// 累计接收字节数（用于计算交付吞吐量）
+    #[serde(default)]
+    pub received_bytes: Option<u64>,
+}
+
+impl ClientTelemetryReport {
+    /// 检查上报内容
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(decode_time_ms) = self.decode_time_ms {
+            if !decode_time_ms.is_finite() || decode_time_ms < 0.0 {
+                return Err(format!("Invalid decode time: {}", decode_time_ms));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 遥测处理结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelemetryDecision {
+    /// 本次上报是否触发了关键帧请求
+    pub request_keyframe: bool,
+    /// 是否正在丢弃非参考帧
+    pub drop_non_reference: bool,
+}
+
+/// 提供给数据源的网络反馈
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetworkFeedback {
+    /// 客户端缓冲时长（毫秒）
+    pub buffered_ms: u64,
+    /// 实测交付吞吐量（Mbps，未知时为0）
+    pub delivery_mbps: f64,
+}
+
+/// 转发任务消费的反馈状态
+#[derive(Debug, Clone, Default)]
+pub struct SourceFeedback {
+    /// 最近一次网络反馈
+    pub network: Option<NetworkFeedback>,
+    /// 关键帧请求序号（每次请求加1）
+    pub keyframe_requests: u64,
+    /// 是否丢弃非参考帧
+    pub drop_non_reference: bool,
+}
+
+/// 客户端遥测控制器
+///
+/// 每个流会话一个，汇总客户端上报并把决策通过 watch 通道交给转发任务。
+pub struct TelemetryController {
+    buffer_config: BufferConfig,
+    stats: ClientPlaybackStats,
+    last_report: Option<(Instant, ClientTelemetryReport)>,
+    frame_interval_ms: Option<f64>,
+    last_keyframe_request: Option<Instant>,
+    feedback: watch::Sender<SourceFeedback>,
+}
+
+impl TelemetryController {
+    /// 创建控制器，返回控制器和转发任务使用的反馈接收端
+    pub fn new(buffer_config: BufferConfig) -> (Self, watch::Receiver<SourceFeedback>) {
+        let (feedback, receiver) = watch::channel(SourceFeedback::default());
+        let controller = Self {
+            buffer_config,
+            stats: ClientPlaybackStats::default(),
+            last_report: None,
+            frame_interval_ms: None,
+            last_keyframe_request: None,
+            feedback,
+        };
+        (controller, receiver)
+    }
+
+    /// 客户端播放状态统计
+    pub fn stats(&self) -> ClientPlaybackStats {
+        self.stats.clone()
+    }
+
+    /// 记录转发分片的时长，用于估计帧间隔
+    pub fn observe_segment(&mut self, duration_sec: f64) {
+        if duration_sec.is_finite() && duration_sec > 0.0 {
+            self.frame_interval_ms = Some(duration_sec * 1000.0);
+        }
+    }
+
+    /// 处理一次客户端上报
+    pub fn record(&mut self, report: ClientTelemetryReport, now: Instant) -> TelemetryDecision {
+        let frame_interval_ms = self.frame_interval_ms.unwrap_or(DEFAULT_FRAME_INTERVAL_MS);
+
+        // 计算上报周期内的增量（计数回退说明客户端重置，按0处理）
+        let (dropped_delta, new_stall, delivery_mbps) = match &self.last_report {
+            Some((last_at, last)) => {
+                let dropped_delta = report.dropped_frames.saturating_sub(last.dropped_frames);
+                let new_stall =
+                    report.stall_count > last.stall_count || (report.stalled && !last.stalled);
+                let elapsed = now.duration_since(*last_at).as_secs_f64();
+                let delivery_mbps = match (report.received_bytes, last.received_bytes) {
+                    (Some(current), Some(previous)) if current >= previous && elapsed > 0.0 => {
+                        (current - previous) as f64 * 8.0 / elapsed / 1_000_000.0
+                    }
+                    _ => 0.0,
+                };
+                (dropped_delta, new_stall, delivery_mbps)
+            }
+            None => (0, report.stalled || report.stall_count > 0, 0.0),
+        };
+
+        if let Some(decode_time_ms) = report.decode_time_ms {
+            self.stats.decode_time_ms = if self.stats.reports == 0 {
+                decode_time_ms
+            } else {
+                self.stats.decode_time_ms
+                    + DECODE_TIME_ALPHA * (decode_time_ms - self.stats.decode_time_ms)
+            };
+        }
+        self.stats.reports += 1;
+        self.stats.buffered_ms = report.buffered_ms;
+        self.stats.dropped_frames = report.dropped_frames;
+        self.stats.stall_count = report.stall_count;
+        self.stats.stall_duration_ms = report.stall_duration_ms;
+        self.stats.stalled = report.stalled;
+        if delivery_mbps > 0.0 {
+            self.stats.delivery_mbps = delivery_mbps;
+        }
+
+        // 丢弃非参考帧：客户端解码跟不上或持续丢帧时进入，恢复后退出
+        let was_dropping = self.feedback.borrow().drop_non_reference;
+        let decode_time_ms = self.stats.decode_time_ms;
+        let drop_non_reference = if was_dropping {
+            !(dropped_delta == 0
+                && decode_time_ms <= frame_interval_ms * DROP_EXIT_DECODE_RATIO
+                && report.buffered_ms >= self.buffer_config.min_buffer_ms as u64)
+        } else {
+            decode_time_ms > frame_interval_ms || dropped_delta >= DROP_ENTER_DROPPED_FRAMES
+        };
+
+        // 关键帧请求：卡顿或大量丢帧后解码器可能丢失参考帧
+        let wants_keyframe = new_stall || dropped_delta >= KEYFRAME_DROPPED_FRAMES;
+        let request_keyframe = wants_keyframe
+            && self
+                .last_keyframe_request
+                .is_none_or(|last| now.duration_since(last) >= KEYFRAME_REQUEST_MIN_INTERVAL);
+        if request_keyframe {
+            self.last_keyframe_request = Some(now);
+        }
+
+        if drop_non_reference != was_dropping {
+            info!(
+                "Client telemetry: {} non-reference frame dropping (decode {:.1}ms, frame interval {:.1}ms, dropped +{})",
+                if drop_non_reference { "start" } else { "stop" },
+                decode_time_ms,
+                frame_interval_ms,
+                dropped_delta
+            );
+        }
+        debug!(
+            "Client telemetry: buffered={}ms, dropped +{}, stalls={}, delivery={:.2}Mbps, keyframe={}",
+            report.buffered_ms, dropped_delta, report.stall_count, delivery_mbps, request_keyframe
+        );
+
+        let network = NetworkFeedback {
+            buffered_ms: report.buffered_ms,
+            delivery_mbps,
+        };
+        self.feedback.send_modify(|feedback| {
+            feedback.network = Some(network);
+            feedback.drop_non_reference = drop_non_reference;
+            if request_keyframe {
+                feedback.keyframe_requests += 1;
+            }
+        });
+        self.last_report = Some((now, report));
+
+        TelemetryDecision {
+            request_keyframe,
+            drop_non_reference,
+        }
+    }
+}
+
+/// 分片是否只包含非参考帧（可以安全丢弃）
+///
+/// 仅识别 H.264 裸流：所有 VCL NAL 单元的 nal_ref_idc 都为0时返回 true。
+/// 其他格式无法判断，一律不丢弃。
+pub fn is_non_reference_segment(segment: &VideoSegment) -> bool {
+    if segment.is_keyframe || segment.format != SegmentFormat::H264Raw {
+        return false;
+    }
+
+    let data = &segment.data;
+    let mut found_vcl = false;
+    let mut i = 0;
+    while i + 3 < data.len() {
+        // 查找起始码 00 00 01（00 00 00 01 的后三字节同样匹配）
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            let header = data[i + 3];
+            let nal_type = header & 0x1F;
+            let nal_ref_idc = (header >> 5) & 0x03;
+            if (1..=5).contains(&nal_type) {
+                if nal_ref_idc != 0 {
+                    return false;
+                }
+                found_vcl = true;
+            }
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+    found_vcl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::source::SegmentSourceType;
+    use uuid::Uuid;
+
+    fn report(buffered_ms: u64) -> ClientTelemetryReport {
+        ClientTelemetryReport {
+            buffered_ms,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_keyframe_request_on_stall_is_rate_limited() {
+        let (mut controller, mut receiver) = TelemetryController::new(BufferConfig::default());
+        let start = Instant::now();
+
+        assert!(!controller.record(report(500), start).request_keyframe);
+
+        let mut stalled = report(0);
+        stalled.stalled = true;
+        stalled.stall_count = 1;
+        assert!(controller.record(stalled.clone(), start + Duration::from_millis(500)).request_keyframe);
+        assert_eq!(receiver.borrow_and_update().keyframe_requests, 1);
+
+        // 2秒内的第二次卡顿不重复请求
+        stalled.stall_count = 2;
+        assert!(!controller.record(stalled.clone(), start + Duration::from_millis(1000)).request_keyframe);
+        stalled.stall_count = 3;
+        assert!(controller.record(stalled, start + Duration::from_secs(3)).request_keyframe);
+        assert_eq!(receiver.borrow().keyframe_requests, 2);
+
+        let stats = controller.stats();
+        assert_eq!(stats.reports, 4);
+        assert_eq!(stats.stall_count, 3);
+        assert!(stats.stalled);
+    }
+
+    #[test]
+    fn test_drop_non_reference_hysteresis() {
+        let (mut controller, receiver) = TelemetryController::new(BufferConfig::default());
+        controller.observe_segment(1.0 / 30.0);
+        let start = Instant::now();
+
+        let mut slow = report(300);
+        slow.decode_time_ms = Some(50.0);
+        assert!(controller.record(slow, start).drop_non_reference);
+        assert!(receiver.borrow().drop_non_reference);
+
+        // 解码耗时回落但仍接近帧间隔时保持丢帧
+        let mut recovering = report(300);
+        recovering.decode_time_ms = Some(30.0);
+        assert!(controller.record(recovering.clone(), start + Duration::from_secs(1)).drop_non_reference);
+
+        for i in 2..10 {
+            recovering.decode_time_ms = Some(10.0);
+            controller.record(recovering.clone(), start + Duration::from_secs(i));
+        }
+        assert!(!receiver.borrow().drop_non_reference);
+    }
+
+    #[test]
+    fn test_delivery_throughput_feedback() {
+        let (mut controller, receiver) = TelemetryController::new(BufferConfig::default());
+        let start = Instant::now();
+
+        let mut first = report(400);
+        first.received_bytes = Some(0);
+        controller.record(first, start);
+
+        let mut second = report(450);
+        second.received_bytes = Some(1_000_000);
+        controller.record(second, start + Duration::from_secs(2));
+
+        let network = receiver.borrow().network.unwrap();
+        assert_eq!(network.buffered_ms, 450);
+        assert!((network.delivery_mbps - 4.0).abs() < 1e-9);
+        assert!((controller.stats().delivery_mbps - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_is_non_reference_segment() {
+        let segment = |data: Vec<u8>, is_keyframe| VideoSegment {
+            segment_id: Uuid::new_v4(),
+            pts: 0,
+            dts: 0,
+            duration: 3_000,
+            data,
+            is_keyframe,
+            decode_only: false,
+            discontinuity: false,
+            format: SegmentFormat::H264Raw,
+            source_type: SegmentSourceType::Live,
+            receive_time: None,
+            forward_time: None,
+            device_send_time: None,
+        };
+
+        // nal_ref_idc=0 的非IDR片
+        assert!(is_non_reference_segment(&segment(vec![0, 0, 0, 1, 0x01, 0xAA], false)));
+        // nal_ref_idc=2 的非IDR片
+        assert!(!is_non_reference_segment(&segment(vec![0, 0, 0, 1, 0x41, 0xAA], false)));
+        // 关键帧与无VCL数据不丢弃
+        assert!(!is_non_reference_segment(&segment(vec![0, 0, 1, 0x01, 0xAA], true)));
+        assert!(!is_non_reference_segment(&segment(vec![0, 0, 1, 0x06, 0xAA], false)));
+    }
+}
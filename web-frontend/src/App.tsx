@@ -45,6 +45,8 @@ function App() {
             client_id: 'web_client_' + Date.now(),
             low_latency_mode: true,
             target_latency_ms: 100,
+            // 画面高度不超过屏幕短边，移动端由平台自动选择子码流
+            max_height: Math.min(window.screen.width, window.screen.height),
           },
         }),
       })
@@ -224,7 +224,8 @@ function H264Player({ sessionId }: H264PlayerProps) {
           const firstBytes = Array.from(h264Data.slice(0, 8)).map(b => b.toString(16).padStart(2, '0')).join(' ')
           console.log(`Received H.264 segment #${count}:`, {
             id: segment.segment_id,
-            timestamp: segment.timestamp,
+            pts: segment.pts,
+            timescale: segment.timescale,
             size: segment.data_length,
             isKeyframe: segment.flags & 0x01,
             firstBytes,
@@ -233,11 +234,12 @@ function H264Player({ sessionId }: H264PlayerProps) {
         }
         
         setSegmentCount(count)
-        setStatus(`接收并转换中... ${count} 个分片 (${segment.timestamp.toFixed(2)}s)`)
+        const timestamp = segment.pts / segment.timescale
+        setStatus(`接收并转换中... ${count} 个分片 (${timestamp.toFixed(2)}s)`)
         
         h264Segments.push({
           data: h264Data,
-          timestamp: segment.timestamp,
+          timestamp,
           isKeyframe: (segment.flags & 0x01) !== 0
         })
 
@@ -15,18 +15,34 @@ interface LatencyStatistics {
   p99_latency_ms: number;
   throughput_mbps: number;
   packet_loss_rate: number;
+  acked_segments: number;
+  end_to_end_avg_ms: number;
+  end_to_end_p95_ms: number;
+  distribution_avg_ms: number;
+  distribution_p95_ms: number;
 }
 
-interface LatencyAlert {
-  type: 'LatencyAlert';
-  session_id: string;
-  alert: {
-    TransmissionLatency?: { segment_id: string; latency_ms: number; threshold_ms: number };
-    ProcessingLatency?: { segment_id: string; latency_ms: number; threshold_ms: number };
-    DistributionLatency?: { segment_id: string; latency_ms: number; threshold_ms: number };
-    EndToEndLatency?: { segment_id: string; latency_ms: number; threshold_ms: number };
+type AlertStatus = 'pending' | 'firing' | 'resolved';
+
+interface AlertStateChanged {
+  type: 'AlertStateChanged';
+  session_id: string | null;
+  event: {
+    status: AlertStatus;
+    previous?: AlertStatus;
+    timestamp: number;
+    id: string;
+    rule_id: string;
+    rule_name: string;
+    severity: 'info' | 'warning' | 'critical';
+    subject: string;
+    metric: string;
+    value: number;
+    threshold: number;
+    started_at: number;
+    acknowledged: boolean;
+    silenced: boolean;
   };
-  timestamp: number;
 }
 
 interface StatisticsUpdate {
@@ -36,7 +52,7 @@ interface StatisticsUpdate {
   timestamp: number;
 }
 
-type AlertMessage = LatencyAlert | StatisticsUpdate;
+type AlertMessage = AlertStateChanged | StatisticsUpdate;
 
 interface LatencyMonitorProps {
   sessionId?: string;
@@ -108,27 +124,14 @@ export const LatencyMonitor: React.FC<LatencyMonitorProps> = ({
 
         if (message.type === 'StatisticsUpdate') {
           setStatistics(message.statistics);
-        } else if (message.type === 'LatencyAlert') {
-          const alert = message.alert;
-          let alertText = '';
-
-          if ('TransmissionLatency' in alert) {
-            const a = alert.TransmissionLatency!;
-            alertText = `传输延迟告警: ${a.latency_ms}ms (阈值: ${a.threshold_ms}ms)`;
-          } else if ('ProcessingLatency' in alert) {
-            const a = alert.ProcessingLatency!;
-            alertText = `处理延迟告警: ${a.latency_ms}ms (阈值: ${a.threshold_ms}ms)`;
-          } else if ('DistributionLatency' in alert) {
-            const a = alert.DistributionLatency!;
-            alertText = `分发延迟告警: ${a.latency_ms}ms (阈值: ${a.threshold_ms}ms)`;
-          } else if ('EndToEndLatency' in alert) {
-            const a = alert.EndToEndLatency!;
-            alertText = `端到端延迟告警: ${a.latency_ms}ms (阈值: ${a.threshold_ms}ms)`;
-          }
-
-          if (alertText) {
-            addAlert(alertText);
-          }
+        } else if (message.type === 'AlertStateChanged') {
+          const a = message.event;
+          const statusText: Record<AlertStatus, string> = {
+            pending: '待触发',
+            firing: '告警',
+            resolved: '已恢复',
+          };
+          addAlert(`[${statusText[a.status]}] ${a.rule_name}: ${a.value} (阈值: ${a.threshold})`);
         }
       } catch (err) {
         console.error('Failed to parse alert message:', err);
@@ -231,6 +234,26 @@ export const LatencyMonitor: React.FC<LatencyMonitorProps> = ({
                   {(statistics.total_bytes / 1024 / 1024).toFixed(2)} MB
                 </span>
               </div>
+              {statistics.acked_segments > 0 && (
+                <>
+                  <div className="detail-item">
+                    <span className="detail-label">端到端延迟(实测):</span>
+                    <span className="detail-value">
+                      {formatLatency(statistics.end_to_end_avg_ms)} / P95 {formatLatency(statistics.end_to_end_p95_ms)}
+                    </span>
+                  </div>
+                  <div className="detail-item">
+                    <span className="detail-label">分发延迟(实测):</span>
+                    <span className="detail-value">
+                      {formatLatency(statistics.distribution_avg_ms)} / P95 {formatLatency(statistics.distribution_p95_ms)}
+                    </span>
+                  </div>
+                  <div className="detail-item">
+                    <span className="detail-label">已确认分片:</span>
+                    <span className="detail-value">{statistics.acked_segments.toLocaleString()}</span>
+                  </div>
+                </>
+              )}
             </div>
           </div>
 
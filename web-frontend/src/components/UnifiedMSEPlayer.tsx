@@ -239,13 +239,25 @@ function UnifiedMSEPlayer({
       
       console.log('[UnifiedMSEPlayer] Received segment:', {
         segment_id: segment.segment_id,
-        timestamp: segment.timestamp,
+        pts: segment.pts,
+        dts: segment.dts,
+        timescale: segment.timescale,
         duration: segment.duration,
         is_keyframe: segment.is_keyframe,
         format: segment.format,
         data_length: segment.data?.length || 0,
       })
       
+      // 音频分片（ADTS）不能追加到视频 SourceBuffer
+      if (segment.format === 'Aac') {
+        return
+      }
+
+      // 服务端已把时间戳重新编排为单调递增，这里只记录不连续点
+      if (segment.discontinuity) {
+        console.warn('[UnifiedMSEPlayer] Timestamp discontinuity at segment:', segment.segment_id)
+      }
+
       // 解码 base64 数据
       if (!segment.data) {
         console.warn('[UnifiedMSEPlayer] Segment has no data, skipping')
@@ -285,7 +297,7 @@ function UnifiedMSEPlayer({
       // 尝试追加分片到 SourceBuffer
       processSegmentQueue()
       
-      setStatus(`播放中 (${segment.timestamp.toFixed(2)}s)`)
+      setStatus(`播放中 (${(segment.pts / segment.timescale).toFixed(2)}s)`)
       
     } catch (error) {
       console.error('[UnifiedMSEPlayer] Error processing segment:', error)
@@ -762,8 +774,8 @@ function UnifiedMSEPlayer({
     // 通知服务器调整速率
     await sendControlCommand('set_rate', { rate })
     
-    // 更新本地播放速率
-    video.playbackRate = rate
+    // 更新本地播放速率（快进超过4x和倒放由服务器只发关键帧并重写时间戳，本地按1x渲染）
+    video.playbackRate = rate < 0 || rate > 4 ? 1.0 : rate
     setPlaybackRate(rate)
   }
 
@@ -939,6 +951,12 @@ function UnifiedMSEPlayer({
               <option value="1.5">1.5x</option>
               <option value="2.0">2.0x</option>
               <option value="4.0">4.0x</option>
+              <option value="8.0">8x ⏩</option>
+              <option value="16.0">16x ⏩</option>
+              <option value="32.0">32x ⏩</option>
+              <option value="-1.0">-1x ⏪</option>
+              <option value="-8.0">-8x ⏪</option>
+              <option value="-32.0">-32x ⏪</option>
             </select>
           </div>
         )}
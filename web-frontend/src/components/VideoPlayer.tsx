@@ -115,16 +115,16 @@ function VideoPlayer({ sessionId, fileId, isLiveMode = false }: VideoPlayerProps
       try {
         const segment = JSON.parse(event.data)
         segmentCount++
-        lastTimestamp = segment.timestamp
+        lastTimestamp = segment.pts / segment.timescale
         
         console.log('Received segment:', {
           id: segment.segment_id,
-          timestamp: segment.timestamp,
+          timestamp: lastTimestamp,
           size: segment.data_length,
           isKeyframe: segment.flags & 0x01
         })
         
-        setStatus(`✅ 数据传输成功！已接收 ${segmentCount} 个分片 (${segment.timestamp.toFixed(2)}s)`)
+        setStatus(`✅ 数据传输成功！已接收 ${segmentCount} 个分片 (${lastTimestamp.toFixed(2)}s)`)
         
         // TODO: 实现 MSE 播放
         // 需要将 H.264 裸流转换为 fMP4 格式
@@ -1,5 +1,18 @@
 import React, { useEffect, useRef, useState } from 'react'
 import LatencyMonitor from './LatencyMonitor'
+import { PlaybackAckReporter } from '../utils/playbackAckReporter'
+import { AacAudioPlayer } from '../utils/aacAudioPlayer'
+
+// 等待渲染确认的分片上限（超出时丢弃最早的记录）
+const MAX_TRACKED_SEGMENTS = 300
+
+type VideoCodec = 'h264' | 'h265'
+
+// 服务端未给出编码字符串时的兜底配置
+const DEFAULT_CODEC_STRINGS: Record<VideoCodec, string> = {
+  h264: 'avc1.42E01E', // H.264 Baseline Profile Level 3.0
+  h265: 'hvc1.1.6.L93.B0', // H.265 Main Profile Level 3.1
+}
 
 interface WebCodecsPlayerProps {
   sessionId: string
@@ -7,8 +20,8 @@ interface WebCodecsPlayerProps {
 }
 
 /**
- * 使用 WebCodecs API 的 H.264 播放器
- * 支持浏览器原生 H.264 解码，低延迟高性能
+ * 使用 WebCodecs API 的 H.264/H.265 播放器
+ * 支持浏览器原生硬件解码，低延迟高性能
  * 
  * 播放模式说明：
  * - fast: 快速模式，解码后立即渲染，最低延迟（<100ms）
@@ -23,6 +36,7 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
   const targetFpsRef = useRef<number>(30) // 使用 ref 存储目标帧率，避免重新渲染
   const [droppedFrames, setDroppedFrames] = useState<number>(0)
   const [averageDelay, setAverageDelay] = useState<number>(0)
+  const [rendition, setRendition] = useState<string | null>(null) // 当前码流（多码流设备）
 
   const decoderRef = useRef<VideoDecoder | null>(null)
   const eventSourceRef = useRef<EventSource | null>(null)
@@ -33,6 +47,11 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
   const pendingChunksRef = useRef<{ data: Uint8Array, timestamp: number }[]>([])
   const pendingFramesRef = useRef<VideoFrame[]>([]) // 用于 normal 模式的帧队列
   const renderTimerRef = useRef<number | null>(null) // 用于调度渲染
+  const ackReporterRef = useRef<PlaybackAckReporter | null>(null) // 播放确认上报
+  const segmentIdsRef = useRef<Map<number, string>>(new Map()) // 帧时间戳 → 分片ID
+  const renditionIdRef = useRef<number | null>(null) // 当前码流ID
+  const codecRef = useRef<VideoCodec>('h264') // 当前视频编码格式
+  const audioPlayerRef = useRef<AacAudioPlayer | null>(null) // 音频播放（设备带麦克风时）
   
   // 播放时钟基准（类似抖音的实现）
   const playbackStartTimeRef = useRef<number>(0) // 播放开始的系统时间（毫秒）
@@ -94,6 +113,12 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
       decoderRef.current = decoder
       console.log('✅ VideoDecoder created (waiting for SPS/PPS to configure)')
 
+      ackReporterRef.current = new PlaybackAckReporter(sessionId)
+
+      if ('AudioDecoder' in window) {
+        audioPlayerRef.current = new AacAudioPlayer(audioClock)
+      }
+
       // 开始接收 SSE 数据
       startSSEStream()
       
@@ -116,6 +141,7 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
       }
 
       ctx.drawImage(frame, 0, 0)
+      acknowledgeFrame(frame.timestamp)
 
       // 更新 FPS
       frameCountRef.current++
@@ -132,6 +158,28 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
     }
   }
 
+  /**
+   * 记录分片对应的解码时间戳，渲染时据此上报播放确认
+   */
+  const trackSegment = (timestamp: number, segmentId: string) => {
+    const segmentIds = segmentIdsRef.current
+    segmentIds.set(timestamp, segmentId)
+    if (segmentIds.size > MAX_TRACKED_SEGMENTS) {
+      const oldest = segmentIds.keys().next().value
+      if (oldest !== undefined) segmentIds.delete(oldest)
+    }
+  }
+
+  /**
+   * 帧已渲染，上报对应分片的播放确认
+   */
+  const acknowledgeFrame = (timestamp: number) => {
+    const segmentId = segmentIdsRef.current.get(timestamp)
+    if (segmentId === undefined) return
+    segmentIdsRef.current.delete(timestamp)
+    ackReporterRef.current?.ack(segmentId)
+  }
+
   /**
    * 调度下一帧渲染（用于 normal 模式）
    * 
@@ -252,6 +300,14 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
     }, waitTime)
   }
 
+  /**
+   * 音频播放时钟：normal 模式下按视频的播放时钟换算，保证音画同步
+   */
+  const audioClock = (timestampUs: number): number | null => {
+    if (playbackMode !== 'normal' || playbackStartTimeRef.current === 0) return null
+    return playbackStartTimeRef.current + (timestampUs / 1000 - playbackStartTimestampRef.current)
+  }
+
   const startSSEStream = () => {
     setStatus('连接到服务器...')
     
@@ -262,12 +318,39 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
     
     let count = 0
     let hasReceivedSPS = false
+    let codecString = ''
 
     eventSource.onopen = () => {
       console.log('SSE connection opened')
       setStatus('已连接，接收视频数据...')
     }
 
+    // 码流切换：平台在新码流的关键帧之前推送，分辨率变化后按新的 SPS 重新配置解码器
+    eventSource.addEventListener('rendition', (event) => {
+      const info = JSON.parse((event as MessageEvent).data)
+      console.log(`🔀 Rendition #${info.id} ${info.name}: ${info.width}x${info.height} @ ${info.bitrate} bps`)
+      setRendition(`${info.name} ${info.width}x${info.height}`)
+      if (renditionIdRef.current !== null && renditionIdRef.current !== info.id) {
+        hasReceivedSPS = false
+        isConfiguredRef.current = false
+        decoderRef.current?.reset()
+      }
+      renditionIdRef.current = info.id
+    })
+
+    // 音频分片（ADTS），与视频共用时间轴
+    eventSource.addEventListener('audio', (event) => {
+      const audioPlayer = audioPlayerRef.current
+      if (!audioPlayer) return
+      try {
+        const segment = JSON.parse((event as MessageEvent).data)
+        const aacData = Uint8Array.from(atob(segment.data), c => c.charCodeAt(0))
+        audioPlayer.push(aacData, Math.round(segment.pts * 1000000 / segment.timescale))
+      } catch (err) {
+        console.error('Error processing audio segment:', err)
+      }
+    })
+
     eventSource.onmessage = (event) => {
       try {
         const segment = JSON.parse(event.data)
@@ -276,28 +359,43 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
         // 将 base64 数据转换为 Uint8Array
         const h264Data = Uint8Array.from(atob(segment.data), c => c.charCodeAt(0))
         
-        // 🔧 使用服务端发送的真实时间戳（秒转微秒）
-        const realTimestamp = segment.timestamp * 1000000 // 秒转微秒
+        // 🔧 使用服务端发送的显示时间戳（时间基刻度转微秒）
+        const realTimestamp = Math.round(segment.pts * 1000000 / segment.timescale)
+        if (segment.segment_id) {
+          trackSegment(realTimestamp, segment.segment_id)
+        }
         
         // 调试：打印前几个分片的信息
         if (count <= 5) {
           const firstBytes = Array.from(h264Data.slice(0, 16)).map(b => b.toString(16).padStart(2, '0')).join(' ')
           console.log(`📦 Segment #${count}:`)
           console.log(`   - Size: ${h264Data.length} bytes`)
-          console.log(`   - Timestamp (from server): ${segment.timestamp.toFixed(3)}s`)
+          console.log(`   - PTS/DTS (from server): ${segment.pts}/${segment.dts} @ ${segment.timescale}Hz`)
           console.log(`   - Timestamp (converted): ${realTimestamp}μs = ${(realTimestamp / 1000).toFixed(1)}ms`)
           console.log(`   - First 16 bytes: ${firstBytes}`)
         }
         
-        // 检查是否包含SPS (NAL type 7)
-        const hasSPS = checkForSPS(h264Data)
+        const codec: VideoCodec = segment.codec === 'h265' ? 'h265' : 'h264'
+        codecRef.current = codec
+
+        // 编码字符串变化（H.264/H.265 切换或 profile 变化）时重新配置
+        if (segment.codec_string && codecString && segment.codec_string !== codecString) {
+          console.log(`🔁 Codec changed: ${codecString} -> ${segment.codec_string}`)
+          hasReceivedSPS = false
+          isConfiguredRef.current = false
+          decoderRef.current?.reset()
+        }
+
+        // 检查是否包含SPS (H.264 NAL type 7 / H.265 NAL type 33)
+        const hasSPS = checkForSPS(h264Data, codec)
         if (hasSPS && !hasReceivedSPS) {
           hasReceivedSPS = true
-          console.log('✅ Received SPS/PPS! Configuring decoder (Annex B mode)...')
+          codecString = segment.codec_string || DEFAULT_CODEC_STRINGS[codec]
+          console.log(`✅ Received parameter sets! Configuring decoder ${codecString} (Annex B mode)...`)
           
           // 简单配置解码器，不使用 description
-          // 让解码器从数据流中读取 SPS/PPS
-          configureDecoderSimple()
+          // 让解码器从数据流中读取参数集
+          configureDecoderSimple(codecString)
         }
         
         // 如果解码器还没配置好，缓存数据
@@ -325,29 +423,33 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
     }
   }
 
-  const checkForSPS = (data: Uint8Array): boolean => {
+  // 遍历起始码后的 NAL 类型（H.264 取低5位，H.265 取 (b>>1)&0x3F）
+  const hasNalType = (data: Uint8Array, codec: VideoCodec, matches: (type: number) => boolean): boolean => {
     for (let i = 0; i < data.length - 4; i++) {
-      // 查找起始码 + SPS (NAL type 7)
-      if ((data[i] === 0x00 && data[i+1] === 0x00 && data[i+2] === 0x00 && data[i+3] === 0x01 && (data[i+4] & 0x1F) === 7) ||
-          (data[i] === 0x00 && data[i+1] === 0x00 && data[i+2] === 0x01 && (data[i+3] & 0x1F) === 7)) {
-        return true
+      if (data[i] !== 0x00 || data[i+1] !== 0x00) continue
+      let header = -1
+      if (data[i+2] === 0x01) {
+        header = data[i+3]
+      } else if (data[i+2] === 0x00 && data[i+3] === 0x01) {
+        header = data[i+4]
       }
-    }
-    return false
-  }
-
-  const checkForKeyFrame = (data: Uint8Array): boolean => {
-    for (let i = 0; i < data.length - 4; i++) {
-      // 查找起始码 + IDR (NAL type 5)
-      if ((data[i] === 0x00 && data[i+1] === 0x00 && data[i+2] === 0x00 && data[i+3] === 0x01 && (data[i+4] & 0x1F) === 5) ||
-          (data[i] === 0x00 && data[i+1] === 0x00 && data[i+2] === 0x01 && (data[i+3] & 0x1F) === 5)) {
+      if (header < 0) continue
+      const type = codec === 'h265' ? (header >> 1) & 0x3F : header & 0x1F
+      if (matches(type)) {
         return true
       }
     }
     return false
   }
 
-  const configureDecoderSimple = () => {
+  const checkForSPS = (data: Uint8Array, codec: VideoCodec): boolean =>
+    hasNalType(data, codec, type => codec === 'h265' ? type === 33 : type === 7)
+
+  // H.264 IDR (5)；H.265 IRAP (16-23)
+  const checkForKeyFrame = (data: Uint8Array, codec: VideoCodec): boolean =>
+    hasNalType(data, codec, type => codec === 'h265' ? type >= 16 && type <= 23 : type === 5)
+
+  const configureDecoderSimple = (codecString: string) => {
     const decoder = decoderRef.current
     if (!decoder) return
     
@@ -355,12 +457,12 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
       // 简单配置：不使用 description
       // WebCodecs 会从第一个 key chunk 中读取 SPS/PPS
       decoder.configure({
-        codec: 'avc1.42E01E', // H.264 Baseline Profile Level 3.0
+        codec: codecString,
         optimizeForLatency: true
       })
       
       isConfiguredRef.current = true
-      console.log('✅ VideoDecoder configured (Annex B mode, in-band SPS/PPS)')
+      console.log(`✅ VideoDecoder configured: ${codecString} (Annex B mode, in-band parameter sets)`)
       
       // 处理缓存的数据（第一个包含 SPS/PPS/IDR）
       if (pendingChunksRef.current.length > 0) {
@@ -384,8 +486,9 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
     }
 
     try {
-      // 检查是否包含关键帧（IDR 或 SPS/PPS）
-      const isKeyFrame = checkForKeyFrame(data) || checkForSPS(data)
+      // 检查是否包含关键帧（IDR/IRAP 或参数集）
+      const codec = codecRef.current
+      const isKeyFrame = checkForKeyFrame(data, codec) || checkForSPS(data, codec)
       
       // 创建 EncodedVideoChunk
       const chunk = new EncodedVideoChunk({
@@ -432,6 +535,15 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
     
     isConfiguredRef.current = false
     pendingChunksRef.current = []
+
+    audioPlayerRef.current?.dispose()
+    audioPlayerRef.current = null
+
+    // 上报剩余的播放确认
+    ackReporterRef.current?.dispose()
+    ackReporterRef.current = null
+    segmentIdsRef.current.clear()
+    renditionIdRef.current = null
   }
 
   return (
@@ -478,6 +590,12 @@ function WebCodecsPlayer({ sessionId, playbackMode = 'normal' }: WebCodecsPlayer
           <span className="label">会话 ID:</span>
           <span className="value">{sessionId.substring(0, 8)}...</span>
         </div>
+        {rendition && (
+          <div className="info-row">
+            <span className="label">码流:</span>
+            <span className="value">{rendition}</span>
+          </div>
+        )}
         <div className="info-row">
           <span className="label">接收分片:</span>
           <span className="value">{segmentCount}</span>
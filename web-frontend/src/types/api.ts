@@ -40,6 +40,7 @@ export interface RecordingInfo {
 export interface StartPlaybackRequest {
   client_id: string
   start_position?: number
+  playback_rate?: number
 }
 
 export interface StartPlaybackResponse {
@@ -0,0 +1,120 @@
+/**
+ * AacAudioPlayer - AAC 音频播放器
+ *
+ * 用 WebCodecs AudioDecoder 解码平台推送的 ADTS 音频分片，排入 AudioContext 播放。
+ * 提供媒体时钟时按视频的播放时钟安排每帧的播放时间，保证音画同步；
+ * 时钟尚未建立（或 fast 模式）时按到达顺序连续播放。
+ */
+
+const SAMPLE_RATES = [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350]
+const SAMPLES_PER_FRAME = 1024
+
+/** 媒体时间戳（微秒）→ 应播放的系统时间（performance.now() 毫秒），返回 null 表示没有时钟 */
+export type MediaClock = (timestampUs: number) => number | null
+
+export class AacAudioPlayer {
+  private context: AudioContext
+  private decoder: AudioDecoder
+  private clock: MediaClock
+  private configuredKey: string | null = null
+  /** 连续播放时下一帧的开始时间（AudioContext 时间，秒） */
+  private nextStartTime = 0
+
+  constructor(clock: MediaClock) {
+    this.clock = clock
+    this.context = new AudioContext()
+    this.decoder = new AudioDecoder({
+      output: (data: AudioData) => this.schedule(data),
+      error: (err: Error) => console.error('Audio decoder error:', err),
+    })
+  }
+
+  /**
+   * 解码一个音频分片（一个或多个 ADTS 帧），timestampUs 为首帧时间戳
+   */
+  push(data: Uint8Array, timestampUs: number): void {
+    // 浏览器自动播放策略可能让 AudioContext 处于挂起状态
+    if (this.context.state === 'suspended') {
+      this.context.resume().catch(() => {})
+    }
+
+    let offset = 0
+    let frameTimestamp = timestampUs
+    while (offset + 7 <= data.length) {
+      if (data[offset] !== 0xff || (data[offset + 1] & 0xf6) !== 0xf0) break
+
+      const objectType = (data[offset + 2] >> 6) + 1
+      const sampleRate = SAMPLE_RATES[(data[offset + 2] >> 2) & 0x0f]
+      const channels = ((data[offset + 2] & 0x01) << 2) | (data[offset + 3] >> 6)
+      const frameLength = ((data[offset + 3] & 0x03) << 11) | (data[offset + 4] << 3) | (data[offset + 5] >> 5)
+      if (!sampleRate || frameLength < 7 || offset + frameLength > data.length) break
+
+      this.configure(objectType, sampleRate, channels)
+      // 未提供 description 时解码器按 ADTS 格式解析
+      this.decoder.decode(new EncodedAudioChunk({
+        type: 'key',
+        timestamp: frameTimestamp,
+        data: data.subarray(offset, offset + frameLength),
+      }))
+
+      offset += frameLength
+      frameTimestamp += SAMPLES_PER_FRAME * 1_000_000 / sampleRate
+    }
+  }
+
+  private configure(objectType: number, sampleRate: number, channels: number): void {
+    const codec = `mp4a.40.${objectType}`
+    const key = `${codec}/${sampleRate}/${channels}`
+    if (this.configuredKey === key) return
+
+    console.log(`🔊 Audio: ${codec} ${sampleRate} Hz, ${channels} channels`)
+    this.decoder.configure({ codec, sampleRate, numberOfChannels: channels })
+    this.configuredKey = key
+  }
+
+  private schedule(audioData: AudioData): void {
+    try {
+      const now = this.context.currentTime
+      const playAt = this.clock(audioData.timestamp)
+      let startTime: number
+      if (playAt === null) {
+        startTime = Math.max(now, this.nextStartTime)
+      } else {
+        startTime = now + (playAt - performance.now()) / 1000
+        // 画面已经播过这一帧，丢弃迟到的音频
+        if (startTime < now) return
+      }
+
+      const buffer = this.context.createBuffer(
+        audioData.numberOfChannels,
+        audioData.numberOfFrames,
+        audioData.sampleRate,
+      )
+      for (let channel = 0; channel < audioData.numberOfChannels; channel++) {
+        const plane = new Float32Array(audioData.numberOfFrames)
+        audioData.copyTo(plane, { planeIndex: channel, format: 'f32-planar' })
+        buffer.copyToChannel(plane, channel)
+      }
+
+      const source = this.context.createBufferSource()
+      source.buffer = buffer
+      source.connect(this.context.destination)
+      source.start(startTime)
+      this.nextStartTime = startTime + buffer.duration
+    } catch (err) {
+      console.error('Failed to schedule audio:', err)
+    } finally {
+      audioData.close()
+    }
+  }
+
+  /**
+   * 停止播放并释放解码器
+   */
+  dispose(): void {
+    if (this.decoder.state !== 'closed') {
+      this.decoder.close()
+    }
+    this.context.close().catch(() => {})
+  }
+}
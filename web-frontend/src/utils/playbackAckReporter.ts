@@ -0,0 +1,69 @@
+/**
+ * PlaybackAckReporter - 播放确认上报器
+ *
+ * 记录已渲染分片的 ID 与渲染时间，批量 POST 到
+ * /api/v1/stream/{sessionId}/acks，供平台测量分发延迟和端到端延迟。
+ * 每个批次附带发送时间，平台只使用本地时钟的相对差值，不要求时钟同步。
+ */
+
+interface PlaybackAck {
+  segment_id: string
+  rendered_at_ms: number
+}
+
+export class PlaybackAckReporter {
+  private sessionId: string
+  private flushIntervalMs: number
+  private maxBatchSize: number
+  private pending: PlaybackAck[] = []
+  private flushTimer: number | null = null
+
+  constructor(sessionId: string, flushIntervalMs: number = 1000, maxBatchSize: number = 50) {
+    this.sessionId = sessionId
+    this.flushIntervalMs = flushIntervalMs
+    this.maxBatchSize = maxBatchSize
+  }
+
+  /**
+   * 记录分片已渲染
+   */
+  ack(segmentId: string): void {
+    this.pending.push({ segment_id: segmentId, rendered_at_ms: Date.now() })
+
+    if (this.pending.length >= this.maxBatchSize) {
+      this.flush()
+    } else if (this.flushTimer === null) {
+      this.flushTimer = window.setTimeout(() => this.flush(), this.flushIntervalMs)
+    }
+  }
+
+  /**
+   * 立即上报所有待发送的确认
+   */
+  flush(): void {
+    if (this.flushTimer !== null) {
+      clearTimeout(this.flushTimer)
+      this.flushTimer = null
+    }
+    if (this.pending.length === 0) return
+
+    const acks = this.pending
+    this.pending = []
+
+    fetch(`/api/v1/stream/${this.sessionId}/acks`, {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify({ acks, sent_at_ms: Date.now() }),
+      keepalive: true,
+    }).catch(err => {
+      console.warn('Failed to report playback acks:', err)
+    })
+  }
+
+  /**
+   * 上报剩余确认并停止
+   */
+  dispose(): void {
+    this.flush()
+  }
+}